@@ -0,0 +1,74 @@
+//! Formatting, msgpack encoding, hashing and signing one order: the work done per order
+//! between the decision to trade and the request going out.
+
+use std::{collections::HashMap, hint::black_box};
+
+use alloy::signers::{local::PrivateKeySigner, SignerSync};
+use criterion::{criterion_group, criterion_main, Criterion};
+use hyperliquid_rust_sdk::{
+    Actions, BaseUrl, BulkOrder, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient,
+    SpotMeta, Tif,
+};
+use uuid::Uuid;
+
+fn sign_and_serialize(c: &mut Criterion) {
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+    let coin_to_asset = HashMap::from([("ETH".to_string(), 1)]);
+    let order = ClientOrderRequest {
+        asset: "ETH".to_string(),
+        is_buy: true,
+        reduce_only: false,
+        limit_px: 1999.7,
+        sz: 0.0123,
+        cloid: Some(Uuid::from_u128(0x1e60610f0b3d420597c88c1fed2ad5ee)),
+        order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Alo }),
+    };
+
+    c.bench_function("format_order", |b| {
+        b.iter(|| black_box(order.clone()).convert(&coin_to_asset).unwrap())
+    });
+    c.bench_function("sign_and_serialize_order", |b| {
+        b.iter(|| {
+            let order = black_box(order.clone()).convert(&coin_to_asset).unwrap();
+            let action = Actions::Order(BulkOrder {
+                orders: vec![order],
+                grouping: "na".to_string(),
+                builder: None,
+            });
+            let hash = action.hash(black_box(1_700_000_000_000), None).unwrap();
+            // Signing the agent struct hashes the connection id once more before this
+            wallet.sign_hash_sync(&hash).unwrap()
+        })
+    });
+
+    let meta = serde_json::from_str(
+        r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50},{"name":"ETH","szDecimals":4,"maxLeverage":50}]}"#,
+    )
+    .unwrap();
+    let exchange_client = tokio::runtime::Runtime::new()
+        .unwrap()
+        .block_on(
+            ExchangeClient::builder()
+                .base_url(BaseUrl::custom("http://127.0.0.1:9"))
+                .meta(meta)
+                .spot_meta(
+                    serde_json::from_str::<SpotMeta>(r#"{"universe":[],"tokens":[]}"#).unwrap(),
+                )
+                .wallet(wallet.clone())
+                .build(),
+        )
+        .unwrap();
+    let prepared = exchange_client.prepare_order(order).unwrap();
+    c.bench_function("sign_prepared_order", |b| {
+        b.iter(|| {
+            let hash = prepared.hash(black_box(1_700_000_000_000), None);
+            wallet.sign_hash_sync(&hash).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, sign_and_serialize);
+criterion_main!(benches);
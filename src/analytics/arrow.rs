@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray,
+    TimestampMillisecondArray,
+};
+use arrow_schema::ArrowError;
+
+use crate::{
+    prelude::*, CandlesSnapshotResponse, Error, FundingHistoryResponse, L2BookData,
+    UserFillsResponse,
+};
+
+impl From<ArrowError> for Error {
+    fn from(err: ArrowError) -> Self {
+        Error::GenericParse(err.to_string())
+    }
+}
+
+fn px(value: &str) -> f64 {
+    value.parse().unwrap_or(f64::NAN)
+}
+
+fn time(values: impl Iterator<Item = u64>) -> ArrayRef {
+    Arc::new(
+        TimestampMillisecondArray::from_iter_values(values.map(|t| t as i64)).with_timezone("UTC"),
+    )
+}
+
+fn double<'a>(values: impl Iterator<Item = &'a str>) -> ArrayRef {
+    Arc::new(Float64Array::from_iter_values(values.map(px)))
+}
+
+fn text<'a>(values: impl Iterator<Item = &'a str>) -> ArrayRef {
+    Arc::new(StringArray::from_iter_values(values))
+}
+
+fn int(values: impl Iterator<Item = u64>) -> ArrayRef {
+    Arc::new(Int64Array::from_iter_values(values.map(|v| v as i64)))
+}
+
+/// Fills with the columns of `write_fills_csv`, numbers as doubles and `time` as a UTC
+/// timestamp. Polars and DataFusion take record batches as they are.
+pub fn fills_record_batch(fills: &[UserFillsResponse]) -> Result<RecordBatch> {
+    Ok(RecordBatch::try_from_iter([
+        ("time", time(fills.iter().map(|f| f.time.as_millis()))),
+        ("coin", text(fills.iter().map(|f| f.coin.as_str()))),
+        ("side", text(fills.iter().map(|f| f.side.as_str()))),
+        ("px", double(fills.iter().map(|f| f.px.as_str()))),
+        ("sz", double(fills.iter().map(|f| f.sz.as_str()))),
+        ("dir", text(fills.iter().map(|f| f.dir.as_str()))),
+        (
+            "closed_pnl",
+            double(fills.iter().map(|f| f.closed_pnl.as_str())),
+        ),
+        ("fee", double(fills.iter().map(|f| f.fee.as_str()))),
+        (
+            "fee_token",
+            text(fills.iter().map(|f| f.fee_token.as_str())),
+        ),
+        (
+            "builder_fee",
+            Arc::new(Float64Array::from_iter(
+                fills.iter().map(|f| f.builder_fee.as_deref().map(px)),
+            )) as ArrayRef,
+        ),
+        (
+            "crossed",
+            Arc::new(BooleanArray::from_iter(
+                fills.iter().map(|f| Some(f.crossed)),
+            )),
+        ),
+        (
+            "start_position",
+            double(fills.iter().map(|f| f.start_position.as_str())),
+        ),
+        ("oid", int(fills.iter().map(|f| f.oid))),
+        ("tid", int(fills.iter().map(|f| f.tid))),
+        ("hash", text(fills.iter().map(|f| f.hash.as_str()))),
+        (
+            "twap_id",
+            Arc::new(Int64Array::from_iter(
+                fills.iter().map(|f| f.twap_id.map(|id| id as i64)),
+            )),
+        ),
+    ])?)
+}
+
+/// Candles with columns `time_open`, `time_close`, `coin`, `interval`, `open`, `high`, `low`,
+/// `close`, `volume` and `num_trades`.
+pub fn candles_record_batch(candles: &[CandlesSnapshotResponse]) -> Result<RecordBatch> {
+    Ok(RecordBatch::try_from_iter([
+        (
+            "time_open",
+            time(candles.iter().map(|c| c.time_open.as_millis())),
+        ),
+        (
+            "time_close",
+            time(candles.iter().map(|c| c.time_close.as_millis())),
+        ),
+        ("coin", text(candles.iter().map(|c| c.coin.as_str()))),
+        (
+            "interval",
+            text(candles.iter().map(|c| c.candle_interval.as_str())),
+        ),
+        ("open", double(candles.iter().map(|c| c.open.as_str()))),
+        ("high", double(candles.iter().map(|c| c.high.as_str()))),
+        ("low", double(candles.iter().map(|c| c.low.as_str()))),
+        ("close", double(candles.iter().map(|c| c.close.as_str()))),
+        ("volume", double(candles.iter().map(|c| c.vlm.as_str()))),
+        ("num_trades", int(candles.iter().map(|c| c.num_trades))),
+    ])?)
+}
+
+/// One row per book level, with columns `time`, `coin`, `side` (`B` or `A`), `level` (0 at the
+/// top of the book), `px`, `sz` and `n`, as written by `write_l2_books_parquet`.
+pub fn l2_books_record_batch(books: &[L2BookData]) -> Result<RecordBatch> {
+    let rows: Vec<(&L2BookData, &str, usize, &crate::BookLevel)> = books
+        .iter()
+        .flat_map(|book| {
+            book.levels
+                .iter()
+                .zip(["B", "A"])
+                .flat_map(move |(levels, side)| {
+                    levels
+                        .iter()
+                        .enumerate()
+                        .map(move |(level, book_level)| (book, side, level, book_level))
+                })
+        })
+        .collect();
+    Ok(RecordBatch::try_from_iter([
+        ("time", time(rows.iter().map(|r| r.0.time.as_millis()))),
+        ("coin", text(rows.iter().map(|r| r.0.coin.as_str()))),
+        ("side", text(rows.iter().map(|r| r.1))),
+        ("level", int(rows.iter().map(|r| r.2 as u64))),
+        ("px", double(rows.iter().map(|r| r.3.px.as_str()))),
+        ("sz", double(rows.iter().map(|r| r.3.sz.as_str()))),
+        ("n", int(rows.iter().map(|r| r.3.n))),
+    ])?)
+}
+
+/// Funding history with columns `time`, `coin`, `funding_rate` and `premium`.
+pub fn funding_history_record_batch(funding: &[FundingHistoryResponse]) -> Result<RecordBatch> {
+    Ok(RecordBatch::try_from_iter([
+        ("time", time(funding.iter().map(|f| f.time.as_millis()))),
+        ("coin", text(funding.iter().map(|f| f.coin.as_str()))),
+        (
+            "funding_rate",
+            double(funding.iter().map(|f| f.funding_rate.as_str())),
+        ),
+        (
+            "premium",
+            double(funding.iter().map(|f| f.premium.as_str())),
+        ),
+    ])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::Array;
+    use arrow_schema::{DataType, TimeUnit};
+
+    use super::*;
+    use crate::EPSILON;
+
+    #[test]
+    fn test_record_batches() {
+        let books: Vec<L2BookData> = serde_json::from_str(
+            r#"[{"coin":"ETH","time":1,"levels":[
+                [{"px":"1999","sz":"1","n":1},{"px":"1998","sz":"5","n":2}],
+                [{"px":"2001","sz":"1","n":1}]
+            ]}]"#,
+        )
+        .unwrap();
+        let batch = l2_books_record_batch(&books).unwrap();
+        assert_eq!((batch.num_rows(), batch.num_columns()), (3, 7));
+        assert_eq!(
+            batch.schema().field(0).data_type(),
+            &DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()))
+        );
+        let px = batch
+            .column_by_name("px")
+            .unwrap()
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert!((px.value(2) - 2001.0).abs() < EPSILON);
+
+        let funding: Vec<FundingHistoryResponse> = serde_json::from_str(
+            r#"[{"coin":"ETH","fundingRate":"0.0000125","premium":"-0.0001","time":1}]"#,
+        )
+        .unwrap();
+        let batch = funding_history_record_batch(&funding).unwrap();
+        assert_eq!(batch.num_rows(), 1);
+        assert_eq!(batch.column_by_name("premium").unwrap().null_count(), 0);
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{helpers::now_timestamp_ms, AssetCtx, Message};
+
+/// Which side of its band the basis of a pair moved to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BasisCrossing {
+    Above,
+    Below,
+}
+
+/// The basis of a pair leaving its band.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasisAlert {
+    pub perp: String,
+    pub spot: String,
+    pub time: u64,
+    pub basis_bps: f64,
+    /// Standard deviations from the mean of the window, None with fewer than two samples
+    pub z_score: Option<f64>,
+    pub crossing: BasisCrossing,
+}
+
+/// Basis statistics over a window, in bps.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BasisSummary {
+    pub samples: usize,
+    pub last: f64,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+}
+
+impl BasisSummary {
+    /// Standard deviations of the last sample from the mean.
+    pub fn z_score(&self) -> Option<f64> {
+        (self.samples > 1 && self.std_dev > 0.0).then(|| (self.last - self.mean) / self.std_dev)
+    }
+}
+
+type AlertCallback = Box<dyn FnMut(&BasisAlert) + Send>;
+
+#[derive(Clone, Debug, Default)]
+struct PairState {
+    spot: String,
+    mark_px: Option<f64>,
+    spot_mid: Option<f64>,
+    /// `(time, basis_bps)`, oldest first
+    samples: VecDeque<(u64, f64)>,
+    side: Option<BasisCrossing>,
+}
+
+/// Live basis of perps against their spot pairs, `(perp mark - spot mid) / spot mid` in bps,
+/// with rolling statistics and alerts when it leaves a band.
+///
+/// Perp marks come from `activeAssetCtx` messages, spot mids from `l2Book` and `allMids`
+/// messages of the spot pair, such as `@151` or `PURR/USDC`, or `activeAssetCtx` of it. A
+/// sample is taken whenever either side updates once both are known. An alert fires when the
+/// basis moves above the upper bound or below the lower one, and again only after it has
+/// been back inside the band.
+pub struct BasisMonitor {
+    window_ms: u64,
+    /// Spot pair by perp
+    pairs: HashMap<String, PairState>,
+    band: Option<(f64, f64)>,
+    alert: Option<AlertCallback>,
+}
+
+impl fmt::Debug for BasisMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasisMonitor")
+            .field("window_ms", &self.window_ms)
+            .field("pairs", &self.pairs)
+            .field("band", &self.band)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BasisMonitor {
+    pub fn new(window: Duration) -> BasisMonitor {
+        BasisMonitor {
+            window_ms: window.as_millis() as u64,
+            pairs: HashMap::new(),
+            band: None,
+            alert: None,
+        }
+    }
+
+    /// Tracks `perp` against the spot pair `spot`.
+    pub fn with_pair(mut self, perp: &str, spot: &str) -> Self {
+        self.pairs.insert(
+            perp.to_string(),
+            PairState {
+                spot: spot.to_string(),
+                ..PairState::default()
+            },
+        );
+        self
+    }
+
+    /// Alerts when the basis goes below `lower_bps` or above `upper_bps`.
+    pub fn with_band(mut self, lower_bps: f64, upper_bps: f64) -> Self {
+        self.band = Some((lower_bps, upper_bps));
+        self
+    }
+
+    /// Called for every alert, as well as returned by `handle_message`.
+    pub fn with_alert(mut self, alert: impl FnMut(&BasisAlert) + Send + 'static) -> Self {
+        self.alert = Some(Box::new(alert));
+        self
+    }
+
+    /// Subscriptions feeding the monitor: the context of each perp and the book of each spot
+    /// pair.
+    #[cfg(feature = "ws")]
+    pub fn subscriptions(&self) -> Vec<crate::Subscription> {
+        let mut perps: Vec<(&String, &PairState)> = self.pairs.iter().collect();
+        perps.sort_by_key(|(perp, _)| *perp);
+        perps
+            .into_iter()
+            .flat_map(|(perp, pair)| {
+                [
+                    crate::Subscription::ActiveAssetCtx { coin: perp.clone() },
+                    crate::Subscription::L2Book {
+                        coin: pair.spot.clone(),
+                    },
+                ]
+            })
+            .collect()
+    }
+
+    /// Updates prices from `message`, returning the alerts it raised.
+    pub fn handle_message(&mut self, message: &Message) -> Vec<BasisAlert> {
+        self.handle_message_at(message, now_timestamp_ms())
+    }
+
+    fn handle_message_at(&mut self, message: &Message, now: u64) -> Vec<BasisAlert> {
+        let mut alerts = Vec::new();
+        match message {
+            Message::ActiveAssetCtx(ctx) => {
+                let coin = &ctx.data.coin;
+                match &ctx.data.ctx {
+                    AssetCtx::Perps(perp) => {
+                        if let Ok(mark_px) = perp.shared.mark_px.parse() {
+                            alerts.extend(self.update_perp_mark(coin, now, mark_px));
+                        }
+                    }
+                    AssetCtx::Spot(spot) => {
+                        let shared = &spot.shared;
+                        let px = shared.mid_px.as_deref().unwrap_or(&shared.mark_px);
+                        if let Ok(mid) = px.parse() {
+                            alerts.extend(self.update_spot_mid(coin, now, mid));
+                        }
+                    }
+                }
+            }
+            Message::ActiveSpotAssetCtx(ctx) => {
+                let shared = &ctx.data.ctx.shared;
+                let px = shared.mid_px.as_deref().unwrap_or(&shared.mark_px);
+                if let Ok(mid) = px.parse() {
+                    alerts.extend(self.update_spot_mid(&ctx.data.coin, now, mid));
+                }
+            }
+            Message::L2Book(book) => {
+                if let Some(mid) = super::OrderBook::from_l2(&book.data).mid() {
+                    alerts.extend(self.update_spot_mid(
+                        &book.data.coin,
+                        book.data.time.as_millis(),
+                        mid,
+                    ));
+                }
+            }
+            Message::AllMids(mids) => {
+                let spots: Vec<String> = self.pairs.values().map(|p| p.spot.clone()).collect();
+                for spot in spots {
+                    if let Some(Ok(mid)) = mids.data.mids.get(&spot).map(|mid| mid.parse()) {
+                        alerts.extend(self.update_spot_mid(&spot, now, mid));
+                    }
+                }
+            }
+            _ => {}
+        }
+        alerts
+    }
+
+    /// Sets the mark of `perp` at `time`, in ms, returning an alert if the basis left its band.
+    pub fn update_perp_mark(&mut self, perp: &str, time: u64, mark_px: f64) -> Option<BasisAlert> {
+        let pair = self.pairs.get_mut(perp)?;
+        pair.mark_px = Some(mark_px);
+        self.sample(perp, time)
+    }
+
+    /// Sets the mid of the spot pair `spot` at `time`, in ms, returning the alerts of the perps
+    /// tracked against it.
+    pub fn update_spot_mid(&mut self, spot: &str, time: u64, mid: f64) -> Vec<BasisAlert> {
+        let perps: Vec<String> = self
+            .pairs
+            .iter_mut()
+            .filter(|(_, pair)| pair.spot == spot)
+            .map(|(perp, pair)| {
+                pair.spot_mid = Some(mid);
+                perp.clone()
+            })
+            .collect();
+        perps
+            .iter()
+            .filter_map(|perp| self.sample(perp, time))
+            .collect()
+    }
+
+    fn sample(&mut self, perp: &str, time: u64) -> Option<BasisAlert> {
+        let window_ms = self.window_ms;
+        let pair = self.pairs.get_mut(perp)?;
+        let (mark_px, spot_mid) = (pair.mark_px?, pair.spot_mid?);
+        if spot_mid <= 0.0 {
+            return None;
+        }
+        let basis_bps = (mark_px - spot_mid) / spot_mid * 10_000.0;
+        pair.samples.push_back((time, basis_bps));
+        let start = time.saturating_sub(window_ms);
+        while pair.samples.front().is_some_and(|&(t, _)| t < start) {
+            pair.samples.pop_front();
+        }
+
+        let (lower, upper) = self.band?;
+        let side = if basis_bps > upper {
+            Some(BasisCrossing::Above)
+        } else if basis_bps < lower {
+            Some(BasisCrossing::Below)
+        } else {
+            None
+        };
+        let crossed = side.filter(|side| pair.side != Some(*side));
+        pair.side = side;
+        let crossing = crossed?;
+        let alert = BasisAlert {
+            perp: perp.to_string(),
+            spot: pair.spot.clone(),
+            time,
+            basis_bps,
+            z_score: summarize(&pair.samples).and_then(|summary| summary.z_score()),
+            crossing,
+        };
+        if let Some(callback) = &mut self.alert {
+            callback(&alert);
+        }
+        Some(alert)
+    }
+
+    /// Latest basis of `perp`, in bps.
+    pub fn basis_bps(&self, perp: &str) -> Option<f64> {
+        self.pairs
+            .get(perp)?
+            .samples
+            .back()
+            .map(|&(_, basis)| basis)
+    }
+
+    /// Statistics of the samples of `perp` in the window ending at its latest sample.
+    pub fn summary(&self, perp: &str) -> Option<BasisSummary> {
+        summarize(&self.pairs.get(perp)?.samples)
+    }
+}
+
+fn summarize(samples: &VecDeque<(u64, f64)>) -> Option<BasisSummary> {
+    let &(_, last) = samples.back()?;
+    let count = samples.len() as f64;
+    let mean = samples.iter().map(|(_, basis)| basis).sum::<f64>() / count;
+    let variance = samples
+        .iter()
+        .map(|(_, basis)| (basis - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    Some(BasisSummary {
+        samples: samples.len(),
+        last,
+        mean,
+        min: samples
+            .iter()
+            .map(|&(_, basis)| basis)
+            .fold(f64::INFINITY, f64::min),
+        max: samples
+            .iter()
+            .map(|&(_, basis)| basis)
+            .fold(f64::NEG_INFINITY, f64::max),
+        std_dev: variance.sqrt(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_basis_band_alerts() {
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let seen = alerts.clone();
+        let mut monitor = BasisMonitor::new(Duration::from_secs(10))
+            .with_pair("ETH", "@151")
+            .with_band(-20.0, 20.0)
+            .with_alert(move |alert| seen.lock().unwrap().push(alert.crossing));
+
+        let ctx = |mark: &str| {
+            serde_json::from_str::<Message>(&format!(
+                r#"{{"channel":"activeAssetCtx","data":{{"coin":"ETH","ctx":{{"dayNtlVlm":"1","prevDayPx":"1","markPx":"{mark}","midPx":"{mark}","funding":"0.0001","openInterest":"1","oraclePx":"{mark}"}}}}}}"#
+            ))
+            .unwrap()
+        };
+        let mids = serde_json::from_str::<Message>(
+            r#"{"channel":"allMids","data":{"mids":{"@151":"2000","ETH":"2001"}}}"#,
+        )
+        .unwrap();
+
+        // No spot mid yet
+        assert!(monitor.handle_message_at(&ctx("2001"), 1_000).is_empty());
+        assert!(monitor.handle_message_at(&mids, 2_000).is_empty());
+        assert!((monitor.basis_bps("ETH").unwrap() - 5.0).abs() < 1e-9);
+
+        let raised = monitor.handle_message_at(&ctx("2006"), 3_000);
+        assert_eq!(raised.len(), 1);
+        assert_eq!(raised[0].crossing, BasisCrossing::Above);
+        assert!((raised[0].basis_bps - 30.0).abs() < 1e-9);
+        assert!(raised[0].z_score.unwrap() > 0.0);
+        // Still above, no new alert until it comes back into the band
+        assert!(monitor.handle_message_at(&ctx("2008"), 4_000).is_empty());
+        assert!(monitor.handle_message_at(&ctx("2000"), 5_000).is_empty());
+        assert!(monitor.update_perp_mark("ETH", 6_000, 1990.0).is_some());
+        assert_eq!(
+            *alerts.lock().unwrap(),
+            vec![BasisCrossing::Above, BasisCrossing::Below]
+        );
+
+        // Samples older than the window are dropped
+        monitor.update_perp_mark("ETH", 15_000, 2000.0);
+        let summary = monitor.summary("ETH").unwrap();
+        assert_eq!(summary.samples, 3);
+        assert!((summary.min + 50.0).abs() < 1e-9 && summary.max == 0.0);
+    }
+}
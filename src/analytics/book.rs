@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{L2BookData, Message, EPSILON};
+
+/// An `l2Book` snapshot with parsed levels, for computing signals.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OrderBook {
+    pub coin: String,
+    pub time: u64,
+    /// `(px, sz)`, best first
+    pub bids: Vec<(f64, f64)>,
+    /// `(px, sz)`, best first
+    pub asks: Vec<(f64, f64)>,
+}
+
+impl OrderBook {
+    /// Parses an `l2Book` update. Levels that fail to parse are skipped.
+    pub fn from_l2(book: &L2BookData) -> OrderBook {
+        let side = |index: usize| -> Vec<(f64, f64)> {
+            book.levels
+                .get(index)
+                .into_iter()
+                .flatten()
+                .filter_map(|level| match (level.px.parse(), level.sz.parse()) {
+                    (Ok(px), Ok(sz)) => Some((px, sz)),
+                    _ => {
+                        warn!("Could not parse {} book level {}", book.coin, level.px);
+                        None
+                    }
+                })
+                .collect()
+        };
+        OrderBook {
+            coin: book.coin.clone(),
+            time: book.time.as_millis(),
+            bids: side(0),
+            asks: side(1),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.first().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.first().copied()
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        let ((bid, _), (ask, _)) = (self.best_bid()?, self.best_ask()?);
+        Some((bid + ask) / 2.0)
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        let ((bid, _), (ask, _)) = (self.best_bid()?, self.best_ask()?);
+        Some(ask - bid)
+    }
+
+    /// Spread relative to the mid, in bps.
+    pub fn spread_bps(&self) -> Option<f64> {
+        let mid = self.mid()?;
+        (mid > 0.0).then(|| self.spread().unwrap_or_default() / mid * 10_000.0)
+    }
+
+    /// Mid weighted by the opposite side's size at the top of the book, which leans towards
+    /// the side more likely to trade next.
+    pub fn microprice(&self) -> Option<f64> {
+        let ((bid, bid_sz), (ask, ask_sz)) = (self.best_bid()?, self.best_ask()?);
+        let total = bid_sz + ask_sz;
+        if total <= 0.0 {
+            return self.mid();
+        }
+        Some((bid * ask_sz + ask * bid_sz) / total)
+    }
+
+    /// `(bid - ask) / (bid + ask)` of the size resting on the best `levels` levels of each
+    /// side, from -1 (all asks) to 1 (all bids). `None` if both are empty.
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let size = |side: &[(f64, f64)]| side.iter().take(levels).map(|(_, sz)| sz).sum::<f64>();
+        let (bid, ask) = (size(&self.bids), size(&self.asks));
+        (bid + ask > 0.0).then(|| (bid - ask) / (bid + ask))
+    }
+
+    /// Size resting within `bps` of the mid on each side, as `(bid, ask)`.
+    pub fn depth_within_bps(&self, bps: f64) -> Option<(f64, f64)> {
+        let mid = self.mid()?;
+        let distance = mid * bps / 10_000.0;
+        let bid = self
+            .bids
+            .iter()
+            .take_while(|(px, _)| *px >= mid - distance)
+            .map(|(_, sz)| sz)
+            .sum();
+        let ask = self
+            .asks
+            .iter()
+            .take_while(|(px, _)| *px <= mid + distance)
+            .map(|(_, sz)| sz)
+            .sum();
+        Some((bid, ask))
+    }
+
+    /// Like `depth_within_bps`, in quote notional.
+    pub fn notional_within_bps(&self, bps: f64) -> Option<(f64, f64)> {
+        let mid = self.mid()?;
+        let distance = mid * bps / 10_000.0;
+        let bid = self
+            .bids
+            .iter()
+            .take_while(|(px, _)| *px >= mid - distance)
+            .map(|(px, sz)| px * sz)
+            .sum();
+        let ask = self
+            .asks
+            .iter()
+            .take_while(|(px, _)| *px <= mid + distance)
+            .map(|(px, sz)| px * sz)
+            .sum();
+        Some((bid, ask))
+    }
+
+    /// Size of a taker order for up to `target_notional` that can execute against the book
+    /// without reaching levels more than `max_impact_bps` from the mid, with its expected
+    /// average price. Sizes are not rounded to the asset's lot size. `None` if the book has an
+    /// empty side.
+    pub fn size_for_impact(
+        &self,
+        is_buy: bool,
+        target_notional: f64,
+        max_impact_bps: f64,
+    ) -> Option<ImpactSizing> {
+        let mid = self.mid()?;
+        let (levels, direction) = if is_buy {
+            (&self.asks, 1.0)
+        } else {
+            (&self.bids, -1.0)
+        };
+        let limit_px = mid * (1.0 + direction * max_impact_bps / 10_000.0);
+        let mut sizing = ImpactSizing::default();
+        for &(px, sz) in levels {
+            let remaining = target_notional - sizing.notional;
+            if remaining <= EPSILON || (px - limit_px) * direction > 0.0 {
+                break;
+            }
+            let take = sz.min(remaining / px);
+            sizing.sz += take;
+            sizing.notional += take * px;
+            sizing.worst_px = Some(px);
+        }
+        if sizing.sz > 0.0 {
+            let avg_px = sizing.notional / sizing.sz;
+            sizing.avg_px = Some(avg_px);
+            sizing.impact_bps = (avg_px - mid) / mid * 10_000.0 * direction;
+        }
+        sizing.complete = target_notional - sizing.notional <= EPSILON;
+        Some(sizing)
+    }
+}
+
+impl From<&L2BookData> for OrderBook {
+    fn from(book: &L2BookData) -> OrderBook {
+        OrderBook::from_l2(book)
+    }
+}
+
+/// What part of a target notional one side of a book absorbs within an impact limit, from
+/// `OrderBook::size_for_impact`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImpactSizing {
+    pub sz: f64,
+    pub notional: f64,
+    /// Expected average fill price, None when nothing is within the limit
+    pub avg_px: Option<f64>,
+    /// Price of the last level reached, the limit price for the order
+    pub worst_px: Option<f64>,
+    /// Average price relative to the mid, positive when worse
+    pub impact_bps: f64,
+    /// The whole target notional fits within the limit
+    pub complete: bool,
+}
+
+/// Spread statistics over a window, in bps.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpreadSummary {
+    pub samples: usize,
+    pub last: f64,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+}
+
+/// Tracks the spread of each coin from `l2Book` updates over a rolling window, measured by
+/// book time.
+#[derive(Clone, Debug)]
+pub struct SpreadStats {
+    window_ms: u64,
+    /// `(time, spread_bps)` by coin, oldest first
+    samples: HashMap<String, VecDeque<(u64, f64)>>,
+}
+
+impl SpreadStats {
+    pub fn new(window: Duration) -> SpreadStats {
+        SpreadStats {
+            window_ms: window.as_millis() as u64,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Adds the spread of an `l2Book` update. Other messages are ignored.
+    pub fn handle_message(&mut self, message: &Message) {
+        if let Message::L2Book(book) = message {
+            self.add(&OrderBook::from_l2(&book.data));
+        }
+    }
+
+    /// Adds the spread of `book`, dropping samples that fell out of the window. Books with an
+    /// empty side are ignored.
+    pub fn add(&mut self, book: &OrderBook) {
+        let Some(spread) = book.spread_bps() else {
+            return;
+        };
+        let samples = self.samples.entry(book.coin.clone()).or_default();
+        samples.push_back((book.time, spread));
+        let start = book.time.saturating_sub(self.window_ms);
+        while samples.front().is_some_and(|&(time, _)| time < start) {
+            samples.pop_front();
+        }
+    }
+
+    /// Statistics of the samples of `coin` in the window ending at its latest book.
+    pub fn summary(&self, coin: &str) -> Option<SpreadSummary> {
+        let samples = self
+            .samples
+            .get(coin)
+            .filter(|samples| !samples.is_empty())?;
+        let count = samples.len() as f64;
+        let mean = samples.iter().map(|(_, spread)| spread).sum::<f64>() / count;
+        let variance = samples
+            .iter()
+            .map(|(_, spread)| (spread - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        Some(SpreadSummary {
+            samples: samples.len(),
+            last: samples
+                .back()
+                .map(|(_, spread)| *spread)
+                .unwrap_or_default(),
+            mean,
+            min: samples
+                .iter()
+                .map(|(_, spread)| *spread)
+                .fold(f64::INFINITY, f64::min),
+            max: samples
+                .iter()
+                .map(|(_, spread)| *spread)
+                .fold(f64::NEG_INFINITY, f64::max),
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(time: u64, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> OrderBook {
+        OrderBook {
+            coin: "ETH".to_string(),
+            time,
+            bids: bids.to_vec(),
+            asks: asks.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_book_metrics() {
+        let message: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"99","sz":"3","n":1},{"px":"98","sz":"2","n":1},{"px":"90","sz":"10","n":1}],[{"px":"101","sz":"1","n":1},{"px":"102","sz":"4","n":2}]]}}"#,
+        )
+        .unwrap();
+        let Message::L2Book(l2) = message else {
+            unreachable!()
+        };
+        let book = OrderBook::from(&l2.data);
+        assert_eq!(book.mid(), Some(100.0));
+        assert!((book.spread_bps().unwrap() - 200.0).abs() < EPSILON);
+        // Three times the size on the bid pulls the microprice towards the ask
+        assert!((book.microprice().unwrap() - 100.5).abs() < EPSILON);
+        assert!((book.imbalance(1).unwrap() - 0.5).abs() < EPSILON);
+        assert!(book.imbalance(2).unwrap().abs() < EPSILON);
+        assert_eq!(book.depth_within_bps(200.0), Some((5.0, 5.0)));
+        assert_eq!(book.notional_within_bps(100.0), Some((297.0, 101.0)));
+        assert_eq!(book.imbalance(0), None);
+
+        // 101 is within 150 bps of the mid, 102 is not
+        let sizing = book.size_for_impact(true, 1000.0, 150.0).unwrap();
+        assert_eq!((sizing.sz, sizing.worst_px), (1.0, Some(101.0)));
+        assert!(!sizing.complete);
+        // Half of the size at 102 completes the notional
+        let sizing = book.size_for_impact(true, 305.0, 250.0).unwrap();
+        assert!((sizing.sz - 3.0).abs() < EPSILON && sizing.complete);
+        assert!((sizing.avg_px.unwrap() - 305.0 / 3.0).abs() < EPSILON);
+        assert!((sizing.impact_bps - (305.0 / 3.0 - 100.0) * 100.0).abs() < 1e-6);
+        let sizing = book.size_for_impact(false, 99.0, 100.0).unwrap();
+        assert_eq!((sizing.sz, sizing.worst_px), (1.0, Some(99.0)));
+        assert!(sizing.complete && (sizing.impact_bps - 100.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_spread_stats_window() {
+        let mut stats = SpreadStats::new(Duration::from_secs(10));
+        stats.add(&book(0, &[(99.0, 1.0)], &[(101.0, 1.0)]));
+        stats.add(&book(5_000, &[(99.5, 1.0)], &[(100.5, 1.0)]));
+        stats.add(&book(8_000, &[(99.0, 1.0)], &[]));
+        let summary = stats.summary("ETH").unwrap();
+        assert_eq!(summary.samples, 2);
+        assert!((summary.mean - 150.0).abs() < EPSILON);
+        assert!((summary.std_dev - 50.0).abs() < EPSILON);
+
+        stats.add(&book(12_000, &[(99.75, 1.0)], &[(100.25, 1.0)]));
+        let summary = stats.summary("ETH").unwrap();
+        assert_eq!(summary.samples, 2);
+        assert!((summary.min - 50.0).abs() < EPSILON);
+        assert!((summary.max - 100.0).abs() < EPSILON);
+        assert!((summary.last - 50.0).abs() < EPSILON);
+        assert!(stats.summary("BTC").is_none());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{prelude::*, BookLevel, Error, L2BookData, Timestamp};
+
+/// Changes to one side of a book between two snapshots.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SideDiff {
+    /// Levels at prices the previous snapshot did not have
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adds: Vec<BookLevel>,
+    /// Levels whose size or order count changed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub updates: Vec<BookLevel>,
+    /// Prices of levels that are gone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removes: Vec<String>,
+}
+
+impl SideDiff {
+    pub fn is_empty(&self) -> bool {
+        self.adds.is_empty() && self.updates.is_empty() && self.removes.is_empty()
+    }
+
+    fn between(prev: &[BookLevel], next: &[BookLevel]) -> SideDiff {
+        let prev_levels: HashMap<&str, &BookLevel> = prev
+            .iter()
+            .map(|level| (level.px.as_str(), level))
+            .collect();
+        let mut diff = SideDiff::default();
+        for level in next {
+            match prev_levels.get(level.px.as_str()) {
+                None => diff.adds.push(level.clone()),
+                Some(old) if old.sz != level.sz || old.n != level.n => {
+                    diff.updates.push(level.clone())
+                }
+                Some(_) => {}
+            }
+        }
+        let next_pxs: HashSet<&str> = next.iter().map(|level| level.px.as_str()).collect();
+        diff.removes = prev
+            .iter()
+            .filter(|level| !next_pxs.contains(level.px.as_str()))
+            .map(|level| level.px.clone())
+            .collect();
+        diff
+    }
+
+    fn apply(&self, levels: &mut Vec<BookLevel>, is_bid: bool) -> Result<()> {
+        let missing = |px: &str| Error::GenericParse(format!("Book diff for missing level {px}"));
+        for px in &self.removes {
+            let index = levels
+                .iter()
+                .position(|level| &level.px == px)
+                .ok_or_else(|| missing(px))?;
+            levels.remove(index);
+        }
+        for update in &self.updates {
+            let level = levels
+                .iter_mut()
+                .find(|level| level.px == update.px)
+                .ok_or_else(|| missing(&update.px))?;
+            *level = update.clone();
+        }
+        levels.extend(self.adds.iter().cloned());
+        let px = |level: &BookLevel| level.px.parse::<f64>().unwrap_or(f64::NAN);
+        // Best first: highest bid, lowest ask
+        levels.sort_by(|a, b| {
+            if is_bid {
+                px(b).total_cmp(&px(a))
+            } else {
+                px(a).total_cmp(&px(b))
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Difference between two consecutive `l2Book` snapshots of a coin, usually a few levels
+/// instead of the whole book. Apply it to the earlier snapshot to get the later one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L2BookDiff {
+    pub coin: String,
+    /// Time of the snapshot the diff applies to
+    pub prev_time: Timestamp,
+    pub time: Timestamp,
+    #[serde(default, skip_serializing_if = "SideDiff::is_empty")]
+    pub bids: SideDiff,
+    #[serde(default, skip_serializing_if = "SideDiff::is_empty")]
+    pub asks: SideDiff,
+}
+
+impl L2BookDiff {
+    /// Changes turning `prev` into `next`, levels compared by their price strings.
+    pub fn between(prev: &L2BookData, next: &L2BookData) -> L2BookDiff {
+        L2BookDiff {
+            coin: next.coin.clone(),
+            prev_time: prev.time,
+            time: next.time,
+            bids: SideDiff::between(side(prev, 0), side(next, 0)),
+            asks: SideDiff::between(side(prev, 1), side(next, 1)),
+        }
+    }
+
+    /// True when only the time changed.
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// Applies the diff to `book`, which must be the snapshot it was taken from. Fails
+    /// without a usable result if the coin or time does not match or a changed level is
+    /// missing, meaning a diff was lost and a new snapshot is needed.
+    pub fn apply(&self, book: &mut L2BookData) -> Result<()> {
+        if book.coin != self.coin || book.time != self.prev_time {
+            return Err(Error::GenericParse(format!(
+                "Book diff for {} at {} does not follow {} at {}",
+                self.coin, self.prev_time, book.coin, book.time
+            )));
+        }
+        book.levels.resize_with(2, Vec::new);
+        self.bids.apply(&mut book.levels[0], true)?;
+        self.asks.apply(&mut book.levels[1], false)?;
+        book.time = self.time;
+        Ok(())
+    }
+}
+
+/// A book as sent by `BookDiffEncoder`: a full snapshot or a diff against the previous one.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BookUpdate {
+    Snapshot(L2BookData),
+    Diff(L2BookDiff),
+}
+
+impl BookUpdate {
+    pub fn coin(&self) -> &str {
+        match self {
+            BookUpdate::Snapshot(book) => &book.coin,
+            BookUpdate::Diff(diff) => &diff.coin,
+        }
+    }
+}
+
+/// Turns a stream of `l2Book` snapshots into diffs against the previous snapshot of each coin,
+/// starting each coin with a full snapshot.
+#[derive(Debug, Default)]
+pub struct BookDiffEncoder {
+    books: HashMap<String, (L2BookData, usize)>,
+    snapshot_every: Option<usize>,
+}
+
+impl BookDiffEncoder {
+    pub fn new() -> BookDiffEncoder {
+        BookDiffEncoder::default()
+    }
+
+    /// Sends a full snapshot every `updates` updates of a coin, so receivers that join late or
+    /// lose a diff recover without asking.
+    pub fn with_snapshot_every(mut self, updates: usize) -> Self {
+        self.snapshot_every = Some(updates.max(1));
+        self
+    }
+
+    pub fn encode(&mut self, book: &L2BookData) -> BookUpdate {
+        let snapshot_every = self.snapshot_every;
+        match self.books.get_mut(&book.coin) {
+            Some((prev, count))
+                if snapshot_every.is_none_or(|every| *count + 1 < every)
+                    && prev.time <= book.time =>
+            {
+                let diff = L2BookDiff::between(prev, book);
+                *prev = book.clone();
+                *count += 1;
+                BookUpdate::Diff(diff)
+            }
+            _ => {
+                self.books.insert(book.coin.clone(), (book.clone(), 0));
+                BookUpdate::Snapshot(book.clone())
+            }
+        }
+    }
+
+    /// Makes the next update of `coin` a full snapshot.
+    pub fn reset(&mut self, coin: &str) {
+        self.books.remove(coin);
+    }
+}
+
+/// Rebuilds books from the updates of a `BookDiffEncoder`.
+#[derive(Debug, Default)]
+pub struct BookDiffDecoder {
+    books: HashMap<String, L2BookData>,
+}
+
+impl BookDiffDecoder {
+    pub fn new() -> BookDiffDecoder {
+        BookDiffDecoder::default()
+    }
+
+    /// Applies `update`, returning the rebuilt book. A diff that does not follow the last book
+    /// of its coin fails and drops that book until the next snapshot.
+    pub fn apply(&mut self, update: &BookUpdate) -> Result<&L2BookData> {
+        match update {
+            BookUpdate::Snapshot(book) => {
+                self.books.insert(book.coin.clone(), book.clone());
+            }
+            BookUpdate::Diff(diff) => {
+                let Some(book) = self.books.get_mut(&diff.coin) else {
+                    return Err(Error::GenericParse(format!(
+                        "Book diff for {} before its snapshot",
+                        diff.coin
+                    )));
+                };
+                if let Err(err) = diff.apply(book) {
+                    self.books.remove(&diff.coin);
+                    return Err(err);
+                }
+            }
+        }
+        self.books
+            .get(update.coin())
+            .ok_or_else(|| Error::GenericParse(format!("No book for {}", update.coin())))
+    }
+
+    pub fn book(&self, coin: &str) -> Option<&L2BookData> {
+        self.books.get(coin)
+    }
+}
+
+fn side(book: &L2BookData, index: usize) -> &[BookLevel] {
+    book.levels.get(index).map_or(&[], Vec::as_slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(time: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> L2BookData {
+        let levels = |levels: &[(&str, &str)]| {
+            levels
+                .iter()
+                .map(|(px, sz)| BookLevel {
+                    px: px.to_string(),
+                    sz: sz.to_string(),
+                    n: 1,
+                })
+                .collect()
+        };
+        L2BookData {
+            coin: "ETH".to_string(),
+            time: time.into(),
+            levels: vec![levels(bids), levels(asks)],
+        }
+    }
+
+    fn levels(book: &L2BookData) -> Vec<Vec<(String, String)>> {
+        book.levels
+            .iter()
+            .map(|side| {
+                side.iter()
+                    .map(|level| (level.px.clone(), level.sz.clone()))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_roundtrip() {
+        let first = book(
+            1,
+            &[("100", "1"), ("99", "2")],
+            &[("101", "1"), ("102", "3")],
+        );
+        let second = book(
+            2,
+            &[("100.5", "1"), ("100", "4")],
+            &[("101", "1"), ("102", "3")],
+        );
+        let third = book(3, &[("100", "4")], &[("100.8", "2"), ("101", "1")]);
+
+        let diff = L2BookDiff::between(&first, &second);
+        assert_eq!(diff.bids.adds.len(), 1);
+        assert_eq!(diff.bids.updates.len(), 1);
+        assert_eq!(diff.bids.removes, vec!["99".to_string()]);
+        assert!(diff.asks.is_empty());
+        let json = serde_json::to_string(&diff).unwrap();
+        assert!(!json.contains("asks"));
+
+        let mut encoder = BookDiffEncoder::new().with_snapshot_every(10);
+        let mut decoder = BookDiffDecoder::new();
+        let updates: Vec<BookUpdate> = [&first, &second, &third]
+            .into_iter()
+            .map(|book| encoder.encode(book))
+            .collect();
+        assert!(matches!(updates[0], BookUpdate::Snapshot(_)));
+        assert!(matches!(updates[2], BookUpdate::Diff(_)));
+        for (update, expected) in updates.iter().zip([&first, &second, &third]) {
+            let rebuilt = decoder.apply(update).unwrap();
+            assert_eq!(levels(rebuilt), levels(expected));
+            assert_eq!(rebuilt.time, expected.time);
+        }
+
+        // A lost diff is detected rather than applied to the wrong book
+        let mut decoder = BookDiffDecoder::new();
+        decoder.apply(&updates[0]).unwrap();
+        assert!(decoder.apply(&updates[2]).is_err());
+        assert!(decoder.book("ETH").is_none());
+    }
+}
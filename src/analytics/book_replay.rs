@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use tracing::warn;
+
+use super::{BookDiffDecoder, BookUpdate, OrderBook};
+use crate::{L2Book, L2BookData, Message, Trade, Trades};
+
+/// Rebuilds the books of recorded `l2Book` snapshots and `BookDiffEncoder` diffs into the
+/// `OrderBook` used live, for studying the book as it was or replaying it into `Backtester`.
+///
+/// Updates must be in time order per coin. A diff that does not follow the last book of its
+/// coin means one was lost, so the coin is dropped until its next snapshot rather than
+/// rebuilt wrong.
+#[derive(Debug, Default)]
+pub struct BookReconstructor {
+    decoder: BookDiffDecoder,
+    books: HashMap<String, OrderBook>,
+    skipped: usize,
+}
+
+impl BookReconstructor {
+    pub fn new() -> BookReconstructor {
+        BookReconstructor::default()
+    }
+
+    /// Applies `update`, returning the rebuilt book, or `None` if it was skipped.
+    pub fn apply(&mut self, update: &BookUpdate) -> Option<&OrderBook> {
+        match self.decoder.apply(update) {
+            Ok(book) => {
+                let book = OrderBook::from_l2(book);
+                let coin = book.coin.clone();
+                self.books.insert(coin.clone(), book);
+                self.books.get(&coin)
+            }
+            Err(err) => {
+                warn!("Skipping book update: {err}");
+                self.books.remove(update.coin());
+                self.skipped += 1;
+                None
+            }
+        }
+    }
+
+    /// Applies a full snapshot, as from `ArchiveClient::l2_books`.
+    pub fn apply_snapshot(&mut self, book: &L2BookData) -> &OrderBook {
+        let coin = book.coin.clone();
+        self.decoder.apply(&BookUpdate::Snapshot(book.clone())).ok();
+        self.books
+            .entry(coin)
+            .insert_entry(OrderBook::from_l2(book))
+            .into_mut()
+    }
+
+    /// Last rebuilt book of `coin`.
+    pub fn book(&self, coin: &str) -> Option<&OrderBook> {
+        self.books.get(coin)
+    }
+
+    /// Last rebuilt book of `coin` as an `l2Book` snapshot.
+    pub fn l2_book(&self, coin: &str) -> Option<&L2BookData> {
+        self.decoder.book(coin)
+    }
+
+    /// Updates skipped because they did not follow the last book of their coin.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Rebuilds every update into the books it leads to.
+    pub fn books<'a>(
+        &mut self,
+        updates: impl IntoIterator<Item = &'a BookUpdate>,
+    ) -> Vec<OrderBook> {
+        updates
+            .into_iter()
+            .filter_map(|update| self.apply(update).cloned())
+            .collect()
+    }
+
+    /// Rebuilds `updates` into `l2Book` messages merged in time order with `trades`, for
+    /// `Backtester::run`, so resting orders fill against the book and tape as they were. Trades
+    /// of the same time form one message and come after a book of that time.
+    pub fn messages<'a>(
+        &mut self,
+        updates: impl IntoIterator<Item = &'a BookUpdate>,
+        trades: &[Trade],
+    ) -> Vec<Message> {
+        let mut timed: Vec<(u64, Message)> = Vec::new();
+        for update in updates {
+            if self.apply(update).is_none() {
+                continue;
+            }
+            if let Some(book) = self.decoder.book(update.coin()) {
+                timed.push((
+                    book.time.as_millis(),
+                    Message::L2Book(L2Book { data: book.clone() }),
+                ));
+            }
+        }
+
+        let mut trades = trades.to_vec();
+        trades.sort_by_key(|trade| trade.time.as_millis());
+        for chunk in trades.chunk_by(|a, b| a.time.as_millis() == b.time.as_millis()) {
+            timed.push((
+                chunk[0].time.as_millis(),
+                Message::Trades(Trades {
+                    data: chunk.to_vec(),
+                }),
+            ));
+        }
+
+        timed.sort_by_key(|(time, message)| (*time, matches!(message, Message::Trades(_))));
+        timed.into_iter().map(|(_, message)| message).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analytics::BookDiffEncoder, BookLevel};
+
+    fn snapshot(time: u64, bid: &str, ask: &str) -> L2BookData {
+        let level = |px: &str| {
+            vec![BookLevel {
+                px: px.to_string(),
+                sz: "1".to_string(),
+                n: 1,
+            }]
+        };
+        L2BookData {
+            coin: "ETH".to_string(),
+            time: time.into(),
+            levels: vec![level(bid), level(ask)],
+        }
+    }
+
+    #[test]
+    fn test_reconstructs_books_and_merges_trades() {
+        let mut encoder = BookDiffEncoder::new();
+        let updates: Vec<BookUpdate> = [
+            snapshot(1_000, "99", "101"),
+            snapshot(2_000, "100", "101"),
+            snapshot(3_000, "100", "102"),
+        ]
+        .iter()
+        .map(|book| encoder.encode(book))
+        .collect();
+        assert!(matches!(updates[2], BookUpdate::Diff(_)));
+
+        let mut reconstructor = BookReconstructor::new();
+        let books = reconstructor.books(&updates);
+        assert_eq!(books.len(), 3);
+        assert_eq!(books[1].mid(), Some(100.5));
+        assert_eq!(books[2], OrderBook::from_l2(&snapshot(3_000, "100", "102")));
+
+        // A lost diff drops the coin until the next snapshot
+        let mut reconstructor = BookReconstructor::new();
+        assert!(reconstructor.apply(&updates[0]).is_some());
+        assert!(reconstructor.apply(&updates[2]).is_none());
+        assert!(reconstructor.book("ETH").is_none());
+        assert_eq!(reconstructor.skipped(), 1);
+
+        let trades: Vec<Trade> = serde_json::from_value(serde_json::json!([
+            {"coin": "ETH", "side": "B", "px": "101", "sz": "1", "time": 2_000, "hash": "0x", "tid": 1, "users": ["0x1", "0x2"]},
+            {"coin": "ETH", "side": "A", "px": "100", "sz": "1", "time": 1_500, "hash": "0x", "tid": 2, "users": ["0x1", "0x2"]}
+        ]))
+        .unwrap();
+        let messages = BookReconstructor::new().messages(&updates, &trades);
+        let kinds: Vec<(&str, u64)> = messages
+            .iter()
+            .map(|message| match message {
+                Message::L2Book(book) => ("book", book.data.time.as_millis()),
+                Message::Trades(trades) => ("trades", trades.data[0].time.as_millis()),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("book", 1_000),
+                ("trades", 1_500),
+                ("book", 2_000),
+                ("trades", 2_000),
+                ("book", 3_000)
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{price_tick_size, BookLevel, Message, Meta, UserFeesResponse};
+
+/// Fee rates paid on each fill, as fractions of notional.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeRates {
+    /// Negative for a rebate
+    pub maker: f64,
+    pub taker: f64,
+    /// Builder fee charged on top of both
+    pub builder: f64,
+}
+
+impl FeeRates {
+    /// The account's current perp rates from `InfoClient::user_fees`, without a builder fee.
+    pub fn from_user_fees(fees: &UserFeesResponse) -> FeeRates {
+        FeeRates {
+            maker: fees.user_add_rate.parse().unwrap_or_default(),
+            taker: fees.user_cross_rate.parse().unwrap_or_default(),
+            builder: 0.0,
+        }
+    }
+
+    /// Adds a builder fee in tenths of a basis point, as in `BuilderInfo::fee`.
+    pub fn with_builder_fee(mut self, tenths_bps: u64) -> Self {
+        self.builder = tenths_bps as f64 / 1e5;
+        self
+    }
+
+    fn round_trip_bps(&self, maker_legs: u32) -> f64 {
+        let taker_legs = 2 - maker_legs;
+        (maker_legs as f64 * self.maker + taker_legs as f64 * self.taker + 2.0 * self.builder) * 1e4
+    }
+}
+
+/// Price move needed for a round trip to break even, in basis points of the mid and in ticks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakEvenCapture {
+    pub bps: f64,
+    pub ticks: f64,
+}
+
+/// What a round trip on one asset must capture to cover its fees at the current spread, from
+/// `BreakEvenCalculator`.
+///
+/// Captures are moves of the mid between entry and exit: each leg resting as a maker order
+/// earns half the spread, each taker leg pays it. A negative capture means the spread alone
+/// covers the fees.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakEven {
+    pub coin: String,
+    pub mid: f64,
+    pub tick_size: f64,
+    pub spread_bps: f64,
+    pub spread_ticks: f64,
+    /// Both legs resting, as when quoting both sides
+    pub maker_maker: BreakEvenCapture,
+    /// Entering or exiting with a taker order
+    pub maker_taker: BreakEvenCapture,
+    pub taker_taker: BreakEvenCapture,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Asset {
+    sz_decimals: u32,
+    is_spot: bool,
+    top: Option<(f64, f64)>,
+}
+
+/// Keeps the break-even capture of each registered asset up to date from its best bid and
+/// ask, fed with `l2Book` or `bbo` messages through `handle_message`, and the account's fee
+/// rates, which change with its volume tier and can be replaced with `set_rates`.
+#[derive(Clone, Debug)]
+pub struct BreakEvenCalculator {
+    rates: FeeRates,
+    assets: HashMap<String, Asset>,
+}
+
+impl BreakEvenCalculator {
+    pub fn new(rates: FeeRates) -> BreakEvenCalculator {
+        BreakEvenCalculator {
+            rates,
+            assets: HashMap::new(),
+        }
+    }
+
+    /// Registers `coin`, whose tick size follows from its size decimals.
+    pub fn with_asset(mut self, coin: impl Into<String>, sz_decimals: u32, is_spot: bool) -> Self {
+        self.assets.insert(
+            coin.into(),
+            Asset {
+                sz_decimals,
+                is_spot,
+                top: None,
+            },
+        );
+        self
+    }
+
+    /// Registers every perp of `meta`.
+    pub fn with_perps(mut self, meta: &Meta) -> Self {
+        for asset in &meta.universe {
+            self = self.with_asset(asset.name.clone(), asset.sz_decimals, false);
+        }
+        self
+    }
+
+    pub fn rates(&self) -> FeeRates {
+        self.rates
+    }
+
+    pub fn set_rates(&mut self, rates: FeeRates) {
+        self.rates = rates;
+    }
+
+    pub fn handle_message(&mut self, message: &Message) {
+        match message {
+            Message::L2Book(book) => {
+                let book = super::OrderBook::from_l2(&book.data);
+                if let (Some((bid, _)), Some((ask, _))) = (book.best_bid(), book.best_ask()) {
+                    self.update_top(&book.coin, bid, ask);
+                }
+            }
+            Message::Bbo(bbo) => {
+                let px = |index: usize| {
+                    bbo.data
+                        .bbo
+                        .get(index)
+                        .and_then(Option::as_ref)
+                        .and_then(|level: &BookLevel| level.px.parse().ok())
+                };
+                if let (Some(bid), Some(ask)) = (px(0), px(1)) {
+                    self.update_top(&bbo.data.coin, bid, ask);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the best bid and ask of a registered coin.
+    pub fn update_top(&mut self, coin: &str, bid: f64, ask: f64) {
+        if let Some(asset) = self.assets.get_mut(coin) {
+            asset.top = Some((bid, ask));
+        }
+    }
+
+    /// The break-even of `coin`, once its book was seen.
+    pub fn break_even(&self, coin: &str) -> Option<BreakEven> {
+        let asset = self.assets.get(coin)?;
+        let (bid, ask) = asset.top?;
+        let mid = (bid + ask) / 2.0;
+        if mid <= 0.0 {
+            return None;
+        }
+        let tick_size = price_tick_size(mid, asset.sz_decimals, asset.is_spot);
+        let spread_bps = (ask - bid) / mid * 1e4;
+        let capture = |bps: f64| BreakEvenCapture {
+            bps,
+            ticks: bps / 1e4 * mid / tick_size,
+        };
+        Some(BreakEven {
+            coin: coin.to_string(),
+            mid,
+            tick_size,
+            spread_bps,
+            spread_ticks: (ask - bid) / tick_size,
+            maker_maker: capture(self.rates.round_trip_bps(2) - spread_bps),
+            maker_taker: capture(self.rates.round_trip_bps(1)),
+            taker_taker: capture(self.rates.round_trip_bps(0) + spread_bps),
+        })
+    }
+
+    /// Break-evens of every registered coin with a book, by coin.
+    pub fn break_evens(&self) -> Vec<BreakEven> {
+        let mut coins: Vec<&String> = self.assets.keys().collect();
+        coins.sort();
+        coins
+            .into_iter()
+            .filter_map(|coin| self.break_even(coin))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_break_even_combines_fees_and_spread() {
+        let rates = FeeRates {
+            maker: 0.00015,
+            taker: 0.00045,
+            builder: 0.0,
+        }
+        .with_builder_fee(10);
+        assert!((rates.builder - 0.0001).abs() < 1e-12);
+        let mut calculator = BreakEvenCalculator::new(rates).with_asset("ETH", 4, false);
+        assert_eq!(calculator.break_even("ETH"), None);
+        calculator.update_top("BTC", 1.0, 2.0);
+        calculator.update_top("ETH", 1999.9, 2000.1);
+
+        let eth = calculator.break_even("ETH").unwrap();
+        assert_eq!(calculator.break_evens(), vec![eth.clone()]);
+        // 5 significant figures at 2000
+        assert!((eth.tick_size - 0.1).abs() < 1e-12);
+        assert!((eth.spread_ticks - 2.0).abs() < 1e-9);
+        assert!((eth.spread_bps - 1.0).abs() < 1e-9);
+        // Fees of 1.5 + 1.5 + 2 bps, less the 1 bp spread earned
+        assert!((eth.maker_maker.bps - 4.0).abs() < 1e-9);
+        assert!((eth.maker_maker.ticks - 8.0).abs() < 1e-9);
+        assert!((eth.maker_taker.bps - 8.0).abs() < 1e-9);
+        assert!((eth.taker_taker.bps - 12.0).abs() < 1e-9);
+    }
+}
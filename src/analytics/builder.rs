@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, io::Write};
+
+use alloy::primitives::Address;
+use chrono::DateTime;
+use serde::Serialize;
+use tracing::warn;
+
+use super::{csv::write_row, export::fetch_user_fills, fees::usdc_fee};
+use crate::{prelude::*, InfoClient, Timestamp, UserFillsResponse};
+
+/// Builder fees earned on a set of fills, in USDC.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderRevenue {
+    pub fills: usize,
+    /// Notional of the fills that carried a builder fee
+    pub volume: f64,
+    pub fees: f64,
+}
+
+impl BuilderRevenue {
+    /// Builder fees per unit of volume.
+    pub fn rate(&self) -> Option<f64> {
+        (self.volume > 0.0).then(|| self.fees / self.volume)
+    }
+
+    fn add(&mut self, volume: f64, fee: f64) {
+        self.fills += 1;
+        self.volume += volume;
+        self.fees += fee;
+    }
+}
+
+/// Builder fees earned from the fills of the users trading through a builder code, in total,
+/// per user and per UTC day, for reconciling revenue.
+///
+/// Fills only report the builder fee they paid, not the builder it went to, so the fills of a
+/// user who also trades through other builders count their fees too. `fetch` leaves out the
+/// users who never approved the builder, whose builder fees cannot be its own.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderRevenueReport {
+    pub total: BuilderRevenue,
+    pub by_user: BTreeMap<Address, BuilderRevenue>,
+    /// Keyed by `YYYY-MM-DD`
+    pub by_day: BTreeMap<String, BuilderRevenue>,
+    /// Keyed by `YYYY-MM-DD`, then by user
+    pub by_day_and_user: BTreeMap<String, BTreeMap<Address, BuilderRevenue>>,
+    /// Highest fee each fetched user approved for the builder, in tenths of a basis point
+    pub approved_fees: BTreeMap<Address, u64>,
+}
+
+impl BuilderRevenueReport {
+    pub fn new() -> BuilderRevenueReport {
+        BuilderRevenueReport::default()
+    }
+
+    /// Fetches the fills of `users` between `start_time` and `end_time` along with the fee each
+    /// approved for `builder`, skipping the fills of users whose approval is zero.
+    pub async fn fetch(
+        info: &InfoClient,
+        builder: Address,
+        users: &[Address],
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> Result<BuilderRevenueReport> {
+        let mut report = BuilderRevenueReport::new();
+        for &user in users {
+            let approved = info.max_builder_fee(user, builder).await?;
+            report.approved_fees.insert(user, approved);
+            if approved == 0 {
+                continue;
+            }
+            for fill in fetch_user_fills(info, user, start_time, end_time).await? {
+                report.add_fill(user, &fill);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Counts the builder fee `user` paid on `fill`, ignoring fills without one.
+    pub fn add_fill(&mut self, user: Address, fill: &UserFillsResponse) {
+        let Some(builder_fee) = fill.builder_fee.as_deref() else {
+            return;
+        };
+        let (Ok(px), Ok(sz), Ok(builder_fee)) = (
+            fill.px.parse::<f64>(),
+            fill.sz.parse::<f64>(),
+            builder_fee.parse::<f64>(),
+        ) else {
+            warn!("Could not parse fill {}", fill.tid);
+            return;
+        };
+        if builder_fee == 0.0 {
+            return;
+        }
+        let fee = usdc_fee(fill, px, builder_fee);
+        let volume = px * sz;
+        let day = DateTime::from_timestamp_millis(fill.time.as_millis() as i64)
+            .map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+
+        for revenue in [
+            &mut self.total,
+            self.by_user.entry(user).or_default(),
+            self.by_day.entry(day.clone()).or_default(),
+            self.by_day_and_user
+                .entry(day)
+                .or_default()
+                .entry(user)
+                .or_default(),
+        ] {
+            revenue.add(volume, fee);
+        }
+    }
+}
+
+/// Writes one row per day and user with columns `day`, `user`, `fills`, `volume` and
+/// `builder_fees`, ordered by day then user.
+pub fn write_builder_revenue_csv<W: Write>(
+    mut writer: W,
+    report: &BuilderRevenueReport,
+) -> Result<()> {
+    let columns = ["day", "user", "fills", "volume", "builder_fees"];
+    write_row(&mut writer, &columns.map(String::from))?;
+    for (day, users) in &report.by_day_and_user {
+        for (user, revenue) in users {
+            write_row(
+                &mut writer,
+                &[
+                    day.clone(),
+                    user.to_string(),
+                    revenue.fills.to_string(),
+                    revenue.volume.to_string(),
+                    revenue.fees.to_string(),
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(time: u64, fee_token: &str, builder_fee: Option<&str>) -> UserFillsResponse {
+        let builder_fee = builder_fee.map_or("null".to_string(), |fee| format!(r#""{fee}""#));
+        serde_json::from_str(&format!(
+            r#"{{"closedPnl":"0","coin":"ETH","crossed":true,"dir":"Open Long","hash":"0x0","oid":1,"px":"100","side":"B","startPosition":"0","sz":"10","time":{time},"fee":"0.5","tid":{time},"feeToken":"{fee_token}","twapId":null,"builderFee":{builder_fee}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_builder_revenue_per_user_and_day() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut report = BuilderRevenueReport::new();
+        report.add_fill(alice, &fill(1_700_000_000_000, "USDC", Some("0.1")));
+        report.add_fill(alice, &fill(1_700_100_000_000, "USDC", Some("0.2")));
+        // Paid in the base token, valued at the fill price
+        report.add_fill(bob, &fill(1_700_100_000_000, "PURR", Some("0.001")));
+        report.add_fill(bob, &fill(1_700_100_000_000, "USDC", None));
+
+        assert_eq!(report.total.fills, 3);
+        assert!((report.total.fees - 0.4).abs() < 1e-9);
+        assert!((report.by_user[&alice].fees - 0.3).abs() < 1e-9);
+        assert!((report.by_day["2023-11-16"].fees - 0.3).abs() < 1e-9);
+        assert!((report.by_user[&bob].rate().unwrap() - 0.0001).abs() < 1e-12);
+
+        let mut csv = Vec::new();
+        write_builder_revenue_csv(&mut csv, &report).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "day,user,fills,volume,builder_fees");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[1].starts_with(&format!("2023-11-14,{alice},1,1000,")));
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use tracing::warn;
+
+use crate::{CandleData, Clock, Message, Trade};
+
+#[derive(Clone, Debug)]
+struct Bar {
+    open: (u64, f64),
+    high: f64,
+    low: f64,
+    close: (u64, f64),
+    volume: f64,
+    tids: HashSet<u64>,
+}
+
+/// Builds OHLCV candles of any interval, sub-minute included, from the `trades` stream, for
+/// intervals the exchange does not serve.
+///
+/// Candles are aligned to multiples of the interval since the Unix epoch, so intervals that
+/// divide a day start on wall-clock boundaries. A candle is closed once a trade at least the
+/// grace period past its end arrives for the same coin, or `advance` passes that point; trades
+/// arriving within the grace period still count, later ones are dropped and counted in
+/// `late_trades`. Trades are deduplicated by trade id, so the snapshot sent on resubscribing
+/// is not counted twice. Intervals without trades produce no candle. `tick` closes candles at
+/// the time of the process clock, or of the clock given with `with_clock`.
+#[derive(Clone, Debug)]
+pub struct CandleAggregator {
+    interval_ms: u64,
+    grace_ms: u64,
+    label: String,
+    /// Open candles by coin and open time
+    bars: BTreeMap<String, BTreeMap<u64, Bar>>,
+    /// Open time before which each coin's candles are closed
+    closed_before: HashMap<String, u64>,
+    late_trades: u64,
+    clock: Arc<dyn Clock>,
+}
+
+impl CandleAggregator {
+    pub fn new(interval: Duration) -> CandleAggregator {
+        let interval_ms = (interval.as_millis() as u64).max(1);
+        CandleAggregator {
+            interval_ms,
+            grace_ms: 0,
+            label: interval_label(interval_ms),
+            bars: BTreeMap::new(),
+            closed_before: HashMap::new(),
+            late_trades: 0,
+            clock: crate::clock(),
+        }
+    }
+
+    /// Keeps candles open for `grace` after they end, for trades delivered late.
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace_ms = grace.as_millis() as u64;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Interval in the form used by `CandleData::interval`, such as `15s` or `2m`.
+    pub fn interval(&self) -> &str {
+        &self.label
+    }
+
+    /// Trades dropped because their candle had already closed.
+    pub fn late_trades(&self) -> u64 {
+        self.late_trades
+    }
+
+    /// Adds the trades in a `trades` message, returning the candles it closed. Other messages
+    /// are ignored.
+    pub fn handle_message(&mut self, message: &Message) -> Vec<CandleData> {
+        match message {
+            Message::Trades(trades) => trades
+                .data
+                .iter()
+                .flat_map(|trade| self.add_trade(trade))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Adds one trade, returning the candles of its coin it closed, oldest first.
+    pub fn add_trade(&mut self, trade: &Trade) -> Vec<CandleData> {
+        let (Ok(px), Ok(sz)) = (trade.px.parse::<f64>(), trade.sz.parse::<f64>()) else {
+            warn!("Could not parse trade {}", trade.tid);
+            return Vec::new();
+        };
+        let time = trade.time.as_millis();
+        let open_time = time - time % self.interval_ms;
+        if self
+            .closed_before
+            .get(&trade.coin)
+            .is_some_and(|&closed| open_time < closed)
+        {
+            self.late_trades += 1;
+            return Vec::new();
+        }
+
+        let bar = self
+            .bars
+            .entry(trade.coin.clone())
+            .or_default()
+            .entry(open_time)
+            .or_insert_with(|| Bar {
+                open: (time, px),
+                high: px,
+                low: px,
+                close: (time, px),
+                volume: 0.0,
+                tids: HashSet::new(),
+            });
+        if bar.tids.insert(trade.tid) {
+            // Trades within the grace period can arrive out of order
+            if time < bar.open.0 {
+                bar.open = (time, px);
+            }
+            if time >= bar.close.0 {
+                bar.close = (time, px);
+            }
+            bar.high = bar.high.max(px);
+            bar.low = bar.low.min(px);
+            bar.volume += sz;
+        }
+        self.close_coin(&trade.coin, time)
+    }
+
+    /// Closes every candle that ended at least the grace period before `now`, in ms, so quiet
+    /// markets still produce candles. Returns them by coin, oldest first.
+    pub fn advance(&mut self, now: u64) -> Vec<CandleData> {
+        let coins: Vec<String> = self.bars.keys().cloned().collect();
+        coins
+            .iter()
+            .flat_map(|coin| self.close_coin(coin, now))
+            .collect()
+    }
+
+    /// Closes candles as `advance` does at the clock's current time.
+    pub fn tick(&mut self) -> Vec<CandleData> {
+        let now = self.clock.now_ms();
+        self.advance(now)
+    }
+
+    /// The open candle of `coin` with the latest start, if any.
+    pub fn current(&self, coin: &str) -> Option<CandleData> {
+        let (&open_time, bar) = self.bars.get(coin)?.last_key_value()?;
+        Some(self.candle(coin, open_time, bar))
+    }
+
+    fn close_coin(&mut self, coin: &str, now: u64) -> Vec<CandleData> {
+        let Some(threshold) = now.checked_sub(self.interval_ms + self.grace_ms) else {
+            return Vec::new();
+        };
+        // Candles opening after the threshold are still within their period or grace
+        let cutoff = (threshold / self.interval_ms + 1) * self.interval_ms;
+        let Some(bars) = self.bars.get_mut(coin) else {
+            return Vec::new();
+        };
+        let open = bars.split_off(&cutoff);
+        let closed = std::mem::replace(bars, open);
+        if bars.is_empty() {
+            self.bars.remove(coin);
+        }
+        let closed_before = self.closed_before.entry(coin.to_string()).or_default();
+        *closed_before = (*closed_before).max(cutoff);
+        closed
+            .iter()
+            .map(|(&open_time, bar)| self.candle(coin, open_time, bar))
+            .collect()
+    }
+
+    fn candle(&self, coin: &str, open_time: u64, bar: &Bar) -> CandleData {
+        CandleData {
+            time_close: (open_time + self.interval_ms - 1).into(),
+            close: bar.close.1.to_string(),
+            high: bar.high.to_string(),
+            interval: self.label.clone(),
+            low: bar.low.to_string(),
+            num_trades: bar.tids.len() as u64,
+            open: bar.open.1.to_string(),
+            coin: coin.to_string(),
+            time_open: open_time.into(),
+            volume: bar.volume.to_string(),
+        }
+    }
+}
+
+fn interval_label(interval_ms: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [
+        (86_400_000, "d"),
+        (3_600_000, "h"),
+        (60_000, "m"),
+        (1_000, "s"),
+    ];
+    UNITS
+        .iter()
+        .find(|(unit_ms, _)| interval_ms.is_multiple_of(*unit_ms))
+        .map(|(unit_ms, unit)| format!("{}{unit}", interval_ms / unit_ms))
+        .unwrap_or_else(|| format!("{interval_ms}ms"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Side, SimClock};
+
+    fn trade(tid: u64, time: u64, px: f64, sz: f64) -> Trade {
+        Trade {
+            coin: "ETH".to_string(),
+            side: Side::Buy,
+            px: px.to_string(),
+            sz: sz.to_string(),
+            time: time.into(),
+            hash: "0x0".to_string(),
+            tid,
+            users: (String::new(), String::new()),
+        }
+    }
+
+    #[test]
+    fn test_aggregates_with_grace_and_late_trades() {
+        let clock = SimClock::new(0);
+        let mut candles = CandleAggregator::new(Duration::from_secs(5))
+            .with_grace(Duration::from_secs(1))
+            .with_clock(clock.clone());
+        assert_eq!(candles.interval(), "5s");
+        assert_eq!(interval_label(90_000), "90s");
+
+        assert!(candles.add_trade(&trade(1, 10_500, 100.0, 1.0)).is_empty());
+        assert!(candles.add_trade(&trade(2, 12_000, 103.0, 2.0)).is_empty());
+        // Next candle, but the first is still within its grace period
+        assert!(candles.add_trade(&trade(3, 15_200, 101.0, 1.0)).is_empty());
+        // Late but within grace, and earlier than the first trade so it becomes the open
+        assert!(candles.add_trade(&trade(4, 10_100, 99.0, 1.0)).is_empty());
+        // Duplicate from a resubscription snapshot
+        assert!(candles.add_trade(&trade(2, 12_000, 103.0, 2.0)).is_empty());
+
+        let closed = candles.add_trade(&trade(5, 16_000, 102.0, 1.0));
+        assert_eq!(closed.len(), 1);
+        let candle = &closed[0];
+        assert_eq!(
+            (candle.time_open.as_millis(), candle.time_close.as_millis()),
+            (10_000, 14_999)
+        );
+        assert_eq!(
+            (
+                candle.open.as_str(),
+                candle.high.as_str(),
+                candle.low.as_str()
+            ),
+            ("99", "103", "99")
+        );
+        assert_eq!(
+            (candle.close.as_str(), candle.volume.as_str()),
+            ("103", "4")
+        );
+        assert_eq!(candle.num_trades, 3);
+
+        assert!(candles.add_trade(&trade(6, 14_000, 100.0, 1.0)).is_empty());
+        assert_eq!(candles.late_trades(), 1);
+        assert_eq!(candles.current("ETH").unwrap().num_trades, 2);
+
+        clock.set(21_000);
+        let closed = candles.tick();
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].time_open, 15_000);
+        assert!(candles.current("ETH").is_none());
+    }
+}
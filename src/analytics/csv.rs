@@ -0,0 +1,24 @@
+use std::io::Write;
+
+use crate::{prelude::*, Error};
+
+/// Writes one CSV row, quoting fields that contain separators, quotes or line breaks.
+pub(crate) fn write_row<W: Write>(writer: &mut W, fields: &[String]) -> Result<()> {
+    let mut line = String::new();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            line.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            line.push('"');
+            line.push_str(&field.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(field);
+        }
+    }
+    line.push('\n');
+    writer
+        .write_all(line.as_bytes())
+        .map_err(|e| Error::Io(e.to_string()))
+}
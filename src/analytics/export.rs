@@ -0,0 +1,328 @@
+use std::io::Write;
+
+use alloy::primitives::Address;
+use serde::Serialize;
+
+use super::csv::write_row;
+use crate::{
+    prelude::*, InfoClient, LedgerUpdate, LedgerUpdateData, Timestamp, UserFillsResponse,
+    UserFundingResponse,
+};
+
+/// All fills between `start_time` and `end_time`, paging through `userFillsByTime`. The
+/// exchange only serves the most recent 10000 fills this way.
+pub async fn fetch_user_fills(
+    info: &InfoClient,
+    user: Address,
+    start_time: Timestamp,
+    end_time: Option<Timestamp>,
+) -> Result<Vec<UserFillsResponse>> {
+    info.user_fills_history(user, start_time, end_time)
+        .collect_all()
+        .await
+}
+
+/// All funding payments between `start_time` and `end_time`.
+pub async fn fetch_user_funding(
+    info: &InfoClient,
+    user: Address,
+    start_time: Timestamp,
+    end_time: Option<Timestamp>,
+) -> Result<Vec<UserFundingResponse>> {
+    info.user_funding_history_stream(user, start_time, end_time)
+        .collect_all()
+        .await
+}
+
+/// All non-funding ledger updates between `start_time` and `end_time`.
+pub async fn fetch_ledger_updates(
+    info: &InfoClient,
+    user: Address,
+    start_time: Timestamp,
+    end_time: Option<Timestamp>,
+) -> Result<Vec<LedgerUpdateData>> {
+    info.ledger_updates_history(user, start_time, end_time)
+        .collect_all()
+        .await
+}
+
+/// A ledger update flattened to the columns shared by every update type.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerRow {
+    pub time: u64,
+    pub hash: String,
+    /// Update type as the exchange names it, e.g. `deposit` or `spotTransfer`
+    pub kind: String,
+    pub token: String,
+    /// Amount in `token`; the account value for liquidations
+    pub amount: String,
+    pub usdc_value: Option<String>,
+    pub fee: Option<String>,
+    /// Sending side of transfers, the vault for vault withdrawals
+    pub user: Option<String>,
+    /// Receiving side of transfers, the vault for vault deposits, or `perp` and `spot` for
+    /// class transfers
+    pub destination: Option<String>,
+}
+
+impl From<&LedgerUpdateData> for LedgerRow {
+    fn from(update: &LedgerUpdateData) -> LedgerRow {
+        let usdc = |amount: &str| (amount.to_string(), Some(amount.to_string()));
+        let address = |address: &Address| Some(address.to_string());
+        let (kind, token, (amount, usdc_value), fee, user, destination) = match &update.delta {
+            LedgerUpdate::Deposit(d) => ("deposit", "USDC", usdc(&d.usdc), None, None, None),
+            LedgerUpdate::Withdraw(w) => (
+                "withdraw",
+                "USDC",
+                usdc(&w.usdc),
+                Some(w.fee.clone()),
+                None,
+                None,
+            ),
+            LedgerUpdate::InternalTransfer(t) => (
+                "internalTransfer",
+                "USDC",
+                usdc(&t.usdc),
+                Some(t.fee.clone()),
+                address(&t.user),
+                address(&t.destination),
+            ),
+            LedgerUpdate::SubAccountTransfer(t) => (
+                "subAccountTransfer",
+                "USDC",
+                usdc(&t.usdc),
+                None,
+                address(&t.user),
+                address(&t.destination),
+            ),
+            LedgerUpdate::LedgerLiquidation(l) => (
+                "ledgerLiquidation",
+                "USDC",
+                usdc(&l.account_value.to_string()),
+                None,
+                None,
+                None,
+            ),
+            LedgerUpdate::VaultDeposit(v) => (
+                "vaultDeposit",
+                "USDC",
+                usdc(&v.usdc),
+                None,
+                None,
+                address(&v.vault),
+            ),
+            LedgerUpdate::VaultCreate(v) => (
+                "vaultCreate",
+                "USDC",
+                usdc(&v.usdc),
+                None,
+                None,
+                address(&v.vault),
+            ),
+            LedgerUpdate::VaultDistribution(v) => (
+                "vaultDistribution",
+                "USDC",
+                usdc(&v.usdc),
+                None,
+                address(&v.vault),
+                None,
+            ),
+            LedgerUpdate::VaultWithdraw(v) => (
+                "vaultWithdraw",
+                "USDC",
+                usdc(&v.net_withdrawn_usd),
+                Some(v.commission.clone()),
+                address(&v.vault),
+                address(&v.user),
+            ),
+            LedgerUpdate::VaultLeaderCommission(v) => (
+                "vaultLeaderCommission",
+                "USDC",
+                usdc(&v.usdc),
+                None,
+                None,
+                address(&v.user),
+            ),
+            LedgerUpdate::AccountClassTransfer(t) => (
+                "accountClassTransfer",
+                "USDC",
+                usdc(&t.usdc),
+                None,
+                None,
+                Some(if t.to_perp { "perp" } else { "spot" }.to_string()),
+            ),
+            LedgerUpdate::SpotTransfer(t) => (
+                "spotTransfer",
+                t.token.as_str(),
+                (t.amount.clone(), Some(t.usdc_value.clone())),
+                Some(t.fee.clone()),
+                address(&t.user),
+                address(&t.destination),
+            ),
+            LedgerUpdate::SpotGenesis(g) => (
+                "spotGenesis",
+                g.token.as_str(),
+                (g.amount.clone(), None),
+                None,
+                None,
+                None,
+            ),
+            LedgerUpdate::Unknown => ("unknown", "", (String::new(), None), None, None, None),
+        };
+        LedgerRow {
+            time: update.time.as_millis(),
+            hash: update.hash.clone(),
+            kind: kind.to_string(),
+            token: token.to_string(),
+            amount,
+            usdc_value,
+            fee,
+            user,
+            destination,
+        }
+    }
+}
+
+fn header<W: Write>(writer: &mut W, columns: &[&str]) -> Result<()> {
+    write_row(
+        writer,
+        &columns.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+    )
+}
+
+/// Writes columns `time`, `coin`, `side`, `px`, `sz`, `dir`, `closed_pnl`, `fee`, `fee_token`,
+/// `builder_fee`, `crossed`, `start_position`, `oid`, `tid`, `hash` and `twap_id`, keeping
+/// decimals as the exchange reported them.
+pub fn write_fills_csv<W: Write>(mut writer: W, fills: &[UserFillsResponse]) -> Result<()> {
+    header(
+        &mut writer,
+        &[
+            "time",
+            "coin",
+            "side",
+            "px",
+            "sz",
+            "dir",
+            "closed_pnl",
+            "fee",
+            "fee_token",
+            "builder_fee",
+            "crossed",
+            "start_position",
+            "oid",
+            "tid",
+            "hash",
+            "twap_id",
+        ],
+    )?;
+    for fill in fills {
+        write_row(
+            &mut writer,
+            &[
+                fill.time.to_string(),
+                fill.coin.clone(),
+                fill.side.to_string(),
+                fill.px.clone(),
+                fill.sz.clone(),
+                fill.dir.to_string(),
+                fill.closed_pnl.clone(),
+                fill.fee.clone(),
+                fill.fee_token.clone(),
+                fill.builder_fee.clone().unwrap_or_default(),
+                fill.crossed.to_string(),
+                fill.start_position.clone(),
+                fill.oid.to_string(),
+                fill.tid.to_string(),
+                fill.hash.clone(),
+                fill.twap_id.map(|id| id.to_string()).unwrap_or_default(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes columns `time`, `coin`, `usdc`, `szi`, `funding_rate` and `hash`.
+pub fn write_funding_csv<W: Write>(mut writer: W, funding: &[UserFundingResponse]) -> Result<()> {
+    header(
+        &mut writer,
+        &["time", "coin", "usdc", "szi", "funding_rate", "hash"],
+    )?;
+    for funding in funding {
+        write_row(
+            &mut writer,
+            &[
+                funding.time.to_string(),
+                funding.delta.coin.clone(),
+                funding.delta.usdc.clone(),
+                funding.delta.szi.clone(),
+                funding.delta.funding_rate.clone(),
+                funding.hash.clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the `LedgerRow` columns, `kind` as `type`.
+pub fn write_ledger_csv<W: Write>(mut writer: W, updates: &[LedgerUpdateData]) -> Result<()> {
+    header(
+        &mut writer,
+        &[
+            "time",
+            "hash",
+            "type",
+            "token",
+            "amount",
+            "usdc_value",
+            "fee",
+            "user",
+            "destination",
+        ],
+    )?;
+    for row in updates.iter().map(LedgerRow::from) {
+        write_row(
+            &mut writer,
+            &[
+                row.time.to_string(),
+                row.hash,
+                row.kind,
+                row.token,
+                row.amount,
+                row.usdc_value.unwrap_or_default(),
+                row.fee.unwrap_or_default(),
+                row.user.unwrap_or_default(),
+                row.destination.unwrap_or_default(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_csv() {
+        let updates: Vec<LedgerUpdateData> = serde_json::from_str(
+            r#"[{"time":1,"hash":"0x1","delta":{"type":"deposit","usdc":"100.5"}},
+            {"time":2,"hash":"0x2","delta":{"type":"spotTransfer","token":"PURR","amount":"10","usdcValue":"2.1","user":"0x0000000000000000000000000000000000000001","destination":"0x0000000000000000000000000000000000000002","fee":"0"}},
+            {"time":3,"hash":"0x3","delta":{"type":"accountClassTransfer","usdc":"5","toPerp":false}}]"#,
+        )
+        .unwrap();
+        let mut csv = Vec::new();
+        write_ledger_csv(&mut csv, &updates).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "time,hash,type,token,amount,usdc_value,fee,user,destination",
+                "1,0x1,deposit,USDC,100.5,100.5,,,",
+                "2,0x2,spotTransfer,PURR,10,2.1,0,0x0000000000000000000000000000000000000001,0x0000000000000000000000000000000000000002",
+                "3,0x3,accountClassTransfer,USDC,5,5,,,spot",
+            ]
+        );
+    }
+}
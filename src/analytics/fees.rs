@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{UserFeesResponse, UserFillsResponse};
+
+const USDC: &str = "USDC";
+
+/// Volume and fees of a set of fills, in USDC.
+///
+/// Fees are the exchange's, net of rebates, and exclude builder fees, which are kept apart in
+/// `builder_fees` so rates can be compared with the fee schedule.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeBucket {
+    pub fills: usize,
+    pub maker_volume: f64,
+    pub taker_volume: f64,
+    /// Negative when rebates exceed fees
+    pub maker_fees: f64,
+    pub taker_fees: f64,
+    pub builder_fees: f64,
+}
+
+impl FeeBucket {
+    pub fn volume(&self) -> f64 {
+        self.maker_volume + self.taker_volume
+    }
+
+    /// Exchange and builder fees together.
+    pub fn total_fees(&self) -> f64 {
+        self.maker_fees + self.taker_fees + self.builder_fees
+    }
+
+    /// Share of volume that added liquidity, `None` without volume.
+    pub fn maker_fraction(&self) -> Option<f64> {
+        ratio(self.maker_volume, self.volume())
+    }
+
+    /// Exchange fees per unit of maker volume.
+    pub fn maker_rate(&self) -> Option<f64> {
+        ratio(self.maker_fees, self.maker_volume)
+    }
+
+    /// Exchange fees per unit of taker volume.
+    pub fn taker_rate(&self) -> Option<f64> {
+        ratio(self.taker_fees, self.taker_volume)
+    }
+
+    /// Exchange fees per unit of volume.
+    pub fn effective_rate(&self) -> Option<f64> {
+        ratio(self.maker_fees + self.taker_fees, self.volume())
+    }
+
+    fn add(&mut self, crossed: bool, volume: f64, fee: f64, builder_fee: f64) {
+        self.fills += 1;
+        if crossed {
+            self.taker_volume += volume;
+            self.taker_fees += fee - builder_fee;
+        } else {
+            self.maker_volume += volume;
+            self.maker_fees += fee - builder_fee;
+        }
+        self.builder_fees += builder_fee;
+    }
+}
+
+/// Values a fee charged in the fill's fee token in USDC, at the fill price for base tokens.
+pub(super) fn usdc_fee(fill: &UserFillsResponse, px: f64, fee: f64) -> f64 {
+    if fill.fee_token == USDC {
+        fee
+    } else {
+        fee * px
+    }
+}
+
+fn ratio(num: f64, den: f64) -> Option<f64> {
+    (den > 0.0).then(|| num / den)
+}
+
+/// Maker/taker volume and fees of an account's fills, in total, per asset and per UTC day.
+///
+/// Fees charged in a token other than USDC, as on spot buys where the fee is taken from the
+/// base token received, are valued at the fill price.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeReport {
+    pub total: FeeBucket,
+    pub by_coin: BTreeMap<String, FeeBucket>,
+    /// Keyed by `YYYY-MM-DD`
+    pub by_day: BTreeMap<String, FeeBucket>,
+}
+
+impl FeeReport {
+    pub fn new() -> FeeReport {
+        FeeReport::default()
+    }
+
+    pub fn from_fills<'a>(fills: impl IntoIterator<Item = &'a UserFillsResponse>) -> FeeReport {
+        let mut report = FeeReport::new();
+        fills.into_iter().for_each(|fill| report.add_fill(fill));
+        report
+    }
+
+    pub fn add_fill(&mut self, fill: &UserFillsResponse) {
+        let (Ok(px), Ok(sz), Ok(fee)) = (
+            fill.px.parse::<f64>(),
+            fill.sz.parse::<f64>(),
+            fill.fee.parse::<f64>(),
+        ) else {
+            warn!("Could not parse fill {}", fill.tid);
+            return;
+        };
+        let builder_fee: f64 = fill
+            .builder_fee
+            .as_deref()
+            .and_then(|fee| fee.parse().ok())
+            .unwrap_or_default();
+        let (fee, builder_fee) = (usdc_fee(fill, px, fee), usdc_fee(fill, px, builder_fee));
+        let volume = px * sz;
+        let day = DateTime::from_timestamp_millis(fill.time.as_millis() as i64)
+            .map(|time| time.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+
+        for bucket in [
+            &mut self.total,
+            self.by_coin.entry(fill.coin.clone()).or_default(),
+            self.by_day.entry(day).or_default(),
+        ] {
+            bucket.add(fill.crossed, volume, fee, builder_fee);
+        }
+    }
+
+    /// Compares the rates actually paid with the account's fee schedule and the tiers its
+    /// exchange-reported volume qualifies for.
+    pub fn check_schedule(&self, fees: &UserFeesResponse) -> FeeTierCheck {
+        let parse = |value: &str| value.parse::<f64>().unwrap_or_default();
+        let (mut volume, mut maker_volume, mut exchange_volume) = (0.0, 0.0, 0.0);
+        for day in &fees.daily_user_vlm {
+            volume += parse(&day.user_add) + parse(&day.user_cross);
+            maker_volume += parse(&day.user_add);
+            exchange_volume += parse(&day.exchange);
+        }
+        let maker_share = ratio(maker_volume, exchange_volume).unwrap_or_default();
+        let tiers = &fees.fee_schedule.tiers;
+        let vip_tier = tiers
+            .vip
+            .iter()
+            .rposition(|tier| volume >= parse(&tier.ntl_cutoff));
+        let mm_tier = tiers
+            .mm
+            .iter()
+            .rposition(|tier| maker_share >= parse(&tier.maker_fraction_cutoff));
+
+        let schedule_add_rate = parse(&fees.user_add_rate);
+        let schedule_cross_rate = parse(&fees.user_cross_rate);
+        let maker_rate = self.total.maker_rate();
+        let taker_rate = self.total.taker_rate();
+        FeeTierCheck {
+            schedule_add_rate,
+            schedule_cross_rate,
+            maker_rate,
+            taker_rate,
+            maker_excess: maker_rate.map(|rate| rate - schedule_add_rate),
+            taker_excess: taker_rate.map(|rate| rate - schedule_cross_rate),
+            volume,
+            vip_tier,
+            maker_share,
+            mm_tier,
+        }
+    }
+}
+
+/// Fee rates paid against the account's schedule, from `FeeReport::check_schedule`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeTierCheck {
+    /// Maker rate the exchange currently charges the account
+    pub schedule_add_rate: f64,
+    /// Taker rate the exchange currently charges the account
+    pub schedule_cross_rate: f64,
+    pub maker_rate: Option<f64>,
+    pub taker_rate: Option<f64>,
+    /// Paid maker rate above the scheduled one, positive when overpaying
+    pub maker_excess: Option<f64>,
+    pub taker_excess: Option<f64>,
+    /// Volume over the window the exchange reports in `dailyUserVlm`
+    pub volume: f64,
+    /// Index of the highest VIP tier the volume reaches, `None` below the first
+    pub vip_tier: Option<usize>,
+    /// Account's share of exchange maker volume over the same window
+    pub maker_share: f64,
+    /// Index of the highest market maker tier the maker share reaches
+    pub mm_tier: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(
+        coin: &str,
+        time: u64,
+        crossed: bool,
+        fee: &str,
+        fee_token: &str,
+        builder_fee: &str,
+    ) -> String {
+        format!(
+            r#"{{"closedPnl":"0","coin":"{coin}","crossed":{crossed},"dir":"Open Long","hash":"0x0","oid":1,"px":"100","side":"B","startPosition":"0","sz":"10","time":{time},"fee":"{fee}","tid":{time},"feeToken":"{fee_token}","twapId":null,"builderFee":"{builder_fee}"}}"#
+        )
+    }
+
+    #[test]
+    fn test_fee_report_and_schedule_check() {
+        let fills: Vec<UserFillsResponse> = serde_json::from_str(&format!(
+            "[{},{},{}]",
+            fill("ETH", 1_700_000_000_000, true, "0.55", "USDC", "0.1"),
+            fill("ETH", 1_700_100_000_000, false, "0.05", "USDC", "0.1"),
+            fill(
+                "PURR/USDC",
+                1_700_100_000_000,
+                true,
+                "0.0055",
+                "PURR",
+                "0.001"
+            ),
+        ))
+        .unwrap();
+        let report = FeeReport::from_fills(&fills);
+
+        assert_eq!(report.total.fills, 3);
+        assert!((report.total.taker_volume - 2000.0).abs() < 1e-9);
+        assert!((report.total.maker_volume - 1000.0).abs() < 1e-9);
+        // The spot fee and its builder share are paid in PURR, valued at the fill price
+        assert!((report.total.builder_fees - 0.3).abs() < 1e-9);
+        let eth = &report.by_coin["ETH"];
+        assert!((eth.taker_rate().unwrap() - 0.00045).abs() < 1e-12);
+        assert!((eth.maker_rate().unwrap() + 0.00005).abs() < 1e-12);
+        assert_eq!(
+            report.by_day.keys().collect::<Vec<_>>(),
+            ["2023-11-14", "2023-11-16"]
+        );
+
+        let fees: UserFeesResponse = serde_json::from_str(
+            r#"{"activeReferralDiscount":"0","dailyUserVlm":[
+                {"date":"2023-11-14","exchange":"1000000","userAdd":"1000","userCross":"2000000"},
+                {"date":"2023-11-15","exchange":"1000000","userAdd":"5000","userCross":"3000000"}],
+            "feeSchedule":{"add":"0.0001","cross":"0.00035","referralDiscount":"0.04","tiers":{
+                "mm":[{"add":"-0.00001","makerFractionCutoff":"0.005"},{"add":"-0.00002","makerFractionCutoff":"0.015"}],
+                "vip":[{"add":"0.00008","cross":"0.0003","ntlCutoff":"5000000"},{"add":"0.00005","cross":"0.00025","ntlCutoff":"25000000"}]}},
+            "userAddRate":"0.00008","userCrossRate":"0.0003"}"#,
+        )
+        .unwrap();
+        let check = report.check_schedule(&fees);
+        assert_eq!(check.vip_tier, Some(0));
+        assert!((check.maker_share - 0.003).abs() < 1e-12);
+        assert_eq!(check.mm_tier, None);
+        assert!(check.taker_excess.unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{prelude::*, FundingHistoryResponse, InfoClient, Timestamp};
+
+/// Which value of a coin's hourly funding record a `RateCandle` follows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FundingSeries {
+    /// The hourly funding rate
+    #[default]
+    Rate,
+    /// The premium of the perp's impact prices over the oracle price, the basis funding is
+    /// computed from
+    Premium,
+}
+
+/// OHLC of a funding series over one interval.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateCandle {
+    pub coin: String,
+    pub time_open: u64,
+    /// Exclusive, in ms
+    pub time_close: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub mean: f64,
+    /// Funding records in the interval
+    pub samples: usize,
+}
+
+/// Groups funding records into candles of `interval`, aligned to multiples of the interval
+/// since the Unix epoch. Intervals without records produce no candle.
+pub fn funding_candles(
+    history: &[FundingHistoryResponse],
+    series: FundingSeries,
+    interval: Duration,
+) -> Vec<RateCandle> {
+    let interval_ms = (interval.as_millis() as u64).max(1);
+    let mut records: Vec<&FundingHistoryResponse> = history.iter().collect();
+    records.sort_by_key(|record| record.time);
+
+    let mut candles: BTreeMap<(String, u64), RateCandle> = BTreeMap::new();
+    for record in records {
+        let value = match series {
+            FundingSeries::Rate => &record.funding_rate,
+            FundingSeries::Premium => &record.premium,
+        };
+        let Ok(value) = value.parse::<f64>() else {
+            warn!(
+                "Could not parse funding of {} at {}",
+                record.coin, record.time
+            );
+            continue;
+        };
+        let time = record.time.as_millis();
+        let time_open = time - time % interval_ms;
+        candles
+            .entry((record.coin.clone(), time_open))
+            .and_modify(|candle| {
+                candle.high = candle.high.max(value);
+                candle.low = candle.low.min(value);
+                candle.close = value;
+                // Holds the sum until every record is in
+                candle.mean += value;
+                candle.samples += 1;
+            })
+            .or_insert_with(|| RateCandle {
+                coin: record.coin.clone(),
+                time_open,
+                time_close: time_open + interval_ms,
+                open: value,
+                high: value,
+                low: value,
+                close: value,
+                mean: value,
+                samples: 1,
+            });
+    }
+    candles
+        .into_values()
+        .map(|mut candle| {
+            candle.mean /= candle.samples as f64;
+            candle
+        })
+        .collect()
+}
+
+/// Candles of `coin`'s funding rate or premium between `start_time` and `end_time`, built from
+/// its funding history.
+///
+/// `candleSnapshot` only serves trade price candles, with no funding, mark or oracle price
+/// variants, so funding series are built client side. `FundingSeries::Premium` gives the basis
+/// of the perp against the oracle price at each funding.
+pub async fn fetch_funding_candles(
+    info: &InfoClient,
+    coin: &str,
+    series: FundingSeries,
+    interval: Duration,
+    start_time: Timestamp,
+    end_time: Option<Timestamp>,
+) -> Result<Vec<RateCandle>> {
+    let history = info
+        .funding_history_stream(coin.to_string(), start_time, end_time)
+        .collect_all()
+        .await?;
+    Ok(funding_candles(&history, series, interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_funding_candles() {
+        let hour = 3_600_000;
+        let history: Vec<FundingHistoryResponse> = serde_json::from_str(&format!(
+            r#"[
+                {{"coin":"ETH","fundingRate":"0.00002","premium":"0.0003","time":{}}},
+                {{"coin":"ETH","fundingRate":"0.00001","premium":"0.0001","time":{}}},
+                {{"coin":"ETH","fundingRate":"0.00004","premium":"0.0005","time":{}}},
+                {{"coin":"ETH","fundingRate":"-0.00001","premium":"-0.0002","time":{}}}
+            ]"#,
+            hour,
+            0,
+            2 * hour,
+            4 * hour + 5,
+        ))
+        .unwrap();
+
+        let candles = funding_candles(&history, FundingSeries::Rate, Duration::from_secs(4 * 3600));
+        assert_eq!(candles.len(), 2);
+        let first = &candles[0];
+        assert_eq!((first.time_open, first.time_close), (0, 4 * hour));
+        assert_eq!((first.open, first.close), (0.00001, 0.00004));
+        assert_eq!((first.low, first.high), (0.00001, 0.00004));
+        assert!((first.mean - 0.00007 / 3.0).abs() < 1e-12);
+        assert_eq!(first.samples, 3);
+        assert_eq!(candles[1].time_open, 4 * hour);
+
+        let premium = funding_candles(&history, FundingSeries::Premium, Duration::from_secs(3600));
+        assert_eq!(premium.len(), 4);
+        assert_eq!(premium[3].close, -0.0002);
+    }
+}
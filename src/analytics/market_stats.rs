@@ -0,0 +1,297 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    fs::{File, OpenOptions},
+    future::Future,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    pin::pin,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{helpers::now_timestamp_ms, prelude::*, rt, AssetContext, Error, InfoClient, Meta};
+
+/// Open interest, volume and funding of one perp at one time, from `metaAndAssetCtxs`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketSample {
+    pub time: u64,
+    pub coin: String,
+    /// In coins
+    pub open_interest: f64,
+    /// Notional volume of the last 24 hours, in USDC
+    pub day_ntl_vlm: f64,
+    pub premium: f64,
+    pub funding: f64,
+    pub mark_px: f64,
+    pub oracle_px: f64,
+}
+
+impl MarketSample {
+    /// `None` if a value does not parse.
+    pub fn from_context(time: u64, coin: &str, ctx: &AssetContext) -> Option<MarketSample> {
+        Some(MarketSample {
+            time,
+            coin: coin.to_string(),
+            open_interest: ctx.open_interest.parse().ok()?,
+            day_ntl_vlm: ctx.day_ntl_vlm.parse().ok()?,
+            premium: ctx.premium.parse().ok()?,
+            funding: ctx.funding.parse().ok()?,
+            mark_px: ctx.mark_px.parse().ok()?,
+            oracle_px: ctx.oracle_px.parse().ok()?,
+        })
+    }
+
+    /// Open interest in USDC at the mark price.
+    pub fn open_interest_ntl(&self) -> f64 {
+        self.open_interest * self.mark_px
+    }
+}
+
+/// Samples `metaAndAssetCtxs` on a schedule into a time series of each perp's open interest,
+/// volume, premium and funding, which the API only serves as current values.
+///
+/// Samples older than the retention period are dropped. With `open`, samples are also appended
+/// to a JSON lines file, reloaded on the next start, and the file is rewritten without expired
+/// samples once they make up half of it.
+#[derive(Debug)]
+pub struct MarketStatsCollector {
+    retention: Duration,
+    coins: Option<HashSet<String>>,
+    samples: BTreeMap<String, VecDeque<MarketSample>>,
+    path: Option<PathBuf>,
+    file: Option<BufWriter<File>>,
+    /// Expired samples still in the file
+    expired_lines: usize,
+}
+
+impl MarketStatsCollector {
+    /// Keeps samples of every perp in memory for `retention`.
+    pub fn new(retention: Duration) -> MarketStatsCollector {
+        MarketStatsCollector {
+            retention,
+            coins: None,
+            samples: BTreeMap::new(),
+            path: None,
+            file: None,
+            expired_lines: 0,
+        }
+    }
+
+    /// Keeps samples for `retention` in the file at `path`, loading those it already holds.
+    pub fn open(path: impl AsRef<Path>, retention: Duration) -> Result<MarketStatsCollector> {
+        let path = path.as_ref().to_path_buf();
+        let mut collector = MarketStatsCollector::new(retention);
+        if path.exists() {
+            let file = File::open(&path).map_err(|e| Error::Io(e.to_string()))?;
+            for line in BufReader::new(file).lines() {
+                let line = line.map_err(|e| Error::Io(e.to_string()))?;
+                if line.is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<MarketSample>(&line) {
+                    Ok(sample) => collector.insert(sample),
+                    Err(err) => warn!("Skipping unreadable market sample: {err}"),
+                }
+            }
+        }
+        collector.path = Some(path);
+        collector.prune(now_timestamp_ms());
+        collector.compact()?;
+        Ok(collector)
+    }
+
+    /// Samples only `coins`, rather than every perp.
+    pub fn with_coins(mut self, coins: impl IntoIterator<Item = String>) -> Self {
+        self.coins = Some(coins.into_iter().collect());
+        self
+    }
+
+    /// Samples of `coin`, oldest first.
+    pub fn series(&self, coin: &str) -> impl Iterator<Item = &MarketSample> {
+        self.samples.get(coin).into_iter().flatten()
+    }
+
+    /// Samples of `coin` taken between `start` and `end`, exclusive.
+    pub fn range(&self, coin: &str, start: u64, end: u64) -> Vec<&MarketSample> {
+        self.series(coin)
+            .filter(|sample| start <= sample.time && sample.time < end)
+            .collect()
+    }
+
+    pub fn latest(&self, coin: &str) -> Option<&MarketSample> {
+        self.samples.get(coin).and_then(|samples| samples.back())
+    }
+
+    pub fn coins(&self) -> impl Iterator<Item = &str> {
+        self.samples.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.values().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.values().all(VecDeque::is_empty)
+    }
+
+    /// Fetches and records the current asset contexts, returning the number of samples added.
+    pub async fn sample(&mut self, info: &InfoClient) -> Result<usize> {
+        let (meta, ctxs) = info.meta_and_asset_contexts().await?;
+        self.record(now_timestamp_ms(), &meta, &ctxs)
+    }
+
+    /// Records asset contexts taken at `time`, then drops samples past the retention period.
+    pub fn record(&mut self, time: u64, meta: &Meta, ctxs: &[AssetContext]) -> Result<usize> {
+        let mut added = Vec::new();
+        for (asset, ctx) in meta.universe.iter().zip(ctxs) {
+            if self
+                .coins
+                .as_ref()
+                .is_some_and(|coins| !coins.contains(&asset.name))
+            {
+                continue;
+            }
+            match MarketSample::from_context(time, &asset.name, ctx) {
+                Some(sample) => added.push(sample),
+                None => warn!("Could not parse asset context of {}", asset.name),
+            }
+        }
+        if let Some(path) = &self.path {
+            if self.file.is_none() {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| Error::Io(e.to_string()))?;
+                self.file = Some(BufWriter::new(file));
+            }
+            if let Some(file) = &mut self.file {
+                for sample in &added {
+                    let line = serde_json::to_string(sample)
+                        .map_err(|e| Error::JsonParse(e.to_string()))?;
+                    writeln!(file, "{line}").map_err(|e| Error::Io(e.to_string()))?;
+                }
+                file.flush().map_err(|e| Error::Io(e.to_string()))?;
+            }
+        }
+        let count = added.len();
+        for sample in added {
+            self.insert(sample);
+        }
+        self.prune(time);
+        if self.expired_lines > self.len() {
+            self.compact()?;
+        }
+        Ok(count)
+    }
+
+    /// Samples every `interval` until `shutdown` resolves, logging failures.
+    pub async fn run(
+        &mut self,
+        info: &InfoClient,
+        interval: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            if let Err(err) = self.sample(info).await {
+                warn!("Could not sample asset contexts: {err}");
+            }
+            tokio::select! {
+                _ = &mut shutdown => return,
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+
+    fn insert(&mut self, sample: MarketSample) {
+        self.samples
+            .entry(sample.coin.clone())
+            .or_default()
+            .push_back(sample);
+    }
+
+    fn prune(&mut self, now: u64) {
+        let cutoff = now.saturating_sub(self.retention.as_millis() as u64);
+        for samples in self.samples.values_mut() {
+            while samples.front().is_some_and(|sample| sample.time < cutoff) {
+                samples.pop_front();
+                self.expired_lines += 1;
+            }
+        }
+        self.samples.retain(|_, samples| !samples.is_empty());
+    }
+
+    /// Rewrites the file with the retained samples only.
+    fn compact(&mut self) -> Result<()> {
+        let Some(path) = &self.path else {
+            self.expired_lines = 0;
+            return Ok(());
+        };
+        self.file = None;
+        let tmp = path.with_extension("tmp");
+        let mut writer = BufWriter::new(File::create(&tmp).map_err(|e| Error::Io(e.to_string()))?);
+        for sample in self.samples.values().flatten() {
+            let line =
+                serde_json::to_string(sample).map_err(|e| Error::JsonParse(e.to_string()))?;
+            writeln!(writer, "{line}").map_err(|e| Error::Io(e.to_string()))?;
+        }
+        writer.flush().map_err(|e| Error::Io(e.to_string()))?;
+        std::fs::rename(&tmp, path).map_err(|e| Error::Io(e.to_string()))?;
+        self.expired_lines = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(open_interest: &str) -> AssetContext {
+        serde_json::from_str(&format!(
+            r#"{{"dayNtlVlm":"1000000","funding":"0.0000125","impactPxs":["1999","2001"],"markPx":"2000","midPx":"2000","openInterest":"{open_interest}","oraclePx":"1999.5","premium":"0.0002","prevDayPx":"1950"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collects_with_retention() {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"ETH","szDecimals":4,"maxLeverage":50},{"name":"BTC","szDecimals":5,"maxLeverage":50}]}"#,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("market_stats_{}.jsonl", std::process::id()));
+        let hour = 3_600_000;
+        let now = now_timestamp_ms();
+
+        let mut collector = MarketStatsCollector::open(&path, Duration::from_secs(90 * 60))
+            .unwrap()
+            .with_coins(["ETH".to_string()]);
+        for (i, oi) in ["100", "110", "120"].into_iter().enumerate() {
+            let time = now - (3 - i as u64) * hour;
+            collector
+                .record(time, &meta, &[context(oi), context("5")])
+                .unwrap();
+        }
+        // The first sample is over 90 minutes older than the last
+        assert_eq!(collector.len(), 2);
+        assert_eq!(collector.coins().collect::<Vec<_>>(), vec!["ETH"]);
+        let latest = collector.latest("ETH").unwrap();
+        assert_eq!(latest.open_interest, 120.0);
+        assert_eq!(latest.open_interest_ntl(), 240_000.0);
+        assert_eq!(collector.range("ETH", 0, now - hour).len(), 1);
+        drop(collector);
+
+        // Samples that expired by now are not reloaded
+        let collector = MarketStatsCollector::open(&path, Duration::from_secs(90 * 60)).unwrap();
+        let reloaded: Vec<f64> = collector
+            .series("ETH")
+            .map(|sample| sample.open_interest)
+            .collect();
+        assert_eq!(reloaded, vec![120.0]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
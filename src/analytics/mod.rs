@@ -0,0 +1,41 @@
+#[cfg(feature = "arrow")]
+mod arrow;
+mod basis;
+mod book;
+mod book_diff;
+mod book_replay;
+mod break_even;
+mod builder;
+mod candles;
+mod csv;
+mod export;
+mod fees;
+mod funding_candles;
+mod market_stats;
+mod pnl;
+mod session;
+mod toxicity;
+
+#[cfg(feature = "arrow")]
+pub use arrow::{
+    candles_record_batch, fills_record_batch, funding_history_record_batch, l2_books_record_batch,
+};
+pub use basis::{BasisAlert, BasisCrossing, BasisMonitor, BasisSummary};
+pub use book::{ImpactSizing, OrderBook, SpreadStats, SpreadSummary};
+pub use book_diff::{BookDiffDecoder, BookDiffEncoder, BookUpdate, L2BookDiff, SideDiff};
+pub use book_replay::BookReconstructor;
+pub use break_even::{BreakEven, BreakEvenCalculator, BreakEvenCapture, FeeRates};
+pub use builder::{write_builder_revenue_csv, BuilderRevenue, BuilderRevenueReport};
+pub use candles::CandleAggregator;
+pub use export::{
+    fetch_ledger_updates, fetch_user_fills, fetch_user_funding, write_fills_csv, write_funding_csv,
+    write_ledger_csv, LedgerRow,
+};
+pub use fees::{FeeBucket, FeeReport, FeeTierCheck};
+pub use funding_candles::{fetch_funding_candles, funding_candles, FundingSeries, RateCandle};
+pub use market_stats::{MarketSample, MarketStatsCollector};
+pub use pnl::{CoinPnl, LotMethod, OpenLot, PnlEngine, RealizedLot};
+pub use session::{write_sessions_csv, SessionReport};
+pub use toxicity::{
+    CoinToxicity, FillMarkout, Liquidity, MarkoutAnalyzer, MarkoutStats, ToxicityReport,
+};
@@ -0,0 +1,380 @@
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    io::Write,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{csv::write_row, fees::usdc_fee};
+use crate::{prelude::*, UserFillsResponse, UserFundingResponse, EPSILON};
+
+/// Which open lots a closing fill is matched against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LotMethod {
+    /// Oldest lot first
+    #[default]
+    Fifo,
+    /// Newest lot first
+    Lifo,
+    /// A single lot per coin at the average entry price
+    Average,
+}
+
+/// Size still open from one or more opening fills.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenLot {
+    pub coin: String,
+    pub is_long: bool,
+    /// Time of the opening fill, the first one for an average lot
+    pub open_time: u64,
+    pub sz: f64,
+    pub px: f64,
+    /// Opening fees not yet attributed to a closed lot
+    pub fees: f64,
+}
+
+/// Size closed out of one lot by one fill.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RealizedLot {
+    pub coin: String,
+    pub is_long: bool,
+    pub open_time: u64,
+    pub close_time: u64,
+    pub sz: f64,
+    pub open_px: f64,
+    pub close_px: f64,
+    /// PnL before fees
+    pub pnl: f64,
+    /// Opening and closing fees attributed to the closed size
+    pub fees: f64,
+}
+
+impl RealizedLot {
+    pub fn net_pnl(&self) -> f64 {
+        self.pnl - self.fees
+    }
+}
+
+/// Realized PnL, fees and funding of one coin.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinPnl {
+    pub coin: String,
+    pub realized_pnl: f64,
+    /// Fees of closed size, fees of open size are carried by the open lots
+    pub fees: f64,
+    /// Funding received, negative when paid
+    pub funding: f64,
+    /// Signed size left open
+    pub open_szi: f64,
+}
+
+impl CoinPnl {
+    pub fn net_pnl(&self) -> f64 {
+        self.realized_pnl - self.fees + self.funding
+    }
+}
+
+/// Recomputes realized PnL from raw fills and funding payments, matching closing fills to
+/// open lots by `LotMethod`.
+///
+/// Fills are applied in the order given and are deduplicated by trade id, so overlapping
+/// pages of history can be passed as they are; `from_history` sorts by time first.
+#[derive(Clone, Debug)]
+pub struct PnlEngine {
+    method: LotMethod,
+    lots: BTreeMap<String, VecDeque<OpenLot>>,
+    realized: Vec<RealizedLot>,
+    funding: BTreeMap<String, f64>,
+    seen_fills: HashSet<u64>,
+}
+
+impl PnlEngine {
+    pub fn new(method: LotMethod) -> PnlEngine {
+        PnlEngine {
+            method,
+            lots: BTreeMap::new(),
+            realized: Vec::new(),
+            funding: BTreeMap::new(),
+            seen_fills: HashSet::new(),
+        }
+    }
+
+    /// Applies fills oldest first, then funding payments.
+    pub fn from_history(
+        method: LotMethod,
+        fills: &[UserFillsResponse],
+        funding: &[UserFundingResponse],
+    ) -> PnlEngine {
+        let mut engine = PnlEngine::new(method);
+        let mut fills: Vec<&UserFillsResponse> = fills.iter().collect();
+        fills.sort_by_key(|fill| (fill.time, fill.tid));
+        fills.into_iter().for_each(|fill| engine.add_fill(fill));
+        funding
+            .iter()
+            .for_each(|funding| engine.add_funding(funding));
+        engine
+    }
+
+    pub fn method(&self) -> LotMethod {
+        self.method
+    }
+
+    /// Closed lots in the order they were closed.
+    pub fn realized(&self) -> &[RealizedLot] {
+        &self.realized
+    }
+
+    pub fn open_lots(&self, coin: &str) -> impl Iterator<Item = &OpenLot> {
+        self.lots.get(coin).into_iter().flatten()
+    }
+
+    pub fn add_fill(&mut self, fill: &UserFillsResponse) {
+        if !self.seen_fills.insert(fill.tid) {
+            return;
+        }
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            warn!("Could not parse fill {}", fill.tid);
+            return;
+        };
+        if sz < EPSILON {
+            return;
+        }
+        let fee = usdc_fee(fill, px, fill.fee.parse().unwrap_or_default());
+        let fee_per_sz = fee / sz;
+        let is_buy = fill.side.is_buy();
+        let method = self.method;
+        let lots = self.lots.entry(fill.coin.clone()).or_default();
+
+        let mut remaining = sz;
+        while remaining > EPSILON {
+            let closing = lots.front().is_some_and(|lot| lot.is_long != is_buy);
+            if !closing {
+                break;
+            }
+            let lot = match method {
+                LotMethod::Lifo => lots.back_mut(),
+                LotMethod::Fifo | LotMethod::Average => lots.front_mut(),
+            }
+            .expect("lots is not empty");
+            let closed = remaining.min(lot.sz);
+            let open_fees = lot.fees * closed / lot.sz;
+            let sign = if lot.is_long { 1.0 } else { -1.0 };
+            self.realized.push(RealizedLot {
+                coin: fill.coin.clone(),
+                is_long: lot.is_long,
+                open_time: lot.open_time,
+                close_time: fill.time.as_millis(),
+                sz: closed,
+                open_px: lot.px,
+                close_px: px,
+                pnl: closed * (px - lot.px) * sign,
+                fees: open_fees + fee_per_sz * closed,
+            });
+            lot.sz -= closed;
+            lot.fees -= open_fees;
+            remaining -= closed;
+            if lot.sz < EPSILON {
+                match method {
+                    LotMethod::Lifo => lots.pop_back(),
+                    LotMethod::Fifo | LotMethod::Average => lots.pop_front(),
+                };
+            }
+        }
+        if remaining < EPSILON {
+            return;
+        }
+
+        // Opening, or the remainder of a fill that flipped the position
+        let fees = fee_per_sz * remaining;
+        match (method, lots.front_mut()) {
+            (LotMethod::Average, Some(lot)) => {
+                lot.px = (lot.px * lot.sz + px * remaining) / (lot.sz + remaining);
+                lot.sz += remaining;
+                lot.fees += fees;
+            }
+            _ => lots.push_back(OpenLot {
+                coin: fill.coin.clone(),
+                is_long: is_buy,
+                open_time: fill.time.as_millis(),
+                sz: remaining,
+                px,
+                fees,
+            }),
+        }
+    }
+
+    pub fn add_funding(&mut self, funding: &UserFundingResponse) {
+        match funding.delta.usdc.parse::<f64>() {
+            Ok(usdc) => *self.funding.entry(funding.delta.coin.clone()).or_default() += usdc,
+            Err(_) => warn!("Could not parse funding for {}", funding.delta.coin),
+        }
+    }
+
+    /// Realized PnL, fees and funding by coin.
+    pub fn summary(&self) -> Vec<CoinPnl> {
+        let mut coins: BTreeMap<&str, CoinPnl> = BTreeMap::new();
+        fn entry<'a, 'b>(
+            coins: &'b mut BTreeMap<&'a str, CoinPnl>,
+            coin: &'a str,
+        ) -> &'b mut CoinPnl {
+            coins.entry(coin).or_insert_with(|| CoinPnl {
+                coin: coin.to_string(),
+                ..CoinPnl::default()
+            })
+        }
+        for lot in &self.realized {
+            let pnl = entry(&mut coins, &lot.coin);
+            pnl.realized_pnl += lot.pnl;
+            pnl.fees += lot.fees;
+        }
+        for (coin, funding) in &self.funding {
+            entry(&mut coins, coin).funding += funding;
+        }
+        for (coin, lots) in &self.lots {
+            entry(&mut coins, coin).open_szi = lots
+                .iter()
+                .map(|lot| if lot.is_long { lot.sz } else { -lot.sz })
+                .sum();
+        }
+        coins.into_values().collect()
+    }
+
+    /// Writes one row per closed lot.
+    pub fn write_realized_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_row(
+            &mut writer,
+            &[
+                "coin",
+                "side",
+                "open_time",
+                "close_time",
+                "sz",
+                "open_px",
+                "close_px",
+                "pnl",
+                "fees",
+                "net_pnl",
+            ]
+            .map(String::from),
+        )?;
+        for lot in &self.realized {
+            write_row(
+                &mut writer,
+                &[
+                    lot.coin.clone(),
+                    if lot.is_long { "long" } else { "short" }.to_string(),
+                    lot.open_time.to_string(),
+                    lot.close_time.to_string(),
+                    lot.sz.to_string(),
+                    lot.open_px.to_string(),
+                    lot.close_px.to_string(),
+                    lot.pnl.to_string(),
+                    lot.fees.to_string(),
+                    lot.net_pnl().to_string(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes one row per coin from `summary`.
+    pub fn write_summary_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        write_row(
+            &mut writer,
+            &[
+                "coin",
+                "realized_pnl",
+                "fees",
+                "funding",
+                "net_pnl",
+                "open_szi",
+            ]
+            .map(String::from),
+        )?;
+        for pnl in self.summary() {
+            write_row(
+                &mut writer,
+                &[
+                    pnl.coin.clone(),
+                    pnl.realized_pnl.to_string(),
+                    pnl.fees.to_string(),
+                    pnl.funding.to_string(),
+                    pnl.net_pnl().to_string(),
+                    pnl.open_szi.to_string(),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fills() -> Vec<UserFillsResponse> {
+        let fill = |tid: u64, side: &str, px: &str, sz: &str| {
+            format!(
+                r#"{{"closedPnl":"0","coin":"ETH","crossed":true,"dir":"","hash":"0x0","oid":1,"px":"{px}","side":"{side}","startPosition":"0","sz":"{sz}","time":{tid},"fee":"{sz}","tid":{tid},"feeToken":"USDC","twapId":null}}"#
+            )
+        };
+        serde_json::from_str(&format!(
+            "[{},{},{},{}]",
+            fill(2, "B", "110", "1"),
+            fill(1, "B", "100", "1"),
+            fill(3, "A", "120", "3"),
+            fill(3, "A", "120", "3"),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lot_methods() {
+        let funding: Vec<UserFundingResponse> = serde_json::from_str(
+            r#"[{"time":4,"hash":"0x0","delta":{"type":"funding","coin":"ETH","usdc":"-0.5","szi":"-1","fundingRate":"0.0001"}}]"#,
+        )
+        .unwrap();
+        let fills = fills();
+        for (method, first_pnl) in [
+            (LotMethod::Fifo, 20.0),
+            (LotMethod::Lifo, 10.0),
+            (LotMethod::Average, 30.0),
+        ] {
+            let engine = PnlEngine::from_history(method, &fills, &funding);
+            let realized = engine.realized();
+            assert_eq!(
+                realized.len(),
+                if method == LotMethod::Average { 1 } else { 2 }
+            );
+            assert!((realized[0].pnl - first_pnl).abs() < EPSILON);
+
+            let summary = engine.summary();
+            assert_eq!(summary.len(), 1);
+            let eth = &summary[0];
+            assert!((eth.realized_pnl - 30.0).abs() < EPSILON);
+            // Both opening fees and two thirds of the closing fee
+            assert!((eth.fees - 4.0).abs() < EPSILON);
+            assert!((eth.open_szi + 1.0).abs() < EPSILON);
+            assert!((eth.net_pnl() - 25.5).abs() < EPSILON);
+            let short = engine.open_lots("ETH").next().unwrap();
+            assert!(!short.is_long && (short.px - 120.0).abs() < EPSILON);
+        }
+
+        let mut csv = Vec::new();
+        PnlEngine::from_history(LotMethod::Fifo, &fills, &[])
+            .write_realized_csv(&mut csv)
+            .unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "coin,side,open_time,close_time,sz,open_px,close_px,pnl,fees,net_pnl"
+        );
+        assert_eq!(lines[1], "ETH,long,1,3,1,100,120,20,2,18");
+    }
+}
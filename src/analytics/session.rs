@@ -0,0 +1,254 @@
+use std::io::Write;
+
+use alloy::primitives::Address;
+use serde::Serialize;
+use tracing::warn;
+
+use super::{
+    csv::write_row,
+    export::{fetch_user_fills, fetch_user_funding},
+    fees::usdc_fee,
+};
+use crate::{prelude::*, InfoClient, OrderInfo, Timestamp, UserFillsResponse, UserFundingResponse};
+
+/// What an account did over a trading session, such as a day, in USDC. Serializes to JSON,
+/// and `write_sessions_csv` writes reports as CSV.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionReport {
+    pub start: Timestamp,
+    /// Exclusive
+    pub end: Timestamp,
+    pub fills: usize,
+    pub maker_volume: f64,
+    pub taker_volume: f64,
+    /// Exchange and builder fees, negative when rebates exceed them
+    pub fees: f64,
+    /// PnL closed by the session's fills, before fees
+    pub realized_pnl: f64,
+    /// Change in the unrealized PnL of open positions, when known at both ends
+    pub unrealized_pnl_change: Option<f64>,
+    /// Funding received, negative when paid
+    pub funding: f64,
+    /// Orders placed during the session
+    pub orders: usize,
+    /// Orders cancelled during the session, by the account or the exchange
+    pub cancels: usize,
+    /// Largest fall of realized PnL net of fees and funding from its running peak
+    pub max_drawdown: f64,
+}
+
+impl SessionReport {
+    /// Report over `start..end` of the given history, which may extend past the session.
+    pub fn from_history(
+        start: Timestamp,
+        end: Timestamp,
+        fills: &[UserFillsResponse],
+        fundings: &[UserFundingResponse],
+        orders: &[OrderInfo],
+    ) -> SessionReport {
+        let within = |time: Timestamp| time >= start && time < end;
+        let mut report = SessionReport {
+            start,
+            end,
+            ..SessionReport::default()
+        };
+        // Changes to realized PnL net of fees and funding, for the drawdown
+        let mut changes: Vec<(u64, f64)> = Vec::new();
+
+        for fill in fills.iter().filter(|fill| within(fill.time)) {
+            let (Ok(px), Ok(sz), Ok(fee)) = (
+                fill.px.parse::<f64>(),
+                fill.sz.parse::<f64>(),
+                fill.fee.parse::<f64>(),
+            ) else {
+                warn!("Could not parse fill {}", fill.tid);
+                continue;
+            };
+            let fee = usdc_fee(fill, px, fee);
+            let closed_pnl = fill.closed_pnl.parse::<f64>().unwrap_or_default();
+            report.fills += 1;
+            if fill.crossed {
+                report.taker_volume += px * sz;
+            } else {
+                report.maker_volume += px * sz;
+            }
+            report.fees += fee;
+            report.realized_pnl += closed_pnl;
+            changes.push((fill.time.as_millis(), closed_pnl - fee));
+        }
+        for funding in fundings.iter().filter(|funding| within(funding.time)) {
+            let usdc = funding.delta.usdc.parse::<f64>().unwrap_or_default();
+            report.funding += usdc;
+            changes.push((funding.time.as_millis(), usdc));
+        }
+        report.orders = orders
+            .iter()
+            .filter(|order| within(order.order.timestamp))
+            .count();
+        report.cancels = orders
+            .iter()
+            .filter(|order| order.status.ends_with("anceled") && within(order.status_timestamp))
+            .count();
+
+        changes.sort_by_key(|(time, _)| *time);
+        let (mut cumulative, mut peak) = (0.0_f64, 0.0_f64);
+        for (_, change) in changes {
+            cumulative += change;
+            peak = peak.max(cumulative);
+            report.max_drawdown = report.max_drawdown.max(peak - cumulative);
+        }
+        report
+    }
+
+    /// Fetches the fills, funding payments and orders of `user` over `start..end` and reports
+    /// on them. Pass the unrealized PnL recorded with `unrealized_pnl` when the session opened
+    /// to also report its change, against the current one.
+    ///
+    /// Orders come from `historicalOrders`, which only keeps the account's latest 2000.
+    pub async fn fetch(
+        info: &InfoClient,
+        user: Address,
+        start: Timestamp,
+        end: Timestamp,
+        start_unrealized_pnl: Option<f64>,
+    ) -> Result<SessionReport> {
+        let fills = fetch_user_fills(info, user, start, Some(end)).await?;
+        let fundings = fetch_user_funding(info, user, start, Some(end)).await?;
+        let orders = info.historical_orders(user).await?;
+        let mut report = SessionReport::from_history(start, end, &fills, &fundings, &orders);
+        if let Some(start_unrealized_pnl) = start_unrealized_pnl {
+            let end_unrealized_pnl = SessionReport::unrealized_pnl(info, user).await?;
+            report.unrealized_pnl_change = Some(end_unrealized_pnl - start_unrealized_pnl);
+        }
+        Ok(report)
+    }
+
+    /// Current unrealized PnL of the account's perp positions.
+    pub async fn unrealized_pnl(info: &InfoClient, user: Address) -> Result<f64> {
+        let state = info.user_state(user).await?;
+        Ok(state
+            .asset_positions
+            .iter()
+            .filter_map(|position| position.position.unrealized_pnl.parse::<f64>().ok())
+            .sum())
+    }
+
+    pub fn volume(&self) -> f64 {
+        self.maker_volume + self.taker_volume
+    }
+
+    /// Realized PnL net of fees and funding, plus the unrealized PnL change when known.
+    pub fn net_pnl(&self) -> f64 {
+        self.realized_pnl - self.fees + self.funding + self.unrealized_pnl_change.unwrap_or(0.0)
+    }
+}
+
+/// Writes one row per session with columns `start`, `end`, `fills`, `maker_volume`,
+/// `taker_volume`, `fees`, `realized_pnl`, `unrealized_pnl_change`, `funding`, `net_pnl`,
+/// `orders`, `cancels` and `max_drawdown`.
+pub fn write_sessions_csv<W: Write>(mut writer: W, reports: &[SessionReport]) -> Result<()> {
+    let columns = [
+        "start",
+        "end",
+        "fills",
+        "maker_volume",
+        "taker_volume",
+        "fees",
+        "realized_pnl",
+        "unrealized_pnl_change",
+        "funding",
+        "net_pnl",
+        "orders",
+        "cancels",
+        "max_drawdown",
+    ];
+    write_row(&mut writer, &columns.map(String::from))?;
+    for report in reports {
+        write_row(
+            &mut writer,
+            &[
+                report.start.as_millis().to_string(),
+                report.end.as_millis().to_string(),
+                report.fills.to_string(),
+                report.maker_volume.to_string(),
+                report.taker_volume.to_string(),
+                report.fees.to_string(),
+                report.realized_pnl.to_string(),
+                report
+                    .unrealized_pnl_change
+                    .map(|change| change.to_string())
+                    .unwrap_or_default(),
+                report.funding.to_string(),
+                report.net_pnl().to_string(),
+                report.orders.to_string(),
+                report.cancels.to_string(),
+                report.max_drawdown.to_string(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(time: u64, crossed: bool, closed_pnl: &str, fee: &str) -> String {
+        format!(
+            r#"{{"closedPnl":"{closed_pnl}","coin":"ETH","crossed":{crossed},"dir":"Close Long","hash":"0x0","oid":1,"px":"100","side":"A","startPosition":"1","sz":"1","time":{time},"fee":"{fee}","tid":{time},"feeToken":"USDC","twapId":null}}"#
+        )
+    }
+
+    fn order(timestamp: u64, status: &str, status_timestamp: u64) -> String {
+        format!(
+            r#"{{"order":{{"coin":"ETH","side":"B","limitPx":"100","sz":"0","oid":{timestamp},"timestamp":{timestamp},"triggerCondition":"N/A","isTrigger":false,"triggerPx":"0","isPositionTpsl":false,"reduceOnly":false,"orderType":"Limit","origSz":"1","tif":"Gtc","cloid":null}},"status":"{status}","statusTimestamp":{status_timestamp}}}"#
+        )
+    }
+
+    #[test]
+    fn test_session_report() {
+        let fills: Vec<UserFillsResponse> = serde_json::from_str(&format!(
+            "[{},{},{},{}]",
+            fill(5, true, "0", "0.1"),
+            fill(1000, false, "10", "0"),
+            fill(2000, true, "-15", "0.1"),
+            fill(3000, false, "8", "-0.01"),
+        ))
+        .unwrap();
+        let fundings: Vec<UserFundingResponse> = serde_json::from_str(
+            r#"[{"time":2500,"hash":"0x0","delta":{"type":"funding","coin":"ETH","usdc":"-1","szi":"1","fundingRate":"0.0001"}}]"#,
+        )
+        .unwrap();
+        let orders: Vec<OrderInfo> = serde_json::from_str(&format!(
+            "[{},{},{}]",
+            order(900, "filled", 1000),
+            order(1500, "canceled", 1600),
+            order(10, "marginCanceled", 20),
+        ))
+        .unwrap();
+
+        let report =
+            SessionReport::from_history(100.into(), 4000.into(), &fills, &fundings, &orders);
+        assert_eq!(report.fills, 3);
+        assert!((report.maker_volume - 200.0).abs() < 1e-9);
+        assert!((report.taker_volume - 100.0).abs() < 1e-9);
+        assert!((report.realized_pnl - 3.0).abs() < 1e-9);
+        assert!((report.fees - 0.09).abs() < 1e-9);
+        assert!((report.funding + 1.0).abs() < 1e-9);
+        assert_eq!((report.orders, report.cancels), (2, 1));
+        // Peak of 10 after the first fill, trough of -6.1 after funding
+        assert!((report.max_drawdown - 16.1).abs() < 1e-9);
+        assert!((report.net_pnl() - 1.91).abs() < 1e-9);
+
+        let mut csv = Vec::new();
+        write_sessions_csv(&mut csv, &[report]).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("100,4000,3,200,100,"));
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{Message, Side, Timestamp, Trade, UserFillsResponse};
+
+/// Whether a fill added liquidity or took it, from its `crossed` flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+impl Liquidity {
+    pub fn of(fill: &UserFillsResponse) -> Liquidity {
+        if fill.crossed {
+            Liquidity::Taker
+        } else {
+            Liquidity::Maker
+        }
+    }
+}
+
+/// PnL of one fill marked to the trade price at each horizon after it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FillMarkout {
+    pub coin: String,
+    pub tid: u64,
+    pub time: Timestamp,
+    pub side: Side,
+    pub liquidity: Liquidity,
+    pub px: f64,
+    pub sz: f64,
+    /// In USD, one per horizon, negative when the price moved against the fill. `None` where
+    /// the tape does not reach the horizon yet.
+    pub markouts: Vec<Option<f64>>,
+}
+
+/// Markouts of a set of fills at one horizon.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkoutStats {
+    pub fills: usize,
+    pub volume: f64,
+    pub pnl: f64,
+    /// Fills the price moved against
+    pub adverse: usize,
+}
+
+impl MarkoutStats {
+    /// Markout PnL per unit of volume in basis points, `None` without volume.
+    pub fn bps(&self) -> Option<f64> {
+        (self.volume > 0.0).then(|| self.pnl / self.volume * 10_000.0)
+    }
+
+    /// Share of fills the price moved against, `None` without fills.
+    pub fn adverse_fraction(&self) -> Option<f64> {
+        (self.fills > 0).then(|| self.adverse as f64 / self.fills as f64)
+    }
+
+    fn add(&mut self, notional: f64, pnl: f64) {
+        self.fills += 1;
+        self.volume += notional;
+        self.pnl += pnl;
+        if pnl < 0.0 {
+            self.adverse += 1;
+        }
+    }
+}
+
+/// Markout statistics of one coin's maker and taker fills, one per horizon.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoinToxicity {
+    pub maker: Vec<MarkoutStats>,
+    pub taker: Vec<MarkoutStats>,
+}
+
+/// Adverse selection of an account's fills per coin, from `MarkoutAnalyzer::report`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToxicityReport {
+    pub horizons: Vec<Duration>,
+    pub by_coin: BTreeMap<String, CoinToxicity>,
+    /// Fills that could not be parsed or marked at any horizon
+    pub skipped: usize,
+}
+
+/// Marks own fills to the trade tape at fixed horizons after each, 1s, 10s and 60s unless set
+/// with `with_horizons`, to measure how much quotes are picked off by informed flow.
+///
+/// The price at a horizon is that of the last trade at or before it. A markout is only taken
+/// once the tape has a trade past the horizon, so fills at the end of the tape are left out of
+/// the longer horizons rather than marked to a stale price.
+#[derive(Clone, Debug)]
+pub struct MarkoutAnalyzer {
+    horizons: Vec<Duration>,
+    /// Trade times and prices by coin, sorted by time
+    tape: HashMap<String, Vec<(u64, f64)>>,
+}
+
+impl Default for MarkoutAnalyzer {
+    fn default() -> Self {
+        MarkoutAnalyzer::new()
+    }
+}
+
+impl MarkoutAnalyzer {
+    pub fn new() -> MarkoutAnalyzer {
+        MarkoutAnalyzer {
+            horizons: [1, 10, 60].map(Duration::from_secs).into(),
+            tape: HashMap::new(),
+        }
+    }
+
+    pub fn with_horizons(mut self, horizons: impl IntoIterator<Item = Duration>) -> Self {
+        self.horizons = horizons.into_iter().collect();
+        self
+    }
+
+    pub fn horizons(&self) -> &[Duration] {
+        &self.horizons
+    }
+
+    pub fn add_trades(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            let Ok(px) = trade.px.parse::<f64>() else {
+                warn!("Could not parse trade {}", trade.tid);
+                continue;
+            };
+            let prices = self.tape.entry(trade.coin.clone()).or_default();
+            let time = trade.time.as_millis();
+            let index = prices.partition_point(|&(t, _)| t <= time);
+            prices.insert(index, (time, px));
+        }
+    }
+
+    /// Adds the trades of recorded messages, as from `read_tape`.
+    pub fn add_messages(&mut self, messages: &[Message]) {
+        for message in messages {
+            if let Message::Trades(trades) = message {
+                self.add_trades(&trades.data);
+            }
+        }
+    }
+
+    /// Price of the last trade of `coin` at or before `time`, `None` unless the tape has a
+    /// trade after it.
+    pub fn price_at(&self, coin: &str, time: Timestamp) -> Option<f64> {
+        let prices = self.tape.get(coin)?;
+        let time = time.as_millis();
+        let index = prices.partition_point(|&(t, _)| t <= time);
+        if index == 0 || index == prices.len() {
+            return None;
+        }
+        Some(prices[index - 1].1)
+    }
+
+    /// Markouts of one fill, `None` if it cannot be parsed.
+    pub fn markout(&self, fill: &UserFillsResponse) -> Option<FillMarkout> {
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            warn!("Could not parse fill {}", fill.tid);
+            return None;
+        };
+        let signed_sz = if fill.side.is_buy() { sz } else { -sz };
+        let markouts = self
+            .horizons
+            .iter()
+            .map(|&horizon| {
+                let mark = self.price_at(&fill.coin, fill.time + horizon)?;
+                Some(signed_sz * (mark - px))
+            })
+            .collect();
+        Some(FillMarkout {
+            coin: fill.coin.clone(),
+            tid: fill.tid,
+            time: fill.time,
+            side: fill.side,
+            liquidity: Liquidity::of(fill),
+            px,
+            sz,
+            markouts,
+        })
+    }
+
+    pub fn report<'a>(
+        &self,
+        fills: impl IntoIterator<Item = &'a UserFillsResponse>,
+    ) -> ToxicityReport {
+        let mut report = ToxicityReport {
+            horizons: self.horizons.clone(),
+            ..ToxicityReport::default()
+        };
+        for fill in fills {
+            let Some(markout) = self.markout(fill) else {
+                report.skipped += 1;
+                continue;
+            };
+            if markout.markouts.iter().all(Option::is_none) {
+                report.skipped += 1;
+                continue;
+            }
+            let coin = report.by_coin.entry(markout.coin).or_insert_with(|| {
+                let stats = vec![MarkoutStats::default(); self.horizons.len()];
+                CoinToxicity {
+                    maker: stats.clone(),
+                    taker: stats,
+                }
+            });
+            let stats = match markout.liquidity {
+                Liquidity::Maker => &mut coin.maker,
+                Liquidity::Taker => &mut coin.taker,
+            };
+            for (stats, pnl) in stats.iter_mut().zip(&markout.markouts) {
+                if let Some(pnl) = pnl {
+                    stats.add(markout.px * markout.sz, *pnl);
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markouts_by_liquidity_and_horizon() {
+        let trades: Vec<Trade> = serde_json::from_value(serde_json::json!([
+            {"coin": "ETH", "side": "B", "px": "100", "sz": "1", "time": 0, "hash": "0x", "tid": 1, "users": ["0x1", "0x2"]},
+            {"coin": "ETH", "side": "A", "px": "99", "sz": "1", "time": 1_500, "hash": "0x", "tid": 2, "users": ["0x1", "0x2"]},
+            {"coin": "ETH", "side": "B", "px": "102", "sz": "1", "time": 30_000, "hash": "0x", "tid": 3, "users": ["0x1", "0x2"]}
+        ]))
+        .unwrap();
+        let fill = |tid: u64, side: &str, crossed: bool| {
+            serde_json::from_value::<UserFillsResponse>(serde_json::json!({
+                "closedPnl": "0", "coin": "ETH", "crossed": crossed, "dir": "Open Long",
+                "hash": "0x0", "oid": 1, "px": "100", "side": side, "startPosition": "0",
+                "sz": "2", "time": 0, "fee": "0", "tid": tid, "feeToken": "USDC"
+            }))
+            .unwrap()
+        };
+        let fills = [fill(1, "B", false), fill(2, "A", false), fill(3, "B", true)];
+        let mut analyzer = MarkoutAnalyzer::new();
+        analyzer.add_trades(&trades);
+
+        let markout = analyzer.markout(&fills[0]).unwrap();
+        assert_eq!(markout.liquidity, Liquidity::Maker);
+        // 100 after 1s and 99 after 10s, while 60s is past the end of the tape
+        assert_eq!(markout.markouts, [Some(0.0), Some(-2.0), None]);
+        let report = analyzer.report(&fills);
+        let eth = &report.by_coin["ETH"];
+        assert_eq!(eth.maker[1].fills, 2);
+        assert_eq!(eth.maker[1].adverse, 1);
+        assert_eq!(eth.taker[1].pnl, -2.0);
+        assert_eq!(eth.taker[1].bps(), Some(-100.0));
+        assert_eq!(eth.maker[2].fills, 0);
+        assert_eq!(report.skipped, 0);
+    }
+}
@@ -0,0 +1,445 @@
+use std::{collections::VecDeque, time::Duration};
+
+use serde::Serialize;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::warn;
+
+use crate::{
+    prelude::*, BookLevel, CandleData, FundingHistoryResponse, L2Book, L2BookData, Message,
+    PaperConfig, PaperExchange, Position, Side, SimClock, Strategy, Trade, Trades,
+};
+
+#[derive(Clone, Debug)]
+pub struct BacktestConfig {
+    /// Fees and address of the simulated exchange. Its latency is ignored, orders take effect
+    /// at the time of the message the strategy is reacting to.
+    pub paper: PaperConfig,
+    /// Account value before any PnL, the base of the equity curve
+    pub initial_balance: f64,
+    /// Half spread of the book synthesized around each candle's close
+    pub candle_half_spread_bps: f64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        BacktestConfig {
+            paper: PaperConfig::default(),
+            initial_balance: 10_000.0,
+            candle_half_spread_bps: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EquityPoint {
+    pub time: u64,
+    pub equity: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BacktestReport {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub initial_balance: f64,
+    pub final_equity: f64,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub fees: f64,
+    /// Funding received, negative when paid
+    pub funding: f64,
+    /// Realized and unrealized PnL net of fees and funding
+    pub total_pnl: f64,
+    pub num_fills: usize,
+    /// Traded notional
+    pub volume: f64,
+    /// Largest fall in equity from a previous peak
+    pub max_drawdown: f64,
+    /// `max_drawdown` as a fraction of the peak it fell from
+    pub max_drawdown_pct: f64,
+    /// Share of position-reducing fills that realized a profit, `None` if there were none
+    pub win_rate: Option<f64>,
+    pub positions: Vec<Position>,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    num_fills: usize,
+    volume: f64,
+    closing_fills: usize,
+    winning_fills: usize,
+    equity_curve: Vec<EquityPoint>,
+}
+
+impl Stats {
+    fn record(&mut self, message: &Message) {
+        let Message::UserFills(fills) = message else {
+            return;
+        };
+        for fill in &fills.data.fills {
+            self.num_fills += 1;
+            self.volume += fill.px.parse::<f64>().unwrap_or_default()
+                * fill.sz.parse::<f64>().unwrap_or_default();
+            if fill.dir.closes_position() {
+                self.closing_fills += 1;
+                if fill.closed_pnl.parse::<f64>().unwrap_or_default() > 0.0 {
+                    self.winning_fills += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Replays historical market data through a `Strategy`, matching its orders on a
+/// `PaperExchange` with simulated time, paying funding and fees, and reports the resulting PnL.
+///
+/// `l2Book` and `trades` messages are matched as in paper trading. Candles are turned into
+/// trades along open, low, high, close (open, high, low, close for down candles), a quarter of
+/// the volume each, followed by a book around the close with the candle's volume on each
+/// side, so resting orders fill when the range passes through them and new orders take the
+/// close. The strategy sees each market message after the exchange, followed by the fills and
+/// order updates it caused.
+#[derive(Debug)]
+pub struct Backtester {
+    config: BacktestConfig,
+    exchange: PaperExchange,
+    receiver: UnboundedReceiver<Message>,
+    /// Pending funding payments as time, coin and rate, earliest first
+    funding: VecDeque<(u64, String, f64)>,
+    clock: Option<SimClock>,
+    stats: Stats,
+}
+
+impl Backtester {
+    pub fn new(config: BacktestConfig) -> Backtester {
+        let (sender, receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..config.paper.clone()
+        })
+        .with_sender(sender);
+        Backtester {
+            config,
+            exchange,
+            receiver,
+            funding: VecDeque::new(),
+            clock: None,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Funding rates, as returned by `InfoClient::funding_history`, paid on open positions as
+    /// the replay passes their time.
+    pub fn with_funding(mut self, history: Vec<FundingHistoryResponse>) -> Self {
+        let mut funding: Vec<(u64, String, f64)> = self.funding.drain(..).collect();
+        for entry in history {
+            match entry.funding_rate.parse::<f64>() {
+                Ok(rate) => funding.push((entry.time.as_millis(), entry.coin, rate)),
+                Err(_) => warn!("Could not parse funding rate for {}", entry.coin),
+            }
+        }
+        funding.sort_by_key(|(time, _, _)| *time);
+        self.funding = funding.into();
+        self
+    }
+
+    /// Moves `clock` to the time of each message replayed, so a strategy, candle aggregator
+    /// or timer on it sees the replay's time rather than the wall clock's.
+    pub fn with_clock(mut self, clock: SimClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn exchange(&self) -> &PaperExchange {
+        &self.exchange
+    }
+
+    /// Runs `strategy` over `messages`, which must be in time order.
+    pub async fn run<S: Strategy>(
+        mut self,
+        strategy: &mut S,
+        messages: impl IntoIterator<Item = Message>,
+    ) -> Result<BacktestReport> {
+        for message in messages {
+            let time = message_time(&message);
+            if let Some(time) = time {
+                self.pay_funding(time);
+                self.exchange.set_time(time);
+                if let Some(clock) = &self.clock {
+                    clock.set(time);
+                }
+            }
+            match &message {
+                Message::Candle(candle) => {
+                    for simulated in self.candle_messages(&candle.data) {
+                        self.exchange.handle_message(&simulated);
+                    }
+                }
+                message => self.exchange.handle_message(message),
+            }
+            self.dispatch(strategy).await?;
+            strategy.on_message(&message, &self.exchange).await?;
+            self.dispatch(strategy).await?;
+            if let Some(time) = time {
+                self.record_equity(time);
+            }
+        }
+        Ok(self.report())
+    }
+
+    fn pay_funding(&mut self, time: u64) {
+        while self.funding.front().is_some_and(|(t, _, _)| *t <= time) {
+            if let Some((funding_time, coin, rate)) = self.funding.pop_front() {
+                self.exchange.set_time(funding_time);
+                self.exchange.apply_funding(&coin, rate);
+            }
+        }
+    }
+
+    /// Passes the fills, order updates and funding payments queued by the exchange to the
+    /// strategy, including those caused by its reactions.
+    async fn dispatch<S: Strategy>(&mut self, strategy: &mut S) -> Result<()> {
+        while let Ok(event) = self.receiver.try_recv() {
+            self.stats.record(&event);
+            strategy.on_message(&event, &self.exchange).await?;
+        }
+        Ok(())
+    }
+
+    fn equity(&self) -> f64 {
+        self.config.initial_balance
+            + self
+                .exchange
+                .positions()
+                .iter()
+                .map(Position::total_pnl)
+                .sum::<f64>()
+    }
+
+    fn record_equity(&mut self, time: u64) {
+        let point = EquityPoint {
+            time,
+            equity: self.equity(),
+        };
+        match self.stats.equity_curve.last_mut() {
+            Some(last) if last.time == time => *last = point,
+            _ => self.stats.equity_curve.push(point),
+        }
+    }
+
+    fn candle_messages(&self, candle: &CandleData) -> Vec<Message> {
+        let parse = |value: &str| value.parse::<f64>().ok();
+        let (Some(open), Some(high), Some(low), Some(close), Some(volume)) = (
+            parse(&candle.open),
+            parse(&candle.high),
+            parse(&candle.low),
+            parse(&candle.close),
+            parse(&candle.volume),
+        ) else {
+            warn!(
+                "Could not parse candle for {} at {}",
+                candle.coin, candle.time_open
+            );
+            return Vec::new();
+        };
+
+        let path = if close >= open {
+            [open, low, high, close]
+        } else {
+            [open, high, low, close]
+        };
+        let mut previous = open;
+        let trades = path
+            .iter()
+            .map(|&px| {
+                let side = Side::from_is_buy(px >= previous);
+                previous = px;
+                Trade {
+                    coin: candle.coin.clone(),
+                    side,
+                    px: px.to_string(),
+                    sz: (volume / 4.0).to_string(),
+                    time: candle.time_close,
+                    hash: String::new(),
+                    tid: 0,
+                    users: (String::new(), String::new()),
+                }
+            })
+            .collect();
+
+        let half_spread = close * self.config.candle_half_spread_bps / 10_000.0;
+        let level = |px: f64| {
+            vec![BookLevel {
+                px: px.to_string(),
+                sz: volume.to_string(),
+                n: 1,
+            }]
+        };
+        vec![
+            Message::Trades(Trades { data: trades }),
+            Message::L2Book(L2Book {
+                data: L2BookData {
+                    coin: candle.coin.clone(),
+                    time: candle.time_close,
+                    levels: vec![level(close - half_spread), level(close + half_spread)],
+                },
+            }),
+        ]
+    }
+
+    fn report(self) -> BacktestReport {
+        let positions = self.exchange.positions();
+        let final_equity = self.equity();
+        let curve = &self.stats.equity_curve;
+
+        let mut peak = self.config.initial_balance;
+        let mut max_drawdown = 0.0;
+        let mut max_drawdown_pct = 0.0;
+        for point in curve {
+            peak = f64::max(peak, point.equity);
+            let drawdown = peak - point.equity;
+            if drawdown > max_drawdown {
+                max_drawdown = drawdown;
+                max_drawdown_pct = if peak > 0.0 { drawdown / peak } else { 0.0 };
+            }
+        }
+
+        BacktestReport {
+            start_time: curve.first().map(|p| p.time).unwrap_or_default(),
+            end_time: curve.last().map(|p| p.time).unwrap_or_default(),
+            initial_balance: self.config.initial_balance,
+            final_equity,
+            realized_pnl: positions.iter().map(|p| p.realized_pnl).sum(),
+            unrealized_pnl: positions.iter().map(Position::unrealized_pnl).sum(),
+            fees: positions.iter().map(|p| p.fees).sum(),
+            funding: positions.iter().map(|p| p.funding).sum(),
+            total_pnl: final_equity - self.config.initial_balance,
+            num_fills: self.stats.num_fills,
+            volume: self.stats.volume,
+            max_drawdown,
+            max_drawdown_pct,
+            win_rate: (self.stats.closing_fills > 0)
+                .then(|| self.stats.winning_fills as f64 / self.stats.closing_fills as f64),
+            positions,
+            equity_curve: self.stats.equity_curve,
+        }
+    }
+}
+
+fn message_time(message: &Message) -> Option<u64> {
+    match message {
+        Message::Candle(candle) => Some(candle.data.time_close.as_millis()),
+        Message::Trades(trades) => trades.data.last().map(|trade| trade.time.as_millis()),
+        Message::L2Book(book) => Some(book.data.time.as_millis()),
+        Message::Bbo(bbo) => Some(bbo.data.time.as_millis()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Candle, ClientLimit, ClientOrder, ClientOrderRequest, Exchange, Tif};
+
+    fn candle(time: u64, open: f64, high: f64, low: f64, close: f64) -> Message {
+        Message::Candle(Candle {
+            data: CandleData {
+                time_close: time.into(),
+                close: close.to_string(),
+                high: high.to_string(),
+                interval: "1h".to_string(),
+                low: low.to_string(),
+                num_trades: 100,
+                open: open.to_string(),
+                coin: "ETH".to_string(),
+                time_open: (time - 3_600_000).into(),
+                volume: "8".to_string(),
+            },
+        })
+    }
+
+    fn order(is_buy: bool, limit_px: f64, sz: f64, tif: Tif) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif }),
+        }
+    }
+
+    /// Buys at the first close with a bid resting below, sells everything at the third.
+    #[derive(Default)]
+    struct BuyThenSell {
+        candles: usize,
+        fills: usize,
+    }
+
+    impl Strategy for BuyThenSell {
+        async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+            match message {
+                Message::Candle(_) => {
+                    self.candles += 1;
+                    match self.candles {
+                        1 => {
+                            exchange
+                                .bulk_order(vec![
+                                    order(true, 2_100.0, 1.0, Tif::Ioc),
+                                    order(true, 1_950.0, 1.0, Tif::Gtc),
+                                ])
+                                .await?;
+                        }
+                        3 => {
+                            exchange.order(order(false, 1.0, 2.0, Tif::Ioc)).await?;
+                        }
+                        _ => {}
+                    }
+                }
+                Message::UserFills(fills) => self.fills += fills.data.fills.len(),
+                _ => {}
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_candles_with_funding() {
+        let hour = 3_600_000;
+        let funding: Vec<FundingHistoryResponse> = serde_json::from_str(&format!(
+            r#"[{{"coin":"ETH","fundingRate":"0.0001","premium":"0","time":{}}}]"#,
+            hour + hour / 2
+        ))
+        .unwrap();
+        let backtester = Backtester::new(BacktestConfig::default()).with_funding(funding);
+        let mut strategy = BuyThenSell::default();
+        let report = backtester
+            .run(
+                &mut strategy,
+                vec![
+                    candle(hour, 2_000.0, 2_010.0, 1_990.0, 2_000.0),
+                    candle(2 * hour, 2_000.0, 2_000.0, 1_940.0, 1_960.0),
+                    candle(3 * hour, 1_960.0, 2_110.0, 1_960.0, 2_100.0),
+                ],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(strategy.fills, 3);
+        assert_eq!(report.num_fills, 3);
+        let (buy_px, sell_px) = (2_000.0 * 1.0001, 2_100.0 * 0.9999);
+        assert!((report.realized_pnl - (2.0 * sell_px - buy_px - 1_950.0)).abs() < 1e-6);
+        let fees = (buy_px + 2.0 * sell_px) * 4.5 / 10_000.0 + 1_950.0 * 1.5 / 10_000.0;
+        assert!((report.fees - fees).abs() < 1e-6);
+        // Paid on one long at the first close's mid
+        assert!((report.funding + 0.2).abs() < 1e-9);
+        assert!((report.total_pnl - (report.realized_pnl - fees - 0.2)).abs() < 1e-6);
+        assert_eq!(report.win_rate, Some(1.0));
+        assert!(report.max_drawdown > 0.0);
+        assert_eq!(report.equity_curve.len(), 3);
+        assert_eq!(report.positions[0].szi, 0.0);
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use hyperliquid_rust_sdk::{
+    BacktestConfig, Backtester, BaseUrl, Candle, CandleData, ClientLimit, ClientOrder,
+    ClientOrderRequest, Error, Exchange, InfoClient, Message, Strategy, Tif, Timestamp,
+};
+use log::info;
+
+/// Goes long when the close crosses above its moving average and flat when it crosses below.
+struct MovingAverageCross {
+    window: usize,
+    closes: Vec<f64>,
+    long: bool,
+}
+
+impl Strategy for MovingAverageCross {
+    async fn on_message<E: Exchange>(
+        &mut self,
+        message: &Message,
+        exchange: &E,
+    ) -> Result<(), Error> {
+        let Message::Candle(candle) = message else {
+            return Ok(());
+        };
+        let close: f64 = candle.data.close.parse().unwrap();
+        self.closes.push(close);
+        if self.closes.len() < self.window {
+            return Ok(());
+        }
+        let average = self.closes[self.closes.len() - self.window..]
+            .iter()
+            .sum::<f64>()
+            / self.window as f64;
+        if (close > average) != self.long {
+            self.long = !self.long;
+            let limit_px = if self.long {
+                close * 1.01
+            } else {
+                close * 0.99
+            };
+            exchange
+                .order(ClientOrderRequest {
+                    asset: candle.data.coin.clone(),
+                    is_buy: self.long,
+                    reduce_only: !self.long,
+                    limit_px,
+                    sz: 1.0,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+                })
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let info_client = InfoClient::new(None, Some(BaseUrl::Mainnet)).await.unwrap();
+
+    let end_time = Timestamp::now();
+    let start_time = end_time - Duration::from_secs(30 * 24 * 3600);
+    let candles = info_client
+        .candles_snapshot("ETH".to_string(), "1h".to_string(), start_time, end_time)
+        .await
+        .unwrap();
+    let funding = info_client
+        .funding_history("ETH".to_string(), start_time, Some(end_time))
+        .await
+        .unwrap();
+    info!("Replaying {} candles", candles.len());
+
+    let messages = candles.into_iter().map(|candle| {
+        Message::Candle(Candle {
+            data: CandleData::from(candle),
+        })
+    });
+    let mut strategy = MovingAverageCross {
+        window: 24,
+        closes: Vec::new(),
+        long: false,
+    };
+    let report = Backtester::new(BacktestConfig::default())
+        .with_funding(funding)
+        .run(&mut strategy, messages)
+        .await
+        .unwrap();
+    info!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
@@ -0,0 +1,30 @@
+use alloy::primitives::Address;
+use hyperliquid_rust_sdk::{blocking::InfoClient, BaseUrl, Message, Subscription};
+use log::info;
+use tokio::sync::mpsc::unbounded_channel;
+
+const ADDRESS: &str = "0xc64cc00b46101bd40aa1c3121195e85c0b0918d8";
+
+fn main() {
+    env_logger::init();
+    let info_client = InfoClient::new(None, Some(BaseUrl::Testnet)).unwrap();
+
+    let user: Address = ADDRESS.parse().unwrap();
+    info!("User state: {:?}", info_client.user_state(user).unwrap());
+    info!("All mids: {:?}", info_client.all_mids().unwrap());
+
+    let (sender, mut receiver) = unbounded_channel();
+    info_client
+        .subscribe(
+            Subscription::Trades {
+                coin: "ETH".to_string(),
+            },
+            sender,
+        )
+        .unwrap();
+    for _ in 0..5 {
+        if let Some(Message::Trades(trades)) = receiver.blocking_recv() {
+            info!("Received trade data: {trades:?}");
+        }
+    }
+}
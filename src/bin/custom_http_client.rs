@@ -0,0 +1,48 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{
+    BaseUrl, ExchangeClient, InfoClient, RateLimitMode, RateLimiter, RetryPolicy,
+};
+use log::info;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+
+    // Any reqwest configuration (proxies, TLS roots, default headers, ...) is used for every
+    // request the SDK sends, including the ones ExchangeClient makes internally
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .pool_idle_timeout(Duration::from_secs(90))
+        .user_agent("my-trading-bot/1.0")
+        .build()
+        .unwrap();
+
+    let rate_limiter = Arc::new(RateLimiter::new(RateLimitMode::Delay));
+
+    let info_client = InfoClient::new(Some(client.clone()), Some(BaseUrl::Testnet))
+        .await
+        .unwrap()
+        .with_retry_policy(RetryPolicy::exponential(3))
+        .with_rate_limiter(rate_limiter.clone());
+
+    let exchange_client =
+        ExchangeClient::new(Some(client), wallet, Some(BaseUrl::Testnet), None, None)
+            .await
+            .unwrap()
+            .with_rate_limiter(rate_limiter.clone());
+
+    let mids = info_client.all_mids().await.unwrap();
+    info!("ETH mid: {:?}", mids.get("ETH"));
+    info!(
+        "Exchange client assets: {}, remaining IP weight: {}",
+        exchange_client.coin_to_asset.len(),
+        rate_limiter.available_weight()
+    );
+}
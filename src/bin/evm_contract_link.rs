@@ -0,0 +1,38 @@
+use alloy::{primitives::address, signers::local::PrivateKeySigner};
+use hyperliquid_rust_sdk::{BaseUrl, ExchangeClient, FinalizeEvmContractInput};
+use log::info;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+
+    let exchange_client =
+        ExchangeClient::new(None, wallet.clone(), Some(BaseUrl::Testnet), None, None)
+            .await
+            .unwrap();
+
+    // Spot token 1111 deployed by this wallet, with its ERC-20 deployed from the same wallet
+    // at transaction nonce 0 and using 2 more wei decimals on HyperEVM
+    let token = 1111;
+    let res = exchange_client
+        .request_evm_contract(
+            token,
+            address!("0x8b983aC3a9B9d4e2605cbE4d5b7C22fb9EbB75eC"),
+            2,
+            None,
+        )
+        .await
+        .unwrap();
+    info!("request evm contract: {res:?}");
+
+    let res = exchange_client
+        .finalize_evm_contract(token, FinalizeEvmContractInput::Create { nonce: 0 }, None)
+        .await
+        .unwrap();
+    info!("finalize evm contract: {res:?}");
+}
@@ -0,0 +1,63 @@
+/*
+Runs an ETH grid on testnet, resuming from grid_state.json if a previous run left one, until
+Ctrl-C, then cancels the grid orders.
+*/
+use std::path::Path;
+
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{BaseUrl, GridConfig, GridTrader, HyperliquidClient, Strategy};
+use log::info;
+use tokio::sync::mpsc::unbounded_channel;
+
+const STATE_FILE: &str = "grid_state.json";
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+    let client = HyperliquidClient::builder(wallet)
+        .base_url(BaseUrl::Testnet)
+        .build()
+        .await
+        .unwrap();
+
+    let mut grid = if Path::new(STATE_FILE).exists() {
+        let mut grid = GridTrader::load(client.address(), STATE_FILE).unwrap();
+        grid.resume(client.exchange(), client.info()).await.unwrap();
+        grid
+    } else {
+        let config: GridConfig = serde_json::from_str(
+            r#"{"coin":"ETH","sz_decimals":4,"lower_px":3000,"upper_px":4000,"levels":11,"size_per_level":0.01,"rebalance":"recenter"}"#,
+        )
+        .unwrap();
+        GridTrader::new(client.address(), config)
+            .unwrap()
+            .with_state_file(STATE_FILE)
+    };
+
+    let (sender, mut receiver) = unbounded_channel();
+    for subscription in grid.subscriptions() {
+        client
+            .subscribe(subscription, sender.clone())
+            .await
+            .unwrap();
+    }
+    loop {
+        tokio::select! {
+            Some(message) = receiver.recv() => {
+                grid.on_message(&message, client.exchange()).await.unwrap();
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    grid.stop(client.exchange()).await.unwrap();
+    info!(
+        "Grid stopped after {} buys and {} sells",
+        grid.state().buys_filled,
+        grid.state().sells_filled
+    );
+}
@@ -0,0 +1,19 @@
+use chrono::{Duration, Utc};
+use hyperliquid_rust_sdk::ArchiveClient;
+use log::info;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Requester pays: AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY must be set
+    let archive = ArchiveClient::new();
+
+    let end = Utc::now() - Duration::days(2);
+    let start = end - Duration::hours(2);
+    let books = archive.l2_books("ETH", start, end).await.unwrap();
+    info!("Downloaded {} ETH book snapshots", books.len());
+    let trades = archive.trades(start, end).await.unwrap();
+    info!("Downloaded {} trades", trades.len());
+    let ctxs = archive.asset_ctxs(start, end).await.unwrap();
+    info!("Downloaded {} asset contexts", ctxs.len());
+}
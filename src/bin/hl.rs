@@ -0,0 +1,350 @@
+//! Command line access to the API, printing responses and messages as JSON.
+//!
+//! ```text
+//! hl [--testnet | --url URL] <command>
+//!
+//! mids
+//! balances [ADDRESS]
+//! positions [ADDRESS]
+//! orders [ADDRESS]
+//! order COIN buy|sell SZ PX [--tif Gtc|Ioc|Alo] [--reduce-only]
+//! market COIN buy|sell SZ [--slippage FRACTION]
+//! cancel COIN OID
+//! transfer AMOUNT DESTINATION
+//! class-transfer AMOUNT perp|spot
+//! approve-agent
+//! big-blocks on|off
+//! subscribe SUBSCRIPTION
+//! ```
+//!
+//! Commands that sign read the private key from `HL_PRIVATE_KEY`; queries default to its
+//! address. `SUBSCRIPTION` is the subscription as JSON, such as `{"type":"l2Book","coin":"ETH"}`,
+//! and messages are printed one per line until interrupted.
+
+use std::{
+    io::{self, Write},
+    process::exit,
+    sync::{Arc, Mutex},
+};
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use hyperliquid_rust_sdk::{
+    BaseUrl, ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient,
+    InfoClient, MarketOrderParams, RecordedEntry, Recorder, Recording, Subscription, Tif,
+};
+use serde_json::json;
+use tokio::sync::mpsc::unbounded_channel;
+
+const USAGE: &str = "usage: hl [--testnet | --url URL] <mids | balances | positions | orders | order | market | cancel | transfer | class-transfer | approve-agent | big-blocks | subscribe> [ARGS]";
+
+type CliResult = Result<(), String>;
+
+/// Prints websocket messages and exchange responses passed through a `Recorder` as they are
+/// received, so output is the API's own JSON.
+#[derive(Clone, Default)]
+struct JsonOut {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for JsonOut {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.extend_from_slice(buf);
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let Ok(recording) = Recording::from_reader(line.as_slice()) else {
+                continue;
+            };
+            for entry in recording.entries() {
+                match entry {
+                    // Keepalive replies to the client's pings
+                    RecordedEntry::Ws { text, .. } if text.contains(r#""channel":"pong""#) => {}
+                    RecordedEntry::Ws { text, .. } => println!("{text}"),
+                    RecordedEntry::Rest {
+                        url_path,
+                        response,
+                        error,
+                        ..
+                    } if url_path == "/exchange" => match (response, error) {
+                        (Some(response), _) => println!("{response}"),
+                        (None, error) => println!("{}", json!({ "error": error })),
+                    },
+                    RecordedEntry::Rest { .. } => {}
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+struct Cli {
+    base_url: BaseUrl,
+    args: Vec<String>,
+}
+
+impl Cli {
+    fn parse() -> Result<Cli, String> {
+        let mut base_url = BaseUrl::Mainnet;
+        let mut args = Vec::new();
+        let mut input = std::env::args().skip(1);
+        while let Some(arg) = input.next() {
+            match arg.as_str() {
+                "--testnet" => base_url = BaseUrl::Testnet,
+                "--url" => {
+                    base_url = BaseUrl::custom(input.next().ok_or("--url needs a value")?);
+                }
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                _ => args.push(arg),
+            }
+        }
+        Ok(Cli { base_url, args })
+    }
+
+    /// Removes `--name VALUE` from the arguments, returning the value.
+    fn option(&mut self, name: &str) -> Result<Option<String>, String> {
+        let Some(index) = self.args.iter().position(|arg| arg == name) else {
+            return Ok(None);
+        };
+        if index + 1 >= self.args.len() {
+            return Err(format!("{name} needs a value"));
+        }
+        let value = self.args.remove(index + 1);
+        self.args.remove(index);
+        Ok(Some(value))
+    }
+
+    /// Removes `--name` from the arguments, returning whether it was given.
+    fn flag(&mut self, name: &str) -> bool {
+        let given = self.args.iter().any(|arg| arg == name);
+        self.args.retain(|arg| arg != name);
+        given
+    }
+
+    fn arg(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.args
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing {name}\n{USAGE}"))
+    }
+
+    fn wallet() -> Result<PrivateKeySigner, String> {
+        std::env::var("HL_PRIVATE_KEY")
+            .map_err(|_| "HL_PRIVATE_KEY is not set".to_string())?
+            .parse()
+            .map_err(|e| format!("invalid HL_PRIVATE_KEY: {e}"))
+    }
+
+    /// The address at `index`, or the key's.
+    fn address(&self, index: usize) -> Result<Address, String> {
+        match self.args.get(index) {
+            Some(address) => address
+                .parse()
+                .map_err(|e| format!("invalid address {address}: {e}")),
+            None => Ok(Cli::wallet()?.address()),
+        }
+    }
+
+    async fn info(&self) -> Result<InfoClient, String> {
+        InfoClient::new(None, Some(self.base_url.clone()))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn exchange(&self) -> Result<ExchangeClient, String> {
+        Ok(ExchangeClient::new(
+            None,
+            Cli::wallet()?,
+            Some(self.base_url.clone()),
+            None,
+            None,
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .with_recorder(Recorder::new(JsonOut::default())))
+    }
+
+    async fn query(&self, request: serde_json::Value) -> CliResult {
+        let response = self
+            .info()
+            .await?
+            .http_client
+            .post("/info", request.to_string())
+            .await
+            .map_err(|e| e.to_string())?;
+        println!("{response}");
+        Ok(())
+    }
+
+    async fn run(mut self) -> CliResult {
+        let command = self.arg(0, "command")?.to_string();
+        match command.as_str() {
+            "mids" => self.query(json!({ "type": "allMids" })).await,
+            "balances" => {
+                let user = self.address(1)?;
+                self.query(json!({ "type": "spotClearinghouseState", "user": user }))
+                    .await
+            }
+            "positions" => {
+                let user = self.address(1)?;
+                self.query(json!({ "type": "clearinghouseState", "user": user }))
+                    .await
+            }
+            "orders" => {
+                let user = self.address(1)?;
+                self.query(json!({ "type": "openOrders", "user": user }))
+                    .await
+            }
+            "order" => {
+                let tif = match self.option("--tif")? {
+                    Some(tif) => tif.parse::<Tif>().map_err(|e| e.to_string())?,
+                    None => Tif::Gtc,
+                };
+                let reduce_only = self.flag("--reduce-only");
+                let order = ClientOrderRequest {
+                    asset: self.arg(1, "COIN")?.to_string(),
+                    is_buy: is_buy(self.arg(2, "side")?)?,
+                    reduce_only,
+                    limit_px: number(self.arg(4, "PX")?)?,
+                    sz: number(self.arg(3, "SZ")?)?,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif }),
+                };
+                self.exchange()
+                    .await?
+                    .order(order, None)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            }
+            "market" => {
+                let slippage = self.option("--slippage")?.map(|s| number(&s)).transpose()?;
+                let exchange = self.exchange().await?;
+                exchange
+                    .market_open(MarketOrderParams {
+                        asset: self.arg(1, "COIN")?,
+                        is_buy: is_buy(self.arg(2, "side")?)?,
+                        sz: number(self.arg(3, "SZ")?)?,
+                        px: None,
+                        slippage,
+                        cloid: None,
+                        wallet: None,
+                    })
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            }
+            "cancel" => {
+                let cancel = ClientCancelRequest {
+                    asset: self.arg(1, "COIN")?.to_string(),
+                    oid: self
+                        .arg(2, "OID")?
+                        .parse()
+                        .map_err(|e| format!("invalid OID: {e}"))?,
+                };
+                self.exchange()
+                    .await?
+                    .cancel(cancel, None)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            }
+            "transfer" => {
+                let (amount, destination) = (self.arg(1, "AMOUNT")?, self.arg(2, "DESTINATION")?);
+                self.exchange()
+                    .await?
+                    .usdc_transfer(amount, destination, None)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            }
+            "class-transfer" => {
+                let amount = number(self.arg(1, "AMOUNT")?)?;
+                let to_perp = match self.arg(2, "perp|spot")? {
+                    "perp" => true,
+                    "spot" => false,
+                    other => return Err(format!("expected perp or spot, got {other}")),
+                };
+                self.exchange()
+                    .await?
+                    .class_transfer(amount, to_perp, None)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            }
+            "approve-agent" => {
+                let (key, _) = self
+                    .exchange()
+                    .await?
+                    .approve_agent(None)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let agent = PrivateKeySigner::from_bytes(&key).map_err(|e| e.to_string())?;
+                println!(
+                    "{}",
+                    json!({ "agentAddress": agent.address(), "agentPrivateKey": key })
+                );
+                Ok(())
+            }
+            "big-blocks" => {
+                let using_big_blocks = match self.arg(1, "on|off")? {
+                    "on" => true,
+                    "off" => false,
+                    other => return Err(format!("expected on or off, got {other}")),
+                };
+                self.exchange()
+                    .await?
+                    .enable_big_blocks(using_big_blocks, None)
+                    .await
+                    .map(drop)
+                    .map_err(|e| e.to_string())
+            }
+            "subscribe" => {
+                let subscription: Subscription = serde_json::from_str(self.arg(1, "SUBSCRIPTION")?)
+                    .map_err(|e| format!("invalid subscription: {e}"))?;
+                let info = InfoClient::with_reconnect(None, Some(self.base_url.clone()))
+                    .await
+                    .map_err(|e| e.to_string())?
+                    .with_recorder(Recorder::new(JsonOut::default()));
+                let (sender, mut receiver) = unbounded_channel();
+                info.subscribe(subscription, sender)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                // Messages are printed by the recorder as received
+                while receiver.recv().await.is_some() {}
+                Ok(())
+            }
+            other => Err(format!("unknown command {other}\n{USAGE}")),
+        }
+    }
+}
+
+fn is_buy(side: &str) -> Result<bool, String> {
+    match side {
+        "buy" | "b" => Ok(true),
+        "sell" | "s" => Ok(false),
+        other => Err(format!("expected buy or sell, got {other}")),
+    }
+}
+
+fn number(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .map_err(|e| format!("invalid number {value}: {e}"))
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let result = match Cli::parse() {
+        Ok(cli) => cli.run().await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        exit(1);
+    }
+}
@@ -0,0 +1,34 @@
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{BaseUrl, HyperliquidClient, Message, RetryPolicy, Subscription};
+use log::info;
+use tokio::sync::mpsc::unbounded_channel;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+
+    let client = HyperliquidClient::builder(wallet)
+        .base_url(BaseUrl::Testnet)
+        .retry_policy(RetryPolicy::exponential(3))
+        .build()
+        .await
+        .unwrap();
+
+    info!("Perp assets: {}", client.meta().universe.len());
+    let user_state = client.info().user_state(client.address()).await.unwrap();
+    info!("Account value: {}", user_state.margin_summary.account_value);
+
+    let (sender, mut receiver) = unbounded_channel();
+    client
+        .subscribe(Subscription::AllMids, sender)
+        .await
+        .unwrap();
+    if let Some(Message::AllMids(all_mids)) = receiver.recv().await {
+        info!("ETH mid: {:?}", all_mids.data.mids.get("ETH"));
+    }
+}
@@ -1,5 +1,5 @@
 use alloy::primitives::Address;
-use hyperliquid_rust_sdk::{BaseUrl, InfoClient};
+use hyperliquid_rust_sdk::{BaseUrl, InfoClient, Timestamp};
 use log::info;
 
 const ADDRESS: &str = "0xc64cc00b46101bd40aa1c3121195e85c0b0918d8";
@@ -115,8 +115,8 @@ async fn user_fills_example(info_client: &InfoClient) {
 async fn funding_history_example(info_client: &InfoClient) {
     let coin = "ETH";
 
-    let start_timestamp = 1690540602225;
-    let end_timestamp = 1690569402225;
+    let start_timestamp = Timestamp::from_millis(1690540602225);
+    let end_timestamp = Timestamp::from_millis(1690569402225);
     info!(
         "Funding data history for {coin} between timestamps {start_timestamp} and {end_timestamp}: {:?}",
         info_client.funding_history(coin.to_string(), start_timestamp, Some(end_timestamp)).await.unwrap()
@@ -134,8 +134,8 @@ async fn l2_snapshot_example(info_client: &InfoClient) {
 
 async fn candles_snapshot_example(info_client: &InfoClient) {
     let coin = "ETH";
-    let start_timestamp = 1690540602225;
-    let end_timestamp = 1690569402225;
+    let start_timestamp = Timestamp::from_millis(1690540602225);
+    let end_timestamp = Timestamp::from_millis(1690569402225);
     let interval = "1h";
 
     info!(
@@ -149,8 +149,8 @@ async fn candles_snapshot_example(info_client: &InfoClient) {
 
 async fn user_funding_example(info_client: &InfoClient) {
     let user = address();
-    let start_timestamp = 1690540602225;
-    let end_timestamp = 1690569402225;
+    let start_timestamp = Timestamp::from_millis(1690540602225);
+    let end_timestamp = Timestamp::from_millis(1690569402225);
     info!(
         "Funding data history for {user} between timestamps {start_timestamp} and {end_timestamp}: {:?}",
         info_client.user_funding_history(user, start_timestamp, Some(end_timestamp)).await.unwrap()
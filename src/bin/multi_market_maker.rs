@@ -0,0 +1,47 @@
+/*
+Quotes ETH and BTC on testnet from a JSON config until Ctrl-C, then cancels all quotes.
+*/
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{BaseUrl, HyperliquidClient, MultiMarketMaker, MultiMarketMakerConfig};
+use tokio::sync::mpsc::unbounded_channel;
+
+const CONFIG: &str = r#"{
+    "coins": [
+        {"coin": "ETH", "sz_decimals": 4, "half_spread_bps": 5, "order_size": 0.01, "max_position": 0.05, "skew_bps": 5},
+        {"coin": "BTC", "sz_decimals": 5, "half_spread_bps": 5, "order_size": 0.001, "max_position": 0.005, "skew_bps": 5}
+    ]
+}"#;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+    let client = HyperliquidClient::builder(wallet)
+        .base_url(BaseUrl::Testnet)
+        .build()
+        .await
+        .unwrap();
+
+    let config: MultiMarketMakerConfig = serde_json::from_str(CONFIG).unwrap();
+    let mut maker = MultiMarketMaker::new(client.address(), config);
+    maker.sync_positions(client.info()).await.unwrap();
+
+    let (sender, receiver) = unbounded_channel();
+    for subscription in maker.subscriptions() {
+        client
+            .subscribe(subscription, sender.clone())
+            .await
+            .unwrap();
+    }
+    let shutdown = async {
+        tokio::signal::ctrl_c().await.unwrap();
+    };
+    maker
+        .run(client.exchange(), receiver, shutdown)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,69 @@
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{
+    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, HyperliquidClient, OrderEvent,
+    OrderManager, Tif,
+};
+use log::info;
+use tokio::sync::mpsc::unbounded_channel;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+
+    let client = HyperliquidClient::builder(wallet)
+        .base_url(BaseUrl::Testnet)
+        .build()
+        .await
+        .unwrap();
+
+    let (event_sender, mut events) = unbounded_channel();
+    let mut manager = OrderManager::new(client.address()).with_events(event_sender);
+    let (sender, mut receiver) = unbounded_channel();
+    for subscription in manager.subscriptions() {
+        client
+            .subscribe(subscription, sender.clone())
+            .await
+            .unwrap();
+    }
+
+    let order = ClientOrderRequest {
+        asset: "ETH".to_string(),
+        is_buy: true,
+        reduce_only: false,
+        limit_px: 1800.0,
+        sz: 0.01,
+        cloid: None,
+        order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+    };
+    let statuses = manager.place(client.exchange(), vec![order]).await.unwrap();
+    let cloid = statuses[0].request;
+    info!("Order {cloid} placed: {:?}", statuses[0].status);
+
+    manager
+        .cancel(client.exchange(), vec![cloid])
+        .await
+        .unwrap();
+    manager.reconcile(client.info()).await.unwrap();
+    while let Ok(event) = events.try_recv() {
+        info!("Order event: {event:?}");
+    }
+
+    // Keep applying stream updates until the order is done
+    while let Some(message) = receiver.recv().await {
+        manager.handle_message(&message);
+        while let Ok(event) = events.try_recv() {
+            info!("Order event: {event:?}");
+            if matches!(event, OrderEvent::Done { .. } | OrderEvent::Rejected { .. }) {
+                return;
+            }
+        }
+        if manager.open_orders().is_empty() {
+            return;
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::{thread::sleep, time::Duration};
 use alloy::signers::local::PrivateKeySigner;
 use hyperliquid_rust_sdk::{
     BaseUrl, BuilderInfo, ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest,
-    ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus,
+    ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, Tif,
 };
 use log::info;
 
@@ -27,9 +27,7 @@ async fn main() {
         limit_px: 1800.0,
         sz: 0.01,
         cloid: None,
-        order_type: ClientOrder::Limit(ClientLimit {
-            tif: "Gtc".to_string(),
-        }),
+        order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
     };
 
     let fee = 1u64;
@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+use hyperliquid_rust_sdk::{
+    BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, Exchange, InfoClient, Message,
+    PaperConfig, PaperExchange, Subscription, Tif,
+};
+use log::info;
+use tokio::sync::mpsc::unbounded_channel;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    let info_client = InfoClient::new(None, Some(BaseUrl::Mainnet)).await.unwrap();
+    let (sender, mut receiver) = unbounded_channel();
+    for subscription in [
+        Subscription::L2Book {
+            coin: "ETH".to_string(),
+        },
+        Subscription::Trades {
+            coin: "ETH".to_string(),
+        },
+    ] {
+        info_client
+            .subscribe(subscription, sender.clone())
+            .await
+            .unwrap();
+    }
+
+    let (fill_sender, mut fills) = unbounded_channel();
+    let exchange = PaperExchange::new(PaperConfig {
+        latency: Duration::from_millis(200),
+        ..PaperConfig::default()
+    })
+    .with_sender(fill_sender);
+
+    // Wait for a book, then rest a bid one tick below the best
+    let best_bid = loop {
+        let message = receiver.recv().await.unwrap();
+        exchange.handle_message(&message);
+        if let Message::L2Book(book) = message {
+            break book.data.levels[0][0].px.parse::<f64>().unwrap();
+        }
+    };
+    let response = exchange
+        .order(ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: best_bid - 0.1,
+            sz: 0.1,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        })
+        .await
+        .unwrap();
+    info!("Paper order: {response:?}");
+
+    while let Some(message) = receiver.recv().await {
+        exchange.handle_message(&message);
+        while let Ok(fill) = fills.try_recv() {
+            info!("Paper event: {fill:?}");
+        }
+        if exchange.open_orders().is_empty() {
+            info!("Position: {:?}", exchange.position("ETH"));
+            break;
+        }
+    }
+}
@@ -0,0 +1,113 @@
+/*
+Quotes a bid 10 bps under the ETH mid on testnet through a StrategyRuntime, requoting every 30
+seconds, until Ctrl-C, then cancels it. Orders pass the RiskEngine's checks on the way out.
+*/
+use std::{collections::HashMap, time::Duration};
+
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{
+    apply_bps, BaseUrl, ClientLimit, ClientOrder, ClientOrderRequest, Error, EventStrategy,
+    Exchange, ExchangeClient, InfoClient, L2BookData, OrderEvent, RiskEngine, RiskLimits,
+    StrategyContext, StrategyRuntime, Subscription, Tif, TradeInfo,
+};
+use log::info;
+
+#[derive(Default)]
+struct Bidder {
+    mid: Option<f64>,
+}
+
+impl EventStrategy for Bidder {
+    fn subscriptions(&self) -> Vec<Subscription> {
+        vec![
+            Subscription::AllMids,
+            Subscription::L2Book {
+                coin: "ETH".to_string(),
+            },
+        ]
+    }
+
+    async fn on_book<E: Exchange>(
+        &mut self,
+        book: &L2BookData,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> Result<(), Error> {
+        let best = |side: usize| -> Option<f64> { book.levels.get(side)?.first()?.px.parse().ok() };
+        self.mid = best(0).zip(best(1)).map(|(bid, ask)| (bid + ask) / 2.0);
+        Ok(())
+    }
+
+    async fn on_fill<E: Exchange>(
+        &mut self,
+        fill: &TradeInfo,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> Result<(), Error> {
+        info!("Filled {} {} at {}", fill.sz, fill.coin, fill.px);
+        Ok(())
+    }
+
+    async fn on_order_update<E: Exchange>(
+        &mut self,
+        event: &OrderEvent,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> Result<(), Error> {
+        info!("Order event: {event:?}");
+        Ok(())
+    }
+
+    async fn on_timer<E: Exchange>(
+        &mut self,
+        ctx: &mut StrategyContext<'_, E>,
+    ) -> Result<(), Error> {
+        let Some(mid) = self.mid else {
+            return Ok(());
+        };
+        let open: Vec<_> = ctx.orders().open_orders().iter().map(|o| o.cloid).collect();
+        if !open.is_empty() {
+            ctx.cancel(open).await?;
+        }
+        ctx.place(vec![ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: (apply_bps(mid, -10.0) * 10.0).round() / 10.0,
+            sz: 0.01,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Alo }),
+        }])
+        .await?;
+        Ok(())
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+    let address = wallet.address();
+    let exchange = ExchangeClient::new(None, wallet, Some(BaseUrl::Testnet), None, None)
+        .await
+        .unwrap();
+    let risk = RiskEngine::new(
+        exchange,
+        RiskLimits {
+            max_position: HashMap::from([("ETH".to_string(), 0.05)]),
+            price_collar_bps: Some(100.0),
+            ..RiskLimits::default()
+        },
+    );
+    let info = InfoClient::new(None, Some(BaseUrl::Testnet)).await.unwrap();
+
+    let mut runtime =
+        StrategyRuntime::new(address, Bidder::default()).with_timer(Duration::from_secs(30));
+    runtime
+        .run(&info, &risk, async {
+            tokio::signal::ctrl_c().await.unwrap();
+        })
+        .await
+        .unwrap();
+}
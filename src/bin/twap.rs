@@ -0,0 +1,64 @@
+/*
+Buys 0.05 ETH on testnet over five minutes in ten IOC slices and logs the slippage versus the
+arrival price.
+*/
+use std::time::Duration;
+
+use alloy::signers::local::PrivateKeySigner;
+use hyperliquid_rust_sdk::{
+    BaseUrl, ChildOrderStyle, ExecutionAlgo, ExecutionConfig, ExecutionSchedule, HyperliquidClient,
+    Strategy,
+};
+use log::info;
+use tokio::sync::mpsc::unbounded_channel;
+
+#[tokio::main]
+async fn main() {
+    env_logger::init();
+    // Key was randomly generated for testing and shouldn't be used with any real funds
+    let wallet: PrivateKeySigner =
+        "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+            .parse()
+            .unwrap();
+    let client = HyperliquidClient::builder(wallet)
+        .base_url(BaseUrl::Testnet)
+        .build()
+        .await
+        .unwrap();
+
+    let config = ExecutionConfig {
+        coin: "ETH".to_string(),
+        sz_decimals: 4,
+        is_buy: true,
+        sz: 0.05,
+        duration_ms: 5 * 60 * 1000,
+        schedule: ExecutionSchedule::Fixed { slices: 10 },
+        style: ChildOrderStyle::Ioc { slippage_bps: 10.0 },
+        limit_px: None,
+        max_participation: None,
+        reduce_only: false,
+    };
+    let mut algo = ExecutionAlgo::new(client.address(), config).unwrap();
+
+    let (sender, mut receiver) = unbounded_channel();
+    for subscription in algo.subscriptions() {
+        client
+            .subscribe(subscription, sender.clone())
+            .await
+            .unwrap();
+    }
+    while !algo.is_done() {
+        if let Ok(Some(message)) =
+            tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await
+        {
+            algo.on_message(&message, client.exchange()).await.unwrap();
+        }
+    }
+    let progress = algo.progress();
+    info!(
+        "Filled {} at {}, slippage {:?} bps",
+        progress.filled_sz,
+        progress.avg_px,
+        progress.slippage_bps(true)
+    );
+}
@@ -0,0 +1,273 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
+use reqwest::Client;
+use tokio::runtime::Runtime;
+
+use crate::{
+    blocking::{blocking_methods, new_runtime, InfoClient},
+    meta::Meta,
+    prelude::*,
+    BaseUrl, BuilderInfo, BulkRequestStatus, ClientCancelRequest, ClientCancelRequestCloid,
+    ClientModifyRequest, ClientOrderRequest, ExchangeResponseStatus, FinalizeEvmContractInput,
+    KeepWarm, MarketCloseParams, MarketOrderParams, PreparedOrder, SignedAction, TestnetAccount,
+    TestnetBootstrap, ValidatorProfile, ValidatorProfileChange,
+};
+
+/// Blocking counterpart of [`crate::ExchangeClient`].
+#[derive(Clone, Debug)]
+pub struct ExchangeClient {
+    inner: crate::ExchangeClient,
+    runtime: Arc<Runtime>,
+}
+
+impl ExchangeClient {
+    pub fn new(
+        client: Option<Client>,
+        wallet: PrivateKeySigner,
+        base_url: Option<BaseUrl>,
+        meta: Option<Meta>,
+        vault_address: Option<Address>,
+    ) -> Result<ExchangeClient> {
+        let runtime = new_runtime()?;
+        let inner = runtime.block_on(crate::ExchangeClient::new(
+            client,
+            wallet,
+            base_url,
+            meta,
+            vault_address,
+        ))?;
+        Ok(ExchangeClient { inner, runtime })
+    }
+
+    /// Wraps an already configured async client.
+    pub fn from_async(inner: crate::ExchangeClient) -> Result<ExchangeClient> {
+        Ok(ExchangeClient {
+            inner,
+            runtime: new_runtime()?,
+        })
+    }
+
+    pub fn inner(&self) -> &crate::ExchangeClient {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> crate::ExchangeClient {
+        self.inner
+    }
+
+    /// Info client sharing this client's HTTP configuration and runtime.
+    pub fn info_client(&self) -> InfoClient {
+        InfoClient::with_runtime(self.inner.info_client(), self.runtime.clone())
+    }
+
+    pub fn prepare_order(&self, order: ClientOrderRequest) -> Result<PreparedOrder> {
+        self.inner.prepare_order(order)
+    }
+
+    /// Warms the connection on this client's runtime until the handle is dropped.
+    pub fn keep_warm(&self, interval: Duration) -> KeepWarm {
+        let _runtime = self.runtime.enter();
+        self.inner.keep_warm(interval)
+    }
+
+    blocking_methods! {
+        fn enable_big_blocks(
+            &self,
+            using_big_blocks: bool,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn usdc_transfer(
+            &self,
+            amount: &str,
+            destination: &str,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn class_transfer(
+            &self,
+            usdc: f64,
+            to_perp: bool,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn vault_transfer(
+            &self,
+            is_deposit: bool,
+            usd: u64,
+            vault_address: Option<Address>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn sub_account_transfer(
+            &self,
+            sub_account_user: Address,
+            is_deposit: bool,
+            usd: u64,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn market_open(&self, params: MarketOrderParams<'_>) -> ExchangeResponseStatus;
+        fn market_open_with_builder(
+            &self,
+            params: MarketOrderParams<'_>,
+            builder: BuilderInfo
+        ) -> ExchangeResponseStatus;
+        fn market_close(&self, params: MarketCloseParams<'_>) -> ExchangeResponseStatus;
+        fn order(
+            &self,
+            order: ClientOrderRequest,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn order_with_builder(
+            &self,
+            order: ClientOrderRequest,
+            wallet: Option<&PrivateKeySigner>,
+            builder: BuilderInfo
+        ) -> ExchangeResponseStatus;
+        fn bulk_order(
+            &self,
+            orders: Vec<ClientOrderRequest>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_order_with_builder(
+            &self,
+            orders: Vec<ClientOrderRequest>,
+            wallet: Option<&PrivateKeySigner>,
+            builder: BuilderInfo
+        ) -> ExchangeResponseStatus;
+        fn bulk_order_with_statuses(
+            &self,
+            orders: Vec<ClientOrderRequest>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> Vec<BulkRequestStatus<ClientOrderRequest>>;
+        fn submit_prepared(
+            &self,
+            prepared: &PreparedOrder,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn warm_connection(&self) -> ();
+        fn bootstrap_testnet(&self, config: TestnetBootstrap) -> TestnetAccount;
+        fn cancel(
+            &self,
+            cancel: ClientCancelRequest,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_cancel(
+            &self,
+            cancels: Vec<ClientCancelRequest>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_cancel_with_statuses(
+            &self,
+            cancels: Vec<ClientCancelRequest>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> Vec<BulkRequestStatus<ClientCancelRequest>>;
+        fn modify(
+            &self,
+            modify: ClientModifyRequest,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_modify(
+            &self,
+            modifies: Vec<ClientModifyRequest>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_modify_with_statuses(
+            &self,
+            modifies: Vec<ClientModifyRequest>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> Vec<BulkRequestStatus<ClientModifyRequest>>;
+        fn cancel_by_cloid(
+            &self,
+            cancel: ClientCancelRequestCloid,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_cancel_by_cloid(
+            &self,
+            cancels: Vec<ClientCancelRequestCloid>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn bulk_cancel_by_cloid_with_statuses(
+            &self,
+            cancels: Vec<ClientCancelRequestCloid>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> Vec<BulkRequestStatus<ClientCancelRequestCloid>>;
+        fn update_leverage(
+            &self,
+            leverage: u32,
+            coin: &str,
+            is_cross: bool,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn update_isolated_margin(
+            &self,
+            amount: f64,
+            coin: &str,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn approve_agent(
+            &self,
+            wallet: Option<&PrivateKeySigner>
+        ) -> (B256, ExchangeResponseStatus);
+        fn withdraw_from_bridge(
+            &self,
+            amount: &str,
+            destination: &str,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn spot_transfer(
+            &self,
+            amount: &str,
+            destination: &str,
+            token: &str,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn request_evm_contract(
+            &self,
+            token: u32,
+            address: Address,
+            evm_extra_wei_decimals: i32,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn finalize_evm_contract(
+            &self,
+            token: u32,
+            input: FinalizeEvmContractInput,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn set_referrer(
+            &self,
+            code: String,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn approve_builder_fee(
+            &self,
+            builder: Address,
+            max_fee_rate: String,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn schedule_cancel(
+            &self,
+            time: Option<u64>,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn claim_rewards(&self, wallet: Option<&PrivateKeySigner>) -> ExchangeResponseStatus;
+        fn noop(&self, nonce: u64, wallet: Option<&PrivateKeySigner>) -> ExchangeResponseStatus;
+        fn send_signed(&self, signed: &SignedAction) -> ExchangeResponseStatus;
+        fn validator_register(
+            &self,
+            profile: ValidatorProfile,
+            unjailed: bool,
+            initial_wei: u64,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn validator_change_profile(
+            &self,
+            change: ValidatorProfileChange,
+            wallet: Option<&PrivateKeySigner>
+        ) -> ExchangeResponseStatus;
+        fn validator_unregister(&self, wallet: Option<&PrivateKeySigner>) -> ExchangeResponseStatus;
+        fn signer_jail_self(&self, wallet: Option<&PrivateKeySigner>) -> ExchangeResponseStatus;
+        fn signer_unjail_self(&self, wallet: Option<&PrivateKeySigner>) -> ExchangeResponseStatus;
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::primitives::Address;
+use reqwest::Client;
+use tokio::runtime::Runtime;
+#[cfg(feature = "ws")]
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    blocking::{blocking_methods, new_runtime},
+    info::{
+        ActiveAssetDataResponse, CandlesSnapshotResponse, FundingHistoryResponse,
+        L2SnapshotResponse, OpenOrdersResponse, OrderInfo, RecentTradesResponse, UserFillsResponse,
+        UserStateResponse, VenueFundings,
+    },
+    meta::{AssetContext, Meta, SpotMeta, SpotMetaAndAssetCtxs},
+    prelude::*,
+    BaseUrl, LedgerUpdateData, OrderStatusResponse, ReferralResponse, Timestamp, UserFeesResponse,
+    UserFundingResponse, UserRateLimitResponse, UserTokenBalanceResponse,
+};
+#[cfg(feature = "ws")]
+use crate::{Message, Subscription};
+
+/// Blocking counterpart of [`crate::InfoClient`].
+///
+/// Subscriptions keep being served in the background; read them with
+/// `UnboundedReceiver::blocking_recv`.
+#[derive(Clone, Debug)]
+pub struct InfoClient {
+    inner: crate::InfoClient,
+    runtime: Arc<Runtime>,
+}
+
+impl InfoClient {
+    pub fn new(client: Option<Client>, base_url: Option<BaseUrl>) -> Result<InfoClient> {
+        let runtime = new_runtime()?;
+        let inner = runtime.block_on(crate::InfoClient::new(client, base_url))?;
+        Ok(InfoClient { inner, runtime })
+    }
+
+    pub fn with_reconnect(client: Option<Client>, base_url: Option<BaseUrl>) -> Result<InfoClient> {
+        let runtime = new_runtime()?;
+        let inner = runtime.block_on(crate::InfoClient::with_reconnect(client, base_url))?;
+        Ok(InfoClient { inner, runtime })
+    }
+
+    /// Wraps an already configured async client.
+    pub fn from_async(inner: crate::InfoClient) -> Result<InfoClient> {
+        Ok(InfoClient {
+            inner,
+            runtime: new_runtime()?,
+        })
+    }
+
+    #[cfg_attr(not(feature = "exchange"), allow(dead_code))]
+    pub(crate) fn with_runtime(inner: crate::InfoClient, runtime: Arc<Runtime>) -> InfoClient {
+        InfoClient { inner, runtime }
+    }
+
+    pub fn inner(&self) -> &crate::InfoClient {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> crate::InfoClient {
+        self.inner
+    }
+
+    #[cfg(feature = "ws")]
+    pub fn subscribe(
+        &self,
+        subscription: Subscription,
+        sender_channel: UnboundedSender<Message>,
+    ) -> Result<u32> {
+        self.runtime
+            .block_on(self.inner.subscribe(subscription, sender_channel))
+    }
+
+    #[cfg(feature = "ws")]
+    pub fn subscribe_and_snapshot(
+        &self,
+        subscription: Subscription,
+        sender_channel: UnboundedSender<Message>,
+        timeout: std::time::Duration,
+    ) -> Result<(u32, Message)> {
+        self.runtime.block_on(self.inner.subscribe_and_snapshot(
+            subscription,
+            sender_channel,
+            timeout,
+        ))
+    }
+
+    #[cfg(feature = "ws")]
+    pub fn unsubscribe(&self, subscription_id: u32) -> Result<()> {
+        self.runtime
+            .block_on(self.inner.unsubscribe(subscription_id))
+    }
+
+    blocking_methods! {
+        fn open_orders(&self, address: Address) -> Vec<OpenOrdersResponse>;
+        fn user_state(&self, address: Address) -> UserStateResponse;
+        fn user_states(&self, addresses: Vec<Address>) -> Vec<UserStateResponse>;
+        fn user_token_balances(&self, address: Address) -> UserTokenBalanceResponse;
+        fn user_fees(&self, address: Address) -> UserFeesResponse;
+        fn meta(&self) -> Meta;
+        fn meta_and_asset_contexts(&self) -> (Meta, Vec<AssetContext>);
+        fn spot_meta(&self) -> SpotMeta;
+        fn spot_meta_and_asset_contexts(&self) -> Vec<SpotMetaAndAssetCtxs>;
+        fn all_mids(&self) -> HashMap<String, String>;
+        fn user_fills(&self, address: Address) -> Vec<UserFillsResponse>;
+        fn user_fills_by_time(
+            &self,
+            user: Address,
+            start_time: Timestamp,
+            end_time: Option<Timestamp>
+        ) -> Vec<UserFillsResponse>;
+        fn funding_history(
+            &self,
+            coin: String,
+            start_time: Timestamp,
+            end_time: Option<Timestamp>
+        ) -> Vec<FundingHistoryResponse>;
+        fn user_funding_history(
+            &self,
+            user: Address,
+            start_time: Timestamp,
+            end_time: Option<Timestamp>
+        ) -> Vec<UserFundingResponse>;
+        fn user_non_funding_ledger_updates(
+            &self,
+            user: Address,
+            start_time: Timestamp,
+            end_time: Option<Timestamp>
+        ) -> Vec<LedgerUpdateData>;
+        fn predicted_fundings(&self) -> Vec<(String, VenueFundings)>;
+        fn recent_trades(&self, coin: String) -> Vec<RecentTradesResponse>;
+        fn l2_snapshot(&self, coin: String) -> L2SnapshotResponse;
+        fn candles_snapshot(
+            &self,
+            coin: String,
+            interval: String,
+            start_time: Timestamp,
+            end_time: Timestamp
+        ) -> Vec<CandlesSnapshotResponse>;
+        fn query_order_by_oid(&self, address: Address, oid: u64) -> OrderStatusResponse;
+        fn query_referral_state(&self, address: Address) -> ReferralResponse;
+        fn historical_orders(&self, address: Address) -> Vec<OrderInfo>;
+        fn active_asset_data(&self, user: Address, coin: String) -> ActiveAssetDataResponse;
+        fn user_rate_limit(&self, user: Address) -> UserRateLimitResponse;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_blocking_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = BaseUrl::custom(format!("http://{}", listener.local_addr().unwrap()));
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).unwrap();
+            let body = r#"{"BTC":"65000.5"}"#;
+            write!(
+                socket,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+        });
+
+        let info_client = InfoClient::new(None, Some(base_url)).unwrap();
+        let mids = info_client.all_mids().unwrap();
+        assert_eq!(mids["BTC"], "65000.5");
+    }
+}
@@ -0,0 +1,39 @@
+//! Synchronous wrappers around the async clients, for scripts and codebases that do not use
+//! tokio. Each client owns (or shares) a small runtime and blocks on it for every call, so these
+//! types must not be used from within an async context.
+
+#[cfg(feature = "exchange")]
+mod exchange_client;
+mod info_client;
+
+use std::sync::Arc;
+
+use tokio::runtime::Runtime;
+
+#[cfg(feature = "exchange")]
+pub use exchange_client::ExchangeClient;
+pub use info_client::InfoClient;
+
+use crate::{prelude::*, Error};
+
+/// Generates blocking versions of async methods on `self.inner`.
+macro_rules! blocking_methods {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            pub fn $name(&self $(, $arg: $ty)*) -> Result<$ret> {
+                self.runtime.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+pub(crate) use blocking_methods;
+
+/// Runtime driving requests and, for subscriptions, the websocket reader in the background.
+pub(crate) fn new_runtime() -> Result<Arc<Runtime>> {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .map(Arc::new)
+        .map_err(|e| Error::GenericRequest(format!("Could not start runtime: {e}")))
+}
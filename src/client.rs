@@ -0,0 +1,188 @@
+use std::{fmt, sync::Arc};
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+#[cfg(feature = "ws")]
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    exchange::coin_to_asset,
+    prelude::*,
+    req::{http_options_setters, HttpOptions, RateLimitMode, RateLimiter},
+    secrets::WalletSource,
+    CircuitBreaker, ExchangeClient, InfoClient, LatencyHook, Meta, SecretsProvider, SpotMeta,
+};
+#[cfg(feature = "ws")]
+use crate::{Message, Subscription};
+
+/// Info, exchange and websocket access for one account, sharing a single HTTP client, rate
+/// limiter, throttle and metadata cache.
+/// Cloning is cheap, and clones share connections and metadata until `refresh_meta` gives
+/// the refreshed client its own.
+#[derive(Clone, Debug)]
+pub struct HyperliquidClient {
+    info: InfoClient,
+    exchange: ExchangeClient,
+    spot_meta: Arc<SpotMeta>,
+}
+
+impl HyperliquidClient {
+    pub fn builder(wallet: PrivateKeySigner) -> HyperliquidClientBuilder {
+        HyperliquidClientBuilder::new(WalletSource::Key(wallet))
+    }
+
+    /// A builder signing with the private key stored under `name` in `provider`, fetched by
+    /// `build`.
+    pub fn builder_with_secret(
+        provider: Arc<dyn SecretsProvider>,
+        name: &str,
+    ) -> HyperliquidClientBuilder {
+        HyperliquidClientBuilder::new(WalletSource::Secret(provider, name.to_string()))
+    }
+
+    pub fn info(&self) -> &InfoClient {
+        &self.info
+    }
+
+    pub fn exchange(&self) -> &ExchangeClient {
+        &self.exchange
+    }
+
+    pub fn meta(&self) -> &Meta {
+        &self.exchange.meta
+    }
+
+    pub fn spot_meta(&self) -> &SpotMeta {
+        &self.spot_meta
+    }
+
+    /// Address whose account is traded, the vault if one is configured.
+    pub fn address(&self) -> Address {
+        self.exchange
+            .vault_address
+            .unwrap_or(self.exchange.wallet.address())
+    }
+
+    /// Refetches perp and spot metadata, picking up newly listed assets.
+    pub async fn refresh_meta(&mut self) -> Result<()> {
+        let meta = self.info.meta().await?;
+        let spot_meta = self.info.spot_meta().await?;
+        self.exchange.coin_to_asset = Arc::new(coin_to_asset(&meta, &spot_meta));
+        self.exchange.meta = Arc::new(meta);
+        self.spot_meta = Arc::new(spot_meta);
+        Ok(())
+    }
+
+    #[cfg(feature = "ws")]
+    pub async fn subscribe(
+        &self,
+        subscription: Subscription,
+        sender_channel: UnboundedSender<Message>,
+    ) -> Result<u32> {
+        self.info.subscribe(subscription, sender_channel).await
+    }
+
+    #[cfg(feature = "ws")]
+    pub async fn unsubscribe(&self, subscription_id: u32) -> Result<()> {
+        self.info.unsubscribe(subscription_id).await
+    }
+}
+
+pub struct HyperliquidClientBuilder {
+    wallet: WalletSource,
+    http: HttpOptions,
+    vault_address: Option<Address>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    latency_hook: Option<LatencyHook>,
+    #[cfg(feature = "meta-cache")]
+    meta_cache: Option<crate::MetaCache>,
+    reconnect: bool,
+}
+
+impl fmt::Debug for HyperliquidClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HyperliquidClientBuilder")
+            .field("signer", &self.wallet)
+            .field("vault_address", &self.vault_address)
+            .field("reconnect", &self.reconnect)
+            .finish_non_exhaustive()
+    }
+}
+
+impl HyperliquidClientBuilder {
+    fn new(wallet: WalletSource) -> HyperliquidClientBuilder {
+        HyperliquidClientBuilder {
+            wallet,
+            http: HttpOptions::default(),
+            vault_address: None,
+            circuit_breaker: None,
+            latency_hook: None,
+            #[cfg(feature = "meta-cache")]
+            meta_cache: None,
+            reconnect: false,
+        }
+    }
+
+    // Without an explicit `rate_limiter`, each `HyperliquidClient` gets its own in
+    // `RateLimitMode::Delay`.
+    http_options_setters!();
+
+    pub fn vault_address(mut self, vault_address: Address) -> Self {
+        self.vault_address = Some(vault_address);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// See `ExchangeClient::with_latency_hook`.
+    pub fn latency_hook(mut self, hook: LatencyHook) -> Self {
+        self.latency_hook = Some(hook);
+        self
+    }
+
+    /// See `ExchangeClientBuilder::meta_cache`.
+    #[cfg(feature = "meta-cache")]
+    pub fn meta_cache(mut self, cache: crate::MetaCache) -> Self {
+        self.meta_cache = Some(cache);
+        self
+    }
+
+    /// Reconnect websocket subscriptions after disconnects.
+    pub fn reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Fetches the key if it comes from a secrets provider, connects and loads perp and spot
+    /// metadata.
+    pub async fn build(mut self) -> Result<HyperliquidClient> {
+        self.http
+            .rate_limiter
+            .get_or_insert_with(|| Arc::new(RateLimiter::new(RateLimitMode::Delay)));
+        let wallet = self.wallet.load().await?;
+        let ws_url = self.http.ws_url();
+        let http_client = self.http.build()?;
+
+        let info =
+            InfoClient::with_http_client(http_client.clone(), self.reconnect).with_ws_url(ws_url);
+        #[cfg(feature = "meta-cache")]
+        let (meta, spot_meta) = match &self.meta_cache {
+            Some(cache) => cache.load_or_fetch(&info).await?,
+            None => (info.meta().await?, info.spot_meta().await?),
+        };
+        #[cfg(not(feature = "meta-cache"))]
+        let (meta, spot_meta) = (info.meta().await?, info.spot_meta().await?);
+        let mut exchange =
+            ExchangeClient::from_parts(http_client, wallet, meta, &spot_meta, self.vault_address);
+        exchange.circuit_breaker = self.circuit_breaker;
+        exchange.latency_hook = self.latency_hook;
+
+        Ok(HyperliquidClient {
+            info,
+            exchange,
+            spot_meta: Arc::new(spot_meta),
+        })
+    }
+}
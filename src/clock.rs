@@ -0,0 +1,222 @@
+//! Wall-clock time and timers, from the system or from a [`SimClock`] that tests and
+//! simulations move forward themselves.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, OnceLock},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use chrono::Utc;
+
+use crate::{prelude::*, Error};
+
+/// A timer from [`Clock::sleep`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+/// A timer from [`Clock::sleep`]. Browser timers are not `Send`.
+#[cfg(target_arch = "wasm32")]
+pub type SleepFuture = Pin<Box<dyn Future<Output = ()> + 'static>>;
+
+/// Source of the current time and of timers. Nonces, timestamps, retry and reconnect delays
+/// and other timers of the SDK use the clock installed with [`set_clock`], the system clock
+/// unless one is; the strategy runtime and candle aggregation can also be given their own.
+pub trait Clock: fmt::Debug + Send + Sync + 'static {
+    /// Milliseconds since the Unix epoch.
+    fn now_ms(&self) -> u64;
+
+    /// Completes once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> SleepFuture;
+}
+
+/// The system clock, with timers from the installed [`Runtime`](crate::Runtime).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        Utc::now().timestamp_millis() as u64
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        Box::pin(crate::rt::runtime_sleep(duration))
+    }
+}
+
+static CLOCK: OnceLock<Arc<dyn Clock>> = OnceLock::new();
+
+/// Installs the clock used for the rest of the process, such as a [`SimClock`] driving a
+/// simulation. Fails if a clock was already installed.
+pub fn set_clock(clock: impl Clock) -> Result<()> {
+    CLOCK
+        .set(Arc::new(clock))
+        .map_err(|_| Error::ClockAlreadySet)
+}
+
+/// The clock installed with [`set_clock`], or the system clock.
+pub fn clock() -> Arc<dyn Clock> {
+    CLOCK
+        .get()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(SystemClock))
+}
+
+pub(crate) fn installed() -> Option<&'static Arc<dyn Clock>> {
+    CLOCK.get()
+}
+
+#[derive(Debug, Default)]
+struct SimState {
+    now: u64,
+    auto_advance: bool,
+    /// Deadlines and wakers of pending timers, by timer id
+    timers: HashMap<u64, (u64, Waker)>,
+    next_timer_id: u64,
+}
+
+/// A clock that only moves when told to, for deterministic tests of time-dependent logic.
+///
+/// Timers complete once `advance` or `set` moves the clock past their deadline. With
+/// `with_auto_advance`, sleeping moves the clock to the end of the sleep and completes at
+/// once instead, so a simulation runs as fast as it can be computed. Clones share the time.
+#[derive(Clone, Debug, Default)]
+pub struct SimClock {
+    state: Arc<Mutex<SimState>>,
+}
+
+impl SimClock {
+    /// Starts at `now_ms`, in milliseconds since the Unix epoch.
+    pub fn new(now_ms: u64) -> SimClock {
+        let clock = SimClock::default();
+        clock.state().now = now_ms;
+        clock
+    }
+
+    pub fn with_auto_advance(self) -> Self {
+        self.state().auto_advance = true;
+        self
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, SimState> {
+        self.state.lock().expect("sim clock lock poisoned")
+    }
+
+    /// Moves the clock forward by `duration`, completing the timers it passes.
+    pub fn advance(&self, duration: Duration) {
+        let now = self.now_ms() + duration.as_millis() as u64;
+        self.set(now);
+    }
+
+    /// Moves the clock to `now_ms`, completing the timers it passes. The clock never goes back.
+    pub fn set(&self, now_ms: u64) {
+        let mut state = self.state();
+        state.now = state.now.max(now_ms);
+        let now = state.now;
+        let (due, pending) = std::mem::take(&mut state.timers)
+            .into_iter()
+            .partition::<HashMap<_, _>, _>(|(_, (deadline, _))| *deadline <= now);
+        state.timers = pending;
+        drop(state);
+        due.into_values().for_each(|(_, waker)| waker.wake());
+    }
+
+    /// Timers waiting for the clock to move.
+    pub fn pending_timers(&self) -> usize {
+        self.state().timers.len()
+    }
+}
+
+impl Clock for SimClock {
+    fn now_ms(&self) -> u64 {
+        self.state().now
+    }
+
+    fn sleep(&self, duration: Duration) -> SleepFuture {
+        let deadline = self.now_ms() + duration.as_millis() as u64;
+        if self.state().auto_advance {
+            self.set(deadline);
+        }
+        let id = {
+            let mut state = self.state();
+            state.next_timer_id += 1;
+            state.next_timer_id
+        };
+        Box::pin(SimSleep {
+            clock: self.clone(),
+            id,
+            deadline,
+        })
+    }
+}
+
+struct SimSleep {
+    clock: SimClock,
+    id: u64,
+    deadline: u64,
+}
+
+impl Future for SimSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.clock.state();
+        if state.now >= self.deadline {
+            return Poll::Ready(());
+        }
+        // Re-polling replaces the waker rather than registering the timer again
+        state
+            .timers
+            .insert(self.id, (self.deadline, cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for SimSleep {
+    fn drop(&mut self) {
+        self.clock.state().timers.remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sim_clock_timers() {
+        let clock = SimClock::new(1_000);
+        let timer = tokio::spawn(clock.sleep(Duration::from_secs(10)));
+        tokio::task::yield_now().await;
+        assert_eq!(clock.pending_timers(), 1);
+
+        clock.advance(Duration::from_secs(5));
+        tokio::task::yield_now().await;
+        assert!(!timer.is_finished());
+        clock.set(11_000);
+        timer.await.unwrap();
+        assert_eq!(clock.now_ms(), 11_000);
+        // Time never goes back
+        clock.set(0);
+        assert_eq!(clock.now_ms(), 11_000);
+
+        let fast = SimClock::new(0).with_auto_advance();
+        fast.sleep(Duration::from_secs(3600)).await;
+        assert_eq!(fast.now_ms(), 3_600_000);
+    }
+
+    #[test]
+    fn test_sim_clock_repoll_keeps_one_timer() {
+        let clock = SimClock::new(0);
+        let mut sleep = clock.sleep(Duration::from_secs(1));
+        let mut cx = Context::from_waker(Waker::noop());
+        for _ in 0..3 {
+            assert!(sleep.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(clock.pending_timers(), 1);
+        drop(sleep);
+        assert_eq!(clock.pending_timers(), 0);
+    }
+}
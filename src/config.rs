@@ -0,0 +1,462 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+#[cfg(feature = "exchange")]
+use alloy::signers::local::PrivateKeySigner;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+
+#[cfg(feature = "exchange")]
+use crate::ExchangeClientBuilder;
+#[cfg(all(feature = "exchange", feature = "ws"))]
+use crate::MarketMakerInput;
+use crate::{
+    prelude::*, BaseUrl, Error, InfoClient, InfoClientBuilder, RateLimitMode, RateLimiter,
+    RetryPolicy, Timeouts, MAINNET_API_URL, TESTNET_API_URL,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+    Localhost,
+}
+
+/// Where a private key is read from, `{ env = "NAME" }` or `{ file = "PATH" }`. Keys are never
+/// written in the config itself.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum KeySource {
+    /// Environment variable holding the hex key
+    Env { env: String },
+    /// File holding the hex key
+    File { file: PathBuf },
+}
+
+impl KeySource {
+    #[cfg(feature = "exchange")]
+    pub fn load(&self) -> Result<PrivateKeySigner> {
+        let key = match self {
+            KeySource::Env { env } => std::env::var(env)
+                .map_err(|_| Error::InvalidConfig(format!("environment variable {env} not set")))?,
+            KeySource::File { file } => {
+                std::fs::read_to_string(file).map_err(|e| Error::Io(e.to_string()))?
+            }
+        };
+        key.trim()
+            .parse()
+            .map_err(|e| Error::PrivateKeyParse(format!("{e}")))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Whether to wait for budget or fail with `Error::RateLimited`
+    #[serde(default)]
+    pub reject: bool,
+    /// IP weight per minute, the exchange's limit by default
+    pub weight_per_minute: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Attempts per request including the first, no retries by default
+    pub max_attempts: Option<u32>,
+    pub connect_timeout_ms: Option<u64>,
+    pub request_timeout_ms: Option<u64>,
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+/// Parameters of a `MarketMaker`, see `MarketMakerInput`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketMakerConfig {
+    pub asset: String,
+    /// Name of the key in `keys`
+    pub key: String,
+    pub target_liquidity: f64,
+    /// In bps
+    pub half_spread: u16,
+    /// In bps
+    pub max_bps_diff: u16,
+    pub max_absolute_position_size: f64,
+    pub decimals: u32,
+}
+
+/// Parameters of a user strategy, shared across its assets with per-asset overrides.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+    /// Name of the key in `keys`
+    pub key: Option<String>,
+    #[serde(default)]
+    pub params: BTreeMap<String, Value>,
+    /// Parameters by coin, replacing those in `params`
+    #[serde(default)]
+    pub assets: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl StrategyConfig {
+    /// `params` as the strategy's own type.
+    pub fn params<T: DeserializeOwned>(&self) -> Result<T> {
+        parse_params(&self.params)
+    }
+
+    /// `params` with the overrides for `coin` applied.
+    pub fn asset_params<T: DeserializeOwned>(&self, coin: &str) -> Result<T> {
+        let mut params = self.params.clone();
+        if let Some(overrides) = self.assets.get(coin) {
+            params.extend(overrides.clone());
+        }
+        parse_params(&params)
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: &BTreeMap<String, Value>) -> Result<T> {
+    serde_json::from_value(Value::Object(params.clone().into_iter().collect()))
+        .map_err(|e| Error::InvalidConfig(format!("strategy params: {e}")))
+}
+
+/// Deployment settings for clients and strategies, loaded from TOML or YAML.
+///
+/// ```toml
+/// network = "testnet"
+///
+/// [keys.main]
+/// env = "HL_PRIVATE_KEY"
+///
+/// [http]
+/// max_attempts = 3
+/// rate_limit = { weight_per_minute = 600 }
+///
+/// [[market_makers]]
+/// asset = "ETH"
+/// key = "main"
+/// target_liquidity = 0.25
+/// half_spread = 1
+/// max_bps_diff = 2
+/// max_absolute_position_size = 0.5
+/// decimals = 1
+///
+/// [strategies.grid]
+/// key = "main"
+/// params = { levels = 5, spacing_bps = 10.0 }
+/// assets.BTC = { levels = 3 }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SdkConfig {
+    #[serde(default)]
+    pub network: Network,
+    /// Self-hosted node or proxy, replacing `network`'s URL
+    pub api_url: Option<String>,
+    pub ws_url: Option<String>,
+    pub vault_address: Option<Address>,
+    /// Key sources by name
+    #[serde(default)]
+    pub keys: BTreeMap<String, KeySource>,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub market_makers: Vec<MarketMakerConfig>,
+    #[serde(default)]
+    pub strategies: BTreeMap<String, StrategyConfig>,
+}
+
+impl SdkConfig {
+    /// Loads and validates a `.toml`, `.yaml` or `.yml` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<SdkConfig> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => SdkConfig::from_toml(&text),
+            Some("yaml" | "yml") => SdkConfig::from_yaml(&text),
+            _ => Err(Error::InvalidConfig(format!(
+                "{} is not a .toml or .yaml file",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn from_toml(text: &str) -> Result<SdkConfig> {
+        let config: SdkConfig =
+            toml::from_str(text).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_yaml(text: &str) -> Result<SdkConfig> {
+        let config: SdkConfig =
+            serde_yaml_ng::from_str(text).map_err(|e| Error::InvalidConfig(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks references between sections and parameter ranges, reporting every problem.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        for (url, name) in [(&self.api_url, "api_url"), (&self.ws_url, "ws_url")] {
+            if let Some(url) = url {
+                if reqwest::Url::parse(url).is_err() {
+                    problems.push(format!("{name} {url} is not a URL"));
+                }
+            }
+        }
+        if let Some(api_url) = &self.api_url {
+            for (public_url, network) in [
+                (MAINNET_API_URL, Network::Mainnet),
+                (TESTNET_API_URL, Network::Testnet),
+            ] {
+                if api_url.trim_end_matches('/') == public_url && self.network != network {
+                    problems.push(format!(
+                        "api_url {api_url} is the {network:?} API but network is {:?}",
+                        self.network
+                    ));
+                }
+            }
+        }
+        if self.http.max_attempts == Some(0) {
+            problems.push("http.max_attempts must be at least 1".to_string());
+        }
+        if self
+            .http
+            .rate_limit
+            .and_then(|limit| limit.weight_per_minute)
+            == Some(0)
+        {
+            problems.push("http.rate_limit.weight_per_minute must be positive".to_string());
+        }
+
+        let mut assets = HashSet::new();
+        for (index, mm) in self.market_makers.iter().enumerate() {
+            let name = format!("market_makers[{index}] ({})", mm.asset);
+            if !self.keys.contains_key(&mm.key) {
+                problems.push(format!("{name} uses unknown key {}", mm.key));
+            }
+            if !assets.insert((&mm.asset, &mm.key)) {
+                problems.push(format!("{name} is configured twice for key {}", mm.key));
+            }
+            if mm.target_liquidity <= 0.0 || mm.max_absolute_position_size <= 0.0 {
+                problems.push(format!(
+                    "{name} needs positive target_liquidity and max_absolute_position_size"
+                ));
+            }
+            if mm.half_spread == 0 {
+                problems.push(format!("{name} needs a positive half_spread"));
+            }
+        }
+        for (name, strategy) in &self.strategies {
+            if let Some(key) = strategy
+                .key
+                .as_ref()
+                .filter(|key| !self.keys.contains_key(*key))
+            {
+                problems.push(format!("strategies.{name} uses unknown key {key}"));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidConfig(problems.join("; ")))
+        }
+    }
+
+    pub fn base_url(&self) -> BaseUrl {
+        match (&self.api_url, self.network) {
+            (Some(url), _) => BaseUrl::custom(url.as_str()),
+            (None, Network::Mainnet) => BaseUrl::Mainnet,
+            (None, Network::Testnet) => BaseUrl::Testnet,
+            (None, Network::Localhost) => BaseUrl::Localhost,
+        }
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.http
+            .max_attempts
+            .map(RetryPolicy::exponential)
+            .unwrap_or_default()
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            connect: self.http.connect_timeout_ms.map(Duration::from_millis),
+            request: self.http.request_timeout_ms.map(Duration::from_millis),
+        }
+    }
+
+    /// A new limiter, to be shared by every client of the deployment.
+    pub fn rate_limiter(&self) -> Option<Arc<RateLimiter>> {
+        self.http.rate_limit.map(|limit| {
+            let mode = if limit.reject {
+                RateLimitMode::Reject
+            } else {
+                RateLimitMode::Delay
+            };
+            Arc::new(match limit.weight_per_minute {
+                Some(weight) => RateLimiter::with_ip_limit(mode, weight, Duration::from_secs(60)),
+                None => RateLimiter::new(mode),
+            })
+        })
+    }
+
+    /// An `InfoClient` builder with the network and HTTP settings applied.
+    pub fn info_client_builder(&self) -> InfoClientBuilder {
+        let mut builder = InfoClient::builder()
+            .base_url(self.base_url())
+            .retry_policy(self.retry_policy())
+            .timeouts(self.timeouts());
+        if let Some(rate_limiter) = self.rate_limiter() {
+            builder = builder.rate_limiter(rate_limiter);
+        }
+        if let Some(ws_url) = &self.ws_url {
+            builder = builder.ws_url(ws_url);
+        }
+        builder
+    }
+
+    #[cfg(feature = "exchange")]
+    pub fn wallet(&self, key: &str) -> Result<PrivateKeySigner> {
+        self.keys
+            .get(key)
+            .ok_or_else(|| Error::InvalidConfig(format!("unknown key {key}")))?
+            .load()
+    }
+
+    /// An `ExchangeClient` builder signing with `key`, with the network, vault and HTTP
+    /// settings applied. Actions are signed for `network`, also behind a custom `api_url`.
+    #[cfg(feature = "exchange")]
+    pub fn exchange_client_builder(&self, key: &str) -> Result<ExchangeClientBuilder> {
+        let mut builder = crate::ExchangeClient::builder()
+            .wallet(self.wallet(key)?)
+            .base_url(self.base_url())
+            .mainnet(self.network == Network::Mainnet)
+            .retry_policy(self.retry_policy())
+            .timeouts(self.timeouts());
+        if let Some(rate_limiter) = self.rate_limiter() {
+            builder = builder.rate_limiter(rate_limiter);
+        }
+        if let Some(vault_address) = self.vault_address {
+            builder = builder.vault_address(vault_address);
+        }
+        Ok(builder)
+    }
+
+    /// The `MarketMakerInput` of the market maker configured for `asset`, with its key loaded.
+    #[cfg(all(feature = "exchange", feature = "ws"))]
+    pub fn market_maker_input(&self, asset: &str) -> Result<MarketMakerInput> {
+        let mm = self
+            .market_makers
+            .iter()
+            .find(|mm| mm.asset == asset)
+            .ok_or_else(|| Error::InvalidConfig(format!("no market maker for {asset}")))?;
+        Ok(MarketMakerInput {
+            asset: mm.asset.clone(),
+            target_liquidity: mm.target_liquidity,
+            half_spread: mm.half_spread,
+            max_bps_diff: mm.max_bps_diff,
+            max_absolute_position_size: mm.max_absolute_position_size,
+            decimals: mm.decimals,
+            wallet: self.wallet(&mm.key)?,
+        })
+    }
+
+    pub fn strategy(&self, name: &str) -> Result<&StrategyConfig> {
+        self.strategies
+            .get(name)
+            .ok_or_else(|| Error::InvalidConfig(format!("no strategy {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+network = "testnet"
+
+[keys.main]
+env = "HL_CONFIG_TEST_KEY"
+
+[http]
+max_attempts = 3
+rate_limit = { weight_per_minute = 600, reject = true }
+
+[[market_makers]]
+asset = "ETH"
+key = "main"
+target_liquidity = 0.25
+half_spread = 1
+max_bps_diff = 2
+max_absolute_position_size = 0.5
+decimals = 1
+
+[strategies.grid]
+key = "main"
+params = { levels = 5, spacing_bps = 10.0 }
+assets.BTC = { levels = 3 }
+"#;
+
+    #[derive(Deserialize)]
+    struct Grid {
+        levels: u32,
+        spacing_bps: f64,
+    }
+
+    #[test]
+    fn test_load_and_validate() {
+        let config = SdkConfig::from_toml(TOML).unwrap();
+        assert_eq!(config.base_url().get_url(), BaseUrl::Testnet.get_url());
+        assert_eq!(config.retry_policy().max_attempts, 3);
+        assert_eq!(config.rate_limiter().unwrap().mode(), RateLimitMode::Reject);
+        let grid = config.strategy("grid").unwrap();
+        let eth: Grid = grid.asset_params("ETH").unwrap();
+        let btc: Grid = grid.asset_params("BTC").unwrap();
+        assert_eq!((eth.levels, btc.levels), (5, 3));
+        assert!((btc.spacing_bps - 10.0).abs() < crate::EPSILON);
+
+        let yaml = r#"
+network: mainnet
+api_url: http://localhost:3001
+keys:
+  main:
+    file: /run/secrets/hl
+strategies:
+  grid:
+    params:
+      levels: 2
+"#;
+        let config = SdkConfig::from_yaml(yaml).unwrap();
+        assert_eq!(config.base_url().get_url(), "http://localhost:3001");
+        assert_eq!(
+            config.keys["main"],
+            KeySource::File {
+                file: PathBuf::from("/run/secrets/hl")
+            }
+        );
+
+        let invalid = TOML
+            .replace("key = \"main\"\ntarget", "key = \"other\"\ntarget")
+            .replace("half_spread = 1", "half_spread = 0");
+        let Err(Error::InvalidConfig(problems)) = SdkConfig::from_toml(&invalid) else {
+            panic!("expected an invalid config");
+        };
+        assert!(problems.contains("unknown key other") && problems.contains("half_spread"));
+        assert!(SdkConfig::from_toml("netwrk = \"testnet\"").is_err());
+        let Err(Error::InvalidConfig(problem)) = SdkConfig::from_toml(
+            "network = \"testnet\"\napi_url = \"https://api.hyperliquid.xyz/\"",
+        ) else {
+            panic!("expected a network mismatch");
+        };
+        assert!(problem.contains("Mainnet API but network is Testnet"));
+    }
+}
@@ -3,3 +3,34 @@ pub static TESTNET_API_URL: &str = "https://api.hyperliquid-testnet.xyz";
 pub static LOCAL_API_URL: &str = "http://localhost:3001";
 pub const EPSILON: f64 = 1e-9;
 pub(crate) const INF_BPS: u16 = 10_001;
+/// Slippage of market orders that do not set one, as a fraction of the price.
+pub const DEFAULT_SLIPPAGE: f64 = 0.05;
+
+/// Values a client falls back on, set with the `defaults` method of the client builders for
+/// deployments that cannot use the built-in ones, such as API URLs behind a proxy.
+///
+/// Tolerances such as `EPSILON` stay constants, as free functions and components not tied
+/// to a client compare against them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientDefaults {
+    /// REST URL of `BaseUrl::Mainnet`, the websocket URL is derived from it. Actions are
+    /// signed for mainnet whatever the URL.
+    pub mainnet_api_url: String,
+    /// REST URL of `BaseUrl::Testnet`
+    pub testnet_api_url: String,
+    /// REST URL of `BaseUrl::Localhost`
+    pub local_api_url: String,
+    /// Slippage of market orders that do not set one, as a fraction of the price
+    pub slippage: f64,
+}
+
+impl Default for ClientDefaults {
+    fn default() -> Self {
+        ClientDefaults {
+            mainnet_api_url: MAINNET_API_URL.to_string(),
+            testnet_api_url: TESTNET_API_URL.to_string(),
+            local_api_url: LOCAL_API_URL.to_string(),
+            slippage: DEFAULT_SLIPPAGE,
+        }
+    }
+}
@@ -0,0 +1,466 @@
+use std::{collections::HashMap, io::Read};
+
+use chrono::{DateTime, Duration, DurationRound, NaiveDateTime, Utc};
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use tracing::{debug, warn};
+
+use crate::aws::{amz_date, authorization, AwsCredentials, UNSIGNED_PAYLOAD};
+use crate::{
+    prelude::*, req::reqwest_error, AssetContext, BookReconstructor, BookUpdate, Error, L2BookData,
+    Message, Side, Timestamp, Trade,
+};
+
+/// L2 book snapshots and asset contexts
+pub const MARKET_DATA_BUCKET: &str = "hyperliquid-archive";
+/// Trades as streamed by the nodes
+pub const NODE_DATA_BUCKET: &str = "hl-mainnet-node-data";
+
+const LZ4_MAGIC: [u8; 4] = [0x04, 0x22, 0x4d, 0x18];
+
+/// Asset context of one coin at one time, as recorded in the `asset_ctxs` archive.
+#[derive(Clone, Debug)]
+pub struct ArchivedAssetCtx {
+    pub time: Timestamp,
+    pub coin: String,
+    pub ctx: AssetContext,
+}
+
+#[derive(Deserialize)]
+struct L2BookLine {
+    raw: L2BookRaw,
+}
+
+#[derive(Deserialize)]
+struct L2BookRaw {
+    data: L2BookData,
+}
+
+#[derive(Deserialize)]
+struct TradeLine {
+    coin: String,
+    side: Side,
+    time: String,
+    px: String,
+    sz: String,
+    hash: String,
+    #[serde(default)]
+    tid: u64,
+    side_info: Vec<SideInfo>,
+}
+
+#[derive(Deserialize)]
+struct SideInfo {
+    user: String,
+}
+
+/// Downloads Hyperliquid's published historical data from S3 and converts it into the SDK's
+/// types.
+///
+/// The buckets are requester pays, so requests are signed with `AwsCredentials`, read from the
+/// environment by default, and the transfer is billed to that account. Hours or days missing
+/// from the archive are skipped with a warning.
+#[derive(Clone, Debug)]
+pub struct ArchiveClient {
+    client: Client,
+    credentials: Option<AwsCredentials>,
+    region: String,
+    endpoint: Option<String>,
+}
+
+impl Default for ArchiveClient {
+    fn default() -> Self {
+        ArchiveClient::new()
+    }
+}
+
+impl ArchiveClient {
+    pub fn new() -> ArchiveClient {
+        ArchiveClient {
+            client: Client::new(),
+            credentials: AwsCredentials::from_env(),
+            region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            endpoint: None,
+        }
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: AwsCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// Region requests are first signed for; S3 redirects to the bucket's own region.
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+        self
+    }
+
+    /// Serves objects from `{endpoint}/{bucket}/{key}` instead of S3, e.g. a local mirror.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// L2 book snapshots of `coin` between `start` and `end`, from the hourly archives.
+    pub async fn l2_books(
+        &self,
+        coin: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<L2BookData>> {
+        let mut books = Vec::new();
+        for hour in hours(start, end) {
+            let key = format!(
+                "market_data/{}/{}/l2Book/{coin}.lz4",
+                hour.format("%Y%m%d"),
+                hour.format("%-H")
+            );
+            let Some(data) = self.fetch(MARKET_DATA_BUCKET, &key).await? else {
+                continue;
+            };
+            books.extend(
+                parse_l2_books(&data)?
+                    .into_iter()
+                    .filter(|book| in_range(book.time, start, end)),
+            );
+        }
+        Ok(books)
+    }
+
+    /// Books and trades of `coin` between `start` and `end` in time order, rebuilt with
+    /// `BookReconstructor` for replaying with `Backtester`.
+    pub async fn replay_messages(
+        &self,
+        coin: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Message>> {
+        let updates: Vec<BookUpdate> = self
+            .l2_books(coin, start, end)
+            .await?
+            .into_iter()
+            .map(BookUpdate::Snapshot)
+            .collect();
+        let mut trades = self.trades(start, end).await?;
+        trades.retain(|trade| trade.coin == coin);
+        Ok(BookReconstructor::new().messages(&updates, &trades))
+    }
+
+    /// Trades on all coins between `start` and `end`, from the hourly node archives.
+    pub async fn trades(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+        for hour in hours(start, end) {
+            let key = format!(
+                "node_trades/hourly/{}/{}.lz4",
+                hour.format("%Y%m%d"),
+                hour.format("%-H")
+            );
+            let Some(data) = self.fetch(NODE_DATA_BUCKET, &key).await? else {
+                continue;
+            };
+            trades.extend(
+                parse_trades(&data)?
+                    .into_iter()
+                    .filter(|trade| in_range(trade.time, start, end)),
+            );
+        }
+        Ok(trades)
+    }
+
+    /// Asset contexts of all perps between `start` and `end`, from the daily archives.
+    pub async fn asset_ctxs(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<ArchivedAssetCtx>> {
+        let mut ctxs = Vec::new();
+        let mut day = start.date_naive();
+        while day.and_time(Default::default()).and_utc() < end {
+            let key = format!("asset_ctxs/{}.csv.lz4", day.format("%Y%m%d"));
+            if let Some(data) = self.fetch(MARKET_DATA_BUCKET, &key).await? {
+                ctxs.extend(
+                    parse_asset_ctxs(&data)?
+                        .into_iter()
+                        .filter(|ctx| in_range(ctx.time, start, end)),
+                );
+            }
+            day += Duration::days(1);
+        }
+        Ok(ctxs)
+    }
+
+    /// Downloads and decompresses one object, `None` if it does not exist.
+    pub async fn fetch(&self, bucket: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut region = self.region.clone();
+        let mut redirected = false;
+        loop {
+            let response = self.get(bucket, key, &region).await?;
+            let status = response.status();
+            if status == StatusCode::NOT_FOUND {
+                warn!("{bucket}/{key} not found in archive");
+                return Ok(None);
+            }
+            // Signed for the wrong region, S3 names the right one
+            let bucket_region = response
+                .headers()
+                .get("x-amz-bucket-region")
+                .and_then(|value| value.to_str().ok())
+                .filter(|value| *value != region)
+                .map(str::to_string);
+            if let (Some(bucket_region), false) = (bucket_region, redirected) {
+                if status.is_client_error() || status.is_redirection() {
+                    debug!("Retrying {bucket}/{key} in {bucket_region}");
+                    region = bucket_region;
+                    redirected = true;
+                    continue;
+                }
+            }
+
+            let body = response.bytes().await.map_err(|e| reqwest_error(&e))?;
+            if status.is_client_error() || status.is_redirection() {
+                return Err(Error::ClientRequest {
+                    status_code: status.as_u16(),
+                    error_code: None,
+                    error_message: String::from_utf8_lossy(&body).to_string(),
+                    error_data: None,
+                });
+            }
+            if status.is_server_error() {
+                return Err(Error::ServerRequest {
+                    status_code: status.as_u16(),
+                    error_message: String::from_utf8_lossy(&body).to_string(),
+                });
+            }
+            return decompress(&body).map(Some);
+        }
+    }
+
+    async fn get(&self, bucket: &str, key: &str, region: &str) -> Result<reqwest::Response> {
+        let (url, host, path) = match &self.endpoint {
+            Some(endpoint) => {
+                let host = endpoint.split("://").last().unwrap_or(endpoint).to_string();
+                (
+                    format!("{endpoint}/{bucket}/{key}"),
+                    host,
+                    format!("/{bucket}/{key}"),
+                )
+            }
+            None => {
+                let host = format!("{bucket}.s3.amazonaws.com");
+                (format!("https://{host}/{key}"), host, format!("/{key}"))
+            }
+        };
+
+        let mut request = self.client.get(url);
+        if let Some(credentials) = &self.credentials {
+            let time = Utc::now();
+            let mut headers = vec![
+                ("host", host),
+                ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_string()),
+                ("x-amz-date", amz_date(time)),
+                ("x-amz-request-payer", "requester".to_string()),
+            ];
+            if let Some(token) = &credentials.session_token {
+                headers.push(("x-amz-security-token", token.clone()));
+            }
+            let authorization =
+                authorization(credentials, region, "s3", "GET", &path, &headers, time);
+            for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+                request = request.header(name, value);
+            }
+            request = request.header("authorization", authorization);
+        }
+        request.send().await.map_err(|e| reqwest_error(&e))
+    }
+}
+
+/// Start of every hour overlapping `start..end`.
+fn hours(start: DateTime<Utc>, end: DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> {
+    let first = start.duration_trunc(Duration::hours(1)).unwrap_or(start);
+    std::iter::successors(Some(first), |hour| Some(*hour + Duration::hours(1)))
+        .take_while(move |hour| *hour < end)
+}
+
+fn in_range(time: Timestamp, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    (Timestamp::from(start)..Timestamp::from(end)).contains(&time)
+}
+
+/// Archives are lz4 frames; anything else is returned as is.
+fn decompress(data: &[u8]) -> Result<Vec<u8>> {
+    if !data.starts_with(&LZ4_MAGIC) {
+        return Ok(data.to_vec());
+    }
+    let mut decompressed = Vec::new();
+    lz4_flex::frame::FrameDecoder::new(data)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| Error::GenericParse(format!("Could not decompress archive: {e}")))?;
+    Ok(decompressed)
+}
+
+/// Timestamps in the archives are UTC, with or without an offset.
+fn parse_time(time: &str) -> Option<Timestamp> {
+    let time = DateTime::parse_from_rfc3339(time)
+        .map(|time| time.to_utc())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f").map(|t| t.and_utc())
+        })
+        .ok()?;
+    Some(time.into())
+}
+
+fn lines(data: &[u8]) -> Result<impl Iterator<Item = &str>> {
+    let text = std::str::from_utf8(data).map_err(|e| Error::GenericParse(e.to_string()))?;
+    Ok(text.lines().filter(|line| !line.trim().is_empty()))
+}
+
+pub(crate) fn parse_l2_books(data: &[u8]) -> Result<Vec<L2BookData>> {
+    lines(data)?
+        .map(|line| {
+            serde_json::from_str::<L2BookLine>(line)
+                .map(|line| line.raw.data)
+                .map_err(|e| Error::JsonParse(e.to_string()))
+        })
+        .collect()
+}
+
+pub(crate) fn parse_trades(data: &[u8]) -> Result<Vec<Trade>> {
+    lines(data)?
+        .map(|line| {
+            let line: TradeLine =
+                serde_json::from_str(line).map_err(|e| Error::JsonParse(e.to_string()))?;
+            let user = |i: usize| {
+                line.side_info
+                    .get(i)
+                    .map(|side| side.user.clone())
+                    .unwrap_or_default()
+            };
+            Ok(Trade {
+                time: parse_time(&line.time)
+                    .ok_or_else(|| Error::GenericParse(format!("Invalid time {}", line.time)))?,
+                users: (user(0), user(1)),
+                coin: line.coin,
+                side: line.side,
+                px: line.px,
+                sz: line.sz,
+                hash: line.hash,
+                tid: line.tid,
+            })
+        })
+        .collect()
+}
+
+const ASSET_CTX_COLUMNS: [&str; 12] = [
+    "time",
+    "coin",
+    "funding",
+    "open_interest",
+    "prev_day_px",
+    "day_ntl_vlm",
+    "premium",
+    "oracle_px",
+    "mark_px",
+    "mid_px",
+    "impact_bid_px",
+    "impact_ask_px",
+];
+
+pub(crate) fn parse_asset_ctxs(data: &[u8]) -> Result<Vec<ArchivedAssetCtx>> {
+    let mut lines = lines(data)?;
+    let columns: HashMap<&str, usize> = lines
+        .next()
+        .unwrap_or_default()
+        .split(',')
+        .enumerate()
+        .map(|(i, column)| (column.trim(), i))
+        .collect();
+    if let Some(missing) = ASSET_CTX_COLUMNS
+        .iter()
+        .find(|column| !columns.contains_key(*column))
+    {
+        return Err(Error::GenericParse(format!("Missing column {missing}")));
+    }
+
+    lines
+        .map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let field = |name: &str| fields.get(columns[name]).copied().unwrap_or_default();
+            Ok(ArchivedAssetCtx {
+                time: parse_time(field("time")).ok_or_else(|| {
+                    Error::GenericParse(format!("Invalid time {}", field("time")))
+                })?,
+                coin: field("coin").to_string(),
+                ctx: AssetContext {
+                    day_ntl_vlm: field("day_ntl_vlm").to_string(),
+                    funding: field("funding").to_string(),
+                    impact_pxs: vec![
+                        field("impact_bid_px").to_string(),
+                        field("impact_ask_px").to_string(),
+                    ],
+                    mark_px: field("mark_px").to_string(),
+                    mid_px: Some(field("mid_px").to_string()).filter(|px| !px.is_empty()),
+                    open_interest: field("open_interest").to_string(),
+                    oracle_px: field("oracle_px").to_string(),
+                    premium: field("premium").to_string(),
+                    prev_day_px: field("prev_day_px").to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn compress(data: &str) -> Vec<u8> {
+        let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+        encoder.write_all(data.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_parse_archives() {
+        let books = compress(concat!(
+            r#"{"time":"2023-09-16T09:00:00.090167044","ver_num":1,"raw":{"channel":"l2Book","data":{"coin":"SOL","time":1694854800089,"levels":[[{"px":"18.932","sz":"391.6","n":4}],[{"px":"18.935","sz":"10.0","n":1}]]}}}"#,
+            "\n"
+        ));
+        let books = parse_l2_books(&decompress(&books).unwrap()).unwrap();
+        assert_eq!(books[0].coin, "SOL");
+        assert_eq!(books[0].levels[1][0].px, "18.935");
+
+        let trades = parse_trades(
+            br#"{"coin":"ETH","side":"A","time":"2025-03-22T09:00:06.131","px":"1990.5","sz":"0.5","hash":"0xab","trade_dir_override":"Na","side_info":[{"user":"0x01","start_pos":"1","oid":1,"twap_id":null,"cloid":null},{"user":"0x02","start_pos":"0","oid":2,"twap_id":null,"cloid":null}]}"#,
+        )
+        .unwrap();
+        assert_eq!(trades[0].time, 1_742_634_006_131);
+        assert_eq!(trades[0].users, ("0x01".to_string(), "0x02".to_string()));
+
+        let ctxs = parse_asset_ctxs(
+            b"time,coin,funding,open_interest,prev_day_px,day_ntl_vlm,premium,oracle_px,mark_px,mid_px,impact_bid_px,impact_ask_px\n\
+              2023-05-20T00:00:00Z,BTC,0.0000125,100.5,27000.0,1000000.0,0.0001,26900.0,26910.0,,26905.0,26915.0\n",
+        )
+        .unwrap();
+        assert_eq!(ctxs[0].coin, "BTC");
+        assert_eq!(ctxs[0].ctx.mid_px, None);
+        assert_eq!(ctxs[0].ctx.impact_pxs, vec!["26905.0", "26915.0"]);
+    }
+
+    #[test]
+    fn test_hours_cover_range() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 22, 30, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 1, 2, 1, 0, 0).unwrap();
+        let hours: Vec<String> = hours(start, end)
+            .map(|hour| hour.format("%Y%m%d/%-H").to_string())
+            .collect();
+        assert_eq!(hours, vec!["20240101/22", "20240101/23", "20240102/0"]);
+    }
+}
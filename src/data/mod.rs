@@ -0,0 +1,12 @@
+mod archive;
+#[cfg(feature = "parquet")]
+mod parquet_writer;
+mod tape;
+
+pub use archive::{ArchiveClient, ArchivedAssetCtx, MARKET_DATA_BUCKET, NODE_DATA_BUCKET};
+#[cfg(feature = "parquet")]
+pub use parquet_writer::{
+    write_asset_ctxs_parquet, write_candles_parquet, write_fills_parquet, write_funding_parquet,
+    write_l2_books_parquet, write_ledger_parquet, write_trades_parquet,
+};
+pub use tape::{read_tape, TapeFormat, TapeGap, TapeRecorder};
@@ -0,0 +1,444 @@
+use std::{fs::File, path::Path, sync::Arc};
+
+use parquet::{
+    basic::Compression,
+    data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type},
+    errors::ParquetError,
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+use crate::{
+    prelude::*, ArchivedAssetCtx, CandleData, Error, L2BookData, LedgerRow, LedgerUpdateData,
+    Trade, UserFillsResponse, UserFundingResponse,
+};
+
+enum Column {
+    Boolean(Vec<bool>),
+    Int64(Vec<i64>),
+    OptionalInt64(Vec<Option<i64>>),
+    Double(Vec<f64>),
+    OptionalDouble(Vec<Option<f64>>),
+    Text(Vec<String>),
+    OptionalText(Vec<Option<String>>),
+}
+
+impl From<ParquetError> for Error {
+    fn from(err: ParquetError) -> Self {
+        Error::Io(err.to_string())
+    }
+}
+
+fn px(value: &str) -> f64 {
+    value.parse().unwrap_or(f64::NAN)
+}
+
+/// Writes one row per book level, with columns `time`, `coin`, `side` (`B` or `A`), `level`
+/// (0 at the top of the book), `px`, `sz` and `n`.
+pub fn write_l2_books_parquet(path: impl AsRef<Path>, books: &[L2BookData]) -> Result<()> {
+    let rows: Vec<(&L2BookData, &str, usize, &crate::BookLevel)> = books
+        .iter()
+        .flat_map(|book| {
+            book.levels
+                .iter()
+                .zip(["B", "A"])
+                .flat_map(move |(levels, side)| {
+                    levels
+                        .iter()
+                        .enumerate()
+                        .map(move |(level, book_level)| (book, side, level, book_level))
+                })
+        })
+        .collect();
+    write(
+        path,
+        "message l2_book {
+            required int64 time (TIMESTAMP(MILLIS, true));
+            required binary coin (STRING);
+            required binary side (STRING);
+            required int64 level;
+            required double px;
+            required double sz;
+            required int64 n;
+        }",
+        vec![
+            Column::Int64(rows.iter().map(|r| r.0.time.as_millis() as i64).collect()),
+            Column::Text(rows.iter().map(|r| r.0.coin.clone()).collect()),
+            Column::Text(rows.iter().map(|r| r.1.to_string()).collect()),
+            Column::Int64(rows.iter().map(|r| r.2 as i64).collect()),
+            Column::Double(rows.iter().map(|r| px(&r.3.px)).collect()),
+            Column::Double(rows.iter().map(|r| px(&r.3.sz)).collect()),
+            Column::Int64(rows.iter().map(|r| r.3.n as i64).collect()),
+        ],
+    )
+}
+
+/// Writes columns `time`, `coin`, `side`, `px`, `sz`, `hash`, `tid`, `buyer` and `seller`.
+pub fn write_trades_parquet(path: impl AsRef<Path>, trades: &[Trade]) -> Result<()> {
+    write(
+        path,
+        "message trade {
+            required int64 time (TIMESTAMP(MILLIS, true));
+            required binary coin (STRING);
+            required binary side (STRING);
+            required double px;
+            required double sz;
+            required binary hash (STRING);
+            required int64 tid;
+            required binary buyer (STRING);
+            required binary seller (STRING);
+        }",
+        vec![
+            Column::Int64(trades.iter().map(|t| t.time.as_millis() as i64).collect()),
+            Column::Text(trades.iter().map(|t| t.coin.clone()).collect()),
+            Column::Text(trades.iter().map(|t| t.side.to_string()).collect()),
+            Column::Double(trades.iter().map(|t| px(&t.px)).collect()),
+            Column::Double(trades.iter().map(|t| px(&t.sz)).collect()),
+            Column::Text(trades.iter().map(|t| t.hash.clone()).collect()),
+            Column::Int64(trades.iter().map(|t| t.tid as i64).collect()),
+            Column::Text(trades.iter().map(|t| t.users.0.clone()).collect()),
+            Column::Text(trades.iter().map(|t| t.users.1.clone()).collect()),
+        ],
+    )
+}
+
+/// Writes columns `time_open`, `time_close`, `coin`, `interval`, `open`, `high`, `low`,
+/// `close`, `volume` and `num_trades`.
+pub fn write_candles_parquet(path: impl AsRef<Path>, candles: &[CandleData]) -> Result<()> {
+    write(
+        path,
+        "message candle {
+            required int64 time_open (TIMESTAMP(MILLIS, true));
+            required int64 time_close (TIMESTAMP(MILLIS, true));
+            required binary coin (STRING);
+            required binary interval (STRING);
+            required double open;
+            required double high;
+            required double low;
+            required double close;
+            required double volume;
+            required int64 num_trades;
+        }",
+        vec![
+            Column::Int64(
+                candles
+                    .iter()
+                    .map(|c| c.time_open.as_millis() as i64)
+                    .collect(),
+            ),
+            Column::Int64(
+                candles
+                    .iter()
+                    .map(|c| c.time_close.as_millis() as i64)
+                    .collect(),
+            ),
+            Column::Text(candles.iter().map(|c| c.coin.clone()).collect()),
+            Column::Text(candles.iter().map(|c| c.interval.clone()).collect()),
+            Column::Double(candles.iter().map(|c| px(&c.open)).collect()),
+            Column::Double(candles.iter().map(|c| px(&c.high)).collect()),
+            Column::Double(candles.iter().map(|c| px(&c.low)).collect()),
+            Column::Double(candles.iter().map(|c| px(&c.close)).collect()),
+            Column::Double(candles.iter().map(|c| px(&c.volume)).collect()),
+            Column::Int64(candles.iter().map(|c| c.num_trades as i64).collect()),
+        ],
+    )
+}
+
+/// Writes the archive's asset context columns, with numbers as doubles.
+pub fn write_asset_ctxs_parquet(path: impl AsRef<Path>, ctxs: &[ArchivedAssetCtx]) -> Result<()> {
+    let double = |f: fn(&ArchivedAssetCtx) -> &str| {
+        Column::Double(ctxs.iter().map(|ctx| px(f(ctx))).collect())
+    };
+    let impact_px = |i: usize| {
+        Column::Double(
+            ctxs.iter()
+                .map(|ctx| ctx.ctx.impact_pxs.get(i).map(|p| px(p)).unwrap_or(f64::NAN))
+                .collect(),
+        )
+    };
+    write(
+        path,
+        "message asset_ctx {
+            required int64 time (TIMESTAMP(MILLIS, true));
+            required binary coin (STRING);
+            required double funding;
+            required double open_interest;
+            required double prev_day_px;
+            required double day_ntl_vlm;
+            required double premium;
+            required double oracle_px;
+            required double mark_px;
+            optional double mid_px;
+            required double impact_bid_px;
+            required double impact_ask_px;
+        }",
+        vec![
+            Column::Int64(ctxs.iter().map(|ctx| ctx.time.as_millis() as i64).collect()),
+            Column::Text(ctxs.iter().map(|ctx| ctx.coin.clone()).collect()),
+            double(|ctx| &ctx.ctx.funding),
+            double(|ctx| &ctx.ctx.open_interest),
+            double(|ctx| &ctx.ctx.prev_day_px),
+            double(|ctx| &ctx.ctx.day_ntl_vlm),
+            double(|ctx| &ctx.ctx.premium),
+            double(|ctx| &ctx.ctx.oracle_px),
+            double(|ctx| &ctx.ctx.mark_px),
+            Column::OptionalDouble(
+                ctxs.iter()
+                    .map(|ctx| ctx.ctx.mid_px.as_deref().map(px))
+                    .collect(),
+            ),
+            impact_px(0),
+            impact_px(1),
+        ],
+    )
+}
+
+/// Writes the columns of `write_fills_csv`, with numbers as doubles.
+pub fn write_fills_parquet(path: impl AsRef<Path>, fills: &[UserFillsResponse]) -> Result<()> {
+    let double = |f: fn(&UserFillsResponse) -> &str| {
+        Column::Double(fills.iter().map(|fill| px(f(fill))).collect())
+    };
+    let text = |f: fn(&UserFillsResponse) -> &str| {
+        Column::Text(fills.iter().map(|fill| f(fill).to_string()).collect())
+    };
+    write(
+        path,
+        "message fill {
+            required int64 time (TIMESTAMP(MILLIS, true));
+            required binary coin (STRING);
+            required binary side (STRING);
+            required double px;
+            required double sz;
+            required binary dir (STRING);
+            required double closed_pnl;
+            required double fee;
+            required binary fee_token (STRING);
+            optional double builder_fee;
+            required boolean crossed;
+            required double start_position;
+            required int64 oid;
+            required int64 tid;
+            required binary hash (STRING);
+            optional int64 twap_id;
+        }",
+        vec![
+            Column::Int64(
+                fills
+                    .iter()
+                    .map(|fill| fill.time.as_millis() as i64)
+                    .collect(),
+            ),
+            text(|fill| &fill.coin),
+            text(|fill| fill.side.as_str()),
+            double(|fill| &fill.px),
+            double(|fill| &fill.sz),
+            text(|fill| fill.dir.as_str()),
+            double(|fill| &fill.closed_pnl),
+            double(|fill| &fill.fee),
+            text(|fill| &fill.fee_token),
+            Column::OptionalDouble(
+                fills
+                    .iter()
+                    .map(|fill| fill.builder_fee.as_deref().map(px))
+                    .collect(),
+            ),
+            Column::Boolean(fills.iter().map(|fill| fill.crossed).collect()),
+            double(|fill| &fill.start_position),
+            Column::Int64(fills.iter().map(|fill| fill.oid as i64).collect()),
+            Column::Int64(fills.iter().map(|fill| fill.tid as i64).collect()),
+            text(|fill| &fill.hash),
+            Column::OptionalInt64(
+                fills
+                    .iter()
+                    .map(|fill| fill.twap_id.map(|id| id as i64))
+                    .collect(),
+            ),
+        ],
+    )
+}
+
+/// Writes the columns of `write_funding_csv`, with numbers as doubles.
+pub fn write_funding_parquet(
+    path: impl AsRef<Path>,
+    funding: &[UserFundingResponse],
+) -> Result<()> {
+    let double = |f: fn(&UserFundingResponse) -> &str| {
+        Column::Double(funding.iter().map(|funding| px(f(funding))).collect())
+    };
+    write(
+        path,
+        "message funding {
+            required int64 time (TIMESTAMP(MILLIS, true));
+            required binary coin (STRING);
+            required double usdc;
+            required double szi;
+            required double funding_rate;
+            required binary hash (STRING);
+        }",
+        vec![
+            Column::Int64(
+                funding
+                    .iter()
+                    .map(|funding| funding.time.as_millis() as i64)
+                    .collect(),
+            ),
+            Column::Text(funding.iter().map(|f| f.delta.coin.clone()).collect()),
+            double(|funding| &funding.delta.usdc),
+            double(|funding| &funding.delta.szi),
+            double(|funding| &funding.delta.funding_rate),
+            Column::Text(funding.iter().map(|f| f.hash.clone()).collect()),
+        ],
+    )
+}
+
+/// Writes the columns of `write_ledger_csv`, with amounts as doubles.
+pub fn write_ledger_parquet(path: impl AsRef<Path>, updates: &[LedgerUpdateData]) -> Result<()> {
+    let rows: Vec<LedgerRow> = updates.iter().map(LedgerRow::from).collect();
+    write(
+        path,
+        "message ledger_update {
+            required int64 time (TIMESTAMP(MILLIS, true));
+            required binary hash (STRING);
+            required binary type (STRING);
+            required binary token (STRING);
+            required double amount;
+            optional double usdc_value;
+            optional double fee;
+            optional binary user (STRING);
+            optional binary destination (STRING);
+        }",
+        vec![
+            Column::Int64(rows.iter().map(|row| row.time as i64).collect()),
+            Column::Text(rows.iter().map(|row| row.hash.clone()).collect()),
+            Column::Text(rows.iter().map(|row| row.kind.clone()).collect()),
+            Column::Text(rows.iter().map(|row| row.token.clone()).collect()),
+            Column::Double(rows.iter().map(|row| px(&row.amount)).collect()),
+            Column::OptionalDouble(
+                rows.iter()
+                    .map(|row| row.usdc_value.as_deref().map(px))
+                    .collect(),
+            ),
+            Column::OptionalDouble(rows.iter().map(|row| row.fee.as_deref().map(px)).collect()),
+            Column::OptionalText(rows.iter().map(|row| row.user.clone()).collect()),
+            Column::OptionalText(rows.iter().map(|row| row.destination.clone()).collect()),
+        ],
+    )
+}
+
+fn write(path: impl AsRef<Path>, schema: &str, columns: Vec<Column>) -> Result<()> {
+    let file = File::create(path).map_err(|e| Error::Io(e.to_string()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = SerializedFileWriter::new(
+        file,
+        Arc::new(parse_message_type(schema)?),
+        Arc::new(properties),
+    )?;
+    let mut row_group = writer.next_row_group()?;
+    for column in columns {
+        let mut writer = row_group
+            .next_column()?
+            .ok_or_else(|| Error::Io("More columns than in schema".to_string()))?;
+        match column {
+            Column::Boolean(values) => {
+                writer
+                    .typed::<BoolType>()
+                    .write_batch(&values, None, None)?;
+            }
+            Column::Int64(values) => {
+                writer
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            Column::OptionalInt64(values) => {
+                let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                let values: Vec<i64> = values.into_iter().flatten().collect();
+                writer
+                    .typed::<Int64Type>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            Column::Double(values) => {
+                writer
+                    .typed::<DoubleType>()
+                    .write_batch(&values, None, None)?;
+            }
+            Column::OptionalDouble(values) => {
+                let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                let values: Vec<f64> = values.into_iter().flatten().collect();
+                writer
+                    .typed::<DoubleType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            Column::Text(values) => {
+                let values: Vec<ByteArray> =
+                    values.iter().map(|v| ByteArray::from(v.as_str())).collect();
+                writer
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, None, None)?;
+            }
+            Column::OptionalText(values) => {
+                let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                let values: Vec<ByteArray> = values
+                    .iter()
+                    .flatten()
+                    .map(|v| ByteArray::from(v.as_str()))
+                    .collect();
+                writer
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+        }
+        writer.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    use super::*;
+
+    #[test]
+    fn test_write_l2_books() {
+        let books: Vec<L2BookData> = serde_json::from_str(
+            r#"[{"coin":"ETH","time":1,"levels":[
+                [{"px":"1999","sz":"1","n":1},{"px":"1998","sz":"5","n":2}],
+                [{"px":"2001","sz":"1","n":1}]
+            ]}]"#,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("l2_books_{}.parquet", std::process::id()));
+        write_l2_books_parquet(&path, &books).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 3);
+        assert_eq!(
+            reader
+                .metadata()
+                .file_metadata()
+                .schema_descr()
+                .num_columns(),
+            7
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_ledger() {
+        let updates: Vec<LedgerUpdateData> = serde_json::from_str(
+            r#"[{"time":1,"hash":"0x1","delta":{"type":"deposit","usdc":"100.5"}},
+            {"time":2,"hash":"0x2","delta":{"type":"withdraw","usdc":"50","nonce":1,"fee":"1"}}]"#,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("ledger_{}.parquet", std::process::id()));
+        write_ledger_parquet(&path, &updates).unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,636 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+#[cfg(feature = "ws")]
+use std::{future::Future, pin::pin};
+
+use chrono::DateTime;
+use lz4_flex::frame::{FrameDecoder, FrameEncoder};
+#[cfg(feature = "ws")]
+use tokio::sync::mpsc::unbounded_channel;
+use tracing::{info, warn};
+
+#[cfg(feature = "parquet")]
+use super::parquet_writer::{write_candles_parquet, write_l2_books_parquet, write_trades_parquet};
+#[cfg(feature = "ws")]
+use crate::Subscription;
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, Candle, Checkpoint, CheckpointStore, Error, InfoClient,
+    Message, Trade, Trades,
+};
+#[cfg(feature = "parquet")]
+use crate::{CandleData, L2BookData};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TapeFormat {
+    /// Lz4-compressed JSON lines of websocket messages, readable with `read_tape`
+    #[default]
+    Jsonl,
+    /// Snappy-compressed Parquet files of trades, books and candles, written when each file
+    /// is rotated
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// Time range of a coin's trades missing from the tape, backfilled with candles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TapeGap {
+    pub coin: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+#[derive(Debug)]
+enum TapeFile {
+    Jsonl(FrameEncoder<BufWriter<File>>),
+    #[cfg(feature = "parquet")]
+    Parquet {
+        trades: Vec<Trade>,
+        books: Vec<L2BookData>,
+        candles: Vec<CandleData>,
+    },
+}
+
+/// Records `trades` and `l2Book` messages of a set of coins into compressed files starting a
+/// new one every rotation period, for replaying with `Backtester` later.
+///
+/// Files are named after the UTC start of their period, such as `20240101-130000.jsonl.lz4`.
+/// When the websocket disconnects, or with `with_max_trade_gap` when a coin's trades stop for
+/// longer than that, the missing range is backfilled with candles from `candleSnapshot`, which
+/// `Backtester` turns into trades. Backfilled candles go into the file open at the time.
+///
+/// With `with_checkpoints`, the last trade written of each coin is saved whenever a file is
+/// closed. After a restart, trades already on tape are dropped and the time since the
+/// checkpoint is backfilled, so the tape has neither gaps nor duplicates. A file whose name is
+/// taken, as when restarting within a period, gets a numbered suffix instead of replacing it.
+#[derive(Debug)]
+pub struct TapeRecorder {
+    dir: PathBuf,
+    coins: HashSet<String>,
+    format: TapeFormat,
+    rotation: Duration,
+    books: bool,
+    candle_interval: String,
+    max_trade_gap: Option<Duration>,
+    /// Start of the open file's period, its path without extension and the file
+    file: Option<(u64, PathBuf, TapeFile)>,
+    written: Vec<PathBuf>,
+    last_trade: HashMap<String, u64>,
+    disconnected_at: Option<u64>,
+    gaps: Vec<TapeGap>,
+    checkpoint_store: Option<Box<dyn CheckpointStore>>,
+    /// Checkpoints of the coins whose trades were seen, by coin
+    checkpoints: HashMap<String, Checkpoint>,
+}
+
+impl TapeRecorder {
+    /// Records the trades and books of `coins` into `dir`, rotating hourly.
+    pub fn new(dir: impl AsRef<Path>, coins: impl IntoIterator<Item = String>) -> TapeRecorder {
+        TapeRecorder {
+            dir: dir.as_ref().to_path_buf(),
+            coins: coins.into_iter().collect(),
+            format: TapeFormat::default(),
+            rotation: Duration::from_secs(60 * 60),
+            books: true,
+            candle_interval: "1m".to_string(),
+            max_trade_gap: None,
+            file: None,
+            written: Vec::new(),
+            last_trade: HashMap::new(),
+            disconnected_at: None,
+            gaps: Vec::new(),
+            checkpoint_store: None,
+            checkpoints: HashMap::new(),
+        }
+    }
+
+    pub fn with_format(mut self, format: TapeFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Length of each file, hourly by default. Periods start on multiples of it since the
+    /// epoch.
+    pub fn with_rotation(mut self, rotation: Duration) -> Self {
+        self.rotation = rotation.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Records trades only.
+    pub fn without_books(mut self) -> Self {
+        self.books = false;
+        self
+    }
+
+    /// Interval of backfilled candles, `1m` by default.
+    pub fn with_candle_interval(mut self, interval: &str) -> Self {
+        self.candle_interval = interval.to_string();
+        self
+    }
+
+    /// Also backfills when consecutive trades of a coin are further apart than `gap`.
+    pub fn with_max_trade_gap(mut self, gap: Duration) -> Self {
+        self.max_trade_gap = Some(gap);
+        self
+    }
+
+    /// Resumes each coin's trades from the checkpoint in `store`, named `tape-<coin>`.
+    pub fn with_checkpoints(mut self, store: impl CheckpointStore + 'static) -> Self {
+        self.checkpoint_store = Some(Box::new(store));
+        self
+    }
+
+    /// Subscriptions whose messages should be passed to `handle_message`.
+    #[cfg(feature = "ws")]
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut coins: Vec<&String> = self.coins.iter().collect();
+        coins.sort();
+        coins
+            .into_iter()
+            .flat_map(|coin| {
+                let trades = Subscription::Trades { coin: coin.clone() };
+                let book = self
+                    .books
+                    .then(|| Subscription::L2Book { coin: coin.clone() });
+                std::iter::once(trades).chain(book)
+            })
+            .collect()
+    }
+
+    /// Files closed so far.
+    pub fn written(&self) -> &[PathBuf] {
+        &self.written
+    }
+
+    /// Gaps waiting for `backfill`.
+    pub fn gaps(&self) -> &[TapeGap] {
+        &self.gaps
+    }
+
+    /// Writes trades and books of the recorded coins and notes gaps; others are ignored.
+    pub fn handle_message(&mut self, message: &Message) -> Result<()> {
+        self.handle_message_at(message, now_timestamp_ms())
+    }
+
+    /// Fetches candles covering each gap and writes them. Gaps that fail stay queued.
+    pub async fn backfill(&mut self, info: &InfoClient) -> Result<()> {
+        while let Some(gap) = self.gaps.first().cloned() {
+            let candles = info
+                .candles_history(
+                    gap.coin.clone(),
+                    self.candle_interval.clone(),
+                    gap.start.into(),
+                    gap.end.into(),
+                )
+                .collect_all()
+                .await?;
+            info!(
+                "Backfilling {} {} candles between {} and {}",
+                candles.len(),
+                gap.coin,
+                gap.start,
+                gap.end
+            );
+            for candle in candles {
+                let message = Message::Candle(Candle {
+                    data: candle.into(),
+                });
+                self.write(&message, now_timestamp_ms())?;
+            }
+            self.gaps.remove(0);
+        }
+        Ok(())
+    }
+
+    /// Closes the open file and saves checkpoints.
+    pub fn close(&mut self) -> Result<()> {
+        let Some((_, stem, file)) = self.file.take() else {
+            return Ok(());
+        };
+        match file {
+            TapeFile::Jsonl(encoder) => {
+                encoder
+                    .finish()
+                    .map_err(|e| Error::Io(e.to_string()))?
+                    .flush()
+                    .map_err(|e| Error::Io(e.to_string()))?;
+                self.written.push(stem.with_extension("jsonl.lz4"));
+            }
+            #[cfg(feature = "parquet")]
+            TapeFile::Parquet {
+                trades,
+                books,
+                candles,
+            } => {
+                if !trades.is_empty() {
+                    let path = stem.with_extension("trades.parquet");
+                    write_trades_parquet(&path, &trades)?;
+                    self.written.push(path);
+                }
+                if !books.is_empty() {
+                    let path = stem.with_extension("l2book.parquet");
+                    write_l2_books_parquet(&path, &books)?;
+                    self.written.push(path);
+                }
+                if !candles.is_empty() {
+                    let path = stem.with_extension("candles.parquet");
+                    write_candles_parquet(&path, &candles)?;
+                    self.written.push(path);
+                }
+            }
+        }
+        if let Some(store) = &self.checkpoint_store {
+            for (coin, checkpoint) in &self.checkpoints {
+                store.save(&format!("tape-{coin}"), checkpoint)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Subscribes to the recorded coins and records until `shutdown` completes, backfilling
+    /// gaps as they are found, then closes the open file. Backfill errors are logged and
+    /// retried on the next message.
+    #[cfg(feature = "ws")]
+    pub async fn run(
+        &mut self,
+        info: &InfoClient,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let (sender, mut receiver) = unbounded_channel();
+        for subscription in self.subscriptions() {
+            info.subscribe(subscription, sender.clone()).await?;
+        }
+        let mut shutdown = pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    self.handle_message(&message)?;
+                    if !self.gaps.is_empty() {
+                        if let Err(err) = self.backfill(info).await {
+                            warn!("Could not backfill tape: {err}");
+                        }
+                    }
+                }
+            }
+        }
+        self.close()
+    }
+
+    fn handle_message_at(&mut self, message: &Message, now: u64) -> Result<()> {
+        match message {
+            Message::NoData => {
+                self.disconnected_at.get_or_insert(now);
+                return Ok(());
+            }
+            Message::Trades(trades) => {
+                let Some(coin) = trades.data.first().map(|trade| trade.coin.clone()) else {
+                    return Ok(());
+                };
+                if !self.coins.contains(&coin) {
+                    return Ok(());
+                }
+                self.reconnected(now);
+                if self.checkpoint_store.is_some() {
+                    let trades = self.new_trades(&coin, &trades.data)?;
+                    if trades.is_empty() {
+                        return Ok(());
+                    }
+                    let message = Message::Trades(Trades { data: trades });
+                    return self.write_trades(coin, &message, now);
+                }
+                return self.write_trades(coin, message, now);
+            }
+            Message::L2Book(book) if self.books && self.coins.contains(&book.data.coin) => {
+                self.reconnected(now);
+            }
+            _ => return Ok(()),
+        }
+        self.write(message, now)
+    }
+
+    /// Trades of `coin` not on tape yet, advancing its checkpoint. The first trades of a coin
+    /// with a saved checkpoint queue a gap back to it.
+    fn new_trades(&mut self, coin: &str, trades: &[Trade]) -> Result<Vec<Trade>> {
+        if !self.checkpoints.contains_key(coin) {
+            let saved = match &self.checkpoint_store {
+                Some(store) => store.load(&format!("tape-{coin}"))?,
+                None => None,
+            };
+            let first = trades.iter().map(|trade| trade.time).min();
+            if let (Some(saved), Some(first)) = (&saved, first) {
+                if first > saved.time {
+                    self.push_gap(TapeGap {
+                        coin: coin.to_string(),
+                        start: saved.time.as_millis(),
+                        end: first.as_millis(),
+                    });
+                }
+            }
+            self.checkpoints
+                .insert(coin.to_string(), saved.unwrap_or_default());
+        }
+        let checkpoint = self
+            .checkpoints
+            .get_mut(coin)
+            .expect("checkpoint inserted above");
+        let mut trades = trades.to_vec();
+        trades.sort_by_key(|trade| trade.time);
+        trades.retain(|trade| checkpoint.advance(trade));
+        Ok(trades)
+    }
+
+    /// Notes a gap if the trades in `message` start too long after the previous ones of
+    /// `coin`, then writes them.
+    fn write_trades(&mut self, coin: String, message: &Message, now: u64) -> Result<()> {
+        let Message::Trades(trades) = message else {
+            return Ok(());
+        };
+        let first = trades.data.iter().map(|trade| trade.time.as_millis()).min();
+        let last = trades.data.iter().map(|trade| trade.time.as_millis()).max();
+        if let (Some(first), Some(last)) = (first, last) {
+            let previous = self.last_trade.insert(coin.clone(), last);
+            if let (Some(previous), Some(max_gap)) = (previous, self.max_trade_gap) {
+                if first.saturating_sub(previous) > max_gap.as_millis() as u64 {
+                    self.push_gap(TapeGap {
+                        coin,
+                        start: previous,
+                        end: first,
+                    });
+                }
+            }
+        }
+        self.write(message, now)
+    }
+
+    /// Queues a gap on every coin for the time the websocket was down.
+    fn reconnected(&mut self, now: u64) {
+        let Some(start) = self.disconnected_at.take() else {
+            return;
+        };
+        let mut coins: Vec<&String> = self.coins.iter().collect();
+        coins.sort();
+        let mut gaps = Vec::with_capacity(coins.len());
+        for coin in coins {
+            let start = self
+                .last_trade
+                .get(coin)
+                .copied()
+                .unwrap_or(start)
+                .min(start);
+            gaps.push(TapeGap {
+                coin: coin.clone(),
+                start,
+                end: now,
+            });
+        }
+        for gap in gaps {
+            self.push_gap(gap);
+        }
+    }
+
+    /// Queues `gap`, merged into a queued gap of the same coin it overlaps.
+    fn push_gap(&mut self, gap: TapeGap) {
+        let overlapping = self.gaps.iter_mut().find(|queued| {
+            queued.coin == gap.coin && queued.start <= gap.end && gap.start <= queued.end
+        });
+        match overlapping {
+            Some(queued) => {
+                queued.start = queued.start.min(gap.start);
+                queued.end = queued.end.max(gap.end);
+            }
+            None => self.gaps.push(gap),
+        }
+    }
+
+    fn write(&mut self, message: &Message, now: u64) -> Result<()> {
+        let rotation = self.rotation.as_millis() as u64;
+        let start = now - now % rotation;
+        if self
+            .file
+            .as_ref()
+            .is_some_and(|(open, _, _)| *open != start)
+        {
+            self.close()?;
+        }
+        if self.file.is_none() {
+            let stem = self.stem(start);
+            let file = self.open(&stem)?;
+            self.file = Some((start, stem, file));
+        }
+        let Some((_, _, file)) = &mut self.file else {
+            return Ok(());
+        };
+        match file {
+            TapeFile::Jsonl(encoder) => {
+                serde_json::to_writer(&mut *encoder, message)
+                    .map_err(|e| Error::JsonParse(e.to_string()))?;
+                encoder
+                    .write_all(b"\n")
+                    .map_err(|e| Error::Io(e.to_string()))?;
+            }
+            #[cfg(feature = "parquet")]
+            TapeFile::Parquet {
+                trades,
+                books,
+                candles,
+            } => match message {
+                Message::Trades(message) => trades.extend(message.data.iter().cloned()),
+                Message::L2Book(message) => books.push(message.data.clone()),
+                Message::Candle(message) => candles.push(message.data.clone()),
+                _ => {}
+            },
+        }
+        Ok(())
+    }
+
+    fn open(&self, stem: &Path) -> Result<TapeFile> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(match self.format {
+            TapeFormat::Jsonl => {
+                let path = stem.with_extension("jsonl.lz4");
+                let file = File::create(path).map_err(|e| Error::Io(e.to_string()))?;
+                TapeFile::Jsonl(FrameEncoder::new(BufWriter::new(file)))
+            }
+            #[cfg(feature = "parquet")]
+            TapeFormat::Parquet => TapeFile::Parquet {
+                trades: Vec::new(),
+                books: Vec::new(),
+                candles: Vec::new(),
+            },
+        })
+    }
+
+    /// Path without extension of the file for the period starting at `start`, numbered if
+    /// an earlier run already wrote one.
+    fn stem(&self, start: u64) -> PathBuf {
+        const EXTENSIONS: [&str; 4] = [
+            "jsonl.lz4",
+            "trades.parquet",
+            "l2book.parquet",
+            "candles.parquet",
+        ];
+        let name = DateTime::from_timestamp_millis(start as i64)
+            .map(|time| time.format("%Y%m%d-%H%M%S").to_string())
+            .unwrap_or_else(|| start.to_string());
+        (0..)
+            .map(|n| match n {
+                0 => self.dir.join(&name),
+                n => self.dir.join(format!("{name}-{n}")),
+            })
+            .find(|stem| {
+                EXTENSIONS
+                    .iter()
+                    .all(|extension| !stem.with_extension(extension).exists())
+            })
+            .expect("unbounded range")
+    }
+}
+
+impl Drop for TapeRecorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.close() {
+            warn!("Could not close tape file: {err}");
+        }
+    }
+}
+
+/// Reads the messages of JSON lines tapes written by `TapeRecorder`, in time order across all
+/// `paths`, ready for `Backtester::run`.
+pub fn read_tape(paths: &[impl AsRef<Path>]) -> Result<Vec<Message>> {
+    let mut messages = Vec::new();
+    for path in paths {
+        let file = File::open(path).map_err(|e| Error::Io(e.to_string()))?;
+        for line in BufReader::new(FrameDecoder::new(file)).lines() {
+            let line = line.map_err(|e| Error::Io(e.to_string()))?;
+            if line.is_empty() {
+                continue;
+            }
+            let message: Message =
+                serde_json::from_str(&line).map_err(|e| Error::JsonParse(e.to_string()))?;
+            messages.push((message_time(&message), message));
+        }
+    }
+    // Backfilled candles are written when fetched, after the trades around them
+    messages.sort_by_key(|(time, _)| *time);
+    Ok(messages.into_iter().map(|(_, message)| message).collect())
+}
+
+fn message_time(message: &Message) -> u64 {
+    match message {
+        Message::Candle(candle) => candle.data.time_close.as_millis(),
+        Message::Trades(trades) => trades.data.last().map_or(0, |trade| trade.time.as_millis()),
+        Message::L2Book(book) => book.data.time.as_millis(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FileCheckpointStore;
+
+    fn trade(time: u64) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"trades","data":[{{"coin":"ETH","side":"B","px":"2000","sz":"1","time":{time},"hash":"0x0","tid":{time},"users":["0x01","0x02"]}}]}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_rotates_and_notes_gaps() {
+        let dir = std::env::temp_dir().join(format!("tape_{}", std::process::id()));
+        let mut recorder = TapeRecorder::new(&dir, ["ETH".to_string()])
+            .with_max_trade_gap(Duration::from_secs(60));
+        let hour = 60 * 60 * 1000;
+        recorder
+            .handle_message_at(&trade(hour + 1000), hour + 1000)
+            .unwrap();
+        recorder
+            .handle_message_at(&trade(hour + 2000), hour + 2000)
+            .unwrap();
+        recorder
+            .handle_message_at(&Message::NoData, hour + 3000)
+            .unwrap();
+        recorder
+            .handle_message_at(&trade(2 * hour + 1000), 2 * hour + 1000)
+            .unwrap();
+        let other: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"BTC","time":1,"levels":[[],[]]}}"#,
+        )
+        .unwrap();
+        recorder.handle_message_at(&other, 2 * hour + 2000).unwrap();
+
+        assert_eq!(
+            recorder.gaps(),
+            [TapeGap {
+                coin: "ETH".to_string(),
+                start: hour + 2000,
+                end: 2 * hour + 1000,
+            }]
+        );
+        let candle: Message = serde_json::from_str(
+            r#"{"channel":"candle","data":{"t":3600000,"T":3659999,"s":"ETH","i":"1m","o":"2000","c":"2001","h":"2002","l":"1999","v":"3","n":2}}"#,
+        )
+        .unwrap();
+        recorder.write(&candle, 2 * hour + 3000).unwrap();
+        recorder.close().unwrap();
+
+        assert_eq!(recorder.written().len(), 2);
+        assert!(recorder.written()[0].ends_with("19700101-010000.jsonl.lz4"));
+        let messages = read_tape(recorder.written()).unwrap();
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(messages[2], Message::Candle(_)));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_resumes_from_checkpoint() {
+        let dir = std::env::temp_dir().join(format!("tape_resume_{}", std::process::id()));
+        let store = FileCheckpointStore::new(dir.join("checkpoints"));
+        let hour = 60 * 60 * 1000;
+        let mut recorder =
+            TapeRecorder::new(&dir, ["ETH".to_string()]).with_checkpoints(store.clone());
+        recorder
+            .handle_message_at(&trade(hour + 1000), hour + 1000)
+            .unwrap();
+        recorder.close().unwrap();
+
+        // Restarted within the same hour: the snapshot repeats the trade already on tape
+        let mut recorder = TapeRecorder::new(&dir, ["ETH".to_string()]).with_checkpoints(store);
+        recorder
+            .handle_message_at(&trade(hour + 1000), hour + 4000)
+            .unwrap();
+        assert!(recorder.gaps().is_empty());
+        recorder
+            .handle_message_at(&trade(hour + 5000), hour + 5000)
+            .unwrap();
+        recorder.close().unwrap();
+        assert!(recorder.written()[0].ends_with("19700101-010000-1.jsonl.lz4"));
+        assert_eq!(read_tape(recorder.written()).unwrap().len(), 1);
+
+        // Trades after a checkpoint backfill the time in between
+        let mut recorder = TapeRecorder::new(&dir, ["ETH".to_string()])
+            .with_checkpoints(FileCheckpointStore::new(dir.join("checkpoints")));
+        recorder
+            .handle_message_at(&trade(hour + 9000), hour + 9000)
+            .unwrap();
+        assert_eq!(
+            recorder.gaps(),
+            [TapeGap {
+                coin: "ETH".to_string(),
+                start: hour + 5000,
+                end: hour + 9000,
+            }]
+        );
+        drop(recorder);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -61,4 +61,46 @@ pub enum Error {
     SignatureFailure(String),
     #[error("Vault address not found")]
     VaultAddressNotFound,
+    #[error("Rate limited: {0}")]
+    RateLimited(String),
+    /// The outcome of the request is unknown: it may or may not have been processed
+    #[error("Request timed out: {0:?}")]
+    Timeout(String),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("A runtime is already set")]
+    RuntimeAlreadySet,
+    #[error("A clock is already set")]
+    ClockAlreadySet,
+    #[cfg(feature = "exchange")]
+    #[error("Risk check failed: {0}")]
+    RiskCheck(crate::RiskViolation),
+    #[cfg(feature = "exchange")]
+    #[error("Spot precondition failed: {0}")]
+    SpotPrecondition(crate::SpotPrecondition),
+    #[error("Account not funded: {0}")]
+    NotFunded(String),
+    #[error("Invalid order: {0}")]
+    InvalidOrder(String),
+    #[error("Order not found: {0}")]
+    OrderNotFound(String),
+    #[error("Io error: {0:?}")]
+    Io(String),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+    #[error("Secret error: {0}")]
+    Secret(String),
+    #[error("Circuit breaker open, retry in {0:?}")]
+    CircuitOpen(std::time::Duration),
+    #[error("Schema drift: {0}")]
+    SchemaDrift(String),
+    #[cfg(feature = "metrics")]
+    #[error("Metrics error: {0}")]
+    Metrics(String),
+    #[cfg(feature = "evm")]
+    #[error("HyperEVM error: {0}")]
+    Evm(String),
+    #[cfg(feature = "journal")]
+    #[error("Journal error: {0}")]
+    Journal(String),
 }
@@ -0,0 +1,372 @@
+use alloy::{
+    primitives::{address, Address, Bytes, U256},
+    sol,
+    sol_types::{SolCall, SolType, SolValue},
+};
+use reqwest::Client;
+use serde_json::json;
+
+use crate::{prelude::*, req::json_rpc, BaseUrl, Error};
+
+pub static MAINNET_EVM_RPC_URL: &str = "https://rpc.hyperliquid.xyz/evm";
+pub static TESTNET_EVM_RPC_URL: &str = "https://rpc.hyperliquid-testnet.xyz/evm";
+
+/// The system contract through which HyperEVM contracts send actions to HyperCore.
+pub const CORE_WRITER_ADDRESS: Address = address!("3333333333333333333333333333333333333333");
+
+const POSITION_PRECOMPILE: Address = address!("0000000000000000000000000000000000000800");
+const SPOT_BALANCE_PRECOMPILE: Address = address!("0000000000000000000000000000000000000801");
+const VAULT_EQUITY_PRECOMPILE: Address = address!("0000000000000000000000000000000000000802");
+const WITHDRAWABLE_PRECOMPILE: Address = address!("0000000000000000000000000000000000000803");
+const DELEGATOR_SUMMARY_PRECOMPILE: Address = address!("0000000000000000000000000000000000000805");
+const MARK_PX_PRECOMPILE: Address = address!("0000000000000000000000000000000000000806");
+const ORACLE_PX_PRECOMPILE: Address = address!("0000000000000000000000000000000000000807");
+const SPOT_PX_PRECOMPILE: Address = address!("0000000000000000000000000000000000000808");
+const L1_BLOCK_NUMBER_PRECOMPILE: Address = address!("0000000000000000000000000000000000000809");
+
+/// Limit prices and sizes in CoreWriter orders are scaled by 10^8.
+const CORE_WRITER_SCALE: f64 = 100_000_000.0;
+
+sol! {
+    /// A perp position as returned by the position precompile, in raw integer units.
+    #[derive(Debug, PartialEq, Eq)]
+    struct EvmPosition {
+        int64 szi;
+        uint64 entryNtl;
+        int64 isolatedRawUsd;
+        uint32 leverage;
+        bool isIsolated;
+    }
+
+    /// A spot balance as returned by the spot balance precompile, in the token's wei.
+    #[derive(Debug, PartialEq, Eq)]
+    struct EvmSpotBalance {
+        uint64 total;
+        uint64 hold;
+        uint64 entryNtl;
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct EvmVaultEquity {
+        uint64 equity;
+        uint64 lockedUntilTimestamp;
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct EvmDelegatorSummary {
+        uint64 delegated;
+        uint64 undelegated;
+        uint64 totalPendingWithdrawal;
+        uint64 nPendingWithdrawals;
+    }
+
+    function sendRawAction(bytes data);
+}
+
+/// Time in force of a CoreWriter limit order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoreWriterTif {
+    Alo = 1,
+    Gtc = 2,
+    Ioc = 3,
+}
+
+/// An action a HyperEVM contract can send to HyperCore through `CoreWriter`.
+///
+/// Amounts are raw integers as HyperCore stores them: `wei` in the token's wei decimals and
+/// `usd`/`ntl` in USDC with 6 decimals. Only `LimitOrder` takes human prices and sizes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CoreWriterAction {
+    LimitOrder {
+        asset: u32,
+        is_buy: bool,
+        limit_px: f64,
+        sz: f64,
+        reduce_only: bool,
+        tif: CoreWriterTif,
+        /// 0 for no cloid
+        cloid: u128,
+    },
+    VaultTransfer {
+        vault: Address,
+        is_deposit: bool,
+        usd: u64,
+    },
+    TokenDelegate {
+        validator: Address,
+        wei: u64,
+        is_undelegate: bool,
+    },
+    StakingDeposit {
+        wei: u64,
+    },
+    StakingWithdraw {
+        wei: u64,
+    },
+    SpotSend {
+        destination: Address,
+        token: u64,
+        wei: u64,
+    },
+    UsdClassTransfer {
+        ntl: u64,
+        to_perp: bool,
+    },
+    AddApiWallet {
+        wallet: Address,
+        name: String,
+    },
+    CancelByOid {
+        asset: u32,
+        oid: u64,
+    },
+    CancelByCloid {
+        asset: u32,
+        cloid: u128,
+    },
+}
+
+impl CoreWriterAction {
+    fn id(&self) -> u32 {
+        match self {
+            CoreWriterAction::LimitOrder { .. } => 1,
+            CoreWriterAction::VaultTransfer { .. } => 2,
+            CoreWriterAction::TokenDelegate { .. } => 3,
+            CoreWriterAction::StakingDeposit { .. } => 4,
+            CoreWriterAction::StakingWithdraw { .. } => 5,
+            CoreWriterAction::SpotSend { .. } => 6,
+            CoreWriterAction::UsdClassTransfer { .. } => 7,
+            CoreWriterAction::AddApiWallet { .. } => 9,
+            CoreWriterAction::CancelByOid { .. } => 10,
+            CoreWriterAction::CancelByCloid { .. } => 11,
+        }
+    }
+
+    /// The `data` passed to `sendRawAction`: a version byte, the 3 byte action id and the
+    /// ABI encoded fields.
+    pub fn encode(&self) -> Bytes {
+        let fields = match self {
+            CoreWriterAction::LimitOrder {
+                asset,
+                is_buy,
+                limit_px,
+                sz,
+                reduce_only,
+                tif,
+                cloid,
+            } => (
+                // Every uint is padded to a word, so `uint8 tif` and `uint128 cloid` encode
+                // the same as these wider types
+                *asset,
+                *is_buy,
+                (limit_px * CORE_WRITER_SCALE).round() as u64,
+                (sz * CORE_WRITER_SCALE).round() as u64,
+                *reduce_only,
+                *tif as u32,
+                U256::from(*cloid),
+            )
+                .abi_encode_params(),
+            CoreWriterAction::VaultTransfer {
+                vault,
+                is_deposit,
+                usd,
+            } => (*vault, *is_deposit, *usd).abi_encode_params(),
+            CoreWriterAction::TokenDelegate {
+                validator,
+                wei,
+                is_undelegate,
+            } => (*validator, *wei, *is_undelegate).abi_encode_params(),
+            CoreWriterAction::StakingDeposit { wei }
+            | CoreWriterAction::StakingWithdraw { wei } => (*wei,).abi_encode_params(),
+            CoreWriterAction::SpotSend {
+                destination,
+                token,
+                wei,
+            } => (*destination, *token, *wei).abi_encode_params(),
+            CoreWriterAction::UsdClassTransfer { ntl, to_perp } => {
+                (*ntl, *to_perp).abi_encode_params()
+            }
+            CoreWriterAction::AddApiWallet { wallet, name } => {
+                (*wallet, name.clone()).abi_encode_params()
+            }
+            CoreWriterAction::CancelByOid { asset, oid } => (*asset, *oid).abi_encode_params(),
+            CoreWriterAction::CancelByCloid { asset, cloid } => {
+                (*asset, U256::from(*cloid)).abi_encode_params()
+            }
+        };
+        let mut data = Vec::with_capacity(4 + fields.len());
+        data.push(1);
+        data.extend_from_slice(&self.id().to_be_bytes()[1..]);
+        data.extend_from_slice(&fields);
+        data.into()
+    }
+
+    /// Calldata for `CoreWriter.sendRawAction`, to send in a transaction to
+    /// `CORE_WRITER_ADDRESS` or to embed in a contract call.
+    pub fn calldata(&self) -> Bytes {
+        sendRawActionCall {
+            data: self.encode(),
+        }
+        .abi_encode()
+        .into()
+    }
+}
+
+/// Reads HyperCore state from HyperEVM through the read precompiles, as contracts see it.
+///
+/// Prices are raw: perp prices have `6 - szDecimals` decimals and spot prices
+/// `8 - szDecimals`, see `evm_px_to_float`. Perps are addressed by their index in
+/// `Meta::universe` and spot pairs by their `SpotAssetMeta::index`.
+#[derive(Clone, Debug)]
+pub struct EvmClient {
+    client: Client,
+    rpc_url: String,
+}
+
+impl EvmClient {
+    pub fn new(rpc_url: impl Into<String>) -> EvmClient {
+        EvmClient {
+            client: Client::new(),
+            rpc_url: rpc_url.into(),
+        }
+    }
+
+    /// The public RPC of the network `base_url` points at, localhost using mainnet's.
+    pub fn for_base_url(base_url: &BaseUrl) -> EvmClient {
+        match base_url {
+            BaseUrl::Testnet => EvmClient::new(TESTNET_EVM_RPC_URL),
+            _ => EvmClient::new(MAINNET_EVM_RPC_URL),
+        }
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// `eth_call` of `data` against `to` at the latest block.
+    pub async fn call(&self, to: Address, data: Bytes) -> Result<Bytes> {
+        let result = json_rpc(
+            &self.client,
+            &self.rpc_url,
+            "eth_call",
+            json!([{ "to": to, "data": data }, "latest"]),
+        )
+        .await?;
+        serde_json::from_value(result).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    async fn read<T: SolValue + From<<T::SolType as SolType>::RustType>>(
+        &self,
+        precompile: Address,
+        input: Vec<u8>,
+    ) -> Result<T> {
+        let output = self.call(precompile, input.into()).await?;
+        T::abi_decode(&output).map_err(|e| Error::Evm(e.to_string()))
+    }
+
+    pub async fn position(&self, user: Address, perp: u16) -> Result<EvmPosition> {
+        self.read(POSITION_PRECOMPILE, (user, perp).abi_encode_params())
+            .await
+    }
+
+    pub async fn spot_balance(&self, user: Address, token: u64) -> Result<EvmSpotBalance> {
+        self.read(SPOT_BALANCE_PRECOMPILE, (user, token).abi_encode_params())
+            .await
+    }
+
+    pub async fn vault_equity(&self, user: Address, vault: Address) -> Result<EvmVaultEquity> {
+        self.read(VAULT_EQUITY_PRECOMPILE, (user, vault).abi_encode_params())
+            .await
+    }
+
+    /// Withdrawable USDC of `user`'s perp account, with 6 decimals.
+    pub async fn withdrawable(&self, user: Address) -> Result<u64> {
+        self.read(WITHDRAWABLE_PRECOMPILE, user.abi_encode()).await
+    }
+
+    pub async fn delegator_summary(&self, user: Address) -> Result<EvmDelegatorSummary> {
+        self.read(DELEGATOR_SUMMARY_PRECOMPILE, user.abi_encode())
+            .await
+    }
+
+    pub async fn mark_px(&self, perp: u32) -> Result<u64> {
+        self.read(MARK_PX_PRECOMPILE, perp.abi_encode()).await
+    }
+
+    pub async fn oracle_px(&self, perp: u32) -> Result<u64> {
+        self.read(ORACLE_PX_PRECOMPILE, perp.abi_encode()).await
+    }
+
+    pub async fn spot_px(&self, spot: u32) -> Result<u64> {
+        self.read(SPOT_PX_PRECOMPILE, spot.abi_encode()).await
+    }
+
+    /// The HyperCore block the EVM block was built on.
+    pub async fn l1_block_number(&self) -> Result<u64> {
+        self.read(L1_BLOCK_NUMBER_PRECOMPILE, Vec::new()).await
+    }
+}
+
+/// Converts a raw precompile price to a float, given the asset's `szDecimals` and whether it
+/// is a spot pair.
+pub fn evm_px_to_float(raw: u64, sz_decimals: u32, is_spot: bool) -> f64 {
+    let max_decimals = if is_spot { 8 } else { 6 };
+    raw as f64 / 10f64.powi(max_decimals - sz_decimals as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EPSILON;
+
+    #[test]
+    fn test_core_writer_encoding() {
+        let order = CoreWriterAction::LimitOrder {
+            asset: 4,
+            is_buy: true,
+            limit_px: 2000.5,
+            sz: 0.1,
+            reduce_only: false,
+            tif: CoreWriterTif::Gtc,
+            cloid: 0,
+        }
+        .encode();
+        assert_eq!(&order[..4], &[1, 0, 0, 1]);
+        assert_eq!(order.len(), 4 + 7 * 32);
+        // limitPx is the third word
+        assert_eq!(
+            U256::from_be_slice(&order[4 + 64..4 + 96]),
+            U256::from(200_050_000_000u64)
+        );
+
+        let transfer = CoreWriterAction::UsdClassTransfer {
+            ntl: 1_000_000,
+            to_perp: true,
+        };
+        let calldata = transfer.calldata();
+        assert_eq!(&calldata[..4], &sendRawActionCall::SELECTOR);
+        assert_eq!(
+            sendRawActionCall::abi_decode(&calldata).unwrap().data,
+            transfer.encode()
+        );
+
+        assert!((evm_px_to_float(200_050, 4, false) - 2000.5).abs() < EPSILON);
+        assert!((evm_px_to_float(2_000_500, 2, true) - 2.0005).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_decode_position() {
+        let position = EvmPosition {
+            szi: -5,
+            entryNtl: 100,
+            isolatedRawUsd: 0,
+            leverage: 10,
+            isIsolated: false,
+        };
+        assert_eq!(
+            <EvmPosition as SolValue>::abi_decode(&position.abi_encode()).unwrap(),
+            position
+        );
+    }
+}
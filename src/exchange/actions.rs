@@ -3,7 +3,7 @@ use alloy::{
     primitives::{keccak256, Address, B256},
     sol_types::{eip712_domain, SolValue},
 };
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use super::{cancel::CancelRequestCloid, BuilderInfo};
 use crate::{
@@ -27,10 +27,18 @@ where
     s.serialize_str(&format!("0x{val:x}"))
 }
 
+fn deserialize_hex<'de, D>(d: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(d)?;
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).map_err(serde::de::Error::custom)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UsdSend {
-    #[serde(serialize_with = "serialize_hex")]
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
     pub signature_chain_id: u64,
     pub hyperliquid_chain: String,
     pub destination: String,
@@ -101,7 +109,7 @@ pub struct BulkCancelCloid {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ApproveAgent {
-    #[serde(serialize_with = "serialize_hex")]
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
     pub signature_chain_id: u64,
     pub hyperliquid_chain: String,
     pub agent_address: Address,
@@ -129,7 +137,7 @@ impl Eip712 for ApproveAgent {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Withdraw3 {
-    #[serde(serialize_with = "serialize_hex")]
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
     pub signature_chain_id: u64,
     pub hyperliquid_chain: String,
     pub destination: String,
@@ -157,7 +165,7 @@ impl Eip712 for Withdraw3 {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SpotSend {
-    #[serde(serialize_with = "serialize_hex")]
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
     pub signature_chain_id: u64,
     pub hyperliquid_chain: String,
     pub destination: String,
@@ -205,6 +213,16 @@ pub struct VaultTransfer {
     pub usd: u64,
 }
 
+/// Moves `usd`, in micro USDC, between the signing master account and one of its
+/// sub-accounts: into it when `is_deposit`, out of it otherwise.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubAccountUsdTransfer {
+    pub sub_account_user: Address,
+    pub is_deposit: bool,
+    pub usd: u64,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SetReferrer {
@@ -217,10 +235,93 @@ pub struct EvmUserModify {
     pub using_big_blocks: bool,
 }
 
+/// Requests linking spot `token` to the ERC-20 contract at `address` on HyperEVM.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestEvmContract {
+    pub token: u32,
+    pub address: Address,
+    /// EVM wei decimals minus the spot token's wei decimals
+    pub evm_extra_wei_decimals: i32,
+}
+
+/// Confirms a requested link, signed by the account that deployed the contract.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalizeEvmContract {
+    pub token: u32,
+    pub input: FinalizeEvmContractInput,
+}
+
+/// How the deployer proves it created the linked contract.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FinalizeEvmContractInput {
+    /// Deployed from an EOA with transaction `nonce`
+    Create { nonce: u64 },
+    /// Deployed by a contract storing the deployer in its first storage slot
+    FirstStorageSlot,
+    /// Deployed by a contract storing the deployer in the custom deployer storage slot
+    CustomStorageSlot,
+}
+
+/// Actions of a validator's signer, the key its node signs with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CSignerAction {
+    JailSelf,
+    UnjailSelf,
+}
+
+/// Actions of a validator's owner, the account holding its self-delegation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum CValidatorAction {
+    Register(ValidatorRegister),
+    ChangeProfile(ValidatorProfileChange),
+    Unregister,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NodeIp {
+    #[serde(rename = "Ip")]
+    pub ip: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidatorProfile {
+    pub node_ip: NodeIp,
+    pub name: String,
+    pub description: String,
+    pub delegations_disabled: bool,
+    pub commission_bps: u64,
+    pub signer: Address,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidatorRegister {
+    pub profile: ValidatorProfile,
+    pub unjailed: bool,
+    /// Initial self-delegation, in HYPE wei
+    pub initial_wei: u64,
+}
+
+/// Profile fields to change, others are left as they are.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ValidatorProfileChange {
+    pub node_ip: Option<NodeIp>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub unjailed: bool,
+    pub disable_delegations: Option<bool>,
+    pub commission_bps: Option<u64>,
+    pub signer: Option<Address>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ApproveBuilderFee {
-    #[serde(serialize_with = "serialize_hex")]
+    #[serde(serialize_with = "serialize_hex", deserialize_with = "deserialize_hex")]
     pub signature_chain_id: u64,
     pub hyperliquid_chain: String,
     pub builder: Address,
@@ -239,6 +340,12 @@ pub struct ScheduleCancel {
 #[serde(rename_all = "camelCase")]
 pub struct ClaimRewards;
 
+/// Does nothing but use up its nonce, so an action pre-signed with the same nonce can no
+/// longer be sent.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Noop;
+
 impl Eip712 for ApproveBuilderFee {
     fn domain(&self) -> Eip712Domain {
         eip_712_domain(self.signature_chain_id)
@@ -1,42 +1,102 @@
-use std::collections::HashMap;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use alloy::{
     primitives::{keccak256, Address, Signature, B256},
     signers::local::PrivateKeySigner,
 };
-use log::debug;
 use reqwest::Client;
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+use tracing::{debug, field, info, info_span, instrument, warn, Instrument, Span};
 
 use crate::{
+    eip712::Eip712,
     exchange::{
         actions::{
-            ApproveAgent, ApproveBuilderFee, BulkCancel, BulkModify, BulkOrder, ClaimRewards,
-            EvmUserModify, ScheduleCancel, SetReferrer, UpdateIsolatedMargin, UpdateLeverage,
-            UsdSend,
+            ApproveAgent, ApproveBuilderFee, BulkCancel, BulkModify, BulkOrder, CSignerAction,
+            CValidatorAction, ClaimRewards, EvmUserModify, FinalizeEvmContract,
+            FinalizeEvmContractInput, Noop, RequestEvmContract, ScheduleCancel, SetReferrer,
+            UpdateIsolatedMargin, UpdateLeverage, UsdSend, ValidatorProfile,
+            ValidatorProfileChange, ValidatorRegister,
         },
         cancel::{CancelRequest, CancelRequestCloid, ClientCancelRequestCloid},
+        exchange_responses::pair_statuses,
+        latency::{ActionLatency, LatencyHook},
         modify::{ClientModifyRequest, ModifyRequest},
-        order::{MarketCloseParams, MarketOrderParams},
+        order::{MarketCloseParams, MarketOrderParams, OrderRequest},
         BuilderInfo, ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest,
     },
-    helpers::{next_nonce, uuid_to_hex_string},
+    helpers::{lot_size, next_nonce, price_tick_size, uuid_to_hex_string},
     info::info_client::InfoClient,
-    meta::Meta,
+    meta::{Meta, SpotMeta},
     prelude::*,
-    req::HttpClient,
-    signature::{sign_l1_action, sign_typed_data},
-    BaseUrl, BulkCancelCloid, ClassTransfer, Error, ExchangeResponseStatus, SpotSend, SpotUser,
-    VaultTransfer, Withdraw3,
+    req::{
+        exchange_weight, http_options_setters, CancellationToken, CircuitBreaker, HttpClient,
+        HttpOptions, ProxyConfig, RateLimiter, Recorder, Replayer, RequestLogger, RequestStats,
+        RequestStatsSnapshot, RetryPolicy, Throttle, ThrottleState, Timeouts,
+    },
+    rt::{self, Instant},
+    secrets::WalletSource,
+    signature::{sign_l1_action, sign_typed_data, SignerId},
+    BaseUrl, BulkCancelCloid, BulkRequestStatus, ClassTransfer, Error, ExchangeError,
+    ExchangeResponseStatus, NonceReservation, NonceStore, OrderGuard, SecretsProvider,
+    SpotPreflight, SpotSend, SpotUser, SubAccountUsdTransfer, Tif, VaultTransfer, Withdraw3,
 };
 
-#[derive(Debug)]
+/// Cloning is cheap: clones share the HTTP connection pool, rate limiter, circuit breaker and
+/// metadata.
+#[derive(Clone)]
 pub struct ExchangeClient {
     pub http_client: HttpClient,
     pub wallet: PrivateKeySigner,
-    pub meta: Meta,
+    pub meta: Arc<Meta>,
     pub vault_address: Option<Address>,
-    pub coin_to_asset: HashMap<String, u32>,
+    /// Account the wallet signs for as an agent, see `with_account_address`
+    pub account_address: Option<Address>,
+    pub coin_to_asset: Arc<HashMap<String, u32>>,
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+    /// Price band and size caps every order is checked against, see `with_order_guard`
+    pub order_guard: Option<Arc<OrderGuard>>,
+    pub latency_hook: Option<LatencyHook>,
+    /// Journal every action is logged to with its hash, see `with_journal`
+    #[cfg(feature = "journal")]
+    pub journal: Option<Arc<crate::Journal>>,
+    /// Client whose websocket connection actions are sent over first, see `with_ws_post`
+    #[cfg(feature = "ws")]
+    pub ws_post: Option<InfoClient>,
+    /// Signs and logs actions without sending them, see `with_dry_run`
+    pub dry_run: bool,
+    /// Store every action's nonce is saved to before sending, see `with_nonce_store`
+    pub nonce_store: Option<Arc<NonceStore>>,
+}
+
+/// Order ids of simulated acks, counting down from the top so they never collide with real ones
+static DRY_RUN_OID: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Wait for a websocket post response when no request timeout is configured.
+#[cfg(feature = "ws")]
+const WS_POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shows the wallet as its `SignerId` and leaves out the metadata.
+impl fmt::Debug for ExchangeClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExchangeClient")
+            .field("signer", &SignerId::of(&self.wallet))
+            .field("vault_address", &self.vault_address)
+            .field("account_address", &self.account_address)
+            .field("base_url", &self.http_client.base_url)
+            .field("mainnet", &self.http_client.mainnet)
+            .field("dry_run", &self.dry_run)
+            .finish_non_exhaustive()
+    }
 }
 
 fn serialize_sig<S>(sig: &Signature, s: S) -> std::result::Result<S::Ok, S::Error>
@@ -75,29 +135,141 @@ pub enum Actions {
     Withdraw3(Withdraw3),
     SpotUser(SpotUser),
     VaultTransfer(VaultTransfer),
+    SubAccountTransfer(SubAccountUsdTransfer),
     SpotSend(SpotSend),
     SetReferrer(SetReferrer),
     ApproveBuilderFee(ApproveBuilderFee),
     EvmUserModify(EvmUserModify),
     ScheduleCancel(ScheduleCancel),
     ClaimRewards(ClaimRewards),
+    Noop(Noop),
+    RequestEvmContract(RequestEvmContract),
+    FinalizeEvmContract(FinalizeEvmContract),
+    #[serde(rename = "CSignerAction")]
+    CSignerAction(CSignerAction),
+    #[serde(rename = "CValidatorAction")]
+    CValidatorAction(CValidatorAction),
 }
 
 impl Actions {
-    fn hash(&self, timestamp: u64, vault_address: Option<Address>) -> Result<B256> {
-        let mut bytes =
-            rmp_serde::to_vec_named(self).map_err(|e| Error::RmpParse(e.to_string()))?;
-        bytes.extend(timestamp.to_be_bytes());
-        if let Some(vault_address) = vault_address {
-            bytes.push(1);
-            bytes.extend(vault_address);
-        } else {
-            bytes.push(0);
-        }
-        Ok(keccak256(bytes))
+    /// The hash signed for the action as an L1 action, its msgpack encoding followed by the
+    /// nonce and vault address. Encodings are written to a per-thread buffer reused across calls.
+    pub fn hash(&self, timestamp: u64, vault_address: Option<Address>) -> Result<B256> {
+        HASH_BUFFER.with_borrow_mut(|bytes| {
+            bytes.clear();
+            rmp_serde::encode::write_named(bytes, self)
+                .map_err(|e| Error::RmpParse(e.to_string()))?;
+            Ok(finish_hash(bytes, timestamp, vault_address))
+        })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        rmp_serde::to_vec_named(self).map_err(|e| Error::RmpParse(e.to_string()))
+    }
+}
+
+thread_local! {
+    static HASH_BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Appends the nonce and vault address to an action's msgpack encoding and hashes the result.
+fn finish_hash(bytes: &mut Vec<u8>, timestamp: u64, vault_address: Option<Address>) -> B256 {
+    bytes.extend(timestamp.to_be_bytes());
+    if let Some(vault_address) = vault_address {
+        bytes.push(1);
+        bytes.extend(vault_address);
+    } else {
+        bytes.push(0);
+    }
+    keccak256(&bytes)
+}
+
+/// An L1 action signed ahead of time with a given nonce, by `ExchangeClient::sign_action` or
+/// `ExchangeClient::sign_prepared`, sent later with `ExchangeClient::send_signed`. It stays
+/// valid until its nonce is used, falls out of the exchange's nonce window, or is invalidated
+/// with `ExchangeClient::noop`.
+#[derive(Clone, Debug)]
+pub struct SignedAction {
+    action: serde_json::Value,
+    signature: Signature,
+    nonce: u64,
+    hash: B256,
+}
+
+impl SignedAction {
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// The connection id that was signed.
+    pub fn hash(&self) -> B256 {
+        self.hash
+    }
+
+    pub fn action(&self) -> &serde_json::Value {
+        &self.action
+    }
+}
+
+/// An order resolved, validated and encoded ahead of time by `ExchangeClient::prepare_order`,
+/// so that submitting it only takes a nonce, a signature and the request.
+#[derive(Clone, Debug)]
+pub struct PreparedOrder {
+    coin: String,
+    cloid: Option<uuid::Uuid>,
+    request: OrderRequest,
+    encoded: Vec<u8>,
+    action: serde_json::Value,
+}
+
+impl PreparedOrder {
+    pub fn coin(&self) -> &str {
+        &self.coin
+    }
+
+    pub fn cloid(&self) -> Option<uuid::Uuid> {
+        self.cloid
+    }
+
+    /// The order as it will be sent.
+    pub fn request(&self) -> &OrderRequest {
+        &self.request
+    }
+
+    /// The hash signed when submitting with `timestamp` as the nonce.
+    pub fn hash(&self, timestamp: u64, vault_address: Option<Address>) -> B256 {
+        HASH_BUFFER.with_borrow_mut(|bytes| {
+            bytes.clear();
+            bytes.extend_from_slice(&self.encoded);
+            finish_hash(bytes, timestamp, vault_address)
+        })
+    }
+}
+
+/// Keeps connections to the API open in the background until dropped. See
+/// `ExchangeClient::keep_warm`.
+#[derive(Debug)]
+pub struct KeepWarm {
+    stop_flag: Arc<AtomicBool>,
+}
+
+impl Drop for KeepWarm {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
     }
 }
 
+/// Asset ids by coin name for perps and spot pairs.
+pub(crate) fn coin_to_asset(meta: &Meta, spot_meta: &SpotMeta) -> HashMap<String, u32> {
+    let coin_to_asset = meta
+        .universe
+        .iter()
+        .enumerate()
+        .map(|(asset_ind, asset)| (asset.name.clone(), asset_ind as u32))
+        .collect();
+    spot_meta.add_pair_and_name_to_index_map(coin_to_asset)
+}
+
 impl ExchangeClient {
     pub async fn new(
         client: Option<Client>,
@@ -106,44 +278,281 @@ impl ExchangeClient {
         meta: Option<Meta>,
         vault_address: Option<Address>,
     ) -> Result<ExchangeClient> {
-        let client = client.unwrap_or_default();
-        let base_url = base_url.unwrap_or(BaseUrl::Mainnet);
-
-        let info = InfoClient::new(None, Some(base_url)).await?;
-        let meta = if let Some(meta) = meta {
-            meta
-        } else {
-            info.meta().await?
-        };
-
-        let mut coin_to_asset = HashMap::new();
-        for (asset_ind, asset) in meta.universe.iter().enumerate() {
-            coin_to_asset.insert(asset.name.clone(), asset_ind as u32);
+        let mut builder = ExchangeClient::builder().wallet(wallet);
+        if let Some(client) = client {
+            builder = builder.client(client);
+        }
+        if let Some(base_url) = base_url {
+            builder = builder.base_url(base_url);
         }
+        if let Some(meta) = meta {
+            builder = builder.meta(meta);
+        }
+        if let Some(vault_address) = vault_address {
+            builder = builder.vault_address(vault_address);
+        }
+        builder.build().await
+    }
 
-        coin_to_asset = info
-            .spot_meta()
-            .await?
-            .add_pair_and_name_to_index_map(coin_to_asset);
+    pub fn builder() -> ExchangeClientBuilder {
+        ExchangeClientBuilder::default()
+    }
 
-        Ok(ExchangeClient {
+    /// Assembles a client from already fetched metadata, sharing `http_client` with others.
+    pub(crate) fn from_parts(
+        http_client: HttpClient,
+        wallet: PrivateKeySigner,
+        meta: Meta,
+        spot_meta: &SpotMeta,
+        vault_address: Option<Address>,
+    ) -> ExchangeClient {
+        ExchangeClient {
+            coin_to_asset: Arc::new(coin_to_asset(&meta, spot_meta)),
             wallet,
-            meta,
+            meta: Arc::new(meta),
             vault_address,
-            http_client: HttpClient {
-                client,
-                base_url: base_url.get_url(),
-            },
-            coin_to_asset,
+            account_address: None,
+            http_client,
+            circuit_breaker: None,
+            order_guard: None,
+            latency_hook: None,
+            #[cfg(feature = "journal")]
+            journal: None,
+            #[cfg(feature = "ws")]
+            ws_post: None,
+            dry_run: false,
+            nonce_store: None,
+        }
+    }
+
+    /// Info client sharing this client's HTTP configuration.
+    pub fn info_client(&self) -> InfoClient {
+        InfoClient::with_http_client(self.http_client.clone(), false)
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.http_client.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.http_client.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Shares rate-limit cooldowns with other clients talking to the same API.
+    pub fn with_throttle(mut self, throttle: Arc<Throttle>) -> Self {
+        self.http_client.throttle = throttle;
+        self
+    }
+
+    /// Counts requests in `stats`, shared with other clients.
+    pub fn with_request_stats(mut self, stats: Arc<RequestStats>) -> Self {
+        self.http_client.stats = stats;
+        self
+    }
+
+    /// Reports every request and response, with signatures redacted, to `logger`.
+    pub fn with_request_logger(mut self, logger: RequestLogger) -> Self {
+        self.http_client.request_logger = Some(logger);
+        self
+    }
+
+    /// Records every REST response to `recorder`.
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.http_client.recorder = Some(recorder);
+        self
+    }
+
+    /// Answers requests from a recording instead of the network.
+    pub fn with_replayer(mut self, replayer: Arc<Replayer>) -> Self {
+        self.http_client.replayer = Some(replayer);
+        self
+    }
+
+    /// Aborts requests in flight once `token` is cancelled. Later requests fail with
+    /// `Error::Cancelled`.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.http_client.cancel = Some(token);
+        self
+    }
+
+    /// Publishes request metrics to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<crate::Metrics>) -> Self {
+        self.http_client.metrics = Some(metrics);
+        self
+    }
+
+    pub fn throttle_state(&self) -> ThrottleState {
+        self.http_client.throttle.state()
+    }
+
+    /// Orders, cancels, modifies and info requests sent over the last minute and in total, by
+    /// this client and those sharing its `RequestStats`.
+    pub fn request_stats(&self) -> RequestStatsSnapshot {
+        self.http_client.stats.snapshot()
+    }
+
+    /// Sets default timeouts. A connect timeout requires rebuilding the underlying reqwest
+    /// client, so configure it on a custom client instead if one was passed in.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Result<Self> {
+        if timeouts.connect.is_some() {
+            self.http_client.connect_timeout = timeouts.connect;
+            self.http_client.rebuild_client()?;
+        }
+        self.http_client.timeout = timeouts.request;
+        Ok(self)
+    }
+
+    /// Routes requests through `proxy`. Like a connect timeout this rebuilds the underlying
+    /// reqwest client, so set it on a custom client instead if one was passed in.
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Result<Self> {
+        self.http_client.proxy = Some(proxy);
+        self.http_client.rebuild_client()?;
+        Ok(self)
+    }
+
+    /// Overrides whether actions are signed for mainnet, needed when a `BaseUrl::Custom`
+    /// endpoint fronts mainnet.
+    pub fn with_mainnet(mut self, mainnet: bool) -> Self {
+        self.http_client.mainnet = mainnet;
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Checks every order placed or modified through the client against `guard`, rejecting
+    /// or clamping those priced outside its band and rejecting those above its size caps.
+    pub fn with_order_guard(mut self, guard: Arc<OrderGuard>) -> Self {
+        self.order_guard = Some(guard);
+        self
+    }
+
+    /// Reports when each action was signed, sent and acknowledged to `hook`.
+    pub fn with_latency_hook(mut self, hook: LatencyHook) -> Self {
+        self.latency_hook = Some(hook);
+        self
+    }
+
+    /// Sends actions as post requests over `info`'s websocket connection, saving the HTTP
+    /// round trip, and over REST whenever the websocket fails or no response arrives in time.
+    /// Responses are the same either way.
+    ///
+    /// The REST fallback resends the signed action with the same nonce, so an action whose
+    /// websocket response was lost is never executed twice; its fallback is rejected instead.
+    #[cfg(feature = "ws")]
+    pub fn with_ws_post(mut self, info: InfoClient) -> Self {
+        self.ws_post = Some(info);
+        self
+    }
+
+    /// Logs every action sent to `journal` with its nonce, signer and hash, the connection id
+    /// of L1 actions or the EIP-712 signing hash of user-signed ones, and whether it was
+    /// accepted, for correlating support requests and explorer lookups with what was signed.
+    #[cfg(feature = "journal")]
+    pub fn with_journal(mut self, journal: Arc<crate::Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Saves the nonce of every action to `store` before sending it, so a restarted process
+    /// does not reuse it. Open the store before any action is signed.
+    pub fn with_nonce_store(mut self, store: Arc<NonceStore>) -> Self {
+        self.nonce_store = Some(store);
+        self
+    }
+
+    /// Marks the wallet as an agent of `account`, which orders belong to and whose
+    /// address-based rate limits are tracked. Not needed when trading for a vault.
+    pub fn with_account_address(mut self, account: Address) -> Self {
+        self.account_address = Some(account);
+        self
+    }
+
+    /// Signs every action and logs the signed payload at info level without sending it,
+    /// returning simulated acks instead: orders rest with made-up oids and cancels and
+    /// modifies succeed. Useful for shadow-testing a strategy against live data.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// `hyperliquidChain` of user-signed actions, checked against the API they are sent to.
+    fn hyperliquid_chain(&self) -> Result<String> {
+        self.http_client.check_network()?;
+        Ok(if self.http_client.is_mainnet() {
+            "Mainnet".to_string()
+        } else {
+            "Testnet".to_string()
         })
     }
 
+    /// Sends a signed action. `hash` is what was signed: the connection id of L1 actions or
+    /// the EIP-712 signing hash of user-signed ones.
     async fn post(
         &self,
         action: serde_json::Value,
         signature: Signature,
         nonce: u64,
+        hash: B256,
+    ) -> Result<ExchangeResponseStatus> {
+        let Some(circuit_breaker) = &self.circuit_breaker else {
+            return self.post_action(action, signature, nonce, hash).await;
+        };
+        let permit = circuit_breaker.allow()?;
+        let result = self.post_action(action, signature, nonce, hash).await;
+        permit.record(matches!(result, Ok(ExchangeResponseStatus::Ok(_))));
+        result
+    }
+
+    #[instrument(
+        name = "exchange_action",
+        skip_all,
+        fields(action = action["type"].as_str().unwrap_or_default(), nonce, %hash, status)
+    )]
+    async fn post_action(
+        &self,
+        action: serde_json::Value,
+        signature: Signature,
+        nonce: u64,
+        hash: B256,
+    ) -> Result<ExchangeResponseStatus> {
+        // Dry-run nonces are never sent, so cannot be reused
+        if let Some(store) = self.nonce_store.as_ref().filter(|_| !self.dry_run) {
+            store.record(self.wallet.address(), nonce);
+        }
+        #[cfg(feature = "journal")]
+        let action_type = action["type"].as_str().unwrap_or_default().to_string();
+        let result = self.send_action(action, signature, nonce, hash).await;
+        #[cfg(feature = "journal")]
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.record_action(
+                &action_type,
+                nonce,
+                hash,
+                self.wallet.address(),
+                self.vault_address,
+                &result,
+            ) {
+                warn!(%hash, %err, "Could not write action to journal");
+            }
+        }
+        result
+    }
+
+    async fn send_action(
+        &self,
+        action: serde_json::Value,
+        signature: Signature,
+        nonce: u64,
+        hash: B256,
     ) -> Result<ExchangeResponseStatus> {
+        // Every action is signed right before being posted
+        let signed_at = Instant::now();
         // let signature = ExchangeSignature {
         //     r: signature.r(),
         //     s: signature.s(),
@@ -158,17 +567,112 @@ impl ExchangeClient {
         };
         let res = serde_json::to_string(&exchange_payload)
             .map_err(|e| Error::JsonParse(e.to_string()))?;
-        debug!("Sending request {res:?}");
+        debug!(action = %exchange_payload.action, nonce, %hash, "Sending request");
 
-        let output = &self
+        let (batch_length, is_cancel) = action_batch(&exchange_payload.action);
+        self.http_client.stats.record_action(
+            exchange_payload.action["type"].as_str().unwrap_or_default(),
+            batch_length,
+        );
+        // Limits apply to the account, not the agent signing for it
+        let address = crate::Exchange::address(self);
+        if let Some(rate_limiter) = self
             .http_client
-            .post("/exchange", res)
-            .await
-            .map_err(|e| Error::JsonParse(e.to_string()))?;
+            .rate_limiter
+            .as_ref()
+            .filter(|_| !self.dry_run)
+        {
+            rate_limiter
+                .acquire_address(address, batch_length as u64, is_cancel)
+                .await?;
+        }
+        if !is_cancel && !self.dry_run {
+            self.http_client.throttle.wait(address).await;
+        }
+
+        let sent_at = Instant::now();
+        let output = &self
+            .send_payload(&exchange_payload, res, batch_length)
+            .await?;
+        let received_at = Instant::now();
         debug!("Response: {output}");
-        serde_json::from_str(output).map_err(|e| Error::JsonParse(e.to_string()))
+        let response: ExchangeResponseStatus = info_span!("parse_response")
+            .in_scope(|| serde_json::from_str(output))
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        if let Some(hook) = &self.latency_hook {
+            hook.report(&ActionLatency {
+                action: exchange_payload.action["type"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                nonce,
+                hash,
+                signed_at,
+                sent_at,
+                received_at,
+                parsed_at: Instant::now(),
+            });
+        }
+        Span::current().record(
+            "status",
+            match &response {
+                ExchangeResponseStatus::Ok(_) => "ok",
+                ExchangeResponseStatus::Err(_) => "err",
+            },
+        );
+        // Address-based limits are reported in the response body rather than as a 429
+        match &response {
+            ExchangeResponseStatus::Err(ExchangeError::RateLimited(_)) => {
+                self.http_client.throttle.record_rate_limited(address)
+            }
+            ExchangeResponseStatus::Ok(_) => self.http_client.throttle.record_success(address),
+            ExchangeResponseStatus::Err(_) => {}
+        }
+        #[cfg(feature = "metrics")]
+        if let (Some(metrics), ExchangeResponseStatus::Err(_)) =
+            (&self.http_client.metrics, &response)
+        {
+            let action = exchange_payload.action["type"]
+                .as_str()
+                .unwrap_or("unknown");
+            metrics.inc_error(&format!("exchange:{action}"), "rejected");
+        }
+        Ok(response)
+    }
+
+    /// Sends a signed action over the websocket if configured, falling back to REST when it
+    /// could not be sent there.
+    async fn send_payload(
+        &self,
+        payload: &ExchangePayload,
+        text: String,
+        batch_length: usize,
+    ) -> Result<String> {
+        if self.dry_run {
+            info!(payload = %text, "Dry run, not sending action");
+            return Ok(simulated_response(&payload.action).to_string());
+        }
+        #[cfg(feature = "ws")]
+        if let Some(info) = &self.ws_post {
+            let timeout = self.http_client.timeout.unwrap_or(WS_POST_TIMEOUT);
+            let result = match serde_json::to_value(payload) {
+                // Boxed to keep the websocket connection setup out of every action's future
+                Ok(payload) => Box::pin(info.ws_post("action", &payload, timeout)).await,
+                Err(err) => Err(Error::JsonParse(err.to_string())),
+            };
+            // Only a request never sent can go over REST, the nonce of one that was sent may
+            // already be used
+            match result {
+                Ok(response) => return response.map(|response| response.to_string()),
+                Err(err) => warn!(error = %err, "Websocket post not sent, sending over REST"),
+            }
+        }
+        self.http_client
+            .post_weighted("/exchange", text, exchange_weight(batch_length))
+            .await
     }
 
+    /// Switches the wallet's HyperEVM transactions between big (`true`) and small blocks.
     pub async fn enable_big_blocks(
         &self,
         using_big_blocks: bool,
@@ -184,7 +688,7 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn usdc_transfer(
@@ -194,11 +698,7 @@ impl ExchangeClient {
         wallet: Option<&PrivateKeySigner>,
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
-        let hyperliquid_chain = if self.http_client.is_mainnet() {
-            "Mainnet".to_string()
-        } else {
-            "Testnet".to_string()
-        };
+        let hyperliquid_chain = self.hyperliquid_chain()?;
 
         let timestamp = next_nonce();
         let usd_send = UsdSend {
@@ -208,11 +708,12 @@ impl ExchangeClient {
             amount: amount.to_string(),
             time: timestamp,
         };
+        let hash = usd_send.eip712_signing_hash();
         let signature = sign_typed_data(&usd_send, wallet)?;
         let action = serde_json::to_value(Actions::UsdSend(usd_send))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, hash).await
     }
 
     pub async fn class_transfer(
@@ -235,7 +736,7 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn vault_transfer(
@@ -263,14 +764,42 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    /// Moves `usd`, in micro USDC, from the master account to `sub_account_user` when
+    /// `is_deposit`, back otherwise. Signed by the master account.
+    pub async fn sub_account_transfer(
+        &self,
+        sub_account_user: Address,
+        is_deposit: bool,
+        usd: u64,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+
+        let timestamp = next_nonce();
+
+        let action = Actions::SubAccountTransfer(SubAccountUsdTransfer {
+            sub_account_user,
+            is_deposit,
+            usd,
+        });
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let is_mainnet = self.http_client.is_mainnet();
+        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn market_open(
         &self,
         params: MarketOrderParams<'_>,
     ) -> Result<ExchangeResponseStatus> {
-        let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
+        let slippage = params
+            .slippage
+            .unwrap_or(self.http_client.defaults.slippage);
         let (px, sz_decimals) = self
             .calculate_slippage_price(params.asset, params.is_buy, slippage, params.px)
             .await?;
@@ -282,9 +811,7 @@ impl ExchangeClient {
             limit_px: px,
             sz: round_to_decimals(params.sz, sz_decimals),
             cloid: params.cloid,
-            order_type: ClientOrder::Limit(ClientLimit {
-                tif: "Ioc".to_string(),
-            }),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
         };
 
         self.order(order, params.wallet).await
@@ -295,7 +822,9 @@ impl ExchangeClient {
         params: MarketOrderParams<'_>,
         builder: BuilderInfo,
     ) -> Result<ExchangeResponseStatus> {
-        let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
+        let slippage = params
+            .slippage
+            .unwrap_or(self.http_client.defaults.slippage);
         let (px, sz_decimals) = self
             .calculate_slippage_price(params.asset, params.is_buy, slippage, params.px)
             .await?;
@@ -307,9 +836,7 @@ impl ExchangeClient {
             limit_px: px,
             sz: round_to_decimals(params.sz, sz_decimals),
             cloid: params.cloid,
-            order_type: ClientOrder::Limit(ClientLimit {
-                tif: "Ioc".to_string(),
-            }),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
         };
 
         self.order_with_builder(order, params.wallet, builder).await
@@ -319,15 +846,12 @@ impl ExchangeClient {
         &self,
         params: MarketCloseParams<'_>,
     ) -> Result<ExchangeResponseStatus> {
-        let slippage = params.slippage.unwrap_or(0.05); // Default 5% slippage
+        let slippage = params
+            .slippage
+            .unwrap_or(self.http_client.defaults.slippage);
         let wallet = params.wallet.unwrap_or(&self.wallet);
 
-        let base_url = match self.http_client.base_url.as_str() {
-            "https://api.hyperliquid.xyz" => BaseUrl::Mainnet,
-            "https://api.hyperliquid-testnet.xyz" => BaseUrl::Testnet,
-            _ => return Err(Error::GenericRequest("Invalid base URL".to_string())),
-        };
-        let info_client = InfoClient::new(None, Some(base_url)).await?;
+        let info_client = self.info_client();
         let user_state = info_client.user_state(wallet.address()).await?;
 
         let position = user_state
@@ -355,27 +879,20 @@ impl ExchangeClient {
             limit_px: px,
             sz,
             cloid: params.cloid,
-            order_type: ClientOrder::Limit(ClientLimit {
-                tif: "Ioc".to_string(),
-            }),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
         };
 
         self.order(order, Some(wallet)).await
     }
 
-    async fn calculate_slippage_price(
+    pub(crate) async fn calculate_slippage_price(
         &self,
         asset: &str,
         is_buy: bool,
         slippage: f64,
         px: Option<f64>,
     ) -> Result<(f64, u32)> {
-        let base_url = match self.http_client.base_url.as_str() {
-            "https://api.hyperliquid.xyz" => BaseUrl::Mainnet,
-            "https://api.hyperliquid-testnet.xyz" => BaseUrl::Testnet,
-            _ => return Err(Error::GenericRequest("Invalid base URL".to_string())),
-        };
-        let info_client = InfoClient::new(None, Some(base_url)).await?;
+        let info_client = self.info_client();
         let meta = info_client.meta().await?;
 
         let asset_meta = meta
@@ -436,6 +953,14 @@ impl ExchangeClient {
             .await
     }
 
+    #[instrument(
+        skip_all,
+        fields(
+            coins = ?order_coins(&orders),
+            cloids = ?order_cloids(&orders),
+            nonce = field::Empty,
+        )
+    )]
     pub async fn bulk_order(
         &self,
         orders: Vec<ClientOrderRequest>,
@@ -443,11 +968,12 @@ impl ExchangeClient {
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
         let timestamp = next_nonce();
+        Span::current().record("nonce", timestamp);
 
         let mut transformed_orders = Vec::new();
 
         for order in orders {
-            transformed_orders.push(order.convert(&self.coin_to_asset)?);
+            transformed_orders.push(self.convert_order(order)?);
         }
 
         let action = Actions::Order(BulkOrder {
@@ -460,9 +986,18 @@ impl ExchangeClient {
 
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
+    #[instrument(
+        skip_all,
+        fields(
+            coins = ?order_coins(&orders),
+            cloids = ?order_cloids(&orders),
+            builder = %builder.builder,
+            nonce = field::Empty,
+        )
+    )]
     pub async fn bulk_order_with_builder(
         &self,
         orders: Vec<ClientOrderRequest>,
@@ -471,13 +1006,14 @@ impl ExchangeClient {
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
         let timestamp = next_nonce();
+        Span::current().record("nonce", timestamp);
 
         builder.builder = builder.builder.to_lowercase();
 
         let mut transformed_orders = Vec::new();
 
         for order in orders {
-            transformed_orders.push(order.convert(&self.coin_to_asset)?);
+            transformed_orders.push(self.convert_order(order)?);
         }
 
         let action = Actions::Order(BulkOrder {
@@ -490,7 +1026,199 @@ impl ExchangeClient {
 
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    pub async fn bulk_order_with_statuses(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<Vec<BulkRequestStatus<ClientOrderRequest>>> {
+        let response = self.bulk_order(orders.clone(), wallet).await?;
+        Ok(pair_statuses(orders, response))
+    }
+
+    /// Resolves, validates and encodes `order` ahead of time, leaving `submit_prepared` to sign
+    /// and send it when the signal fires. Perp prices and sizes must lie on the asset's tick and
+    /// lot sizes; spot orders are only checked for positive values.
+    pub fn prepare_order(&self, order: ClientOrderRequest) -> Result<PreparedOrder> {
+        self.validate_order(&order)?;
+        let coin = order.asset.clone();
+        let cloid = order.cloid;
+        let request = self.convert_order(order)?;
+        let action = Actions::Order(BulkOrder {
+            orders: vec![request.clone()],
+            grouping: "na".to_string(),
+            builder: None,
+        });
+        Ok(PreparedOrder {
+            coin,
+            cloid,
+            request,
+            encoded: action.encode()?,
+            action: serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?,
+        })
+    }
+
+    /// Signs `prepared` with a fresh nonce and sends it. Submitting the same order twice places
+    /// it twice unless it has a cloid.
+    #[instrument(
+        skip_all,
+        fields(coin = prepared.coin, cloid = ?prepared.cloid, nonce = field::Empty)
+    )]
+    pub async fn submit_prepared(
+        &self,
+        prepared: &PreparedOrder,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let timestamp = next_nonce();
+        Span::current().record("nonce", timestamp);
+
+        let connection_id = prepared.hash(timestamp, self.vault_address);
+        let is_mainnet = self.http_client.is_mainnet();
+        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        self.post(prepared.action.clone(), signature, timestamp, connection_id)
+            .await
+    }
+
+    /// Signs `prepared` with `nonce` without sending it, as for a batch pre-signed with a
+    /// `NonceReservation`.
+    pub fn sign_prepared(
+        &self,
+        prepared: &PreparedOrder,
+        nonce: u64,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<SignedAction> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let hash = prepared.hash(nonce, self.vault_address);
+        Ok(SignedAction {
+            action: prepared.action.clone(),
+            signature: sign_l1_action(wallet, hash, self.http_client.is_mainnet())?,
+            nonce,
+            hash,
+        })
+    }
+
+    /// Signs the L1 action `action`, such as a cancel or `ScheduleCancel`, with `nonce` without
+    /// sending it. User-signed actions like transfers are signed differently and cannot be
+    /// pre-signed this way.
+    pub fn sign_action(
+        &self,
+        action: &Actions,
+        nonce: u64,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<SignedAction> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let hash = action.hash(nonce, self.vault_address)?;
+        Ok(SignedAction {
+            action: serde_json::to_value(action).map_err(|e| Error::JsonParse(e.to_string()))?,
+            signature: sign_l1_action(wallet, hash, self.http_client.is_mainnet())?,
+            nonce,
+            hash,
+        })
+    }
+
+    /// Sends an action signed ahead of time.
+    #[instrument(skip_all, fields(nonce = signed.nonce))]
+    pub async fn send_signed(&self, signed: &SignedAction) -> Result<ExchangeResponseStatus> {
+        self.post(
+            signed.action.clone(),
+            signed.signature,
+            signed.nonce,
+            signed.hash,
+        )
+        .await
+    }
+
+    /// Opens a pooled connection to the API with a cheap info request, so the next order does
+    /// not wait on the TCP and TLS handshakes.
+    pub async fn warm_connection(&self) -> Result<()> {
+        warm_connection(&self.http_client).await
+    }
+
+    /// Warms the connection every `interval` in the background until the returned handle is
+    /// dropped, keeping it from being closed while idle. An interval well under a minute keeps
+    /// connections open with the default reqwest client. Failures are logged.
+    pub fn keep_warm(&self, interval: Duration) -> KeepWarm {
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&stop_flag);
+        let http_client = self.http_client.clone();
+        rt::spawn(
+            async move {
+                while !stop.load(Ordering::Relaxed) {
+                    if let Err(err) = warm_connection(&http_client).await {
+                        warn!(error = %err, "Could not warm connection");
+                    }
+                    rt::sleep(interval).await;
+                }
+            }
+            .instrument(info_span!("keep_warm")),
+        );
+        KeepWarm { stop_flag }
+    }
+
+    /// Checks `order` against the order guard, which may clamp its price, and resolves its
+    /// asset.
+    fn convert_order(&self, mut order: ClientOrderRequest) -> Result<OrderRequest> {
+        if let Some(guard) = &self.order_guard {
+            let sz_decimals = self
+                .coin_to_asset
+                .get(&order.asset)
+                .filter(|&&asset| asset < 10_000)
+                .and_then(|&asset| self.meta.universe.get(asset as usize))
+                .map(|asset_meta| asset_meta.sz_decimals);
+            guard
+                .check(&mut order, sz_decimals)
+                .map_err(Error::RiskCheck)?;
+        }
+        order.convert(&self.coin_to_asset)
+    }
+
+    fn validate_order(&self, order: &ClientOrderRequest) -> Result<()> {
+        order.validate_trigger(None)?;
+        let &asset = self
+            .coin_to_asset
+            .get(&order.asset)
+            .ok_or(Error::AssetNotFound)?;
+        let trigger_px = match &order.order_type {
+            ClientOrder::Trigger(trigger) => Some(trigger.trigger_px),
+            ClientOrder::Limit(_) => None,
+        };
+        let prices = std::iter::once(order.limit_px).chain(trigger_px);
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        if !positive(order.sz) || !prices.clone().all(positive) {
+            return Err(Error::InvalidOrder(format!(
+                "{} order needs a positive size and price",
+                order.asset
+            )));
+        }
+        // Spot assets are numbered from 10000 and their size decimals are not kept
+        let Some(asset_meta) = self
+            .meta
+            .universe
+            .get(asset as usize)
+            .filter(|_| asset < 10_000)
+        else {
+            return Ok(());
+        };
+        let lot = lot_size(asset_meta.sz_decimals);
+        if !on_increment(order.sz, lot) {
+            return Err(Error::InvalidOrder(format!(
+                "{} size {} is not a multiple of {lot}",
+                order.asset, order.sz
+            )));
+        }
+        for px in prices {
+            let tick = price_tick_size(px, asset_meta.sz_decimals, false);
+            if !on_increment(px, tick) {
+                return Err(Error::InvalidOrder(format!(
+                    "{} price {px} is not a multiple of {tick}",
+                    order.asset
+                )));
+            }
+        }
+        Ok(())
     }
 
     pub async fn cancel(
@@ -501,6 +1229,14 @@ impl ExchangeClient {
         self.bulk_cancel(vec![cancel], wallet).await
     }
 
+    #[instrument(
+        skip_all,
+        fields(
+            coins = ?cancels.iter().map(|c| c.asset.as_str()).collect::<Vec<_>>(),
+            oids = ?cancels.iter().map(|c| c.oid).collect::<Vec<_>>(),
+            nonce = field::Empty,
+        )
+    )]
     pub async fn bulk_cancel(
         &self,
         cancels: Vec<ClientCancelRequest>,
@@ -508,6 +1244,7 @@ impl ExchangeClient {
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
         let timestamp = next_nonce();
+        Span::current().record("nonce", timestamp);
 
         let mut transformed_cancels = Vec::new();
         for cancel in cancels.into_iter() {
@@ -530,7 +1267,16 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    pub async fn bulk_cancel_with_statuses(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<Vec<BulkRequestStatus<ClientCancelRequest>>> {
+        let response = self.bulk_cancel(cancels.clone(), wallet).await?;
+        Ok(pair_statuses(cancels, response))
     }
 
     pub async fn modify(
@@ -541,6 +1287,14 @@ impl ExchangeClient {
         self.bulk_modify(vec![modify], wallet).await
     }
 
+    #[instrument(
+        skip_all,
+        fields(
+            coins = ?modifies.iter().map(|m| m.order.asset.as_str()).collect::<Vec<_>>(),
+            oids = ?modifies.iter().map(|m| m.oid).collect::<Vec<_>>(),
+            nonce = field::Empty,
+        )
+    )]
     pub async fn bulk_modify(
         &self,
         modifies: Vec<ClientModifyRequest>,
@@ -548,12 +1302,13 @@ impl ExchangeClient {
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
         let timestamp = next_nonce();
+        Span::current().record("nonce", timestamp);
 
         let mut transformed_modifies = Vec::new();
         for modify in modifies.into_iter() {
             transformed_modifies.push(ModifyRequest {
                 oid: modify.oid,
-                order: modify.order.convert(&self.coin_to_asset)?,
+                order: self.convert_order(modify.order)?,
             });
         }
 
@@ -566,7 +1321,16 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    pub async fn bulk_modify_with_statuses(
+        &self,
+        modifies: Vec<ClientModifyRequest>,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<Vec<BulkRequestStatus<ClientModifyRequest>>> {
+        let response = self.bulk_modify(modifies.clone(), wallet).await?;
+        Ok(pair_statuses(modifies, response))
     }
 
     pub async fn cancel_by_cloid(
@@ -577,6 +1341,14 @@ impl ExchangeClient {
         self.bulk_cancel_by_cloid(vec![cancel], wallet).await
     }
 
+    #[instrument(
+        skip_all,
+        fields(
+            coins = ?cancels.iter().map(|c| c.asset.as_str()).collect::<Vec<_>>(),
+            cloids = ?cancels.iter().map(|c| c.cloid.to_string()).collect::<Vec<_>>(),
+            nonce = field::Empty,
+        )
+    )]
     pub async fn bulk_cancel_by_cloid(
         &self,
         cancels: Vec<ClientCancelRequestCloid>,
@@ -584,6 +1356,7 @@ impl ExchangeClient {
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
         let timestamp = next_nonce();
+        Span::current().record("nonce", timestamp);
 
         let mut transformed_cancels: Vec<CancelRequestCloid> = Vec::new();
         for cancel in cancels.into_iter() {
@@ -606,7 +1379,16 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    pub async fn bulk_cancel_by_cloid_with_statuses(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<Vec<BulkRequestStatus<ClientCancelRequestCloid>>> {
+        let response = self.bulk_cancel_by_cloid(cancels.clone(), wallet).await?;
+        Ok(pair_statuses(cancels, response))
     }
 
     pub async fn update_leverage(
@@ -631,7 +1413,7 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn update_isolated_margin(
@@ -656,7 +1438,7 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn approve_agent(
@@ -666,11 +1448,7 @@ impl ExchangeClient {
         let wallet = wallet.unwrap_or(&self.wallet);
         let agent = PrivateKeySigner::random();
 
-        let hyperliquid_chain = if self.http_client.is_mainnet() {
-            "Mainnet".to_string()
-        } else {
-            "Testnet".to_string()
-        };
+        let hyperliquid_chain = self.hyperliquid_chain()?;
 
         let nonce = next_nonce();
         let approve_agent = ApproveAgent {
@@ -680,10 +1458,14 @@ impl ExchangeClient {
             agent_name: None,
             nonce,
         };
+        let hash = approve_agent.eip712_signing_hash();
         let signature = sign_typed_data(&approve_agent, wallet)?;
         let action = serde_json::to_value(Actions::ApproveAgent(approve_agent))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
-        Ok((agent.to_bytes(), self.post(action, signature, nonce).await?))
+        Ok((
+            agent.to_bytes(),
+            self.post(action, signature, nonce, hash).await?,
+        ))
     }
 
     pub async fn withdraw_from_bridge(
@@ -693,11 +1475,7 @@ impl ExchangeClient {
         wallet: Option<&PrivateKeySigner>,
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
-        let hyperliquid_chain = if self.http_client.is_mainnet() {
-            "Mainnet".to_string()
-        } else {
-            "Testnet".to_string()
-        };
+        let hyperliquid_chain = self.hyperliquid_chain()?;
 
         let timestamp = next_nonce();
         let withdraw = Withdraw3 {
@@ -707,11 +1485,12 @@ impl ExchangeClient {
             amount: amount.to_string(),
             time: timestamp,
         };
+        let hash = withdraw.eip712_signing_hash();
         let signature = sign_typed_data(&withdraw, wallet)?;
         let action = serde_json::to_value(Actions::Withdraw3(withdraw))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, hash).await
     }
 
     pub async fn spot_transfer(
@@ -722,11 +1501,7 @@ impl ExchangeClient {
         wallet: Option<&PrivateKeySigner>,
     ) -> Result<ExchangeResponseStatus> {
         let wallet = wallet.unwrap_or(&self.wallet);
-        let hyperliquid_chain = if self.http_client.is_mainnet() {
-            "Mainnet".to_string()
-        } else {
-            "Testnet".to_string()
-        };
+        let hyperliquid_chain = self.hyperliquid_chain()?;
 
         let timestamp = next_nonce();
         let spot_send = SpotSend {
@@ -737,11 +1512,78 @@ impl ExchangeClient {
             time: timestamp,
             token: token.to_string(),
         };
+        let hash = spot_send.eip712_signing_hash();
         let signature = sign_typed_data(&spot_send, wallet)?;
         let action = serde_json::to_value(Actions::SpotSend(spot_send))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, hash).await
+    }
+
+    /// Sends like `spot_transfer` once `SpotPreflight` finds the token in spot meta, the amount
+    /// within its wei decimals and the signer's balance less holds, and the destination a
+    /// valid address, failing with `Error::SpotPrecondition` otherwise.
+    pub async fn spot_transfer_checked(
+        &self,
+        amount: &str,
+        destination: &str,
+        token: &str,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let preflight = SpotPreflight::fetch(&self.info_client(), wallet.address()).await?;
+        preflight
+            .check_send(token, amount, destination)
+            .map_err(Error::SpotPrecondition)?;
+        self.spot_transfer(amount, destination, token, Some(wallet))
+            .await
+    }
+
+    /// Starts linking spot `token` to its ERC-20 at `address`. The link takes effect once
+    /// the contract's deployer calls `finalize_evm_contract`.
+    pub async fn request_evm_contract(
+        &self,
+        token: u32,
+        address: Address,
+        evm_extra_wei_decimals: i32,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let timestamp = next_nonce();
+
+        let action = Actions::RequestEvmContract(RequestEvmContract {
+            token,
+            address,
+            evm_extra_wei_decimals,
+        });
+
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let is_mainnet = self.http_client.is_mainnet();
+        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    /// Completes the link requested with `request_evm_contract`, signed by `wallet` as the
+    /// contract's deployer.
+    pub async fn finalize_evm_contract(
+        &self,
+        token: u32,
+        input: FinalizeEvmContractInput,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let timestamp = next_nonce();
+
+        let action = Actions::FinalizeEvmContract(FinalizeEvmContract { token, input });
+
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+
+        let is_mainnet = self.http_client.is_mainnet();
+        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn set_referrer(
@@ -759,7 +1601,7 @@ impl ExchangeClient {
 
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn approve_builder_fee(
@@ -771,11 +1613,7 @@ impl ExchangeClient {
         let wallet = wallet.unwrap_or(&self.wallet);
         let timestamp = next_nonce();
 
-        let hyperliquid_chain = if self.http_client.is_mainnet() {
-            "Mainnet".to_string()
-        } else {
-            "Testnet".to_string()
-        };
+        let hyperliquid_chain = self.hyperliquid_chain()?;
 
         let approve_builder_fee = ApproveBuilderFee {
             signature_chain_id: 421614,
@@ -784,11 +1622,12 @@ impl ExchangeClient {
             max_fee_rate,
             nonce: timestamp,
         };
+        let hash = approve_builder_fee.eip712_signing_hash();
         let signature = sign_typed_data(&approve_builder_fee, wallet)?;
         let action = serde_json::to_value(Actions::ApproveBuilderFee(approve_builder_fee))
             .map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, hash).await
     }
 
     pub async fn schedule_cancel(
@@ -805,7 +1644,7 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
 
     pub async fn claim_rewards(
@@ -821,8 +1660,179 @@ impl ExchangeClient {
         let is_mainnet = self.http_client.is_mainnet();
         let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
 
-        self.post(action, signature, timestamp).await
+        self.post(action, signature, timestamp, connection_id).await
     }
+
+    /// Uses up `nonce` without doing anything, so an action pre-signed with it can no longer
+    /// be sent.
+    pub async fn noop(
+        &self,
+        nonce: u64,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let signed = self.sign_action(&Actions::Noop(Noop), nonce, wallet)?;
+        self.send_signed(&signed).await
+    }
+
+    /// Sends a noop for every nonce of `reservation` not marked used, marking those the
+    /// exchange accepted as used. A rejected noop usually means its nonce was used already.
+    pub async fn invalidate_reservation(
+        &self,
+        reservation: &mut NonceReservation,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<Vec<ExchangeResponseStatus>> {
+        let unused: Vec<u64> = reservation.unused().collect();
+        let mut statuses = Vec::with_capacity(unused.len());
+        for nonce in unused {
+            let status = self.noop(nonce, wallet).await?;
+            if matches!(status, ExchangeResponseStatus::Ok(_)) {
+                reservation.mark_used(nonce);
+            }
+            statuses.push(status);
+        }
+        Ok(statuses)
+    }
+
+    /// Registers a validator owned by the wallet, self-delegating `initial_wei` HYPE wei.
+    pub async fn validator_register(
+        &self,
+        profile: ValidatorProfile,
+        unjailed: bool,
+        initial_wei: u64,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let action = CValidatorAction::Register(ValidatorRegister {
+            profile,
+            unjailed,
+            initial_wei,
+        });
+        self.validator_action(action, wallet).await
+    }
+
+    pub async fn validator_change_profile(
+        &self,
+        change: ValidatorProfileChange,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.validator_action(CValidatorAction::ChangeProfile(change), wallet)
+            .await
+    }
+
+    pub async fn validator_unregister(
+        &self,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.validator_action(CValidatorAction::Unregister, wallet)
+            .await
+    }
+
+    async fn validator_action(
+        &self,
+        action: CValidatorAction,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let timestamp = next_nonce();
+
+        let action = Actions::CValidatorAction(action);
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let is_mainnet = self.http_client.is_mainnet();
+        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+
+        self.post(action, signature, timestamp, connection_id).await
+    }
+
+    /// Jails the validator whose signer is `wallet`, taking it out of the active set, such
+    /// as before node maintenance.
+    pub async fn signer_jail_self(
+        &self,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.signer_action(CSignerAction::JailSelf, wallet).await
+    }
+
+    pub async fn signer_unjail_self(
+        &self,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.signer_action(CSignerAction::UnjailSelf, wallet).await
+    }
+
+    async fn signer_action(
+        &self,
+        action: CSignerAction,
+        wallet: Option<&PrivateKeySigner>,
+    ) -> Result<ExchangeResponseStatus> {
+        let wallet = wallet.unwrap_or(&self.wallet);
+        let timestamp = next_nonce();
+
+        let action = Actions::CSignerAction(action);
+        let connection_id = action.hash(timestamp, self.vault_address)?;
+        let action = serde_json::to_value(&action).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let is_mainnet = self.http_client.is_mainnet();
+        let signature = sign_l1_action(wallet, connection_id, is_mainnet)?;
+
+        self.post(action, signature, timestamp, connection_id).await
+    }
+}
+
+fn order_coins(orders: &[ClientOrderRequest]) -> Vec<&str> {
+    orders.iter().map(|order| order.asset.as_str()).collect()
+}
+
+fn order_cloids(orders: &[ClientOrderRequest]) -> Vec<String> {
+    orders
+        .iter()
+        .filter_map(|order| order.cloid.map(|cloid| cloid.to_string()))
+        .collect()
+}
+
+fn action_batch(action: &serde_json::Value) -> (usize, bool) {
+    let batch_length = ["orders", "cancels", "modifies"]
+        .iter()
+        .find_map(|key| action.get(key)?.as_array().map(|items| items.len()))
+        .unwrap_or(1);
+    let is_cancel = matches!(
+        action.get("type").and_then(|t| t.as_str()),
+        Some("cancel" | "cancelByCloid")
+    );
+    (batch_length, is_cancel)
+}
+
+/// The response the exchange would give to `action` if every request in it succeeded.
+fn simulated_response(action: &serde_json::Value) -> serde_json::Value {
+    let action_type = action["type"].as_str().unwrap_or_default();
+    let resting = |count: usize| {
+        (0..count)
+            .map(|_| {
+                let oid = DRY_RUN_OID.fetch_sub(1, Ordering::Relaxed);
+                serde_json::json!({"resting": {"oid": oid}})
+            })
+            .collect::<Vec<_>>()
+    };
+    let (batch_length, _) = action_batch(action);
+    let statuses = match action_type {
+        "order" | "batchModify" => resting(batch_length),
+        "cancel" | "cancelByCloid" => vec![serde_json::json!("success"); batch_length],
+        _ => return serde_json::json!({"status": "ok", "response": {"type": "default"}}),
+    };
+    serde_json::json!({
+        "status": "ok",
+        "response": {"type": action_type, "data": {"statuses": statuses}},
+    })
+}
+
+async fn warm_connection(http_client: &HttpClient) -> Result<()> {
+    http_client
+        .post_weighted("/info", r#"{"type":"allMids"}"#.to_string(), 2)
+        .await
+        .map(drop)
+}
+
+fn on_increment(value: f64, increment: f64) -> bool {
+    let steps = value / increment;
+    (steps - steps.round()).abs() < 1e-6
 }
 
 fn round_to_decimals(value: f64, decimals: u32) -> f64 {
@@ -838,6 +1848,194 @@ fn round_to_significant_and_decimal(value: f64, sig_figs: u32, max_decimals: u32
     round_to_decimals(rounded.copysign(value), max_decimals)
 }
 
+/// Configures an `ExchangeClient` without a long positional constructor. Only the wallet is
+/// required; metadata that is not preloaded is fetched by `build`.
+#[derive(Default)]
+pub struct ExchangeClientBuilder {
+    http: HttpOptions,
+    wallet: Option<WalletSource>,
+    meta: Option<Meta>,
+    spot_meta: Option<SpotMeta>,
+    #[cfg(feature = "meta-cache")]
+    meta_cache: Option<crate::MetaCache>,
+    vault_address: Option<Address>,
+    account_address: Option<Address>,
+    mainnet: Option<bool>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    order_guard: Option<Arc<OrderGuard>>,
+    latency_hook: Option<LatencyHook>,
+    #[cfg(feature = "journal")]
+    journal: Option<Arc<crate::Journal>>,
+    #[cfg(feature = "ws")]
+    ws_post: bool,
+    dry_run: bool,
+    nonce_store: Option<Arc<NonceStore>>,
+}
+
+impl fmt::Debug for ExchangeClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExchangeClientBuilder")
+            .field("signer", &self.wallet)
+            .field("vault_address", &self.vault_address)
+            .field("account_address", &self.account_address)
+            .field("mainnet", &self.mainnet)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ExchangeClientBuilder {
+    http_options_setters!();
+
+    /// Signer for all actions.
+    pub fn wallet(mut self, wallet: PrivateKeySigner) -> Self {
+        self.wallet = Some(WalletSource::Key(wallet));
+        self
+    }
+
+    /// Signer for all actions, the private key stored under `name` in `provider`, fetched by
+    /// `build`.
+    pub fn wallet_secret(mut self, provider: Arc<dyn SecretsProvider>, name: &str) -> Self {
+        self.wallet = Some(WalletSource::Secret(provider, name.to_string()));
+        self
+    }
+
+    /// Preloaded perp metadata, skipping the `meta` request.
+    pub fn meta(mut self, meta: Meta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Preloaded spot metadata, skipping the `spotMeta` request.
+    pub fn spot_meta(mut self, spot_meta: SpotMeta) -> Self {
+        self.spot_meta = Some(spot_meta);
+        self
+    }
+
+    /// Loads metadata that is not preloaded from `cache`, fetching and saving it on a miss.
+    #[cfg(feature = "meta-cache")]
+    pub fn meta_cache(mut self, cache: crate::MetaCache) -> Self {
+        self.meta_cache = Some(cache);
+        self
+    }
+
+    pub fn vault_address(mut self, vault_address: Address) -> Self {
+        self.vault_address = Some(vault_address);
+        self
+    }
+
+    /// See `ExchangeClient::with_account_address`.
+    pub fn account_address(mut self, account: Address) -> Self {
+        self.account_address = Some(account);
+        self
+    }
+
+    /// See `ExchangeClient::with_mainnet`.
+    pub fn mainnet(mut self, mainnet: bool) -> Self {
+        self.mainnet = Some(mainnet);
+        self
+    }
+
+    pub fn circuit_breaker(mut self, circuit_breaker: Arc<CircuitBreaker>) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// See `ExchangeClient::with_order_guard`.
+    pub fn order_guard(mut self, guard: Arc<OrderGuard>) -> Self {
+        self.order_guard = Some(guard);
+        self
+    }
+
+    /// See `ExchangeClient::with_latency_hook`.
+    pub fn latency_hook(mut self, hook: LatencyHook) -> Self {
+        self.latency_hook = Some(hook);
+        self
+    }
+
+    /// See `ExchangeClient::with_journal`.
+    #[cfg(feature = "journal")]
+    pub fn journal(mut self, journal: Arc<crate::Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Sends actions over a reconnecting websocket connection of the client's own, see
+    /// `ExchangeClient::with_ws_post`.
+    #[cfg(feature = "ws")]
+    pub fn ws_post(mut self, ws_post: bool) -> Self {
+        self.ws_post = ws_post;
+        self
+    }
+
+    /// See `ExchangeClient::with_dry_run`.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// See `ExchangeClient::with_nonce_store`.
+    pub fn nonce_store(mut self, store: Arc<NonceStore>) -> Self {
+        self.nonce_store = Some(store);
+        self
+    }
+
+    pub async fn build(self) -> Result<ExchangeClient> {
+        let wallet = self
+            .wallet
+            .ok_or_else(|| Error::Wallet("No wallet configured".to_string()))?
+            .load()
+            .await?;
+        let mut http_client = self.http.build()?;
+        if let Some(mainnet) = self.mainnet {
+            http_client.mainnet = mainnet;
+        }
+        http_client.check_network()?;
+
+        let info = InfoClient::with_http_client(http_client.clone(), false);
+        let (meta, spot_meta) = (self.meta, self.spot_meta);
+        #[cfg(feature = "meta-cache")]
+        let (meta, spot_meta) = match &self.meta_cache {
+            Some(cache) if meta.is_none() || spot_meta.is_none() => {
+                let (cached_meta, cached_spot_meta) = cache.load_or_fetch(&info).await?;
+                (
+                    Some(meta.unwrap_or(cached_meta)),
+                    Some(spot_meta.unwrap_or(cached_spot_meta)),
+                )
+            }
+            _ => (meta, spot_meta),
+        };
+        let meta = match meta {
+            Some(meta) => meta,
+            None => info.meta().await?,
+        };
+        let spot_meta = match spot_meta {
+            Some(spot_meta) => spot_meta,
+            None => info.spot_meta().await?,
+        };
+
+        let mut exchange_client =
+            ExchangeClient::from_parts(http_client, wallet, meta, &spot_meta, self.vault_address);
+        exchange_client.account_address = self.account_address;
+        exchange_client.circuit_breaker = self.circuit_breaker;
+        exchange_client.order_guard = self.order_guard;
+        exchange_client.latency_hook = self.latency_hook;
+        exchange_client.dry_run = self.dry_run;
+        exchange_client.nonce_store = self.nonce_store;
+        #[cfg(feature = "journal")]
+        {
+            exchange_client.journal = self.journal;
+        }
+        #[cfg(feature = "ws")]
+        if self.ws_post {
+            exchange_client.ws_post = Some(InfoClient::with_http_client(
+                exchange_client.http_client.clone(),
+                true,
+            ));
+        }
+        Ok(exchange_client)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
@@ -846,8 +2044,11 @@ mod tests {
 
     use super::*;
     use crate::{
-        exchange::order::{Limit, OrderRequest, Trigger},
-        Order,
+        exchange::{
+            actions::NodeIp,
+            order::{Limit, OrderRequest, Trigger},
+        },
+        ExchangeDataStatus, Order, TriggerCondition,
     };
 
     fn get_wallet() -> Result<PrivateKeySigner> {
@@ -857,6 +2058,157 @@ mod tests {
             .map_err(|e| Error::Wallet(e.to_string()))
     }
 
+    #[tokio::test]
+    async fn test_builder_uses_preloaded_meta() -> Result<()> {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50}]}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let spot_meta = SpotMeta {
+            universe: vec![],
+            tokens: vec![],
+        };
+        // Nothing listens here, so any metadata request would fail
+        let builder = ExchangeClient::builder()
+            .base_url(BaseUrl::custom("http://127.0.0.1:9"))
+            .meta(meta)
+            .spot_meta(spot_meta)
+            .mainnet(true);
+        assert!(matches!(
+            ExchangeClientBuilder::default().build().await,
+            Err(Error::Wallet(_))
+        ));
+
+        let exchange_client = builder.wallet(get_wallet()?).build().await?;
+        assert_eq!(exchange_client.coin_to_asset.get("BTC"), Some(&0));
+        assert!(exchange_client.http_client.is_mainnet());
+
+        // Actions signed for mainnet are never sent to the testnet API, nor the reverse
+        let mismatched = ExchangeClient::builder()
+            .base_url(BaseUrl::Testnet)
+            .meta(Meta {
+                universe: vec![],
+                margin_tables: vec![],
+            })
+            .spot_meta(SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            })
+            .mainnet(true)
+            .wallet(get_wallet()?)
+            .build()
+            .await;
+        assert!(matches!(mismatched, Err(Error::InvalidConfig(_))));
+        let mut exchange_client = exchange_client.with_mainnet(false);
+        exchange_client.http_client.base_url = BaseUrl::Mainnet.get_url();
+        assert!(matches!(
+            exchange_client.usdc_transfer("1", "0x0", None).await,
+            Err(Error::InvalidConfig(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_simulated_acks() -> Result<()> {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50}]}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        // Nothing listens here, so any request sent would fail
+        let exchange_client = ExchangeClient::builder()
+            .base_url(BaseUrl::custom("http://127.0.0.1:9"))
+            .meta(meta)
+            .spot_meta(SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            })
+            .dry_run(true)
+            .wallet(get_wallet()?)
+            .build()
+            .await?;
+
+        let order = ClientOrderRequest {
+            asset: "BTC".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 50000.0,
+            sz: 0.001,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+        let statuses = exchange_client
+            .bulk_order_with_statuses(vec![order.clone(), order], None)
+            .await?;
+        let oids: Vec<u64> = statuses
+            .iter()
+            .map(|status| match &status.status {
+                Ok(ExchangeDataStatus::Resting(resting)) => resting.oid,
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(oids.len(), 2);
+        assert_ne!(oids[0], oids[1]);
+
+        let cancel = ClientCancelRequest {
+            asset: "BTC".to_string(),
+            oid: oids[0],
+        };
+        let statuses = exchange_client
+            .bulk_cancel_with_statuses(vec![cancel], None)
+            .await?;
+        assert!(matches!(
+            statuses[0].status,
+            Ok(ExchangeDataStatus::Success)
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_action_times_out() -> Result<()> {
+        // Accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        let exchange_client = ExchangeClient::builder()
+            .base_url(BaseUrl::custom(base_url))
+            .meta(
+                serde_json::from_str(
+                    r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50}]}"#,
+                )
+                .unwrap(),
+            )
+            .spot_meta(SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            })
+            .timeouts(Timeouts {
+                connect: None,
+                request: Some(Duration::from_millis(100)),
+            })
+            .wallet(get_wallet()?)
+            .build()
+            .await?;
+
+        let order = ClientOrderRequest {
+            asset: "BTC".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 50000.0,
+            sz: 0.001,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+        // Whether the order was placed is unknown, which callers tell apart from a rejection
+        let res = exchange_client.order(order, None).await;
+        assert!(matches!(res, Err(Error::Timeout(_))), "{res:?}");
+        Ok(())
+    }
+
     #[test]
     fn test_limit_order_action_hashing() -> Result<()> {
         let wallet = get_wallet()?;
@@ -867,9 +2219,7 @@ mod tests {
                 limit_px: "2000.0".to_string(),
                 sz: "3.5".to_string(),
                 reduce_only: false,
-                order_type: Order::Limit(Limit {
-                    tif: "Ioc".to_string(),
-                }),
+                order_type: Order::Limit(Limit { tif: Tif::Ioc }),
                 cloid: None,
             }],
             grouping: "na".to_string(),
@@ -898,9 +2248,7 @@ mod tests {
                 limit_px: "2000.0".to_string(),
                 sz: "3.5".to_string(),
                 reduce_only: false,
-                order_type: Order::Limit(Limit {
-                    tif: "Ioc".to_string(),
-                }),
+                order_type: Order::Limit(Limit { tif: Tif::Ioc }),
                 cloid: Some(uuid_to_hex_string(cloid.unwrap())),
             }],
             grouping: "na".to_string(),
@@ -921,12 +2269,12 @@ mod tests {
     fn test_tpsl_order_action_hashing() -> Result<()> {
         for (tpsl, mainnet_signature, testnet_signature) in [
             (
-                "tp",
+                TriggerCondition::TakeProfit,
                 "0xb91e5011dff15e4b4a40753730bda44972132e7b75641f3cac58b66159534a170d422ee1ac3c7a7a2e11e298108a2d6b8da8612caceaeeb3e571de3b2dfda9e41b",
                 "0x6df38b609904d0d4439884756b8f366f22b3a081801dbdd23f279094a2299fac6424cb0cdc48c3706aeaa368f81959e91059205403d3afd23a55983f710aee871b"
             ),
             (
-                "sl",
+                TriggerCondition::StopLoss,
                 "0x8456d2ace666fce1bee1084b00e9620fb20e810368841e9d4dd80eb29014611a0843416e51b1529c22dd2fc28f7ff8f6443875635c72011f60b62cbb8ce90e2d1c",
                 "0xeb5bdb52297c1d19da45458758bd569dcb24c07e5c7bd52cf76600fd92fdd8213e661e21899c985421ec018a9ee7f3790e7b7d723a9932b7b5adcd7def5354601c"
             )
@@ -943,7 +2291,7 @@ mod tests {
                         order_type: Order::Trigger(Trigger {
                             trigger_px: "2000.0".to_string(),
                             is_market: true,
-                            tpsl: tpsl.to_string(),
+                            tpsl,
                         }),
                         cloid: None,
                     }
@@ -1063,4 +2411,187 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_evm_contract_actions() {
+        let request = Actions::RequestEvmContract(RequestEvmContract {
+            token: 1,
+            address: address!("0x8b983aC3a9B9d4e2605cbE4d5b7C22fb9EbB75eC"),
+            evm_extra_wei_decimals: -2,
+        });
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({
+                "type": "requestEvmContract",
+                "token": 1,
+                "address": "0x8b983ac3a9b9d4e2605cbe4d5b7c22fb9ebb75ec",
+                "evmExtraWeiDecimals": -2,
+            })
+        );
+        let finalize = |input| {
+            serde_json::to_value(Actions::FinalizeEvmContract(FinalizeEvmContract {
+                token: 1,
+                input,
+            }))
+            .unwrap()
+        };
+        assert_eq!(
+            finalize(FinalizeEvmContractInput::Create { nonce: 3 }),
+            serde_json::json!({
+                "type": "finalizeEvmContract",
+                "token": 1,
+                "input": { "create": { "nonce": 3 } },
+            })
+        );
+        assert_eq!(
+            finalize(FinalizeEvmContractInput::FirstStorageSlot)["input"],
+            "firstStorageSlot"
+        );
+    }
+
+    #[test]
+    fn test_validator_actions() -> Result<()> {
+        assert_eq!(
+            serde_json::to_value(Actions::CSignerAction(CSignerAction::UnjailSelf)).unwrap(),
+            serde_json::json!({ "type": "CSignerAction", "unjailSelf": null })
+        );
+        assert_eq!(
+            serde_json::to_value(Actions::CValidatorAction(CValidatorAction::Unregister)).unwrap(),
+            serde_json::json!({ "type": "CValidatorAction", "unregister": null })
+        );
+        let register = Actions::CValidatorAction(CValidatorAction::Register(ValidatorRegister {
+            profile: ValidatorProfile {
+                node_ip: NodeIp {
+                    ip: "1.2.3.4".to_string(),
+                },
+                name: "validator".to_string(),
+                description: "".to_string(),
+                delegations_disabled: true,
+                commission_bps: 5,
+                signer: Address::ZERO,
+            },
+            unjailed: false,
+            initial_wei: 10,
+        }));
+        let value = serde_json::to_value(&register).unwrap();
+        assert_eq!(value["type"], "CValidatorAction");
+        assert_eq!(value["register"]["profile"]["node_ip"]["Ip"], "1.2.3.4");
+        assert_eq!(value["register"]["initial_wei"], 10);
+        // Unset profile fields are sent as null
+        let change = serde_json::to_value(Actions::CValidatorAction(
+            CValidatorAction::ChangeProfile(ValidatorProfileChange {
+                commission_bps: Some(10),
+                ..Default::default()
+            }),
+        ))
+        .unwrap();
+        assert!(change["changeProfile"]["name"].is_null());
+        assert_eq!(change["changeProfile"]["commission_bps"], 10);
+        register.hash(1583838, None)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_evm_user_modify_action() -> Result<()> {
+        let action = Actions::EvmUserModify(EvmUserModify {
+            using_big_blocks: true,
+        });
+        assert_eq!(
+            serde_json::to_value(&action).unwrap(),
+            serde_json::json!({ "type": "evmUserModify", "usingBigBlocks": true })
+        );
+        // Both settings sign distinct actions
+        let disable = Actions::EvmUserModify(EvmUserModify {
+            using_big_blocks: false,
+        });
+        assert_ne!(action.hash(1583838, None)?, disable.hash(1583838, None)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepared_order_matches_bulk_order() -> Result<()> {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"BTC","szDecimals":5,"maxLeverage":50},{"name":"ETH","szDecimals":4,"maxLeverage":50}]}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let spot_meta = SpotMeta {
+            universe: vec![],
+            tokens: vec![],
+        };
+        let exchange_client = ExchangeClient::from_parts(
+            HttpClient::new(Client::new(), "http://127.0.0.1:9".to_string()),
+            get_wallet()?,
+            meta,
+            &spot_meta,
+            None,
+        );
+        let order = |limit_px: f64, sz: f64| ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: Some(uuid::Uuid::from_u128(7)),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+
+        let prepared = exchange_client.prepare_order(order(2000.5, 3.5))?;
+        assert_eq!(prepared.request().asset, 1);
+        let action = Actions::Order(BulkOrder {
+            orders: vec![order(2000.5, 3.5).convert(&exchange_client.coin_to_asset)?],
+            grouping: "na".to_string(),
+            builder: None,
+        });
+        let vault = Some(address!("0x1719884eb866cb12b2287399b15f7db5e7d775ea"));
+        assert_eq!(prepared.hash(1583838, vault), action.hash(1583838, vault)?);
+        assert_eq!(prepared.action, serde_json::to_value(&action).unwrap());
+
+        // Off the 0.1 tick for five significant figures, off the 0.0001 lot and empty
+        for (limit_px, sz) in [(2000.55, 3.5), (2000.5, 0.00005), (2000.5, 0.0)] {
+            assert!(matches!(
+                exchange_client.prepare_order(order(limit_px, sz)),
+                Err(Error::InvalidOrder(_))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_debug_redacts_wallet() -> Result<()> {
+        let wallet = get_wallet()?;
+        let key = alloy::hex::encode(wallet.to_bytes());
+        let exchange_client = ExchangeClient::from_parts(
+            HttpClient::new(Client::new(), "http://127.0.0.1:9".to_string()),
+            wallet.clone(),
+            serde_json::from_str(r#"{"universe":[]}"#).unwrap(),
+            &SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            },
+            None,
+        );
+        let signer = SignerId::of(&wallet);
+        for debug in [
+            format!("{exchange_client:?}"),
+            format!("{:?}", ExchangeClient::builder().wallet(wallet.clone())),
+        ] {
+            assert!(!debug.contains(&key), "{debug}");
+            assert!(
+                debug.contains(&format!("{:?}", wallet.address())),
+                "{debug}"
+            );
+            assert!(debug.contains(&alloy::hex::encode(signer.fingerprint)));
+        }
+        assert_eq!(signer.to_string().len(), 42 + 11);
+
+        let clone = exchange_client.clone();
+        assert!(Arc::ptr_eq(&clone.meta, &exchange_client.meta));
+        assert!(Arc::ptr_eq(
+            &clone.coin_to_asset,
+            &exchange_client.coin_to_asset
+        ));
+        Ok(())
+    }
 }
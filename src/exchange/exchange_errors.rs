@@ -0,0 +1,177 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+/// Error returned by the exchange, classified from the server message. The raw message is
+/// always preserved and is what `Display` prints.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "String")]
+pub enum ExchangeError {
+    InsufficientMargin(String),
+    InsufficientBalance(String),
+    InvalidPrice(String),
+    InvalidSize(String),
+    MinTradeNotional(String),
+    ReduceOnlyViolation(String),
+    PostOnlyWouldMatch(String),
+    IocCancelled(String),
+    RateLimited(String),
+    NonceError(String),
+    OrderNotFound(String),
+    UnknownSigner(String),
+    Other(String),
+}
+
+impl ExchangeError {
+    pub fn message(&self) -> &str {
+        match self {
+            ExchangeError::InsufficientMargin(msg)
+            | ExchangeError::InsufficientBalance(msg)
+            | ExchangeError::InvalidPrice(msg)
+            | ExchangeError::InvalidSize(msg)
+            | ExchangeError::MinTradeNotional(msg)
+            | ExchangeError::ReduceOnlyViolation(msg)
+            | ExchangeError::PostOnlyWouldMatch(msg)
+            | ExchangeError::IocCancelled(msg)
+            | ExchangeError::RateLimited(msg)
+            | ExchangeError::NonceError(msg)
+            | ExchangeError::OrderNotFound(msg)
+            | ExchangeError::UnknownSigner(msg)
+            | ExchangeError::Other(msg) => msg,
+        }
+    }
+}
+
+impl From<String> for ExchangeError {
+    fn from(msg: String) -> Self {
+        let lower = msg.to_lowercase();
+        let contains = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+
+        if contains(&["insufficient margin"]) {
+            ExchangeError::InsufficientMargin(msg)
+        } else if contains(&["insufficient spot balance", "insufficient balance"]) {
+            ExchangeError::InsufficientBalance(msg)
+        } else if contains(&["reduce only"]) {
+            ExchangeError::ReduceOnlyViolation(msg)
+        } else if contains(&["post only order would have immediately matched"]) {
+            ExchangeError::PostOnlyWouldMatch(msg)
+        } else if contains(&["could not immediately match"]) {
+            ExchangeError::IocCancelled(msg)
+        } else if contains(&["minimum value"]) {
+            ExchangeError::MinTradeNotional(msg)
+        } else if contains(&[
+            "invalid price",
+            "tick size",
+            "away from the reference price",
+            "away from the oracle price",
+        ]) {
+            ExchangeError::InvalidPrice(msg)
+        } else if contains(&["invalid size"]) {
+            ExchangeError::InvalidSize(msg)
+        } else if contains(&["too many", "rate limit"]) {
+            ExchangeError::RateLimited(msg)
+        } else if contains(&["nonce"]) {
+            ExchangeError::NonceError(msg)
+        } else if contains(&[
+            "never placed, already canceled, or filled",
+            "order not found",
+        ]) {
+            ExchangeError::OrderNotFound(msg)
+        } else if contains(&["does not exist"]) && contains(&["wallet", "user"]) {
+            ExchangeError::UnknownSigner(msg)
+        } else {
+            ExchangeError::Other(msg)
+        }
+    }
+}
+
+impl From<&str> for ExchangeError {
+    fn from(msg: &str) -> Self {
+        msg.to_string().into()
+    }
+}
+
+impl fmt::Display for ExchangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for ExchangeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ExchangeDataStatus, ExchangeResponseStatus};
+
+    #[test]
+    fn test_parse_known_messages() {
+        let cases = [
+            (
+                "Insufficient margin to place order. asset=4",
+                ExchangeError::InsufficientMargin as fn(String) -> ExchangeError,
+            ),
+            (
+                "Order must have minimum value of $10.",
+                ExchangeError::MinTradeNotional,
+            ),
+            (
+                "Price must be divisible by tick size. asset=4",
+                ExchangeError::InvalidPrice,
+            ),
+            (
+                "Reduce only order would increase position. asset=4",
+                ExchangeError::ReduceOnlyViolation,
+            ),
+            (
+                "Post only order would have immediately matched, bbo was 1800.1@1800.2. asset=4",
+                ExchangeError::PostOnlyWouldMatch,
+            ),
+            (
+                "Order could not immediately match against any resting orders. asset=4",
+                ExchangeError::IocCancelled,
+            ),
+            (
+                "Too many cumulative requests sent (10538 > 10504) for cumulative volume traded $4.58.",
+                ExchangeError::RateLimited,
+            ),
+            ("Invalid nonce: duplicate nonce", ExchangeError::NonceError),
+            (
+                "Order was never placed, already canceled, or filled. asset=4",
+                ExchangeError::OrderNotFound,
+            ),
+            (
+                "User or API Wallet 0x0d1d9635d0640821d15e323ac8adadfa9c111414 does not exist.",
+                ExchangeError::UnknownSigner,
+            ),
+            ("Something new", ExchangeError::Other),
+        ];
+        for (msg, expected) in cases {
+            assert_eq!(ExchangeError::from(msg), expected(msg.to_string()));
+            assert_eq!(ExchangeError::from(msg).to_string(), msg);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_statuses() {
+        let status: ExchangeResponseStatus = serde_json::from_str(
+            r#"{"status":"ok","response":{"type":"order","data":{"statuses":[{"error":"Insufficient margin to place order. asset=1"}]}}}"#,
+        )
+        .unwrap();
+        let ExchangeResponseStatus::Ok(response) = status else {
+            panic!("expected ok response");
+        };
+        assert!(matches!(
+            response.data.unwrap().statuses[0],
+            ExchangeDataStatus::Error(ExchangeError::InsufficientMargin(_))
+        ));
+
+        let status: ExchangeResponseStatus =
+            serde_json::from_str(r#"{"status":"err","response":"Invalid nonce: duplicate nonce"}"#)
+                .unwrap();
+        assert!(matches!(
+            status,
+            ExchangeResponseStatus::Err(ExchangeError::NonceError(_))
+        ));
+    }
+}
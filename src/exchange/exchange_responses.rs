@@ -1,5 +1,7 @@
 use serde::Deserialize;
 
+use crate::ExchangeError;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct RestingOrder {
     pub oid: u64,
@@ -19,7 +21,7 @@ pub enum ExchangeDataStatus {
     Success,
     WaitingForFill,
     WaitingForTrigger,
-    Error(String),
+    Error(ExchangeError),
     Resting(RestingOrder),
     Filled(FilledOrder),
 }
@@ -41,5 +43,82 @@ pub struct ExchangeResponse {
 #[serde(tag = "status", content = "response")]
 pub enum ExchangeResponseStatus {
     Ok(ExchangeResponse),
-    Err(String),
+    Err(ExchangeError),
+}
+
+/// Outcome of a single request within a bulk action, paired with the request that produced it.
+#[derive(Debug, Clone)]
+pub struct BulkRequestStatus<T> {
+    pub request: T,
+    pub status: std::result::Result<ExchangeDataStatus, ExchangeError>,
+}
+
+impl<T> BulkRequestStatus<T> {
+    pub fn is_ok(&self) -> bool {
+        self.status.is_ok()
+    }
+}
+
+pub(crate) fn pair_statuses<T>(
+    requests: Vec<T>,
+    response: ExchangeResponseStatus,
+) -> Vec<BulkRequestStatus<T>> {
+    let statuses = match response {
+        ExchangeResponseStatus::Ok(response) => {
+            Ok(response.data.map(|data| data.statuses).unwrap_or_default())
+        }
+        ExchangeResponseStatus::Err(err) => Err(err),
+    };
+
+    requests
+        .into_iter()
+        .enumerate()
+        .map(|(i, request)| {
+            let status = match &statuses {
+                Ok(statuses) => match statuses.get(i) {
+                    Some(ExchangeDataStatus::Error(err)) => Err(err.clone()),
+                    Some(status) => Ok(status.clone()),
+                    None => Err(ExchangeError::Other(format!(
+                        "No status returned for request at index {i}"
+                    ))),
+                },
+                Err(err) => Err(err.clone()),
+            };
+            BulkRequestStatus { request, status }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pair_statuses_partial_failure() {
+        let response: ExchangeResponseStatus = serde_json::from_str(
+            r#"{"status":"ok","response":{"type":"order","data":{"statuses":[{"resting":{"oid":77738308}},{"error":"Order must have minimum value of $10."}]}}}"#,
+        )
+        .unwrap();
+        let paired = pair_statuses(vec!["a", "b", "c"], response);
+
+        assert!(matches!(
+            paired[0].status,
+            Ok(ExchangeDataStatus::Resting(RestingOrder { oid: 77738308 }))
+        ));
+        assert_eq!(paired[1].request, "b");
+        assert!(matches!(
+            paired[1].status,
+            Err(ExchangeError::MinTradeNotional(_))
+        ));
+        assert!(matches!(paired[2].status, Err(ExchangeError::Other(_))));
+    }
+
+    #[test]
+    fn test_pair_statuses_top_level_error() {
+        let response = ExchangeResponseStatus::Err("Invalid nonce".into());
+        let paired = pair_statuses(vec![1, 2], response);
+        assert!(paired
+            .iter()
+            .all(|p| matches!(p.status, Err(ExchangeError::NonceError(_)))));
+    }
 }
@@ -0,0 +1,80 @@
+use std::future::Future;
+
+use alloy::primitives::Address;
+
+use crate::{
+    prelude::*, rt::MaybeSend, ClientCancelRequest, ClientCancelRequestCloid, ClientOrderRequest,
+    ExchangeClient, ExchangeResponseStatus,
+};
+
+/// Order entry implemented by `ExchangeClient` and `PaperExchange`, so a strategy written
+/// against it runs unchanged on either.
+pub trait Exchange {
+    /// Account orders are placed for, the vault if trading for one.
+    fn address(&self) -> Address;
+
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend;
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend;
+
+    fn bulk_cancel_by_cloid(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend;
+
+    fn order(
+        &self,
+        order: ClientOrderRequest,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.bulk_order(vec![order])
+    }
+
+    fn cancel(
+        &self,
+        cancel: ClientCancelRequest,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.bulk_cancel(vec![cancel])
+    }
+
+    fn cancel_by_cloid(
+        &self,
+        cancel: ClientCancelRequestCloid,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.bulk_cancel_by_cloid(vec![cancel])
+    }
+}
+
+impl Exchange for ExchangeClient {
+    fn address(&self) -> Address {
+        self.vault_address
+            .or(self.account_address)
+            .unwrap_or(self.wallet.address())
+    }
+
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        ExchangeClient::bulk_order(self, orders, None)
+    }
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        ExchangeClient::bulk_cancel(self, cancels, None)
+    }
+
+    fn bulk_cancel_by_cloid(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        ExchangeClient::bulk_cancel_by_cloid(self, cancels, None)
+    }
+}
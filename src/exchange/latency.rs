@@ -0,0 +1,99 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use alloy::primitives::B256;
+
+use crate::rt::Instant;
+
+/// When one exchange action passed each stage on its way to an acknowledgement, as seen by a
+/// `LatencyHook`. Only actions answered with a parseable response are reported.
+#[derive(Clone, Debug)]
+pub struct ActionLatency {
+    /// The action `type`, such as `order` or `cancel`
+    pub action: String,
+    pub nonce: u64,
+    /// What was signed: the connection id of L1 actions or the EIP-712 signing hash of
+    /// user-signed ones
+    pub hash: B256,
+    /// The action was signed and handed over for sending
+    pub signed_at: Instant,
+    /// The request went out, after waiting on the rate limiter
+    pub sent_at: Instant,
+    /// The response body was read, including any retries
+    pub received_at: Instant,
+    /// The response was parsed into an `ExchangeResponseStatus`
+    pub parsed_at: Instant,
+}
+
+impl ActionLatency {
+    /// Time spent between signing and sending, mostly waiting on the rate limiter.
+    pub fn queued(&self) -> Duration {
+        self.sent_at.duration_since(self.signed_at)
+    }
+
+    /// Time from sending the request until its response was read.
+    pub fn round_trip(&self) -> Duration {
+        self.received_at.duration_since(self.sent_at)
+    }
+
+    /// Time from signing until the acknowledgement was parsed.
+    pub fn total(&self) -> Duration {
+        self.parsed_at.duration_since(self.signed_at)
+    }
+}
+
+/// Passes the timings of every exchange action to a user callback, for monitoring exchange and
+/// network latency per order. The callback runs on the task sending the action, so keep it
+/// cheap.
+#[derive(Clone)]
+pub struct LatencyHook {
+    hook: Arc<dyn Fn(&ActionLatency) + Send + Sync>,
+}
+
+impl fmt::Debug for LatencyHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LatencyHook").finish_non_exhaustive()
+    }
+}
+
+impl LatencyHook {
+    pub fn new(hook: impl Fn(&ActionLatency) + Send + Sync + 'static) -> LatencyHook {
+        LatencyHook {
+            hook: Arc::new(hook),
+        }
+    }
+
+    pub(crate) fn report(&self, latency: &ActionLatency) {
+        (self.hook)(latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_reports_stage_durations() {
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let hook = {
+            let reported = reported.clone();
+            LatencyHook::new(move |latency| reported.lock().unwrap().push(latency.clone()))
+        };
+        let signed_at = Instant::now();
+        hook.report(&ActionLatency {
+            action: "order".to_string(),
+            nonce: 1,
+            hash: B256::ZERO,
+            signed_at,
+            sent_at: signed_at + Duration::from_millis(2),
+            received_at: signed_at + Duration::from_millis(30),
+            parsed_at: signed_at + Duration::from_millis(31),
+        });
+
+        let reported = reported.lock().unwrap();
+        assert_eq!(reported[0].queued(), Duration::from_millis(2));
+        assert_eq!(reported[0].round_trip(), Duration::from_millis(28));
+        assert_eq!(reported[0].total(), Duration::from_millis(31));
+    }
+}
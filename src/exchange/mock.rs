@@ -0,0 +1,988 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::SocketAddr,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc::{unbounded_channel, UnboundedReceiver},
+    },
+    task::JoinHandle,
+};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::order::OrderRequest;
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, Actions, AssetMeta, BaseUrl, ClientCancelRequest,
+    ClientCancelRequestCloid, ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, Error,
+    Exchange, ExchangeDataStatus, ExchangeResponseStatus, InfoRequest, Message, OpenOrdersResponse,
+    Order, PaperConfig, PaperExchange, Position, Side, TradeInfo,
+};
+
+/// How the mock answers orders.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum MockFill {
+    /// Orders match against the books and trades set with `MockServer::set_book` and
+    /// `MockServer::trade`, as on a `PaperExchange`
+    #[default]
+    Book,
+    /// Orders fill completely at their limit price as soon as they arrive
+    Immediate,
+    /// Every order is rejected with this message
+    Reject(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockEndpoint {
+    Info,
+    Exchange,
+}
+
+#[derive(Clone, Debug)]
+pub struct MockConfig {
+    /// Perps served by `meta`, in asset id order
+    pub universe: Vec<AssetMeta>,
+    /// Account the mock trades for. Actions are accepted whoever signs them.
+    pub user: Address,
+    pub fill: MockFill,
+    /// Collateral reported by `clearinghouseState` before PnL
+    pub account_value: f64,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        let asset = |name: &str, sz_decimals, max_leverage| AssetMeta {
+            name: name.to_string(),
+            sz_decimals,
+            max_leverage,
+            only_isolated: None,
+            margin_table_id: None,
+        };
+        MockConfig {
+            universe: vec![asset("BTC", 5, 40), asset("ETH", 4, 25)],
+            user: Address::ZERO,
+            fill: MockFill::Book,
+            account_value: 10_000.0,
+        }
+    }
+}
+
+type Levels = Vec<(f64, f64)>;
+
+#[derive(Debug)]
+struct Failure {
+    endpoint: MockEndpoint,
+    status: u16,
+    body: String,
+}
+
+#[derive(Debug)]
+struct State {
+    fill: MockFill,
+    books: BTreeMap<String, (Levels, Levels)>,
+    /// Newest first, as served by `userFills`
+    fills: Vec<Value>,
+    failures: VecDeque<Failure>,
+    requests: Vec<(MockEndpoint, Value)>,
+    next_tid: u64,
+}
+
+#[derive(Clone, Debug)]
+enum Outbound {
+    Text(String),
+    Disconnect,
+}
+
+#[derive(Debug)]
+struct Inner {
+    config: MockConfig,
+    paper: PaperExchange,
+    events: Mutex<UnboundedReceiver<Message>>,
+    state: Mutex<State>,
+    /// Orders are handled one at a time, `MockFill::Immediate` swaps the book for each
+    exchange_lock: tokio::sync::Mutex<()>,
+    outbound: broadcast::Sender<Outbound>,
+}
+
+/// In-process stand-in for the API, for integration tests of bots that should not touch
+/// testnet.
+///
+/// It serves `/info`, `/exchange` and `/ws` on a local port; point any client at it with
+/// `base_url`. Orders, cancels and cancels by cloid are matched by a `PaperExchange` for
+/// `MockConfig::user`, and their order updates and fills are pushed to every websocket
+/// along with the books and trades set on the mock. Signatures are not checked and other
+/// actions are acknowledged without effect. The info requests answered are `meta`,
+/// `spotMeta`, `allMids`, `l2Book`, `openOrders`, `userFills` and `clearinghouseState`, with
+/// `spotClearinghouseState` always empty; `fail_next` injects errors. Websocket post requests
+/// are answered like the matching REST requests.
+#[derive(Debug)]
+pub struct MockServer {
+    inner: Arc<Inner>,
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Binds a free local port and serves until dropped.
+    pub async fn start(config: MockConfig) -> Result<MockServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| Error::Io(e.to_string()))?;
+        let (sender, events) = unbounded_channel();
+        let paper = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            address: config.user,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let inner = Arc::new(Inner {
+            state: Mutex::new(State {
+                fill: config.fill.clone(),
+                books: BTreeMap::new(),
+                fills: Vec::new(),
+                failures: VecDeque::new(),
+                requests: Vec::new(),
+                next_tid: 1,
+            }),
+            config,
+            paper,
+            events: Mutex::new(events),
+            exchange_lock: tokio::sync::Mutex::new(()),
+            outbound: broadcast::channel(4096).0,
+        });
+        // Orders rest until a book is set rather than being rejected
+        for asset in &inner.config.universe {
+            inner.feed_book(&asset.name, &[], &[]);
+        }
+        let task = tokio::spawn(serve(listener, inner.clone()));
+        Ok(MockServer { inner, addr, task })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn base_url(&self) -> BaseUrl {
+        BaseUrl::custom(format!("http://{}", self.addr))
+    }
+
+    /// Replaces the book of `coin`, best levels first, and pushes it with the mids.
+    pub fn set_book(&self, coin: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+        self.inner
+            .state()
+            .books
+            .insert(coin.to_string(), (bids.to_vec(), asks.to_vec()));
+        let book = self.inner.feed_book(coin, bids, asks);
+        self.inner.broadcast(book);
+        let mids = json!({"channel": "allMids", "data": {"mids": self.inner.mids()}});
+        self.inner.broadcast(mids);
+        self.inner.flush_events();
+    }
+
+    /// Prints a trade, filling resting orders it goes through.
+    pub fn trade(&self, coin: &str, is_buy: bool, px: f64, sz: f64) {
+        let tid = {
+            let mut state = self.inner.state();
+            state.next_tid += 1;
+            state.next_tid - 1
+        };
+        let trades = json!({
+            "channel": "trades",
+            "data": [{
+                "coin": coin,
+                "side": Side::from_is_buy(is_buy),
+                "px": px.to_string(),
+                "sz": sz.to_string(),
+                "time": now_timestamp_ms(),
+                "hash": format!("{:#066x}", tid),
+                "tid": tid,
+                "users": [Address::ZERO, Address::ZERO],
+            }],
+        });
+        self.inner.feed(&trades);
+        self.inner.broadcast(trades);
+        self.inner.flush_events();
+    }
+
+    pub fn set_fill(&self, fill: MockFill) {
+        self.inner.state().fill = fill;
+    }
+
+    /// Answers the next request to `endpoint` with `status` and `body` instead of handling
+    /// it. Failures queue up, so calling this twice fails the next two requests.
+    pub fn fail_next(&self, endpoint: MockEndpoint, status: u16, body: impl Into<String>) {
+        self.inner.state().failures.push_back(Failure {
+            endpoint,
+            status,
+            body: body.into(),
+        });
+    }
+
+    /// Bodies of the requests received on `endpoint`, oldest first.
+    pub fn requests(&self, endpoint: MockEndpoint) -> Vec<Value> {
+        self.inner
+            .state()
+            .requests
+            .iter()
+            .filter(|(e, _)| *e == endpoint)
+            .map(|(_, body)| body.clone())
+            .collect()
+    }
+
+    /// Closes every websocket connection, for testing reconnects.
+    pub fn disconnect_websockets(&self) {
+        let _ = self.inner.outbound.send(Outbound::Disconnect);
+    }
+
+    pub fn open_orders(&self) -> Vec<OpenOrdersResponse> {
+        self.inner.paper.open_orders()
+    }
+
+    pub fn positions(&self) -> Vec<Position> {
+        self.inner.paper.positions()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl Inner {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("mock server lock poisoned")
+    }
+
+    fn broadcast(&self, message: Value) {
+        let _ = self.outbound.send(Outbound::Text(message.to_string()));
+    }
+
+    fn feed(&self, message: &Value) {
+        match serde_json::from_value::<Message>(message.clone()) {
+            Ok(message) => self.paper.handle_message(&message),
+            Err(err) => debug!(%err, "Could not parse mock message"),
+        }
+    }
+
+    /// Sets the paper exchange's book, returning it as an `l2Book` message.
+    fn feed_book(&self, coin: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> Value {
+        let book = json!({"channel": "l2Book", "data": book_data(coin, bids, asks)});
+        self.feed(&book);
+        book
+    }
+
+    fn mids(&self) -> BTreeMap<String, String> {
+        self.state()
+            .books
+            .iter()
+            .filter_map(|(coin, (bids, asks))| {
+                let mid = (bids.first()?.0 + asks.first()?.0) / 2.0;
+                Some((coin.clone(), mid.to_string()))
+            })
+            .collect()
+    }
+
+    /// Pushes the paper exchange's order updates and fills to the websockets.
+    fn flush_events(&self) {
+        let mut events = self.events.lock().expect("mock server lock poisoned");
+        while let Ok(event) = events.try_recv() {
+            match event {
+                Message::OrderUpdates(updates) => {
+                    let data: Vec<Value> = updates
+                        .data
+                        .iter()
+                        .map(|update| {
+                            let order = &update.order;
+                            json!({
+                                "order": {
+                                    "coin": order.coin,
+                                    "side": order.side,
+                                    "limitPx": order.limit_px,
+                                    "sz": order.sz,
+                                    "oid": order.oid,
+                                    "timestamp": order.timestamp,
+                                    "origSz": order.orig_sz,
+                                    "cloid": order.cloid,
+                                },
+                                "status": update.status,
+                                "statusTimestamp": update.status_timestamp,
+                            })
+                        })
+                        .collect();
+                    self.broadcast(json!({"channel": "orderUpdates", "data": data}));
+                }
+                Message::UserFills(fills) => {
+                    let data: Vec<Value> = fills.data.fills.iter().map(fill_json).collect();
+                    self.state().fills.splice(0..0, data.iter().rev().cloned());
+                    self.broadcast(json!({
+                        "channel": "userFills",
+                        "data": {"user": fills.data.user, "fills": data},
+                    }));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn respond(&self, path: &str, body: &[u8]) -> (u16, String) {
+        let endpoint = match path {
+            "/info" => MockEndpoint::Info,
+            "/exchange" => MockEndpoint::Exchange,
+            _ => return (404, format!("Unknown path {path}")),
+        };
+        let Ok(body) = serde_json::from_slice::<Value>(body) else {
+            return (400, "Invalid JSON body".to_string());
+        };
+        {
+            let mut state = self.state();
+            state.requests.push((endpoint, body.clone()));
+            let failure = state
+                .failures
+                .iter()
+                .position(|failure| failure.endpoint == endpoint)
+                .and_then(|i| state.failures.remove(i));
+            if let Some(failure) = failure {
+                return (failure.status, failure.body);
+            }
+        }
+        match endpoint {
+            MockEndpoint::Info => self.info(body),
+            MockEndpoint::Exchange => self.exchange(body).await,
+        }
+    }
+
+    fn info(&self, body: Value) -> (u16, String) {
+        let Ok(request) = serde_json::from_value::<InfoRequest>(body) else {
+            return (422, "Failed to deserialize the JSON body".to_string());
+        };
+        let response = match request {
+            InfoRequest::Meta => {
+                let universe: Vec<Value> = self
+                    .config
+                    .universe
+                    .iter()
+                    .map(|asset| {
+                        json!({
+                            "name": asset.name,
+                            "szDecimals": asset.sz_decimals,
+                            "maxLeverage": asset.max_leverage,
+                        })
+                    })
+                    .collect();
+                json!({"universe": universe})
+            }
+            InfoRequest::SpotMeta => json!({"universe": [], "tokens": []}),
+            InfoRequest::AllMids => json!(self.mids()),
+            InfoRequest::L2Book { coin } => {
+                let state = self.state();
+                let (bids, asks) = state.books.get(&coin).cloned().unwrap_or_default();
+                book_data(&coin, &bids, &asks)
+            }
+            InfoRequest::OpenOrders { .. } => {
+                let orders: Vec<Value> = self
+                    .paper
+                    .open_orders()
+                    .into_iter()
+                    .map(|order| {
+                        json!({
+                            "coin": order.coin,
+                            "limitPx": order.limit_px,
+                            "oid": order.oid,
+                            "side": order.side,
+                            "sz": order.sz,
+                            "timestamp": order.timestamp,
+                            "cloid": order.cloid,
+                        })
+                    })
+                    .collect();
+                json!(orders)
+            }
+            InfoRequest::UserFills { .. } => json!(self.state().fills),
+            InfoRequest::UserState { .. } => self.clearinghouse_state(),
+            InfoRequest::UserTokenBalances { .. } => json!({"balances": []}),
+            _ => return (422, "Info request not supported by the mock".to_string()),
+        };
+        (200, response.to_string())
+    }
+
+    fn clearinghouse_state(&self) -> Value {
+        let mut account_value = self.config.account_value;
+        let mut total_ntl = 0.0;
+        let mut margin_used = 0.0;
+        let mut asset_positions = Vec::new();
+        for position in self.paper.positions() {
+            account_value += position.total_pnl();
+            if position.szi == 0.0 {
+                continue;
+            }
+            let leverage = self
+                .config
+                .universe
+                .iter()
+                .find(|asset| asset.name == position.coin)
+                .map_or(1, |asset| asset.max_leverage);
+            let ntl = position.szi.abs() * position.mark_px.unwrap_or(position.entry_px);
+            let margin = ntl / leverage as f64;
+            total_ntl += ntl;
+            margin_used += margin;
+            asset_positions.push(json!({
+                "type": "oneWay",
+                "position": {
+                    "coin": position.coin,
+                    "entryPx": position.entry_px.to_string(),
+                    "leverage": {"type": "cross", "value": leverage},
+                    "liquidationPx": null,
+                    "marginUsed": margin.to_string(),
+                    "positionValue": ntl.to_string(),
+                    "returnOnEquity": (position.unrealized_pnl() / margin).to_string(),
+                    "szi": position.szi.to_string(),
+                    "unrealizedPnl": position.unrealized_pnl().to_string(),
+                    "maxLeverage": leverage,
+                    "cumFunding": {
+                        "allTime": (-position.funding).to_string(),
+                        "sinceOpen": (-position.funding).to_string(),
+                        "sinceChange": (-position.funding).to_string(),
+                    },
+                },
+            }));
+        }
+        let summary = json!({
+            "accountValue": account_value.to_string(),
+            "totalMarginUsed": margin_used.to_string(),
+            "totalNtlPos": total_ntl.to_string(),
+            "totalRawUsd": account_value.to_string(),
+        });
+        json!({
+            "assetPositions": asset_positions,
+            "crossMarginSummary": summary,
+            "marginSummary": summary,
+            "withdrawable": (account_value - margin_used).max(0.0).to_string(),
+        })
+    }
+
+    async fn exchange(&self, body: Value) -> (u16, String) {
+        let _guard = self.exchange_lock.lock().await;
+        let Ok(action) = serde_json::from_value::<Actions>(body["action"].clone()) else {
+            let response = json!({"status": "err", "response": "Unsupported action"});
+            return (200, response.to_string());
+        };
+        let (response_type, statuses) = match action {
+            Actions::Order(bulk) => {
+                let mut statuses = Vec::new();
+                for order in bulk.orders {
+                    statuses.push(self.place(order).await);
+                }
+                ("order", statuses)
+            }
+            Actions::Cancel(bulk) => {
+                let mut statuses = Vec::new();
+                for cancel in bulk.cancels {
+                    let status = match self.coin(cancel.asset) {
+                        Ok(asset) => first_status(
+                            self.paper
+                                .cancel(ClientCancelRequest {
+                                    asset,
+                                    oid: cancel.oid,
+                                })
+                                .await,
+                        ),
+                        Err(err) => err,
+                    };
+                    statuses.push(status);
+                }
+                ("cancel", statuses)
+            }
+            Actions::CancelByCloid(bulk) => {
+                let mut statuses = Vec::new();
+                for cancel in bulk.cancels {
+                    let status = match (self.coin(cancel.asset), parse_cloid(&cancel.cloid)) {
+                        (Ok(asset), Ok(cloid)) => first_status(
+                            self.paper
+                                .cancel_by_cloid(ClientCancelRequestCloid { asset, cloid })
+                                .await,
+                        ),
+                        (Err(err), _) | (_, Err(err)) => err,
+                    };
+                    statuses.push(status);
+                }
+                ("cancel", statuses)
+            }
+            _ => {
+                let response = json!({"status": "ok", "response": {"type": "default"}});
+                return (200, response.to_string());
+            }
+        };
+        self.flush_events();
+        let statuses: Vec<Value> = statuses.iter().map(status_json).collect();
+        let response = json!({
+            "status": "ok",
+            "response": {"type": response_type, "data": {"statuses": statuses}},
+        });
+        (200, response.to_string())
+    }
+
+    async fn place(&self, order: OrderRequest) -> ExchangeDataStatus {
+        let fill = self.state().fill.clone();
+        if let MockFill::Reject(message) = fill {
+            return error_status(&message);
+        }
+        let request = match self.client_order(order) {
+            Ok(request) => request,
+            Err(err) => return err,
+        };
+        if fill != MockFill::Immediate {
+            return first_status(self.paper.order(request).await);
+        }
+
+        // Exactly the order's size is offered at its limit price, then the book is restored
+        let coin = request.asset.clone();
+        let (bids, asks) = self.state().books.get(&coin).cloned().unwrap_or_default();
+        let offered = [(request.limit_px, request.sz)];
+        if request.is_buy {
+            self.feed_book(&coin, &bids, &offered);
+        } else {
+            self.feed_book(&coin, &offered, &asks);
+        }
+        let status = first_status(self.paper.order(request).await);
+        let (bids, asks) = self.state().books.get(&coin).cloned().unwrap_or_default();
+        self.feed_book(&coin, &bids, &asks);
+        status
+    }
+
+    fn coin(&self, asset: u32) -> std::result::Result<String, ExchangeDataStatus> {
+        self.config
+            .universe
+            .get(asset as usize)
+            .map(|asset| asset.name.clone())
+            .ok_or_else(|| error_status(&format!("Unknown asset {asset}")))
+    }
+
+    fn client_order(
+        &self,
+        order: OrderRequest,
+    ) -> std::result::Result<ClientOrderRequest, ExchangeDataStatus> {
+        let parse = |value: &str| {
+            f64::from_str(value).map_err(|_| error_status(&format!("Invalid number {value}")))
+        };
+        let order_type = match order.order_type {
+            Order::Limit(limit) => ClientOrder::Limit(ClientLimit { tif: limit.tif }),
+            Order::Trigger(trigger) => ClientOrder::Trigger(ClientTrigger {
+                is_market: trigger.is_market,
+                trigger_px: parse(&trigger.trigger_px)?,
+                tpsl: trigger.tpsl,
+            }),
+        };
+        Ok(ClientOrderRequest {
+            asset: self.coin(order.asset)?,
+            is_buy: order.is_buy,
+            reduce_only: order.reduce_only,
+            limit_px: parse(&order.limit_px)?,
+            sz: parse(&order.sz)?,
+            cloid: order.cloid.as_deref().map(parse_cloid).transpose()?,
+            order_type,
+        })
+    }
+
+    /// Response to a websocket post request, `None` for other messages.
+    async fn ws_post(&self, text: &str) -> Option<Value> {
+        let request = serde_json::from_str::<Value>(text).ok()?;
+        if request["method"] != "post" {
+            return None;
+        }
+        let request_type = request["request"]["type"].as_str().unwrap_or_default();
+        let path = match request_type {
+            "action" => "/exchange",
+            _ => "/info",
+        };
+        let body = request["request"]["payload"].to_string();
+        let (status, body) = self.respond(path, body.as_bytes()).await;
+        let response = match serde_json::from_str::<Value>(&body) {
+            Ok(payload) if status == 200 => json!({"type": request_type, "payload": payload}),
+            _ => json!({"type": "error", "payload": body}),
+        };
+        Some(json!({
+            "channel": "post",
+            "data": {"id": request["id"], "response": response},
+        }))
+    }
+
+    fn ws_replies(&self, text: &str) -> Vec<Value> {
+        let Ok(request) = serde_json::from_str::<Value>(text) else {
+            return Vec::new();
+        };
+        match request["method"].as_str() {
+            Some("ping") => vec![json!({"channel": "pong"})],
+            Some(method @ ("subscribe" | "unsubscribe")) => {
+                let subscription = &request["subscription"];
+                let mut replies = vec![json!({
+                    "channel": "subscriptionResponse",
+                    "data": {"method": method, "subscription": subscription},
+                })];
+                // Like the API, a book subscription starts with a snapshot
+                if method == "subscribe" && subscription["type"] == "l2Book" {
+                    let coin = subscription["coin"].as_str().unwrap_or_default();
+                    if let Some((bids, asks)) = self.state().books.get(coin) {
+                        replies.push(json!({
+                            "channel": "l2Book",
+                            "data": book_data(coin, bids, asks),
+                        }));
+                    }
+                }
+                replies
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+async fn serve(listener: TcpListener, inner: Arc<Inner>) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let inner = inner.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(stream, inner).await {
+                debug!(%err, "Mock connection closed");
+            }
+        });
+    }
+}
+
+async fn serve_connection(stream: TcpStream, inner: Arc<Inner>) -> Result<()> {
+    let mut first = [0u8; 1];
+    stream
+        .peek(&mut first)
+        .await
+        .map_err(|e| Error::Io(e.to_string()))?;
+    // Websocket upgrades are the only GET requests
+    if first[0] == b'G' {
+        serve_websocket(stream, inner).await
+    } else {
+        serve_http(stream, inner)
+            .await
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+async fn serve_websocket(stream: TcpStream, inner: Arc<Inner>) -> Result<()> {
+    // Subscribed before the handshake completes so nothing sent after it is missed
+    let mut outbound = inner.outbound.subscribe();
+    let ws = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|e| Error::Websocket(e.to_string()))?;
+    let (mut writer, mut reader) = ws.split();
+    loop {
+        let text = tokio::select! {
+            message = reader.next() => match message {
+                Some(Ok(WsMessage::Text(text))) => {
+                    let replies = match inner.ws_post(&text).await {
+                        Some(reply) => vec![reply],
+                        None => inner.ws_replies(&text),
+                    };
+                    for reply in replies {
+                        writer
+                            .send(WsMessage::Text(reply.to_string()))
+                            .await
+                            .map_err(|e| Error::Websocket(e.to_string()))?;
+                    }
+                    continue;
+                }
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            outbound = outbound.recv() => match outbound {
+                Ok(Outbound::Text(text)) => text,
+                Ok(Outbound::Disconnect) | Err(RecvError::Closed) => break,
+                Err(RecvError::Lagged(_)) => continue,
+            },
+        };
+        writer
+            .send(WsMessage::Text(text))
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?;
+    }
+    let _ = writer.close().await;
+    Ok(())
+}
+
+/// Minimal HTTP/1.1 with keep-alive, enough for the clients' JSON POSTs.
+async fn serve_http(stream: TcpStream, inner: Arc<Inner>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).await? == 0 {
+            return Ok(());
+        }
+        let path = request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or_default()
+            .to_string();
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).await? == 0 {
+                return Ok(());
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or_default();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+
+        let (status, response) = inner.respond(&path, &body).await;
+        let reason = match status {
+            200 => "OK",
+            404 => "Not Found",
+            429 => "Too Many Requests",
+            500.. => "Internal Server Error",
+            _ => "Bad Request",
+        };
+        let head = format!(
+            "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            response.len()
+        );
+        let stream = reader.get_mut();
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(response.as_bytes()).await?;
+    }
+}
+
+fn book_data(coin: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> Value {
+    let levels = |levels: &[(f64, f64)]| -> Vec<Value> {
+        levels
+            .iter()
+            .map(|(px, sz)| json!({"px": px.to_string(), "sz": sz.to_string(), "n": 1}))
+            .collect()
+    };
+    json!({
+        "coin": coin,
+        "time": now_timestamp_ms(),
+        "levels": [levels(bids), levels(asks)],
+    })
+}
+
+fn fill_json(fill: &TradeInfo) -> Value {
+    json!({
+        "coin": fill.coin,
+        "side": fill.side,
+        "px": fill.px,
+        "sz": fill.sz,
+        "time": fill.time,
+        "hash": fill.hash,
+        "startPosition": fill.start_position,
+        "dir": fill.dir,
+        "closedPnl": fill.closed_pnl,
+        "oid": fill.oid,
+        "cloid": fill.cloid,
+        "crossed": fill.crossed,
+        "fee": fill.fee,
+        "feeToken": fill.fee_token,
+        "tid": fill.tid,
+        "twapId": fill.twap_id,
+        "builderFee": fill.builder_fee,
+        "liquidation": fill.liquidation,
+    })
+}
+
+fn status_json(status: &ExchangeDataStatus) -> Value {
+    match status {
+        ExchangeDataStatus::Success => json!("success"),
+        ExchangeDataStatus::WaitingForFill => json!("waitingForFill"),
+        ExchangeDataStatus::WaitingForTrigger => json!("waitingForTrigger"),
+        ExchangeDataStatus::Error(err) => json!({"error": err.message()}),
+        ExchangeDataStatus::Resting(resting) => json!({"resting": {"oid": resting.oid}}),
+        ExchangeDataStatus::Filled(filled) => json!({
+            "filled": {"totalSz": filled.total_sz, "avgPx": filled.avg_px, "oid": filled.oid},
+        }),
+    }
+}
+
+fn first_status(response: Result<ExchangeResponseStatus>) -> ExchangeDataStatus {
+    match response {
+        Ok(ExchangeResponseStatus::Ok(response)) => response
+            .data
+            .and_then(|data| data.statuses.into_iter().next())
+            .unwrap_or(ExchangeDataStatus::Success),
+        Ok(ExchangeResponseStatus::Err(err)) => ExchangeDataStatus::Error(err),
+        Err(err) => error_status(&err.to_string()),
+    }
+}
+
+fn parse_cloid(cloid: &str) -> std::result::Result<Uuid, ExchangeDataStatus> {
+    Uuid::parse_str(cloid.trim_start_matches("0x"))
+        .map_err(|_| error_status(&format!("Invalid cloid {cloid}")))
+}
+
+fn error_status(message: &str) -> ExchangeDataStatus {
+    ExchangeDataStatus::Error(message.to_string().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::{ExchangeClient, InfoClient, Subscription, Tif};
+
+    fn order(is_buy: bool, limit_px: f64, tif: Tif) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz: 1.0,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif }),
+        }
+    }
+
+    fn status(response: ExchangeResponseStatus) -> ExchangeDataStatus {
+        match response {
+            ExchangeResponseStatus::Ok(response) => response.data.unwrap().statuses.remove(0),
+            ExchangeResponseStatus::Err(err) => panic!("{err}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mock_server_round_trip() {
+        let wallet: PrivateKeySigner =
+            "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .unwrap();
+        let user = wallet.address();
+        let server = MockServer::start(MockConfig {
+            user,
+            ..MockConfig::default()
+        })
+        .await
+        .unwrap();
+        server.set_book("ETH", &[(1999.0, 5.0)], &[(2001.0, 5.0)]);
+
+        let exchange = ExchangeClient::new(None, wallet, Some(server.base_url()), None, None)
+            .await
+            .unwrap();
+        let info = InfoClient::new(None, Some(server.base_url()))
+            .await
+            .unwrap();
+        let (sender, mut receiver) = unbounded_channel();
+        info.subscribe(Subscription::OrderUpdates { user }, sender)
+            .await
+            .unwrap();
+
+        let response = exchange
+            .order(order(true, 2002.0, Tif::Ioc), None)
+            .await
+            .unwrap();
+        assert!(
+            matches!(status(response), ExchangeDataStatus::Filled(filled) if filled.avg_px == "2001")
+        );
+        let update = tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .unwrap();
+        assert!(matches!(update, Some(Message::OrderUpdates(u)) if u.data[0].status == "filled"));
+        let state = info.user_state(user).await.unwrap();
+        assert_eq!(state.asset_positions[0].position.szi, "1");
+        assert_eq!(info.user_fills(user).await.unwrap().len(), 1);
+
+        let response = exchange
+            .order(order(false, 2010.0, Tif::Gtc), None)
+            .await
+            .unwrap();
+        let ExchangeDataStatus::Resting(resting) = status(response) else {
+            panic!("expected resting order");
+        };
+        assert_eq!(info.open_orders(user).await.unwrap().len(), 1);
+        let snapshot = info.account_snapshot(user).await.unwrap();
+        assert_eq!(snapshot.state.asset_positions[0].position.szi, "1");
+        assert_eq!((snapshot.open_orders.len(), snapshot.fills.len()), (1, 1));
+        assert!(snapshot.balances.is_empty());
+        let response = exchange
+            .cancel(
+                ClientCancelRequest {
+                    asset: "ETH".to_string(),
+                    oid: resting.oid,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(status(response), ExchangeDataStatus::Success));
+        assert!(server.open_orders().is_empty());
+
+        server.fail_next(MockEndpoint::Exchange, 500, "boom");
+        assert!(exchange
+            .order(order(true, 1990.0, Tif::Gtc), None)
+            .await
+            .is_err());
+        assert!(server.open_orders().is_empty());
+
+        server.set_fill(MockFill::Immediate);
+        let response = exchange
+            .order(order(false, 2005.0, Tif::Gtc), None)
+            .await
+            .unwrap();
+        assert!(
+            matches!(status(response), ExchangeDataStatus::Filled(filled) if filled.avg_px == "2005")
+        );
+        assert!(server.positions()[0].szi.abs() < crate::EPSILON);
+        assert_eq!(server.requests(MockEndpoint::Exchange).len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_ws_post_is_not_resent_over_rest() {
+        let wallet = PrivateKeySigner::random();
+        let server = MockServer::start(MockConfig {
+            user: wallet.address(),
+            ..MockConfig::default()
+        })
+        .await
+        .unwrap();
+        server.set_book("ETH", &[(1999.0, 5.0)], &[(2001.0, 5.0)]);
+        let exchange = ExchangeClient::builder()
+            .wallet(wallet)
+            .base_url(server.base_url())
+            .ws_post(true)
+            .build()
+            .await
+            .unwrap();
+
+        let response = exchange
+            .order(order(true, 2002.0, Tif::Ioc), None)
+            .await
+            .unwrap();
+        assert!(
+            matches!(status(response), ExchangeDataStatus::Filled(filled) if filled.avg_px == "2001")
+        );
+        assert_eq!(server.requests(MockEndpoint::Exchange).len(), 1);
+
+        // An action that reached the websocket is not sent again over REST with its nonce
+        server.fail_next(MockEndpoint::Exchange, 500, "boom");
+        let res = exchange.order(order(false, 2010.0, Tif::Gtc), None).await;
+        assert!(matches!(res, Err(Error::Websocket(_))));
+        assert_eq!(server.requests(MockEndpoint::Exchange).len(), 2);
+    }
+}
@@ -2,17 +2,39 @@ mod actions;
 mod builder;
 mod cancel;
 mod exchange_client;
+mod exchange_errors;
 mod exchange_responses;
+mod exchange_trait;
+mod latency;
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+mod mock;
 mod modify;
+mod nonce;
 mod order;
+mod paper;
+mod scheduler;
+mod spot_preflight;
+mod testnet;
+mod wallet_pool;
 
 pub use actions::*;
 pub use builder::*;
 pub use cancel::{ClientCancelRequest, ClientCancelRequestCloid};
 pub use exchange_client::*;
+pub use exchange_errors::ExchangeError;
 pub use exchange_responses::*;
+pub use exchange_trait::Exchange;
+pub use latency::{ActionLatency, LatencyHook};
+#[cfg(all(feature = "mock", not(target_arch = "wasm32")))]
+pub use mock::{MockConfig, MockEndpoint, MockFill, MockServer};
 pub use modify::{ClientModifyRequest, ModifyRequest};
+pub use nonce::{NonceManager, NonceReservation, NonceStore};
 pub use order::{
     ClientLimit, ClientOrder, ClientOrderRequest, ClientTrigger, MarketCloseParams,
-    MarketOrderParams, Order,
+    MarketOrderParams, Order, OrderRequest,
 };
+pub use paper::{PaperConfig, PaperExchange};
+pub use scheduler::{ActionPriority, ActionScheduler};
+pub use spot_preflight::{SpotPrecondition, SpotPreflight};
+pub use testnet::{TestnetAccount, TestnetBootstrap, TESTNET_FAUCET_URL};
+pub use wallet_pool::{WalletPool, WalletSelection};
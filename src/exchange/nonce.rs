@@ -0,0 +1,281 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{mpsc::Sender, Arc, Mutex, MutexGuard, OnceLock},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use tracing::{info, warn};
+
+use crate::{
+    helpers::{advance_nonce_past, next_nonce, now_timestamp_ms, reserve_nonces},
+    prelude::*,
+    trading::persist::{load_json, save_json},
+    InfoClient,
+};
+
+/// Hands out nonces from the counter every `ExchangeClient` in the process signs with, so
+/// nonces taken here are never used by another action.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NonceManager;
+
+impl NonceManager {
+    pub fn next(&self) -> u64 {
+        next_nonce()
+    }
+
+    /// Atomically takes `count` consecutive nonces for pre-signing a batch of actions, such as
+    /// a ladder of contingency cancels. The reservation expires after an hour by default.
+    pub fn reserve(&self, count: usize) -> NonceReservation {
+        let start = reserve_nonces(count as u64);
+        NonceReservation {
+            start,
+            used: vec![false; count],
+            expires_at: start + 3_600_000,
+        }
+    }
+}
+
+/// Consecutive nonces taken by `NonceManager::reserve`, tracking which were used.
+///
+/// The exchange only accepts a nonce within a couple of days of its time and above the
+/// smallest of the signer's 100 highest nonces, so unused nonces lapse on their own, but not
+/// at a known time. Once a reservation expires, `ExchangeClient::invalidate_reservation` uses
+/// up its unused nonces with noops so nothing signed with them can still be sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonceReservation {
+    start: u64,
+    used: Vec<bool>,
+    expires_at: u64,
+}
+
+impl NonceReservation {
+    /// Expires `ttl` after the reservation was taken.
+    pub fn with_expiry(mut self, ttl: Duration) -> Self {
+        self.expires_at = self.start + ttl.as_millis() as u64;
+        self
+    }
+
+    pub fn nonces(&self) -> Range<u64> {
+        self.start..self.start + self.used.len() as u64
+    }
+
+    pub fn contains(&self, nonce: u64) -> bool {
+        self.nonces().contains(&nonce)
+    }
+
+    /// Marks and returns the lowest nonce not used yet.
+    pub fn take(&mut self) -> Option<u64> {
+        let index = self.used.iter().position(|used| !used)?;
+        self.used[index] = true;
+        Some(self.start + index as u64)
+    }
+
+    /// Marks `nonce` as used, returning false if it is not part of the reservation.
+    pub fn mark_used(&mut self, nonce: u64) -> bool {
+        if !self.contains(nonce) {
+            return false;
+        }
+        self.used[(nonce - self.start) as usize] = true;
+        true
+    }
+
+    /// Nonces not marked used, in order.
+    pub fn unused(&self) -> impl Iterator<Item = u64> + '_ {
+        self.nonces()
+            .zip(&self.used)
+            .filter(|(_, used)| !**used)
+            .map(|(nonce, _)| nonce)
+    }
+
+    /// Time in milliseconds after which unused nonces should be invalidated.
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    pub fn is_expired(&self) -> bool {
+        now_timestamp_ms() >= self.expires_at
+    }
+}
+
+/// Highest nonce each signer may have used, kept in a JSON file so a restarted process starts
+/// its nonces past them instead of reusing ones from the same milliseconds, which the exchange
+/// rejects as already used.
+///
+/// Opening the store moves the nonces of every `ExchangeClient` in the process past the saved
+/// ones. Attached with `ExchangeClient::with_nonce_store`, each action's nonce is recorded
+/// before it is sent and written by a background thread, off the order path. To write the
+/// file less often, a nonce is saved ahead by the reservation, and nonces are only saved again
+/// once they pass it, which also covers actions sent while a write is in progress.
+#[derive(Debug)]
+pub struct NonceStore {
+    path: PathBuf,
+    reservation: u64,
+    nonces: Arc<Mutex<HashMap<Address, u64>>>,
+    /// Held while writing, so a newer snapshot is never overwritten by an older one
+    write_lock: Arc<Mutex<()>>,
+    /// Wakes the writer thread, started on the first save
+    writer: OnceLock<Sender<()>>,
+}
+
+impl NonceStore {
+    /// Opens the store at `path`, created on the first save, and moves nonces past it.
+    pub fn open(path: impl AsRef<Path>) -> Result<NonceStore> {
+        let path = path.as_ref().to_path_buf();
+        let nonces: HashMap<Address, u64> = if path.exists() {
+            load_json(&path)?
+        } else {
+            HashMap::new()
+        };
+        if let Some(&highest) = nonces.values().max() {
+            info!(nonce = highest, "Starting nonces past the saved ones");
+            advance_nonce_past(highest);
+        }
+        Ok(NonceStore {
+            path,
+            reservation: 1000,
+            nonces: Arc::new(Mutex::new(nonces)),
+            write_lock: Arc::default(),
+            writer: OnceLock::new(),
+        })
+    }
+
+    /// How far ahead nonces are saved, 1 second by default.
+    pub fn with_reservation(mut self, reservation: Duration) -> Self {
+        self.reservation = reservation.as_millis() as u64;
+        self
+    }
+
+    /// Highest nonce `signer` may have used.
+    pub fn last_nonce(&self, signer: Address) -> Option<u64> {
+        self.nonces().get(&signer).copied()
+    }
+
+    /// Checks the latest orders of `user` against the saved nonces, moving nonces past them if
+    /// the exchange saw orders later than every saved nonce, as when actions were sent without
+    /// the store. Returns the time of the latest order.
+    pub async fn verify(&self, info: &InfoClient, user: Address) -> Result<Option<u64>> {
+        let latest = info
+            .historical_orders(user)
+            .await?
+            .iter()
+            .map(|order| {
+                order
+                    .order
+                    .timestamp
+                    .max(order.status_timestamp)
+                    .as_millis()
+            })
+            .max();
+        let saved = self.nonces().values().max().copied();
+        if let Some(latest) = latest.filter(|&latest| saved.is_none_or(|saved| latest > saved)) {
+            warn!(
+                %user,
+                latest,
+                saved,
+                "Exchange has orders after the last saved nonce, moving nonces past them"
+            );
+            advance_nonce_past(latest);
+        }
+        Ok(latest)
+    }
+
+    /// Writes the recorded nonces now, such as before exiting.
+    pub fn flush(&self) -> Result<()> {
+        write(&self.path, &self.nonces, &self.write_lock)
+    }
+
+    /// Records `nonce` for `signer`, having it written if it passed the saved reservation.
+    pub(crate) fn record(&self, signer: Address, nonce: u64) {
+        {
+            let mut nonces = self.nonces();
+            if nonces.get(&signer).is_some_and(|&saved| nonce <= saved) {
+                return;
+            }
+            nonces.insert(signer, nonce + self.reservation);
+        }
+        let writer = self.writer.get_or_init(|| {
+            let (sender, receiver) = std::sync::mpsc::channel::<()>();
+            let (path, nonces, write_lock) = (
+                self.path.clone(),
+                Arc::clone(&self.nonces),
+                Arc::clone(&self.write_lock),
+            );
+            // Runs until the store is dropped, after writing what was recorded until then
+            std::thread::spawn(move || {
+                while receiver.recv().is_ok() {
+                    // Saves recorded meanwhile are covered by this write
+                    while receiver.try_recv().is_ok() {}
+                    if let Err(err) = write(&path, &nonces, &write_lock) {
+                        warn!(%err, "Could not save nonces");
+                    }
+                }
+            });
+            sender
+        });
+        let _ = writer.send(());
+    }
+
+    fn nonces(&self) -> MutexGuard<'_, HashMap<Address, u64>> {
+        self.nonces.lock().expect("nonce store lock poisoned")
+    }
+}
+
+fn write(path: &Path, nonces: &Mutex<HashMap<Address, u64>>, write_lock: &Mutex<()>) -> Result<()> {
+    let _writing = write_lock.lock().expect("nonce store lock poisoned");
+    let snapshot = nonces.lock().expect("nonce store lock poisoned").clone();
+    save_json(path, &snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Actions, Noop};
+
+    #[test]
+    fn test_reservation_is_contiguous_and_tracks_use() {
+        let manager = NonceManager;
+        let mut reservation = manager.reserve(3);
+        let nonces = reservation.nonces();
+        assert_eq!(nonces.end - nonces.start, 3);
+        assert!(manager.next() >= nonces.end);
+        assert!(!reservation.is_expired());
+
+        assert_eq!(reservation.take(), Some(nonces.start));
+        assert!(reservation.mark_used(nonces.start + 2));
+        assert!(!reservation.mark_used(nonces.end));
+        assert_eq!(reservation.unused().collect::<Vec<_>>(), [nonces.start + 1]);
+        assert_eq!(reservation.take(), Some(nonces.start + 1));
+        assert_eq!(reservation.take(), None);
+
+        // Nonces may run ahead of the clock once moved past saved ones, so compare with the start
+        let reservation = manager.reserve(1).with_expiry(Duration::ZERO);
+        assert_eq!(reservation.expires_at(), reservation.nonces().start);
+        assert_eq!(
+            serde_json::to_value(Actions::Noop(Noop)).unwrap(),
+            serde_json::json!({"type": "noop"})
+        );
+    }
+
+    #[test]
+    fn test_restart_starts_past_saved_nonces() {
+        let path = std::env::temp_dir().join(format!("nonces_{}.json", std::process::id()));
+        let signer = Address::repeat_byte(1);
+        let store = NonceStore::open(&path).unwrap();
+        let nonce = next_nonce();
+        store.record(signer, nonce);
+        let saved = store.last_nonce(signer).unwrap();
+        assert_eq!(saved, nonce + 1000);
+        // Within the reservation nothing is written
+        store.record(signer, nonce + 1);
+        assert_eq!(store.last_nonce(signer), Some(saved));
+        store.flush().unwrap();
+
+        let restarted = NonceStore::open(&path).unwrap();
+        assert_eq!(restarted.last_nonce(signer), Some(saved));
+        assert!(next_nonce() > saved);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -8,11 +8,12 @@ use crate::{
     errors::Error,
     helpers::{float_to_string_for_hashing, uuid_to_hex_string},
     prelude::*,
+    Tif, TriggerCondition,
 };
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Limit {
-    pub tif: String,
+    pub tif: Tif,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -20,7 +21,7 @@ pub struct Limit {
 pub struct Trigger {
     pub is_market: bool,
     pub trigger_px: String,
-    pub tpsl: String,
+    pub tpsl: TriggerCondition,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -49,16 +50,54 @@ pub struct OrderRequest {
     pub cloid: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClientLimit {
-    pub tif: String,
+    pub tif: Tif,
 }
 
-#[derive(Debug)]
+/// Trigger of a take-profit or stop-loss order, fired when the mark price reaches
+/// `trigger_px`. Triggers always compare against the mark price; Hyperliquid has no last or
+/// oracle price triggers.
+///
+/// Once fired, a market trigger executes at once with the exchange's slippage tolerance,
+/// while a limit trigger places a limit order at the order's `limit_px`.
+#[derive(Debug, Clone)]
 pub struct ClientTrigger {
     pub is_market: bool,
     pub trigger_px: f64,
-    pub tpsl: String,
+    pub tpsl: TriggerCondition,
+}
+
+impl ClientTrigger {
+    pub fn market(trigger_px: f64, tpsl: TriggerCondition) -> ClientTrigger {
+        ClientTrigger {
+            is_market: true,
+            trigger_px,
+            tpsl,
+        }
+    }
+
+    pub fn limit(trigger_px: f64, tpsl: TriggerCondition) -> ClientTrigger {
+        ClientTrigger {
+            is_market: false,
+            trigger_px,
+            tpsl,
+        }
+    }
+
+    /// Whether the trigger of an order on side `is_buy` has already been reached at
+    /// `mark_px`. A take-profit sells a long above, or buys back a short below, the mark; a
+    /// stop-loss the other way round.
+    pub fn is_reached(&self, is_buy: bool, mark_px: f64) -> bool {
+        match (self.tpsl, is_buy) {
+            (TriggerCondition::TakeProfit, false) | (TriggerCondition::StopLoss, true) => {
+                mark_px >= self.trigger_px
+            }
+            (TriggerCondition::TakeProfit, true) | (TriggerCondition::StopLoss, false) => {
+                mark_px <= self.trigger_px
+            }
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -82,13 +121,13 @@ pub struct MarketCloseParams<'a> {
     pub wallet: Option<&'a PrivateKeySigner>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ClientOrder {
     Limit(ClientLimit),
     Trigger(ClientTrigger),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ClientOrderRequest {
     pub asset: String,
     pub is_buy: bool,
@@ -100,7 +139,96 @@ pub struct ClientOrderRequest {
 }
 
 impl ClientOrderRequest {
-    pub(crate) fn convert(self, coin_to_asset: &HashMap<String, u32>) -> Result<OrderRequest> {
+    /// A reduce-only take-profit of `sz`, selling a long (`is_buy` false) or buying back a
+    /// short once the mark price reaches `trigger_px`. Executes at market without `limit_px`.
+    pub fn take_profit(
+        asset: &str,
+        is_buy: bool,
+        sz: f64,
+        trigger_px: f64,
+        limit_px: Option<f64>,
+    ) -> ClientOrderRequest {
+        Self::tpsl(
+            asset,
+            is_buy,
+            sz,
+            trigger_px,
+            limit_px,
+            TriggerCondition::TakeProfit,
+        )
+    }
+
+    /// A reduce-only stop-loss of `sz`, like `take_profit` but fired as the price moves
+    /// against the position.
+    pub fn stop_loss(
+        asset: &str,
+        is_buy: bool,
+        sz: f64,
+        trigger_px: f64,
+        limit_px: Option<f64>,
+    ) -> ClientOrderRequest {
+        Self::tpsl(
+            asset,
+            is_buy,
+            sz,
+            trigger_px,
+            limit_px,
+            TriggerCondition::StopLoss,
+        )
+    }
+
+    fn tpsl(
+        asset: &str,
+        is_buy: bool,
+        sz: f64,
+        trigger_px: f64,
+        limit_px: Option<f64>,
+        tpsl: TriggerCondition,
+    ) -> ClientOrderRequest {
+        let trigger = match limit_px {
+            Some(_) => ClientTrigger::limit(trigger_px, tpsl),
+            None => ClientTrigger::market(trigger_px, tpsl),
+        };
+        ClientOrderRequest {
+            asset: asset.to_string(),
+            is_buy,
+            reduce_only: true,
+            // The exchange takes the trigger price as the limit of market triggers
+            limit_px: limit_px.unwrap_or(trigger_px),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Trigger(trigger),
+        }
+    }
+
+    /// Checks the trigger of a trigger order: positive prices and, given the current
+    /// `mark_px`, a trigger price on the side of the mark where it has not fired yet, as a
+    /// take-profit below the mark on a long would fill at once. Limit orders always pass.
+    pub fn validate_trigger(&self, mark_px: Option<f64>) -> Result<()> {
+        let ClientOrder::Trigger(trigger) = &self.order_type else {
+            return Ok(());
+        };
+        let positive = |value: f64| value.is_finite() && value > 0.0;
+        if !positive(trigger.trigger_px) || !positive(self.limit_px) {
+            return Err(Error::InvalidOrder(format!(
+                "{} trigger order needs positive trigger and limit prices",
+                self.asset
+            )));
+        }
+        if let Some(mark_px) = mark_px.filter(|&mark_px| trigger.is_reached(self.is_buy, mark_px)) {
+            return Err(Error::InvalidOrder(format!(
+                "{} {} trigger {} is already reached at mark {mark_px}",
+                self.asset,
+                trigger.tpsl.as_str(),
+                trigger.trigger_px
+            )));
+        }
+        Ok(())
+    }
+
+    /// The order's wire form, with prices and sizes formatted for signing and the asset
+    /// resolved through `coin_to_asset`.
+    pub fn convert(self, coin_to_asset: &HashMap<String, u32>) -> Result<OrderRequest> {
         let order_type = match self.order_type {
             ClientOrder::Limit(limit) => Order::Limit(Limit { tif: limit.tif }),
             ClientOrder::Trigger(trigger) => Order::Trigger(Trigger {
@@ -124,3 +252,43 @@ impl ClientOrderRequest {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tpsl_constructors_and_trigger_validation() {
+        let tp = ClientOrderRequest::take_profit("ETH", false, 1.0, 2200.0, None);
+        let ClientOrder::Trigger(trigger) = &tp.order_type else {
+            panic!("expected a trigger");
+        };
+        assert!(trigger.is_market && tp.reduce_only);
+        assert_eq!(tp.limit_px, 2200.0);
+        assert!(tp.validate_trigger(Some(2000.0)).is_ok());
+        // A take-profit on a long below the mark would sell at once
+        assert!(matches!(
+            tp.validate_trigger(Some(2300.0)),
+            Err(Error::InvalidOrder(_))
+        ));
+
+        let sl = ClientOrderRequest::stop_loss("ETH", true, 1.0, 2100.0, Some(2110.0));
+        let ClientOrder::Trigger(trigger) = &sl.order_type else {
+            panic!("expected a trigger");
+        };
+        assert!(!trigger.is_market);
+        assert!(trigger.is_reached(true, 2100.0));
+        assert!(sl.validate_trigger(Some(2000.0)).is_ok());
+        assert!(sl.validate_trigger(Some(2150.0)).is_err());
+
+        let wire = sl
+            .convert(&HashMap::from([("ETH".to_string(), 1)]))
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&wire.order_type).unwrap(),
+            serde_json::json!({"trigger": {"isMarket": false, "triggerPx": "2100", "tpsl": "sl"}})
+        );
+        let invalid = ClientOrderRequest::stop_loss("ETH", true, 1.0, f64::NAN, None);
+        assert!(invalid.validate_trigger(None).is_err());
+    }
+}
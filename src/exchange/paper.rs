@@ -0,0 +1,686 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    helpers::{now_timestamp_ms, uuid_to_hex_string},
+    prelude::*,
+    rt::{self, MaybeSend},
+    BasicOrder, ClientCancelRequest, ClientCancelRequestCloid, ClientOrder, ClientOrderRequest,
+    Exchange, ExchangeDataStatus, ExchangeDataStatuses, ExchangeError, ExchangeResponse,
+    ExchangeResponseStatus, FillDirection, FilledOrder, Message, OpenOrdersResponse, OrderUpdate,
+    OrderUpdates, Position, RestingOrder, Side, Tif, TradeInfo, UserFills, UserFillsData,
+    UserFunding, UserFundings, UserFundingsData, EPSILON,
+};
+
+#[derive(Clone, Debug)]
+pub struct PaperConfig {
+    /// Delay before each action takes effect, modelling the round trip to the exchange
+    pub latency: Duration,
+    pub taker_fee_bps: f64,
+    pub maker_fee_bps: f64,
+    /// Address reported by `Exchange::address` and in simulated fills
+    pub address: Address,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        PaperConfig {
+            latency: Duration::from_millis(100),
+            taker_fee_bps: 4.5,
+            maker_fee_bps: 1.5,
+            address: Address::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PaperOrder {
+    coin: String,
+    is_buy: bool,
+    limit_px: f64,
+    sz: f64,
+    orig_sz: f64,
+    cloid: Option<String>,
+    timestamp: u64,
+}
+
+#[derive(Debug, Default)]
+struct Book {
+    /// Best first
+    bids: Vec<(f64, f64)>,
+    asks: Vec<(f64, f64)>,
+}
+
+#[derive(Debug)]
+struct State {
+    books: HashMap<String, Book>,
+    orders: BTreeMap<u64, PaperOrder>,
+    positions: HashMap<String, Position>,
+    next_oid: u64,
+    next_tid: u64,
+    /// Simulated time, wall clock time if unset
+    time: Option<u64>,
+}
+
+impl State {
+    fn now(&self) -> u64 {
+        self.time.unwrap_or_else(now_timestamp_ms)
+    }
+
+    fn set_mark_px(&mut self, coin: &str, mark_px: f64) {
+        if let Some(position) = self.positions.get_mut(coin) {
+            position.mark_px = Some(mark_px);
+        }
+    }
+}
+
+/// Simulated exchange for trying strategies on live market data without risking funds.
+///
+/// Feed it `l2Book` and `trades` messages with `handle_message`. New orders take liquidity
+/// from the latest book, paying the taker fee; resting orders fill at their limit price,
+/// paying the maker fee, when the book crosses them or a trade prints through them. Queue
+/// position is not modelled. Fills and order updates are sent to the channel set with
+/// `with_sender` in the same shape as the `userFills` and `orderUpdates` streams.
+#[derive(Debug)]
+pub struct PaperExchange {
+    config: PaperConfig,
+    state: Mutex<State>,
+    sender: Option<UnboundedSender<Message>>,
+}
+
+impl PaperExchange {
+    pub fn new(config: PaperConfig) -> PaperExchange {
+        PaperExchange {
+            config,
+            state: Mutex::new(State {
+                books: HashMap::new(),
+                orders: BTreeMap::new(),
+                positions: HashMap::new(),
+                next_oid: 1,
+                next_tid: 1,
+                time: None,
+            }),
+            sender: None,
+        }
+    }
+
+    pub fn with_sender(mut self, sender: UnboundedSender<Message>) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("paper exchange lock poisoned")
+    }
+
+    pub fn position(&self, coin: &str) -> Option<Position> {
+        self.state().positions.get(coin).cloned()
+    }
+
+    pub fn positions(&self) -> Vec<Position> {
+        self.state().positions.values().cloned().collect()
+    }
+
+    pub fn open_orders(&self) -> Vec<OpenOrdersResponse> {
+        self.state()
+            .orders
+            .iter()
+            .map(|(&oid, order)| OpenOrdersResponse {
+                coin: order.coin.clone(),
+                limit_px: order.limit_px.to_string(),
+                oid,
+                side: Side::from_is_buy(order.is_buy),
+                sz: order.sz.to_string(),
+                timestamp: order.timestamp.into(),
+                cloid: order.cloid.clone(),
+            })
+            .collect()
+    }
+
+    /// Sets the time stamped on fills and order updates, for replaying historical data.
+    pub fn set_time(&self, time: u64) {
+        self.state().time = Some(time);
+    }
+
+    /// Pays `funding_rate` on the open position in `coin` at its mark price, longs paying
+    /// when the rate is positive, and sends the payment as a `userFundings` message.
+    pub fn apply_funding(&self, coin: &str, funding_rate: f64) {
+        let mut state = self.state();
+        let time = state.now();
+        let Some(position) = state.positions.get_mut(coin) else {
+            return;
+        };
+        let Some(mark_px) = position.mark_px.filter(|_| position.szi != 0.0) else {
+            return;
+        };
+        let usdc = -position.szi * mark_px * funding_rate;
+        position.funding += usdc;
+        let event = Message::UserFundings(UserFundings {
+            data: UserFundingsData {
+                is_snapshot: None,
+                user: self.config.address,
+                fundings: vec![UserFunding {
+                    time: time.into(),
+                    coin: coin.to_string(),
+                    usdc: usdc.to_string(),
+                    szi: position.szi.to_string(),
+                    funding_rate: funding_rate.to_string(),
+                }],
+            },
+        });
+        drop(state);
+        self.send(vec![event]);
+    }
+
+    /// Applies `l2Book` and `trades` messages; others are ignored.
+    pub fn handle_message(&self, message: &Message) {
+        let mut state = self.state();
+        let mut events = Vec::new();
+        match message {
+            Message::L2Book(book) => {
+                let parse = |levels: Option<&Vec<crate::BookLevel>>| -> Vec<(f64, f64)> {
+                    levels
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|l| Some((l.px.parse().ok()?, l.sz.parse().ok()?)))
+                        .collect()
+                };
+                let coin = book.data.coin.clone();
+                let new_book = Book {
+                    bids: parse(book.data.levels.first()),
+                    asks: parse(book.data.levels.get(1)),
+                };
+                let best_bid = new_book.bids.first().map(|l| l.0);
+                let best_ask = new_book.asks.first().map(|l| l.0);
+                state.books.insert(coin.clone(), new_book);
+                if let (Some(bid), Some(ask)) = (best_bid, best_ask) {
+                    state.set_mark_px(&coin, (bid + ask) / 2.0);
+                }
+                let crossed: Vec<u64> = state
+                    .orders
+                    .iter()
+                    .filter(|(_, o)| o.coin == coin)
+                    .filter(|(_, o)| {
+                        if o.is_buy {
+                            best_ask.is_some_and(|ask| ask <= o.limit_px)
+                        } else {
+                            best_bid.is_some_and(|bid| bid >= o.limit_px)
+                        }
+                    })
+                    .map(|(&oid, _)| oid)
+                    .collect();
+                for oid in crossed {
+                    let sz = state.orders[&oid].sz;
+                    self.fill_resting(&mut state, oid, sz, &mut events);
+                }
+            }
+            Message::Trades(trades) => {
+                for trade in &trades.data {
+                    let (Ok(px), Ok(mut sz)) = (trade.px.parse::<f64>(), trade.sz.parse::<f64>())
+                    else {
+                        continue;
+                    };
+                    state.set_mark_px(&trade.coin, px);
+                    let through: Vec<u64> = state
+                        .orders
+                        .iter()
+                        .filter(|(_, o)| o.coin == trade.coin)
+                        .filter(|(_, o)| {
+                            if o.is_buy {
+                                px <= o.limit_px
+                            } else {
+                                px >= o.limit_px
+                            }
+                        })
+                        .map(|(&oid, _)| oid)
+                        .collect();
+                    for oid in through {
+                        if sz < EPSILON {
+                            break;
+                        }
+                        let fill_sz = state.orders[&oid].sz.min(sz);
+                        sz -= fill_sz;
+                        self.fill_resting(&mut state, oid, fill_sz, &mut events);
+                    }
+                }
+            }
+            _ => {}
+        }
+        drop(state);
+        self.send(events);
+    }
+
+    fn send(&self, events: Vec<Message>) {
+        if let Some(sender) = &self.sender {
+            for event in events {
+                let _ = sender.send(event);
+            }
+        }
+    }
+
+    fn fill_resting(&self, state: &mut State, oid: u64, sz: f64, events: &mut Vec<Message>) {
+        let Some(order) = state.orders.get(&oid).cloned() else {
+            return;
+        };
+        self.record_fill(state, &order, oid, order.limit_px, sz, false, events);
+        let remaining = order.sz - sz;
+        if remaining < EPSILON {
+            state.orders.remove(&oid);
+            events.push(self.order_update(state, &order, oid, 0.0, "filled"));
+        } else if let Some(resting) = state.orders.get_mut(&oid) {
+            resting.sz = remaining;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn record_fill(
+        &self,
+        state: &mut State,
+        order: &PaperOrder,
+        oid: u64,
+        px: f64,
+        sz: f64,
+        crossed: bool,
+        events: &mut Vec<Message>,
+    ) {
+        let fee_bps = if crossed {
+            self.config.taker_fee_bps
+        } else {
+            self.config.maker_fee_bps
+        };
+        let fee = px * sz * fee_bps / 10_000.0;
+        let mid = state
+            .books
+            .get(&order.coin)
+            .and_then(|book| Some((book.bids.first()?.0 + book.asks.first()?.0) / 2.0));
+        let position = state
+            .positions
+            .entry(order.coin.clone())
+            .or_insert_with(|| Position {
+                coin: order.coin.clone(),
+                ..Position::default()
+            });
+        let start_position = position.szi;
+        let realized_before = position.realized_pnl;
+        position.apply_fill(order.is_buy, px, sz);
+        position.fees += fee;
+        position.mark_px = position.mark_px.or(mid).or(Some(px));
+        let dir = direction(start_position, position.szi);
+        let closed_pnl = position.realized_pnl - realized_before;
+
+        let tid = state.next_tid;
+        state.next_tid += 1;
+        events.push(Message::UserFills(UserFills {
+            data: UserFillsData {
+                is_snapshot: None,
+                user: self.config.address,
+                fills: vec![TradeInfo {
+                    coin: order.coin.clone(),
+                    side: Side::from_is_buy(order.is_buy),
+                    px: px.to_string(),
+                    sz: sz.to_string(),
+                    time: state.now().into(),
+                    hash: format!("{:#066x}", tid),
+                    start_position: start_position.to_string(),
+                    dir,
+                    closed_pnl: closed_pnl.to_string(),
+                    oid,
+                    cloid: order.cloid.clone(),
+                    crossed,
+                    fee: fee.to_string(),
+                    fee_token: "USDC".to_string(),
+                    tid,
+                    twap_id: None,
+                    builder_fee: None,
+                    liquidation: None,
+                }],
+            },
+        }));
+    }
+
+    fn order_update(
+        &self,
+        state: &State,
+        order: &PaperOrder,
+        oid: u64,
+        sz: f64,
+        status: &str,
+    ) -> Message {
+        Message::OrderUpdates(OrderUpdates {
+            data: vec![OrderUpdate {
+                order: BasicOrder {
+                    coin: order.coin.clone(),
+                    side: Side::from_is_buy(order.is_buy),
+                    limit_px: order.limit_px.to_string(),
+                    sz: sz.to_string(),
+                    oid,
+                    timestamp: order.timestamp.into(),
+                    orig_sz: order.orig_sz.to_string(),
+                    cloid: order.cloid.clone(),
+                },
+                status: status.to_string(),
+                status_timestamp: state.now().into(),
+            }],
+        })
+    }
+
+    fn place(
+        &self,
+        state: &mut State,
+        request: ClientOrderRequest,
+        events: &mut Vec<Message>,
+    ) -> ExchangeDataStatus {
+        let ClientOrder::Limit(limit) = &request.order_type else {
+            return error_status("Trigger orders are not supported by the paper exchange");
+        };
+        let mut sz = request.sz;
+        if request.reduce_only {
+            let szi = state
+                .positions
+                .get(&request.asset)
+                .map(|p| p.szi)
+                .unwrap_or_default();
+            if szi == 0.0 || (szi > 0.0) == request.is_buy {
+                return error_status("Reduce only order would increase position.");
+            }
+            sz = sz.min(szi.abs());
+        }
+        let Some(book) = state.books.get_mut(&request.asset) else {
+            return error_status(&format!("No book received yet for {}", request.asset));
+        };
+
+        let levels = if request.is_buy {
+            &mut book.asks
+        } else {
+            &mut book.bids
+        };
+        let crosses = |px: f64| {
+            if request.is_buy {
+                px <= request.limit_px
+            } else {
+                px >= request.limit_px
+            }
+        };
+        if limit.tif == Tif::Alo && levels.first().is_some_and(|l| crosses(l.0)) {
+            return error_status("Post only order would have immediately matched, bbo was 0.");
+        }
+
+        let mut taken = Vec::new();
+        let mut remaining = sz;
+        for level in levels.iter_mut() {
+            if remaining < EPSILON || !crosses(level.0) {
+                break;
+            }
+            let fill_sz = level.1.min(remaining);
+            level.1 -= fill_sz;
+            remaining -= fill_sz;
+            taken.push((level.0, fill_sz));
+        }
+        levels.retain(|l| l.1 > EPSILON);
+
+        let oid = state.next_oid;
+        state.next_oid += 1;
+        let order = PaperOrder {
+            coin: request.asset.clone(),
+            is_buy: request.is_buy,
+            limit_px: request.limit_px,
+            sz: remaining,
+            orig_sz: sz,
+            cloid: request.cloid.map(uuid_to_hex_string),
+            timestamp: state.now(),
+        };
+        for &(px, fill_sz) in &taken {
+            self.record_fill(state, &order, oid, px, fill_sz, true, events);
+        }
+        let filled: f64 = taken.iter().map(|t| t.1).sum();
+
+        if remaining < EPSILON {
+            let avg_px = taken.iter().map(|t| t.0 * t.1).sum::<f64>() / filled;
+            events.push(self.order_update(state, &order, oid, 0.0, "filled"));
+            return ExchangeDataStatus::Filled(FilledOrder {
+                total_sz: filled.to_string(),
+                avg_px: avg_px.to_string(),
+                oid,
+            });
+        }
+        if limit.tif == Tif::Ioc {
+            events.push(self.order_update(state, &order, oid, remaining, "canceled"));
+            if filled == 0.0 {
+                return error_status(
+                    "Order could not immediately match against any resting orders.",
+                );
+            }
+            let avg_px = taken.iter().map(|t| t.0 * t.1).sum::<f64>() / filled;
+            return ExchangeDataStatus::Filled(FilledOrder {
+                total_sz: filled.to_string(),
+                avg_px: avg_px.to_string(),
+                oid,
+            });
+        }
+
+        events.push(self.order_update(state, &order, oid, remaining, "open"));
+        state.orders.insert(oid, order);
+        ExchangeDataStatus::Resting(RestingOrder { oid })
+    }
+
+    fn cancel_oid(
+        &self,
+        state: &mut State,
+        oid: Option<u64>,
+        events: &mut Vec<Message>,
+    ) -> ExchangeDataStatus {
+        match oid.and_then(|oid| state.orders.remove(&oid).map(|order| (oid, order))) {
+            Some((oid, order)) => {
+                events.push(self.order_update(state, &order, oid, order.sz, "canceled"));
+                ExchangeDataStatus::Success
+            }
+            None => error_status("Order was never placed, already canceled, or filled."),
+        }
+    }
+
+    async fn act<F>(&self, response_type: &str, apply: F) -> Result<ExchangeResponseStatus>
+    where
+        F: FnOnce(&Self, &mut State, &mut Vec<Message>) -> Vec<ExchangeDataStatus>,
+    {
+        rt::sleep(self.config.latency).await;
+        let mut events = Vec::new();
+        let statuses = {
+            let mut state = self.state();
+            apply(self, &mut state, &mut events)
+        };
+        self.send(events);
+        Ok(ExchangeResponseStatus::Ok(ExchangeResponse {
+            response_type: response_type.to_string(),
+            data: Some(ExchangeDataStatuses { statuses }),
+        }))
+    }
+}
+
+impl Exchange for PaperExchange {
+    fn address(&self) -> Address {
+        self.config.address
+    }
+
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.act("order", move |exchange, state, events| {
+            orders
+                .into_iter()
+                .map(|order| exchange.place(state, order, events))
+                .collect()
+        })
+    }
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.act("cancel", move |exchange, state, events| {
+            cancels
+                .into_iter()
+                .map(|cancel| {
+                    let oid = state
+                        .orders
+                        .get(&cancel.oid)
+                        .filter(|o| o.coin == cancel.asset)
+                        .map(|_| cancel.oid);
+                    exchange.cancel_oid(state, oid, events)
+                })
+                .collect()
+        })
+    }
+
+    fn bulk_cancel_by_cloid(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.act("cancel", move |exchange, state, events| {
+            cancels
+                .into_iter()
+                .map(|cancel| {
+                    let cloid = uuid_to_hex_string(cancel.cloid);
+                    let oid = state
+                        .orders
+                        .iter()
+                        .find(|(_, o)| {
+                            o.coin == cancel.asset && o.cloid.as_deref() == Some(cloid.as_str())
+                        })
+                        .map(|(&oid, _)| oid);
+                    exchange.cancel_oid(state, oid, events)
+                })
+                .collect()
+        })
+    }
+}
+
+fn direction(start: f64, end: f64) -> FillDirection {
+    match (start, end) {
+        (s, e) if s >= 0.0 && e > s => FillDirection::OpenLong,
+        (s, e) if s <= 0.0 && e < s => FillDirection::OpenShort,
+        (s, e) if s > 0.0 && e < 0.0 => FillDirection::LongToShort,
+        (s, e) if s < 0.0 && e > 0.0 => FillDirection::ShortToLong,
+        (s, _) if s > 0.0 => FillDirection::CloseLong,
+        _ => FillDirection::CloseShort,
+    }
+}
+
+fn error_status(message: &str) -> ExchangeDataStatus {
+    ExchangeDataStatus::Error(ExchangeError::from(message.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::ClientLimit;
+
+    fn paper() -> (PaperExchange, tokio::sync::mpsc::UnboundedReceiver<Message>) {
+        let (sender, receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[
+                [{"px":"1999","sz":"1","n":1},{"px":"1998","sz":"5","n":2}],
+                [{"px":"2001","sz":"1","n":1},{"px":"2002","sz":"5","n":3}]
+            ]}}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&book);
+        (exchange, receiver)
+    }
+
+    fn order(is_buy: bool, limit_px: f64, sz: f64, tif: Tif) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif }),
+        }
+    }
+
+    fn statuses(response: ExchangeResponseStatus) -> Vec<ExchangeDataStatus> {
+        match response {
+            ExchangeResponseStatus::Ok(response) => response.data.unwrap().statuses,
+            ExchangeResponseStatus::Err(err) => panic!("{err}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_taker_fill_walks_book() {
+        let (exchange, mut receiver) = paper();
+        let response = Exchange::order(&exchange, order(true, 2002.0, 2.0, Tif::Ioc))
+            .await
+            .unwrap();
+        let ExchangeDataStatus::Filled(filled) = &statuses(response)[0] else {
+            panic!("expected fill");
+        };
+        assert_eq!(filled.avg_px, "2001.5");
+
+        let position = exchange.position("ETH").unwrap();
+        assert_eq!(position.szi, 2.0);
+        assert!((position.fees - 4003.0 * 4.5 / 10_000.0).abs() < 1e-9);
+        assert!(matches!(receiver.try_recv(), Ok(Message::UserFills(_))));
+
+        // The taken liquidity is gone until the next book
+        let response = Exchange::order(&exchange, order(true, 2001.0, 1.0, Tif::Ioc))
+            .await
+            .unwrap();
+        assert!(matches!(
+            statuses(response)[0],
+            ExchangeDataStatus::Error(ExchangeError::IocCancelled(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_resting_order_fills_on_trades() {
+        let (exchange, _receiver) = paper();
+        let response = exchange
+            .bulk_order(vec![
+                order(false, 1999.0, 1.0, Tif::Alo),
+                order(true, 1999.5, 1.5, Tif::Gtc),
+            ])
+            .await
+            .unwrap();
+        let statuses = statuses(response);
+        assert!(matches!(
+            statuses[0],
+            ExchangeDataStatus::Error(ExchangeError::PostOnlyWouldMatch(_))
+        ));
+        let ExchangeDataStatus::Resting(resting) = &statuses[1] else {
+            panic!("expected resting order");
+        };
+
+        let trades: Message = serde_json::from_str(
+            r#"{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"1999.5","sz":"1","time":2,"hash":"0x0","tid":9,"users":["0x0","0x0"]}]}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&trades);
+        assert_eq!(exchange.open_orders()[0].sz, "0.5");
+        assert_eq!(exchange.position("ETH").unwrap().szi, 1.0);
+
+        exchange
+            .cancel(ClientCancelRequest {
+                asset: "ETH".to_string(),
+                oid: resting.oid,
+            })
+            .await
+            .unwrap();
+        assert!(exchange.open_orders().is_empty());
+    }
+}
@@ -0,0 +1,569 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    future::Future,
+    pin::pin,
+    sync::Mutex,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use tokio::sync::Notify;
+
+use crate::{
+    prelude::*,
+    req::TokenBucket,
+    rt::{self, Instant, MaybeSend},
+    ClientCancelRequest, ClientCancelRequestCloid, ClientModifyRequest, ClientOrderRequest, Error,
+    Exchange, ExchangeClient, ExchangeResponseStatus,
+};
+
+/// Order in which queued actions are sent, most urgent first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ActionPriority {
+    Cancel,
+    /// Orders that only reduce positions
+    ReduceOnly,
+    Modify,
+    Order,
+}
+
+#[derive(Debug)]
+enum JobKind {
+    Order(Vec<ClientOrderRequest>),
+    Cancel(Vec<ClientCancelRequest>),
+    CancelByCloid(Vec<ClientCancelRequestCloid>),
+    Modify(Vec<ClientModifyRequest>),
+}
+
+impl JobKind {
+    fn priority(&self) -> ActionPriority {
+        match self {
+            JobKind::Order(orders)
+                if !orders.is_empty() && orders.iter().all(|order| order.reduce_only) =>
+            {
+                ActionPriority::ReduceOnly
+            }
+            JobKind::Order(_) => ActionPriority::Order,
+            JobKind::Cancel(_) | JobKind::CancelByCloid(_) => ActionPriority::Cancel,
+            JobKind::Modify(_) => ActionPriority::Modify,
+        }
+    }
+
+    fn cancel_oids(&self) -> HashSet<u64> {
+        match self {
+            JobKind::Cancel(cancels) => cancels.iter().map(|cancel| cancel.oid).collect(),
+            _ => HashSet::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    seq: u64,
+    kind: JobKind,
+    /// Callers waiting for this job's response after their own action was coalesced into it
+    followers: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Ticket {
+    Queued(u64),
+    Follow(u64),
+}
+
+#[derive(Debug)]
+struct State {
+    next_seq: u64,
+    queue: Vec<Job>,
+    bucket: TokenBucket,
+    results: HashMap<u64, (Result<ExchangeResponseStatus>, usize)>,
+    /// Jobs emptied by coalescing, mapped to the job whose response their waiting callers
+    /// take instead and how many callers have yet to switch
+    redirects: HashMap<u64, (u64, usize)>,
+}
+
+impl State {
+    fn head(&self) -> Option<u64> {
+        self.queue
+            .iter()
+            .min_by_key(|job| (job.kind.priority(), job.seq))
+            .map(|job| job.seq)
+    }
+
+    fn follow(&mut self, seq: u64) -> Ticket {
+        if let Some(job) = self.queue.iter_mut().find(|job| job.seq == seq) {
+            job.followers += 1;
+        }
+        Ticket::Follow(seq)
+    }
+
+    /// Drops the modifies of `oids` from queued jobs, redirecting jobs left empty to `seq`.
+    fn supersede_modifies(&mut self, oids: &HashSet<u64>, seq: u64) {
+        let mut emptied = Vec::new();
+        for job in self.queue.iter_mut().filter(|job| job.seq != seq) {
+            if let JobKind::Modify(modifies) = &mut job.kind {
+                modifies.retain(|modify| !oids.contains(&modify.oid));
+                if modifies.is_empty() {
+                    emptied.push((job.seq, job.followers));
+                }
+            }
+        }
+        for (emptied, followers) in emptied {
+            self.queue.retain(|job| job.seq != emptied);
+            // The emptied job's owner and followers all wait for `seq` instead
+            self.redirects.insert(emptied, (seq, followers + 1));
+            if let Some(job) = self.queue.iter_mut().find(|job| job.seq == seq) {
+                job.followers += followers + 1;
+            }
+        }
+    }
+
+    fn enqueue(&mut self, mut kind: JobKind) -> Ticket {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        match &mut kind {
+            JobKind::Cancel(cancels) => {
+                let mut covering = None;
+                cancels.retain(|cancel| {
+                    let queued = self
+                        .queue
+                        .iter()
+                        .find(|job| job.kind.cancel_oids().contains(&cancel.oid));
+                    covering = covering.or(queued.map(|job| job.seq));
+                    queued.is_none()
+                });
+                if let (true, Some(covering)) = (cancels.is_empty(), covering) {
+                    return self.follow(covering);
+                }
+            }
+            JobKind::CancelByCloid(cancels) => {
+                let mut covering = None;
+                cancels.retain(|cancel| {
+                    let queued = self.queue.iter().find(|job| match &job.kind {
+                        JobKind::CancelByCloid(queued) => {
+                            queued.iter().any(|queued| queued.cloid == cancel.cloid)
+                        }
+                        _ => false,
+                    });
+                    covering = covering.or(queued.map(|job| job.seq));
+                    queued.is_none()
+                });
+                if let (true, Some(covering)) = (cancels.is_empty(), covering) {
+                    return self.follow(covering);
+                }
+            }
+            JobKind::Modify(modifies) => {
+                // Modifying an order about to be cancelled is pointless
+                let mut covering = None;
+                modifies.retain(|modify| {
+                    let queued = self
+                        .queue
+                        .iter()
+                        .find(|job| job.kind.cancel_oids().contains(&modify.oid));
+                    covering = covering.or(queued.map(|job| job.seq));
+                    queued.is_none()
+                });
+                if let (true, Some(covering)) = (modifies.is_empty(), covering) {
+                    return self.follow(covering);
+                }
+            }
+            JobKind::Order(_) => {}
+        }
+        let oids: HashSet<u64> = match &kind {
+            JobKind::Cancel(cancels) => cancels.iter().map(|cancel| cancel.oid).collect(),
+            JobKind::Modify(modifies) => modifies.iter().map(|modify| modify.oid).collect(),
+            _ => HashSet::new(),
+        };
+        self.queue.push(Job {
+            seq,
+            kind,
+            followers: 0,
+        });
+        if !oids.is_empty() {
+            self.supersede_modifies(&oids, seq);
+        }
+        Ticket::Queued(seq)
+    }
+
+    fn take_redirect(&mut self, seq: u64) -> Option<u64> {
+        let (target, readers) = self.redirects.get_mut(&seq)?;
+        let target = *target;
+        *readers -= 1;
+        if *readers == 0 {
+            self.redirects.remove(&seq);
+        }
+        Some(target)
+    }
+
+    fn take_result(&mut self, seq: u64) -> Option<Result<ExchangeResponseStatus>> {
+        let (result, readers) = self.results.get_mut(&seq)?;
+        let result = result.clone();
+        *readers -= 1;
+        if *readers == 0 {
+            self.results.remove(&seq);
+        }
+        Some(result)
+    }
+}
+
+/// Removes its job from the queue if the caller is dropped before the job completes, so
+/// it does not block the queue, and fails the job's followers.
+struct Dispatched<'a> {
+    state: &'a Mutex<State>,
+    notify: &'a Notify,
+    seq: u64,
+    followers: Option<usize>,
+}
+
+impl Dispatched<'_> {
+    fn finish(mut self, result: Result<ExchangeResponseStatus>) -> Result<ExchangeResponseStatus> {
+        let followers = self.followers.take().unwrap_or_default();
+        if followers > 0 {
+            let mut state = self.state.lock().expect("scheduler lock poisoned");
+            state.results.insert(self.seq, (result.clone(), followers));
+            self.notify.notify_waiters();
+        }
+        result
+    }
+}
+
+impl Drop for Dispatched<'_> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("scheduler lock poisoned");
+        let queued = state.queue.iter().position(|job| job.seq == self.seq);
+        let followers = match (queued, self.followers) {
+            (Some(index), _) => state.queue.remove(index).followers,
+            (None, Some(followers)) if followers > 0 => followers,
+            (None, _) => return,
+        };
+        if followers > 0 {
+            let err =
+                Error::GenericRequest("scheduled action was dropped before completing".into());
+            state.results.insert(self.seq, (Err(err), followers));
+        }
+        self.notify.notify_waiters();
+    }
+}
+
+/// Queues exchange actions from concurrent tasks and sends them at most `actions_per_second`,
+/// cancels first, so bursts are paced instead of tripping the exchange's rate limits.
+///
+/// Redundant actions are coalesced while queued: a cancel of an order that already has one
+/// queued waits for that cancel's response, and a modify or cancel of an order replaces its
+/// queued modifies. Callers whose actions were all coalesced get the response of the action
+/// that replaced them, so statuses only cover the orders that action carried.
+///
+/// Wraps any `Exchange`, and implements it, so strategies can use it in place of their
+/// client. Modifies are queued with `bulk_modify` on `ActionScheduler<ExchangeClient>`.
+pub struct ActionScheduler<E> {
+    exchange: E,
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+impl<E> fmt::Debug for ActionScheduler<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActionScheduler")
+            .field("queued", &self.queued())
+            .finish_non_exhaustive()
+    }
+}
+
+enum Dispatch<'a> {
+    Send(Dispatched<'a>, JobKind),
+    Done(Result<ExchangeResponseStatus>),
+}
+
+impl<E> ActionScheduler<E> {
+    /// Allows bursts of up to one second's worth of actions.
+    pub fn new(exchange: E, actions_per_second: u32) -> ActionScheduler<E> {
+        ActionScheduler::with_burst(exchange, actions_per_second, actions_per_second)
+    }
+
+    /// Sends up to `burst` actions at once before pacing to `actions_per_second`.
+    pub fn with_burst(exchange: E, actions_per_second: u32, burst: u32) -> ActionScheduler<E> {
+        let per = Duration::from_secs_f64(burst.max(1) as f64 / actions_per_second.max(1) as f64);
+        ActionScheduler {
+            exchange,
+            state: Mutex::new(State {
+                next_seq: 0,
+                queue: Vec::new(),
+                bucket: TokenBucket::new(burst.max(1), per, Instant::now()),
+                results: HashMap::new(),
+                redirects: HashMap::new(),
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    pub fn exchange(&self) -> &E {
+        &self.exchange
+    }
+
+    /// Actions waiting to be sent.
+    pub fn queued(&self) -> usize {
+        self.state
+            .lock()
+            .expect("scheduler lock poisoned")
+            .queue
+            .len()
+    }
+
+    /// Waits until `kind` is next and the rate allows it, or until the action it was
+    /// coalesced into has completed.
+    async fn dispatch(&self, kind: JobKind) -> Dispatch<'_> {
+        let mut ticket = self
+            .state
+            .lock()
+            .expect("scheduler lock poisoned")
+            .enqueue(kind);
+        let mut guard = match ticket {
+            Ticket::Queued(seq) => Some(Dispatched {
+                state: &self.state,
+                notify: &self.notify,
+                seq,
+                followers: None,
+            }),
+            Ticket::Follow(_) => None,
+        };
+        loop {
+            let mut notified = pin!(self.notify.notified());
+            notified.as_mut().enable();
+            let wait = {
+                let mut state = self.state.lock().expect("scheduler lock poisoned");
+                let (Ticket::Queued(seq) | Ticket::Follow(seq)) = ticket;
+                if let Some(target) = state.take_redirect(seq) {
+                    // Coalescing already counted this caller as a follower of `target`
+                    if let Some(mut guard) = guard.take() {
+                        guard.followers = Some(0);
+                    }
+                    ticket = Ticket::Follow(target);
+                    continue;
+                }
+                match ticket {
+                    Ticket::Follow(target) => match state.take_result(target) {
+                        Some(result) => return Dispatch::Done(result),
+                        None => None,
+                    },
+                    Ticket::Queued(seq) => {
+                        if state.head() != Some(seq) {
+                            None
+                        } else {
+                            match state.bucket.try_take(1, Instant::now()) {
+                                Ok(()) => {
+                                    let index = state
+                                        .queue
+                                        .iter()
+                                        .position(|job| job.seq == seq)
+                                        .expect("head is queued");
+                                    let job = state.queue.remove(index);
+                                    let mut guard = guard.take().expect("queued jobs are guarded");
+                                    guard.followers = Some(job.followers);
+                                    // The next job may now be at the head
+                                    self.notify.notify_waiters();
+                                    return Dispatch::Send(guard, job.kind);
+                                }
+                                Err(wait) => Some(wait),
+                            }
+                        }
+                    }
+                }
+            };
+            match wait {
+                Some(wait) => rt::sleep(wait).await,
+                None => notified.await,
+            }
+        }
+    }
+}
+
+impl<E: Exchange + Sync> ActionScheduler<E> {
+    async fn run(&self, kind: JobKind) -> Result<ExchangeResponseStatus> {
+        let (guard, kind) = match self.dispatch(kind).await {
+            Dispatch::Done(result) => return result,
+            Dispatch::Send(guard, kind) => (guard, kind),
+        };
+        let result = match kind {
+            JobKind::Order(orders) => self.exchange.bulk_order(orders).await,
+            JobKind::Cancel(cancels) => self.exchange.bulk_cancel(cancels).await,
+            JobKind::CancelByCloid(cancels) => self.exchange.bulk_cancel_by_cloid(cancels).await,
+            JobKind::Modify(_) => unreachable!("modifies are only queued through ExchangeClient"),
+        };
+        guard.finish(result)
+    }
+}
+
+impl ActionScheduler<ExchangeClient> {
+    pub async fn modify(&self, modify: ClientModifyRequest) -> Result<ExchangeResponseStatus> {
+        self.bulk_modify(vec![modify]).await
+    }
+
+    pub async fn bulk_modify(
+        &self,
+        modifies: Vec<ClientModifyRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        match self.dispatch(JobKind::Modify(modifies)).await {
+            Dispatch::Done(result) => result,
+            Dispatch::Send(guard, JobKind::Modify(modifies)) => {
+                guard.finish(self.exchange.bulk_modify(modifies, None).await)
+            }
+            Dispatch::Send(..) => unreachable!("jobs are sent as queued"),
+        }
+    }
+}
+
+impl<E: Exchange + Sync> Exchange for ActionScheduler<E> {
+    fn address(&self) -> Address {
+        self.exchange.address()
+    }
+
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.run(JobKind::Order(orders))
+    }
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.run(JobKind::Cancel(cancels))
+    }
+
+    fn bulk_cancel_by_cloid(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        self.run(JobKind::CancelByCloid(cancels))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{ClientLimit, ClientOrder, ExchangeError, Tif};
+
+    fn order(reduce_only: bool) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only,
+            limit_px: 2000.0,
+            sz: 1.0,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        }
+    }
+
+    fn cancel(oid: u64) -> JobKind {
+        JobKind::Cancel(vec![ClientCancelRequest {
+            asset: "ETH".to_string(),
+            oid,
+        }])
+    }
+
+    fn modify(oid: u64) -> JobKind {
+        JobKind::Modify(vec![ClientModifyRequest {
+            oid,
+            order: order(false),
+        }])
+    }
+
+    #[test]
+    fn test_priorities_and_coalescing() {
+        let mut state = ActionScheduler::new((), 10).state.into_inner().unwrap();
+        assert_eq!(
+            state.enqueue(JobKind::Order(vec![order(false)])),
+            Ticket::Queued(0)
+        );
+        assert_eq!(
+            state.enqueue(JobKind::Order(vec![order(true)])),
+            Ticket::Queued(1)
+        );
+        assert_eq!(state.head(), Some(1));
+        assert_eq!(state.enqueue(modify(7)), Ticket::Queued(2));
+        assert_eq!(state.enqueue(cancel(5)), Ticket::Queued(3));
+        assert_eq!(state.head(), Some(3));
+
+        // A second cancel of the same order waits for the first
+        assert_eq!(state.enqueue(cancel(5)), Ticket::Follow(3));
+        // A newer modify replaces the queued one
+        assert_eq!(state.enqueue(modify(7)), Ticket::Queued(5));
+        assert_eq!(state.take_redirect(2), Some(5));
+        assert_eq!(state.take_redirect(2), None);
+        // Cancels replace queued modifies, and later modifies of the order are dropped
+        assert_eq!(state.enqueue(cancel(7)), Ticket::Queued(6));
+        assert_eq!(state.take_redirect(5), Some(6));
+        assert_eq!(state.enqueue(modify(7)), Ticket::Follow(6));
+        let followers = |state: &State, seq| {
+            state
+                .queue
+                .iter()
+                .find(|job| job.seq == seq)
+                .map(|job| job.followers)
+        };
+        assert_eq!(followers(&state, 3), Some(1));
+        assert_eq!(followers(&state, 6), Some(3));
+        assert_eq!(state.queue.len(), 4);
+    }
+
+    #[derive(Default)]
+    struct CountingExchange {
+        cancels: AtomicUsize,
+    }
+
+    impl Exchange for CountingExchange {
+        fn address(&self) -> Address {
+            Address::ZERO
+        }
+
+        async fn bulk_order(
+            &self,
+            _orders: Vec<ClientOrderRequest>,
+        ) -> Result<ExchangeResponseStatus> {
+            Ok(ExchangeResponseStatus::Err("order".into()))
+        }
+
+        async fn bulk_cancel(
+            &self,
+            _cancels: Vec<ClientCancelRequest>,
+        ) -> Result<ExchangeResponseStatus> {
+            self.cancels.fetch_add(1, Ordering::Relaxed);
+            Ok(ExchangeResponseStatus::Err("Order was never placed".into()))
+        }
+
+        async fn bulk_cancel_by_cloid(
+            &self,
+            _cancels: Vec<ClientCancelRequestCloid>,
+        ) -> Result<ExchangeResponseStatus> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paced_and_shared_responses() {
+        let scheduler = ActionScheduler::with_burst(CountingExchange::default(), 20, 1);
+        let start = Instant::now();
+        scheduler.order(order(false)).await.unwrap();
+        let cancel = || {
+            scheduler.cancel(ClientCancelRequest {
+                asset: "ETH".to_string(),
+                oid: 5,
+            })
+        };
+        let (first, second) = tokio::join!(cancel(), cancel());
+        assert!(
+            matches!(first, Ok(ExchangeResponseStatus::Err(ref err)) if *err == ExchangeError::from("Order was never placed"))
+        );
+        assert!(
+            matches!(second, Ok(ExchangeResponseStatus::Err(ref err)) if *err == ExchangeError::from("Order was never placed"))
+        );
+        assert_eq!(scheduler.exchange().cancels.load(Ordering::Relaxed), 1);
+        assert!(start.elapsed() >= Duration::from_millis(45));
+        assert_eq!(scheduler.queued(), 0);
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+
+use alloy::primitives::Address;
+
+use crate::{
+    meta::TokenInfo, prelude::*, ClientOrderRequest, InfoClient, SpotMeta, UserTokenBalance,
+    EPSILON,
+};
+
+/// Why a spot send or spot order would be rejected, found before it is signed.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum SpotPrecondition {
+    #[error("token {0} is not of the form NAME:0xTOKENID")]
+    MalformedToken(String),
+    #[error("token {0} is not in spot meta")]
+    UnknownToken(String),
+    #[error("token {token} has id {token_id} in spot meta")]
+    TokenIdMismatch { token: String, token_id: String },
+    #[error("spot pair {0} is not in spot meta")]
+    UnknownPair(String),
+    #[error("amount {0} is not a positive number")]
+    InvalidAmount(String),
+    #[error("amount {amount} has more than the {wei_decimals} decimals of {token}")]
+    TooPrecise {
+        token: String,
+        amount: String,
+        wei_decimals: u8,
+    },
+    #[error("{amount} {token} needed, {available} available after holds")]
+    InsufficientBalance {
+        token: String,
+        amount: f64,
+        available: f64,
+    },
+    #[error("destination {0} is not a valid address")]
+    InvalidDestination(String),
+}
+
+/// Checks spot sends and spot orders against spot meta and the account's spot balances, so
+/// that a missing token, an overly precise amount, an insufficient balance or a malformed
+/// destination fails with a `SpotPrecondition` rather than an exchange rejection.
+///
+/// Balances are those of the last `fetch` and are not updated by what is checked.
+#[derive(Clone, Debug)]
+pub struct SpotPreflight {
+    spot_meta: SpotMeta,
+    /// Total less holds, by token name
+    available: HashMap<String, f64>,
+}
+
+impl SpotPreflight {
+    pub fn new(spot_meta: SpotMeta, balances: &[UserTokenBalance]) -> SpotPreflight {
+        let available = balances
+            .iter()
+            .map(|balance| {
+                let total: f64 = balance.total.parse().unwrap_or_default();
+                let hold: f64 = balance.hold.parse().unwrap_or_default();
+                (balance.coin.clone(), total - hold)
+            })
+            .collect();
+        SpotPreflight {
+            spot_meta,
+            available,
+        }
+    }
+
+    /// Fetches spot meta and the spot balances of `user`.
+    pub async fn fetch(info: &InfoClient, user: Address) -> Result<SpotPreflight> {
+        let (spot_meta, balances) =
+            tokio::try_join!(info.spot_meta(), info.user_token_balances(user))?;
+        Ok(SpotPreflight::new(spot_meta, &balances.balances))
+    }
+
+    /// Balance of `token`, by name, less holds.
+    pub fn available(&self, token: &str) -> f64 {
+        self.available.get(token).copied().unwrap_or_default()
+    }
+
+    /// Checks a `spotSend` of `amount` of `token`, given as `NAME:0xTOKENID`, to
+    /// `destination`, returning the token.
+    pub fn check_send(
+        &self,
+        token: &str,
+        amount: &str,
+        destination: &str,
+    ) -> std::result::Result<&TokenInfo, SpotPrecondition> {
+        let (name, token_id) = token
+            .split_once(':')
+            .ok_or_else(|| SpotPrecondition::MalformedToken(token.to_string()))?;
+        let info = self
+            .spot_meta
+            .tokens
+            .iter()
+            .find(|info| info.name == name)
+            .ok_or_else(|| SpotPrecondition::UnknownToken(name.to_string()))?;
+        let expected = info.token_id.to_string();
+        if !token_id.eq_ignore_ascii_case(&expected) {
+            return Err(SpotPrecondition::TokenIdMismatch {
+                token: name.to_string(),
+                token_id: expected,
+            });
+        }
+        let sz = parse_amount(amount)?;
+        let decimals = amount
+            .split_once('.')
+            .map_or(0, |(_, fraction)| fraction.trim_end_matches('0').len());
+        if decimals > info.wei_decimals as usize {
+            return Err(SpotPrecondition::TooPrecise {
+                token: name.to_string(),
+                amount: amount.to_string(),
+                wei_decimals: info.wei_decimals,
+            });
+        }
+        self.check_balance(name, sz)?;
+        check_destination(destination)?;
+        Ok(info)
+    }
+
+    /// Checks that the balance sold by a spot order covers it: the base token for a sell and
+    /// the quote token at the limit price for a buy. `order.asset` is the pair name, such as
+    /// `PURR/USDC` or `@107`.
+    pub fn check_order(
+        &self,
+        order: &ClientOrderRequest,
+    ) -> std::result::Result<(), SpotPrecondition> {
+        let pair = self
+            .spot_meta
+            .universe
+            .iter()
+            .find(|pair| pair.name == order.asset || format!("@{}", pair.index) == order.asset)
+            .ok_or_else(|| SpotPrecondition::UnknownPair(order.asset.clone()))?;
+        let token = |index: usize| {
+            self.spot_meta
+                .tokens
+                .iter()
+                .find(|info| info.index == index)
+                .map(|info| info.name.as_str())
+                .ok_or_else(|| SpotPrecondition::UnknownPair(order.asset.clone()))
+        };
+        if order.sz <= 0.0 || !order.sz.is_finite() {
+            return Err(SpotPrecondition::InvalidAmount(order.sz.to_string()));
+        }
+        if order.is_buy {
+            self.check_balance(token(pair.tokens[1])?, order.sz * order.limit_px)
+        } else {
+            self.check_balance(token(pair.tokens[0])?, order.sz)
+        }
+    }
+
+    fn check_balance(&self, token: &str, amount: f64) -> std::result::Result<(), SpotPrecondition> {
+        let available = self.available(token);
+        if amount > available + EPSILON {
+            return Err(SpotPrecondition::InsufficientBalance {
+                token: token.to_string(),
+                amount,
+                available,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn parse_amount(amount: &str) -> std::result::Result<f64, SpotPrecondition> {
+    amount
+        .parse::<f64>()
+        .ok()
+        .filter(|sz| sz.is_finite() && *sz > 0.0)
+        .ok_or_else(|| SpotPrecondition::InvalidAmount(amount.to_string()))
+}
+
+/// A destination must be a 0x-prefixed address, with a valid checksum if it is mixed case.
+fn check_destination(destination: &str) -> std::result::Result<(), SpotPrecondition> {
+    let invalid = || SpotPrecondition::InvalidDestination(destination.to_string());
+    let hex = destination.strip_prefix("0x").ok_or_else(invalid)?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mixed_case =
+        hex.chars().any(|c| c.is_ascii_uppercase()) && hex.chars().any(|c| c.is_ascii_lowercase());
+    if mixed_case && Address::parse_checksummed(destination, None).is_err() {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientLimit, ClientOrder, Tif};
+
+    fn preflight() -> SpotPreflight {
+        let spot_meta: SpotMeta = serde_json::from_value(serde_json::json!({
+            "universe": [{"tokens": [1, 0], "name": "PURR/USDC", "index": 0, "isCanonical": true}],
+            "tokens": [
+                {"name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0,
+                 "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true},
+                {"name": "PURR", "szDecimals": 0, "weiDecimals": 5, "index": 1,
+                 "tokenId": "0xc4bf3f870c0e9465323c0b6ed28096c2", "isCanonical": true}
+            ]
+        }))
+        .unwrap();
+        let balances: Vec<UserTokenBalance> = serde_json::from_value(serde_json::json!([
+            {"coin": "USDC", "hold": "40", "total": "100", "entryNtl": "0"},
+            {"coin": "PURR", "hold": "0", "total": "10", "entryNtl": "5"}
+        ]))
+        .unwrap();
+        SpotPreflight::new(spot_meta, &balances)
+    }
+
+    #[test]
+    fn test_spot_send_and_order_preconditions() {
+        let preflight = preflight();
+        let purr = "PURR:0xc4bf3f870c0e9465323c0b6ed28096c2";
+        let destination = "0x0D1d9635D0640821d15e323ac8AdADfA9c111414";
+        assert_eq!(
+            preflight.check_send(purr, "2.5", destination).unwrap().name,
+            "PURR"
+        );
+        assert!(matches!(
+            preflight.check_send("PURR", "1", destination),
+            Err(SpotPrecondition::MalformedToken(_))
+        ));
+        assert!(matches!(
+            preflight.check_send("HYPE:0x01", "1", destination),
+            Err(SpotPrecondition::UnknownToken(_))
+        ));
+        assert!(matches!(
+            preflight.check_send("PURR:0x01", "1", destination),
+            Err(SpotPrecondition::TokenIdMismatch { .. })
+        ));
+        assert!(matches!(
+            preflight.check_send(purr, "0.000001", destination),
+            Err(SpotPrecondition::TooPrecise {
+                wei_decimals: 5,
+                ..
+            })
+        ));
+        assert!(matches!(
+            preflight.check_send(purr, "11", destination),
+            Err(SpotPrecondition::InsufficientBalance { .. })
+        ));
+        // A checksum error in a mixed-case address
+        assert!(matches!(
+            preflight.check_send(purr, "1", "0x0d1D9635D0640821d15e323ac8AdADfA9c111414"),
+            Err(SpotPrecondition::InvalidDestination(_))
+        ));
+
+        let order = |is_buy: bool, sz: f64, limit_px: f64| ClientOrderRequest {
+            asset: "PURR/USDC".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+        assert!(preflight.check_order(&order(false, 10.0, 1.0)).is_ok());
+        // 60 USDC is free of holds
+        assert!(preflight.check_order(&order(true, 100.0, 0.5)).is_ok());
+        assert!(matches!(
+            preflight.check_order(&order(true, 100.0, 0.7)),
+            Err(SpotPrecondition::InsufficientBalance {
+                available: 60.0,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+use std::{fmt, time::Duration};
+
+use alloy::signers::local::PrivateKeySigner;
+use tracing::info;
+
+use crate::{
+    prelude::*,
+    req::{parse_response, reqwest_error},
+    rt::{self, Instant},
+    Error, ExchangeClient, ExchangeResponseStatus, SignerId,
+};
+
+/// Where testnet USDC is claimed in the browser. Claims need the address to have deposited on
+/// mainnet before.
+pub const TESTNET_FAUCET_URL: &str = "https://app.hyperliquid-testnet.xyz/drip";
+
+/// What `ExchangeClient::bootstrap_testnet` sets up.
+#[derive(Clone, Debug)]
+pub struct TestnetBootstrap {
+    /// Faucet service sent `{"user": "0x..."}` before waiting for funds. Without one the
+    /// account has to be funded through [`TESTNET_FAUCET_URL`].
+    pub faucet_url: Option<String>,
+    /// Account value in USDC the account needs before bootstrapping continues
+    pub min_account_value: f64,
+    pub funding_timeout: Duration,
+    /// Coins whose leverage is set, as cross margin
+    pub coins: Vec<String>,
+    pub leverage: u32,
+    /// Whether to approve a fresh agent wallet for the account
+    pub approve_agent: bool,
+}
+
+impl Default for TestnetBootstrap {
+    fn default() -> Self {
+        TestnetBootstrap {
+            faucet_url: None,
+            min_account_value: 10.0,
+            funding_timeout: Duration::from_secs(60),
+            coins: Vec::new(),
+            leverage: 1,
+            approve_agent: false,
+        }
+    }
+}
+
+/// A funded testnet account, as left by `ExchangeClient::bootstrap_testnet`.
+pub struct TestnetAccount {
+    pub account_value: f64,
+    pub agent: Option<PrivateKeySigner>,
+}
+
+impl fmt::Debug for TestnetAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestnetAccount")
+            .field("account_value", &self.account_value)
+            .field("agent", &self.agent.as_ref().map(SignerId::of))
+            .finish()
+    }
+}
+
+const FUNDING_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+fn check(status: ExchangeResponseStatus, what: &str) -> Result<()> {
+    match status {
+        ExchangeResponseStatus::Ok(_) => Ok(()),
+        ExchangeResponseStatus::Err(err) => {
+            Err(Error::GenericRequest(format!("Could not {what}: {err}")))
+        }
+    }
+}
+
+impl ExchangeClient {
+    fn require_testnet(&self) -> Result<()> {
+        if self.http_client.is_mainnet() {
+            return Err(Error::ChainNotAllowed);
+        }
+        Ok(())
+    }
+
+    /// Asks the faucet service at `faucet_url` to fund the account. Testnet only.
+    pub async fn request_testnet_funds(&self, faucet_url: &str) -> Result<()> {
+        self.require_testnet()?;
+        let user = self.vault_address.unwrap_or(self.wallet.address());
+        let response = self
+            .http_client
+            .client
+            .post(faucet_url)
+            .header("Content-Type", "application/json")
+            .body(serde_json::json!({ "user": user }).to_string())
+            .send()
+            .await
+            .map_err(|e| reqwest_error(&e))?;
+        parse_response(response).await?;
+        info!(%user, "Requested testnet funds");
+        Ok(())
+    }
+
+    /// Waits until the account is worth at least `min_account_value` USDC, returning its
+    /// value. Fails with `Error::NotFunded` after `timeout`. Testnet only.
+    pub async fn wait_for_testnet_funds(
+        &self,
+        min_account_value: f64,
+        timeout: Duration,
+    ) -> Result<f64> {
+        self.require_testnet()?;
+        let user = self.vault_address.unwrap_or(self.wallet.address());
+        let info = self.info_client();
+        let deadline = Instant::now() + timeout;
+        loop {
+            let state = info.user_state(user).await?;
+            let account_value = state
+                .margin_summary
+                .account_value
+                .parse::<f64>()
+                .map_err(|_| Error::FloatStringParse)?;
+            if account_value >= min_account_value {
+                return Ok(account_value);
+            }
+            if Instant::now() + FUNDING_POLL_INTERVAL > deadline {
+                return Err(Error::NotFunded(format!(
+                    "{user} is worth {account_value} USDC, below {min_account_value}; \
+                     claim testnet USDC at {TESTNET_FAUCET_URL}"
+                )));
+            }
+            rt::sleep(FUNDING_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Funds the account if a faucet is configured, waits for the funds, sets leverage on
+    /// `config.coins` and optionally approves an agent, so tests and demos start from a
+    /// working account. Testnet only.
+    pub async fn bootstrap_testnet(&self, config: TestnetBootstrap) -> Result<TestnetAccount> {
+        self.require_testnet()?;
+        if let Some(faucet_url) = &config.faucet_url {
+            self.request_testnet_funds(faucet_url).await?;
+        }
+        let account_value = self
+            .wait_for_testnet_funds(config.min_account_value, config.funding_timeout)
+            .await?;
+        for coin in &config.coins {
+            let status = self
+                .update_leverage(config.leverage, coin, true, None)
+                .await?;
+            check(status, &format!("set {coin} leverage"))?;
+        }
+        let agent = if config.approve_agent {
+            let (key, status) = self.approve_agent(None).await?;
+            check(status, "approve agent")?;
+            Some(
+                PrivateKeySigner::from_bytes(&key)
+                    .map_err(|e| Error::PrivateKeyParse(e.to_string()))?,
+            )
+        } else {
+            None
+        };
+        Ok(TestnetAccount {
+            account_value,
+            agent,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{MockConfig, MockEndpoint, MockServer};
+
+    #[tokio::test]
+    async fn test_bootstrap_testnet() {
+        let wallet = PrivateKeySigner::random();
+        let server = MockServer::start(MockConfig {
+            user: wallet.address(),
+            account_value: 1000.0,
+            ..MockConfig::default()
+        })
+        .await
+        .unwrap();
+        let exchange = ExchangeClient::new(None, wallet, Some(server.base_url()), None, None)
+            .await
+            .unwrap();
+        let account = exchange
+            .bootstrap_testnet(TestnetBootstrap {
+                coins: vec!["ETH".to_string()],
+                leverage: 3,
+                approve_agent: true,
+                ..TestnetBootstrap::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(account.account_value, 1000.0);
+        assert!(account.agent.is_some());
+        let types: Vec<_> = server
+            .requests(MockEndpoint::Exchange)
+            .iter()
+            .map(|body| body["action"]["type"].clone())
+            .collect();
+        assert_eq!(types, ["updateLeverage", "approveAgent"]);
+
+        let res = exchange
+            .wait_for_testnet_funds(5000.0, Duration::ZERO)
+            .await;
+        assert!(matches!(res, Err(Error::NotFunded(_))));
+    }
+}
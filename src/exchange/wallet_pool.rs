@@ -0,0 +1,207 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+
+use crate::{
+    prelude::*, ClientCancelRequest, ClientCancelRequestCloid, ClientOrderRequest, Error, Exchange,
+    ExchangeClient, ExchangeResponseStatus,
+};
+
+/// How a `WalletPool` picks the wallet signing the next action.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WalletSelection {
+    /// Each wallet in turn
+    #[default]
+    RoundRobin,
+    /// The wallet with the fewest actions in flight, in turn among equals
+    LeastLoaded,
+}
+
+/// Spreads order flow over several agent wallets approved for the same account, behind a
+/// single `Exchange`.
+///
+/// Every signer keeps its own window of recent nonces, so several agents can sign more actions
+/// in parallel without nonces colliding or falling out of the window. Orders, cancels and
+/// fills all belong to the account, so any wallet can cancel any order and a single
+/// `userFills` or `orderUpdates` subscription for `address` sees the fills of all of them.
+/// The account's per-address request limit is shared by every agent and is not raised.
+#[derive(Debug)]
+pub struct WalletPool {
+    user: Address,
+    clients: Vec<ExchangeClient>,
+    selection: WalletSelection,
+    next: AtomicUsize,
+    in_flight: Vec<AtomicUsize>,
+}
+
+impl WalletPool {
+    /// Pool over `clients`, each signing with a different agent of `user`, the account or
+    /// vault orders are placed for.
+    pub fn new(user: Address, clients: Vec<ExchangeClient>) -> Result<WalletPool> {
+        if clients.is_empty() {
+            return Err(Error::InvalidConfig(
+                "a wallet pool needs at least one client".to_string(),
+            ));
+        }
+        let in_flight = clients.iter().map(|_| AtomicUsize::new(0)).collect();
+        Ok(WalletPool {
+            user,
+            clients,
+            selection: WalletSelection::default(),
+            next: AtomicUsize::new(0),
+            in_flight,
+        })
+    }
+
+    /// Pool signing with `agents`, each a clone of `client` sharing its HTTP connections,
+    /// metadata and rate limiter. The agents must already be approved for `user`.
+    pub fn from_agents(
+        client: &ExchangeClient,
+        user: Address,
+        agents: Vec<PrivateKeySigner>,
+    ) -> Result<WalletPool> {
+        let clients = agents
+            .into_iter()
+            .map(|wallet| ExchangeClient {
+                wallet,
+                account_address: Some(user),
+                ..client.clone()
+            })
+            .collect();
+        WalletPool::new(user, clients)
+    }
+
+    pub fn with_selection(mut self, selection: WalletSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    pub fn clients(&self) -> &[ExchangeClient] {
+        &self.clients
+    }
+
+    /// Actions each wallet has in flight, in the order of `clients`.
+    pub fn in_flight(&self) -> Vec<usize> {
+        self.in_flight
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Picks the wallet for the next action, counting it in flight until the guard drops.
+    fn acquire(&self) -> (&ExchangeClient, InFlight<'_>) {
+        let index = self.pick();
+        self.in_flight[index].fetch_add(1, Ordering::Relaxed);
+        (&self.clients[index], InFlight(&self.in_flight[index]))
+    }
+
+    fn pick(&self) -> usize {
+        let start = self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        match self.selection {
+            WalletSelection::RoundRobin => start,
+            WalletSelection::LeastLoaded => (0..self.clients.len())
+                .map(|offset| (start + offset) % self.clients.len())
+                .min_by_key(|&index| self.in_flight[index].load(Ordering::Relaxed))
+                .unwrap_or(start),
+        }
+    }
+}
+
+/// Takes an action out of its wallet's in-flight count when dropped, including on
+/// cancellation.
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Exchange for WalletPool {
+    fn address(&self) -> Address {
+        self.user
+    }
+
+    async fn bulk_order(&self, orders: Vec<ClientOrderRequest>) -> Result<ExchangeResponseStatus> {
+        let (client, _in_flight) = self.acquire();
+        client.bulk_order(orders, None).await
+    }
+
+    async fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        let (client, _in_flight) = self.acquire();
+        client.bulk_cancel(cancels, None).await
+    }
+
+    async fn bulk_cancel_by_cloid(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+    ) -> Result<ExchangeResponseStatus> {
+        let (client, _in_flight) = self.acquire();
+        client.bulk_cancel_by_cloid(cancels, None).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{meta::SpotMeta, BaseUrl, ClientLimit, ClientOrder, Meta, Tif};
+
+    #[tokio::test]
+    async fn test_spreads_orders_over_wallets() -> Result<()> {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"ETH","szDecimals":4,"maxLeverage":50}]}"#,
+        )
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        // Dry run, so nothing is sent to the unreachable API
+        let client = ExchangeClient::builder()
+            .base_url(BaseUrl::custom("http://127.0.0.1:9"))
+            .meta(meta)
+            .spot_meta(SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            })
+            .wallet(PrivateKeySigner::random())
+            .dry_run(true)
+            .build()
+            .await?;
+        let agents: Vec<_> = (0..3).map(|_| PrivateKeySigner::random()).collect();
+        let user = Address::repeat_byte(1);
+        let pool = WalletPool::from_agents(&client, user, agents.clone())?;
+        assert_eq!(pool.address(), user);
+        // Each agent's address-based limits are the account's
+        assert!(pool.clients.iter().all(|client| client.address() == user));
+        assert!(WalletPool::new(user, vec![]).is_err());
+
+        let signers: Vec<Address> = (0..4)
+            .map(|_| pool.clients[pool.pick()].wallet.address())
+            .collect();
+        let expected: Vec<Address> = [0, 1, 2, 0].iter().map(|&i| agents[i].address()).collect();
+        assert_eq!(signers, expected);
+
+        let pool = pool.with_selection(WalletSelection::LeastLoaded);
+        pool.in_flight[0].fetch_add(2, Ordering::Relaxed);
+        pool.in_flight[1].fetch_add(1, Ordering::Relaxed);
+        assert_eq!(pool.pick(), 2);
+        pool.in_flight[2].fetch_add(3, Ordering::Relaxed);
+        assert_eq!(pool.pick(), 1);
+
+        let order = ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 2000.0,
+            sz: 0.1,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+        assert!(matches!(
+            pool.order(order).await,
+            Ok(ExchangeResponseStatus::Ok(_))
+        ));
+        assert_eq!(pool.in_flight(), vec![2, 1, 3]);
+        Ok(())
+    }
+}
@@ -2,23 +2,36 @@ use std::sync::atomic::{AtomicU64, Ordering};
 
 use chrono::prelude::Utc;
 use lazy_static::lazy_static;
-use log::info;
+use tracing::info;
+#[cfg(feature = "exchange")]
 use uuid::Uuid;
 
 use crate::consts::*;
 
-fn now_timestamp_ms() -> u64 {
-    let now = Utc::now();
-    now.timestamp_millis() as u64
+pub(crate) fn now_timestamp_ms() -> u64 {
+    match crate::clock::installed() {
+        Some(clock) => clock.now_ms(),
+        None => Utc::now().timestamp_millis() as u64,
+    }
 }
 
+#[cfg_attr(not(feature = "exchange"), allow(dead_code))]
 pub(crate) fn next_nonce() -> u64 {
+    reserve_nonces(1)
+}
+
+/// Takes `count` consecutive nonces, returning the first.
+#[cfg_attr(not(feature = "exchange"), allow(dead_code))]
+pub(crate) fn reserve_nonces(count: u64) -> u64 {
     loop {
         let now_ms = now_timestamp_ms();
         let current = CUR_NONCE.load(Ordering::Relaxed);
 
         if current > now_ms + 1000 {
-            info!("nonce progressed too far ahead {current} {now_ms}");
+            info!(
+                nonce = current,
+                now_ms, "Nonce progressed too far ahead of the clock"
+            );
         }
 
         // Prevent returning stale values by jumping forward to "now" when lagging too far.
@@ -28,7 +41,7 @@ pub(crate) fn next_nonce() -> u64 {
             current
         };
 
-        let next = target.saturating_add(1);
+        let next = target.saturating_add(count);
 
         match CUR_NONCE.compare_exchange(
             current,
@@ -42,9 +55,37 @@ pub(crate) fn next_nonce() -> u64 {
     }
 }
 
+/// Makes every later nonce larger than `nonce`.
+#[cfg(feature = "exchange")]
+pub(crate) fn advance_nonce_past(nonce: u64) {
+    CUR_NONCE.fetch_max(nonce.saturating_add(1), Ordering::Relaxed);
+}
+
+#[cfg_attr(not(feature = "exchange"), allow(dead_code))]
 pub(crate) const WIRE_DECIMALS: u8 = 8;
 
+/// Formats `x` with at most 8 decimals and no trailing zeros, as prices and sizes are signed.
+#[cfg(feature = "exchange")]
 pub(crate) fn float_to_string_for_hashing(x: f64) -> String {
+    // Below 1e7 a double is within 1e-9 of its shortest representation, so when that has at most
+    // 8 decimals it is also the nearest 8-decimal value `format!("{x:.8}")` would round to
+    if x.abs() < 1e7 {
+        let mut buffer = ryu::Buffer::new();
+        let shortest = buffer.format_finite(x);
+        if let Some((int, frac)) = shortest.split_once('.') {
+            if frac == "0" {
+                return if int == "-0" { "0" } else { int }.to_string();
+            }
+            if frac.len() <= WIRE_DECIMALS as usize && !frac.contains('e') {
+                return shortest.to_string();
+            }
+        }
+    }
+    float_to_string_fixed(x)
+}
+
+#[cfg(feature = "exchange")]
+fn float_to_string_fixed(x: f64) -> String {
     let mut x = format!("{:.*}", WIRE_DECIMALS.into(), x);
     while x.ends_with('0') {
         x.pop();
@@ -59,14 +100,9 @@ pub(crate) fn float_to_string_for_hashing(x: f64) -> String {
     }
 }
 
+#[cfg(feature = "exchange")]
 pub(crate) fn uuid_to_hex_string(uuid: Uuid) -> String {
-    let hex_string = uuid
-        .as_bytes()
-        .iter()
-        .map(|byte| format!("{byte:02x}"))
-        .collect::<Vec<String>>()
-        .join("");
-    format!("0x{hex_string}")
+    alloy::hex::encode_prefixed(uuid.as_bytes())
 }
 
 pub fn truncate_float(float: f64, decimals: u32, round_up: bool) -> f64 {
@@ -86,23 +122,148 @@ pub fn bps_diff(x: f64, y: f64) -> u16 {
     }
 }
 
-#[derive(Copy, Clone)]
+pub fn bps_diff_signed(x: f64, y: f64) -> f64 {
+    if (y - x).abs() < EPSILON {
+        0.0
+    } else if x.abs() < EPSILON {
+        INF_BPS as f64 * (y - x).signum()
+    } else {
+        ((y - x) / x) * 10_000.0
+    }
+}
+
+pub fn apply_bps(px: f64, bps: f64) -> f64 {
+    px * (1.0 + bps / 10_000.0)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RoundingMode {
+    Down,
+    Up,
+    Nearest,
+}
+
+/// Smallest price increment accepted for `px`: prices are limited to 5 significant figures and
+/// `MAX_DECIMALS - sz_decimals` decimal places (6 for perps, 8 for spot). Integer prices are
+/// always accepted.
+pub fn price_tick_size(px: f64, sz_decimals: u32, is_spot: bool) -> f64 {
+    let max_decimals: u32 = if is_spot { 8 } else { 6 };
+    let decimal_tick = 10f64.powi(-(max_decimals.saturating_sub(sz_decimals) as i32));
+    if px.abs() < EPSILON {
+        return decimal_tick;
+    }
+    let magnitude = px.abs().log10().floor() as i32;
+    let sig_fig_tick = 10f64.powi(magnitude - 4);
+    sig_fig_tick.max(decimal_tick).min(1.0)
+}
+
+/// Smallest size increment of an asset with `sz_decimals`.
+pub fn lot_size(sz_decimals: u32) -> f64 {
+    10f64.powi(-(sz_decimals as i32))
+}
+
+pub fn round_to_tick(px: f64, tick_size: f64, mode: RoundingMode) -> f64 {
+    if tick_size <= 0.0 {
+        return px;
+    }
+    let ticks = px / tick_size;
+    // Guard against values like 2.9999999999 ticks that are really on the tick
+    let nearest = ticks.round();
+    let ticks = if (ticks - nearest).abs() < 1e-6 {
+        nearest
+    } else {
+        match mode {
+            RoundingMode::Down => ticks.floor(),
+            RoundingMode::Up => ticks.ceil(),
+            RoundingMode::Nearest => nearest,
+        }
+    };
+    let decimals = (-tick_size.log10().floor()).max(0.0) as i32;
+    let factor = 10f64.powi(decimals);
+    (ticks * tick_size * factor).round() / factor
+}
+
+/// Offsets `px` by `bps` and rounds to a valid tick away from the original price, so the applied
+/// offset is never smaller than requested. A zero offset rounds to the nearest tick.
+pub fn offset_px_bps(px: f64, bps: f64, sz_decimals: u32, is_spot: bool) -> f64 {
+    let shifted = apply_bps(px, bps);
+    let mode = if bps < 0.0 {
+        RoundingMode::Down
+    } else if bps > 0.0 {
+        RoundingMode::Up
+    } else {
+        RoundingMode::Nearest
+    };
+    round_to_tick(
+        shifted,
+        price_tick_size(shifted, sz_decimals, is_spot),
+        mode,
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum BaseUrl {
     Localhost,
     Testnet,
     Mainnet,
+    /// Self-hosted node or proxy. Exchange actions are signed for testnet unless `rest` is the
+    /// mainnet API URL, see `ExchangeClient::with_mainnet`.
+    Custom {
+        rest: String,
+        ws: String,
+    },
 }
 
 impl BaseUrl {
+    /// Custom endpoint with the websocket URL derived from `rest`.
+    pub fn custom(rest: impl Into<String>) -> BaseUrl {
+        let rest = rest.into().trim_end_matches('/').to_string();
+        let ws = ws_url(&rest);
+        BaseUrl::Custom { rest, ws }
+    }
+
     pub(crate) fn get_url(&self) -> String {
+        self.url_with(&ClientDefaults::default())
+    }
+
+    #[cfg(any(test, feature = "ws"))]
+    pub(crate) fn get_ws_url(&self) -> String {
+        self.ws_url_with(&ClientDefaults::default())
+    }
+
+    /// REST URL with the named networks resolved through `defaults`.
+    pub(crate) fn url_with(&self, defaults: &ClientDefaults) -> String {
+        match self {
+            BaseUrl::Localhost => defaults.local_api_url.clone(),
+            BaseUrl::Mainnet => defaults.mainnet_api_url.clone(),
+            BaseUrl::Testnet => defaults.testnet_api_url.clone(),
+            BaseUrl::Custom { rest, .. } => rest.clone(),
+        }
+    }
+
+    pub(crate) fn ws_url_with(&self, defaults: &ClientDefaults) -> String {
         match self {
-            BaseUrl::Localhost => LOCAL_API_URL.to_string(),
-            BaseUrl::Mainnet => MAINNET_API_URL.to_string(),
-            BaseUrl::Testnet => TESTNET_API_URL.to_string(),
+            BaseUrl::Custom { ws, .. } => ws.clone(),
+            _ => ws_url(&self.url_with(defaults)),
         }
     }
 }
 
+/// Websocket endpoint served alongside the REST API at `rest_url`.
+pub(crate) fn ws_url(rest_url: &str) -> String {
+    let rest_url = rest_url.trim_end_matches('/');
+    let host = rest_url
+        .strip_prefix("https://")
+        .map(|host| format!("wss://{host}"))
+        .or_else(|| {
+            rest_url
+                .strip_prefix("http://")
+                .map(|host| format!("ws://{host}"))
+        })
+        .unwrap_or_else(|| rest_url.to_string());
+    format!("{host}/ws")
+}
+
 lazy_static! {
     static ref CUR_NONCE: AtomicU64 = AtomicU64::new(now_timestamp_ms());
 }
@@ -112,6 +273,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[cfg(feature = "exchange")]
     fn float_to_string_for_hashing_test() {
         assert_eq!(float_to_string_for_hashing(0.), "0".to_string());
         assert_eq!(float_to_string_for_hashing(-0.), "0".to_string());
@@ -151,4 +313,86 @@ mod tests {
             "987654321".to_string()
         );
     }
+
+    #[test]
+    #[cfg(feature = "exchange")]
+    fn float_to_string_for_hashing_matches_fixed_formatting() {
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut values = vec![
+            1e-9,
+            5e-9,
+            1.5e-8,
+            0.1 + 0.2,
+            9_999_999.99999999,
+            1e7,
+            1e7 + 0.5,
+        ];
+        for _ in 0..20_000 {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            let mantissa = (seed % 1_000_000_000) as f64;
+            let scale = 10f64.powi((seed >> 40) as i32 % 12 - 8);
+            values.push(mantissa * scale);
+            values.push(-(mantissa / 1e8).round() * 1e-4);
+        }
+        for x in values {
+            assert_eq!(
+                float_to_string_for_hashing(x),
+                float_to_string_fixed(x),
+                "{x}"
+            );
+        }
+        assert_eq!(
+            uuid_to_hex_string(Uuid::from_u128(0x1e60610f0b3d420597c88c1fed2ad5ee)),
+            "0x1e60610f0b3d420597c88c1fed2ad5ee"
+        );
+    }
+
+    #[test]
+    fn bps_diff_signed_test() {
+        assert!((bps_diff_signed(100.0, 101.0) - 100.0).abs() < 1e-9);
+        assert!((bps_diff_signed(100.0, 99.5) + 50.0).abs() < 1e-9);
+        assert_eq!(bps_diff_signed(100.0, 100.0), 0.0);
+        assert_eq!(bps_diff_signed(0.0, 1.0), INF_BPS as f64);
+        assert_eq!(bps_diff_signed(0.0, -1.0), -(INF_BPS as f64));
+        assert!((apply_bps(2000.0, -25.0) - 1995.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tick_rounding_test() {
+        assert_eq!(price_tick_size(1234.5, 4, false), 0.1);
+        assert_eq!(price_tick_size(123456.0, 2, false), 1.0);
+        assert_eq!(price_tick_size(0.012345, 0, false), 0.000001);
+        assert_eq!(price_tick_size(0.0012345, 0, true), 0.0000001);
+        assert_eq!(price_tick_size(0.00012345, 0, true), 0.00000001);
+
+        assert_eq!(round_to_tick(1234.56, 0.1, RoundingMode::Down), 1234.5);
+        assert_eq!(round_to_tick(1234.51, 0.1, RoundingMode::Up), 1234.6);
+        assert_eq!(round_to_tick(1234.56, 0.1, RoundingMode::Nearest), 1234.6);
+        assert_eq!(round_to_tick(0.3, 0.1, RoundingMode::Up), 0.3);
+
+        assert_eq!(offset_px_bps(2000.0, -1.0, 4, false), 1999.8);
+        assert_eq!(offset_px_bps(2000.0, 1.0, 4, false), 2000.2);
+        assert_eq!(offset_px_bps(2000.0, 0.1, 4, false), 2000.1);
+        assert_eq!(offset_px_bps(2000.04, 0.0, 4, false), 2000.0);
+        assert_eq!(lot_size(2), 0.01);
+    }
+
+    #[test]
+    fn test_ws_url() {
+        assert_eq!(
+            BaseUrl::Mainnet.get_ws_url(),
+            "wss://api.hyperliquid.xyz/ws"
+        );
+        assert_eq!(BaseUrl::Localhost.get_ws_url(), "ws://localhost:3001/ws");
+        let custom = BaseUrl::custom("http://10.0.0.5:3001/");
+        assert_eq!(custom.get_url(), "http://10.0.0.5:3001");
+        assert_eq!(custom.get_ws_url(), "ws://10.0.0.5:3001/ws");
+        let custom = BaseUrl::Custom {
+            rest: "https://eu.example.com".to_string(),
+            ws: "wss://eu-ws.example.com/stream".to_string(),
+        };
+        assert_eq!(custom.get_ws_url(), "wss://eu-ws.example.com/stream");
+    }
 }
@@ -0,0 +1,191 @@
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{prelude::*, CandlesSnapshotResponse, InfoClient, Timestamp};
+
+/// Length of a candle interval such as `15m` or `4h`, or None for `1M`, whose months vary, and
+/// for intervals the exchange does not serve.
+pub fn candle_interval_ms(interval: &str) -> Option<u64> {
+    let unit_ms = match interval.chars().last()? {
+        'm' => 60_000,
+        'h' => 3_600_000,
+        'd' => 86_400_000,
+        'w' => 7 * 86_400_000,
+        _ => return None,
+    };
+    let count: u64 = interval[..interval.len() - 1].parse().ok()?;
+    Some(count * unit_ms).filter(|&ms| ms > 0)
+}
+
+/// Candles missing from a series, from the open time of the first missing one up to the open
+/// time of the next candle present, or the end of the range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CandleGap {
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+/// Candles fetched by `InfoClient::candles_history_checked`, with the gaps that could not be
+/// filled.
+#[derive(Debug)]
+pub struct CandleSeries {
+    /// Oldest first, one per open time
+    pub candles: Vec<CandlesSnapshotResponse>,
+    pub gaps: Vec<CandleGap>,
+    /// Candles found by fetching gaps again
+    pub refetched: usize,
+}
+
+impl CandleSeries {
+    pub fn is_complete(&self) -> bool {
+        self.gaps.is_empty()
+    }
+}
+
+/// Gaps in `candles`, sorted by open time, between `start_time` and `end_time`.
+///
+/// A candle should open where the previous one closed. Before the first candle and after the
+/// last, only a whole missing interval counts, since the range need not be aligned to candles,
+/// and neither is checked for `1M`.
+pub fn find_candle_gaps(
+    candles: &[CandlesSnapshotResponse],
+    interval: &str,
+    start_time: Timestamp,
+    end_time: Timestamp,
+) -> Vec<CandleGap> {
+    let interval_ms = candle_interval_ms(interval);
+    let (start, end) = (start_time.as_millis(), end_time.as_millis());
+    let gap = |start: u64, end: u64| CandleGap {
+        start: start.into(),
+        end: end.into(),
+    };
+    let mut gaps = Vec::new();
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        if start < end && interval_ms.is_none_or(|ms| start + ms <= end) {
+            gaps.push(gap(start, end));
+        }
+        return gaps;
+    };
+    if let Some(ms) = interval_ms {
+        if first.time_open.as_millis() >= start + ms {
+            gaps.push(gap(start, first.time_open.as_millis()));
+        }
+    }
+    for pair in candles.windows(2) {
+        let expected = pair[0].time_close.as_millis() + 1;
+        if pair[1].time_open.as_millis() > expected {
+            gaps.push(gap(expected, pair[1].time_open.as_millis()));
+        }
+    }
+    if let Some(ms) = interval_ms {
+        let expected = last.time_close.as_millis() + 1;
+        if expected + ms <= end {
+            gaps.push(gap(expected, end));
+        }
+    }
+    gaps
+}
+
+impl InfoClient {
+    /// Candles of `coin` like `candles_history`, checked for missing intervals. Each gap is
+    /// fetched again up to `retries` times; gaps still missing after that are returned with the
+    /// series, as for candles older than the 5000 the exchange serves or before the market was
+    /// listed. The range ends at the current time at the latest.
+    pub async fn candles_history_checked(
+        &self,
+        coin: String,
+        interval: String,
+        start_time: Timestamp,
+        end_time: Timestamp,
+        retries: u32,
+    ) -> Result<CandleSeries> {
+        let end_time = end_time.min(Timestamp::now());
+        let mut candles = self
+            .candles_history(coin.clone(), interval.clone(), start_time, end_time)
+            .collect_all()
+            .await?;
+        let mut refetched = 0;
+        for _ in 0..retries {
+            let gaps = find_candle_gaps(&candles, &interval, start_time, end_time);
+            if gaps.is_empty() {
+                break;
+            }
+            for gap in gaps {
+                let fetched = self
+                    .candles_snapshot(coin.clone(), interval.clone(), gap.start, gap.end)
+                    .await?;
+                for candle in fetched {
+                    if candle.time_open < start_time || candle.time_open > end_time {
+                        continue;
+                    }
+                    if let Err(index) =
+                        candles.binary_search_by_key(&candle.time_open, |c| c.time_open)
+                    {
+                        candles.insert(index, candle);
+                        refetched += 1;
+                    }
+                }
+            }
+        }
+        let gaps = find_candle_gaps(&candles, &interval, start_time, end_time);
+        for gap in &gaps {
+            warn!(
+                coin,
+                interval,
+                start = gap.start.as_millis(),
+                end = gap.end.as_millis(),
+                "Candles missing"
+            );
+        }
+        Ok(CandleSeries {
+            candles,
+            gaps,
+            refetched,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(open: u64) -> CandlesSnapshotResponse {
+        serde_json::from_value(serde_json::json!({
+            "t": open, "T": open + 59_999, "s": "ETH", "i": "1m", "o": "1", "c": "1",
+            "h": "1", "l": "1", "v": "0", "n": 0
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_finds_missing_minutes() {
+        assert_eq!(candle_interval_ms("15m"), Some(900_000));
+        assert_eq!(candle_interval_ms("1w"), Some(604_800_000));
+        assert_eq!(candle_interval_ms("1M"), None);
+
+        let minute = 60_000;
+        let candles: Vec<_> = [1, 2, 5, 6].map(|i| candle(i * minute)).into();
+        // Starting mid-minute does not count as a gap
+        let gaps = find_candle_gaps(&candles, "1m", 30_000.into(), (7 * minute).into());
+        assert_eq!(
+            gaps,
+            [CandleGap {
+                start: (3 * minute).into(),
+                end: (5 * minute).into()
+            }]
+        );
+
+        let gaps = find_candle_gaps(&candles, "1m", 0.into(), (9 * minute).into());
+        assert_eq!(
+            gaps.iter()
+                .map(|gap| gap.start.as_millis())
+                .collect::<Vec<_>>(),
+            [0, 3 * minute, 7 * minute]
+        );
+        assert_eq!(
+            find_candle_gaps(&[], "1m", 0.into(), minute.into()).len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,213 @@
+#[cfg(feature = "journal")]
+use std::sync::Mutex;
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "journal")]
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{prelude::*, Error, HistoryItem, Timestamp};
+
+/// How far a stream was processed: every item before `time`, and the items at `time` whose
+/// keys are listed. Starting a stream again at `time` and dropping the items it covers resumes
+/// without gaps or duplicates.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub time: Timestamp,
+    /// Keys of the items at `time` already processed
+    pub keys: Vec<String>,
+}
+
+impl Checkpoint {
+    /// A stream starting at `time`, with nothing processed yet.
+    pub fn new(time: Timestamp) -> Checkpoint {
+        Checkpoint {
+            time,
+            keys: Vec::new(),
+        }
+    }
+
+    /// Whether `item` was processed before the checkpoint was taken.
+    pub fn covers(&self, item: &impl HistoryItem) -> bool {
+        let time = item.history_time();
+        time < self.time || (time == self.time && self.keys.contains(&item.history_key()))
+    }
+
+    /// Records `item` as processed, returning false if it already was. Items are expected
+    /// in time order.
+    pub fn advance(&mut self, item: &impl HistoryItem) -> bool {
+        if self.covers(item) {
+            return false;
+        }
+        let time = item.history_time();
+        if time > self.time {
+            self.time = time;
+            self.keys.clear();
+        }
+        self.keys.push(item.history_key());
+        true
+    }
+}
+
+/// Where checkpoints of named streams are kept between runs.
+pub trait CheckpointStore: fmt::Debug + Send + Sync {
+    /// The last checkpoint saved for `stream`, if any.
+    fn load(&self, stream: &str) -> Result<Option<Checkpoint>>;
+
+    fn save(&self, stream: &str, checkpoint: &Checkpoint) -> Result<()>;
+}
+
+/// Keeps each stream's checkpoint in `<dir>/<stream>.checkpoint.json`, replaced atomically on
+/// every save.
+#[derive(Clone, Debug)]
+pub struct FileCheckpointStore {
+    dir: PathBuf,
+}
+
+impl FileCheckpointStore {
+    pub fn new(dir: impl AsRef<Path>) -> FileCheckpointStore {
+        FileCheckpointStore {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, stream: &str) -> PathBuf {
+        self.dir.join(format!("{stream}.checkpoint.json"))
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self, stream: &str) -> Result<Option<Checkpoint>> {
+        let data = match std::fs::read_to_string(self.path(stream)) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Io(err.to_string())),
+        };
+        serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|e| Error::JsonParse(e.to_string()))
+    }
+
+    fn save(&self, stream: &str, checkpoint: &Checkpoint) -> Result<()> {
+        std::fs::create_dir_all(&self.dir).map_err(|e| Error::Io(e.to_string()))?;
+        let data =
+            serde_json::to_string(checkpoint).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let path = self.path(stream);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+}
+
+/// Keeps checkpoints in a `checkpoints` table of a SQLite database, which can be the one
+/// behind a `Journal`.
+#[cfg(feature = "journal")]
+#[derive(Debug)]
+pub struct SqliteCheckpointStore {
+    conn: Mutex<Connection>,
+}
+
+#[cfg(feature = "journal")]
+impl SqliteCheckpointStore {
+    /// Opens or creates the database at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<SqliteCheckpointStore> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checkpoints (
+                stream TEXT PRIMARY KEY,
+                checkpoint TEXT NOT NULL
+            );",
+        )?;
+        Ok(SqliteCheckpointStore {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().expect("checkpoint store lock poisoned")
+    }
+}
+
+#[cfg(feature = "journal")]
+impl CheckpointStore for SqliteCheckpointStore {
+    fn load(&self, stream: &str) -> Result<Option<Checkpoint>> {
+        let data: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT checkpoint FROM checkpoints WHERE stream = ?1",
+                [stream],
+                |row| row.get(0),
+            )
+            .optional()?;
+        data.map(|data| serde_json::from_str(&data).map_err(|e| Error::JsonParse(e.to_string())))
+            .transpose()
+    }
+
+    fn save(&self, stream: &str, checkpoint: &Checkpoint) -> Result<()> {
+        let data =
+            serde_json::to_string(checkpoint).map_err(|e| Error::JsonParse(e.to_string()))?;
+        self.conn().execute(
+            "INSERT INTO checkpoints (stream, checkpoint) VALUES (?1, ?2)
+             ON CONFLICT(stream) DO UPDATE SET checkpoint = excluded.checkpoint",
+            params![stream, data],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Item(u64, u64);
+
+    impl HistoryItem for Item {
+        fn history_time(&self) -> Timestamp {
+            self.0.into()
+        }
+
+        fn history_key(&self) -> String {
+            self.1.to_string()
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_resumes_without_duplicates() {
+        // Items of the same time told apart by their key
+        let items = [Item(1, 1), Item(2, 2), Item(2, 3), Item(3, 4)];
+        let mut checkpoint = Checkpoint::new(1.into());
+        assert!(checkpoint.advance(&items[0]));
+        assert!(checkpoint.advance(&items[1]));
+        assert!(!checkpoint.advance(&items[1]));
+        assert_eq!(checkpoint.time, 2);
+
+        let dir = std::env::temp_dir().join(format!("checkpoints_{}", std::process::id()));
+        let store = FileCheckpointStore::new(&dir);
+        assert_eq!(store.load("fills").unwrap(), None);
+        store.save("fills", &checkpoint).unwrap();
+        let restored = store.load("fills").unwrap().unwrap();
+        assert_eq!(restored, checkpoint);
+        // A restart at the checkpoint's time only processes what was left
+        let left: Vec<&Item> = items
+            .iter()
+            .filter(|item| restored.time <= item.0 && !restored.covers(*item))
+            .collect();
+        assert_eq!(left, [&Item(2, 3), &Item(3, 4)]);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        #[cfg(feature = "journal")]
+        {
+            let store = SqliteCheckpointStore::open(":memory:").unwrap();
+            assert_eq!(store.load("fills").unwrap(), None);
+            store.save("fills", &Checkpoint::new(1.into())).unwrap();
+            store.save("fills", &checkpoint).unwrap();
+            assert_eq!(store.load("fills").unwrap(), Some(checkpoint));
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::{collections::HashSet, time::Duration};
+
+use alloy::primitives::{address, Address, U256};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::{
+    helpers::now_timestamp_ms,
+    info::info_client::InfoClient,
+    prelude::*,
+    req::json_rpc,
+    rt::{self, Instant},
+    Error, LedgerUpdate, LedgerUpdateData, Timestamp,
+};
+
+/// The Hyperliquid bridge contract on Arbitrum, credited for USDC sent to it.
+pub const MAINNET_BRIDGE_ADDRESS: Address = address!("2Df1c51E09aECF9cacB7bc98cB1742757f163dF7");
+/// The testnet bridge contract on Arbitrum Sepolia.
+pub const TESTNET_BRIDGE_ADDRESS: Address = address!("08cfc1B6b2dCF36A1480b99353A354AA8AC56f89");
+
+const MAINNET_ARBITRUM_USDC: Address = address!("af88d065e77c8cC2239327C5EDb3A432268e5831");
+const TESTNET_ARBITRUM_USDC: Address = address!("1baAbB04529D43a73232B713C0FE471f7c7334d5");
+/// `Transfer(address,address,uint256)`
+const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+const USDC_DECIMALS: f64 = 1_000_000.0;
+
+/// A deposit credited to the account, from its ledger updates.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CreditedDeposit {
+    pub usdc: f64,
+    pub time: Timestamp,
+    pub hash: String,
+}
+
+/// USDC sent to the bridge on Arbitrum, which is credited once the validators sign it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BridgeTransfer {
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub usdc: f64,
+}
+
+#[derive(Clone, Debug)]
+struct BridgeWatch {
+    rpc_url: String,
+    lookback_blocks: u64,
+}
+
+/// Watches a user's ledger for bridge deposits credited after the monitor was created, for
+/// onboarding flows that fund an account and then wait to trade.
+///
+/// With `with_arbitrum_rpc` it also looks for the USDC transfer to the bridge on Arbitrum,
+/// logging when a deposit is on its way before it is credited.
+#[derive(Clone, Debug)]
+pub struct DepositMonitor {
+    user: Address,
+    cursor: u64,
+    seen: HashSet<String>,
+    poll_interval: Duration,
+    bridge: Option<BridgeWatch>,
+}
+
+impl DepositMonitor {
+    /// Polls every 2 seconds for deposits from now on.
+    pub fn new(user: Address) -> DepositMonitor {
+        DepositMonitor {
+            user,
+            cursor: now_timestamp_ms(),
+            seen: HashSet::new(),
+            poll_interval: Duration::from_secs(2),
+            bridge: None,
+        }
+    }
+
+    /// Counts deposits credited from `start_time`, in ms, such as ones made just before
+    /// the monitor was created.
+    pub fn with_start_time(mut self, start_time: u64) -> Self {
+        self.cursor = start_time;
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Also watches bridge transfers in the last 1000 blocks through the Arbitrum node at
+    /// `rpc_url`, on Arbitrum One or Sepolia following the `InfoClient`'s network.
+    pub fn with_arbitrum_rpc(mut self, rpc_url: impl Into<String>) -> Self {
+        self.bridge = Some(BridgeWatch {
+            rpc_url: rpc_url.into(),
+            lookback_blocks: 1000,
+        });
+        self
+    }
+
+    /// Deposits credited since the last call.
+    pub async fn credited_deposits(&mut self, info: &InfoClient) -> Result<Vec<CreditedDeposit>> {
+        let updates = info
+            .user_non_funding_ledger_updates(self.user, self.cursor.into(), None)
+            .await?;
+        Ok(self.new_deposits(updates))
+    }
+
+    /// USDC transfers from the user to the bridge in recent blocks, empty without
+    /// `with_arbitrum_rpc`.
+    pub async fn bridge_transfers(&self, info: &InfoClient) -> Result<Vec<BridgeTransfer>> {
+        let Some(bridge) = &self.bridge else {
+            return Ok(Vec::new());
+        };
+        let (bridge_address, usdc) = if info.http_client.is_mainnet() {
+            (MAINNET_BRIDGE_ADDRESS, MAINNET_ARBITRUM_USDC)
+        } else {
+            (TESTNET_BRIDGE_ADDRESS, TESTNET_ARBITRUM_USDC)
+        };
+        let client = &info.http_client.client;
+        let latest = json_rpc(client, &bridge.rpc_url, "eth_blockNumber", json!([])).await?;
+        let latest = latest.as_str().and_then(parse_hex_u64).ok_or_else(|| {
+            Error::GenericParse(format!("invalid eth_blockNumber result {latest}"))
+        })?;
+        let filter = json!({
+            "fromBlock": format!("0x{:x}", latest.saturating_sub(bridge.lookback_blocks)),
+            "toBlock": "latest",
+            "address": usdc,
+            "topics": [TRANSFER_TOPIC, topic(self.user), topic(bridge_address)],
+        });
+        let logs = json_rpc(client, &bridge.rpc_url, "eth_getLogs", json!([filter])).await?;
+        Ok(logs
+            .as_array()
+            .map(|logs| logs.iter().filter_map(parse_transfer).collect())
+            .unwrap_or_default())
+    }
+
+    /// Waits for a deposit of at least `min_usdc` to be credited, failing with
+    /// `Error::Timeout` after `timeout`. Smaller deposits credited meanwhile are skipped.
+    pub async fn wait_for_deposit(
+        &mut self,
+        info: &InfoClient,
+        min_usdc: f64,
+        timeout: Duration,
+    ) -> Result<CreditedDeposit> {
+        let start = Instant::now();
+        let mut bridged = HashSet::new();
+        loop {
+            if let Some(deposit) = self
+                .credited_deposits(info)
+                .await?
+                .into_iter()
+                .find(|deposit| deposit.usdc >= min_usdc)
+            {
+                info!(usdc = deposit.usdc, hash = %deposit.hash, "Deposit credited");
+                return Ok(deposit);
+            }
+            for transfer in self.bridge_transfers(info).await? {
+                if transfer.usdc >= min_usdc && bridged.insert(transfer.tx_hash.clone()) {
+                    info!(
+                        usdc = transfer.usdc,
+                        tx_hash = %transfer.tx_hash,
+                        "Bridge transfer seen on Arbitrum, waiting for it to be credited"
+                    );
+                }
+            }
+            if start.elapsed() >= timeout {
+                return Err(Error::Timeout(format!(
+                    "no deposit of at least {min_usdc} USDC credited within {timeout:?}"
+                )));
+            }
+            rt::sleep(self.poll_interval).await;
+        }
+    }
+
+    fn new_deposits(&mut self, updates: Vec<LedgerUpdateData>) -> Vec<CreditedDeposit> {
+        let mut deposits = Vec::new();
+        for update in updates {
+            // Queries from the latest time seen return its updates again
+            self.cursor = self.cursor.max(update.time.as_millis());
+            if !self.seen.insert(update.hash.clone()) {
+                continue;
+            }
+            if let LedgerUpdate::Deposit(deposit) = update.delta {
+                if let Ok(usdc) = deposit.usdc.parse() {
+                    deposits.push(CreditedDeposit {
+                        usdc,
+                        time: update.time,
+                        hash: update.hash,
+                    });
+                }
+            }
+        }
+        deposits
+    }
+}
+
+fn topic(address: Address) -> String {
+    format!("0x{:0>64}", format!("{address:x}").trim_start_matches("0x"))
+}
+
+fn parse_hex_u64(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.trim_start_matches("0x"), 16).ok()
+}
+
+fn parse_transfer(log: &Value) -> Option<BridgeTransfer> {
+    let amount: U256 = log["data"].as_str()?.parse().ok()?;
+    Some(BridgeTransfer {
+        tx_hash: log["transactionHash"].as_str()?.to_string(),
+        block_number: parse_hex_u64(log["blockNumber"].as_str()?)?,
+        usdc: u128::try_from(amount).ok()? as f64 / USDC_DECIMALS,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EPSILON;
+
+    #[test]
+    fn test_deposits_and_transfers() {
+        let updates: Vec<LedgerUpdateData> = serde_json::from_str(
+            r#"[
+                {"time":1000,"hash":"0x1","delta":{"type":"deposit","usdc":"250.5"}},
+                {"time":1500,"hash":"0x2","delta":{"type":"accountClassTransfer","usdc":"10","toPerp":false}},
+                {"time":2000,"hash":"0x3","delta":{"type":"deposit","usdc":"5"}}
+            ]"#,
+        )
+        .unwrap();
+        let mut monitor = DepositMonitor::new(Address::ZERO).with_start_time(0);
+        let deposits = monitor.new_deposits(updates.clone());
+        assert_eq!(deposits.len(), 2);
+        assert!((deposits[0].usdc - 250.5).abs() < EPSILON);
+        assert_eq!(monitor.cursor, 2000);
+        // Already returned
+        assert!(monitor.new_deposits(updates).is_empty());
+
+        assert_eq!(
+            topic(address!("2Df1c51E09aECF9cacB7bc98cB1742757f163dF7")),
+            "0x0000000000000000000000002df1c51e09aecf9cacb7bc98cb1742757f163df7"
+        );
+        let transfer = parse_transfer(&json!({
+            "transactionHash": "0xabc",
+            "blockNumber": "0x10",
+            "data": "0x0000000000000000000000000000000000000000000000000000000005f5e100",
+        }))
+        .unwrap();
+        assert_eq!(transfer.block_number, 16);
+        assert!((transfer.usdc - 100.0).abs() < EPSILON);
+    }
+}
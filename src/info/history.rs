@@ -0,0 +1,401 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use alloy::primitives::Address;
+use futures_util::{future, stream, Stream, StreamExt, TryStreamExt};
+
+use crate::{
+    prelude::*, rt::MaybeSend, CandlesSnapshotResponse, Checkpoint, FundingHistoryResponse,
+    InfoClient, LedgerUpdateData, Timestamp, Trade, UserFillsResponse, UserFundingResponse,
+};
+
+const FILLS_PAGE: usize = 2000;
+const FUNDING_PAGE: usize = 500;
+const LEDGER_PAGE: usize = 500;
+const CANDLES_PAGE: usize = 5000;
+
+#[cfg(not(target_arch = "wasm32"))]
+type Items<T> = stream::BoxStream<'static, Result<T>>;
+#[cfg(target_arch = "wasm32")]
+type Items<T> = stream::LocalBoxStream<'static, Result<T>>;
+
+/// Items of a history endpoint from a start time on, oldest first, fetched a page at a time
+/// as the stream is polled. Each page restarts at the time of the last item so items sharing
+/// a millisecond across a page boundary are kept, and the overlap is dropped. Pages go through
+/// the client's rate limiter and throttle like any other request.
+///
+/// The stream ends after the first error.
+pub struct HistoryStream<T> {
+    items: Items<T>,
+}
+
+/// An item of a history or message stream, identified for a `Checkpoint` by its time and a
+/// key telling it apart from other items of the same time.
+pub trait HistoryItem {
+    fn history_time(&self) -> Timestamp;
+
+    fn history_key(&self) -> String;
+}
+
+impl HistoryItem for UserFillsResponse {
+    fn history_time(&self) -> Timestamp {
+        self.time
+    }
+
+    fn history_key(&self) -> String {
+        self.tid.to_string()
+    }
+}
+
+impl HistoryItem for UserFundingResponse {
+    fn history_time(&self) -> Timestamp {
+        self.time
+    }
+
+    fn history_key(&self) -> String {
+        self.delta.coin.clone()
+    }
+}
+
+impl HistoryItem for LedgerUpdateData {
+    fn history_time(&self) -> Timestamp {
+        self.time
+    }
+
+    fn history_key(&self) -> String {
+        self.hash.clone()
+    }
+}
+
+impl HistoryItem for FundingHistoryResponse {
+    fn history_time(&self) -> Timestamp {
+        self.time
+    }
+
+    fn history_key(&self) -> String {
+        self.coin.clone()
+    }
+}
+
+impl HistoryItem for CandlesSnapshotResponse {
+    fn history_time(&self) -> Timestamp {
+        self.time_open
+    }
+
+    fn history_key(&self) -> String {
+        self.coin.clone()
+    }
+}
+
+impl HistoryItem for Trade {
+    fn history_time(&self) -> Timestamp {
+        self.time
+    }
+
+    fn history_key(&self) -> String {
+        self.tid.to_string()
+    }
+}
+
+impl<T> fmt::Debug for HistoryStream<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HistoryStream").finish_non_exhaustive()
+    }
+}
+
+struct Pager<T, K, F> {
+    start_time: u64,
+    page_size: usize,
+    fetch: F,
+    time: fn(&T) -> u64,
+    key: fn(&T) -> K,
+    /// Keys of the items at `start_time` already yielded
+    boundary: HashSet<K>,
+    buffer: VecDeque<T>,
+    done: bool,
+}
+
+impl<T, K, F, Fut> Pager<T, K, F>
+where
+    K: Eq + Hash,
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    async fn next_page(&mut self) -> Result<()> {
+        let mut page = (self.fetch)(self.start_time).await?;
+        let full = page.len() >= self.page_size;
+        page.sort_by_key(self.time);
+        let page_start = self.start_time;
+        let last_time = page.last().map(self.time);
+        let (time, key) = (self.time, self.key);
+        let boundary = &self.boundary;
+        let new: Vec<T> = page
+            .into_iter()
+            .filter(|item| time(item) != page_start || !boundary.contains(&key(item)))
+            .collect();
+        match last_time {
+            Some(last_time) if full => {
+                // A full page of one millisecond would otherwise be fetched forever
+                let start_time = if last_time > page_start {
+                    last_time
+                } else {
+                    page_start + 1
+                };
+                self.start_time = start_time;
+                self.boundary.clear();
+                self.boundary
+                    .extend(new.iter().filter(|item| time(item) == start_time).map(key));
+            }
+            _ => self.done = true,
+        }
+        self.buffer.extend(new);
+        Ok(())
+    }
+}
+
+impl<T: MaybeSend + 'static> HistoryStream<T> {
+    pub(crate) fn paged<K, F, Fut>(
+        start_time: u64,
+        page_size: usize,
+        fetch: F,
+        time: fn(&T) -> u64,
+        key: fn(&T) -> K,
+    ) -> HistoryStream<T>
+    where
+        K: Eq + Hash + MaybeSend + 'static,
+        F: FnMut(u64) -> Fut + MaybeSend + 'static,
+        Fut: Future<Output = Result<Vec<T>>> + MaybeSend,
+    {
+        let pager = Pager {
+            start_time,
+            page_size,
+            fetch,
+            time,
+            key,
+            boundary: HashSet::new(),
+            buffer: VecDeque::new(),
+            done: false,
+        };
+        let items = stream::unfold(pager, |mut pager| async move {
+            loop {
+                if let Some(item) = pager.buffer.pop_front() {
+                    return Some((Ok(item), pager));
+                }
+                if pager.done {
+                    return None;
+                }
+                if let Err(err) = pager.next_page().await {
+                    pager.done = true;
+                    return Some((Err(err), pager));
+                }
+            }
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let items = items.boxed();
+        #[cfg(target_arch = "wasm32")]
+        let items = items.boxed_local();
+        HistoryStream { items }
+    }
+
+    /// Drops the items `checkpoint` covers, for a stream started at the checkpoint's time to
+    /// resume where a previous run stopped.
+    pub fn after(self, checkpoint: Checkpoint) -> HistoryStream<T>
+    where
+        T: HistoryItem,
+    {
+        let items = self
+            .items
+            .try_filter(move |item| future::ready(!checkpoint.covers(item)));
+        #[cfg(not(target_arch = "wasm32"))]
+        let items = items.boxed();
+        #[cfg(target_arch = "wasm32")]
+        let items = items.boxed_local();
+        HistoryStream { items }
+    }
+
+    /// Every remaining item, failing on the first error.
+    pub async fn collect_all(self) -> Result<Vec<T>> {
+        self.try_collect().await
+    }
+}
+
+impl<T> Stream for HistoryStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<T>>> {
+        self.items.poll_next_unpin(cx)
+    }
+}
+
+impl InfoClient {
+    /// Fills between `start_time` and `end_time`, paging through `userFillsByTime`. The
+    /// exchange only serves the most recent 10000 fills this way.
+    pub fn user_fills_history(
+        &self,
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> HistoryStream<UserFillsResponse> {
+        let info = self.clone();
+        HistoryStream::paged(
+            start_time.as_millis(),
+            FILLS_PAGE,
+            move |start_time| {
+                let info = info.clone();
+                async move {
+                    info.user_fills_by_time(user, start_time.into(), end_time)
+                        .await
+                }
+            },
+            |fill| fill.time.as_millis(),
+            |fill| fill.tid,
+        )
+    }
+
+    /// Funding payments between `start_time` and `end_time`.
+    pub fn user_funding_history_stream(
+        &self,
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> HistoryStream<UserFundingResponse> {
+        let info = self.clone();
+        HistoryStream::paged(
+            start_time.as_millis(),
+            FUNDING_PAGE,
+            move |start_time| {
+                let info = info.clone();
+                async move {
+                    info.user_funding_history(user, start_time.into(), end_time)
+                        .await
+                }
+            },
+            |funding| funding.time.as_millis(),
+            |funding| (funding.time, funding.delta.coin.clone()),
+        )
+    }
+
+    /// Non-funding ledger updates between `start_time` and `end_time`.
+    pub fn ledger_updates_history(
+        &self,
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> HistoryStream<LedgerUpdateData> {
+        let info = self.clone();
+        HistoryStream::paged(
+            start_time.as_millis(),
+            LEDGER_PAGE,
+            move |start_time| {
+                let info = info.clone();
+                async move {
+                    info.user_non_funding_ledger_updates(user, start_time.into(), end_time)
+                        .await
+                }
+            },
+            |update| update.time.as_millis(),
+            |update| (update.time, update.hash.clone()),
+        )
+    }
+
+    /// Funding rates of `coin` between `start_time` and `end_time`.
+    pub fn funding_history_stream(
+        &self,
+        coin: String,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> HistoryStream<FundingHistoryResponse> {
+        let info = self.clone();
+        HistoryStream::paged(
+            start_time.as_millis(),
+            FUNDING_PAGE,
+            move |start_time| {
+                let (info, coin) = (info.clone(), coin.clone());
+                async move {
+                    info.funding_history(coin, start_time.into(), end_time)
+                        .await
+                }
+            },
+            |funding| funding.time.as_millis(),
+            |funding| funding.time.as_millis(),
+        )
+    }
+
+    /// Candles of `coin` opening between `start_time` and `end_time`. The exchange only serves
+    /// the most recent 5000 candles of each interval.
+    pub fn candles_history(
+        &self,
+        coin: String,
+        interval: String,
+        start_time: Timestamp,
+        end_time: Timestamp,
+    ) -> HistoryStream<CandlesSnapshotResponse> {
+        let info = self.clone();
+        HistoryStream::paged(
+            start_time.as_millis(),
+            CANDLES_PAGE,
+            move |start_time| {
+                let (info, coin, interval) = (info.clone(), coin.clone(), interval.clone());
+                async move {
+                    info.candles_snapshot(coin, interval, start_time.into(), end_time)
+                        .await
+                }
+            },
+            |candle| candle.time_open.as_millis(),
+            |candle| candle.time_open.as_millis(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_history_keeps_boundary_items() {
+        // Pages of two, with items 2 and 3 sharing a millisecond across the boundary
+        let items = [(1u64, 1u64), (2, 2), (2, 3), (5, 4)];
+        let fetched = HistoryStream::paged(
+            0,
+            2,
+            move |start_time| async move {
+                Ok(items
+                    .iter()
+                    .filter(|item| item.0 >= start_time)
+                    .take(2)
+                    .copied()
+                    .collect())
+            },
+            |item| item.0,
+            |item| item.1,
+        )
+        .collect_all()
+        .await
+        .unwrap();
+        assert_eq!(fetched, items);
+
+        // A full page within one millisecond moves on to the next
+        let fetched = HistoryStream::paged(
+            0,
+            2,
+            move |start_time| async move {
+                Ok(match start_time {
+                    0 => vec![(0u64, 1u64), (0, 2)],
+                    _ => vec![],
+                })
+            },
+            |item| item.0,
+            |item| item.1,
+        )
+        .collect_all()
+        .await
+        .unwrap();
+        assert_eq!(fetched.len(), 2);
+    }
+}
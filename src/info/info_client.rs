@@ -1,21 +1,36 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use alloy::primitives::Address;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc::UnboundedSender;
 
+#[cfg(feature = "ws")]
+use tokio::sync::{mpsc::UnboundedSender, oneshot, Mutex, MutexGuard};
+
+#[cfg(all(feature = "ws", feature = "exchange"))]
+use crate::PostResponse;
 use crate::{
+    helpers::ws_url,
     info::{
-        CandlesSnapshotResponse, FundingHistoryResponse, L2SnapshotResponse, OpenOrdersResponse,
-        OrderInfo, RecentTradesResponse, UserFillsResponse, UserStateResponse, ActiveAssetDataResponse,
+        AccountSnapshot, ActiveAssetDataResponse, CandlesSnapshotResponse, ExtraAgent,
+        FundingHistoryResponse, L2SnapshotResponse, OpenOrdersResponse, OrderInfo,
+        RecentTradesResponse, SubAccount, UserFillsResponse, UserRole, UserStateResponse,
+        VaultDetailsResponse, VenueFundings,
     },
     meta::{AssetContext, Meta, SpotMeta, SpotMetaAndAssetCtxs},
     prelude::*,
-    req::HttpClient,
-    ws::{Subscription, WsManager},
-    BaseUrl, Error, Message, OrderStatusResponse, ReferralResponse, UserFeesResponse,
-    UserFundingResponse, UserTokenBalanceResponse,
+    req::{
+        http_options_setters, CancellationToken, EndpointRoute, HttpClient, HttpOptions,
+        ProxyConfig, RateLimiter, Recorder, Replayer, RequestLogger, RequestStats,
+        RequestStatsSnapshot, RetryPolicy, SchemaCheck, Throttle, ThrottleState, Timeouts,
+    },
+    BaseUrl, Error, LedgerUpdateData, OrderStatusResponse, ReferralResponse, Timestamp,
+    UserFeesResponse, UserFundingResponse, UserRateLimitResponse, UserTokenBalanceResponse,
+};
+#[cfg(feature = "ws")]
+use crate::{
+    ws::{WsManager, WsOptions},
+    CustomMessage, CustomSubscription, Message, Subscription,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -23,8 +38,8 @@ use crate::{
 pub struct CandleSnapshotRequest {
     coin: String,
     interval: String,
-    start_time: u64,
-    end_time: u64,
+    start_time: Timestamp,
+    end_time: Timestamp,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -53,6 +68,13 @@ pub enum InfoRequest {
         user: Address,
         oid: u64,
     },
+    /// `orderStatus` also accepts a cloid in place of the oid.
+    #[serde(rename = "orderStatus", skip_deserializing)]
+    OrderStatusByCloid {
+        user: Address,
+        #[serde(rename = "oid")]
+        cloid: String,
+    },
     Meta,
     MetaAndAssetCtxs,
     SpotMeta,
@@ -62,16 +84,29 @@ pub enum InfoRequest {
         user: Address,
     },
     #[serde(rename_all = "camelCase")]
+    UserFillsByTime {
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    },
+    #[serde(rename_all = "camelCase")]
     FundingHistory {
         coin: String,
-        start_time: u64,
-        end_time: Option<u64>,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
     },
+    PredictedFundings,
     #[serde(rename_all = "camelCase")]
     UserFunding {
         user: Address,
-        start_time: u64,
-        end_time: Option<u64>,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    },
+    #[serde(rename_all = "camelCase")]
+    UserNonFundingLedgerUpdates {
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
     },
     L2Book {
         coin: String,
@@ -93,13 +128,74 @@ pub enum InfoRequest {
         user: Address,
         coin: String,
     },
+    UserRateLimit {
+        user: Address,
+    },
+    UserRole {
+        user: Address,
+    },
+    ExtraAgents {
+        user: Address,
+    },
+    SubAccounts {
+        user: Address,
+    },
+    MaxBuilderFee {
+        user: Address,
+        builder: Address,
+    },
+    #[serde(rename_all = "camelCase")]
+    VaultDetails {
+        vault_address: Address,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user: Option<Address>,
+    },
+}
+
+impl InfoRequest {
+    pub(crate) fn weight(&self) -> u32 {
+        match self {
+            InfoRequest::L2Book { .. }
+            | InfoRequest::AllMids
+            | InfoRequest::UserState { .. }
+            | InfoRequest::OrderStatus { .. }
+            | InfoRequest::OrderStatusByCloid { .. }
+            | InfoRequest::UserTokenBalances { .. } => 2,
+            _ => 20,
+        }
+    }
+
+    /// Some endpoints carry an additional weight of one per this many returned items.
+    fn items_per_extra_weight(&self) -> Option<usize> {
+        match self {
+            InfoRequest::RecentTrades { .. }
+            | InfoRequest::HistoricalOrders { .. }
+            | InfoRequest::UserFills { .. }
+            | InfoRequest::UserFillsByTime { .. }
+            | InfoRequest::FundingHistory { .. }
+            | InfoRequest::UserFunding { .. }
+            | InfoRequest::UserNonFundingLedgerUpdates { .. } => Some(20),
+            InfoRequest::CandleSnapshot { .. } => Some(60),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug)]
+/// Cloning is cheap: clones share the HTTP connection pool, rate limiter and websocket
+/// connection, which is closed once the last clone is dropped.
+#[derive(Clone, Debug)]
 pub struct InfoClient {
     pub http_client: HttpClient,
-    pub(crate) ws_manager: Option<WsManager>,
+    #[cfg(feature = "ws")]
+    ws_manager: Arc<Mutex<Option<WsManager>>>,
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
     reconnect: bool,
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    pub(crate) ws_url: String,
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    book_coalescing: Option<std::time::Duration>,
+    #[cfg(feature = "ws")]
+    bandwidth_budget: Option<crate::BandwidthBudget>,
 }
 
 impl InfoClient {
@@ -119,65 +215,387 @@ impl InfoClient {
         base_url: Option<BaseUrl>,
         reconnect: bool,
     ) -> Result<InfoClient> {
-        let client = client.unwrap_or_default();
-        let base_url = base_url.unwrap_or(BaseUrl::Mainnet).get_url();
+        let mut builder = InfoClient::builder().reconnect(reconnect);
+        if let Some(client) = client {
+            builder = builder.client(client);
+        }
+        if let Some(base_url) = base_url {
+            builder = builder.base_url(base_url);
+        }
+        builder.build()
+    }
 
-        Ok(InfoClient {
-            http_client: HttpClient { client, base_url },
-            ws_manager: None,
+    pub fn builder() -> InfoClientBuilder {
+        InfoClientBuilder::default()
+    }
+
+    /// Builds a client on top of an already configured `HttpClient`, sharing its reqwest client
+    /// (and with it proxies, TLS roots and connection pool), retry policy and rate limiter.
+    // Browser websocket handles are single threaded, like the client on that target
+    #[cfg_attr(
+        all(target_arch = "wasm32", feature = "ws"),
+        allow(clippy::arc_with_non_send_sync)
+    )]
+    pub fn with_http_client(http_client: HttpClient, reconnect: bool) -> InfoClient {
+        InfoClient {
+            ws_url: ws_url(&http_client.base_url),
+            http_client,
+            #[cfg(feature = "ws")]
+            ws_manager: Arc::new(Mutex::new(None)),
             reconnect,
-        })
+            book_coalescing: None,
+            #[cfg(feature = "ws")]
+            bandwidth_budget: None,
+        }
+    }
+
+    /// Overrides the websocket endpoint, which defaults to `/ws` on the REST host.
+    pub fn with_ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.ws_url = ws_url.into();
+        self
+    }
+
+    /// Delivers `l2Book` updates at most once per `window` and coin, the latest book replacing
+    /// any held back, trading latency for less work when subscribed to many books. Applies to
+    /// websocket connections opened afterwards.
+    pub fn with_book_coalescing(mut self, window: std::time::Duration) -> Self {
+        self.book_coalescing = Some(window);
+        self
+    }
+
+    /// Sheds market data of lower priority coins when websocket traffic exceeds `budget`,
+    /// see `BandwidthBudget`. Applies to websocket connections opened afterwards.
+    #[cfg(feature = "ws")]
+    pub fn with_bandwidth_budget(mut self, budget: crate::BandwidthBudget) -> Self {
+        self.bandwidth_budget = Some(budget);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.http_client.retry_policy = retry_policy;
+        self
     }
 
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.http_client.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Shares rate-limit cooldowns with other clients talking to the same API.
+    pub fn with_throttle(mut self, throttle: Arc<Throttle>) -> Self {
+        self.http_client.throttle = throttle;
+        self
+    }
+
+    /// Counts requests in `stats`, shared with other clients.
+    pub fn with_request_stats(mut self, stats: Arc<RequestStats>) -> Self {
+        self.http_client.stats = stats;
+        self
+    }
+
+    /// Reports every request and response, with signatures redacted, to `logger`.
+    pub fn with_request_logger(mut self, logger: RequestLogger) -> Self {
+        self.http_client.request_logger = Some(logger);
+        self
+    }
+
+    /// Checks responses and websocket messages for fields and values the SDK does not know,
+    /// see `SchemaCheck`.
+    pub fn with_schema_check(mut self, check: SchemaCheck) -> Self {
+        self.http_client.schema_check = Some(check);
+        self
+    }
+
+    /// Records every REST response and websocket message to `recorder`.
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.http_client.recorder = Some(recorder);
+        self
+    }
+
+    /// Answers REST requests from a recording instead of the network. Websocket messages
+    /// are replayed with `Recording::replay_messages`.
+    pub fn with_replayer(mut self, replayer: Arc<Replayer>) -> Self {
+        self.http_client.replayer = Some(replayer);
+        self
+    }
+
+    /// Aborts requests in flight and closes the websocket connection once `token` is
+    /// cancelled. Later requests and subscriptions fail with `Error::Cancelled`.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.http_client.cancel = Some(token);
+        self
+    }
+
+    /// Publishes request and websocket metrics to `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<crate::Metrics>) -> Self {
+        self.http_client.metrics = Some(metrics);
+        self
+    }
+
+    pub fn throttle_state(&self) -> ThrottleState {
+        self.http_client.throttle.state()
+    }
+
+    /// Orders, cancels, modifies and info requests sent over the last minute and in total, by
+    /// this client and those sharing its `RequestStats`.
+    pub fn request_stats(&self) -> RequestStatsSnapshot {
+        self.http_client.stats.snapshot()
+    }
+
+    /// Sets default timeouts. A connect timeout requires rebuilding the underlying reqwest
+    /// client, so configure it on a custom client instead if one was passed in.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Result<Self> {
+        if timeouts.connect.is_some() {
+            self.http_client.connect_timeout = timeouts.connect;
+            self.http_client.rebuild_client()?;
+        }
+        self.http_client.timeout = timeouts.request;
+        Ok(self)
+    }
+
+    /// Routes requests through `proxy`. Like a connect timeout this rebuilds the underlying
+    /// reqwest client, so set it on a custom client instead if one was passed in.
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Result<Self> {
+        self.http_client.proxy = Some(proxy);
+        self.http_client.rebuild_client()?;
+        Ok(self)
+    }
+
+    /// Subscribes on the websocket connection shared by this client and its clones, opening it
+    /// on first use.
+    #[cfg(feature = "ws")]
     pub async fn subscribe(
-        &mut self,
+        &self,
         subscription: Subscription,
         sender_channel: UnboundedSender<Message>,
     ) -> Result<u32> {
-        if self.ws_manager.is_none() {
-            let ws_manager = WsManager::new(
-                format!("ws{}/ws", &self.http_client.base_url[4..]),
-                self.reconnect,
-            )
+        self.add_subscription(subscription, sender_channel, None)
+            .await
+    }
+
+    /// Subscribes like `subscribe` and waits up to `timeout` for the first message of the
+    /// channel, returned here rather than sent to `sender_channel`, which gets every later
+    /// one. For `l2Book` and `webData2` that is the full state, and for user channels the
+    /// message with `isSnapshot` set, so startup can finish before updates are handled.
+    ///
+    /// A channel already subscribed by this client is not sent again by the exchange, so
+    /// its snapshot is the next update. On timeout the subscription is removed.
+    #[cfg(feature = "ws")]
+    pub async fn subscribe_and_snapshot(
+        &self,
+        subscription: Subscription,
+        sender_channel: UnboundedSender<Message>,
+        timeout: std::time::Duration,
+    ) -> Result<(u32, Message)> {
+        let (sender, receiver) = oneshot::channel();
+        let subscription_id = self
+            .add_subscription(subscription, sender_channel, Some(sender))
             .await?;
-            self.ws_manager = Some(ws_manager);
+        tokio::select! {
+            snapshot = receiver => match snapshot {
+                Ok(snapshot) => Ok((subscription_id, snapshot)),
+                Err(_) => Err(Error::Websocket(
+                    "Connection closed before the subscription snapshot".to_string(),
+                )),
+            },
+            _ = crate::rt::sleep(timeout) => {
+                self.unsubscribe(subscription_id).await?;
+                Err(Error::Timeout("No subscription snapshot".to_string()))
+            }
         }
+    }
 
+    /// Subscribes to a channel this version does not know yet, sending `subscription` as is
+    /// and passing each message of its channel through `decode`. Messages that fail to decode
+    /// are logged and skipped.
+    ///
+    /// Every subscriber of the channel receives its messages whatever the payload, so
+    /// subscriptions differing only in their parameters should filter in `decode`.
+    #[cfg(feature = "ws")]
+    pub async fn subscribe_custom<T, F>(
+        &self,
+        subscription: CustomSubscription,
+        mut decode: F,
+        sender_channel: UnboundedSender<T>,
+    ) -> Result<u32>
+    where
+        T: Send + 'static,
+        F: FnMut(CustomMessage) -> Result<T> + Send + 'static,
+    {
+        let identifier = serde_json::to_string(&subscription.subscription)
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let subscription_id = self
+            .ws_manager()
+            .await?
+            .as_mut()
+            .ok_or(Error::WsManagerNotFound)?
+            .add_custom_subscription(&subscription.channel, identifier, sender)
+            .await?;
+        crate::rt::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                let custom = match message {
+                    Message::Custom(custom) => custom,
+                    Message::HyperliquidError(err) => {
+                        tracing::warn!(
+                            channel = subscription.channel,
+                            error = err,
+                            "Websocket error"
+                        );
+                        continue;
+                    }
+                    _ => continue,
+                };
+                match decode(custom) {
+                    Ok(decoded) => {
+                        if sender_channel.send(decoded).is_err() {
+                            return;
+                        }
+                    }
+                    Err(err) => {
+                        tracing::warn!(channel = subscription.channel, error = %err, "Could not decode message");
+                    }
+                }
+            }
+        });
+        Ok(subscription_id)
+    }
+
+    #[cfg(feature = "ws")]
+    async fn add_subscription(
+        &self,
+        subscription: Subscription,
+        sender_channel: UnboundedSender<Message>,
+        snapshot: Option<oneshot::Sender<Message>>,
+    ) -> Result<u32> {
         let identifier =
             serde_json::to_string(&subscription).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        self.ws_manager
+        self.ws_manager()
+            .await?
             .as_mut()
             .ok_or(Error::WsManagerNotFound)?
-            .add_subscription(identifier, sender_channel)
+            .add_subscription(identifier, sender_channel, snapshot)
             .await
     }
 
-    pub async fn unsubscribe(&mut self, subscription_id: u32) -> Result<()> {
-        if self.ws_manager.is_none() {
-            let ws_manager = WsManager::new(
-                format!("ws{}/ws", &self.http_client.base_url[4..]),
-                self.reconnect,
-            )
-            .await?;
-            self.ws_manager = Some(ws_manager);
-        }
-
-        self.ws_manager
+    #[cfg(feature = "ws")]
+    pub async fn unsubscribe(&self, subscription_id: u32) -> Result<()> {
+        self.ws_manager()
+            .await?
             .as_mut()
             .ok_or(Error::WsManagerNotFound)?
             .remove_subscription(subscription_id)
             .await
     }
 
-    async fn send_info_request<T: for<'a> Deserialize<'a>>(
+    /// Sends `payload` as a websocket post request of `request_type`, `info` or `action`,
+    /// opening the connection on first use, and waits up to `timeout` for the response payload.
+    ///
+    /// The outer error means nothing was sent. Once the frame is written, the inner result is
+    /// the response or, when none arrives before `timeout` or the connection drops,
+    /// `Error::Timeout`, as the request may still have been executed.
+    #[cfg(all(feature = "ws", feature = "exchange"))]
+    pub(crate) async fn ws_post(
+        &self,
+        request_type: &'static str,
+        payload: &serde_json::Value,
+        timeout: std::time::Duration,
+    ) -> Result<Result<serde_json::Value>> {
+        let receiver = self
+            .ws_manager()
+            .await?
+            .as_mut()
+            .ok_or(Error::WsManagerNotFound)?
+            .post(request_type, payload)
+            .await?;
+        let response = tokio::select! {
+            response = receiver => match response {
+                Ok(response) => response,
+                Err(_) => {
+                    return Ok(Err(Error::Timeout(
+                        "Connection closed before the websocket post response".to_string(),
+                    )));
+                }
+            },
+            _ = crate::rt::sleep(timeout) => {
+                return Ok(Err(Error::Timeout("No websocket post response".to_string())));
+            }
+        };
+        Ok(match response {
+            PostResponse::Info(payload) | PostResponse::Action(payload) => Ok(payload),
+            PostResponse::Error(err) => Err(Error::Websocket(err)),
+        })
+    }
+
+    #[cfg(feature = "ws")]
+    async fn ws_manager(&self) -> Result<MutexGuard<'_, Option<WsManager>>> {
+        let cancel = match &self.http_client.cancel {
+            Some(token) if token.is_cancelled() => return Err(Error::Cancelled),
+            Some(token) => token.child_token(),
+            None => CancellationToken::new(),
+        };
+        let mut ws_manager = self.ws_manager.lock().await;
+        if ws_manager.is_none() {
+            *ws_manager = Some(
+                WsManager::new(
+                    self.ws_url.clone(),
+                    cancel,
+                    WsOptions {
+                        reconnect: self.reconnect,
+                        proxy: self.http_client.proxy.clone(),
+                        recorder: self.http_client.recorder.clone(),
+                        schema_check: self.http_client.schema_check.clone(),
+                        endpoints: self.http_client.endpoints.clone(),
+                        book_coalescing: self.book_coalescing,
+                        bandwidth_budget: self.bandwidth_budget.clone(),
+                        #[cfg(feature = "metrics")]
+                        metrics: self.http_client.metrics.clone(),
+                    },
+                )
+                .await?,
+            );
+        }
+        Ok(ws_manager)
+    }
+
+    async fn send_info_request<T: for<'a> Deserialize<'a> + Serialize>(
         &self,
         info_request: InfoRequest,
     ) -> Result<T> {
         let data =
             serde_json::to_string(&info_request).map_err(|e| Error::JsonParse(e.to_string()))?;
 
-        let return_data = self.http_client.post("/info", data).await?;
+        // History is the same on every endpoint, so it is spread over all of them
+        let route = if info_request.items_per_extra_weight().is_some() {
+            EndpointRoute::Any
+        } else {
+            EndpointRoute::Active
+        };
+        let return_data = self
+            .http_client
+            .post_routed("/info", data, info_request.weight(), route)
+            .await?;
+
+        if let (Some(rate_limiter), Some(items_per_weight)) = (
+            &self.http_client.rate_limiter,
+            info_request.items_per_extra_weight(),
+        ) {
+            if let Ok(serde_json::Value::Array(items)) =
+                serde_json::from_str::<serde_json::Value>(&return_data)
+            {
+                rate_limiter.charge((items.len() / items_per_weight) as u32);
+            }
+        }
+
+        if let Some(check) = &self.http_client.schema_check {
+            let source = serde_json::to_value(&info_request)
+                .ok()
+                .and_then(|request| request["type"].as_str().map(|kind| format!("info:{kind}")))
+                .unwrap_or_else(|| "info".to_string());
+            return check.decode(&source, &return_data);
+        }
         serde_json::from_str(&return_data).map_err(|e| Error::JsonParse(e.to_string()))
     }
 
@@ -201,6 +619,27 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    /// Fetches the account's clearinghouse state, spot balances, open orders and recent fills
+    /// concurrently, failing if any request does. Components resyncing with the exchange
+    /// start from one of these.
+    pub async fn account_snapshot(&self, address: Address) -> Result<AccountSnapshot> {
+        let time = Timestamp::now();
+        let (state, balances, open_orders, fills) = tokio::try_join!(
+            self.user_state(address),
+            self.user_token_balances(address),
+            self.open_orders(address),
+            self.user_fills(address),
+        )?;
+        Ok(AccountSnapshot {
+            user: address,
+            time,
+            state,
+            balances: balances.balances,
+            open_orders,
+            fills,
+        })
+    }
+
     pub async fn user_fees(&self, address: Address) -> Result<UserFeesResponse> {
         let input = InfoRequest::UserFees { user: address };
         self.send_info_request(input).await
@@ -236,11 +675,26 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    /// Fills from `start_time`, at most 2000 per call, oldest first.
+    pub async fn user_fills_by_time(
+        &self,
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> Result<Vec<UserFillsResponse>> {
+        let input = InfoRequest::UserFillsByTime {
+            user,
+            start_time,
+            end_time,
+        };
+        self.send_info_request(input).await
+    }
+
     pub async fn funding_history(
         &self,
         coin: String,
-        start_time: u64,
-        end_time: Option<u64>,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
     ) -> Result<Vec<FundingHistoryResponse>> {
         let input = InfoRequest::FundingHistory {
             coin,
@@ -253,8 +707,8 @@ impl InfoClient {
     pub async fn user_funding_history(
         &self,
         user: Address,
-        start_time: u64,
-        end_time: Option<u64>,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
     ) -> Result<Vec<UserFundingResponse>> {
         let input = InfoRequest::UserFunding {
             user,
@@ -264,6 +718,29 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    /// Deposits, withdrawals, transfers and other non-funding ledger updates from
+    /// `start_time`, at most 500 per call.
+    pub async fn user_non_funding_ledger_updates(
+        &self,
+        user: Address,
+        start_time: Timestamp,
+        end_time: Option<Timestamp>,
+    ) -> Result<Vec<LedgerUpdateData>> {
+        let input = InfoRequest::UserNonFundingLedgerUpdates {
+            user,
+            start_time,
+            end_time,
+        };
+        self.send_info_request(input).await
+    }
+
+    /// Predicted next funding by coin, as `(venue, funding)` pairs with venues such as
+    /// `HlPerp`, `BinPerp` and `BybitPerp`.
+    pub async fn predicted_fundings(&self) -> Result<Vec<(String, VenueFundings)>> {
+        let input = InfoRequest::PredictedFundings;
+        self.send_info_request(input).await
+    }
+
     pub async fn recent_trades(&self, coin: String) -> Result<Vec<RecentTradesResponse>> {
         let input = InfoRequest::RecentTrades { coin };
         self.send_info_request(input).await
@@ -278,8 +755,8 @@ impl InfoClient {
         &self,
         coin: String,
         interval: String,
-        start_time: u64,
-        end_time: u64,
+        start_time: Timestamp,
+        end_time: Timestamp,
     ) -> Result<Vec<CandlesSnapshotResponse>> {
         let input = InfoRequest::CandleSnapshot {
             req: CandleSnapshotRequest {
@@ -301,6 +778,20 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
+    /// The status of the order placed with `cloid`, `"unknownOid"` if none was.
+    #[cfg(feature = "exchange")]
+    pub async fn query_order_by_cloid(
+        &self,
+        address: Address,
+        cloid: uuid::Uuid,
+    ) -> Result<OrderStatusResponse> {
+        let input = InfoRequest::OrderStatusByCloid {
+            user: address,
+            cloid: crate::helpers::uuid_to_hex_string(cloid),
+        };
+        self.send_info_request(input).await
+    }
+
     pub async fn query_referral_state(&self, address: Address) -> Result<ReferralResponse> {
         let input = InfoRequest::Referral { user: address };
         self.send_info_request(input).await
@@ -311,8 +802,107 @@ impl InfoClient {
         self.send_info_request(input).await
     }
 
-    pub async fn active_asset_data(&self, user: Address, coin: String) -> Result<ActiveAssetDataResponse> {
+    pub async fn active_asset_data(
+        &self,
+        user: Address,
+        coin: String,
+    ) -> Result<ActiveAssetDataResponse> {
         let input = InfoRequest::ActiveAssetData { user, coin };
         self.send_info_request(input).await
     }
+
+    pub async fn user_rate_limit(&self, user: Address) -> Result<UserRateLimitResponse> {
+        let input = InfoRequest::UserRateLimit { user };
+        self.send_info_request(input).await
+    }
+
+    pub async fn user_role(&self, user: Address) -> Result<UserRole> {
+        let input = InfoRequest::UserRole { user };
+        self.send_info_request(input).await
+    }
+
+    /// Sub-accounts of the master account `user`, with their perp account state.
+    pub async fn sub_accounts(&self, user: Address) -> Result<Vec<SubAccount>> {
+        let input = InfoRequest::SubAccounts { user };
+        // Accounts without sub-accounts get null rather than an empty list
+        let sub_accounts: Option<Vec<SubAccount>> = self.send_info_request(input).await?;
+        Ok(sub_accounts.unwrap_or_default())
+    }
+
+    /// Agents approved for `user`, with when their approval expires.
+    pub async fn extra_agents(&self, user: Address) -> Result<Vec<ExtraAgent>> {
+        let input = InfoRequest::ExtraAgents { user };
+        self.send_info_request(input).await
+    }
+
+    /// Highest fee `user` allows `builder` to charge, in tenths of a basis point.
+    pub async fn max_builder_fee(&self, user: Address, builder: Address) -> Result<u64> {
+        let input = InfoRequest::MaxBuilderFee { user, builder };
+        self.send_info_request(input).await
+    }
+
+    /// The vault's leader and followers, with `user`'s own stake as `follower_state` if given.
+    pub async fn vault_details(
+        &self,
+        vault_address: Address,
+        user: Option<Address>,
+    ) -> Result<VaultDetailsResponse> {
+        let input = InfoRequest::VaultDetails {
+            vault_address,
+            user,
+        };
+        self.send_info_request(input).await
+    }
+}
+
+/// Configures an `InfoClient` without a long positional constructor.
+#[derive(Debug, Default)]
+pub struct InfoClientBuilder {
+    http: HttpOptions,
+    reconnect: bool,
+    ws_url: Option<String>,
+    book_coalescing: Option<std::time::Duration>,
+    #[cfg(feature = "ws")]
+    bandwidth_budget: Option<crate::BandwidthBudget>,
+}
+
+impl InfoClientBuilder {
+    http_options_setters!();
+
+    /// Reconnect websocket subscriptions after disconnects.
+    pub fn reconnect(mut self, reconnect: bool) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+
+    /// Websocket endpoint, by default the one matching the base URL.
+    pub fn ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.ws_url = Some(ws_url.into());
+        self
+    }
+
+    /// See `InfoClient::with_book_coalescing`.
+    pub fn book_coalescing(mut self, window: std::time::Duration) -> Self {
+        self.book_coalescing = Some(window);
+        self
+    }
+
+    /// See `InfoClient::with_bandwidth_budget`.
+    #[cfg(feature = "ws")]
+    pub fn bandwidth_budget(mut self, budget: crate::BandwidthBudget) -> Self {
+        self.bandwidth_budget = Some(budget);
+        self
+    }
+
+    pub fn build(self) -> Result<InfoClient> {
+        let ws_url = self.ws_url.unwrap_or_else(|| self.http.ws_url());
+        let mut info =
+            InfoClient::with_http_client(self.http.build()?, self.reconnect).with_ws_url(ws_url);
+        info.book_coalescing = self.book_coalescing;
+        #[cfg(feature = "ws")]
+        {
+            info.bandwidth_budget = self.bandwidth_budget;
+        }
+        Ok(info)
+    }
 }
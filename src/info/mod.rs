@@ -1,6 +1,20 @@
+mod candle_check;
+mod checkpoint;
+mod deposit;
+mod history;
 pub(super) mod info_client;
+mod permissions;
 mod response_structs;
 mod sub_structs;
 
+pub use candle_check::{candle_interval_ms, find_candle_gaps, CandleGap, CandleSeries};
+#[cfg(feature = "journal")]
+pub use checkpoint::SqliteCheckpointStore;
+pub use checkpoint::{Checkpoint, CheckpointStore, FileCheckpointStore};
+pub use deposit::{
+    BridgeTransfer, CreditedDeposit, DepositMonitor, MAINNET_BRIDGE_ADDRESS, TESTNET_BRIDGE_ADDRESS,
+};
+pub use history::{HistoryItem, HistoryStream};
+pub use permissions::SignerCapabilities;
 pub use response_structs::*;
 pub use sub_structs::*;
@@ -0,0 +1,148 @@
+use alloy::primitives::Address;
+
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, Error, ExtraAgent, InfoClient, Timestamp, UserRole,
+};
+
+/// What a signer can do on the exchange, checked before its first live order so a wrong key,
+/// an expired agent or a missing builder fee approval fails at startup rather than on the
+/// first action.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignerCapabilities {
+    pub signer: Address,
+    pub role: UserRole,
+    /// Account the signer trades for: the approving account for an agent, itself for a user
+    pub account: Option<Address>,
+    /// Name the agent was approved under, empty for the unnamed agent
+    pub agent_name: Option<String>,
+    /// When the agent's approval expires, in milliseconds
+    pub valid_until: Option<Timestamp>,
+    pub expired: bool,
+    /// Highest fee the account allows the requested builder to charge, in tenths of a basis
+    /// point, 0 when it was never approved
+    pub max_builder_fee: Option<u64>,
+}
+
+impl SignerCapabilities {
+    /// Looks up the role of `signer` and, for an agent, the account that approved it and
+    /// whether that approval has expired. With a `builder`, also fetches the fee the account
+    /// approved for it.
+    pub async fn check(
+        info: &InfoClient,
+        signer: Address,
+        builder: Option<Address>,
+    ) -> Result<SignerCapabilities> {
+        let role = info.user_role(signer).await?;
+        let agents = match role {
+            UserRole::Agent { user } => info.extra_agents(user).await?,
+            _ => Vec::new(),
+        };
+        let mut capabilities =
+            SignerCapabilities::from_role(signer, role, &agents, now_timestamp_ms());
+        if let (Some(account), Some(builder)) = (capabilities.account, builder) {
+            capabilities.max_builder_fee = Some(info.max_builder_fee(account, builder).await?);
+        }
+        Ok(capabilities)
+    }
+
+    fn from_role(
+        signer: Address,
+        role: UserRole,
+        agents: &[ExtraAgent],
+        now: u64,
+    ) -> SignerCapabilities {
+        let account = match role {
+            UserRole::User => Some(signer),
+            UserRole::Agent { user } => Some(user),
+            UserRole::Missing | UserRole::Vault | UserRole::SubAccount { .. } => None,
+        };
+        let agent = agents.iter().find(|agent| agent.address == signer);
+        let valid_until = agent.map(|agent| agent.valid_until);
+        SignerCapabilities {
+            signer,
+            role,
+            account,
+            agent_name: agent.map(|agent| agent.name.clone()),
+            valid_until,
+            expired: valid_until.is_some_and(|valid_until| valid_until <= now),
+            max_builder_fee: None,
+        }
+    }
+
+    /// Whether the signer can place and cancel orders for `account`.
+    pub fn can_trade(&self) -> bool {
+        self.account.is_some() && !self.expired
+    }
+
+    /// Whether the signer can sign transfers, withdrawals and approvals, which agents cannot.
+    pub fn can_transfer(&self) -> bool {
+        self.role == UserRole::User
+    }
+
+    /// Whether orders can carry a builder fee of `fee` tenths of a basis point.
+    pub fn builder_fee_approved(&self, fee: u64) -> bool {
+        self.max_builder_fee.is_some_and(|max| fee <= max)
+    }
+
+    /// Errors describing the first missing permission, if any, for trading with an optional
+    /// builder fee of `builder_fee` tenths of a basis point.
+    pub fn ensure_can_trade(&self, builder_fee: Option<u64>) -> Result<()> {
+        let problem = match (&self.role, self.account) {
+            (UserRole::Missing, _) => {
+                "is unknown to the exchange, not a funded account nor an approved agent".to_string()
+            }
+            (UserRole::Vault, _) => "is a vault, which trades through its leader".to_string(),
+            (UserRole::SubAccount { master }, _) => {
+                format!("is a sub-account, which trades through {master} with a vault address")
+            }
+            _ if self.expired => format!(
+                "is an agent whose approval expired at {}",
+                self.valid_until.unwrap_or_default()
+            ),
+            (_, Some(account))
+                if builder_fee.is_some_and(|fee| !self.builder_fee_approved(fee)) =>
+            {
+                format!(
+                    "trades for {account}, which approved a builder fee of at most {}",
+                    self.max_builder_fee.unwrap_or_default()
+                )
+            }
+            _ => return Ok(()),
+        };
+        Err(Error::Wallet(format!("signer {} {problem}", self.signer)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_agent_capabilities() {
+        let signer = Address::repeat_byte(2);
+        let role: UserRole = serde_json::from_str(&format!(
+            r#"{{"role":"agent","data":{{"user":"{}"}}}}"#,
+            Address::repeat_byte(1)
+        ))
+        .unwrap();
+        let agents: Vec<ExtraAgent> = serde_json::from_str(&format!(
+            r#"[{{"address":"{signer}","name":"bot","validUntil":2000}}]"#
+        ))
+        .unwrap();
+
+        let capabilities = SignerCapabilities::from_role(signer, role.clone(), &agents, 1000);
+        assert_eq!(capabilities.account, Some(Address::repeat_byte(1)));
+        assert_eq!(capabilities.agent_name.as_deref(), Some("bot"));
+        assert!(capabilities.can_trade() && !capabilities.can_transfer());
+        assert!(capabilities.ensure_can_trade(None).is_ok());
+        assert!(capabilities.ensure_can_trade(Some(10)).is_err());
+
+        let expired = SignerCapabilities::from_role(signer, role, &agents, 3000);
+        assert!(!expired.can_trade());
+        assert!(expired.ensure_can_trade(None).is_err());
+
+        let missing: UserRole = serde_json::from_str(r#"{"role":"missing"}"#).unwrap();
+        let capabilities = SignerCapabilities::from_role(signer, missing, &[], 1000);
+        assert!(!capabilities.can_trade());
+    }
+}
@@ -1,15 +1,16 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use alloy::primitives::Address;
 
 use crate::{
-    info::{AssetPosition, Level, MarginSummary},
-    DailyUserVlm, Delta, FeeSchedule, Leverage, OrderInfo, Referrer, ReferrerState,
-    UserTokenBalance,
+    info::{AssetPosition, FillLiquidation, Level, MarginSummary},
+    DailyUserVlm, Delta, FeeSchedule, FillDirection, Leverage, OrderInfo, OrderTag, Referrer,
+    ReferrerState, Side, Timestamp, UserTokenBalance,
 };
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserStateResponse {
     pub asset_positions: Vec<AssetPosition>,
     pub cross_margin_summary: MarginSummary,
@@ -17,13 +18,30 @@ pub struct UserStateResponse {
     pub withdrawable: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[non_exhaustive]
 pub struct UserTokenBalanceResponse {
     pub balances: Vec<UserTokenBalance>,
 }
 
-#[derive(Deserialize, Debug)]
+/// An account's perp state, spot balances, resting orders and recent fills, fetched together by
+/// `InfoClient::account_snapshot`.
+#[derive(Debug, Serialize)]
+#[non_exhaustive]
+pub struct AccountSnapshot {
+    pub user: Address,
+    /// When the requests were sent, in ms
+    pub time: Timestamp,
+    pub state: UserStateResponse,
+    pub balances: Vec<UserTokenBalance>,
+    pub open_orders: Vec<OpenOrdersResponse>,
+    /// The most recent fills, up to 2000
+    pub fills: Vec<UserFillsResponse>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserFeesResponse {
     pub active_referral_discount: String,
     pub daily_user_vlm: Vec<DailyUserVlm>,
@@ -32,79 +50,124 @@ pub struct UserFeesResponse {
     pub user_cross_rate: String,
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct OpenOrdersResponse {
     pub coin: String,
     pub limit_px: String,
     pub oid: u64,
-    pub side: String,
+    pub side: Side,
     pub sz: String,
-    pub timestamp: u64,
+    pub timestamp: Timestamp,
     pub cloid: Option<String>,
 }
 
-#[derive(serde::Deserialize, Debug)]
+impl OpenOrdersResponse {
+    /// Tag of the order's cloid, see `OrderTag`.
+    pub fn tag(&self) -> Option<OrderTag> {
+        self.cloid.as_deref().and_then(OrderTag::from_cloid)
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserFillsResponse {
     pub closed_pnl: String,
     pub coin: String,
     pub crossed: bool,
-    pub dir: String,
+    pub dir: FillDirection,
     pub hash: String,
     pub oid: u64,
+    pub cloid: Option<String>,
     pub px: String,
-    pub side: String,
+    pub side: Side,
     pub start_position: String,
     pub sz: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub fee: String,
     pub tid: u64,
     pub fee_token: String,
     pub twap_id: Option<u64>,
+    /// Part of `fee` paid to the builder, when the order had one
+    pub builder_fee: Option<String>,
+    pub liquidation: Option<FillLiquidation>,
+}
+
+impl UserFillsResponse {
+    pub fn is_liquidation(&self) -> bool {
+        self.liquidation.is_some()
+    }
+
+    /// Tag of the order's cloid, see `OrderTag`.
+    pub fn tag(&self) -> Option<OrderTag> {
+        self.cloid.as_deref().and_then(OrderTag::from_cloid)
+    }
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct FundingHistoryResponse {
     pub coin: String,
     pub funding_rate: String,
     pub premium: String,
-    pub time: u64,
+    pub time: Timestamp,
 }
 
-#[derive(Deserialize, Debug)]
+/// Next funding on one venue, from `predictedFundings`.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct PredictedFunding {
+    pub funding_rate: String,
+    pub next_funding_time: Timestamp,
+    /// Hours between payments, 1 on Hyperliquid and usually 8 elsewhere
+    #[serde(default)]
+    pub funding_interval_hours: Option<u32>,
+}
+
+/// Predicted fundings of one coin as `(venue, funding)` pairs, `None` where a venue does not
+/// list the coin.
+pub type VenueFundings = Vec<(String, Option<PredictedFunding>)>;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[non_exhaustive]
 pub struct UserFundingResponse {
-    pub time: u64,
+    pub time: Timestamp,
     pub hash: String,
     pub delta: Delta,
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct L2SnapshotResponse {
     pub coin: String,
     pub levels: Vec<Vec<Level>>,
-    pub time: u64,
+    pub time: Timestamp,
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct RecentTradesResponse {
     pub coin: String,
-    pub side: String,
+    pub side: Side,
     pub px: String,
     pub sz: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub hash: String,
 }
 
-#[derive(serde::Deserialize, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+#[non_exhaustive]
 pub struct CandlesSnapshotResponse {
     #[serde(rename = "t")]
-    pub time_open: u64,
+    pub time_open: Timestamp,
     #[serde(rename = "T")]
-    pub time_close: u64,
+    pub time_close: Timestamp,
     #[serde(rename = "s")]
     pub coin: String,
     #[serde(rename = "i")]
@@ -123,7 +186,8 @@ pub struct CandlesSnapshotResponse {
     pub num_trades: u64,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[non_exhaustive]
 pub struct OrderStatusResponse {
     pub status: String,
     /// `None` if the order is not found
@@ -131,8 +195,9 @@ pub struct OrderStatusResponse {
     pub order: Option<OrderInfo>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ReferralResponse {
     pub referred_by: Option<Referrer>,
     pub cum_vlm: String,
@@ -141,8 +206,9 @@ pub struct ReferralResponse {
     pub referrer_state: ReferrerState,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ActiveAssetDataResponse {
     pub user: Address,
     pub coin: String,
@@ -151,3 +217,92 @@ pub struct ActiveAssetDataResponse {
     pub available_to_trade: Vec<String>,
     pub mark_px: String,
 }
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct UserRateLimitResponse {
+    pub cum_vlm: String,
+    pub n_requests_used: u64,
+    pub n_requests_cap: u64,
+}
+
+/// What an address is to the exchange, from `userRole`.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "role", content = "data", rename_all = "camelCase")]
+pub enum UserRole {
+    /// Unknown to the exchange, never funded nor approved as an agent
+    Missing,
+    User,
+    /// An API wallet signing for `user`
+    Agent {
+        user: Address,
+    },
+    Vault,
+    SubAccount {
+        master: Address,
+    },
+}
+
+/// A sub-account of a master account, from `subAccounts`.
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct SubAccount {
+    pub name: String,
+    pub sub_account_user: Address,
+    pub master: Address,
+    pub clearinghouse_state: UserStateResponse,
+}
+
+/// An agent approved for an account, from `extraAgents`.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtraAgent {
+    pub address: Address,
+    pub name: String,
+    /// When the approval expires, in milliseconds
+    pub valid_until: Timestamp,
+}
+
+/// A vault's leader, followers and limits, from `vaultDetails`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct VaultDetailsResponse {
+    pub name: String,
+    pub vault_address: Address,
+    pub leader: Address,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub apr: f64,
+    /// The leader's share of the vault's equity
+    pub leader_fraction: f64,
+    /// The leader's share of follower profits
+    pub leader_commission: f64,
+    pub followers: Vec<VaultFollower>,
+    /// State of the `user` the details were requested for, if they follow the vault
+    pub follower_state: Option<VaultFollower>,
+    /// USDC the leader can currently distribute to followers
+    pub max_distributable: f64,
+    pub max_withdrawable: f64,
+    pub is_closed: bool,
+    #[serde(default)]
+    pub allow_deposits: bool,
+}
+
+/// One depositor's stake in a vault.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct VaultFollower {
+    pub user: Address,
+    pub vault_equity: String,
+    pub pnl: String,
+    pub all_time_pnl: String,
+    pub days_following: u64,
+    pub vault_entry_time: Timestamp,
+    /// Until when, in ms, deposits cannot be withdrawn
+    pub lockup_until: Option<Timestamp>,
+}
@@ -1,8 +1,11 @@
 use alloy::primitives::Address;
 use serde::{Deserialize, Serialize};
 
+use crate::{LiquidationMethod, Side, Timestamp};
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct Leverage {
     #[serde(rename = "type")]
     pub type_string: String,
@@ -10,16 +13,18 @@ pub struct Leverage {
     pub raw_usd: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct CumulativeFunding {
     pub all_time: String,
     pub since_open: String,
     pub since_change: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct PositionData {
     pub coin: String,
     pub entry_px: Option<String>,
@@ -34,15 +39,17 @@ pub struct PositionData {
     pub cum_funding: CumulativeFunding,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[non_exhaustive]
 pub struct AssetPosition {
     pub position: PositionData,
     #[serde(rename = "type")]
     pub type_string: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct MarginSummary {
     pub account_value: String,
     pub total_margin_used: String,
@@ -50,16 +57,18 @@ pub struct MarginSummary {
     pub total_raw_usd: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct Level {
     pub n: u64,
     pub px: String,
     pub sz: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct Delta {
     #[serde(rename = "type")]
     pub type_string: String,
@@ -69,8 +78,9 @@ pub struct Delta {
     pub funding_rate: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct DailyUserVlm {
     pub date: String,
     pub exchange: String,
@@ -78,8 +88,9 @@ pub struct DailyUserVlm {
     pub user_cross: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct FeeSchedule {
     pub add: String,
     pub cross: String,
@@ -87,29 +98,33 @@ pub struct FeeSchedule {
     pub tiers: Tiers,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
+#[non_exhaustive]
 pub struct Tiers {
     pub mm: Vec<Mm>,
     pub vip: Vec<Vip>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct Mm {
     pub add: String,
     pub maker_fraction_cutoff: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct Vip {
     pub add: String,
     pub cross: String,
     pub ntl_cutoff: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserTokenBalance {
     pub coin: String,
     pub hold: String,
@@ -117,23 +132,25 @@ pub struct UserTokenBalance {
     pub entry_ntl: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct OrderInfo {
     pub order: BasicOrderInfo,
     pub status: String,
-    pub status_timestamp: u64,
+    pub status_timestamp: Timestamp,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct BasicOrderInfo {
     pub coin: String,
-    pub side: String,
+    pub side: Side,
     pub limit_px: String,
     pub sz: String,
     pub oid: u64,
-    pub timestamp: u64,
+    pub timestamp: Timestamp,
     pub trigger_condition: String,
     pub is_trigger: bool,
     pub trigger_px: String,
@@ -145,22 +162,36 @@ pub struct BasicOrderInfo {
     pub cloid: Option<String>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct Referrer {
     pub referrer: Address,
     pub code: String,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ReferrerState {
     pub stage: String,
     pub data: ReferrerData,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ReferrerData {
     pub required: String,
 }
+
+/// Set on fills that came from a liquidation, for the liquidated account and the liquidator.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct FillLiquidation {
+    /// Absent for the liquidated account's own fills
+    pub liquidated_user: Option<Address>,
+    pub mark_px: String,
+    pub method: LiquidationMethod,
+}
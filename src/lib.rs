@@ -1,21 +1,186 @@
 #![deny(unreachable_pub)]
+mod analytics;
+#[cfg(any(feature = "data", feature = "aws-secrets"))]
+mod aws;
+#[cfg(feature = "backtest")]
+mod backtest;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+#[cfg(feature = "exchange")]
+mod client;
+mod clock;
+#[cfg(feature = "config")]
+mod config;
 mod consts;
+#[cfg(feature = "data")]
+mod data;
+#[cfg(feature = "exchange")]
 mod eip712;
 mod errors;
+#[cfg(feature = "evm")]
+mod evm;
+#[cfg(feature = "exchange")]
 mod exchange;
 mod helpers;
 mod info;
+#[cfg(all(feature = "exchange", feature = "ws"))]
 mod market_maker;
 mod meta;
+#[cfg(feature = "meta-cache")]
+mod meta_cache;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod notify;
+#[cfg(feature = "otel")]
+pub mod otel;
 mod prelude;
+mod reference;
 mod req;
+mod risk;
+mod rt;
+#[cfg(feature = "exchange")]
+mod scenarios;
+mod secrets;
+#[cfg(feature = "exchange")]
 mod signature;
+mod trading;
+mod types;
 mod ws;
-pub use consts::{EPSILON, LOCAL_API_URL, MAINNET_API_URL, TESTNET_API_URL};
+#[cfg(feature = "arrow")]
+pub use analytics::{
+    candles_record_batch, fills_record_batch, funding_history_record_batch, l2_books_record_batch,
+};
+pub use analytics::{
+    fetch_funding_candles, fetch_ledger_updates, fetch_user_fills, fetch_user_funding,
+    funding_candles, write_builder_revenue_csv, write_fills_csv, write_funding_csv,
+    write_ledger_csv, write_sessions_csv, BasisAlert, BasisCrossing, BasisMonitor, BasisSummary,
+    BookDiffDecoder, BookDiffEncoder, BookReconstructor, BookUpdate, BreakEven,
+    BreakEvenCalculator, BreakEvenCapture, BuilderRevenue, BuilderRevenueReport, CandleAggregator,
+    CoinPnl, CoinToxicity, FeeBucket, FeeRates, FeeReport, FeeTierCheck, FillMarkout,
+    FundingSeries, ImpactSizing, L2BookDiff, LedgerRow, Liquidity, LotMethod, MarketSample,
+    MarketStatsCollector, MarkoutAnalyzer, MarkoutStats, OpenLot, OrderBook, PnlEngine, RateCandle,
+    RealizedLot, SessionReport, SideDiff, SpreadStats, SpreadSummary, ToxicityReport,
+};
+#[cfg(any(feature = "data", feature = "aws-secrets"))]
+pub use aws::AwsCredentials;
+#[cfg(feature = "backtest")]
+pub use backtest::{BacktestConfig, BacktestReport, Backtester, EquityPoint};
+#[cfg(feature = "exchange")]
+pub use client::{HyperliquidClient, HyperliquidClientBuilder};
+pub use clock::{clock, set_clock, Clock, SimClock, SleepFuture, SystemClock};
+#[cfg(feature = "config")]
+pub use config::{
+    HttpConfig, KeySource, MarketMakerConfig, Network, RateLimitConfig, SdkConfig, StrategyConfig,
+};
+pub use consts::{
+    ClientDefaults, DEFAULT_SLIPPAGE, EPSILON, LOCAL_API_URL, MAINNET_API_URL, TESTNET_API_URL,
+};
+#[cfg(feature = "data")]
+pub use data::*;
 pub use errors::Error;
+#[cfg(feature = "evm")]
+pub use evm::{
+    evm_px_to_float, CoreWriterAction, CoreWriterTif, EvmClient, EvmDelegatorSummary, EvmPosition,
+    EvmSpotBalance, EvmVaultEquity, CORE_WRITER_ADDRESS, MAINNET_EVM_RPC_URL, TESTNET_EVM_RPC_URL,
+};
+#[cfg(feature = "exchange")]
 pub use exchange::*;
-pub use helpers::{bps_diff, truncate_float, BaseUrl};
+pub use helpers::{
+    apply_bps, bps_diff, bps_diff_signed, lot_size, offset_px_bps, price_tick_size, round_to_tick,
+    truncate_float, BaseUrl, RoundingMode,
+};
 pub use info::{info_client::*, *};
+#[cfg(all(feature = "exchange", feature = "ws"))]
 pub use market_maker::{MarketMaker, MarketMakerInput, MarketMakerRestingOrder};
-pub use meta::{AssetContext, AssetMeta, Meta, MetaAndAssetCtxs, SpotAssetMeta, SpotMeta};
+pub use meta::{
+    AssetContext, AssetMeta, MarginTableMeta, MarginTierMeta, Meta, MetaAndAssetCtxs,
+    SpotAssetMeta, SpotMeta,
+};
+#[cfg(feature = "meta-cache")]
+pub use meta_cache::{CachedMeta, MetaCache, META_CACHE_VERSION};
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+pub use notify::{Alert, AlertKind, Notifier, Webhook, WebhookFormat};
+#[cfg(feature = "reference")]
+pub use reference::{BinanceReference, CoinbaseReference};
+pub use reference::{
+    ReferenceGuard, ReferencePrice, ReferencePriceSource, ReferencePrices, ReferenceViolation,
+};
+pub use req::{
+    schema_drift, with_cancellation, with_timeout, CancellationToken, DriftIssue, DriftKind,
+    EndpointHealth, EndpointPool, FailoverConfig, HttpClient, LatencyRoutingConfig, ProxyConfig,
+    RateLimitMode, RateLimiter, RecordedEntry, Recorder, Recording, Replayer, RequestCounts,
+    RequestLog, RequestLogger, RequestStats, RequestStatsSnapshot, RetryPolicy, SchemaCheck,
+    SchemaDrift, Throttle, ThrottleState, Timeouts, ADDRESS_INITIAL_BUFFER, IP_WEIGHT_PER_MINUTE,
+    RATE_LIMITED_COOLDOWN,
+};
+#[cfg(feature = "exchange")]
+pub use req::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+pub use risk::{
+    AccountMargin, AccountSummary, ActivityAnomaly, ActivityMonitor, AnomalyKind, BookGuard,
+    BookViolation, Capability, CapabilityHealth, CoinMarginForecast, DegradationMode,
+    DegradationSupervisor, DegradationTransition, DrawdownBreach, EquitySnapshot, EquityTracker,
+    ExchangeMonitor, ExchangeStatus, FleetMonitor, FleetTotals, MarginCalculator, MarginForecast,
+    MarginForecaster, MarginTable, MarginTier, NodeConsistencyChecker, NodeConsistencyReport,
+    NodeDivergence, PendingOrder, PortfolioRisk, PositionInput, PositionMargin, StressResult,
+    StressScenario, ValueAtRisk,
+};
+#[cfg(feature = "exchange")]
+pub use risk::{
+    IsolatedMarginConfig, IsolatedMarginKeeper, LiquidationAlert, LiquidationThreshold,
+    LiquidationWatchdog, MarginAlert, OrderGuard, PriceBandPolicy, RiskEngine, RiskLimits,
+    RiskViolation, TopUp,
+};
+#[cfg(all(
+    any(feature = "smol", feature = "async-std"),
+    not(target_arch = "wasm32")
+))]
+pub use rt::compat;
+#[cfg(all(feature = "async-std", not(target_arch = "wasm32")))]
+pub use rt::AsyncStdRuntime;
+pub use rt::MaybeSend;
+#[cfg(all(feature = "smol", not(target_arch = "wasm32")))]
+pub use rt::SmolRuntime;
+#[cfg(not(target_arch = "wasm32"))]
+pub use rt::{set_runtime, Runtime, TaskFuture, TokioRuntime};
+#[cfg(feature = "exchange")]
+pub use scenarios::{Scenario, ScenarioReport, ScenarioStep, StepOutcome, StepResult};
+#[cfg(feature = "exchange")]
+pub use secrets::load_wallet;
+#[cfg(feature = "aws-secrets")]
+pub use secrets::AwsSecretsManager;
+#[cfg(feature = "vault")]
+pub use secrets::VaultSecrets;
+pub use secrets::{EnvSecrets, FileSecrets, SecretFuture, SecretsProvider};
+#[cfg(feature = "exchange")]
+pub use signature::SignerId;
+#[cfg(feature = "exchange")]
+pub use trading::{
+    forecast_funding, funding_carry, reconcile, AccountRouter, AllocationPlan, AllocationTransfer,
+    CarryOptions, CatchUp, ChildAllocation, ChildOrderStyle, CoinQuoteConfig, CopyTradeConfig,
+    CopyTrader, DeltaHedgeConfig, DeltaHedger, DeltaNeutralConfig, DeltaNeutralExecutor,
+    Discrepancy, DustBalance, DustConversion, DustSweep, DustSweepConfig, EventStrategy,
+    ExecutionAlgo, ExecutionConfig, ExecutionProgress, ExecutionReport, ExecutionSchedule,
+    ExecutionStats, FairValue, FollowerEquity, FundingAction, FundingCarry, FundingForecast,
+    FundingGuard, FundingGuardConfig, GridConfig, GridLevel, GridRebalance, GridState, GridTrader,
+    IcebergConfig, IcebergOrder, LegFill, LinearSkew, ManagedOrder, MidFairValue, MultiMarketMaker,
+    MultiMarketMakerConfig, OcoLeg, OcoManager, OcoPair, OcoState, OrderEvent, OrderManager,
+    OrderState, OwnRestingOrder, QueuePosition, Quote, QuoteLevel, QuotePlan, QuoteSkew, QuoteSync,
+    QuoteSyncStatuses, QuoteTarget, RebalanceConfig, RebalanceExecution, RebalancePlan,
+    RebalanceTrade, ReconcileOptions, ReconcileReport, RecurringAction, RecurringJob,
+    RecurringJobState, RecurringSchedule, RecurringScheduler, ResidualPolicy, RestingQuote,
+    RoutePlan, RoutedAccount, RoutedChild, RoutedOrder, SelfTradeBook, SelfTradePolicy,
+    ShadowComparator, ShadowReport, Skew, Strategy, StrategyContext, StrategyRuntime,
+    SubAccountAllocator, SubAccountBalance, SubmitOnce, SubmitOutcome, TradeLeg, TrailDistance,
+    TrailPriceSource, TrailingStop, TrailingStopConfig, TrailingStopState, TwoLegConfig,
+    TwoLegExecutor, TwoLegOutcome, VaultOperator, VaultState, VenueFunding,
+};
+pub use trading::{
+    JitterConfig, Position, PositionDrift, PositionSnapshot, PositionTracker, QuoteJitter,
+};
+#[cfg(feature = "journal")]
+pub use trading::{Journal, JournalEntry, JournalKind};
+pub use types::{
+    FillDirection, LiquidationMethod, OrderTag, Side, Tif, Timestamp, TriggerCondition,
+};
 pub use ws::*;
@@ -1,11 +1,11 @@
 use alloy::{primitives::Address, signers::local::PrivateKeySigner};
-use log::{error, info};
 use tokio::sync::mpsc::unbounded_channel;
+use tracing::{error, info, warn};
 
 use crate::{
     bps_diff, truncate_float, BaseUrl, ClientCancelRequest, ClientLimit, ClientOrder,
     ClientOrderRequest, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, InfoClient,
-    Message, Subscription, UserData, EPSILON,
+    Message, ReferenceGuard, Subscription, Tif, UserData, EPSILON,
 };
 #[derive(Debug)]
 pub struct MarketMakerRestingOrder {
@@ -40,6 +40,8 @@ pub struct MarketMaker {
     pub info_client: InfoClient,
     pub exchange_client: ExchangeClient,
     pub user_address: Address,
+    /// Quotes are left as they are while the mid is out of line with the reference
+    pub reference: Option<ReferenceGuard>,
 }
 
 impl MarketMaker {
@@ -74,9 +76,15 @@ impl MarketMaker {
             info_client,
             exchange_client,
             user_address,
+            reference: None,
         }
     }
 
+    pub fn with_reference(mut self, guard: ReferenceGuard) -> Self {
+        self.reference = Some(guard);
+        self
+    }
+
     pub async fn start(&mut self) {
         let (sender, mut receiver) = unbounded_channel();
 
@@ -125,7 +133,7 @@ impl MarketMaker {
                         for fill in fills {
                             let amount: f64 = fill.sz.parse().unwrap();
                             // Update our resting positions whenever we see a fill
-                            if fill.side.eq("B") {
+                            if fill.side.is_buy() {
                                 self.cur_position += amount;
                                 self.lower_resting.position -= amount;
                                 info!("Fill: bought {amount} {}", self.asset.clone());
@@ -197,9 +205,7 @@ impl MarketMaker {
                     limit_px: price,
                     sz: amount,
                     cloid: None,
-                    order_type: ClientOrder::Limit(ClientLimit {
-                        tif: "Gtc".to_string(),
-                    }),
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
                 },
                 None,
             )
@@ -238,6 +244,12 @@ impl MarketMaker {
     }
 
     async fn potentially_update(&mut self) {
+        if let Some(guard) = &self.reference {
+            if let Err(violation) = guard.check(&self.asset, self.latest_mid_price) {
+                warn!("Not updating quotes: {violation}");
+                return;
+            }
+        }
         let half_spread = (self.latest_mid_price * self.half_spread as f64) / 10000.0;
         // Determine prices to target from the half spread
         let (lower_price, upper_price) = (
@@ -1,14 +1,36 @@
 use std::collections::HashMap;
 
 use alloy::primitives::B128;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct Meta {
     pub universe: Vec<AssetMeta>,
+    /// Margin tables by id, referenced by `AssetMeta::margin_table_id`
+    #[serde(rename = "marginTables", default)]
+    pub margin_tables: Vec<(u32, MarginTableMeta)>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct MarginTableMeta {
+    pub description: String,
+    pub margin_tiers: Vec<MarginTierMeta>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct MarginTierMeta {
+    /// Position notional from which the tier applies
+    pub lower_bound: String,
+    pub max_leverage: u32,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct SpotMeta {
     pub universe: Vec<SpotAssetMeta>,
     pub tokens: Vec<TokenInfo>,
@@ -45,22 +67,23 @@ impl SpotMeta {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum SpotMetaAndAssetCtxs {
     SpotMeta(SpotMeta),
     Context(Vec<SpotAssetContext>),
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum MetaAndAssetCtxs {
     Meta(Meta),
     Context(Vec<AssetContext>),
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct SpotAssetContext {
     pub day_ntl_vlm: String,
     pub mark_px: String,
@@ -70,8 +93,9 @@ pub struct SpotAssetContext {
     pub coin: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct AssetContext {
     pub day_ntl_vlm: String,
     pub funding: String,
@@ -84,18 +108,22 @@ pub struct AssetContext {
     pub prev_day_px: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct AssetMeta {
     pub name: String,
     pub sz_decimals: u32,
     pub max_leverage: usize,
     #[serde(default)]
     pub only_isolated: Option<bool>,
+    #[serde(default)]
+    pub margin_table_id: Option<u32>,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct SpotAssetMeta {
     pub tokens: [usize; 2],
     pub name: String,
@@ -103,8 +131,9 @@ pub struct SpotAssetMeta {
     pub is_canonical: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct TokenInfo {
     pub name: String,
     pub sz_decimals: u8,
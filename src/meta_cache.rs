@@ -0,0 +1,192 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{helpers::now_timestamp_ms, prelude::*, Error, InfoClient, Meta, SpotMeta};
+
+const MAGIC: &[u8; 4] = b"HLMC";
+
+/// Layout version of cache files. Files of another version, such as written before `Meta`
+/// gained a field, are ignored and replaced on the next fetch.
+pub const META_CACHE_VERSION: u16 = 1;
+
+/// Perp metadata with its margin tables and spot metadata, as saved by `MetaCache`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedMeta {
+    pub meta: Meta,
+    pub spot_meta: SpotMeta,
+    /// API the metadata was fetched from
+    pub base_url: String,
+    /// Milliseconds since the Unix epoch
+    pub saved_at: u64,
+}
+
+/// Perp and spot metadata kept in a compact MessagePack file, so latency-sensitive processes
+/// start without the `meta` and `spotMeta` round trips.
+///
+/// A file is only used if it has the current `META_CACHE_VERSION`, was fetched from the same
+/// API and, with `with_max_age`, is recent enough. Anything else, including a corrupt file, is
+/// a miss. Metadata changes when assets are listed, so long-running processes should still
+/// refresh it.
+#[derive(Clone, Debug)]
+pub struct MetaCache {
+    path: PathBuf,
+    max_age: Option<Duration>,
+}
+
+impl MetaCache {
+    pub fn new(path: impl AsRef<Path>) -> MetaCache {
+        MetaCache {
+            path: path.as_ref().to_path_buf(),
+            max_age: None,
+        }
+    }
+
+    /// Ignores files saved more than `max_age` ago.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The cached metadata of `base_url`, `None` on a miss.
+    pub fn load(&self, base_url: &str) -> Option<CachedMeta> {
+        let data = std::fs::read(&self.path).ok()?;
+        let cached = match decode(&data) {
+            Ok(cached) => cached,
+            Err(err) => {
+                warn!("Ignoring meta cache {}: {err}", self.path.display());
+                return None;
+            }
+        };
+        if cached.base_url != base_url {
+            debug!(
+                "Meta cache {} is for {}",
+                self.path.display(),
+                cached.base_url
+            );
+            return None;
+        }
+        let age = now_timestamp_ms().saturating_sub(cached.saved_at);
+        if self
+            .max_age
+            .is_some_and(|max_age| age > max_age.as_millis() as u64)
+        {
+            debug!("Meta cache {} is {age}ms old", self.path.display());
+            return None;
+        }
+        Some(cached)
+    }
+
+    /// Writes to a temporary file renamed over the cache, so a crash never leaves a partial
+    /// file.
+    pub fn save(&self, base_url: &str, meta: &Meta, spot_meta: &SpotMeta) -> Result<()> {
+        let cached = CachedMeta {
+            meta: meta.clone(),
+            spot_meta: spot_meta.clone(),
+            base_url: base_url.to_string(),
+            saved_at: now_timestamp_ms(),
+        };
+        let mut data = MAGIC.to_vec();
+        data.extend(META_CACHE_VERSION.to_le_bytes());
+        rmp_serde::encode::write(&mut data, &cached).map_err(|e| Error::RmpParse(e.to_string()))?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, data)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+
+    /// Metadata from the cache, or fetched with `info` and saved on a miss. Failing to save
+    /// is only logged.
+    pub async fn load_or_fetch(&self, info: &InfoClient) -> Result<(Meta, SpotMeta)> {
+        let base_url = &info.http_client.base_url;
+        if let Some(cached) = self.load(base_url) {
+            return Ok((cached.meta, cached.spot_meta));
+        }
+        let meta = info.meta().await?;
+        let spot_meta = info.spot_meta().await?;
+        if let Err(err) = self.save(base_url, &meta, &spot_meta) {
+            warn!("Could not save meta cache {}: {err}", self.path.display());
+        }
+        Ok((meta, spot_meta))
+    }
+}
+
+fn decode(data: &[u8]) -> Result<CachedMeta> {
+    let Some((header, body)) = data.split_at_checked(MAGIC.len() + 2) else {
+        return Err(Error::RmpParse("Truncated meta cache".to_string()));
+    };
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(Error::RmpParse("Not a meta cache".to_string()));
+    }
+    let version = u16::from_le_bytes([header[4], header[5]]);
+    if version != META_CACHE_VERSION {
+        return Err(Error::RmpParse(format!(
+            "Meta cache version {version}, expected {META_CACHE_VERSION}"
+        )));
+    }
+    rmp_serde::from_slice(body).map_err(|e| Error::RmpParse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_cache_round_trip_and_misses() {
+        let meta: Meta = serde_json::from_value(serde_json::json!({
+            "universe": [
+                {"name": "BTC", "szDecimals": 5, "maxLeverage": 40, "marginTableId": 56}
+            ],
+            "marginTables": [
+                [56, {"description": "tiered", "marginTiers": [
+                    {"lowerBound": "0.0", "maxLeverage": 40},
+                    {"lowerBound": "150000000.0", "maxLeverage": 20}
+                ]}]
+            ]
+        }))
+        .unwrap();
+        let spot_meta: SpotMeta = serde_json::from_value(serde_json::json!({
+            "universe": [{"tokens": [1, 0], "name": "PURR/USDC", "index": 0, "isCanonical": true}],
+            "tokens": [
+                {"name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0,
+                 "tokenId": "0x6d1e7cde53ba9467b783cb7c530ce054", "isCanonical": true},
+                {"name": "PURR", "szDecimals": 0, "weiDecimals": 5, "index": 1,
+                 "tokenId": "0xc1fb593aeffbeb02f85e0308e9956a90", "isCanonical": true}
+            ]
+        }))
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("meta_cache_{}.bin", std::process::id()));
+        let cache = MetaCache::new(&path);
+        assert!(cache.load("https://api.hyperliquid.xyz").is_none());
+
+        cache
+            .save("https://api.hyperliquid.xyz", &meta, &spot_meta)
+            .unwrap();
+        let cached = cache.load("https://api.hyperliquid.xyz").unwrap();
+        assert_eq!(cached.meta.universe[0].margin_table_id, Some(56));
+        assert_eq!(
+            cached.meta.margin_tables[0].1.margin_tiers[1].max_leverage,
+            20
+        );
+        assert_eq!(
+            cached.spot_meta.tokens[1].token_id,
+            spot_meta.tokens[1].token_id
+        );
+        assert!(cache.load("https://api.hyperliquid-testnet.xyz").is_none());
+
+        // Another layout version is a miss
+        let mut data = std::fs::read(cache.path()).unwrap();
+        data[4] = 0xff;
+        std::fs::write(cache.path(), data).unwrap();
+        assert!(cache.load("https://api.hyperliquid.xyz").is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
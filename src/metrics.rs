@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use prometheus::{Gauge, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry};
+use serde::Deserialize;
+
+use crate::{prelude::*, Error};
+
+/// Exchange actions whose round trip is recorded as order ack latency.
+const ORDER_ACTIONS: [&str; 6] = [
+    "order",
+    "modify",
+    "batchModify",
+    "cancel",
+    "cancelByCloid",
+    "twapOrder",
+];
+
+/// Latency buckets in seconds, from 5ms to 10s.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 7.5, 10.0,
+];
+
+/// Prometheus collectors for request latency by endpoint, order ack latency, websocket
+/// message rates and reconnects, rate-limit headroom, and errors.
+///
+/// Registered once into a caller-provided registry and shared between clients with
+/// `with_metrics` or the builders' `metrics` setter:
+///
+/// - `hyperliquid_request_duration_seconds{endpoint}`, per attempt, where `endpoint` is
+///   `info:<type>` or `exchange:<action>`
+/// - `hyperliquid_order_ack_duration_seconds{action}`, from first send to the exchange's
+///   response, including retries
+/// - `hyperliquid_errors_total{endpoint, kind}`, with `kind` one of `rate_limited`, `server`,
+///   `timeout`, `connect`, `other` or `rejected` for error statuses returned by the exchange
+/// - `hyperliquid_rate_limit_available_weight`, the IP weight left in the client's
+///   `RateLimiter`
+/// - `hyperliquid_ws_messages_total{channel}` and `hyperliquid_ws_reconnects_total`
+/// - `hyperliquid_ws_messages_shed_total{coin, action}`, with `action` `coalesced` or
+///   `dropped`, for market data shed by a `BandwidthBudget`
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    request_duration: HistogramVec,
+    order_ack_duration: HistogramVec,
+    errors: IntCounterVec,
+    rate_limit_available: Gauge,
+    ws_messages: IntCounterVec,
+    ws_reconnects: IntCounter,
+    ws_shed: IntCounterVec,
+}
+
+impl From<prometheus::Error> for Error {
+    fn from(err: prometheus::Error) -> Self {
+        Error::Metrics(err.to_string())
+    }
+}
+
+impl Metrics {
+    pub fn new(registry: &Registry) -> Result<Metrics> {
+        let latency = |name: &str, help: &str, label: &str| {
+            HistogramVec::new(
+                HistogramOpts::new(name, help)
+                    .namespace("hyperliquid")
+                    .buckets(LATENCY_BUCKETS.to_vec()),
+                &[label],
+            )
+        };
+        let metrics = Metrics {
+            request_duration: latency(
+                "request_duration_seconds",
+                "REST request latency per attempt",
+                "endpoint",
+            )?,
+            order_ack_duration: latency(
+                "order_ack_duration_seconds",
+                "Time from sending an order action to the exchange's response",
+                "action",
+            )?,
+            errors: IntCounterVec::new(
+                Opts::new("errors_total", "Failed requests and rejected actions")
+                    .namespace("hyperliquid"),
+                &["endpoint", "kind"],
+            )?,
+            rate_limit_available: Gauge::with_opts(
+                Opts::new(
+                    "rate_limit_available_weight",
+                    "IP weight left in the client-side rate limiter",
+                )
+                .namespace("hyperliquid"),
+            )?,
+            ws_messages: IntCounterVec::new(
+                Opts::new("ws_messages_total", "Websocket messages received")
+                    .namespace("hyperliquid"),
+                &["channel"],
+            )?,
+            ws_reconnects: IntCounter::with_opts(
+                Opts::new("ws_reconnects_total", "Websocket reconnections")
+                    .namespace("hyperliquid"),
+            )?,
+            ws_shed: IntCounterVec::new(
+                Opts::new(
+                    "ws_messages_shed_total",
+                    "Websocket market data coalesced or dropped over the bandwidth budget",
+                )
+                .namespace("hyperliquid"),
+                &["coin", "action"],
+            )?,
+        };
+        registry.register(Box::new(metrics.request_duration.clone()))?;
+        registry.register(Box::new(metrics.order_ack_duration.clone()))?;
+        registry.register(Box::new(metrics.errors.clone()))?;
+        registry.register(Box::new(metrics.rate_limit_available.clone()))?;
+        registry.register(Box::new(metrics.ws_messages.clone()))?;
+        registry.register(Box::new(metrics.ws_reconnects.clone()))?;
+        registry.register(Box::new(metrics.ws_shed.clone()))?;
+        Ok(metrics)
+    }
+
+    pub(crate) fn observe_request(&self, endpoint: &str, elapsed: Duration) {
+        self.request_duration
+            .with_label_values(&[endpoint])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records the full round trip of an exchange action if it places, modifies or cancels
+    /// orders.
+    pub(crate) fn observe_ack(&self, endpoint: &str, elapsed: Duration) {
+        if let Some(action) = endpoint
+            .strip_prefix("exchange:")
+            .filter(|action| ORDER_ACTIONS.contains(action))
+        {
+            self.order_ack_duration
+                .with_label_values(&[action])
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+
+    pub(crate) fn inc_error(&self, endpoint: &str, kind: &str) {
+        self.errors.with_label_values(&[endpoint, kind]).inc();
+    }
+
+    pub(crate) fn set_rate_limit_available(&self, weight: f64) {
+        self.rate_limit_available.set(weight);
+    }
+
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    pub(crate) fn inc_ws_message(&self, channel: &str) {
+        self.ws_messages.with_label_values(&[channel]).inc();
+    }
+
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    pub(crate) fn inc_ws_reconnect(&self) {
+        self.ws_reconnects.inc();
+    }
+
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    pub(crate) fn inc_ws_shed(&self, coin: &str, action: &str) {
+        self.ws_shed.with_label_values(&[coin, action]).inc();
+    }
+}
+
+#[derive(Deserialize)]
+struct TypeField {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RequestLabel {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    action: Option<TypeField>,
+}
+
+/// Endpoint label of a request body: the info request type or the exchange action type.
+pub(crate) fn endpoint_label(url_path: &str, body: &str) -> String {
+    let label = serde_json::from_str::<RequestLabel>(body).ok();
+    let kind = match url_path {
+        "/exchange" => label.and_then(|label| label.action?.kind),
+        _ => label.and_then(|label| label.kind),
+    };
+    format!(
+        "{}:{}",
+        url_path.trim_start_matches('/'),
+        kind.as_deref().unwrap_or("unknown")
+    )
+}
+
+/// Channel of a raw websocket message, read without parsing the whole payload.
+#[cfg_attr(not(feature = "ws"), allow(dead_code))]
+pub(crate) fn ws_channel(text: &str) -> &str {
+    text.split_once(r#""channel":""#)
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(channel, _)| channel)
+        .unwrap_or("unknown")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_and_registration() {
+        assert_eq!(
+            endpoint_label("/info", r#"{"type":"l2Book","coin":"ETH"}"#),
+            "info:l2Book"
+        );
+        assert_eq!(
+            endpoint_label(
+                "/exchange",
+                r#"{"action":{"type":"order","orders":[]},"nonce":1}"#
+            ),
+            "exchange:order"
+        );
+        assert_eq!(ws_channel(r#"{"channel":"trades","data":[]}"#), "trades");
+
+        let registry = Registry::new();
+        let metrics = Metrics::new(&registry).unwrap();
+        metrics.observe_ack("exchange:order", Duration::from_millis(20));
+        metrics.observe_ack("exchange:usdSend", Duration::from_millis(20));
+        metrics.inc_error("info:l2Book", "timeout");
+        let families = registry.gather();
+        let acks = families
+            .iter()
+            .find(|family| family.name() == "hyperliquid_order_ack_duration_seconds")
+            .unwrap();
+        assert_eq!(acks.get_metric().len(), 1);
+        assert!(Metrics::new(&registry).is_err());
+    }
+}
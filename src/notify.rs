@@ -0,0 +1,408 @@
+use std::{collections::BTreeMap, fmt};
+
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::{
+    helpers::now_timestamp_ms,
+    prelude::*,
+    req::{classify_error, classify_reqwest_error, parse_response, reqwest_error},
+    rt, ActivityAnomaly, Message, RetryPolicy, TradeInfo, UserData,
+};
+#[cfg(feature = "exchange")]
+use crate::{LiquidationAlert, RiskViolation};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertKind {
+    Fill,
+    Liquidation,
+    Disconnected,
+    RiskBreach,
+    Custom,
+}
+
+/// An event to alert on, rendered by each `Webhook` in its own format.
+///
+/// `fields` holds the event's details by name, available to templates as `{name}` next to
+/// `{kind}`, `{title}`, `{text}` and `{time}`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub title: String,
+    pub text: String,
+    pub fields: BTreeMap<String, String>,
+    /// When the event happened, in ms
+    pub time: u64,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, title: impl Into<String>, text: impl Into<String>) -> Self {
+        Alert {
+            kind,
+            title: title.into(),
+            text: text.into(),
+            fields: BTreeMap::new(),
+            time: now_timestamp_ms(),
+        }
+    }
+
+    pub fn with_field(mut self, name: &str, value: impl ToString) -> Self {
+        self.fields.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn fill(fill: &TradeInfo) -> Alert {
+        let side = if fill.side.is_buy() { "Bought" } else { "Sold" };
+        Alert {
+            time: fill.time.as_millis(),
+            ..Alert::new(
+                AlertKind::Fill,
+                format!("{} fill", fill.coin),
+                format!("{side} {} {} at {}", fill.sz, fill.coin, fill.px),
+            )
+        }
+        .with_field("coin", &fill.coin)
+        .with_field("side", fill.side)
+        .with_field("px", &fill.px)
+        .with_field("sz", &fill.sz)
+        .with_field("oid", fill.oid)
+        .with_field("tid", fill.tid)
+        .with_field("closedPnl", &fill.closed_pnl)
+        .with_field("fee", &fill.fee)
+    }
+
+    /// A websocket connection dropped, as reported by `Message::NoData`.
+    pub fn disconnected() -> Alert {
+        Alert::new(
+            AlertKind::Disconnected,
+            "Websocket disconnected",
+            "The websocket connection dropped and is reconnecting",
+        )
+    }
+
+    #[cfg(feature = "exchange")]
+    pub fn liquidation(liquidation: &LiquidationAlert) -> Alert {
+        let mut alert = Alert::new(
+            AlertKind::Liquidation,
+            format!("{} near liquidation", liquidation.coin),
+            format!(
+                "{} is {:.2}% from its liquidation price {} at mid {}",
+                liquidation.coin,
+                liquidation.distance * 100.0,
+                liquidation.liquidation_px,
+                liquidation.mid
+            ),
+        )
+        .with_field("coin", &liquidation.coin)
+        .with_field("level", liquidation.level)
+        .with_field("distance", liquidation.distance)
+        .with_field("mid", liquidation.mid)
+        .with_field("liquidationPx", liquidation.liquidation_px);
+        if let Some(reduce_sz) = liquidation.reduce_sz {
+            alert = alert.with_field("reduceSz", reduce_sz);
+        }
+        alert
+    }
+
+    #[cfg(feature = "exchange")]
+    pub fn risk_breach(violation: &RiskViolation) -> Alert {
+        Alert::new(
+            AlertKind::RiskBreach,
+            "Risk check failed",
+            violation.to_string(),
+        )
+    }
+
+    /// Account activity outside its baseline, from `ActivityMonitor`.
+    pub fn anomaly(anomaly: &ActivityAnomaly) -> Alert {
+        let mut alert = Alert {
+            time: anomaly.time,
+            ..Alert::new(
+                AlertKind::RiskBreach,
+                "Unexpected account activity",
+                &anomaly.detail,
+            )
+        }
+        .with_field("anomaly", format!("{:?}", anomaly.kind));
+        if let Some(coin) = &anomaly.coin {
+            alert = alert.with_field("coin", coin);
+        }
+        alert
+    }
+
+    /// Replaces `{name}` placeholders in `template`, JSON-escaping values so they can be
+    /// used inside JSON strings. Unknown placeholders are left as they are.
+    pub fn render(&self, template: &str) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let value = after
+                .find('}')
+                .and_then(|end| Some((end, self.placeholder(&after[..end])?)));
+            match value {
+                Some((end, value)) => {
+                    let escaped = Value::String(value).to_string();
+                    rendered.push_str(&escaped[1..escaped.len() - 1]);
+                    rest = &after[end + 1..];
+                }
+                None => {
+                    rendered.push('{');
+                    rest = after;
+                }
+            }
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+
+    fn placeholder(&self, name: &str) -> Option<String> {
+        match name {
+            "kind" => serde_json::to_value(self.kind)
+                .ok()
+                .and_then(|kind| kind.as_str().map(str::to_string)),
+            "title" => Some(self.title.clone()),
+            "text" => Some(self.text.clone()),
+            "time" => Some(self.time.to_string()),
+            _ => self.fields.get(name).cloned(),
+        }
+    }
+}
+
+/// Body sent to a webhook.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WebhookFormat {
+    /// Slack incoming webhook, `{"text": ...}`
+    Slack,
+    /// Discord webhook, `{"content": ...}`
+    Discord,
+    /// Telegram bot `sendMessage`, with the bot token in the url
+    Telegram { chat_id: String },
+    /// The `Alert` as JSON
+    Json,
+    /// A JSON template rendered with `Alert::render`
+    Template(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    pub url: String,
+    pub format: WebhookFormat,
+    /// Kinds sent to this webhook, every kind if empty
+    pub kinds: Vec<AlertKind>,
+}
+
+impl Webhook {
+    pub fn new(url: impl Into<String>, format: WebhookFormat) -> Webhook {
+        Webhook {
+            url: url.into(),
+            format,
+            kinds: Vec::new(),
+        }
+    }
+
+    pub fn slack(url: impl Into<String>) -> Webhook {
+        Webhook::new(url, WebhookFormat::Slack)
+    }
+
+    pub fn discord(url: impl Into<String>) -> Webhook {
+        Webhook::new(url, WebhookFormat::Discord)
+    }
+
+    pub fn telegram(bot_token: &str, chat_id: impl Into<String>) -> Webhook {
+        Webhook::new(
+            format!("https://api.telegram.org/bot{bot_token}/sendMessage"),
+            WebhookFormat::Telegram {
+                chat_id: chat_id.into(),
+            },
+        )
+    }
+
+    pub fn with_kinds(mut self, kinds: &[AlertKind]) -> Self {
+        self.kinds = kinds.to_vec();
+        self
+    }
+
+    fn accepts(&self, kind: AlertKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    fn body(&self, alert: &Alert) -> String {
+        let message = format!("*{}*\n{}", alert.title, alert.text);
+        match &self.format {
+            WebhookFormat::Slack => json!({ "text": message }).to_string(),
+            WebhookFormat::Discord => json!({
+                "content": format!("**{}**\n{}", alert.title, alert.text),
+            })
+            .to_string(),
+            WebhookFormat::Telegram { chat_id } => json!({
+                "chat_id": chat_id,
+                "text": message,
+                "parse_mode": "Markdown",
+            })
+            .to_string(),
+            WebhookFormat::Json => json!(alert).to_string(),
+            WebhookFormat::Template(template) => alert.render(template),
+        }
+    }
+}
+
+/// Sends `Alert`s to webhooks, retrying failed deliveries with a `RetryPolicy`.
+///
+/// `handle_message` turns fills and websocket disconnects from subscriptions into
+/// alerts; liquidation alerts and risk breaches are sent with `notify` from the
+/// `LiquidationWatchdog` callback or where an order fails `Error::RiskCheck`.
+#[derive(Clone)]
+pub struct Notifier {
+    client: Client,
+    webhooks: Vec<Webhook>,
+    retry_policy: RetryPolicy,
+}
+
+impl fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Webhook urls carry their credentials
+        f.debug_struct("Notifier")
+            .field("webhooks", &self.webhooks.len())
+            .field("retry_policy", &self.retry_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Notifier::new()
+    }
+}
+
+impl Notifier {
+    /// Retries each delivery up to 3 times.
+    pub fn new() -> Notifier {
+        Notifier {
+            client: Client::new(),
+            webhooks: Vec::new(),
+            retry_policy: RetryPolicy::exponential(3),
+        }
+    }
+
+    pub fn with_webhook(mut self, webhook: Webhook) -> Self {
+        self.webhooks.push(webhook);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Sends `alert` to every webhook accepting its kind. All are tried, and the last
+    /// failure is returned.
+    pub async fn notify(&self, alert: &Alert) -> Result<()> {
+        let mut result = Ok(());
+        for webhook in self
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.accepts(alert.kind))
+        {
+            if let Err(err) = self.send(webhook, &webhook.body(alert)).await {
+                warn!(%err, kind = ?alert.kind, "Could not deliver alert");
+                result = Err(err);
+            }
+        }
+        result
+    }
+
+    /// Notifies of the fills and disconnects in `message`. Fill snapshots sent on subscribing
+    /// are skipped.
+    pub async fn handle_message(&self, message: &Message) -> Result<()> {
+        let alerts: Vec<Alert> = match message {
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                fills.data.fills.iter().map(Alert::fill).collect()
+            }
+            Message::User(user) => match &user.data {
+                UserData::Fills(fills) => fills.iter().map(Alert::fill).collect(),
+                _ => Vec::new(),
+            },
+            Message::NoData => vec![Alert::disconnected()],
+            _ => Vec::new(),
+        };
+        for alert in &alerts {
+            self.notify(alert).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, webhook: &Webhook, body: &str) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            let response = self
+                .client
+                .post(&webhook.url)
+                .header("Content-Type", "application/json")
+                .body(body.to_string())
+                .send()
+                .await;
+            let (err, kind) = match response {
+                Ok(response) => match parse_response(response).await {
+                    Ok(_) => return Ok(()),
+                    Err(err) => {
+                        let kind = classify_error(&err);
+                        (err, kind)
+                    }
+                },
+                Err(err) => (reqwest_error(&err), classify_reqwest_error(&err)),
+            };
+            if !self.retry_policy.should_retry(attempt, kind, true) {
+                return Err(err);
+            }
+            rt::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_and_bodies() {
+        let alert = Alert::new(AlertKind::Custom, "Hello", "say \"hi\"").with_field("coin", "ETH");
+        assert_eq!(
+            alert.render(r#"{"msg":"{title} {coin}: {text}","k":"{kind}","x":"{nope}"}"#),
+            r#"{"msg":"Hello ETH: say \"hi\"","k":"custom","x":"{nope}"}"#
+        );
+        let body: Value = serde_json::from_str(
+            &Webhook::new(
+                "",
+                WebhookFormat::Template(r#"{"text":"{text}"}"#.to_string()),
+            )
+            .body(&alert),
+        )
+        .unwrap();
+        assert_eq!(body["text"], "say \"hi\"");
+
+        let telegram = Webhook::telegram("TOKEN", "42");
+        assert_eq!(
+            telegram.url,
+            "https://api.telegram.org/botTOKEN/sendMessage"
+        );
+        let body: Value = serde_json::from_str(&telegram.body(&alert)).unwrap();
+        assert_eq!(body["chat_id"], "42");
+        assert_eq!(body["text"], "*Hello*\nsay \"hi\"");
+
+        let fills = Webhook::slack("").with_kinds(&[AlertKind::Fill]);
+        assert!(!fills.accepts(AlertKind::Custom));
+        assert!(Webhook::discord("").accepts(AlertKind::Disconnected));
+    }
+}
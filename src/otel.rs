@@ -0,0 +1,93 @@
+//! W3C trace context for the order path, for spans exported through `tracing-opentelemetry`.
+//!
+//! Exchange calls open `exchange_action`, signing and `http_request` spans under whatever span
+//! is current, so running a strategy decision inside a span, optionally parented to a remote
+//! context with `set_remote_parent`, puts decision, signing, the HTTP round trip and response
+//! parsing in one trace. Requests also carry a `traceparent` header for proxies in between.
+
+use opentelemetry::{
+    trace::{SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{prelude::*, Error};
+
+const VERSION: &str = "00";
+
+/// Parses a `traceparent` header value such as
+/// `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+fn parse_traceparent(traceparent: &str) -> Option<SpanContext> {
+    let mut parts = traceparent.trim().split('-');
+    let (Some(VERSION), Some(trace_id), Some(span_id), Some(flags), None) = (
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+        parts.next(),
+    ) else {
+        return None;
+    };
+    if trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+        return None;
+    }
+    let context = SpanContext::new(
+        TraceId::from_hex(trace_id).ok()?,
+        SpanId::from_hex(span_id).ok()?,
+        TraceFlags::new(u8::from_str_radix(flags, 16).ok()?),
+        true,
+        TraceState::default(),
+    );
+    context.is_valid().then_some(context)
+}
+
+/// Makes `span` a child of the remote span described by `traceparent`, as received from an
+/// upstream service. Call before the span is first entered.
+pub fn set_remote_parent(span: &Span, traceparent: &str) -> Result<()> {
+    let parent = parse_traceparent(traceparent)
+        .ok_or_else(|| Error::GenericParse(format!("Invalid traceparent {traceparent}")))?;
+    span.set_parent(Context::new().with_remote_span_context(parent))
+        .map_err(|e| Error::GenericParse(e.to_string()))
+}
+
+/// `traceparent` of `span`, `None` unless an OpenTelemetry layer is recording it.
+pub fn traceparent(span: &Span) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    span_context.is_valid().then(|| {
+        format!(
+            "{VERSION}-{}-{}-{:02x}",
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn test_remote_parent_round_trip() {
+        assert!(parse_traceparent("01-abc-def-00").is_none());
+        assert!(
+            parse_traceparent("00-00000000000000000000000000000000-00f067aa0ba902b7-01").is_none()
+        );
+
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_opentelemetry::layer()
+                .with_tracer(opentelemetry::trace::noop::NoopTracer::new()),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("decision");
+            let parent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+            set_remote_parent(&span, parent).unwrap();
+            let traceparent = traceparent(&span).unwrap();
+            assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        });
+    }
+}
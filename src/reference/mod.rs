@@ -0,0 +1,190 @@
+#[cfg(feature = "reference")]
+mod venues;
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::helpers::now_timestamp_ms;
+#[cfg(feature = "reference")]
+pub use venues::{BinanceReference, CoinbaseReference};
+
+/// A price of a coin on another venue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReferencePrice {
+    pub px: f64,
+    /// When the price was received, in ms
+    pub time: u64,
+}
+
+/// An external venue's prices, keyed by Hyperliquid coin name, for fair value and for
+/// detecting stale or dislocated prices.
+pub trait ReferencePriceSource: fmt::Debug + Send + Sync {
+    /// Venue name used in logs and violations.
+    fn name(&self) -> &str;
+
+    /// Latest price of `coin`, `None` if the venue has not quoted it.
+    fn price(&self, coin: &str) -> Option<ReferencePrice>;
+}
+
+/// Prices set by hand, or by an adapter for a venue without one in the SDK. Clones share the
+/// same prices.
+#[derive(Clone, Debug, Default)]
+pub struct ReferencePrices {
+    name: String,
+    prices: Arc<RwLock<HashMap<String, ReferencePrice>>>,
+}
+
+impl ReferencePrices {
+    pub fn new(name: impl Into<String>) -> ReferencePrices {
+        ReferencePrices {
+            name: name.into(),
+            prices: Arc::default(),
+        }
+    }
+
+    /// Sets the price of `coin`, received now.
+    pub fn set(&self, coin: &str, px: f64) {
+        self.set_at(coin, px, now_timestamp_ms());
+    }
+
+    pub fn set_at(&self, coin: &str, px: f64, time: u64) {
+        self.prices
+            .write()
+            .expect("reference prices lock poisoned")
+            .insert(coin.to_string(), ReferencePrice { px, time });
+    }
+}
+
+impl ReferencePriceSource for ReferencePrices {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn price(&self, coin: &str) -> Option<ReferencePrice> {
+        self.prices
+            .read()
+            .expect("reference prices lock poisoned")
+            .get(coin)
+            .copied()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum ReferenceViolation {
+    #[error("no {venue} price for {coin} newer than {max_age:?}")]
+    Stale {
+        coin: String,
+        venue: String,
+        max_age: Duration,
+    },
+    #[error("{coin} price {px} is {bps:.1} bps from {venue} price {reference}")]
+    Deviation {
+        coin: String,
+        venue: String,
+        px: f64,
+        reference: f64,
+        bps: f64,
+    },
+}
+
+/// Checks Hyperliquid prices against a `ReferencePriceSource`, used by `RiskEngine` and
+/// `MarketMaker` to stop quoting when either venue's price looks wrong.
+#[derive(Clone, Debug)]
+pub struct ReferenceGuard {
+    source: Arc<dyn ReferencePriceSource>,
+    max_deviation_bps: f64,
+    max_age: Duration,
+}
+
+impl ReferenceGuard {
+    /// Prices further than `max_deviation_bps` from the reference fail the check, as do
+    /// references older than 5 seconds.
+    pub fn new(source: Arc<dyn ReferencePriceSource>, max_deviation_bps: f64) -> ReferenceGuard {
+        ReferenceGuard {
+            source,
+            max_deviation_bps,
+            max_age: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn source(&self) -> &Arc<dyn ReferencePriceSource> {
+        &self.source
+    }
+
+    /// The reference price of `coin`, if fresh enough.
+    pub fn reference(&self, coin: &str) -> Option<f64> {
+        self.reference_at(coin, now_timestamp_ms())
+    }
+
+    /// Checks `px` of `coin` against its reference and returns the deviation in bps.
+    pub fn check(&self, coin: &str, px: f64) -> Result<f64, ReferenceViolation> {
+        self.check_at(coin, px, now_timestamp_ms())
+    }
+
+    fn reference_at(&self, coin: &str, now: u64) -> Option<f64> {
+        self.source
+            .price(coin)
+            .filter(|price| now.saturating_sub(price.time) <= self.max_age.as_millis() as u64)
+            .map(|price| price.px)
+    }
+
+    fn check_at(&self, coin: &str, px: f64, now: u64) -> Result<f64, ReferenceViolation> {
+        let reference = self
+            .reference_at(coin, now)
+            .filter(|reference| *reference > 0.0)
+            .ok_or_else(|| ReferenceViolation::Stale {
+                coin: coin.to_string(),
+                venue: self.source.name().to_string(),
+                max_age: self.max_age,
+            })?;
+        let bps = (px - reference).abs() / reference * 10_000.0;
+        if bps > self.max_deviation_bps {
+            return Err(ReferenceViolation::Deviation {
+                coin: coin.to_string(),
+                venue: self.source.name().to_string(),
+                px,
+                reference,
+                bps,
+            });
+        }
+        Ok(bps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EPSILON;
+
+    #[test]
+    fn test_guard() {
+        let prices = ReferencePrices::new("test");
+        prices.set_at("ETH", 2000.0, 10_000);
+        let guard = ReferenceGuard::new(Arc::new(prices.clone()), 50.0)
+            .with_max_age(Duration::from_secs(2));
+
+        assert!((guard.check_at("ETH", 2004.0, 11_000).unwrap() - 20.0).abs() < EPSILON);
+        assert!(matches!(
+            guard.check_at("ETH", 2020.0, 11_000),
+            Err(ReferenceViolation::Deviation { bps, .. }) if (bps - 100.0).abs() < EPSILON
+        ));
+        assert!(matches!(
+            guard.check_at("ETH", 2000.0, 12_001),
+            Err(ReferenceViolation::Stale { .. })
+        ));
+        assert!(guard.check_at("BTC", 2000.0, 11_000).is_err());
+
+        // Clones share prices
+        prices.set_at("ETH", 2020.0, 12_000);
+        assert!(guard.check_at("ETH", 2020.0, 12_001).is_ok());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{info, info_span, warn, Instrument};
+
+use super::{ReferencePrice, ReferencePriceSource, ReferencePrices};
+use crate::{
+    rt::{self, spawn},
+    ws::transport::{connect, message_text, text_message},
+};
+
+const BINANCE_URL: &str = "wss://stream.binance.com:9443/stream";
+const COINBASE_URL: &str = "wss://ws-feed.exchange.coinbase.com";
+
+/// Which venue a feed parses, and how it names the coins it follows.
+#[derive(Clone, Copy, Debug)]
+enum Venue {
+    Binance,
+    Coinbase,
+}
+
+impl Venue {
+    fn name(self) -> &'static str {
+        match self {
+            Venue::Binance => "binance",
+            Venue::Coinbase => "coinbase",
+        }
+    }
+
+    fn default_symbol(self, coin: &str) -> String {
+        match self {
+            Venue::Binance => format!("{coin}USDT"),
+            Venue::Coinbase => format!("{coin}-USD"),
+        }
+    }
+
+    fn url(self, symbols: &[String]) -> String {
+        match self {
+            Venue::Binance => {
+                let streams: Vec<String> = symbols
+                    .iter()
+                    .map(|symbol| format!("{}@bookTicker", symbol.to_lowercase()))
+                    .collect();
+                format!("{BINANCE_URL}?streams={}", streams.join("/"))
+            }
+            Venue::Coinbase => COINBASE_URL.to_string(),
+        }
+    }
+
+    /// Message to send after connecting, if the venue subscribes over the socket.
+    fn subscribe(self, symbols: &[String]) -> Option<String> {
+        match self {
+            Venue::Binance => None,
+            Venue::Coinbase => Some(
+                json!({ "type": "subscribe", "product_ids": symbols, "channels": ["ticker"] })
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Symbol and mid of a top of book update.
+    fn parse(self, text: &str) -> Option<(String, f64)> {
+        #[derive(Deserialize)]
+        struct BinanceStream {
+            data: BinanceBookTicker,
+        }
+        #[derive(Deserialize)]
+        struct BinanceBookTicker {
+            s: String,
+            b: String,
+            a: String,
+        }
+        #[derive(Deserialize)]
+        struct CoinbaseTicker {
+            product_id: String,
+            best_bid: String,
+            best_ask: String,
+        }
+
+        let (symbol, bid, ask) = match self {
+            Venue::Binance => {
+                let ticker = serde_json::from_str::<BinanceStream>(text).ok()?.data;
+                (ticker.s, ticker.b, ticker.a)
+            }
+            Venue::Coinbase => {
+                let value = serde_json::from_str::<serde_json::Value>(text).ok()?;
+                if value["type"] != "ticker" {
+                    return None;
+                }
+                let ticker = serde_json::from_value::<CoinbaseTicker>(value).ok()?;
+                (ticker.product_id, ticker.best_bid, ticker.best_ask)
+            }
+        };
+        let (bid, ask) = (bid.parse::<f64>().ok()?, ask.parse::<f64>().ok()?);
+        Some((symbol.to_uppercase(), (bid + ask) / 2.0))
+    }
+}
+
+/// Background websocket feed writing a venue's mids into `ReferencePrices`.
+#[derive(Debug)]
+struct Feed {
+    prices: ReferencePrices,
+    stop_flag: Arc<AtomicBool>,
+}
+
+impl Feed {
+    fn start(venue: Venue, symbols: Vec<(String, String)>) -> Feed {
+        let prices = ReferencePrices::new(venue.name());
+        let stop_flag = Arc::new(AtomicBool::new(false));
+        let coins: HashMap<String, String> = symbols
+            .iter()
+            .map(|(coin, symbol)| (symbol.to_uppercase(), coin.clone()))
+            .collect();
+        let symbols: Vec<String> = symbols.into_iter().map(|(_, symbol)| symbol).collect();
+
+        let task = {
+            let prices = prices.clone();
+            let stop_flag = Arc::clone(&stop_flag);
+            async move {
+                while !stop_flag.load(Ordering::Relaxed) {
+                    let mut ws = match connect(&venue.url(&symbols), None).await {
+                        Ok(ws) => ws,
+                        Err(err) => {
+                            warn!(%err, "Could not connect reference feed");
+                            rt::sleep(Duration::from_secs(1)).await;
+                            continue;
+                        }
+                    };
+                    if let Some(payload) = venue.subscribe(&symbols) {
+                        if let Err(err) = ws.send(text_message(payload)).await {
+                            warn!(%err, "Could not subscribe reference feed");
+                        }
+                    }
+                    info!("Reference feed connected");
+                    while let Some(Ok(message)) = ws.next().await {
+                        if stop_flag.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let Ok(text) = message_text(message) else {
+                            continue;
+                        };
+                        if let Some((coin, mid)) = venue
+                            .parse(&text)
+                            .and_then(|(symbol, mid)| Some((coins.get(&symbol)?, mid)))
+                        {
+                            prices.set(coin, mid);
+                        }
+                    }
+                    warn!("Reference feed disconnected");
+                    rt::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        };
+        spawn(task.instrument(info_span!("reference_feed", venue = venue.name())));
+        Feed { prices, stop_flag }
+    }
+
+    fn symbols(venue: Venue, coins: &[&str]) -> Vec<(String, String)> {
+        coins
+            .iter()
+            .map(|coin| (coin.to_string(), venue.default_symbol(coin)))
+            .collect()
+    }
+}
+
+impl Drop for Feed {
+    fn drop(&mut self) {
+        self.stop_flag.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Mids from Binance spot `bookTicker` streams. Coins map to their USDT pair unless given
+/// explicitly, so `ETH` follows `ETHUSDT`.
+#[derive(Debug)]
+pub struct BinanceReference {
+    feed: Feed,
+}
+
+impl BinanceReference {
+    pub fn connect(coins: &[&str]) -> BinanceReference {
+        BinanceReference::connect_symbols(Feed::symbols(Venue::Binance, coins))
+    }
+
+    /// Follows explicit `(coin, symbol)` pairs, such as `("kPEPE", "1000PEPEUSDT")`.
+    pub fn connect_symbols(symbols: Vec<(String, String)>) -> BinanceReference {
+        BinanceReference {
+            feed: Feed::start(Venue::Binance, symbols),
+        }
+    }
+}
+
+impl ReferencePriceSource for BinanceReference {
+    fn name(&self) -> &str {
+        self.feed.prices.name()
+    }
+
+    fn price(&self, coin: &str) -> Option<ReferencePrice> {
+        self.feed.prices.price(coin)
+    }
+}
+
+/// Mids from the Coinbase Exchange `ticker` channel. Coins map to their USD product unless
+/// given explicitly, so `ETH` follows `ETH-USD`.
+#[derive(Debug)]
+pub struct CoinbaseReference {
+    feed: Feed,
+}
+
+impl CoinbaseReference {
+    pub fn connect(coins: &[&str]) -> CoinbaseReference {
+        CoinbaseReference::connect_symbols(Feed::symbols(Venue::Coinbase, coins))
+    }
+
+    /// Follows explicit `(coin, product)` pairs, such as `("UBTC", "BTC-USD")`.
+    pub fn connect_symbols(symbols: Vec<(String, String)>) -> CoinbaseReference {
+        CoinbaseReference {
+            feed: Feed::start(Venue::Coinbase, symbols),
+        }
+    }
+}
+
+impl ReferencePriceSource for CoinbaseReference {
+    fn name(&self) -> &str {
+        self.feed.prices.name()
+    }
+
+    fn price(&self, coin: &str) -> Option<ReferencePrice> {
+        self.feed.prices.price(coin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_venues() {
+        assert_eq!(
+            Venue::Binance.url(&["ETHUSDT".to_string(), "BTCUSDT".to_string()]),
+            "wss://stream.binance.com:9443/stream?streams=ethusdt@bookTicker/btcusdt@bookTicker"
+        );
+        assert_eq!(
+            Venue::Binance.parse(
+                r#"{"stream":"ethusdt@bookTicker","data":{"u":1,"s":"ETHUSDT","b":"2000","B":"3","a":"2001","A":"1"}}"#
+            ),
+            Some(("ETHUSDT".to_string(), 2000.5))
+        );
+        assert_eq!(
+            Venue::Coinbase.parse(
+                r#"{"type":"ticker","product_id":"ETH-USD","price":"2000.5","best_bid":"2000","best_ask":"2001"}"#
+            ),
+            Some(("ETH-USD".to_string(), 2000.5))
+        );
+        assert_eq!(
+            Venue::Coinbase.parse(r#"{"type":"subscriptions","channels":[]}"#),
+            None
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use std::{sync::Mutex, time::Duration};
+
+use crate::{prelude::*, rt::Instant, Error};
+
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures after which the circuit opens
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a probe request is let through
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    /// The cooldown elapsed and a single probe request is in flight
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Rejects requests fast after repeated failures instead of letting them pile up against a
+/// failing connection.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> CircuitBreaker {
+        CircuitBreaker {
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner
+            .lock()
+            .expect("circuit breaker lock poisoned")
+            .state
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.inner
+            .lock()
+            .expect("circuit breaker lock poisoned")
+            .consecutive_failures
+    }
+
+    pub fn reset(&self) {
+        let mut inner = self.inner.lock().expect("circuit breaker lock poisoned");
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+    }
+
+    pub(crate) fn allow(&self) -> Result<Permit<'_>> {
+        self.allow_at(Instant::now())
+    }
+
+    fn allow_at(&self, now: Instant) -> Result<Permit<'_>> {
+        let mut inner = self.inner.lock().expect("circuit breaker lock poisoned");
+        let probe = match inner.state {
+            CircuitState::Closed => false,
+            CircuitState::HalfOpen => return Err(Error::CircuitOpen(self.config.cooldown)),
+            CircuitState::Open => {
+                let elapsed = inner
+                    .opened_at
+                    .map(|opened_at| now.saturating_duration_since(opened_at))
+                    .unwrap_or_default();
+                if elapsed < self.config.cooldown {
+                    return Err(Error::CircuitOpen(self.config.cooldown - elapsed));
+                }
+                inner.state = CircuitState::HalfOpen;
+                true
+            }
+        };
+        Ok(Permit {
+            breaker: self,
+            probe,
+        })
+    }
+
+    /// Reopens a half-open circuit whose probe ended without an outcome.
+    fn abandon_probe(&self, now: Instant) {
+        let mut inner = self.inner.lock().expect("circuit breaker lock poisoned");
+        if inner.state == CircuitState::HalfOpen {
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(now);
+        }
+    }
+
+    fn record_success(&self) {
+        let mut inner = self.inner.lock().expect("circuit breaker lock poisoned");
+        inner.state = CircuitState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+    }
+
+    fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut inner = self.inner.lock().expect("circuit breaker lock poisoned");
+        inner.consecutive_failures += 1;
+        if inner.state == CircuitState::HalfOpen
+            || inner.consecutive_failures >= self.config.failure_threshold
+        {
+            inner.state = CircuitState::Open;
+            inner.opened_at = Some(now);
+        }
+    }
+}
+
+/// Lets one request through a `CircuitBreaker`, whose outcome is passed to `record`.
+///
+/// Dropping the probe of a half-open circuit unrecorded, as when the request's future is
+/// cancelled, reopens the circuit so it is probed again after the cooldown.
+#[must_use]
+pub(crate) struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Permit<'_> {
+    pub(crate) fn record(mut self, success: bool) {
+        self.probe = false;
+        if success {
+            self.breaker.record_success();
+        } else {
+            self.breaker.record_failure();
+        }
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.abandon_probe(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(10),
+        });
+        let now = Instant::now();
+
+        breaker.record_failure_at(now);
+        assert!(breaker.allow_at(now).is_ok());
+        breaker.record_failure_at(now);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(matches!(
+            breaker.allow_at(now + Duration::from_secs(4)),
+            Err(Error::CircuitOpen(remaining)) if remaining == Duration::from_secs(6)
+        ));
+
+        // One probe after the cooldown, concurrent requests are still rejected
+        let probe = breaker.allow_at(now + Duration::from_secs(10)).unwrap();
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.allow_at(now + Duration::from_secs(10)).is_err());
+
+        // A failed probe reopens immediately
+        probe.record(false);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        let probe = breaker
+            .allow_at(Instant::now() + Duration::from_secs(10))
+            .unwrap();
+        probe.record(true);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe_reopens_circuit() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            cooldown: Duration::ZERO,
+        });
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // The request future is cancelled while the probe is in flight
+        let request = async {
+            let _permit = breaker.allow()?;
+            std::future::pending::<()>().await;
+            Ok::<_, Error>(())
+        };
+        assert!(tokio::time::timeout(Duration::from_millis(10), request)
+            .await
+            .is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.allow().is_ok());
+    }
+}
@@ -0,0 +1,449 @@
+use std::{future::Future, pin::pin, sync::Mutex, time::Duration};
+
+use reqwest::Client;
+use tracing::{info, warn};
+
+use super::{parse_response, reqwest_error, retry::FailureKind};
+use crate::{prelude::*, rt, rt::Instant, BaseUrl, Error};
+
+#[derive(Clone, Debug)]
+pub struct FailoverConfig {
+    /// Consecutive timeouts or server errors after which an endpoint is taken out of rotation.
+    /// Connection failures, including DNS errors, take it out at once.
+    pub failure_threshold: u32,
+    /// How long an endpoint stays out of rotation before requests are tried on it again
+    pub retry_after: Duration,
+}
+
+/// Routing of exchange actions to the endpoint with the lowest probed latency.
+#[derive(Clone, Debug)]
+pub struct LatencyRoutingConfig {
+    /// Fraction by which another endpoint must be faster than the current one before exchange
+    /// actions move to it, so similar endpoints do not take turns
+    pub hysteresis: f64,
+    /// Weight of each new probe in the moving average of an endpoint's latency
+    pub smoothing: f64,
+}
+
+impl Default for LatencyRoutingConfig {
+    fn default() -> Self {
+        LatencyRoutingConfig {
+            hysteresis: 0.2,
+            smoothing: 0.3,
+        }
+    }
+}
+
+/// Which endpoints a request may be sent to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum EndpointRoute {
+    /// Exchange actions: the fastest endpoint with latency routing, else the active one
+    Critical,
+    /// The active endpoint
+    Active,
+    /// History queries: any endpoint in rotation, in turn
+    Any,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        FailoverConfig {
+            failure_threshold: 3,
+            retry_after: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Health of one endpoint of an `EndpointPool`.
+#[derive(Clone, Debug)]
+pub struct EndpointHealth {
+    pub endpoint: BaseUrl,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Moving average of probed round trips
+    pub latency: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct EndpointState {
+    endpoint: BaseUrl,
+    consecutive_failures: u32,
+    down_since: Option<Instant>,
+    last_error: Option<String>,
+    latency: Option<Duration>,
+}
+
+#[derive(Debug)]
+struct Endpoints {
+    states: Vec<EndpointState>,
+    /// Endpoint exchange actions are routed to with latency routing
+    fastest: Option<usize>,
+    /// Next endpoint tried for `EndpointRoute::Any`
+    next_any: usize,
+}
+
+/// Ordered REST and websocket endpoints, such as the official API followed by self-hosted
+/// replicas, shared by clients that fail over between them.
+///
+/// Requests go to the first endpoint in rotation. Failing endpoints are taken out of rotation
+/// and put back once `retry_after` has passed or a health check succeeds, so traffic returns to
+/// the preferred endpoint after an outage. When every endpoint is down the one that went down
+/// first is used.
+///
+/// With latency routing, `check` also measures each endpoint's round trip and exchange actions
+/// go to the fastest endpoint in rotation, while history queries are spread over all of them.
+#[derive(Debug)]
+pub struct EndpointPool {
+    config: FailoverConfig,
+    latency_routing: Option<LatencyRoutingConfig>,
+    endpoints: Mutex<Endpoints>,
+}
+
+impl EndpointPool {
+    pub fn new(endpoints: Vec<BaseUrl>) -> Result<EndpointPool> {
+        if endpoints.is_empty() {
+            return Err(Error::InvalidConfig(
+                "Endpoint pool needs at least one endpoint".to_string(),
+            ));
+        }
+        let endpoints = endpoints
+            .into_iter()
+            .map(|endpoint| EndpointState {
+                endpoint,
+                consecutive_failures: 0,
+                down_since: None,
+                last_error: None,
+                latency: None,
+            })
+            .collect();
+        Ok(EndpointPool {
+            config: FailoverConfig::default(),
+            latency_routing: None,
+            endpoints: Mutex::new(Endpoints {
+                states: endpoints,
+                fastest: None,
+                next_any: 0,
+            }),
+        })
+    }
+
+    pub fn with_config(mut self, config: FailoverConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Routes exchange actions to the fastest endpoint, as measured by `check`.
+    pub fn with_latency_routing(mut self, config: LatencyRoutingConfig) -> Self {
+        self.latency_routing = Some(config);
+        self
+    }
+
+    /// Endpoint requests are sent to.
+    pub fn active(&self) -> BaseUrl {
+        self.active_at(Instant::now())
+    }
+
+    /// Endpoint exchange actions are sent to: the fastest one in rotation with latency routing,
+    /// otherwise the active one.
+    pub fn fastest(&self) -> BaseUrl {
+        self.route(EndpointRoute::Critical)
+    }
+
+    /// First endpoint, used to decide which chain exchange actions are signed for.
+    pub fn primary(&self) -> BaseUrl {
+        self.lock().states[0].endpoint.clone()
+    }
+
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        self.lock()
+            .states
+            .iter()
+            .map(|state| EndpointHealth {
+                endpoint: state.endpoint.clone(),
+                healthy: state.down_since.is_none(),
+                consecutive_failures: state.consecutive_failures,
+                last_error: state.last_error.clone(),
+                latency: state.latency,
+            })
+            .collect()
+    }
+
+    /// Puts `endpoint` back in rotation.
+    pub fn record_success(&self, endpoint: &BaseUrl) {
+        let mut endpoints = self.lock();
+        if let Some(state) = endpoints
+            .states
+            .iter_mut()
+            .find(|s| &s.endpoint == endpoint)
+        {
+            if state.down_since.is_some() {
+                info!(endpoint = %endpoint.get_url(), "Endpoint back in rotation");
+            }
+            state.consecutive_failures = 0;
+            state.down_since = None;
+        }
+    }
+
+    /// Probes every endpoint with a light info request, updating their health and latency.
+    pub async fn check(&self, client: &Client) -> Vec<EndpointHealth> {
+        let endpoints: Vec<BaseUrl> = self
+            .lock()
+            .states
+            .iter()
+            .map(|s| s.endpoint.clone())
+            .collect();
+        for endpoint in endpoints {
+            let started = Instant::now();
+            let result = client
+                .post(format!("{}/info", endpoint.get_url()))
+                .header("Content-Type", "application/json")
+                .body(r#"{"type":"allMids"}"#)
+                .send()
+                .await;
+            let result = match result {
+                Ok(response) => parse_response(response).await.map(|_| ()),
+                Err(err) => Err(reqwest_error(&err)),
+            };
+            match result {
+                Ok(()) => {
+                    self.record_success(&endpoint);
+                    self.record_latency(&endpoint, started.elapsed());
+                }
+                Err(err) => self.record_failure(&endpoint, FailureKind::Connect, &err),
+            }
+        }
+        self.health()
+    }
+
+    /// Calls `check` every `interval` until `shutdown` completes, so endpoints are failed over
+    /// and back without waiting for requests to fail and latencies stay current.
+    pub async fn run_health_checks(
+        &self,
+        client: &Client,
+        interval: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            self.check(client).await;
+            tokio::select! {
+                _ = &mut shutdown => return,
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+
+    pub(crate) fn record_failure(&self, endpoint: &BaseUrl, kind: FailureKind, err: &Error) {
+        self.record_failure_at(endpoint, kind, err, Instant::now());
+    }
+
+    pub(crate) fn route(&self, route: EndpointRoute) -> BaseUrl {
+        self.route_at(route, Instant::now())
+    }
+
+    /// Folds a probed round trip into `endpoint`'s latency and picks the endpoint exchange
+    /// actions go to.
+    pub(crate) fn record_latency(&self, endpoint: &BaseUrl, latency: Duration) {
+        let Some(routing) = &self.latency_routing else {
+            return;
+        };
+        let mut endpoints = self.lock();
+        let Some(state) = endpoints
+            .states
+            .iter_mut()
+            .find(|s| &s.endpoint == endpoint)
+        else {
+            return;
+        };
+        state.latency = Some(match state.latency {
+            Some(average) => {
+                average.mul_f64(1.0 - routing.smoothing) + latency.mul_f64(routing.smoothing)
+            }
+            None => latency,
+        });
+        let candidate = endpoints
+            .states
+            .iter()
+            .enumerate()
+            .filter(|(_, state)| state.down_since.is_none())
+            .filter_map(|(index, state)| Some((index, state.latency?)))
+            .min_by_key(|(_, latency)| *latency);
+        let current = endpoints
+            .fastest
+            .map(|index| &endpoints.states[index])
+            .filter(|state| state.down_since.is_none())
+            .and_then(|state| state.latency);
+        let switch = match (candidate, current) {
+            (Some((_, fastest)), Some(current)) => {
+                fastest < current.mul_f64(1.0 - routing.hysteresis)
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        if switch {
+            endpoints.fastest = candidate.map(|(index, _)| index);
+        }
+    }
+
+    fn active_at(&self, now: Instant) -> BaseUrl {
+        let endpoints = self.lock();
+        let index = self.active_index(&endpoints.states, now);
+        endpoints.states[index].endpoint.clone()
+    }
+
+    fn route_at(&self, route: EndpointRoute, now: Instant) -> BaseUrl {
+        let mut endpoints = self.lock();
+        let in_rotation = |state: &EndpointState| self.in_rotation(state, now);
+        let index = match route {
+            EndpointRoute::Critical => endpoints
+                .fastest
+                .filter(|index| in_rotation(&endpoints.states[*index]))
+                .unwrap_or_else(|| self.active_index(&endpoints.states, now)),
+            EndpointRoute::Active => self.active_index(&endpoints.states, now),
+            EndpointRoute::Any => {
+                let count = endpoints.states.len();
+                let start = endpoints.next_any;
+                let next = (0..count)
+                    .map(|offset| (start + offset) % count)
+                    .find(|index| in_rotation(&endpoints.states[*index]));
+                match next {
+                    Some(index) => {
+                        endpoints.next_any = index + 1;
+                        index
+                    }
+                    None => self.active_index(&endpoints.states, now),
+                }
+            }
+        };
+        endpoints.states[index].endpoint.clone()
+    }
+
+    fn active_index(&self, states: &[EndpointState], now: Instant) -> usize {
+        states
+            .iter()
+            .position(|state| self.in_rotation(state, now))
+            .or_else(|| {
+                states
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, state)| state.down_since)
+                    .map(|(index, _)| index)
+            })
+            .expect("endpoint pool is never empty")
+    }
+
+    fn in_rotation(&self, state: &EndpointState, now: Instant) -> bool {
+        state
+            .down_since
+            .is_none_or(|since| now.duration_since(since) >= self.config.retry_after)
+    }
+
+    fn record_failure_at(&self, endpoint: &BaseUrl, kind: FailureKind, err: &Error, now: Instant) {
+        // Rate limits and rejected requests say nothing about the endpoint's health
+        if matches!(kind, FailureKind::RateLimited | FailureKind::Other) {
+            return;
+        }
+        let mut endpoints = self.lock();
+        let Some(state) = endpoints
+            .states
+            .iter_mut()
+            .find(|s| &s.endpoint == endpoint)
+        else {
+            return;
+        };
+        state.consecutive_failures += 1;
+        state.last_error = Some(err.to_string());
+        if kind == FailureKind::Connect
+            || state.consecutive_failures >= self.config.failure_threshold
+        {
+            if state.down_since.is_none() {
+                warn!(endpoint = %endpoint.get_url(), error = %err, "Endpoint out of rotation");
+            }
+            state.down_since = Some(now);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Endpoints> {
+        self.endpoints.lock().expect("endpoint pool lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_and_failback() {
+        let replica = BaseUrl::custom("https://replica.example.com");
+        let pool = EndpointPool::new(vec![BaseUrl::Mainnet, replica.clone()])
+            .unwrap()
+            .with_config(FailoverConfig {
+                failure_threshold: 2,
+                retry_after: Duration::from_secs(30),
+            });
+        let start = Instant::now();
+        let err = Error::Timeout("timed out".to_string());
+        assert_eq!(pool.active_at(start), BaseUrl::Mainnet);
+
+        // Rejected requests do not count, timeouts do up to the threshold
+        pool.record_failure_at(&BaseUrl::Mainnet, FailureKind::Other, &err, start);
+        pool.record_failure_at(&BaseUrl::Mainnet, FailureKind::Timeout, &err, start);
+        assert_eq!(pool.active_at(start), BaseUrl::Mainnet);
+        pool.record_failure_at(&BaseUrl::Mainnet, FailureKind::Timeout, &err, start);
+        assert_eq!(pool.active_at(start), replica);
+        assert!(!pool.health()[0].healthy);
+
+        // Both down: the endpoint down longest is used
+        let later = start + Duration::from_secs(5);
+        pool.record_failure_at(&replica, FailureKind::Connect, &err, later);
+        assert_eq!(pool.active_at(later), BaseUrl::Mainnet);
+
+        // The primary is retried after the cooldown and restored by a success
+        pool.record_success(&replica);
+        assert_eq!(pool.active_at(later), replica);
+        assert_eq!(
+            pool.active_at(start + Duration::from_secs(30)),
+            BaseUrl::Mainnet
+        );
+        pool.record_success(&BaseUrl::Mainnet);
+        assert!(pool.health().iter().all(|health| health.healthy));
+    }
+
+    #[test]
+    fn test_latency_routing() {
+        let near = BaseUrl::custom("https://near.example.com");
+        let far = BaseUrl::custom("https://far.example.com");
+        let pool = EndpointPool::new(vec![far.clone(), near.clone()])
+            .unwrap()
+            .with_latency_routing(LatencyRoutingConfig {
+                hysteresis: 0.2,
+                smoothing: 1.0,
+            });
+        let now = Instant::now();
+        let ms = Duration::from_millis;
+        assert_eq!(pool.route_at(EndpointRoute::Critical, now), far);
+
+        pool.record_latency(&far, ms(100));
+        pool.record_latency(&near, ms(50));
+        assert_eq!(pool.route_at(EndpointRoute::Critical, now), near);
+        // Info requests keep following priority, history queries take turns
+        assert_eq!(pool.route_at(EndpointRoute::Active, now), far);
+        assert_eq!(pool.route_at(EndpointRoute::Any, now), far);
+        assert_eq!(pool.route_at(EndpointRoute::Any, now), near);
+        assert_eq!(pool.route_at(EndpointRoute::Any, now), far);
+
+        // Slightly faster is not enough to switch back
+        pool.record_latency(&far, ms(45));
+        assert_eq!(pool.route_at(EndpointRoute::Critical, now), near);
+        pool.record_latency(&far, ms(30));
+        assert_eq!(pool.route_at(EndpointRoute::Critical, now), far);
+
+        // Leaves the fastest endpoint as soon as it is out of rotation
+        let err = Error::Websocket("refused".to_string());
+        pool.record_failure_at(&far, FailureKind::Connect, &err, now);
+        assert_eq!(pool.route_at(EndpointRoute::Critical, now), near);
+        assert_eq!(pool.route_at(EndpointRoute::Any, now), near);
+        assert_eq!(pool.route_at(EndpointRoute::Any, now), near);
+    }
+}
@@ -0,0 +1,144 @@
+use std::{fmt, sync::Arc, time::Duration};
+
+use serde_json::Value;
+
+/// Keys whose values are replaced before a payload reaches the hook. Matched case-insensitively
+/// at any depth.
+const DEFAULT_REDACTED_KEYS: [&str; 5] =
+    ["signature", "privateKey", "secret", "apiKey", "password"];
+const REDACTED: &str = "<redacted>";
+
+/// One REST attempt as seen by a `RequestLogger` hook. Retried requests produce one entry per
+/// attempt.
+#[derive(Clone, Debug)]
+pub struct RequestLog {
+    pub url_path: String,
+    pub attempt: u32,
+    pub request_body: String,
+    /// Present when the request succeeded
+    pub response_body: Option<String>,
+    /// Present when the request failed, including error responses from the server
+    pub error: Option<String>,
+    pub elapsed: Duration,
+}
+
+/// Passes every request and response payload, with secrets redacted, to a user callback.
+#[derive(Clone)]
+pub struct RequestLogger {
+    hook: Arc<dyn Fn(&RequestLog) + Send + Sync>,
+    redacted_keys: Vec<String>,
+}
+
+impl fmt::Debug for RequestLogger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestLogger")
+            .field("redacted_keys", &self.redacted_keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestLogger {
+    pub fn new(hook: impl Fn(&RequestLog) + Send + Sync + 'static) -> RequestLogger {
+        RequestLogger {
+            hook: Arc::new(hook),
+            redacted_keys: DEFAULT_REDACTED_KEYS
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+        }
+    }
+
+    /// Also redacts values stored under `key`.
+    pub fn redact_key(mut self, key: impl Into<String>) -> Self {
+        self.redacted_keys.push(key.into());
+        self
+    }
+
+    pub(crate) fn log(
+        &self,
+        url_path: &str,
+        attempt: u32,
+        request_body: &str,
+        result: std::result::Result<&str, String>,
+        elapsed: Duration,
+    ) {
+        let (response_body, error) = match result {
+            Ok(body) => (Some(self.redact(body)), None),
+            Err(err) => (None, Some(err)),
+        };
+        (self.hook)(&RequestLog {
+            url_path: url_path.to_string(),
+            attempt,
+            request_body: self.redact(request_body),
+            response_body,
+            error,
+            elapsed,
+        });
+    }
+
+    fn redact(&self, body: &str) -> String {
+        match serde_json::from_str::<Value>(body) {
+            Ok(mut value) => {
+                redact_value(&mut value, &self.redacted_keys);
+                value.to_string()
+            }
+            Err(_) => body.to_string(),
+        }
+    }
+}
+
+fn redact_value(value: &mut Value, keys: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if keys.iter().any(|k| k.eq_ignore_ascii_case(key)) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_value(value, keys);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|v| redact_value(v, keys)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_redacts_signatures() {
+        let logs = Arc::new(Mutex::new(Vec::new()));
+        let logger = {
+            let logs = logs.clone();
+            RequestLogger::new(move |log| logs.lock().unwrap().push(log.clone()))
+                .redact_key("vaultAddress")
+        };
+        logger.log(
+            "/exchange",
+            1,
+            r#"{"action":{"type":"order","orders":[]},"nonce":1,"signature":{"r":"0x1","s":"0x2","v":27},"vaultAddress":"0xabc"}"#,
+            Ok(r#"{"status":"ok"}"#),
+            Duration::from_millis(5),
+        );
+        logger.log(
+            "/info",
+            2,
+            "not json",
+            Err("boom".to_string()),
+            Duration::ZERO,
+        );
+
+        let logs = logs.lock().unwrap();
+        let request: Value = serde_json::from_str(&logs[0].request_body).unwrap();
+        assert_eq!(request["signature"], REDACTED);
+        assert_eq!(request["vaultAddress"], REDACTED);
+        assert_eq!(request["nonce"], 1);
+        assert_eq!(logs[0].response_body.as_deref(), Some(r#"{"status":"ok"}"#));
+        assert_eq!(logs[1].request_body, "not json");
+        assert_eq!(logs[1].error.as_deref(), Some("boom"));
+    }
+}
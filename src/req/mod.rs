@@ -0,0 +1,754 @@
+#[cfg(feature = "exchange")]
+mod circuit_breaker;
+mod failover;
+mod logging;
+mod proxy;
+mod rate_limit;
+mod recording;
+mod retry;
+mod schema;
+mod stats;
+mod throttle;
+#[cfg(all(feature = "ws", not(target_arch = "wasm32")))]
+mod tunnel;
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use reqwest::{Client, Response};
+use serde::Deserialize;
+pub use tokio_util::sync::CancellationToken;
+use tracing::{debug_span, instrument, warn, Instrument};
+
+use crate::{
+    prelude::*, rt, rt::Instant, BaseUrl, ClientDefaults, Error, MAINNET_API_URL, TESTNET_API_URL,
+};
+#[cfg(feature = "exchange")]
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+pub(crate) use failover::EndpointRoute;
+pub use failover::{EndpointHealth, EndpointPool, FailoverConfig, LatencyRoutingConfig};
+pub use logging::{RequestLog, RequestLogger};
+pub use proxy::ProxyConfig;
+pub(crate) use rate_limit::exchange_weight;
+#[cfg(feature = "exchange")]
+pub(crate) use rate_limit::TokenBucket;
+pub use rate_limit::{RateLimitMode, RateLimiter, ADDRESS_INITIAL_BUFFER, IP_WEIGHT_PER_MINUTE};
+pub use recording::{RecordedEntry, Recorder, Recording, Replayer};
+pub(crate) use retry::FailureKind;
+pub use retry::RetryPolicy;
+pub use schema::{schema_drift, DriftIssue, DriftKind, SchemaCheck, SchemaDrift};
+pub use stats::{RequestCounts, RequestStats, RequestStatsSnapshot};
+pub use throttle::{Throttle, ThrottleState, RATE_LIMITED_COOLDOWN};
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Runs `fut` with a deadline applied to every request it sends, overriding the client's
+/// configured timeout. Requests still in flight when the deadline passes fail with
+/// `Error::Timeout`, and no retries are started after it.
+pub async fn with_timeout<F: Future>(timeout: Duration, fut: F) -> F::Output {
+    DEADLINE.scope(Instant::now() + timeout, fut).await
+}
+
+/// Runs `fut` until `token` is cancelled, dropping it at that point and returning
+/// `Error::Cancelled`. Requests still in flight are aborted with it.
+pub async fn with_cancellation<T>(
+    token: &CancellationToken,
+    fut: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(Error::Cancelled),
+        res = fut => res,
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Timeouts {
+    /// Applied when establishing connections
+    pub connect: Option<Duration>,
+    /// Applied to each request, from sending until the response body is read
+    pub request: Option<Duration>,
+}
+
+/// HTTP settings collected by the client builders before an `HttpClient` is created.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HttpOptions {
+    pub(crate) base_url: Option<BaseUrl>,
+    pub(crate) endpoints: Option<Arc<EndpointPool>>,
+    pub(crate) client: Option<Client>,
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) throttle: Option<Arc<Throttle>>,
+    pub(crate) stats: Option<Arc<RequestStats>>,
+    pub(crate) timeouts: Timeouts,
+    pub(crate) proxy: Option<ProxyConfig>,
+    pub(crate) request_logger: Option<RequestLogger>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) replayer: Option<Arc<Replayer>>,
+    pub(crate) schema_check: Option<SchemaCheck>,
+    pub(crate) cancel: Option<CancellationToken>,
+    pub(crate) defaults: ClientDefaults,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Option<Arc<crate::Metrics>>,
+}
+
+impl HttpOptions {
+    pub(crate) fn base_url(&self) -> BaseUrl {
+        self.base_url
+            .clone()
+            .or_else(|| self.endpoints.as_ref().map(|pool| pool.primary()))
+            .unwrap_or(BaseUrl::Mainnet)
+    }
+
+    pub(crate) fn ws_url(&self) -> String {
+        self.base_url().ws_url_with(&self.defaults)
+    }
+
+    pub(crate) fn build(self) -> Result<HttpClient> {
+        let base_url = self.base_url();
+        let mut http_client = HttpClient::new(
+            self.client.unwrap_or_default(),
+            base_url.url_with(&self.defaults),
+        );
+        // Mainnet behind an overridden URL still signs for mainnet
+        http_client.mainnet |= base_url == BaseUrl::Mainnet;
+        http_client.defaults = self.defaults;
+        http_client.endpoints = self.endpoints;
+        http_client.retry_policy = self.retry_policy;
+        http_client.rate_limiter = self.rate_limiter;
+        if let Some(throttle) = self.throttle {
+            http_client.throttle = throttle;
+        }
+        if let Some(stats) = self.stats {
+            http_client.stats = stats;
+        }
+        http_client.timeout = self.timeouts.request;
+        http_client.connect_timeout = self.timeouts.connect;
+        http_client.proxy = self.proxy;
+        http_client.request_logger = self.request_logger;
+        http_client.recorder = self.recorder;
+        http_client.replayer = self.replayer;
+        http_client.schema_check = self.schema_check;
+        http_client.cancel = self.cancel;
+        #[cfg(feature = "metrics")]
+        {
+            http_client.metrics = self.metrics;
+        }
+        if http_client.connect_timeout.is_some() || http_client.proxy.is_some() {
+            http_client.rebuild_client()?;
+        }
+        Ok(http_client)
+    }
+}
+
+/// Setters for the `http: HttpOptions` field shared by the client builders.
+macro_rules! http_options_setters {
+    () => {
+        pub fn base_url(mut self, base_url: $crate::BaseUrl) -> Self {
+            self.http.base_url = Some(base_url);
+            self
+        }
+
+        /// Fails over between `endpoints`, for both REST requests and websocket connections.
+        /// Exchange actions are signed for the chain of the first endpoint.
+        pub fn endpoints(mut self, endpoints: std::sync::Arc<$crate::EndpointPool>) -> Self {
+            self.http.endpoints = Some(endpoints);
+            self
+        }
+
+        /// Custom reqwest client. Connect timeouts and proxies configured on the builder
+        /// replace it.
+        pub fn client(mut self, client: reqwest::Client) -> Self {
+            self.http.client = Some(client);
+            self
+        }
+
+        pub fn retry_policy(mut self, retry_policy: $crate::RetryPolicy) -> Self {
+            self.http.retry_policy = retry_policy;
+            self
+        }
+
+        pub fn rate_limiter(mut self, rate_limiter: std::sync::Arc<$crate::RateLimiter>) -> Self {
+            self.http.rate_limiter = Some(rate_limiter);
+            self
+        }
+
+        /// Shares rate-limit cooldowns with other clients talking to the same API.
+        pub fn throttle(mut self, throttle: std::sync::Arc<$crate::Throttle>) -> Self {
+            self.http.throttle = Some(throttle);
+            self
+        }
+
+        /// Shares request counters with other clients, see `RequestStats`.
+        pub fn request_stats(mut self, stats: std::sync::Arc<$crate::RequestStats>) -> Self {
+            self.http.stats = Some(stats);
+            self
+        }
+
+        pub fn timeouts(mut self, timeouts: $crate::Timeouts) -> Self {
+            self.http.timeouts = timeouts;
+            self
+        }
+
+        pub fn proxy(mut self, proxy: $crate::ProxyConfig) -> Self {
+            self.http.proxy = Some(proxy);
+            self
+        }
+
+        /// Reports every request and response, with signatures redacted, to `logger`.
+        pub fn request_logger(mut self, logger: $crate::RequestLogger) -> Self {
+            self.http.request_logger = Some(logger);
+            self
+        }
+
+        /// Records every REST response and websocket message to `recorder`.
+        pub fn recorder(mut self, recorder: $crate::Recorder) -> Self {
+            self.http.recorder = Some(recorder);
+            self
+        }
+
+        /// Answers REST requests from a recording instead of the network.
+        pub fn replayer(mut self, replayer: std::sync::Arc<$crate::Replayer>) -> Self {
+            self.http.replayer = Some(replayer);
+            self
+        }
+
+        /// Checks REST responses and websocket messages for fields and values the SDK does
+        /// not know, see `SchemaCheck`.
+        pub fn schema_check(mut self, check: $crate::SchemaCheck) -> Self {
+            self.http.schema_check = Some(check);
+            self
+        }
+
+        /// API URLs and default slippage replacing the built-in ones.
+        pub fn defaults(mut self, defaults: $crate::ClientDefaults) -> Self {
+            self.http.defaults = defaults;
+            self
+        }
+
+        /// Aborts requests in flight, and closes websocket connections, once `token` is
+        /// cancelled. Later requests fail with `Error::Cancelled`.
+        pub fn cancellation_token(mut self, token: $crate::CancellationToken) -> Self {
+            self.http.cancel = Some(token);
+            self
+        }
+
+        /// Publishes request, order ack and websocket metrics to `metrics`.
+        #[cfg(feature = "metrics")]
+        pub fn metrics(mut self, metrics: std::sync::Arc<$crate::Metrics>) -> Self {
+            self.http.metrics = Some(metrics);
+            self
+        }
+    };
+}
+pub(crate) use http_options_setters;
+
+#[derive(Deserialize, Debug)]
+struct ErrorData {
+    data: String,
+    code: u16,
+    msg: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    pub client: Client,
+    pub base_url: String,
+    /// Endpoints failed over between, replacing `base_url` when set
+    pub endpoints: Option<Arc<EndpointPool>>,
+    pub retry_policy: RetryPolicy,
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    pub throttle: Arc<Throttle>,
+    /// Orders, cancels, modifies and info requests sent, shared between clones
+    pub stats: Arc<RequestStats>,
+    pub timeout: Option<Duration>,
+    pub connect_timeout: Option<Duration>,
+    /// Whether exchange actions are signed for mainnet
+    pub mainnet: bool,
+    /// Also used for websocket connections made by `InfoClient`
+    pub proxy: Option<ProxyConfig>,
+    pub request_logger: Option<RequestLogger>,
+    /// Also records websocket messages received by `InfoClient`
+    pub recorder: Option<Recorder>,
+    pub replayer: Option<Arc<Replayer>>,
+    /// Checks responses decoded by `InfoClient`, and its websocket messages, for drift
+    pub schema_check: Option<SchemaCheck>,
+    /// Aborts requests in flight, and websocket connections made by `InfoClient`, once
+    /// cancelled
+    pub cancel: Option<CancellationToken>,
+    /// API URLs and default slippage, see `ClientDefaults`
+    pub defaults: ClientDefaults,
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<Arc<crate::Metrics>>,
+}
+
+pub(crate) fn reqwest_error(err: &reqwest::Error) -> Error {
+    if err.is_timeout() {
+        Error::Timeout(err.to_string())
+    } else {
+        Error::GenericRequest(err.to_string())
+    }
+}
+
+pub(crate) async fn parse_response(response: Response) -> Result<String> {
+    let status_code = response.status().as_u16();
+    let text = response.text().await.map_err(|e| reqwest_error(&e))?;
+
+    if status_code < 400 {
+        return Ok(text);
+    }
+    let error_data = serde_json::from_str::<ErrorData>(&text);
+    if (400..500).contains(&status_code) {
+        let client_error = match error_data {
+            Ok(error_data) => Error::ClientRequest {
+                status_code,
+                error_code: Some(error_data.code),
+                error_message: error_data.msg,
+                error_data: Some(error_data.data),
+            },
+            Err(err) => Error::ClientRequest {
+                status_code,
+                error_message: text,
+                error_code: None,
+                error_data: Some(err.to_string()),
+            },
+        };
+        return Err(client_error);
+    }
+
+    Err(Error::ServerRequest {
+        status_code,
+        error_message: text,
+    })
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<serde_json::Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Result of a JSON-RPC call to an EVM node, such as HyperEVM or Arbitrum.
+pub(crate) async fn json_rpc(
+    client: &Client,
+    url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value> {
+    let body = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await
+        .map_err(|e| reqwest_error(&e))?;
+    let text = parse_response(response).await?;
+    let response: RpcResponse =
+        serde_json::from_str(&text).map_err(|e| Error::JsonParse(e.to_string()))?;
+    match (response.result, response.error) {
+        (_, Some(error)) => Err(Error::GenericRequest(format!(
+            "{method} failed with {}: {}",
+            error.code, error.message
+        ))),
+        (Some(result), None) => Ok(result),
+        (None, None) => Err(Error::GenericRequest(format!(
+            "{method} returned no result"
+        ))),
+    }
+}
+
+pub(crate) fn classify_error(err: &Error) -> FailureKind {
+    match err {
+        Error::ClientRequest {
+            status_code: 429, ..
+        } => FailureKind::RateLimited,
+        Error::ServerRequest { .. } => FailureKind::Server,
+        Error::Timeout(_) => FailureKind::Timeout,
+        _ => FailureKind::Other,
+    }
+}
+
+pub(crate) fn classify_reqwest_error(err: &reqwest::Error) -> FailureKind {
+    #[cfg(not(target_arch = "wasm32"))]
+    if err.is_connect() {
+        return FailureKind::Connect;
+    }
+    if err.is_timeout() {
+        FailureKind::Timeout
+    } else {
+        FailureKind::Other
+    }
+}
+
+impl HttpClient {
+    pub fn new(client: Client, base_url: String) -> HttpClient {
+        HttpClient {
+            mainnet: base_url == BaseUrl::Mainnet.get_url(),
+            client,
+            base_url,
+            endpoints: None,
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            throttle: Arc::new(Throttle::default()),
+            stats: Arc::new(RequestStats::default()),
+            timeout: None,
+            connect_timeout: None,
+            proxy: None,
+            request_logger: None,
+            recorder: None,
+            replayer: None,
+            schema_check: None,
+            cancel: None,
+            defaults: ClientDefaults::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Replaces the reqwest client with one built from `connect_timeout` and `proxy`. In the
+    /// browser both are controlled by the user agent and ignored.
+    pub(crate) fn rebuild_client(&mut self) -> Result<()> {
+        #[allow(unused_mut)]
+        let mut builder = Client::builder();
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if let Some(connect) = self.connect_timeout {
+                builder = builder.connect_timeout(connect);
+            }
+            if let Some(proxy) = &self.proxy {
+                builder = builder.proxy(proxy.reqwest_proxy()?);
+            }
+        }
+        self.client = builder
+            .build()
+            .map_err(|e| Error::GenericRequest(e.to_string()))?;
+        Ok(())
+    }
+
+    pub async fn post(&self, url_path: &'static str, data: String) -> Result<String> {
+        let weight = if url_path == "/exchange" {
+            exchange_weight(1)
+        } else {
+            20
+        };
+        self.post_weighted(url_path, data, weight).await
+    }
+
+    pub(crate) async fn post_weighted(
+        &self,
+        url_path: &'static str,
+        data: String,
+        weight: u32,
+    ) -> Result<String> {
+        let route = if url_path == "/exchange" {
+            EndpointRoute::Critical
+        } else {
+            EndpointRoute::Active
+        };
+        self.post_routed(url_path, data, weight, route).await
+    }
+
+    /// Sends to the endpoints allowed by `route` when an endpoint pool is configured.
+    #[instrument(
+        name = "http_request",
+        skip(self, data),
+        fields(base_url = %self.base_url)
+    )]
+    pub(crate) async fn post_routed(
+        &self,
+        url_path: &'static str,
+        data: String,
+        weight: u32,
+        route: EndpointRoute,
+    ) -> Result<String> {
+        if let Some(replayer) = &self.replayer {
+            return replayer.respond(url_path, &data);
+        }
+        if url_path == "/info" {
+            self.stats.record_info(weight);
+        }
+        match &self.cancel {
+            Some(token) => {
+                with_cancellation(token, self.post_recorded(url_path, data, weight, route)).await
+            }
+            None => self.post_recorded(url_path, data, weight, route).await,
+        }
+    }
+
+    async fn post_recorded(
+        &self,
+        url_path: &'static str,
+        data: String,
+        weight: u32,
+        route: EndpointRoute,
+    ) -> Result<String> {
+        let Some(recorder) = &self.recorder else {
+            return self.send_weighted(url_path, &data, weight, route).await;
+        };
+        let result = self.send_weighted(url_path, &data, weight, route).await;
+        recorder.record_rest(
+            url_path,
+            &data,
+            result.as_deref().map_err(|err| err.to_string()),
+        );
+        result
+    }
+
+    async fn send_weighted(
+        &self,
+        url_path: &'static str,
+        data: &str,
+        weight: u32,
+        route: EndpointRoute,
+    ) -> Result<String> {
+        // Only info requests are safe to repeat after an ambiguous failure
+        let idempotent = url_path != "/exchange";
+        #[cfg(feature = "metrics")]
+        let endpoint = self
+            .metrics
+            .as_ref()
+            .map(|_| crate::metrics::endpoint_label(url_path, data));
+        #[cfg(feature = "metrics")]
+        let first_sent = Instant::now();
+        let mut attempt = 1;
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire(weight).await?;
+            }
+            let timeout = self.attempt_timeout()?;
+            let started = Instant::now();
+            let result = self
+                .post_once(url_path, data, timeout, route)
+                .instrument(debug_span!("attempt", attempt))
+                .await;
+            #[cfg(feature = "metrics")]
+            if let (Some(metrics), Some(endpoint)) = (&self.metrics, &endpoint) {
+                metrics.observe_request(endpoint, started.elapsed());
+                match &result {
+                    Ok(_) => metrics.observe_ack(endpoint, first_sent.elapsed()),
+                    Err((_, kind)) => metrics.inc_error(endpoint, kind.label()),
+                }
+                if let Some(rate_limiter) = &self.rate_limiter {
+                    metrics.set_rate_limit_available(rate_limiter.available_weight());
+                }
+            }
+            if let Some(logger) = &self.request_logger {
+                logger.log(
+                    url_path,
+                    attempt,
+                    data,
+                    result.as_deref().map_err(|(err, _)| err.to_string()),
+                    started.elapsed(),
+                );
+            }
+            match result {
+                Ok(text) => return Ok(text),
+                Err((err, kind)) => {
+                    if kind == FailureKind::RateLimited {
+                        self.throttle.record_http_rate_limited();
+                    }
+                    if !self.retry_policy.should_retry(attempt, kind, idempotent) {
+                        return Err(err);
+                    }
+                    let backoff = self.retry_policy.backoff(attempt);
+                    if DEADLINE
+                        .try_with(|deadline| Instant::now() + backoff >= *deadline)
+                        .unwrap_or(false)
+                    {
+                        return Err(err);
+                    }
+                    warn!(
+                        url_path,
+                        attempt,
+                        ?backoff,
+                        error = %err,
+                        "Request failed, retrying"
+                    );
+                    rt::sleep(backoff).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Timeout for the next attempt: the time left until the scoped deadline if there is one,
+    /// otherwise the configured timeout.
+    fn attempt_timeout(&self) -> Result<Option<Duration>> {
+        match DEADLINE.try_with(|deadline| deadline.saturating_duration_since(Instant::now())) {
+            Ok(remaining) if remaining.is_zero() => Err(Error::Timeout(
+                "deadline exceeded before sending".to_string(),
+            )),
+            Ok(remaining) => Ok(Some(remaining)),
+            Err(_) => Ok(self.timeout),
+        }
+    }
+
+    /// Sends once to `base_url`, or with an endpoint pool to the endpoint picked for `route`,
+    /// moving on to the next one right away if the connection cannot be established.
+    async fn post_once(
+        &self,
+        url_path: &'static str,
+        data: &str,
+        timeout: Option<Duration>,
+        route: EndpointRoute,
+    ) -> std::result::Result<String, (Error, FailureKind)> {
+        let Some(endpoints) = &self.endpoints else {
+            return self.post_to(&self.base_url, url_path, data, timeout).await;
+        };
+        let mut tried = Vec::new();
+        loop {
+            let endpoint = endpoints.route(route);
+            let result = self
+                .post_to(&endpoint.get_url(), url_path, data, timeout)
+                .await;
+            match &result {
+                Ok(_) => endpoints.record_success(&endpoint),
+                Err((err, kind)) => endpoints.record_failure(&endpoint, *kind, err),
+            }
+            tried.push(endpoint);
+            let connect_failed = matches!(result, Err((_, FailureKind::Connect)));
+            if !connect_failed || tried.contains(&endpoints.active()) {
+                return result;
+            }
+        }
+    }
+
+    async fn post_to(
+        &self,
+        base_url: &str,
+        url_path: &'static str,
+        data: &str,
+        timeout: Option<Duration>,
+    ) -> std::result::Result<String, (Error, FailureKind)> {
+        let full_url = format!("{base_url}{url_path}");
+        let mut request = self
+            .client
+            .post(full_url)
+            .header("Content-Type", "application/json")
+            .body(data.to_string());
+        if let Some(timeout) = timeout {
+            request = request.timeout(timeout);
+        }
+        #[cfg(feature = "otel")]
+        if let Some(traceparent) = crate::otel::traceparent(&tracing::Span::current()) {
+            request = request.header("traceparent", traceparent);
+        }
+        let request = request
+            .build()
+            .map_err(|e| (Error::GenericRequest(e.to_string()), FailureKind::Other))?;
+        let result = self
+            .client
+            .execute(request)
+            .await
+            .map_err(|e| (reqwest_error(&e), classify_reqwest_error(&e)))?;
+        parse_response(result).await.map_err(|e| {
+            let kind = classify_error(&e);
+            (e, kind)
+        })
+    }
+
+    pub fn is_mainnet(&self) -> bool {
+        self.mainnet
+    }
+
+    /// Fails when actions would be signed for one network and sent to the public API of the
+    /// other, where they are rejected or, signed for mainnet from a testnet setup, executed.
+    pub fn check_network(&self) -> Result<()> {
+        let mut urls = vec![self.base_url.clone()];
+        if let Some(endpoints) = &self.endpoints {
+            urls.extend(endpoints.health().iter().map(|h| h.endpoint.get_url()));
+        }
+        let (signed_for, other_api) = if self.mainnet {
+            ("mainnet", TESTNET_API_URL)
+        } else {
+            ("testnet", MAINNET_API_URL)
+        };
+        match urls
+            .iter()
+            .find(|url| url.trim_end_matches('/') == other_api)
+        {
+            Some(url) => Err(Error::InvalidConfig(format!(
+                "actions are signed for {signed_for} but sent to {url}, set the network to match \
+                 the API"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scoped_deadline_times_out() {
+        // Accepts connections but never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let mut http_client = HttpClient::new(Client::new(), base_url);
+        http_client.retry_policy = RetryPolicy::exponential(5);
+        let res = with_timeout(
+            Duration::from_millis(100),
+            http_client.post("/info", "{}".to_string()),
+        )
+        .await;
+        assert!(matches!(res, Err(Error::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_aborts_request() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let token = CancellationToken::new();
+        let mut http_client = HttpClient::new(Client::new(), base_url);
+        http_client.cancel = Some(token.child_token());
+        let cancel = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+        let res = http_client.post("/info", "{}".to_string()).await;
+        assert!(matches!(res, Err(Error::Cancelled)));
+        let res = http_client.post("/info", "{}".to_string()).await;
+        assert!(matches!(res, Err(Error::Cancelled)));
+    }
+
+    #[test]
+    fn test_defaults_override_urls() {
+        let options = HttpOptions {
+            base_url: Some(BaseUrl::Mainnet),
+            defaults: ClientDefaults {
+                mainnet_api_url: "https://hl-proxy.example.com".to_string(),
+                ..ClientDefaults::default()
+            },
+            ..HttpOptions::default()
+        };
+        assert_eq!(options.ws_url(), "wss://hl-proxy.example.com/ws");
+        let http_client = options.build().unwrap();
+        assert_eq!(http_client.base_url, "https://hl-proxy.example.com");
+        assert!(http_client.is_mainnet());
+        assert_eq!(http_client.defaults.slippage, crate::DEFAULT_SLIPPAGE);
+    }
+}
@@ -0,0 +1,63 @@
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Url;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{prelude::*, Error};
+
+/// Proxy used for both REST requests and websocket connections.
+///
+/// Supports `http://`, `socks5://` and `socks5h://` URLs (`https://` proxies only for REST).
+/// With `socks5h` the proxy resolves host names, with `socks5` they are resolved locally.
+/// Credentials can be embedded in the URL or set with `with_auth`. On `wasm32` the browser's
+/// proxy settings apply instead.
+#[derive(Clone)]
+pub struct ProxyConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl std::fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl ProxyConfig {
+    pub fn new(url: impl Into<String>) -> ProxyConfig {
+        ProxyConfig {
+            url: url.into(),
+            username: None,
+            password: None,
+        }
+    }
+
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Parsed proxy URL with explicitly configured credentials applied.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn parsed_url(&self) -> Result<Url> {
+        let mut url = Url::parse(&self.url)
+            .map_err(|e| Error::GenericParse(format!("Invalid proxy url: {e}")))?;
+        if let Some(username) = &self.username {
+            url.set_username(username)
+                .map_err(|_| Error::GenericParse("Proxy url cannot carry credentials".into()))?;
+            url.set_password(self.password.as_deref())
+                .map_err(|_| Error::GenericParse("Proxy url cannot carry credentials".into()))?;
+        }
+        Ok(url)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn reqwest_proxy(&self) -> Result<reqwest::Proxy> {
+        reqwest::Proxy::all(self.parsed_url()?).map_err(|e| Error::GenericRequest(e.to_string()))
+    }
+}
@@ -0,0 +1,261 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use alloy::primitives::Address;
+
+use crate::{prelude::*, rt, rt::Instant, Error};
+
+/// Aggregated REST weight allowed per IP per minute.
+pub const IP_WEIGHT_PER_MINUTE: u32 = 1200;
+/// Requests every address may send before volume-based allowance kicks in.
+pub const ADDRESS_INITIAL_BUFFER: u64 = 10_000;
+const ADDRESS_LIMITED_INTERVAL: Duration = Duration::from_secs(10);
+const CANCEL_EXTRA_ALLOWANCE: u64 = 100_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// Wait until enough budget is available
+    Delay,
+    /// Fail immediately with `Error::RateLimited`
+    Reject,
+}
+
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(capacity: u32, per: Duration, now: Instant) -> TokenBucket {
+        TokenBucket {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_sec: capacity as f64 / per.as_secs_f64(),
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes `weight` tokens, or returns how long to wait until they are available.
+    pub(crate) fn try_take(
+        &mut self,
+        weight: u32,
+        now: Instant,
+    ) -> std::result::Result<(), Duration> {
+        self.refill(now);
+        let weight = (weight as f64).min(self.capacity);
+        if self.tokens >= weight {
+            self.tokens -= weight;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (weight - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+
+    /// Debits weight that is only known after the fact, possibly going into deficit.
+    fn charge(&mut self, weight: u32, now: Instant) {
+        self.refill(now);
+        self.tokens -= weight as f64;
+    }
+}
+
+#[derive(Debug, Default)]
+struct AddressUsage {
+    n_requests_used: u64,
+    n_requests_cap: u64,
+    last_limited_request: Option<Instant>,
+}
+
+impl AddressUsage {
+    fn cap(&self, is_cancel: bool) -> u64 {
+        if is_cancel {
+            (self.n_requests_cap + CANCEL_EXTRA_ALLOWANCE).min(self.n_requests_cap * 2)
+        } else {
+            self.n_requests_cap
+        }
+    }
+}
+
+/// Client-side limiter modelled on the exchange's limits: a per-IP weight budget shared by all
+/// REST requests, plus a per-address budget of exchange actions that grows with traded volume.
+///
+/// Share one limiter between all clients of a process by passing the same `Arc<RateLimiter>`.
+#[derive(Debug)]
+pub struct RateLimiter {
+    mode: RateLimitMode,
+    ip_bucket: Mutex<TokenBucket>,
+    addresses: Mutex<HashMap<Address, AddressUsage>>,
+}
+
+impl RateLimiter {
+    pub fn new(mode: RateLimitMode) -> RateLimiter {
+        Self::with_ip_limit(mode, IP_WEIGHT_PER_MINUTE, Duration::from_secs(60))
+    }
+
+    pub fn with_ip_limit(mode: RateLimitMode, weight: u32, per: Duration) -> RateLimiter {
+        RateLimiter {
+            mode,
+            ip_bucket: Mutex::new(TokenBucket::new(weight, per, Instant::now())),
+            addresses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Currently available IP weight.
+    pub fn available_weight(&self) -> f64 {
+        let mut bucket = self.ip_bucket.lock().expect("rate limiter lock poisoned");
+        bucket.refill(Instant::now());
+        bucket.tokens
+    }
+
+    pub async fn acquire(&self, weight: u32) -> Result<()> {
+        loop {
+            let wait = {
+                let mut bucket = self.ip_bucket.lock().expect("rate limiter lock poisoned");
+                match bucket.try_take(weight, Instant::now()) {
+                    Ok(()) => return Ok(()),
+                    Err(wait) => wait,
+                }
+            };
+            if self.mode == RateLimitMode::Reject {
+                return Err(Error::RateLimited(format!(
+                    "IP weight budget exhausted, {wait:?} until {weight} weight is available"
+                )));
+            }
+            rt::sleep(wait).await;
+        }
+    }
+
+    pub fn charge(&self, weight: u32) {
+        self.ip_bucket
+            .lock()
+            .expect("rate limiter lock poisoned")
+            .charge(weight, Instant::now());
+    }
+
+    /// Seeds the address budget, typically from `InfoClient::user_rate_limit`.
+    pub fn set_address_usage(&self, address: Address, n_requests_used: u64, n_requests_cap: u64) {
+        let mut addresses = self.addresses.lock().expect("rate limiter lock poisoned");
+        let usage = addresses.entry(address).or_default();
+        usage.n_requests_used = n_requests_used;
+        usage.n_requests_cap = n_requests_cap;
+    }
+
+    /// Remaining exchange actions for `address`, if its usage is known.
+    pub fn address_remaining(&self, address: Address) -> Option<u64> {
+        let addresses = self.addresses.lock().expect("rate limiter lock poisoned");
+        addresses
+            .get(&address)
+            .map(|usage| usage.cap(false).saturating_sub(usage.n_requests_used))
+    }
+
+    /// Accounts for `requests` exchange actions sent on behalf of `address`. Once the address
+    /// budget is exhausted only one request per 10 seconds is let through.
+    pub async fn acquire_address(
+        &self,
+        address: Address,
+        requests: u64,
+        is_cancel: bool,
+    ) -> Result<()> {
+        loop {
+            let wait = {
+                let mut addresses = self.addresses.lock().expect("rate limiter lock poisoned");
+                let usage = addresses.entry(address).or_insert_with(|| AddressUsage {
+                    n_requests_cap: ADDRESS_INITIAL_BUFFER,
+                    ..Default::default()
+                });
+                let now = Instant::now();
+                let wait = if usage.n_requests_used + requests <= usage.cap(is_cancel) {
+                    None
+                } else {
+                    usage
+                        .last_limited_request
+                        .map(|last| ADDRESS_LIMITED_INTERVAL.saturating_sub(now - last))
+                        .filter(|wait| !wait.is_zero())
+                };
+                match wait {
+                    None => {
+                        if usage.n_requests_used + requests > usage.cap(is_cancel) {
+                            usage.last_limited_request = Some(now);
+                        }
+                        usage.n_requests_used += requests;
+                        return Ok(());
+                    }
+                    Some(wait) => wait,
+                }
+            };
+            if self.mode == RateLimitMode::Reject {
+                return Err(Error::RateLimited(format!(
+                    "address budget exhausted for {address}, next request allowed in {wait:?}"
+                )));
+            }
+            rt::sleep(wait).await;
+        }
+    }
+}
+
+/// Weight of an exchange action carrying `batch_length` orders or cancels.
+pub(crate) fn exchange_weight(batch_length: usize) -> u32 {
+    1 + (batch_length / 40) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1200, Duration::from_secs(60), start);
+        assert!(bucket.try_take(1200, start).is_ok());
+        let wait = bucket.try_take(20, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert!(bucket.try_take(20, start + Duration::from_secs(1)).is_ok());
+
+        bucket.charge(40, start + Duration::from_secs(1));
+        assert!(bucket.try_take(1, start + Duration::from_secs(2)).is_err());
+    }
+
+    #[test]
+    fn test_exchange_weight() {
+        assert_eq!(exchange_weight(1), 1);
+        assert_eq!(exchange_weight(39), 1);
+        assert_eq!(exchange_weight(40), 2);
+        assert_eq!(exchange_weight(85), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reject_mode() {
+        let limiter =
+            RateLimiter::with_ip_limit(RateLimitMode::Reject, 20, Duration::from_secs(60));
+        assert!(limiter.acquire(20).await.is_ok());
+        assert!(matches!(
+            limiter.acquire(2).await,
+            Err(Error::RateLimited(_))
+        ));
+
+        let address = Address::ZERO;
+        limiter.set_address_usage(address, 99, 100);
+        assert_eq!(limiter.address_remaining(address), Some(1));
+        assert!(limiter.acquire_address(address, 1, false).await.is_ok());
+        // Over the cap one request is still allowed, then the 10 second cooldown applies
+        assert!(limiter.acquire_address(address, 1, false).await.is_ok());
+        assert!(limiter.acquire_address(address, 1, false).await.is_err());
+        // Cancels get extra headroom
+        assert!(limiter.acquire_address(address, 1, true).await.is_ok());
+    }
+}
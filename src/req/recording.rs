@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+use crate::{helpers::now_timestamp_ms, prelude::*, Error, Message};
+
+/// One inbound payload as written by a `Recorder`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RecordedEntry {
+    /// Final outcome of a REST request, after retries
+    #[serde(rename_all = "camelCase")]
+    Rest {
+        time: u64,
+        url_path: String,
+        request: String,
+        /// Present when the request succeeded
+        response: Option<String>,
+        /// Present when the request failed
+        error: Option<String>,
+    },
+    /// Text of a websocket message as received
+    Ws { time: u64, text: String },
+}
+
+/// Writes every REST response and inbound websocket message of the clients it is attached to
+/// as JSON lines, for replaying with `Recording`.
+///
+/// Clones share the underlying writer, so one recorder can capture an `InfoClient` and an
+/// `ExchangeClient` into the same file in the order the traffic arrived. Requests are recorded
+/// as sent, signatures included.
+#[derive(Clone)]
+pub struct Recorder {
+    writer: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+impl Recorder {
+    pub fn new(writer: impl Write + Send + 'static) -> Recorder {
+        Recorder {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+        }
+    }
+
+    /// Records to a new file at `path`, replacing any existing one.
+    pub fn create(path: impl AsRef<Path>) -> Result<Recorder> {
+        let file = File::create(path).map_err(|e| Error::Io(e.to_string()))?;
+        Ok(Recorder::new(BufWriter::new(file)))
+    }
+
+    pub(crate) fn record_rest(
+        &self,
+        url_path: &str,
+        request: &str,
+        result: std::result::Result<&str, String>,
+    ) {
+        let (response, error) = match result {
+            Ok(response) => (Some(response.to_string()), None),
+            Err(err) => (None, Some(err)),
+        };
+        self.write(&RecordedEntry::Rest {
+            time: now_timestamp_ms(),
+            url_path: url_path.to_string(),
+            request: request.to_string(),
+            response,
+            error,
+        });
+    }
+
+    #[cfg_attr(not(feature = "ws"), allow(dead_code))]
+    pub(crate) fn record_ws(&self, text: &str) {
+        self.write(&RecordedEntry::Ws {
+            time: now_timestamp_ms(),
+            text: text.to_string(),
+        });
+    }
+
+    fn write(&self, entry: &RecordedEntry) {
+        let mut writer = self.writer.lock().expect("recorder lock poisoned");
+        let result = serde_json::to_writer(&mut *writer, entry)
+            .map_err(|e| e.to_string())
+            .and_then(|_| writeln!(writer).map_err(|e| e.to_string()))
+            .and_then(|_| writer.flush().map_err(|e| e.to_string()));
+        if let Err(err) = result {
+            warn!(%err, "Could not write recording");
+        }
+    }
+}
+
+/// Traffic captured by a `Recorder`, replayed into the same interfaces it came from:
+/// websocket messages into a subscription channel and REST responses through a `Replayer`
+/// attached to the clients.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    entries: Vec<RecordedEntry>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Recording> {
+        let file = File::open(path).map_err(|e| Error::Io(e.to_string()))?;
+        Recording::from_reader(BufReader::new(file))
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Recording> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line.map_err(|e| Error::Io(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line).map_err(|e| Error::JsonParse(e.to_string()))?);
+        }
+        Ok(Recording { entries })
+    }
+
+    pub fn entries(&self) -> &[RecordedEntry] {
+        &self.entries
+    }
+
+    /// Recorded websocket messages in the order they arrived.
+    pub fn messages(&self) -> Result<Vec<Message>> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                RecordedEntry::Ws { text, .. } => Some(text),
+                RecordedEntry::Rest { .. } => None,
+            })
+            .map(|text| serde_json::from_str(text).map_err(|e| Error::JsonParse(e.to_string())))
+            .collect()
+    }
+
+    /// Sends the recorded websocket messages to `sender`, as a subscription would, and
+    /// returns how many were sent.
+    pub fn replay_messages(&self, sender: &UnboundedSender<Message>) -> Result<usize> {
+        let messages = self.messages()?;
+        let count = messages.len();
+        for message in messages {
+            sender
+                .send(message)
+                .map_err(|e| Error::WsSend(e.to_string()))?;
+        }
+        Ok(count)
+    }
+
+    /// Serves the recorded REST responses to clients it is attached to.
+    pub fn replayer(&self) -> Arc<Replayer> {
+        Arc::new(Replayer::new(&self.entries))
+    }
+}
+
+/// Answers REST requests from a `Recording` instead of the network.
+///
+/// Info requests are matched by their body, so the same query gets the recorded responses in
+/// order. Exchange requests carry fresh nonces and signatures, so they are matched by action
+/// type in the order they were sent. A request with nothing left to replay fails with
+/// `Error::GenericRequest`, and recorded failures are returned as that error too.
+#[derive(Debug)]
+pub struct Replayer {
+    responses: Mutex<HashMap<String, VecDeque<std::result::Result<String, String>>>>,
+}
+
+impl Replayer {
+    fn new(entries: &[RecordedEntry]) -> Replayer {
+        let mut responses: HashMap<String, VecDeque<_>> = HashMap::new();
+        for entry in entries {
+            if let RecordedEntry::Rest {
+                url_path,
+                request,
+                response,
+                error,
+                ..
+            } = entry
+            {
+                let result = match (response, error) {
+                    (Some(response), _) => Ok(response.clone()),
+                    (None, error) => Err(error.clone().unwrap_or_default()),
+                };
+                responses
+                    .entry(replay_key(url_path, request))
+                    .or_default()
+                    .push_back(result);
+            }
+        }
+        Replayer {
+            responses: Mutex::new(responses),
+        }
+    }
+
+    pub(crate) fn respond(&self, url_path: &str, request: &str) -> Result<String> {
+        let key = replay_key(url_path, request);
+        self.responses
+            .lock()
+            .expect("replayer lock poisoned")
+            .get_mut(&key)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| Error::GenericRequest(format!("no recorded response for {key}")))?
+            .map_err(Error::GenericRequest)
+    }
+
+    /// Requests left to replay.
+    pub fn remaining(&self) -> usize {
+        self.responses
+            .lock()
+            .expect("replayer lock poisoned")
+            .values()
+            .map(VecDeque::len)
+            .sum()
+    }
+}
+
+fn replay_key(url_path: &str, request: &str) -> String {
+    // Parsing sorts keys, so bodies match regardless of field order
+    let body = serde_json::from_str::<Value>(request).ok();
+    match (url_path, body) {
+        ("/exchange", Some(body)) => {
+            format!(
+                "/exchange {}",
+                body["action"]["type"].as_str().unwrap_or_default()
+            )
+        }
+        (_, Some(body)) => format!("{url_path} {body}"),
+        (_, None) => format!("{url_path} {request}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let buffer = Shared::default();
+        let recorder = Recorder::new(buffer.clone());
+        recorder.record_rest("/info", r#"{"type":"allMids"}"#, Ok(r#"{"ETH":"2000"}"#));
+        recorder.record_rest("/info", r#"{"type":"allMids"}"#, Ok(r#"{"ETH":"2001"}"#));
+        recorder.record_ws(r#"{"channel":"allMids","data":{"mids":{"ETH":"2000.5"}}}"#);
+        recorder.record_rest(
+            "/exchange",
+            r#"{"action":{"type":"order","orders":[]},"nonce":1,"signature":{}}"#,
+            Err("timed out".to_string()),
+        );
+
+        let bytes = buffer.0.lock().unwrap().clone();
+        let recording = Recording::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(recording.entries().len(), 4);
+
+        let (sender, mut receiver) = unbounded_channel();
+        assert_eq!(recording.replay_messages(&sender).unwrap(), 1);
+        assert!(matches!(receiver.try_recv(), Ok(Message::AllMids(_))));
+
+        let replayer = recording.replayer();
+        // Field order and whitespace do not matter for info requests
+        assert_eq!(
+            replayer
+                .respond("/info", r#"{ "type": "allMids" }"#)
+                .unwrap(),
+            r#"{"ETH":"2000"}"#
+        );
+        assert_eq!(
+            replayer.respond("/info", r#"{"type":"allMids"}"#).unwrap(),
+            r#"{"ETH":"2001"}"#
+        );
+        assert!(replayer.respond("/info", r#"{"type":"allMids"}"#).is_err());
+        // A different nonce still gets the recorded failure
+        assert!(matches!(
+            replayer.respond("/exchange", r#"{"action":{"type":"order","orders":[]},"nonce":2}"#),
+            Err(Error::GenericRequest(err)) if err == "timed out"
+        ));
+        assert_eq!(replayer.remaining(), 0);
+    }
+}
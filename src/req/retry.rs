@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum FailureKind {
+    /// The connection could not be established, so the request never reached the server.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    Connect,
+    Timeout,
+    RateLimited,
+    Server,
+    Other,
+}
+
+impl FailureKind {
+    #[cfg(feature = "metrics")]
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            FailureKind::Connect => "connect",
+            FailureKind::Timeout => "timeout",
+            FailureKind::RateLimited => "rate_limited",
+            FailureKind::Server => "server",
+            FailureKind::Other => "other",
+        }
+    }
+}
+
+/// Retry behaviour for HTTP requests. `max_attempts` counts the initial attempt, so a value of
+/// 1 disables retries.
+///
+/// Exchange actions are not idempotent: they are only retried when the server provably did not
+/// process them (connection failures and rate-limit rejections), never after a timeout or a
+/// server error where the action may already have been executed.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub backoff_multiplier: f64,
+    pub retry_on_server_error: bool,
+    pub retry_on_timeout: bool,
+    pub retry_on_rate_limit: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Self::exponential(1)
+        }
+    }
+
+    pub fn exponential(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(10),
+            backoff_multiplier: 2.0,
+            retry_on_server_error: true,
+            retry_on_timeout: true,
+            retry_on_rate_limit: true,
+        }
+    }
+
+    /// Delay before the attempt following `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .backoff_multiplier
+            .max(1.0)
+            .powi(attempt.saturating_sub(1).min(i32::MAX as u32) as i32);
+        // Past `Duration::MAX` the delay is capped like any other beyond `max_backoff`
+        Duration::try_from_secs_f64(self.initial_backoff.as_secs_f64() * factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+            .max(self.initial_backoff.min(self.max_backoff))
+    }
+
+    pub(crate) fn should_retry(&self, attempt: u32, kind: FailureKind, idempotent: bool) -> bool {
+        if attempt >= self.max_attempts {
+            return false;
+        }
+        match kind {
+            FailureKind::Connect => true,
+            FailureKind::RateLimited => self.retry_on_rate_limit,
+            FailureKind::Timeout => idempotent && self.retry_on_timeout,
+            FailureKind::Server => idempotent && self.retry_on_server_error,
+            FailureKind::Other => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let policy = RetryPolicy::exponential(10);
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(400));
+        assert_eq!(policy.backoff(3), Duration::from_millis(800));
+        assert_eq!(policy.backoff(20), Duration::from_secs(10));
+        // Factors past `Duration::MAX` do not overflow
+        assert_eq!(policy.backoff(65), Duration::from_secs(10));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_exchange_actions_not_retried_when_ambiguous() {
+        let policy = RetryPolicy::exponential(3);
+        assert!(policy.should_retry(1, FailureKind::Timeout, true));
+        assert!(!policy.should_retry(1, FailureKind::Timeout, false));
+        assert!(!policy.should_retry(1, FailureKind::Server, false));
+        assert!(policy.should_retry(1, FailureKind::Connect, false));
+        assert!(policy.should_retry(2, FailureKind::RateLimited, false));
+        assert!(!policy.should_retry(3, FailureKind::Connect, true));
+        assert!(!RetryPolicy::none().should_retry(1, FailureKind::Connect, true));
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{prelude::*, Error};
+
+/// What a response carried that its decoded type did not keep.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DriftKind {
+    /// A field the type does not have
+    UnknownField { value: Value },
+    /// A value the type did not keep as received, such as an enum variant decoded as
+    /// `Unknown`
+    UnknownValue { received: Value, decoded: Value },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DriftIssue {
+    /// Location in the response, such as `data.levels[0][1].px`
+    pub path: String,
+    #[serde(flatten)]
+    pub kind: DriftKind,
+}
+
+/// Differences between a response and the type it was decoded into, found by `SchemaCheck`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SchemaDrift {
+    /// Endpoint or channel of the response, such as `info:clearinghouseState` or `ws:l2Book`
+    pub source: String,
+    pub type_name: &'static str,
+    pub issues: Vec<DriftIssue>,
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths: Vec<&str> = self
+            .issues
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect();
+        write!(
+            f,
+            "{} decoded as {} dropped {}",
+            self.source,
+            self.type_name,
+            paths.join(", ")
+        )
+    }
+}
+
+type DriftHook = Arc<dyn Fn(&SchemaDrift) + Send + Sync>;
+
+/// Opt-in check of REST and websocket responses for fields and values the SDK does not know,
+/// which it otherwise drops silently, to notice server-side schema changes early.
+///
+/// Each response is decoded, encoded back and compared with what was received. Drift is
+/// logged and passed to the hook the first time it is seen for a source and path, or with
+/// `strict` fails decoding with `Error::SchemaDrift`, dropping websocket messages. Decoding twice costs time, so the check
+/// is meant for tests, canaries and debugging rather than latency-sensitive clients.
+#[derive(Clone)]
+pub struct SchemaCheck {
+    strict: bool,
+    hook: Option<DriftHook>,
+    seen: Arc<Mutex<HashSet<(String, String)>>>,
+}
+
+impl fmt::Debug for SchemaCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SchemaCheck")
+            .field("strict", &self.strict)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SchemaCheck {
+    /// Logs drift and keeps decoding.
+    pub fn log() -> SchemaCheck {
+        SchemaCheck {
+            strict: false,
+            hook: None,
+            seen: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Fails decoding on any drift.
+    pub fn strict() -> SchemaCheck {
+        SchemaCheck {
+            strict: true,
+            ..SchemaCheck::log()
+        }
+    }
+
+    /// Also reports new drift to `hook`.
+    pub fn with_hook(mut self, hook: impl Fn(&SchemaDrift) + Send + Sync + 'static) -> Self {
+        self.hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Decodes `text` as `T`, checking it for drift.
+    pub(crate) fn decode<T: DeserializeOwned + Serialize>(
+        &self,
+        source: &str,
+        text: &str,
+    ) -> Result<T> {
+        let received: Value =
+            serde_json::from_str(text).map_err(|e| Error::JsonParse(e.to_string()))?;
+        let decoded = T::deserialize(&received).map_err(|e| Error::JsonParse(e.to_string()))?;
+        self.check(source, &received, &decoded)?;
+        Ok(decoded)
+    }
+
+    /// Checks an already decoded response against what was received.
+    pub(crate) fn check<T: Serialize>(
+        &self,
+        source: &str,
+        received: &Value,
+        decoded: &T,
+    ) -> Result<()> {
+        let Ok(encoded) = serde_json::to_value(decoded) else {
+            return Ok(());
+        };
+        let Some(drift) = schema_drift(source, std::any::type_name::<T>(), received, &encoded)
+        else {
+            return Ok(());
+        };
+        if self.strict {
+            return Err(Error::SchemaDrift(drift.to_string()));
+        }
+        let mut seen = self.seen.lock().expect("schema check lock poisoned");
+        let issues: Vec<DriftIssue> = drift
+            .issues
+            .into_iter()
+            .filter(|issue| seen.insert((source.to_string(), issue.path.clone())))
+            .collect();
+        drop(seen);
+        if issues.is_empty() {
+            return Ok(());
+        }
+        let drift = SchemaDrift { issues, ..drift };
+        warn!(
+            source,
+            type_name = drift.type_name,
+            "Response schema drift: {drift}"
+        );
+        if let Some(hook) = &self.hook {
+            hook(&drift);
+        }
+        Ok(())
+    }
+}
+
+/// Differences between a `received` response and the same response `decoded` into
+/// `type_name` and encoded back to JSON, `None` if it was kept whole.
+///
+/// Fields the encoding adds, such as absent options written as null, are not drift, and
+/// strings are compared ignoring case and numbers by value, since addresses and numbers
+/// are not always written back the way the exchange writes them.
+pub fn schema_drift(
+    source: &str,
+    type_name: &'static str,
+    received: &Value,
+    decoded: &Value,
+) -> Option<SchemaDrift> {
+    let mut issues = Vec::new();
+    diff(String::new(), received, decoded, &mut issues);
+    (!issues.is_empty()).then(|| SchemaDrift {
+        source: source.to_string(),
+        type_name,
+        issues,
+    })
+}
+
+fn diff(path: String, received: &Value, decoded: &Value, issues: &mut Vec<DriftIssue>) {
+    let number = |value: &Value| match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.parse::<f64>().ok(),
+        _ => None,
+    };
+    let kept = match (received, decoded) {
+        (Value::Null, _) => true,
+        (Value::Object(received), Value::Object(decoded)) => {
+            for (key, value) in received {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match decoded.get(key) {
+                    Some(decoded) => diff(field, value, decoded, issues),
+                    None if value.is_null() => {}
+                    None => issues.push(DriftIssue {
+                        path: field,
+                        kind: DriftKind::UnknownField {
+                            value: value.clone(),
+                        },
+                    }),
+                }
+            }
+            true
+        }
+        (Value::Array(received), Value::Array(decoded)) if received.len() == decoded.len() => {
+            for (index, (received, decoded)) in received.iter().zip(decoded).enumerate() {
+                diff(format!("{path}[{index}]"), received, decoded, issues);
+            }
+            true
+        }
+        (Value::String(received), Value::String(decoded))
+            if received.eq_ignore_ascii_case(decoded) =>
+        {
+            true
+        }
+        (Value::Bool(received), Value::Bool(decoded)) => received == decoded,
+        (Value::Number(_) | Value::String(_), Value::Number(_) | Value::String(_)) => {
+            number(received).is_some_and(|received| number(decoded) == Some(received))
+        }
+        _ => false,
+    };
+    if !kept {
+        issues.push(DriftIssue {
+            path,
+            kind: DriftKind::UnknownValue {
+                received: received.clone(),
+                decoded: decoded.clone(),
+            },
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LedgerUpdateData, Message};
+
+    #[test]
+    fn test_reports_unknown_fields_and_variants_once() {
+        let text = r#"{"time":1,"hash":"0x1","delta":{"type":"somethingNew","usdc":"1"}}"#;
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let check = SchemaCheck::log().with_hook({
+            let reports = reports.clone();
+            move |drift: &SchemaDrift| reports.lock().unwrap().push(drift.clone())
+        });
+        let update: LedgerUpdateData = check.decode("info:ledger", text).unwrap();
+        assert_eq!(update.time.as_millis(), 1);
+        check
+            .decode::<LedgerUpdateData>("info:ledger", text)
+            .unwrap();
+
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        let paths: Vec<&str> = reports[0]
+            .issues
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect();
+        assert_eq!(paths, ["delta.type", "delta.usdc"]);
+        assert!(matches!(
+            SchemaCheck::strict().decode::<LedgerUpdateData>("info:ledger", text),
+            Err(Error::SchemaDrift(_))
+        ));
+
+        // Known messages round trip whole, addresses and numbers included
+        let text = r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"2000","sz":"1","n":2}],[]]}}"#;
+        SchemaCheck::strict()
+            .decode::<Message>("ws:l2Book", text)
+            .unwrap();
+        let text =
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[],[]],"depth":5}}"#;
+        let err = SchemaCheck::strict()
+            .decode::<Message>("ws:l2Book", text)
+            .unwrap_err();
+        assert!(err.to_string().contains("data.depth"));
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::VecDeque, ops::AddAssign, sync::Mutex, time::Duration};
+
+use crate::rt::Instant;
+
+/// Window request rates are counted over.
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Requests sent by the clients, by kind.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RequestCounts {
+    /// Exchange actions, each of which may carry several orders, cancels or modifies
+    pub actions: u64,
+    pub orders: u64,
+    pub cancels: u64,
+    pub modifies: u64,
+    /// Actions other than orders, cancels and modifies, such as transfers and leverage updates
+    pub other_actions: u64,
+    pub info_requests: u64,
+    /// Weight of the info requests, as charged against the IP limit
+    pub info_weight: u64,
+}
+
+impl AddAssign for RequestCounts {
+    fn add_assign(&mut self, other: RequestCounts) {
+        self.actions += other.actions;
+        self.orders += other.orders;
+        self.cancels += other.cancels;
+        self.modifies += other.modifies;
+        self.other_actions += other.other_actions;
+        self.info_requests += other.info_requests;
+        self.info_weight += other.info_weight;
+    }
+}
+
+/// Requests over the last minute and since the counters were created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RequestStatsSnapshot {
+    pub per_minute: RequestCounts,
+    pub total: RequestCounts,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    recent: VecDeque<(Instant, RequestCounts)>,
+    total: RequestCounts,
+}
+
+/// Counts the orders, cancels, modifies and info requests sent by the clients sharing it, for
+/// monitoring how close they run to the IP and per-address rate limits.
+#[derive(Debug, Default)]
+pub struct RequestStats {
+    inner: Mutex<Inner>,
+}
+
+impl RequestStats {
+    pub fn snapshot(&self) -> RequestStatsSnapshot {
+        self.snapshot_at(Instant::now())
+    }
+
+    /// Counts an exchange action of `action_type` carrying `batch_length` requests.
+    #[cfg(feature = "exchange")]
+    pub(crate) fn record_action(&self, action_type: &str, batch_length: usize) {
+        self.record_at(action_counts(action_type, batch_length), Instant::now());
+    }
+
+    pub(crate) fn record_info(&self, weight: u32) {
+        let counts = RequestCounts {
+            info_requests: 1,
+            info_weight: weight.into(),
+            ..RequestCounts::default()
+        };
+        self.record_at(counts, Instant::now());
+    }
+
+    fn record_at(&self, counts: RequestCounts, now: Instant) {
+        let mut inner = self.lock();
+        inner.total += counts;
+        inner.recent.push_back((now, counts));
+        prune(&mut inner.recent, now);
+    }
+
+    fn snapshot_at(&self, now: Instant) -> RequestStatsSnapshot {
+        let mut inner = self.lock();
+        prune(&mut inner.recent, now);
+        let mut per_minute = RequestCounts::default();
+        for (_, counts) in &inner.recent {
+            per_minute += *counts;
+        }
+        RequestStatsSnapshot {
+            per_minute,
+            total: inner.total,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().expect("request stats lock poisoned")
+    }
+}
+
+#[cfg(feature = "exchange")]
+fn action_counts(action_type: &str, batch_length: usize) -> RequestCounts {
+    let batch_length = batch_length as u64;
+    let mut counts = RequestCounts {
+        actions: 1,
+        ..RequestCounts::default()
+    };
+    match action_type {
+        "order" => counts.orders = batch_length,
+        "cancel" | "cancelByCloid" => counts.cancels = batch_length,
+        "modify" | "batchModify" => counts.modifies = batch_length,
+        _ => counts.other_actions = 1,
+    }
+    counts
+}
+
+fn prune(recent: &mut VecDeque<(Instant, RequestCounts)>, now: Instant) {
+    while recent
+        .front()
+        .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= RATE_WINDOW)
+    {
+        recent.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "exchange")]
+    use super::*;
+
+    #[cfg(feature = "exchange")]
+    #[test]
+    fn test_counts_over_the_last_minute() {
+        let stats = RequestStats::default();
+        let start = Instant::now();
+        stats.record_at(action_counts("order", 3), start);
+        stats.record_at(
+            action_counts("cancelByCloid", 2),
+            start + Duration::from_secs(30),
+        );
+        stats.record_at(action_counts("usdSend", 1), start);
+
+        let snapshot = stats.snapshot_at(start + Duration::from_secs(61));
+        assert_eq!(snapshot.per_minute.orders, 0);
+        assert_eq!(snapshot.per_minute.cancels, 2);
+        assert_eq!(snapshot.total.orders, 3);
+        assert_eq!(snapshot.total.actions, 3);
+        assert_eq!(snapshot.total.other_actions, 1);
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use alloy::primitives::Address;
+
+#[cfg(feature = "exchange")]
+use crate::rt;
+use crate::rt::Instant;
+
+/// Once rate limited, the exchange allows an address one action every 10 seconds.
+pub const RATE_LIMITED_COOLDOWN: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThrottleState {
+    pub throttled: bool,
+    /// Wait before the next action of the longest throttled address
+    pub cooldown_remaining: Duration,
+    pub consecutive_rate_limits: u32,
+    /// Address-based rejections and 429 responses
+    pub total_rate_limits: u64,
+}
+
+#[derive(Debug)]
+struct Limited {
+    /// When the address may send its next action
+    next_allowed: Instant,
+    consecutive_rate_limits: u32,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    limited: HashMap<Address, Limited>,
+    total_rate_limits: u64,
+}
+
+/// Paces the actions of addresses the exchange reported as rate limited to one every
+/// `RATE_LIMITED_COOLDOWN`, until one is accepted again.
+///
+/// Only `/exchange` actions of the limited address wait. Cancels, which the exchange accepts
+/// beyond the limit, and info requests are never delayed, and 429 responses are only counted.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    inner: Mutex<Inner>,
+}
+
+impl Throttle {
+    pub fn state(&self) -> ThrottleState {
+        self.state_at(Instant::now())
+    }
+
+    fn state_at(&self, now: Instant) -> ThrottleState {
+        let inner = self.inner.lock().expect("throttle lock poisoned");
+        let cooldown_remaining = inner
+            .limited
+            .values()
+            .map(|limited| limited.next_allowed.saturating_duration_since(now))
+            .max()
+            .unwrap_or_default();
+        ThrottleState {
+            throttled: !cooldown_remaining.is_zero(),
+            cooldown_remaining,
+            consecutive_rate_limits: inner
+                .limited
+                .values()
+                .map(|limited| limited.consecutive_rate_limits)
+                .max()
+                .unwrap_or_default(),
+            total_rate_limits: inner.total_rate_limits,
+        }
+    }
+
+    #[cfg(feature = "exchange")]
+    /// Records an address-based rejection of an action of `address`.
+    pub(crate) fn record_rate_limited(&self, address: Address) {
+        self.record_rate_limited_at(address, Instant::now())
+    }
+
+    #[cfg(feature = "exchange")]
+    fn record_rate_limited_at(&self, address: Address, now: Instant) {
+        let mut inner = self.inner.lock().expect("throttle lock poisoned");
+        inner.total_rate_limits += 1;
+        let limited = inner.limited.entry(address).or_insert(Limited {
+            next_allowed: now,
+            consecutive_rate_limits: 0,
+        });
+        limited.consecutive_rate_limits += 1;
+        limited.next_allowed = limited.next_allowed.max(now + RATE_LIMITED_COOLDOWN);
+    }
+
+    /// Counts a 429 response, which is limited by IP rather than address.
+    pub(crate) fn record_http_rate_limited(&self) {
+        self.inner
+            .lock()
+            .expect("throttle lock poisoned")
+            .total_rate_limits += 1;
+    }
+
+    #[cfg(feature = "exchange")]
+    pub(crate) fn record_success(&self, address: Address) {
+        self.inner
+            .lock()
+            .expect("throttle lock poisoned")
+            .limited
+            .remove(&address);
+    }
+
+    #[cfg(feature = "exchange")]
+    /// Waits for the next slot of a limited `address`, taking it.
+    pub(crate) async fn wait(&self, address: Address) {
+        let delay = self.reserve_at(address, Instant::now());
+        if !delay.is_zero() {
+            rt::sleep(delay).await;
+        }
+    }
+
+    #[cfg(feature = "exchange")]
+    fn reserve_at(&self, address: Address, now: Instant) -> Duration {
+        let mut inner = self.inner.lock().expect("throttle lock poisoned");
+        let Some(limited) = inner.limited.get_mut(&address) else {
+            return Duration::ZERO;
+        };
+        let slot = limited.next_allowed.max(now);
+        limited.next_allowed = slot + RATE_LIMITED_COOLDOWN;
+        slot - now
+    }
+}
+
+#[cfg(all(test, feature = "exchange"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limited_address_is_paced() {
+        let throttle = Throttle::default();
+        let (limited, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let now = Instant::now();
+        assert!(!throttle.state_at(now).throttled);
+
+        throttle.record_rate_limited_at(limited, now);
+        throttle.record_rate_limited_at(limited, now);
+        let state = throttle.state_at(now);
+        assert!(state.throttled);
+        assert_eq!(state.cooldown_remaining, RATE_LIMITED_COOLDOWN);
+        assert_eq!(state.consecutive_rate_limits, 2);
+
+        // One action every 10 seconds, whatever the number of rejections
+        assert_eq!(throttle.reserve_at(limited, now), RATE_LIMITED_COOLDOWN);
+        assert_eq!(throttle.reserve_at(limited, now), RATE_LIMITED_COOLDOWN * 2);
+        assert_eq!(throttle.reserve_at(other, now), Duration::ZERO);
+
+        throttle.record_http_rate_limited();
+        throttle.record_success(limited);
+        let state = throttle.state_at(now);
+        assert!(!state.throttled);
+        assert_eq!(state.total_rate_limits, 3);
+        assert_eq!(throttle.reserve_at(limited, now), Duration::ZERO);
+    }
+}
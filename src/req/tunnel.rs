@@ -0,0 +1,299 @@
+//! Native TCP tunnelling through HTTP CONNECT and SOCKS5 proxies, used for websocket
+//! connections. REST requests go through reqwest's own proxy support.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use percent_encoding::percent_decode_str;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use crate::{prelude::*, req::ProxyConfig, Error};
+
+const MAX_CONNECT_RESPONSE_SIZE: usize = 8 * 1024;
+
+impl ProxyConfig {
+    /// Opens a TCP stream to `host:port` tunnelled through the proxy.
+    pub(crate) async fn connect(&self, host: &str, port: u16) -> Result<TcpStream> {
+        let url = self.parsed_url()?;
+        let proxy_host = url
+            .host_str()
+            .ok_or_else(|| Error::GenericParse("Proxy url has no host".into()))?;
+        // The URL keeps credentials percent-encoded, the proxy expects them as typed
+        let decode = |value: &str| {
+            percent_decode_str(value)
+                .decode_utf8()
+                .map(|value| value.into_owned())
+                .map_err(|_| Error::GenericParse("Proxy credentials are not UTF-8".into()))
+        };
+        let credentials = if url.username().is_empty() {
+            None
+        } else {
+            Some((
+                decode(url.username())?,
+                decode(url.password().unwrap_or_default())?,
+            ))
+        };
+
+        match url.scheme() {
+            "http" => {
+                let mut stream = open(proxy_host, url.port().unwrap_or(80)).await?;
+                http_connect(&mut stream, host, port, credentials).await?;
+                Ok(stream)
+            }
+            scheme @ ("socks5" | "socks5h") => {
+                let mut stream = open(proxy_host, url.port().unwrap_or(1080)).await?;
+                let target = if scheme == "socks5h" {
+                    SocksTarget::Domain(host)
+                } else {
+                    let addr = tokio::net::lookup_host((host, port))
+                        .await
+                        .map_err(|e| proxy_error(format!("could not resolve {host}: {e}")))?
+                        .next()
+                        .ok_or_else(|| proxy_error(format!("could not resolve {host}")))?;
+                    SocksTarget::Ip(addr.ip())
+                };
+                socks5_connect(&mut stream, target, port, credentials).await?;
+                Ok(stream)
+            }
+            scheme => Err(proxy_error(format!(
+                "{scheme} proxies are not supported for websocket connections"
+            ))),
+        }
+    }
+}
+
+fn proxy_error(msg: impl std::fmt::Display) -> Error {
+    Error::Websocket(format!("proxy: {msg}"))
+}
+
+async fn open(host: &str, port: u16) -> Result<TcpStream> {
+    TcpStream::connect((host, port))
+        .await
+        .map_err(|e| proxy_error(format!("could not connect to {host}:{port}: {e}")))
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> Result<()> {
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some((username, password)) = credentials {
+        let token = STANDARD.encode(format!("{username}:{password}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {token}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(proxy_error)?;
+
+    // Read byte by byte so nothing past the header is consumed from the tunnel
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE_SIZE {
+            return Err(proxy_error("CONNECT response too large"));
+        }
+        let byte = stream.read_u8().await.map_err(proxy_error)?;
+        response.push(byte);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(proxy_error(format!("CONNECT rejected: {status_line}"))),
+    }
+}
+
+enum SocksTarget<'a> {
+    Domain(&'a str),
+    Ip(std::net::IpAddr),
+}
+
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    target: SocksTarget<'_>,
+    port: u16,
+    credentials: Option<(String, String)>,
+) -> Result<()> {
+    const NO_AUTH: u8 = 0x00;
+    const USERNAME_PASSWORD: u8 = 0x02;
+
+    let method = if credentials.is_some() {
+        USERNAME_PASSWORD
+    } else {
+        NO_AUTH
+    };
+    stream
+        .write_all(&[0x05, 0x01, method])
+        .await
+        .map_err(proxy_error)?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await.map_err(proxy_error)?;
+    if reply != [0x05, method] {
+        return Err(proxy_error("SOCKS5 authentication method rejected"));
+    }
+
+    if let Some((username, password)) = credentials {
+        if username.len() > 255 || password.len() > 255 {
+            return Err(proxy_error("SOCKS5 credentials longer than 255 bytes"));
+        }
+        let mut auth = vec![0x01, username.len() as u8];
+        auth.extend_from_slice(username.as_bytes());
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password.as_bytes());
+        stream.write_all(&auth).await.map_err(proxy_error)?;
+        stream.read_exact(&mut reply).await.map_err(proxy_error)?;
+        if reply[1] != 0x00 {
+            return Err(proxy_error("SOCKS5 authentication failed"));
+        }
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    match target {
+        SocksTarget::Domain(host) => {
+            if host.len() > 255 {
+                return Err(proxy_error("host name longer than 255 bytes"));
+            }
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+        SocksTarget::Ip(std::net::IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        SocksTarget::Ip(std::net::IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await.map_err(proxy_error)?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header).await.map_err(proxy_error)?;
+    if header[1] != 0x00 {
+        return Err(proxy_error(format!(
+            "SOCKS5 connect failed with reply code {}",
+            header[1]
+        )));
+    }
+    // Skip the bound address and port
+    let addr_len = match header[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await.map_err(proxy_error)? as usize,
+        other => return Err(proxy_error(format!("unknown SOCKS5 address type {other}"))),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await.map_err(proxy_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn echo_after<F, Fut>(handshake: F) -> String
+    where
+        F: FnOnce(TcpStream) -> Fut + Send + 'static,
+        Fut: std::future::Future<Output = TcpStream> + Send,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut socket = handshake(socket).await;
+            let mut buf = [0u8; 4];
+            if socket.read_exact(&mut buf).await.is_ok() {
+                socket.write_all(&buf).await.unwrap();
+            }
+        });
+        addr.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_http_connect_tunnel() {
+        let proxy_addr = echo_after(|mut socket| async move {
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            assert!(request.starts_with("CONNECT api.hyperliquid.xyz:443 HTTP/1.1\r\n"));
+            let token = STANDARD.encode("us@er:p:a/s%s@");
+            assert!(request.contains(&format!("Proxy-Authorization: Basic {token}\r\n")));
+            socket
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            socket
+        })
+        .await;
+
+        let proxy = ProxyConfig::new(format!("http://{proxy_addr}")).with_auth("us@er", "p:a/s%s@");
+        let mut stream = proxy.connect("api.hyperliquid.xyz", 443).await.unwrap();
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_socks5_tunnel() {
+        let proxy_addr = echo_after(|mut socket| async move {
+            let mut greeting = [0u8; 3];
+            socket.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [0x05, 0x01, 0x02]);
+            socket.write_all(&[0x05, 0x02]).await.unwrap();
+
+            let mut auth = [0u8; 16];
+            socket.read_exact(&mut auth).await.unwrap();
+            assert_eq!(&auth, b"\x01\x05us@er\x08p:a/s%s@");
+            socket.write_all(&[0x01, 0x00]).await.unwrap();
+
+            let host = b"api.hyperliquid.xyz";
+            let mut request = vec![0u8; 5 + host.len() + 2];
+            socket.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[..5], [0x05, 0x01, 0x00, 0x03, host.len() as u8]);
+            assert_eq!(&request[5..5 + host.len()], host);
+            assert_eq!(request[5 + host.len()..], 443u16.to_be_bytes());
+            socket
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0x1f, 0x90])
+                .await
+                .unwrap();
+            socket
+        })
+        .await;
+
+        let proxy = ProxyConfig::new(format!("socks5h://us%40er:p%3Aa%2Fs%25s%40@{proxy_addr}"));
+        let mut stream = proxy.connect("api.hyperliquid.xyz", 443).await.unwrap();
+        stream.write_all(b"pong").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_connect() {
+        let proxy_addr = echo_after(|mut socket| async move {
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            socket
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+            socket
+        })
+        .await;
+
+        let proxy = ProxyConfig::new(format!("http://{proxy_addr}"));
+        assert!(proxy.connect("api.hyperliquid.xyz", 443).await.is_err());
+    }
+}
@@ -0,0 +1,526 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    BasicOrder, LedgerUpdate, LedgerUpdateData, Message, TradeInfo, UserData, UserFillsResponse,
+};
+
+/// Kind of account activity an `ActivityMonitor` did not expect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnomalyKind {
+    /// A withdrawal to the bridge while withdrawals are not allowed
+    Withdrawal,
+    /// USDC, tokens or vault deposits sent to an address not seen or allowed before
+    Transfer,
+    /// An order or fill on a coin the account never traded
+    NewCoin,
+    /// An order or fill far larger than any in the baseline
+    OversizedTrade,
+    /// More new orders in a minute than the baseline's busiest minute allows
+    OrderBurst,
+    /// A new order while the strategy is paused
+    OrderWhilePaused,
+    /// A fill while the strategy is paused
+    FillWhilePaused,
+}
+
+/// Activity that does not fit the account's baseline, possibly placed with a leaked key.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityAnomaly {
+    pub kind: AnomalyKind,
+    pub coin: Option<String>,
+    pub detail: String,
+    /// When the activity happened, in ms
+    pub time: u64,
+}
+
+type AnomalyCallback = Box<dyn FnMut(&ActivityAnomaly) + Send>;
+
+/// Learns which coins an account trades, how large its orders and fills are, how busy it
+/// gets and where it sends funds, then flags activity outside that baseline from its
+/// `userFills` (or `userEvents`), `orderUpdates` and `userNonFundingLedgerUpdates` messages.
+///
+/// The baseline is learned from subscription snapshots, from `learn_fills` with the fill
+/// history and, with `with_learning_period`, from live activity for a while after the first
+/// message. Withdrawals are always flagged unless allowed, and a new coin only once. While
+/// `pause`d, any new order or fill is flagged, so pause the strategy after cancelling its
+/// orders. The monitor only raises anomalies; acting on them, such as revoking the agent
+/// key, is left to the caller.
+pub struct ActivityMonitor {
+    account: Address,
+    learning_period: Option<Duration>,
+    /// End of the learning period, set by the first live message
+    learning_until: Option<u64>,
+    size_multiple: f64,
+    burst_multiple: f64,
+    allow_withdrawals: bool,
+    destinations: HashSet<Address>,
+    coins: HashSet<String>,
+    /// Largest order or fill notional by coin
+    max_notional: HashMap<String, f64>,
+    max_orders_per_minute: usize,
+    /// Times of the new orders of the last minute
+    recent_orders: VecDeque<u64>,
+    paused: bool,
+    on_anomaly: Option<AnomalyCallback>,
+}
+
+impl fmt::Debug for ActivityMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActivityMonitor")
+            .field("account", &self.account)
+            .field("learning_until", &self.learning_until)
+            .field("coins", &self.coins)
+            .field("max_orders_per_minute", &self.max_orders_per_minute)
+            .field("paused", &self.paused)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ActivityMonitor {
+    const MINUTE_MS: u64 = 60_000;
+
+    pub fn new(account: Address) -> ActivityMonitor {
+        ActivityMonitor {
+            account,
+            learning_period: None,
+            learning_until: None,
+            size_multiple: 3.0,
+            burst_multiple: 3.0,
+            allow_withdrawals: false,
+            destinations: HashSet::new(),
+            coins: HashSet::new(),
+            max_notional: HashMap::new(),
+            max_orders_per_minute: 0,
+            recent_orders: VecDeque::new(),
+            paused: false,
+            on_anomaly: None,
+        }
+    }
+
+    /// Also learns from live activity for `period` after the first message.
+    pub fn with_learning_period(mut self, period: Duration) -> Self {
+        self.learning_period = Some(period);
+        self
+    }
+
+    /// Flags orders and fills over `multiple` times the largest notional of their coin in
+    /// the baseline, 3 unless set.
+    pub fn with_size_multiple(mut self, multiple: f64) -> Self {
+        self.size_multiple = multiple;
+        self
+    }
+
+    /// Flags more new orders in a minute than `multiple` times the baseline's busiest
+    /// minute, 3 unless set.
+    pub fn with_burst_multiple(mut self, multiple: f64) -> Self {
+        self.burst_multiple = multiple;
+        self
+    }
+
+    pub fn with_withdrawals_allowed(mut self) -> Self {
+        self.allow_withdrawals = true;
+        self
+    }
+
+    /// Allows transfers to `destination`, such as the account's own subaccounts.
+    pub fn with_allowed_destination(mut self, destination: Address) -> Self {
+        self.destinations.insert(destination);
+        self
+    }
+
+    pub fn with_coins(mut self, coins: impl IntoIterator<Item = String>) -> Self {
+        self.coins.extend(coins);
+        self
+    }
+
+    pub fn with_on_anomaly(
+        mut self,
+        on_anomaly: impl FnMut(&ActivityAnomaly) + Send + 'static,
+    ) -> Self {
+        self.on_anomaly = Some(Box::new(on_anomaly));
+        self
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Ends the learning period early.
+    pub fn finish_learning(&mut self) {
+        self.learning_period = None;
+        self.learning_until = None;
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learning_until.is_some()
+    }
+
+    /// Coins in the baseline.
+    pub fn coins(&self) -> &HashSet<String> {
+        &self.coins
+    }
+
+    /// Adds past fills, as from `InfoClient::user_fills`, to the baseline.
+    pub fn learn_fills(&mut self, fills: &[UserFillsResponse]) {
+        for fill in fills {
+            if let Some(notional) = notional(&fill.px, &fill.sz) {
+                self.learn_trade(&fill.coin, notional);
+            }
+        }
+    }
+
+    /// Checks the account's messages, returning the anomalies found, which also go to the
+    /// `with_on_anomaly` callback.
+    pub fn handle_message(&mut self, message: &Message) -> Vec<ActivityAnomaly> {
+        let mut anomalies = Vec::new();
+        match message {
+            Message::UserFills(fills) if fills.data.user == self.account => {
+                let snapshot = fills.data.is_snapshot == Some(true);
+                for fill in &fills.data.fills {
+                    if snapshot {
+                        if let Some(notional) = notional(&fill.px, &fill.sz) {
+                            self.learn_trade(&fill.coin, notional);
+                        }
+                    } else {
+                        anomalies.extend(self.check_fill(fill));
+                    }
+                }
+            }
+            Message::User(user) => {
+                if let UserData::Fills(fills) = &user.data {
+                    for fill in fills {
+                        anomalies.extend(self.check_fill(fill));
+                    }
+                }
+            }
+            Message::OrderUpdates(updates) => {
+                for update in updates.data.iter().filter(|update| update.status == "open") {
+                    anomalies.extend(self.check_order(&update.order));
+                }
+            }
+            Message::UserNonFundingLedgerUpdates(updates) if updates.data.user == self.account => {
+                let snapshot = updates.data.is_snapshot == Some(true);
+                for update in &updates.data.non_funding_ledger_updates {
+                    if snapshot {
+                        if let Some(destination) = self.outgoing(&update.delta) {
+                            self.destinations.insert(destination);
+                        }
+                    } else {
+                        anomalies.extend(self.check_ledger(update));
+                    }
+                }
+            }
+            _ => {}
+        }
+        for anomaly in &anomalies {
+            warn!(kind = ?anomaly.kind, coin = ?anomaly.coin, "{}", anomaly.detail);
+            if let Some(on_anomaly) = &mut self.on_anomaly {
+                on_anomaly(anomaly);
+            }
+        }
+        anomalies
+    }
+
+    /// Whether live activity at `time` is still learned, starting the learning period with
+    /// the first.
+    fn learning_at(&mut self, time: u64) -> bool {
+        if let Some(period) = self.learning_period.take() {
+            self.learning_until = Some(time + period.as_millis() as u64);
+        }
+        match self.learning_until {
+            Some(until) if time < until => true,
+            Some(_) => {
+                self.learning_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    fn learn_trade(&mut self, coin: &str, notional: f64) {
+        self.coins.insert(coin.to_string());
+        let max = self.max_notional.entry(coin.to_string()).or_default();
+        *max = max.max(notional);
+    }
+
+    fn check_fill(&mut self, fill: &TradeInfo) -> Vec<ActivityAnomaly> {
+        let time = fill.time.as_millis();
+        let notional = notional(&fill.px, &fill.sz);
+        if self.learning_at(time) {
+            if let Some(notional) = notional {
+                self.learn_trade(&fill.coin, notional);
+            }
+            return Vec::new();
+        }
+        let mut anomalies = Vec::new();
+        if self.paused {
+            anomalies.push(anomaly(
+                AnomalyKind::FillWhilePaused,
+                &fill.coin,
+                format!(
+                    "Fill of {} {} at {} while paused",
+                    fill.sz, fill.coin, fill.px
+                ),
+                time,
+            ));
+        }
+        anomalies.extend(self.check_trade(&fill.coin, notional, "Fill", time));
+        anomalies
+    }
+
+    fn check_order(&mut self, order: &BasicOrder) -> Vec<ActivityAnomaly> {
+        let time = order.timestamp.as_millis();
+        let notional = notional(&order.limit_px, &order.orig_sz);
+        while self
+            .recent_orders
+            .front()
+            .is_some_and(|&t| t + Self::MINUTE_MS <= time)
+        {
+            self.recent_orders.pop_front();
+        }
+        self.recent_orders.push_back(time);
+
+        if self.learning_at(time) {
+            if let Some(notional) = notional {
+                self.learn_trade(&order.coin, notional);
+            }
+            self.max_orders_per_minute = self.max_orders_per_minute.max(self.recent_orders.len());
+            return Vec::new();
+        }
+        let mut anomalies = Vec::new();
+        if self.paused {
+            anomalies.push(anomaly(
+                AnomalyKind::OrderWhilePaused,
+                &order.coin,
+                format!(
+                    "Order {} for {} {} at {} while paused",
+                    order.oid, order.orig_sz, order.coin, order.limit_px
+                ),
+                time,
+            ));
+        }
+        anomalies.extend(self.check_trade(&order.coin, notional, "Order", time));
+        let limit =
+            (self.max_orders_per_minute.max(1) as f64 * self.burst_multiple).ceil() as usize;
+        // Flagged once as the minute crosses the limit
+        if self.recent_orders.len() == limit + 1 {
+            anomalies.push(anomaly(
+                AnomalyKind::OrderBurst,
+                &order.coin,
+                format!(
+                    "{} orders in a minute, the baseline's busiest had {}",
+                    self.recent_orders.len(),
+                    self.max_orders_per_minute
+                ),
+                time,
+            ));
+        }
+        anomalies
+    }
+
+    fn check_trade(
+        &mut self,
+        coin: &str,
+        notional: Option<f64>,
+        what: &str,
+        time: u64,
+    ) -> Option<ActivityAnomaly> {
+        if self.coins.insert(coin.to_string()) {
+            return Some(anomaly(
+                AnomalyKind::NewCoin,
+                coin,
+                format!("{what} on {coin}, which the account never traded"),
+                time,
+            ));
+        }
+        let (notional, &max) = (notional?, self.max_notional.get(coin)?);
+        (max > 0.0 && notional > max * self.size_multiple).then(|| {
+            anomaly(
+                AnomalyKind::OversizedTrade,
+                coin,
+                format!("{what} of {notional:.2} on {coin}, the baseline's largest was {max:.2}"),
+                time,
+            )
+        })
+    }
+
+    fn check_ledger(&mut self, update: &LedgerUpdateData) -> Option<ActivityAnomaly> {
+        let time = update.time.as_millis();
+        if let LedgerUpdate::Withdraw(withdraw) = &update.delta {
+            return (!self.allow_withdrawals).then(|| ActivityAnomaly {
+                kind: AnomalyKind::Withdrawal,
+                coin: None,
+                detail: format!("Withdrawal of {} USDC", withdraw.usdc),
+                time,
+            });
+        }
+        let destination = self.outgoing(&update.delta)?;
+        if self.learning_at(time) {
+            self.destinations.insert(destination);
+            return None;
+        }
+        self.destinations
+            .insert(destination)
+            .then(|| ActivityAnomaly {
+                kind: AnomalyKind::Transfer,
+                coin: None,
+                detail: format!("Transfer to {destination}, not seen or allowed before"),
+                time,
+            })
+    }
+
+    /// Where funds leave the account to, for transfers sent by it.
+    fn outgoing(&self, delta: &LedgerUpdate) -> Option<Address> {
+        match delta {
+            LedgerUpdate::InternalTransfer(transfer) if transfer.user == self.account => {
+                Some(transfer.destination)
+            }
+            LedgerUpdate::SubAccountTransfer(transfer) if transfer.user == self.account => {
+                Some(transfer.destination)
+            }
+            LedgerUpdate::SpotTransfer(transfer) if transfer.user == self.account => {
+                Some(transfer.destination)
+            }
+            LedgerUpdate::VaultDeposit(deposit) => Some(deposit.vault),
+            _ => None,
+        }
+    }
+}
+
+fn anomaly(kind: AnomalyKind, coin: &str, detail: String, time: u64) -> ActivityAnomaly {
+    ActivityAnomaly {
+        kind,
+        coin: Some(coin.to_string()),
+        detail,
+        time,
+    }
+}
+
+fn notional(px: &str, sz: &str) -> Option<f64> {
+    Some(px.parse::<f64>().ok()? * sz.parse::<f64>().ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: &str = "0x0000000000000000000000000000000000000001";
+
+    fn fills(snapshot: bool, fills: &[(&str, &str, u64)]) -> Message {
+        let fills: Vec<_> = fills
+            .iter()
+            .map(|(coin, sz, time)| {
+                serde_json::json!({
+                    "coin": coin, "side": "B", "px": "100", "sz": sz, "time": time, "hash": "0x",
+                    "startPosition": "0", "dir": "Open Long", "closedPnl": "0", "oid": 1,
+                    "crossed": true, "fee": "0", "feeToken": "USDC", "tid": time
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "channel": "userFills",
+            "data": {"isSnapshot": snapshot, "user": ACCOUNT, "fills": fills}
+        }))
+        .unwrap()
+    }
+
+    fn order(coin: &str, time: u64) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "channel": "orderUpdates",
+            "data": [{
+                "order": {"coin": coin, "side": "B", "limitPx": "100", "sz": "1", "oid": time,
+                          "timestamp": time, "origSz": "1", "cloid": null},
+                "status": "open",
+                "statusTimestamp": time
+            }]
+        }))
+        .unwrap()
+    }
+
+    fn ledger(delta: serde_json::Value) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "channel": "userNonFundingLedgerUpdates",
+            "data": {"isSnapshot": false, "user": ACCOUNT, "nonFundingLedgerUpdates": [
+                {"time": 1, "hash": "0x", "delta": delta}
+            ]}
+        }))
+        .unwrap()
+    }
+
+    fn kinds(anomalies: Vec<ActivityAnomaly>) -> Vec<AnomalyKind> {
+        anomalies.into_iter().map(|anomaly| anomaly.kind).collect()
+    }
+
+    #[test]
+    fn test_flags_activity_outside_the_baseline() {
+        let mut monitor = ActivityMonitor::new(ACCOUNT.parse().unwrap())
+            .with_learning_period(Duration::from_secs(60))
+            .with_burst_multiple(1.5);
+        assert!(monitor
+            .handle_message(&fills(true, &[("ETH", "2", 0)]))
+            .is_empty());
+        // The first minute of live orders is learned
+        for time in [1_000, 2_000] {
+            assert!(monitor.handle_message(&order("ETH", time)).is_empty());
+        }
+        assert!(monitor.is_learning());
+
+        assert!(monitor.handle_message(&order("ETH", 61_000)).is_empty());
+        assert!(!monitor.is_learning());
+        assert_eq!(
+            kinds(monitor.handle_message(&order("DOGE", 62_000))),
+            [AnomalyKind::NewCoin]
+        );
+        assert!(monitor.handle_message(&order("DOGE", 63_000)).is_empty());
+        assert_eq!(
+            kinds(monitor.handle_message(&order("ETH", 64_000))),
+            [AnomalyKind::OrderBurst]
+        );
+        assert_eq!(
+            kinds(monitor.handle_message(&fills(false, &[("ETH", "7", 65_000)]))),
+            [AnomalyKind::OversizedTrade]
+        );
+
+        monitor.pause();
+        assert_eq!(
+            kinds(monitor.handle_message(&fills(false, &[("ETH", "1", 66_000)]))),
+            [AnomalyKind::FillWhilePaused]
+        );
+        monitor.resume();
+
+        let withdraw =
+            serde_json::json!({"type": "withdraw", "usdc": "1000", "nonce": 1, "fee": "1"});
+        assert_eq!(
+            kinds(monitor.handle_message(&ledger(withdraw))),
+            [AnomalyKind::Withdrawal]
+        );
+        let transfer = serde_json::json!({
+            "type": "internalTransfer", "usdc": "1000", "user": ACCOUNT,
+            "destination": "0x0000000000000000000000000000000000000002", "fee": "1"
+        });
+        assert_eq!(
+            kinds(monitor.handle_message(&ledger(transfer.clone()))),
+            [AnomalyKind::Transfer]
+        );
+        // A destination is only flagged the first time
+        assert!(monitor.handle_message(&ledger(transfer)).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use crate::{rt::Instant, BookLevel, Message, OrderBook, ReferenceGuard, ReferenceViolation};
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum BookViolation {
+    #[error("no book received for {0}")]
+    MissingBook(String),
+    #[error("{coin} book not updated for {age:?}, limit {max_age:?}")]
+    Stale {
+        coin: String,
+        age: Duration,
+        max_age: Duration,
+    },
+    #[error("{coin} book is crossed, bid {bid} ask {ask}")]
+    Crossed { coin: String, bid: f64, ask: f64 },
+    #[error(transparent)]
+    Reference(#[from] ReferenceViolation),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Top {
+    bid: Option<f64>,
+    ask: Option<f64>,
+    updated: Instant,
+}
+
+/// Blocks orders on coins whose local book looks wrong: not updated for longer than the
+/// staleness limit, crossed, or with a mid out of line with a `ReferenceGuard`. Meant to stop
+/// quoting into bad data after a websocket stall.
+///
+/// Books come from `l2Book` and `bbo` messages passed to `handle_message`. Coins without a
+/// book yet fail the check.
+#[derive(Debug)]
+pub struct BookGuard {
+    max_age: Duration,
+    reference: Option<ReferenceGuard>,
+    tops: Mutex<HashMap<String, Top>>,
+}
+
+impl BookGuard {
+    /// Books not updated within `max_age` fail the check.
+    pub fn new(max_age: Duration) -> BookGuard {
+        BookGuard {
+            max_age,
+            reference: None,
+            tops: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Also fails books whose mid, or only quoted side, is out of line with `guard`'s reference.
+    pub fn with_reference(mut self, guard: ReferenceGuard) -> Self {
+        self.reference = Some(guard);
+        self
+    }
+
+    pub fn handle_message(&self, message: &Message) {
+        self.handle_message_at(message, Instant::now());
+    }
+
+    /// Checks the book of `coin` before an order is sent on it.
+    pub fn check(&self, coin: &str) -> Result<(), BookViolation> {
+        self.check_at(coin, Instant::now())
+    }
+
+    fn handle_message_at(&self, message: &Message, now: Instant) {
+        let (coin, bid, ask) = match message {
+            Message::L2Book(book) => {
+                let book = OrderBook::from_l2(&book.data);
+                let bid = book.best_bid().map(|(px, _)| px);
+                let ask = book.best_ask().map(|(px, _)| px);
+                (book.coin, bid, ask)
+            }
+            Message::Bbo(bbo) => {
+                let px = |index: usize| {
+                    bbo.data
+                        .bbo
+                        .get(index)
+                        .and_then(Option::as_ref)
+                        .and_then(|level: &BookLevel| level.px.parse().ok())
+                };
+                (bbo.data.coin.clone(), px(0), px(1))
+            }
+            _ => return,
+        };
+        self.tops.lock().expect("book guard lock poisoned").insert(
+            coin,
+            Top {
+                bid,
+                ask,
+                updated: now,
+            },
+        );
+    }
+
+    fn check_at(&self, coin: &str, now: Instant) -> Result<(), BookViolation> {
+        let top = *self
+            .tops
+            .lock()
+            .expect("book guard lock poisoned")
+            .get(coin)
+            .ok_or_else(|| BookViolation::MissingBook(coin.to_string()))?;
+        let age = now.saturating_duration_since(top.updated);
+        if age > self.max_age {
+            return Err(BookViolation::Stale {
+                coin: coin.to_string(),
+                age,
+                max_age: self.max_age,
+            });
+        }
+        let px = match (top.bid, top.ask) {
+            (Some(bid), Some(ask)) if bid >= ask => {
+                return Err(BookViolation::Crossed {
+                    coin: coin.to_string(),
+                    bid,
+                    ask,
+                });
+            }
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            (bid, ask) => bid.or(ask),
+        };
+        if let (Some(guard), Some(px)) = (&self.reference, px) {
+            guard.check(coin, px)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ReferencePrices;
+
+    fn book(bid: &str, ask: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"l2Book","data":{{"coin":"ETH","time":1,"levels":[[{{"px":"{bid}","sz":"1","n":1}}],[{{"px":"{ask}","sz":"1","n":1}}]]}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stale_crossed_and_reference() {
+        let prices = ReferencePrices::new("test");
+        let guard = BookGuard::new(Duration::from_millis(500))
+            .with_reference(ReferenceGuard::new(Arc::new(prices.clone()), 50.0));
+        let now = Instant::now();
+        assert_eq!(
+            guard.check_at("ETH", now),
+            Err(BookViolation::MissingBook("ETH".to_string()))
+        );
+
+        prices.set("ETH", 2000.0);
+        guard.handle_message_at(&book("1999", "2001"), now);
+        guard.check_at("ETH", now).unwrap();
+        assert!(matches!(
+            guard.check_at("ETH", now + Duration::from_millis(501)),
+            Err(BookViolation::Stale { .. })
+        ));
+
+        guard.handle_message_at(&book("2001", "2000"), now);
+        assert!(matches!(
+            guard.check_at("ETH", now),
+            Err(BookViolation::Crossed { .. })
+        ));
+
+        guard.handle_message_at(&book("2029", "2031"), now);
+        assert!(matches!(
+            guard.check_at("ETH", now),
+            Err(BookViolation::Reference(
+                ReferenceViolation::Deviation { .. }
+            ))
+        ));
+    }
+}
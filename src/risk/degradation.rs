@@ -0,0 +1,386 @@
+use std::{collections::HashMap, fmt, time::Duration};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{prelude::*, rt::Instant, Message};
+
+/// Part of the exchange API a strategy depends on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// Placing and cancelling orders
+    OrderEntry,
+    /// Books, trades, mids and candles
+    MarketData,
+    /// Fills, order updates, positions and balances
+    AccountData,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CapabilityHealth {
+    Healthy,
+    /// Recent failures, but not enough to consider it down
+    Degraded,
+    Down,
+}
+
+/// What a strategy should do while some capabilities are unhealthy, from least to most
+/// restrictive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DegradationMode {
+    /// Everything is healthy
+    Normal,
+    /// Something is degraded: quote smaller and wider
+    Cautious,
+    /// Market or account data is down: cancel quotes and hold positions until it is back
+    Hold,
+    /// Order entry is down: nothing can be sent, not even cancels
+    Halted,
+}
+
+/// A change of `DegradationMode`, with the health change that caused it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DegradationTransition {
+    pub from: DegradationMode,
+    pub to: DegradationMode,
+    pub capability: Capability,
+    pub health: CapabilityHealth,
+}
+
+type Policy = Box<dyn Fn(&HashMap<Capability, CapabilityHealth>) -> DegradationMode + Send>;
+type TransitionCallback = Box<dyn FnMut(&DegradationTransition) + Send>;
+
+#[derive(Clone, Debug)]
+struct CapabilityState {
+    health: CapabilityHealth,
+    consecutive_failures: u32,
+    last_success: Option<Instant>,
+    stale_after: Option<Duration>,
+}
+
+/// Tracks the health of order entry, market data and account data separately and derives a
+/// `DegradationMode` from them, calling the transition callback on every change, so a
+/// strategy reacts to one state machine rather than inferring outages from scattered errors.
+///
+/// A failure degrades a capability and `down_after` consecutive failures, 3 by default, take
+/// it down; a success makes it healthy again. With `with_stale_after`, a capability that has
+/// had no success for that long is down too, as when a websocket goes quiet without closing,
+/// once it has had a first success. Outcomes come from `record`, `record_result` and the
+/// websocket messages passed to `on_message`; `tick` re-evaluates staleness.
+///
+/// The default policy halts while order entry is down, holds while market or account data is
+/// down and is cautious while anything is degraded; `with_policy` replaces it.
+pub struct DegradationSupervisor {
+    down_after: u32,
+    capabilities: HashMap<Capability, CapabilityState>,
+    mode: DegradationMode,
+    policy: Option<Policy>,
+    on_transition: Option<TransitionCallback>,
+}
+
+impl fmt::Debug for DegradationSupervisor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DegradationSupervisor")
+            .field("down_after", &self.down_after)
+            .field("capabilities", &self.capabilities)
+            .field("mode", &self.mode)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for DegradationSupervisor {
+    fn default() -> Self {
+        DegradationSupervisor::new()
+    }
+}
+
+impl DegradationSupervisor {
+    pub fn new() -> DegradationSupervisor {
+        let state = CapabilityState {
+            health: CapabilityHealth::Healthy,
+            consecutive_failures: 0,
+            last_success: None,
+            stale_after: None,
+        };
+        DegradationSupervisor {
+            down_after: 3,
+            capabilities: [
+                Capability::OrderEntry,
+                Capability::MarketData,
+                Capability::AccountData,
+            ]
+            .into_iter()
+            .map(|capability| (capability, state.clone()))
+            .collect(),
+            mode: DegradationMode::Normal,
+            policy: None,
+            on_transition: None,
+        }
+    }
+
+    /// Consecutive failures after which a capability is down, 3 by default.
+    pub fn with_down_after(mut self, failures: u32) -> Self {
+        self.down_after = failures.max(1);
+        self
+    }
+
+    /// Considers `capability` down once it has had no success for `timeout`.
+    pub fn with_stale_after(mut self, capability: Capability, timeout: Duration) -> Self {
+        if let Some(state) = self.capabilities.get_mut(&capability) {
+            state.stale_after = Some(timeout);
+        }
+        self
+    }
+
+    /// Derives the mode from the health of each capability instead of the default policy.
+    pub fn with_policy(
+        mut self,
+        policy: impl Fn(&HashMap<Capability, CapabilityHealth>) -> DegradationMode + Send + 'static,
+    ) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Called on every change of mode.
+    pub fn with_on_transition(
+        mut self,
+        on_transition: impl FnMut(&DegradationTransition) + Send + 'static,
+    ) -> Self {
+        self.on_transition = Some(Box::new(on_transition));
+        self
+    }
+
+    pub fn mode(&self) -> DegradationMode {
+        self.mode
+    }
+
+    pub fn health(&self, capability: Capability) -> CapabilityHealth {
+        self.capabilities
+            .get(&capability)
+            .map_or(CapabilityHealth::Healthy, |state| state.health)
+    }
+
+    /// Whether new orders may be placed, in the normal and cautious modes.
+    pub fn can_quote(&self) -> bool {
+        self.mode <= DegradationMode::Cautious
+    }
+
+    /// Records the outcome of using `capability`, returning the updated mode.
+    pub fn record(&mut self, capability: Capability, ok: bool) -> DegradationMode {
+        self.record_at(capability, ok, Instant::now())
+    }
+
+    /// Records the outcome of a request using `capability`, returning the updated mode.
+    pub fn record_result<T>(
+        &mut self,
+        capability: Capability,
+        result: &Result<T>,
+    ) -> DegradationMode {
+        if let Err(err) = result {
+            warn!(?capability, "Request failed: {err}");
+        }
+        self.record(capability, result.is_ok())
+    }
+
+    /// Counts market data and account messages as successes of their capability, and
+    /// `NoData`, sent when the connection drops, as a failure of both.
+    pub fn on_message(&mut self, message: &Message) -> DegradationMode {
+        self.on_message_at(message, Instant::now())
+    }
+
+    /// Re-evaluates staleness, returning the updated mode.
+    pub fn tick(&mut self) -> DegradationMode {
+        self.update(None, Instant::now())
+    }
+
+    fn on_message_at(&mut self, message: &Message, now: Instant) -> DegradationMode {
+        match message {
+            Message::NoData => {
+                self.record_at(Capability::MarketData, false, now);
+                self.record_at(Capability::AccountData, false, now)
+            }
+            Message::AllMids(_)
+            | Message::Trades(_)
+            | Message::L2Book(_)
+            | Message::Candle(_)
+            | Message::Bbo(_)
+            | Message::ActiveAssetCtx(_)
+            | Message::ActiveSpotAssetCtx(_) => self.record_at(Capability::MarketData, true, now),
+            Message::User(_)
+            | Message::UserFills(_)
+            | Message::OrderUpdates(_)
+            | Message::UserFundings(_)
+            | Message::UserNonFundingLedgerUpdates(_)
+            | Message::WebData2(_)
+            | Message::ActiveAssetData(_) => self.record_at(Capability::AccountData, true, now),
+            _ => self.mode,
+        }
+    }
+
+    fn record_at(&mut self, capability: Capability, ok: bool, now: Instant) -> DegradationMode {
+        if let Some(state) = self.capabilities.get_mut(&capability) {
+            if ok {
+                state.consecutive_failures = 0;
+                state.last_success = Some(now);
+            } else {
+                state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            }
+        }
+        self.update(Some(capability), now)
+    }
+
+    fn update(&mut self, cause: Option<Capability>, now: Instant) -> DegradationMode {
+        let mut changed = None;
+        for (&capability, state) in &mut self.capabilities {
+            let stale = state.stale_after.is_some_and(|timeout| {
+                state
+                    .last_success
+                    .is_some_and(|at| now.saturating_duration_since(at) > timeout)
+            });
+            let health = if stale || state.consecutive_failures >= self.down_after {
+                CapabilityHealth::Down
+            } else if state.consecutive_failures > 0 {
+                CapabilityHealth::Degraded
+            } else {
+                CapabilityHealth::Healthy
+            };
+            if health != state.health {
+                info!(
+                    ?capability,
+                    "Health changed from {:?} to {health:?}", state.health
+                );
+                state.health = health;
+                if changed.is_none() || cause == Some(capability) {
+                    changed = Some((capability, health));
+                }
+            }
+        }
+        let Some((capability, health)) = changed else {
+            return self.mode;
+        };
+
+        let healths: HashMap<Capability, CapabilityHealth> = self
+            .capabilities
+            .iter()
+            .map(|(&capability, state)| (capability, state.health))
+            .collect();
+        let mode = match &self.policy {
+            Some(policy) => policy(&healths),
+            None => default_policy(&healths),
+        };
+        if mode != self.mode {
+            let transition = DegradationTransition {
+                from: std::mem::replace(&mut self.mode, mode),
+                to: mode,
+                capability,
+                health,
+            };
+            warn!(
+                "Degradation mode changed from {:?} to {mode:?} as {capability:?} became {health:?}",
+                transition.from
+            );
+            if let Some(on_transition) = &mut self.on_transition {
+                on_transition(&transition);
+            }
+        }
+        self.mode
+    }
+}
+
+fn default_policy(healths: &HashMap<Capability, CapabilityHealth>) -> DegradationMode {
+    let health = |capability| {
+        healths
+            .get(&capability)
+            .copied()
+            .unwrap_or(CapabilityHealth::Healthy)
+    };
+    if health(Capability::OrderEntry) == CapabilityHealth::Down {
+        DegradationMode::Halted
+    } else if health(Capability::MarketData) == CapabilityHealth::Down
+        || health(Capability::AccountData) == CapabilityHealth::Down
+    {
+        DegradationMode::Hold
+    } else if healths
+        .values()
+        .any(|health| *health != CapabilityHealth::Healthy)
+    {
+        DegradationMode::Cautious
+    } else {
+        DegradationMode::Normal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_degradation_modes() {
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = transitions.clone();
+        let mut supervisor = DegradationSupervisor::new()
+            .with_down_after(2)
+            .with_stale_after(Capability::MarketData, Duration::from_secs(5))
+            .with_on_transition(move |transition| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((transition.to, transition.capability))
+            });
+        let start = Instant::now();
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[],[]]}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            supervisor.on_message_at(&book, start),
+            DegradationMode::Normal
+        );
+        // Account data failing degrades, then holds until it answers again
+        supervisor.record_at(Capability::AccountData, false, start);
+        assert_eq!(supervisor.mode(), DegradationMode::Cautious);
+        supervisor.record_at(Capability::AccountData, false, start);
+        assert_eq!(supervisor.mode(), DegradationMode::Hold);
+        assert!(!supervisor.can_quote());
+        supervisor.record_at(Capability::AccountData, true, start);
+        assert_eq!(supervisor.mode(), DegradationMode::Normal);
+
+        // A quiet book stream goes stale, and order entry down outranks it
+        let later = start + Duration::from_secs(6);
+        assert_eq!(supervisor.update(None, later), DegradationMode::Hold);
+        assert_eq!(
+            supervisor.health(Capability::MarketData),
+            CapabilityHealth::Down
+        );
+        supervisor.record_at(Capability::OrderEntry, false, later);
+        supervisor.record_at(Capability::OrderEntry, false, later);
+        assert_eq!(supervisor.mode(), DegradationMode::Halted);
+        supervisor.record_at(Capability::OrderEntry, true, later);
+        assert_eq!(
+            supervisor.on_message_at(&book, later),
+            DegradationMode::Normal
+        );
+
+        use Capability::*;
+        use DegradationMode::*;
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![
+                (Cautious, AccountData),
+                (Hold, AccountData),
+                (Normal, AccountData),
+                (Hold, MarketData),
+                (Halted, OrderEntry),
+                (Hold, OrderEntry),
+                (Normal, MarketData),
+            ]
+        );
+    }
+}
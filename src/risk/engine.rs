@@ -0,0 +1,542 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::Mutex,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use tracing::warn;
+
+use crate::{
+    exchange::pair_statuses,
+    prelude::*,
+    rt::{Instant, MaybeSend},
+    BookGuard, BookViolation, ClientCancelRequest, ClientCancelRequestCloid, ClientOrder,
+    ClientOrderRequest, Error, Exchange, ExchangeClient, ExchangeDataStatus,
+    ExchangeResponseStatus, InfoClient, Message, ReferenceGuard, ReferenceViolation, TradeInfo,
+    UserData,
+};
+
+const NOTIONAL_WINDOW: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug, Default)]
+pub struct RiskLimits {
+    /// Largest absolute position by coin, counting resting orders on the same side
+    pub max_position: HashMap<String, f64>,
+    pub max_open_orders: Option<usize>,
+    /// Notional submitted over any rolling minute
+    pub max_notional_per_minute: Option<f64>,
+    /// Furthest a limit price may be from the mid, in bps
+    pub price_collar_bps: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum RiskViolation {
+    #[error("kill switch is tripped")]
+    KillSwitch,
+    #[error("{coin} position would reach {resulting}, limit {limit}")]
+    MaxPosition {
+        coin: String,
+        limit: f64,
+        resulting: f64,
+    },
+    #[error("open orders would reach {resulting}, limit {limit}")]
+    MaxOpenOrders { limit: usize, resulting: usize },
+    #[error("notional over the last minute would reach {resulting}, limit {limit}")]
+    MaxNotionalPerMinute { limit: f64, resulting: f64 },
+    #[error("{coin} price {px} is {bps:.1} bps from mid {mid}")]
+    PriceCollar {
+        coin: String,
+        px: f64,
+        mid: f64,
+        bps: f64,
+    },
+    #[error("{coin} order size {sz} is above the limit of {limit}")]
+    MaxOrderSize { coin: String, limit: f64, sz: f64 },
+    #[error("no mid known for {0}")]
+    MissingMid(String),
+    #[error(transparent)]
+    Reference(#[from] ReferenceViolation),
+    #[error(transparent)]
+    Book(#[from] BookViolation),
+}
+
+#[derive(Clone, Debug)]
+struct RestingOrder {
+    coin: String,
+    /// Signed remaining size
+    szi: f64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    killed: bool,
+    positions: HashMap<String, f64>,
+    open_orders: HashMap<u64, RestingOrder>,
+    mids: HashMap<String, f64>,
+    submitted: VecDeque<(Instant, f64)>,
+}
+
+/// Pre-trade checks in front of an `ExchangeClient`.
+///
+/// Orders sent through the engine are checked against `RiskLimits` and rejected with
+/// `Error::RiskCheck` if they would breach one. Positions, resting orders and mids come from
+/// messages passed to `handle_message` (`userFills`, `orderUpdates`, `allMids`) and from
+/// `reconcile`. Tripping the kill switch cancels every open order and blocks new ones until
+/// it is reset.
+#[derive(Debug)]
+pub struct RiskEngine {
+    exchange: ExchangeClient,
+    limits: RiskLimits,
+    reference: Option<ReferenceGuard>,
+    book_guard: Option<BookGuard>,
+    state: Mutex<State>,
+}
+
+impl RiskEngine {
+    pub fn new(exchange: ExchangeClient, limits: RiskLimits) -> RiskEngine {
+        RiskEngine {
+            exchange,
+            limits,
+            reference: None,
+            book_guard: None,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Rejects orders while the mid of their coin is out of line with `guard`'s reference or
+    /// the reference is stale. Needs mids, as for the price collar.
+    pub fn with_reference(mut self, guard: ReferenceGuard) -> Self {
+        self.reference = Some(guard);
+        self
+    }
+
+    /// Rejects orders on coins whose book is stale, crossed or out of line with its reference.
+    /// Needs `l2Book` or `bbo` messages for every coin traded.
+    pub fn with_book_guard(mut self, guard: BookGuard) -> Self {
+        self.book_guard = Some(guard);
+        self
+    }
+
+    pub fn exchange(&self) -> &ExchangeClient {
+        &self.exchange
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("risk engine lock poisoned")
+    }
+
+    fn user(&self) -> Address {
+        self.exchange
+            .vault_address
+            .unwrap_or(self.exchange.wallet.address())
+    }
+
+    pub fn is_killed(&self) -> bool {
+        self.state().killed
+    }
+
+    /// Blocks new orders and cancels all open orders of the account.
+    pub async fn kill(&self) -> Result<()> {
+        self.state().killed = true;
+        warn!("Kill switch tripped, cancelling all open orders");
+        let open_orders = self.exchange.info_client().open_orders(self.user()).await?;
+        if open_orders.is_empty() {
+            return Ok(());
+        }
+        let cancels = open_orders
+            .into_iter()
+            .map(|order| ClientCancelRequest {
+                asset: order.coin,
+                oid: order.oid,
+            })
+            .collect();
+        self.exchange.bulk_cancel(cancels, None).await?;
+        Ok(())
+    }
+
+    pub fn reset_kill_switch(&self) {
+        self.state().killed = false;
+    }
+
+    pub fn set_mid(&self, coin: &str, mid: f64) {
+        self.state().mids.insert(coin.to_string(), mid);
+    }
+
+    pub fn position(&self, coin: &str) -> f64 {
+        self.state()
+            .positions
+            .get(coin)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn open_order_count(&self) -> usize {
+        self.state().open_orders.len()
+    }
+
+    pub async fn order(&self, order: ClientOrderRequest) -> Result<ExchangeResponseStatus> {
+        self.bulk_order(vec![order]).await
+    }
+
+    pub async fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> Result<ExchangeResponseStatus> {
+        self.check_at(&orders, Instant::now())
+            .map_err(Error::RiskCheck)?;
+        let response = self.exchange.bulk_order(orders.clone(), None).await?;
+
+        let mut state = self.state();
+        for status in pair_statuses(orders, response.clone()) {
+            if let Ok(ExchangeDataStatus::Resting(resting)) = status.status {
+                let sz = if status.request.is_buy {
+                    status.request.sz
+                } else {
+                    -status.request.sz
+                };
+                state.open_orders.insert(
+                    resting.oid,
+                    RestingOrder {
+                        coin: status.request.asset,
+                        szi: sz,
+                    },
+                );
+            }
+        }
+        Ok(response)
+    }
+
+    /// Runs the checks for `orders` without sending them. Orders that pass are counted
+    /// toward the notional limit, since the caller is expected to send them.
+    pub fn check(&self, orders: &[ClientOrderRequest]) -> std::result::Result<(), RiskViolation> {
+        self.check_at(orders, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        orders: &[ClientOrderRequest],
+        now: Instant,
+    ) -> std::result::Result<(), RiskViolation> {
+        let mut state = self.state();
+        if state.killed {
+            return Err(RiskViolation::KillSwitch);
+        }
+
+        if let Some(limit) = self.limits.max_open_orders {
+            let resulting = state.open_orders.len() + orders.len();
+            if resulting > limit {
+                return Err(RiskViolation::MaxOpenOrders { limit, resulting });
+            }
+        }
+
+        let mut batch: HashMap<&str, f64> = HashMap::new();
+        for order in orders {
+            if let (Some(collar_bps), ClientOrder::Limit(_)) =
+                (self.limits.price_collar_bps, &order.order_type)
+            {
+                let mid = *state
+                    .mids
+                    .get(&order.asset)
+                    .ok_or_else(|| RiskViolation::MissingMid(order.asset.clone()))?;
+                let bps = (order.limit_px - mid).abs() / mid * 10_000.0;
+                if bps > collar_bps {
+                    return Err(RiskViolation::PriceCollar {
+                        coin: order.asset.clone(),
+                        px: order.limit_px,
+                        mid,
+                        bps,
+                    });
+                }
+            }
+
+            if let Some(guard) = &self.reference {
+                let mid = *state
+                    .mids
+                    .get(&order.asset)
+                    .ok_or_else(|| RiskViolation::MissingMid(order.asset.clone()))?;
+                guard.check(&order.asset, mid)?;
+            }
+
+            if let Some(guard) = &self.book_guard {
+                guard.check(&order.asset)?;
+            }
+
+            if order.reduce_only {
+                continue;
+            }
+            let Some(&limit) = self.limits.max_position.get(&order.asset) else {
+                continue;
+            };
+            let sz = if order.is_buy { order.sz } else { -order.sz };
+            let pending = batch.entry(&order.asset).or_default();
+            *pending += sz;
+            let same_side_resting: f64 = state
+                .open_orders
+                .values()
+                .filter(|o| o.coin == order.asset && o.szi * sz > 0.0)
+                .map(|o| o.szi)
+                .sum();
+            let resulting = (state
+                .positions
+                .get(&order.asset)
+                .copied()
+                .unwrap_or_default()
+                + same_side_resting
+                + *pending)
+                .abs();
+            if resulting > limit {
+                return Err(RiskViolation::MaxPosition {
+                    coin: order.asset.clone(),
+                    limit,
+                    resulting,
+                });
+            }
+        }
+
+        let notional: f64 = orders.iter().map(|o| o.limit_px * o.sz).sum();
+        if let Some(limit) = self.limits.max_notional_per_minute {
+            while state
+                .submitted
+                .front()
+                .is_some_and(|(at, _)| now.duration_since(*at) >= NOTIONAL_WINDOW)
+            {
+                state.submitted.pop_front();
+            }
+            let resulting = state.submitted.iter().map(|(_, n)| n).sum::<f64>() + notional;
+            if resulting > limit {
+                return Err(RiskViolation::MaxNotionalPerMinute { limit, resulting });
+            }
+        }
+        state.submitted.push_back((now, notional));
+        Ok(())
+    }
+
+    pub fn handle_message(&self, message: &Message) {
+        if let Some(guard) = &self.book_guard {
+            guard.handle_message(message);
+        }
+        let mut state = self.state();
+        match message {
+            Message::AllMids(all_mids) => {
+                for (coin, mid) in &all_mids.data.mids {
+                    if let Ok(mid) = mid.parse() {
+                        state.mids.insert(coin.clone(), mid);
+                    }
+                }
+            }
+            Message::OrderUpdates(updates) => {
+                for update in &updates.data {
+                    if update.status == "open" {
+                        let sz = update.order.sz.parse::<f64>().unwrap_or_default();
+                        state.open_orders.insert(
+                            update.order.oid,
+                            RestingOrder {
+                                coin: update.order.coin.clone(),
+                                szi: update.order.side.sign() * sz,
+                            },
+                        );
+                    } else {
+                        state.open_orders.remove(&update.order.oid);
+                    }
+                }
+            }
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                fills
+                    .data
+                    .fills
+                    .iter()
+                    .for_each(|fill| apply_fill(&mut state, fill));
+            }
+            Message::User(user) => {
+                if let UserData::Fills(fills) = &user.data {
+                    fills.iter().for_each(|fill| apply_fill(&mut state, fill));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replaces tracked positions and resting orders with the exchange's.
+    pub async fn reconcile(&self, info: &InfoClient) -> Result<()> {
+        let user = self.user();
+        let user_state = info.user_state(user).await?;
+        let open_orders = info.open_orders(user).await?;
+
+        let mut state = self.state();
+        state.positions = user_state
+            .asset_positions
+            .into_iter()
+            .filter_map(|p| Some((p.position.coin, p.position.szi.parse().ok()?)))
+            .collect();
+        state.open_orders = open_orders
+            .into_iter()
+            .map(|o| {
+                let sz = o.sz.parse::<f64>().unwrap_or_default();
+                let szi = o.side.sign() * sz;
+                (o.oid, RestingOrder { coin: o.coin, szi })
+            })
+            .collect();
+        Ok(())
+    }
+}
+
+/// Orders go through the checks, cancels straight to the `ExchangeClient`.
+impl Exchange for RiskEngine {
+    fn address(&self) -> Address {
+        self.user()
+    }
+
+    fn bulk_order(
+        &self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        RiskEngine::bulk_order(self, orders)
+    }
+
+    fn bulk_cancel(
+        &self,
+        cancels: Vec<ClientCancelRequest>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        Exchange::bulk_cancel(&self.exchange, cancels)
+    }
+
+    fn bulk_cancel_by_cloid(
+        &self,
+        cancels: Vec<ClientCancelRequestCloid>,
+    ) -> impl Future<Output = Result<ExchangeResponseStatus>> + MaybeSend {
+        Exchange::bulk_cancel_by_cloid(&self.exchange, cancels)
+    }
+}
+
+fn apply_fill(state: &mut State, fill: &TradeInfo) {
+    let Ok(sz) = fill.sz.parse::<f64>() else {
+        return;
+    };
+    let szi = fill.side.sign() * sz;
+    *state.positions.entry(fill.coin.clone()).or_default() += szi;
+    if let Some(resting) = state.open_orders.get_mut(&fill.oid) {
+        resting.szi -= szi;
+        if resting.szi.abs() < crate::EPSILON {
+            state.open_orders.remove(&fill.oid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::{ClientLimit, Meta, ReferencePrices, SpotMeta, Tif};
+
+    async fn engine(limits: RiskLimits) -> RiskEngine {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"ETH","szDecimals":4,"maxLeverage":25}]}"#,
+        )
+        .unwrap();
+        let wallet: PrivateKeySigner =
+            "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .unwrap();
+        let exchange = ExchangeClient::builder()
+            .wallet(wallet)
+            .meta(meta)
+            .spot_meta(SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            })
+            .build()
+            .await
+            .unwrap();
+        RiskEngine::new(exchange, limits)
+    }
+
+    fn order(is_buy: bool, limit_px: f64, sz: f64) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limits() {
+        let engine = engine(RiskLimits {
+            max_position: HashMap::from([("ETH".to_string(), 2.0)]),
+            max_open_orders: Some(3),
+            max_notional_per_minute: Some(5_000.0),
+            price_collar_bps: Some(100.0),
+        })
+        .await;
+        assert_eq!(
+            engine.check(&[order(true, 2_000.0, 1.0)]),
+            Err(RiskViolation::MissingMid("ETH".to_string()))
+        );
+        engine.set_mid("ETH", 2_000.0);
+        assert!(matches!(
+            engine.check(&[order(true, 2_100.0, 1.0)]),
+            Err(RiskViolation::PriceCollar { .. })
+        ));
+        assert!(matches!(
+            engine.check(&[order(true, 2_000.0, 1.5), order(true, 2_000.0, 1.0)]),
+            Err(RiskViolation::MaxPosition { .. })
+        ));
+        let orders: Vec<_> = (0..4).map(|_| order(true, 2_000.0, 0.1)).collect();
+        assert!(matches!(
+            engine.check(&orders),
+            Err(RiskViolation::MaxOpenOrders { .. })
+        ));
+
+        let now = Instant::now();
+        engine.check_at(&[order(false, 2_000.0, 2.0)], now).unwrap();
+        assert!(matches!(
+            engine.check_at(&[order(false, 2_000.0, 2.0)], now),
+            Err(RiskViolation::MaxNotionalPerMinute { .. })
+        ));
+        engine
+            .check_at(&[order(false, 2_000.0, 2.0)], now + NOTIONAL_WINDOW)
+            .unwrap();
+
+        engine.state().killed = true;
+        assert_eq!(
+            engine.check(&[order(true, 2_000.0, 0.1)]),
+            Err(RiskViolation::KillSwitch)
+        );
+        engine.reset_kill_switch();
+        assert!(!engine.is_killed());
+    }
+
+    #[tokio::test]
+    async fn test_reference_guard() {
+        let prices = ReferencePrices::new("test");
+        let engine = engine(RiskLimits::default())
+            .await
+            .with_reference(ReferenceGuard::new(Arc::new(prices.clone()), 50.0));
+        engine.set_mid("ETH", 2_000.0);
+        assert!(matches!(
+            engine.check(&[order(true, 2_000.0, 0.1)]),
+            Err(RiskViolation::Reference(ReferenceViolation::Stale { .. }))
+        ));
+        prices.set("ETH", 2_020.0);
+        assert!(matches!(
+            engine.check(&[order(true, 2_000.0, 0.1)]),
+            Err(RiskViolation::Reference(
+                ReferenceViolation::Deviation { .. }
+            ))
+        ));
+        prices.set("ETH", 2_005.0);
+        engine.check(&[order(true, 2_000.0, 0.1)]).unwrap();
+    }
+}
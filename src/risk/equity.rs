@@ -0,0 +1,425 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, InfoClient, Message, Subscription, TradeInfo, UserData,
+    UserFunding, UserStateResponse, EPSILON,
+};
+
+const DAY_MS: u64 = 86_400_000;
+
+/// A drawdown limit of `EquityTracker` being crossed.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum DrawdownBreach {
+    #[error(
+        "equity {equity:.2} is {:.2}% below its peak {peak:.2}, limit {:.2}%",
+        .drawdown * 100.0,
+        .limit * 100.0
+    )]
+    Drawdown {
+        equity: f64,
+        peak: f64,
+        drawdown: f64,
+        limit: f64,
+    },
+    #[error("lost {loss:.2} today from {day_start:.2}, limit {limit:.2}")]
+    DailyLoss {
+        equity: f64,
+        day_start: f64,
+        loss: f64,
+        limit: f64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EquitySnapshot {
+    pub time: u64,
+    pub equity: f64,
+    /// USDC less what was paid for open positions, `totalRawUsd` on the exchange
+    pub cash: f64,
+    /// Highest equity within the peak window
+    pub peak: f64,
+    /// Fraction of the peak lost, 0 at the peak
+    pub drawdown: f64,
+    /// Equity change since the start of the UTC day
+    pub daily_pnl: f64,
+}
+
+type BreachCallback = Box<dyn FnMut(&DrawdownBreach) + Send>;
+
+/// Keeps a perp account's equity up to date between clearinghouse snapshots, with its peak,
+/// drawdown and daily PnL.
+///
+/// Equity is the cash, which fills, fees and funding move, plus positions marked at the
+/// latest mids, as the exchange computes the account value. Seed it with `reconcile` or
+/// `apply_state`, then pass `userFills`, `userFundings`, `userEvents` and `allMids` messages
+/// to `handle_message`. Deposits and withdrawals are not PnL and are passed to
+/// `record_transfer`. Spot fills are ignored.
+///
+/// A limit breach calls the breach callback and is returned by `handle_message` once, until
+/// equity recovers within the limit, so a caller can trip `RiskEngine::kill` on it.
+pub struct EquityTracker {
+    user: Address,
+    cash: Option<f64>,
+    positions: HashMap<String, (f64, Option<f64>)>,
+    seen_fills: HashSet<u64>,
+    peak_window: Option<Duration>,
+    /// Equity samples that can still be the peak, decreasing
+    peaks: VecDeque<(u64, f64)>,
+    day: Option<(u64, f64)>,
+    max_drawdown: Option<f64>,
+    max_daily_loss: Option<f64>,
+    breached: [bool; 2],
+    on_breach: Option<BreachCallback>,
+}
+
+impl fmt::Debug for EquityTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EquityTracker")
+            .field("user", &self.user)
+            .field("cash", &self.cash)
+            .field("positions", &self.positions)
+            .field("peak_window", &self.peak_window)
+            .field("max_drawdown", &self.max_drawdown)
+            .field("max_daily_loss", &self.max_daily_loss)
+            .field("breached", &self.breached)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EquityTracker {
+    pub fn new(user: Address) -> EquityTracker {
+        EquityTracker {
+            user,
+            cash: None,
+            positions: HashMap::new(),
+            seen_fills: HashSet::new(),
+            peak_window: None,
+            peaks: VecDeque::new(),
+            day: None,
+            max_drawdown: None,
+            max_daily_loss: None,
+            breached: [false; 2],
+            on_breach: None,
+        }
+    }
+
+    /// Measures drawdown from the highest equity within `window` rather than since the start.
+    pub fn with_peak_window(mut self, window: Duration) -> Self {
+        self.peak_window = Some(window);
+        self
+    }
+
+    /// Breaches when equity falls more than `fraction` below its peak.
+    pub fn with_max_drawdown(mut self, fraction: f64) -> Self {
+        self.max_drawdown = Some(fraction);
+        self
+    }
+
+    /// Breaches when equity falls more than `usd` below the start of the UTC day.
+    pub fn with_max_daily_loss(mut self, usd: f64) -> Self {
+        self.max_daily_loss = Some(usd);
+        self
+    }
+
+    pub fn with_on_breach(
+        mut self,
+        on_breach: impl FnMut(&DrawdownBreach) + Send + 'static,
+    ) -> Self {
+        self.on_breach = Some(Box::new(on_breach));
+        self
+    }
+
+    /// Subscriptions whose messages should be passed to `handle_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        vec![
+            Subscription::UserFills { user: self.user },
+            Subscription::UserFundings { user: self.user },
+            Subscription::AllMids,
+        ]
+    }
+
+    /// Current equity, once seeded.
+    pub fn equity(&self) -> Option<f64> {
+        let marked: f64 = self
+            .positions
+            .values()
+            .map(|(szi, mark_px)| szi * mark_px.unwrap_or_default())
+            .sum();
+        Some(self.cash? + marked)
+    }
+
+    pub fn snapshot(&self) -> Option<EquitySnapshot> {
+        self.snapshot_at(now_timestamp_ms())
+    }
+
+    /// Fetches the clearinghouse state and seeds from it.
+    pub async fn reconcile(&mut self, info: &InfoClient) -> Result<Option<DrawdownBreach>> {
+        let state = info.user_state(self.user).await?;
+        Ok(self.apply_state(&state))
+    }
+
+    /// Replaces cash, positions and marks with the exchange's.
+    pub fn apply_state(&mut self, state: &UserStateResponse) -> Option<DrawdownBreach> {
+        self.apply_state_at(state, now_timestamp_ms())
+    }
+
+    /// Moves cash by a deposit, or a withdrawal when negative, without counting it as PnL.
+    pub fn record_transfer(&mut self, usd: f64) {
+        if let Some(cash) = &mut self.cash {
+            *cash += usd;
+        }
+        for (_, equity) in self.peaks.iter_mut().chain(self.day.as_mut()) {
+            *equity += usd;
+        }
+    }
+
+    /// Updates from `message`, returning a limit it newly breached.
+    pub fn handle_message(&mut self, message: &Message) -> Option<DrawdownBreach> {
+        self.handle_message_at(message, now_timestamp_ms())
+    }
+
+    fn handle_message_at(&mut self, message: &Message, now: u64) -> Option<DrawdownBreach> {
+        match message {
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                fills
+                    .data
+                    .fills
+                    .iter()
+                    .for_each(|fill| self.apply_fill(fill));
+            }
+            Message::UserFundings(fundings) if !fundings.data.is_snapshot.unwrap_or(false) => {
+                let fundings = &fundings.data.fundings;
+                fundings
+                    .iter()
+                    .for_each(|funding| self.apply_funding(funding));
+            }
+            Message::User(user) => match &user.data {
+                UserData::Fills(fills) => fills.iter().for_each(|fill| self.apply_fill(fill)),
+                UserData::Funding(funding) => self.apply_funding(funding),
+                _ => return None,
+            },
+            Message::AllMids(all_mids) => {
+                for (coin, (_, mark_px)) in self.positions.iter_mut() {
+                    if let Some(Ok(mid)) = all_mids.data.mids.get(coin).map(|mid| mid.parse()) {
+                        *mark_px = Some(mid);
+                    }
+                }
+            }
+            _ => return None,
+        }
+        self.update(now)
+    }
+
+    fn apply_fill(&mut self, fill: &TradeInfo) {
+        if fill.coin.contains('/') || fill.coin.starts_with('@') {
+            return;
+        }
+        if !self.seen_fills.insert(fill.tid) {
+            return;
+        }
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            warn!("Could not parse fill {}", fill.tid);
+            return;
+        };
+        let signed = if fill.side.is_buy() { sz } else { -sz };
+        let fee = fill.fee.parse::<f64>().unwrap_or_default();
+        if let Some(cash) = &mut self.cash {
+            *cash -= signed * px + fee;
+        }
+        let (szi, mark_px) = self.positions.entry(fill.coin.clone()).or_default();
+        *szi += signed;
+        mark_px.get_or_insert(px);
+    }
+
+    fn apply_funding(&mut self, funding: &UserFunding) {
+        match (funding.usdc.parse::<f64>(), &mut self.cash) {
+            (Ok(usdc), Some(cash)) => *cash += usdc,
+            (Ok(_), None) => {}
+            (Err(_), _) => warn!("Could not parse funding for {}", funding.coin),
+        }
+    }
+
+    fn apply_state_at(&mut self, state: &UserStateResponse, now: u64) -> Option<DrawdownBreach> {
+        let parse = |value: &str| value.parse::<f64>().unwrap_or_default();
+        self.cash = Some(parse(&state.margin_summary.total_raw_usd));
+        self.positions = state
+            .asset_positions
+            .iter()
+            .map(|asset_position| {
+                let position = &asset_position.position;
+                let szi = parse(&position.szi);
+                let mark_px =
+                    (szi.abs() > EPSILON).then(|| parse(&position.position_value) / szi.abs());
+                (position.coin.clone(), (szi, mark_px))
+            })
+            .collect();
+        self.update(now)
+    }
+
+    fn update(&mut self, now: u64) -> Option<DrawdownBreach> {
+        let equity = self.equity()?;
+        while self.peaks.back().is_some_and(|&(_, peak)| peak <= equity) {
+            self.peaks.pop_back();
+        }
+        self.peaks.push_back((now, equity));
+        if let Some(window) = self.peak_window {
+            let start = now.saturating_sub(window.as_millis() as u64);
+            while self.peaks.len() > 1 && self.peaks[0].0 < start {
+                self.peaks.pop_front();
+            }
+        }
+        if self.day.is_none_or(|(day, _)| day != now / DAY_MS) {
+            self.day = Some((now / DAY_MS, equity));
+        }
+
+        let snapshot = self.snapshot_at(now)?;
+        let breaches = [
+            self.max_drawdown
+                .filter(|&limit| snapshot.drawdown > limit)
+                .map(|limit| DrawdownBreach::Drawdown {
+                    equity,
+                    peak: snapshot.peak,
+                    drawdown: snapshot.drawdown,
+                    limit,
+                }),
+            self.max_daily_loss
+                .filter(|&limit| -snapshot.daily_pnl > limit)
+                .map(|limit| DrawdownBreach::DailyLoss {
+                    equity,
+                    day_start: equity - snapshot.daily_pnl,
+                    loss: -snapshot.daily_pnl,
+                    limit,
+                }),
+        ];
+        let mut new_breach = None;
+        for (breached, breach) in self.breached.iter_mut().zip(breaches) {
+            let was_breached = std::mem::replace(breached, breach.is_some());
+            let Some(breach) = breach.filter(|_| !was_breached) else {
+                continue;
+            };
+            warn!("Drawdown limit breached: {breach}");
+            if let Some(on_breach) = &mut self.on_breach {
+                on_breach(&breach);
+            }
+            new_breach.get_or_insert(breach);
+        }
+        new_breach
+    }
+
+    fn snapshot_at(&self, now: u64) -> Option<EquitySnapshot> {
+        let equity = self.equity()?;
+        let peak = self
+            .peaks
+            .front()
+            .map_or(equity, |&(_, peak)| peak.max(equity));
+        let day_start = self.day.map_or(equity, |(_, start)| start);
+        Some(EquitySnapshot {
+            time: now,
+            equity,
+            cash: self.cash?,
+            peak,
+            drawdown: if peak > 0.0 {
+                (peak - equity) / peak
+            } else {
+                0.0
+            },
+            daily_pnl: equity - day_start,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    fn state() -> UserStateResponse {
+        let summary = serde_json::json!({
+            "accountValue": "1200",
+            "totalMarginUsed": "0",
+            "totalNtlPos": "2000",
+            "totalRawUsd": "-800",
+        });
+        serde_json::from_value(serde_json::json!({
+            "assetPositions": [{"type": "oneWay", "position": {
+                "coin": "ETH", "entryPx": "1900", "liquidationPx": null,
+                "leverage": {"type": "cross", "value": 10},
+                "marginUsed": "200", "positionValue": "2000", "returnOnEquity": "0",
+                "szi": "1", "unrealizedPnl": "100", "maxLeverage": 50,
+                "cumFunding": {"allTime": "0", "sinceChange": "0", "sinceOpen": "0"},
+            }}],
+            "crossMarginSummary": summary,
+            "marginSummary": summary,
+            "withdrawable": "1000",
+        }))
+        .unwrap()
+    }
+
+    fn mid(px: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "channel": "allMids",
+            "data": {"mids": {"ETH": px}},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_equity_drawdown_and_daily_loss() {
+        let breaches = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&breaches);
+        let mut tracker = EquityTracker::new(Address::ZERO)
+            .with_max_drawdown(0.1)
+            .with_max_daily_loss(150.0)
+            .with_on_breach(move |breach| seen.lock().unwrap().push(breach.clone()));
+        let day = 20_000 * DAY_MS;
+        assert_eq!(tracker.handle_message_at(&mid("2000"), day), None);
+        assert_eq!(tracker.apply_state_at(&state(), day), None);
+        assert_eq!(tracker.equity(), Some(1200.0));
+
+        assert_eq!(tracker.handle_message_at(&mid("2100"), day + 1), None);
+        // Selling half at 2100 with a 1 USDC fee leaves equity unchanged but for the fee
+        let fill: Message = serde_json::from_str(
+            r#"{"channel":"user","data":{"fills":[
+                {"coin":"ETH","side":"A","px":"2100","sz":"0.5","time":2,"hash":"0x0","startPosition":"1","dir":"Close Long","closedPnl":"100","oid":1,"cloid":null,"crossed":true,"fee":"1","feeToken":"USDC","tid":1}
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(tracker.handle_message_at(&fill, day + 2), None);
+        tracker.handle_message_at(&fill, day + 2);
+        assert!((tracker.equity().unwrap() - 1299.0).abs() < EPSILON);
+
+        // A deposit moves the peak with the equity
+        tracker.record_transfer(100.0);
+        let snapshot = tracker.snapshot_at(day + 3).unwrap();
+        assert!((snapshot.peak - 1400.0).abs() < EPSILON);
+        assert!((snapshot.daily_pnl - 99.0).abs() < EPSILON);
+
+        // Down 100 from the peak: within both limits
+        assert_eq!(tracker.handle_message_at(&mid("1900"), day + 4), None);
+        let breach = tracker.handle_message_at(&mid("1500"), day + 5).unwrap();
+        assert!(matches!(breach, DrawdownBreach::Drawdown { .. }));
+        // Each breach is reported once
+        assert_eq!(tracker.handle_message_at(&mid("1499"), day + 6), None);
+        assert_eq!(breaches.lock().unwrap().len(), 2);
+        assert!(matches!(
+            breaches.lock().unwrap()[1],
+            DrawdownBreach::DailyLoss { .. }
+        ));
+
+        // A new day starts from the current equity
+        let snapshot = tracker.snapshot_at(day + DAY_MS).unwrap();
+        assert!(snapshot.drawdown > 0.1);
+        tracker.handle_message_at(&mid("1499"), day + DAY_MS);
+        assert_eq!(tracker.snapshot_at(day + DAY_MS).unwrap().daily_pnl, 0.0);
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use serde::Serialize;
+#[cfg(feature = "ws")]
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+#[cfg(feature = "ws")]
+use crate::prelude::*;
+use crate::{
+    helpers::now_timestamp_ms, AccountSnapshot, InfoClient, Message, Subscription, EPSILON,
+};
+
+/// Equity, margin and exposure of one monitored account, from its latest snapshot.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSummary {
+    pub user: Address,
+    /// Perp account value
+    pub equity: f64,
+    pub margin_used: f64,
+    /// Margin used as a fraction of equity, zero for empty accounts
+    pub margin_usage: f64,
+    pub withdrawable: f64,
+    /// Notional of open positions
+    pub position_notional: f64,
+    pub positions: usize,
+    /// Notional of resting orders at their limit prices
+    pub open_order_notional: f64,
+    pub open_orders: usize,
+    /// Position and resting order notional over equity, zero for empty accounts
+    pub leverage: f64,
+    /// When the snapshot was taken, in ms
+    pub time: u64,
+}
+
+impl AccountSummary {
+    pub fn from_snapshot(snapshot: &AccountSnapshot) -> AccountSummary {
+        let margin = &snapshot.state.margin_summary;
+        let equity = parse(&margin.account_value);
+        let margin_used = parse(&margin.total_margin_used);
+        let position_notional = parse(&margin.total_ntl_pos);
+        let open_order_notional = snapshot
+            .open_orders
+            .iter()
+            .map(|order| parse(&order.sz) * parse(&order.limit_px))
+            .sum();
+        let ratio = |value: f64| {
+            if equity > EPSILON {
+                value / equity
+            } else {
+                0.0
+            }
+        };
+        AccountSummary {
+            user: snapshot.user,
+            equity,
+            margin_used,
+            margin_usage: ratio(margin_used),
+            withdrawable: parse(&snapshot.state.withdrawable),
+            position_notional,
+            positions: snapshot
+                .state
+                .asset_positions
+                .iter()
+                .filter(|p| parse(&p.position.szi).abs() > EPSILON)
+                .count(),
+            open_order_notional,
+            open_orders: snapshot.open_orders.len(),
+            leverage: ratio(position_notional + open_order_notional),
+            time: snapshot.time.as_millis(),
+        }
+    }
+}
+
+/// Sums over every monitored account with a snapshot.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetTotals {
+    pub accounts: usize,
+    pub equity: f64,
+    pub margin_used: f64,
+    pub position_notional: f64,
+    pub open_order_notional: f64,
+}
+
+/// Tracks the equity, margin usage and open risk of many accounts at once, such as
+/// sub-accounts or customer wallets.
+///
+/// Accounts are refreshed from `InfoClient::account_snapshot` by `refresh`, which should be
+/// called in a loop: it snapshots accounts with fills since their last snapshot and those not
+/// refreshed within the poll interval. Fills arrive on `userFills` subscriptions, split into
+/// shards of a few users each since the API caps the users one connection can follow;
+/// `subscribe` opens a connection per shard, or pass each of `subscription_shards` to a
+/// client of your own, and feed the messages to `on_message`.
+#[derive(Clone, Debug)]
+pub struct FleetMonitor {
+    users: Vec<Address>,
+    users_per_shard: usize,
+    poll_interval: Duration,
+    accounts: HashMap<Address, AccountSummary>,
+    dirty: HashSet<Address>,
+}
+
+impl FleetMonitor {
+    /// Polls every account every 30 seconds, with 10 users per websocket shard.
+    pub fn new(users: impl IntoIterator<Item = Address>) -> FleetMonitor {
+        let mut monitor = FleetMonitor {
+            users: Vec::new(),
+            users_per_shard: 10,
+            poll_interval: Duration::from_secs(30),
+            accounts: HashMap::new(),
+            dirty: HashSet::new(),
+        };
+        for user in users {
+            monitor.add(user);
+        }
+        monitor
+    }
+
+    pub fn with_users_per_shard(mut self, users_per_shard: usize) -> Self {
+        self.users_per_shard = users_per_shard.max(1);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Starts monitoring `user`, snapshotted on the next `refresh`. Existing shards are not
+    /// resubscribed.
+    pub fn add(&mut self, user: Address) {
+        if !self.users.contains(&user) {
+            self.users.push(user);
+            self.dirty.insert(user);
+        }
+    }
+
+    pub fn remove(&mut self, user: Address) {
+        self.users.retain(|u| *u != user);
+        self.accounts.remove(&user);
+        self.dirty.remove(&user);
+    }
+
+    pub fn users(&self) -> &[Address] {
+        &self.users
+    }
+
+    /// `userFills` subscriptions for the monitored users, one list per connection.
+    pub fn subscription_shards(&self) -> Vec<Vec<Subscription>> {
+        self.users
+            .chunks(self.users_per_shard)
+            .map(|users| {
+                users
+                    .iter()
+                    .map(|&user| Subscription::UserFills { user })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Subscribes each shard on its own connection, sharing `info`'s HTTP client, and sends
+    /// every message to `sender`. The returned clients must be kept alive.
+    #[cfg(feature = "ws")]
+    pub async fn subscribe(
+        &self,
+        info: &InfoClient,
+        sender: UnboundedSender<Message>,
+    ) -> Result<Vec<InfoClient>> {
+        let mut clients = Vec::new();
+        for shard in self.subscription_shards() {
+            let client = InfoClient::with_http_client(info.http_client.clone(), true)
+                .with_ws_url(info.ws_url.clone());
+            for subscription in shard {
+                client.subscribe(subscription, sender.clone()).await?;
+            }
+            clients.push(client);
+        }
+        Ok(clients)
+    }
+
+    /// Marks accounts with new fills for the next `refresh`; other messages are ignored.
+    pub fn on_message(&mut self, message: &Message) {
+        if let Message::UserFills(fills) = message {
+            let user = fills.data.user;
+            if self.users.contains(&user) {
+                self.dirty.insert(user);
+            }
+        }
+    }
+
+    /// Snapshots accounts with fills since their last snapshot or older than the poll
+    /// interval, returning those refreshed. An account whose snapshot fails keeps its last
+    /// summary and is retried on the next call.
+    pub async fn refresh(&mut self, info: &InfoClient) -> Vec<Address> {
+        let now = now_timestamp_ms();
+        let stale: Vec<Address> = self
+            .users
+            .iter()
+            .copied()
+            .filter(|user| {
+                self.dirty.contains(user)
+                    || self.accounts.get(user).is_none_or(|account| {
+                        now.saturating_sub(account.time) >= self.poll_interval.as_millis() as u64
+                    })
+            })
+            .collect();
+        let mut refreshed = Vec::with_capacity(stale.len());
+        for user in stale {
+            match info.account_snapshot(user).await {
+                Ok(snapshot) => {
+                    self.apply_snapshot(&snapshot);
+                    refreshed.push(user);
+                }
+                Err(err) => warn!(%user, %err, "Could not snapshot account"),
+            }
+        }
+        refreshed
+    }
+
+    /// Replaces the summary of the snapshot's account, if monitored.
+    pub fn apply_snapshot(&mut self, snapshot: &AccountSnapshot) {
+        if !self.users.contains(&snapshot.user) {
+            return;
+        }
+        self.dirty.remove(&snapshot.user);
+        self.accounts
+            .insert(snapshot.user, AccountSummary::from_snapshot(snapshot));
+    }
+
+    pub fn account(&self, user: Address) -> Option<&AccountSummary> {
+        self.accounts.get(&user)
+    }
+
+    /// Summaries of accounts snapshotted so far, in the order they were added.
+    pub fn accounts(&self) -> Vec<&AccountSummary> {
+        self.users
+            .iter()
+            .filter_map(|user| self.accounts.get(user))
+            .collect()
+    }
+
+    pub fn totals(&self) -> FleetTotals {
+        self.accounts
+            .values()
+            .fold(FleetTotals::default(), |mut totals, account| {
+                totals.accounts += 1;
+                totals.equity += account.equity;
+                totals.margin_used += account.margin_used;
+                totals.position_notional += account.position_notional;
+                totals.open_order_notional += account.open_order_notional;
+                totals
+            })
+    }
+}
+
+fn parse(value: &str) -> f64 {
+    value.parse().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(user: Address, account_value: &str, orders: &str) -> AccountSnapshot {
+        AccountSnapshot {
+            user,
+            time: 1.into(),
+            state: serde_json::from_str(&format!(
+                r#"{{"assetPositions":[],"withdrawable":"500",
+                "crossMarginSummary":{{"accountValue":"{account_value}","totalMarginUsed":"200","totalNtlPos":"1000","totalRawUsd":"0"}},
+                "marginSummary":{{"accountValue":"{account_value}","totalMarginUsed":"200","totalNtlPos":"1000","totalRawUsd":"0"}}}}"#
+            ))
+            .unwrap(),
+            balances: Vec::new(),
+            open_orders: serde_json::from_str(orders).unwrap(),
+            fills: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_fleet_summaries_and_shards() {
+        let users: Vec<Address> = (1..=3u8).map(Address::repeat_byte).collect();
+        let mut monitor = FleetMonitor::new(users.clone()).with_users_per_shard(2);
+        let shards = monitor.subscription_shards();
+        assert_eq!(shards.iter().map(Vec::len).collect::<Vec<_>>(), vec![2, 1]);
+
+        monitor.apply_snapshot(&snapshot(
+            users[0],
+            "1000",
+            r#"[{"coin":"ETH","limitPx":"2000","oid":1,"side":"B","sz":"0.5","timestamp":1,"cloid":null}]"#,
+        ));
+        monitor.apply_snapshot(&snapshot(users[1], "0", "[]"));
+        // Not monitored
+        monitor.apply_snapshot(&snapshot(Address::ZERO, "1000", "[]"));
+
+        let account = monitor.account(users[0]).unwrap();
+        assert!((account.margin_usage - 0.2).abs() < EPSILON);
+        assert!((account.open_order_notional - 1000.0).abs() < EPSILON);
+        assert!((account.leverage - 2.0).abs() < EPSILON);
+        assert!(monitor.account(users[1]).unwrap().leverage.abs() < EPSILON);
+        assert_eq!(monitor.accounts().len(), 2);
+
+        let totals = monitor.totals();
+        assert_eq!(totals.accounts, 2);
+        assert!((totals.equity - 1000.0).abs() < EPSILON);
+        assert!((totals.position_notional - 2000.0).abs() < EPSILON);
+
+        monitor.remove(users[0]);
+        assert!(monitor.account(users[0]).is_none());
+        assert_eq!(monitor.subscription_shards().len(), 1);
+    }
+}
@@ -0,0 +1,264 @@
+use std::{collections::HashMap, fmt};
+
+use alloy::primitives::Address;
+use tracing::{info, warn};
+
+use crate::{
+    prelude::*, ExchangeClient, ExchangeResponseStatus, InfoClient, UserStateResponse, EPSILON,
+};
+
+/// Smallest top-up sent, in USDC
+const MIN_TOP_UP: f64 = 0.01;
+
+#[derive(Clone, Debug)]
+pub struct IsolatedMarginConfig {
+    /// Distance of the liquidation price from the mark, as a fraction of the mark, below
+    /// which margin is added
+    pub buffer: f64,
+    /// Distance restored by a top-up, at least `buffer`
+    pub target_buffer: f64,
+    /// Largest single top-up, in USDC
+    pub max_top_up: f64,
+    /// Largest total added to one coin over the keeper's lifetime, in USDC
+    pub max_total_per_coin: f64,
+    /// Coins to manage, all isolated positions if empty
+    pub coins: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarginAlert {
+    ToppedUp {
+        coin: String,
+        amount: f64,
+        /// Liquidation distance before the top-up
+        distance: f64,
+    },
+    /// Less than needed was added, or nothing, because a cap or the withdrawable balance was
+    /// reached
+    Capped {
+        coin: String,
+        needed: f64,
+        amount: f64,
+        distance: f64,
+    },
+    Failed {
+        coin: String,
+        amount: f64,
+        error: String,
+    },
+}
+
+type AlertCallback = Box<dyn FnMut(&MarginAlert) + Send>;
+
+/// A top-up planned by `IsolatedMarginKeeper::plan`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopUp {
+    pub coin: String,
+    pub amount: f64,
+    /// Amount that would restore `target_buffer`, before caps
+    pub needed: f64,
+    pub distance: f64,
+}
+
+/// Keeps isolated positions' liquidation prices at least `buffer` away from the mark by
+/// calling `updateIsolatedMargin`, polling the clearinghouse state in `check`.
+///
+/// The margin needed is worked out from the liquidation price the exchange reports, scaled
+/// linearly with the first-tier maintenance rate, so on tiered assets a top-up can land
+/// slightly short of `target_buffer`; the next check tops up the rest.
+pub struct IsolatedMarginKeeper {
+    user: Address,
+    config: IsolatedMarginConfig,
+    added: HashMap<String, f64>,
+    alert: Option<AlertCallback>,
+}
+
+impl fmt::Debug for IsolatedMarginKeeper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IsolatedMarginKeeper")
+            .field("user", &self.user)
+            .field("config", &self.config)
+            .field("added", &self.added)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IsolatedMarginKeeper {
+    pub fn new(user: Address, config: IsolatedMarginConfig) -> IsolatedMarginKeeper {
+        IsolatedMarginKeeper {
+            user,
+            config,
+            added: HashMap::new(),
+            alert: None,
+        }
+    }
+
+    /// Called for every top-up, capped top-up and failure.
+    pub fn with_alert(mut self, alert: impl FnMut(&MarginAlert) + Send + 'static) -> Self {
+        self.alert = Some(Box::new(alert));
+        self
+    }
+
+    /// Total added so far by coin.
+    pub fn added(&self) -> &HashMap<String, f64> {
+        &self.added
+    }
+
+    /// Top-ups needed for `state`, within the caps and the withdrawable balance.
+    pub fn plan(&self, state: &UserStateResponse) -> Vec<TopUp> {
+        let mut withdrawable: f64 = state.withdrawable.parse().unwrap_or_default();
+        let target_buffer = self.config.target_buffer.max(self.config.buffer);
+        let mut top_ups = Vec::new();
+        for position in state.asset_positions.iter().map(|p| &p.position) {
+            if position.leverage.type_string != "isolated"
+                || (!self.config.coins.is_empty() && !self.config.coins.contains(&position.coin))
+            {
+                continue;
+            }
+            let szi: f64 = position.szi.parse().unwrap_or_default();
+            let value: f64 = position.position_value.parse().unwrap_or_default();
+            let Some(liquidation_px) = position
+                .liquidation_px
+                .as_deref()
+                .and_then(|px| px.parse::<f64>().ok())
+            else {
+                continue;
+            };
+            if szi.abs() < EPSILON || value <= 0.0 {
+                continue;
+            }
+            let mark_px = value / szi.abs();
+            let distance = (mark_px - liquidation_px).abs() / mark_px;
+            if distance >= self.config.buffer {
+                continue;
+            }
+
+            let maintenance_rate = 1.0 / (2.0 * position.max_leverage.max(1) as f64);
+            let needed = (target_buffer - distance)
+                * szi.abs()
+                * mark_px
+                * (1.0 - maintenance_rate * szi.signum());
+            let added = self.added.get(&position.coin).copied().unwrap_or_default();
+            let amount = needed
+                .min(self.config.max_top_up)
+                .min(self.config.max_total_per_coin - added)
+                .min(withdrawable)
+                .max(0.0);
+            withdrawable -= amount;
+            top_ups.push(TopUp {
+                coin: position.coin.clone(),
+                amount,
+                needed,
+                distance,
+            });
+        }
+        top_ups
+    }
+
+    /// Fetches the clearinghouse state and sends the planned top-ups, returning them.
+    pub async fn check(
+        &mut self,
+        info: &InfoClient,
+        exchange: &ExchangeClient,
+    ) -> Result<Vec<TopUp>> {
+        let state = info.user_state(self.user).await?;
+        let top_ups = self.plan(&state);
+        for top_up in &top_ups {
+            if top_up.amount < top_up.needed - MIN_TOP_UP {
+                warn!(
+                    "Isolated margin top-up for {} capped at {} of {}",
+                    top_up.coin, top_up.amount, top_up.needed
+                );
+                self.emit(MarginAlert::Capped {
+                    coin: top_up.coin.clone(),
+                    needed: top_up.needed,
+                    amount: top_up.amount,
+                    distance: top_up.distance,
+                });
+            }
+            if top_up.amount < MIN_TOP_UP {
+                continue;
+            }
+
+            let error = match exchange
+                .update_isolated_margin(top_up.amount, &top_up.coin, None)
+                .await
+            {
+                Ok(ExchangeResponseStatus::Ok(_)) => None,
+                Ok(ExchangeResponseStatus::Err(err)) => Some(err.to_string()),
+                Err(err) => Some(err.to_string()),
+            };
+            match error {
+                None => {
+                    info!(
+                        "Added {} margin to {} at {:.2}% from liquidation",
+                        top_up.amount,
+                        top_up.coin,
+                        top_up.distance * 100.0
+                    );
+                    *self.added.entry(top_up.coin.clone()).or_default() += top_up.amount;
+                    self.emit(MarginAlert::ToppedUp {
+                        coin: top_up.coin.clone(),
+                        amount: top_up.amount,
+                        distance: top_up.distance,
+                    });
+                }
+                Some(error) => {
+                    warn!("Could not add margin to {}: {error}", top_up.coin);
+                    self.emit(MarginAlert::Failed {
+                        coin: top_up.coin.clone(),
+                        amount: top_up.amount,
+                        error,
+                    });
+                }
+            }
+        }
+        Ok(top_ups)
+    }
+
+    fn emit(&mut self, alert: MarginAlert) {
+        if let Some(callback) = &mut self.alert {
+            callback(&alert);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(liquidation_px: &str, withdrawable: &str) -> UserStateResponse {
+        serde_json::from_str(&format!(
+            r#"{{"assetPositions":[{{"type":"oneWay","position":{{"coin":"ETH","entryPx":"2000","leverage":{{"type":"isolated","value":10,"rawUsd":"-1800"}},"liquidationPx":"{liquidation_px}","marginUsed":"200","positionValue":"2000","returnOnEquity":"0","szi":"1","unrealizedPnl":"0","maxLeverage":25,"cumFunding":{{"allTime":"0","sinceOpen":"0","sinceChange":"0"}}}}}}],
+            "crossMarginSummary":{{"accountValue":"1000","totalMarginUsed":"0","totalNtlPos":"0","totalRawUsd":"1000"}},
+            "marginSummary":{{"accountValue":"1200","totalMarginUsed":"200","totalNtlPos":"2000","totalRawUsd":"1000"}},
+            "withdrawable":"{withdrawable}"}}"#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_top_up_with_caps() {
+        let keeper = IsolatedMarginKeeper::new(
+            Address::ZERO,
+            IsolatedMarginConfig {
+                buffer: 0.1,
+                target_buffer: 0.15,
+                max_top_up: 500.0,
+                max_total_per_coin: 1000.0,
+                coins: Vec::new(),
+            },
+        );
+        assert!(keeper.plan(&state("1700", "1000")).is_empty());
+
+        // 5% from liquidation, 10% more of a 2000 notional less the 2% maintenance share
+        let top_ups = keeper.plan(&state("1900", "1000"));
+        assert_eq!(top_ups.len(), 1);
+        assert!((top_ups[0].distance - 0.05).abs() < 1e-9);
+        assert!((top_ups[0].needed - 196.0).abs() < 1e-9);
+        assert!((top_ups[0].amount - 196.0).abs() < 1e-9);
+
+        let top_ups = keeper.plan(&state("1900", "50"));
+        assert_eq!(top_ups[0].amount, 50.0);
+    }
+}
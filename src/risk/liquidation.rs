@@ -0,0 +1,363 @@
+use std::{collections::HashMap, fmt};
+
+use alloy::primitives::Address;
+use tracing::{info, warn};
+
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Exchange, ExchangeDataStatus, ExchangeResponseStatus, InfoClient, Message,
+    Meta, RoundingMode, Strategy, Subscription, Tif, UserStateResponse, EPSILON,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LiquidationThreshold {
+    /// Distance of the liquidation price from the mid, as a fraction of the mid
+    pub distance: f64,
+    /// Fraction of the position closed when the threshold is crossed
+    pub reduce_fraction: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiquidationAlert {
+    pub coin: String,
+    /// Index of the crossed threshold, ordered from the furthest from liquidation
+    pub level: usize,
+    pub threshold: f64,
+    pub distance: f64,
+    pub mid: f64,
+    pub liquidation_px: f64,
+    /// Size sent to reduce the position, if the threshold has a de-risking action
+    pub reduce_sz: Option<f64>,
+}
+
+type AlertCallback = Box<dyn FnMut(&LiquidationAlert) + Send>;
+
+#[derive(Clone, Debug)]
+struct WatchedPosition {
+    szi: f64,
+    liquidation_px: f64,
+    /// Deepest threshold crossed, re-armed once the distance recovers
+    level: Option<usize>,
+}
+
+/// Watches the distance from each position's liquidation price to the mid on every
+/// `allMids` message, calling the alert callback when a threshold is crossed and sending a
+/// reduce-only `Ioc` order for thresholds with a `reduce_fraction`.
+///
+/// Positions and liquidation prices come from the clearinghouse state through `refresh`,
+/// which should be called periodically and after fills, as liquidation prices of cross
+/// positions move with the rest of the account. Each threshold fires once until the distance
+/// recovers above it.
+pub struct LiquidationWatchdog {
+    user: Address,
+    thresholds: Vec<LiquidationThreshold>,
+    slippage_bps: f64,
+    sz_decimals: HashMap<String, u32>,
+    positions: HashMap<String, WatchedPosition>,
+    alert: Option<AlertCallback>,
+}
+
+impl fmt::Debug for LiquidationWatchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiquidationWatchdog")
+            .field("user", &self.user)
+            .field("thresholds", &self.thresholds)
+            .field("slippage_bps", &self.slippage_bps)
+            .field("positions", &self.positions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LiquidationWatchdog {
+    pub fn new(user: Address, mut thresholds: Vec<LiquidationThreshold>) -> LiquidationWatchdog {
+        thresholds.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+        LiquidationWatchdog {
+            user,
+            thresholds,
+            slippage_bps: 50.0,
+            sz_decimals: HashMap::new(),
+            positions: HashMap::new(),
+            alert: None,
+        }
+    }
+
+    pub fn with_alert(mut self, alert: impl FnMut(&LiquidationAlert) + Send + 'static) -> Self {
+        self.alert = Some(Box::new(alert));
+        self
+    }
+
+    /// How far through the mid de-risking orders are priced, 50 bps by default.
+    pub fn with_slippage_bps(mut self, slippage_bps: f64) -> Self {
+        self.slippage_bps = slippage_bps;
+        self
+    }
+
+    /// Size decimals for de-risking orders, fetched by `refresh` if not set.
+    pub fn with_meta(mut self, meta: &Meta) -> Self {
+        self.set_meta(meta);
+        self
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        vec![Subscription::AllMids]
+    }
+
+    pub async fn refresh(&mut self, info: &InfoClient) -> Result<()> {
+        if self.sz_decimals.is_empty() {
+            let meta = info.meta().await?;
+            self.set_meta(&meta);
+        }
+        let state = info.user_state(self.user).await?;
+        self.update_positions(&state);
+        Ok(())
+    }
+
+    /// Replaces the watched positions with those in `state`, keeping crossed thresholds.
+    pub fn update_positions(&mut self, state: &UserStateResponse) {
+        let mut positions = HashMap::new();
+        for position in state.asset_positions.iter().map(|p| &p.position) {
+            let szi: f64 = position.szi.parse().unwrap_or_default();
+            let Some(liquidation_px) = position
+                .liquidation_px
+                .as_deref()
+                .and_then(|px| px.parse::<f64>().ok())
+            else {
+                continue;
+            };
+            if szi.abs() < EPSILON {
+                continue;
+            }
+            let level = self.positions.get(&position.coin).and_then(|p| p.level);
+            positions.insert(
+                position.coin.clone(),
+                WatchedPosition {
+                    szi,
+                    liquidation_px,
+                    level,
+                },
+            );
+        }
+        self.positions = positions;
+    }
+
+    /// Distance to liquidation of `coin` at `mid`, as a fraction of the mid.
+    pub fn distance(&self, coin: &str, mid: f64) -> Option<f64> {
+        let position = self.positions.get(coin)?;
+        Some((mid - position.liquidation_px).abs() / mid)
+    }
+
+    fn set_meta(&mut self, meta: &Meta) {
+        self.sz_decimals = meta
+            .universe
+            .iter()
+            .map(|asset| (asset.name.clone(), asset.sz_decimals))
+            .collect();
+    }
+
+    async fn check<E: Exchange>(&mut self, exchange: &E, coin: &str, mid: f64) -> Result<()> {
+        let Some(distance) = self.distance(coin, mid) else {
+            return Ok(());
+        };
+        let crossed = self
+            .thresholds
+            .iter()
+            .rposition(|threshold| distance < threshold.distance);
+        let Some(position) = self.positions.get_mut(coin) else {
+            return Ok(());
+        };
+        let previous = position.level;
+        position.level = crossed;
+        let Some(level) = crossed.filter(|level| previous.is_none_or(|p| *level > p)) else {
+            return Ok(());
+        };
+        let (szi, liquidation_px) = (position.szi, position.liquidation_px);
+
+        // Actions of every threshold crossed since the last check, deepest last
+        let first = previous.map_or(0, |p| p + 1);
+        let reduce_fraction = self.thresholds[first..=level]
+            .iter()
+            .filter_map(|threshold| threshold.reduce_fraction)
+            .fold(0.0, |closed: f64, fraction| {
+                closed + (1.0 - closed) * fraction.clamp(0.0, 1.0)
+            });
+        let mut reduce_sz = None;
+        if reduce_fraction > 0.0 {
+            reduce_sz = self
+                .reduce(exchange, coin, szi, mid, reduce_fraction)
+                .await?;
+        }
+        warn!(
+            "{coin} is {:.2}% from liquidation at {liquidation_px}",
+            distance * 100.0
+        );
+        let alert = LiquidationAlert {
+            coin: coin.to_string(),
+            level,
+            threshold: self.thresholds[level].distance,
+            distance,
+            mid,
+            liquidation_px,
+            reduce_sz,
+        };
+        if let Some(callback) = &mut self.alert {
+            callback(&alert);
+        }
+        Ok(())
+    }
+
+    async fn reduce<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        coin: &str,
+        szi: f64,
+        mid: f64,
+        fraction: f64,
+    ) -> Result<Option<f64>> {
+        let Some(&sz_decimals) = self.sz_decimals.get(coin) else {
+            warn!("No size decimals for {coin}, not reducing");
+            return Ok(None);
+        };
+        let lot = lot_size(sz_decimals);
+        let sz = round_to_tick(szi.abs() * fraction, lot, RoundingMode::Up).min(szi.abs());
+        let is_buy = szi < 0.0;
+        let limit_px = apply_bps(mid, if is_buy { 1.0 } else { -1.0 } * self.slippage_bps);
+        let tick = price_tick_size(limit_px, sz_decimals, false);
+        let order = ClientOrderRequest {
+            asset: coin.to_string(),
+            is_buy,
+            reduce_only: true,
+            limit_px: round_to_tick(limit_px, tick, RoundingMode::Nearest),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        };
+        info!("Reducing {coin} by {sz} ahead of liquidation");
+        let filled = match exchange.order(order).await? {
+            ExchangeResponseStatus::Ok(response) => response
+                .data
+                .and_then(|data| data.statuses.into_iter().next())
+                .and_then(|status| match status {
+                    ExchangeDataStatus::Filled(filled) => filled.total_sz.parse::<f64>().ok(),
+                    ExchangeDataStatus::Error(err) => {
+                        warn!("Reduce order for {coin} failed: {err}");
+                        None
+                    }
+                    _ => None,
+                }),
+            ExchangeResponseStatus::Err(err) => {
+                warn!("Reduce order for {coin} failed: {err}");
+                None
+            }
+        };
+        if let (Some(filled), Some(position)) = (filled, self.positions.get_mut(coin)) {
+            position.szi -= filled * position.szi.signum();
+        }
+        Ok(Some(sz))
+    }
+}
+
+impl Strategy for LiquidationWatchdog {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        let Message::AllMids(all_mids) = message else {
+            return Ok(());
+        };
+        let coins: Vec<String> = self.positions.keys().cloned().collect();
+        for coin in coins {
+            let Some(mid) = all_mids
+                .data
+                .mids
+                .get(&coin)
+                .and_then(|mid| mid.parse::<f64>().ok())
+            else {
+                continue;
+            };
+            self.check(exchange, &coin, mid).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    fn mid(px: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"allMids","data":{{"mids":{{"ETH":"{px}"}}}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_alerts_and_reduces_at_thresholds() {
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        exchange.handle_message(
+            &serde_json::from_str(
+                r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1889","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+            )
+            .unwrap(),
+        );
+        exchange
+            .order(ClientOrderRequest {
+                asset: "ETH".to_string(),
+                is_buy: true,
+                reduce_only: false,
+                limit_px: 2001.0,
+                sz: 1.0,
+                cloid: None,
+                order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+            })
+            .await
+            .unwrap();
+
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let recorded = alerts.clone();
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"ETH","szDecimals":2,"maxLeverage":25}]}"#,
+        )
+        .unwrap();
+        let mut watchdog = LiquidationWatchdog::new(
+            Address::ZERO,
+            vec![
+                LiquidationThreshold {
+                    distance: 0.05,
+                    reduce_fraction: Some(0.5),
+                },
+                LiquidationThreshold {
+                    distance: 0.1,
+                    reduce_fraction: None,
+                },
+            ],
+        )
+        .with_meta(&meta)
+        .with_alert(move |alert| recorded.lock().unwrap().push(alert.clone()));
+        let state: UserStateResponse = serde_json::from_str(
+            r#"{"assetPositions":[{"type":"oneWay","position":{"coin":"ETH","entryPx":"2001","leverage":{"type":"cross","value":20},"liquidationPx":"1800","marginUsed":"100","positionValue":"2000","returnOnEquity":"0","szi":"1","unrealizedPnl":"0","maxLeverage":25,"cumFunding":{"allTime":"0","sinceOpen":"0","sinceChange":"0"}}}],
+            "crossMarginSummary":{"accountValue":"300","totalMarginUsed":"100","totalNtlPos":"2000","totalRawUsd":"-1700"},
+            "marginSummary":{"accountValue":"300","totalMarginUsed":"100","totalNtlPos":"2000","totalRawUsd":"-1700"},
+            "withdrawable":"200"}"#,
+        )
+        .unwrap();
+        watchdog.update_positions(&state);
+
+        for px in ["2000", "1990", "1985", "1890"] {
+            watchdog.on_message(&mid(px), &exchange).await.unwrap();
+        }
+        let alerts = alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 2);
+        assert_eq!((alerts[0].level, alerts[0].reduce_sz), (0, None));
+        assert_eq!((alerts[1].level, alerts[1].reduce_sz), (1, Some(0.5)));
+        let szi = exchange.position("ETH").unwrap().szi;
+        assert!((szi - 0.5).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use crate::{prelude::*, Error, Meta, UserStateResponse};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MarginTier {
+    /// Position notional from which the tier applies
+    pub lower_bound: f64,
+    pub max_leverage: u32,
+}
+
+/// Maintenance margin schedule of one asset. Maintenance margin is half the initial margin at
+/// the tier's max leverage, with a deduction keeping it continuous across tiers.
+#[derive(Clone, Debug)]
+pub struct MarginTable {
+    tiers: Vec<MarginTier>,
+}
+
+impl MarginTable {
+    pub fn new(mut tiers: Vec<MarginTier>) -> MarginTable {
+        tiers.sort_by(|a, b| a.lower_bound.total_cmp(&b.lower_bound));
+        MarginTable { tiers }
+    }
+
+    pub fn single(max_leverage: u32) -> MarginTable {
+        MarginTable::new(vec![MarginTier {
+            lower_bound: 0.0,
+            max_leverage,
+        }])
+    }
+
+    pub fn tiers(&self) -> &[MarginTier] {
+        &self.tiers
+    }
+
+    pub fn max_leverage(&self, notional: f64) -> u32 {
+        self.tiers[self.tier_index(notional)].max_leverage
+    }
+
+    pub fn maintenance_rate(&self, notional: f64) -> f64 {
+        rate(&self.tiers[self.tier_index(notional)])
+    }
+
+    pub fn maintenance_margin(&self, notional: f64) -> f64 {
+        let index = self.tier_index(notional);
+        notional * rate(&self.tiers[index]) - self.deduction(index)
+    }
+
+    fn tier_index(&self, notional: f64) -> usize {
+        self.tiers
+            .iter()
+            .rposition(|tier| notional >= tier.lower_bound)
+            .unwrap_or(0)
+    }
+
+    fn deduction(&self, index: usize) -> f64 {
+        (1..=index)
+            .map(|i| self.tiers[i].lower_bound * (rate(&self.tiers[i]) - rate(&self.tiers[i - 1])))
+            .sum()
+    }
+}
+
+fn rate(tier: &MarginTier) -> f64 {
+    1.0 / (2.0 * tier.max_leverage as f64)
+}
+
+/// One position as input to `MarginCalculator::compute`.
+#[derive(Clone, Debug)]
+pub struct PositionInput {
+    pub coin: String,
+    /// Signed size, positive when long
+    pub szi: f64,
+    pub mark_px: f64,
+    /// Margin allocated to the position, including unrealized PnL. `None` for cross positions.
+    pub isolated_margin: Option<f64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PositionMargin {
+    pub coin: String,
+    pub szi: f64,
+    pub notional: f64,
+    pub maintenance_margin: f64,
+    /// `None` if the position cannot be liquidated by price moves alone
+    pub liquidation_px: Option<f64>,
+    pub is_isolated: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct AccountMargin {
+    /// Cross account value, excluding isolated margin
+    pub account_value: f64,
+    /// Maintenance margin of cross positions
+    pub maintenance_margin: f64,
+    /// Cross maintenance margin over account value; the account is liquidated at 1
+    pub margin_ratio: f64,
+    pub positions: Vec<PositionMargin>,
+}
+
+/// Computes maintenance margin, margin ratio and liquidation prices locally, using the margin
+/// tables from `meta`.
+#[derive(Clone, Debug)]
+pub struct MarginCalculator {
+    tables: HashMap<String, MarginTable>,
+}
+
+impl MarginCalculator {
+    pub fn from_meta(meta: &Meta) -> MarginCalculator {
+        let tables: HashMap<u32, MarginTable> = meta
+            .margin_tables
+            .iter()
+            .map(|(id, table)| {
+                let tiers = table
+                    .margin_tiers
+                    .iter()
+                    .map(|tier| MarginTier {
+                        lower_bound: tier.lower_bound.parse().unwrap_or_default(),
+                        max_leverage: tier.max_leverage,
+                    })
+                    .collect();
+                (*id, MarginTable::new(tiers))
+            })
+            .collect();
+
+        let tables = meta
+            .universe
+            .iter()
+            .map(|asset| {
+                // Tables without tiers published are a single tier at the asset's max leverage
+                let table = asset
+                    .margin_table_id
+                    .and_then(|id| tables.get(&id))
+                    .filter(|table| !table.tiers.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| MarginTable::single(asset.max_leverage as u32));
+                (asset.name.clone(), table)
+            })
+            .collect();
+        MarginCalculator { tables }
+    }
+
+    pub fn table(&self, coin: &str) -> Option<&MarginTable> {
+        self.tables.get(coin)
+    }
+
+    /// Price at which a position of `szi` reaches maintenance margin, holding everything else
+    /// fixed. `margin_available` is the account value (cross) or isolated margin, less the
+    /// maintenance margin currently required.
+    pub fn liquidation_px(
+        &self,
+        coin: &str,
+        szi: f64,
+        mark_px: f64,
+        margin_available: f64,
+    ) -> Result<Option<f64>> {
+        let table = self.tables.get(coin).ok_or(Error::AssetNotFound)?;
+        if szi == 0.0 {
+            return Ok(None);
+        }
+        let side = szi.signum();
+        let l = table.maintenance_rate(szi.abs() * mark_px);
+        let px = mark_px - side * margin_available / szi.abs() / (1.0 - l * side);
+        Ok((px > 0.0).then_some(px))
+    }
+
+    pub fn compute(
+        &self,
+        account_value: f64,
+        positions: &[PositionInput],
+    ) -> Result<AccountMargin> {
+        let mut margins = Vec::with_capacity(positions.len());
+        for position in positions {
+            let table = self
+                .tables
+                .get(&position.coin)
+                .ok_or(Error::AssetNotFound)?;
+            let notional = position.szi.abs() * position.mark_px;
+            margins.push(PositionMargin {
+                coin: position.coin.clone(),
+                szi: position.szi,
+                notional,
+                maintenance_margin: table.maintenance_margin(notional),
+                liquidation_px: None,
+                is_isolated: position.isolated_margin.is_some(),
+            });
+        }
+
+        let maintenance_margin: f64 = margins
+            .iter()
+            .filter(|m| !m.is_isolated)
+            .map(|m| m.maintenance_margin)
+            .sum();
+        for (margin, position) in margins.iter_mut().zip(positions) {
+            let margin_available = match position.isolated_margin {
+                Some(isolated_margin) => isolated_margin - margin.maintenance_margin,
+                None => account_value - maintenance_margin,
+            };
+            margin.liquidation_px = self.liquidation_px(
+                &position.coin,
+                position.szi,
+                position.mark_px,
+                margin_available,
+            )?;
+        }
+
+        Ok(AccountMargin {
+            account_value,
+            maintenance_margin,
+            margin_ratio: if account_value > 0.0 {
+                maintenance_margin / account_value
+            } else {
+                f64::INFINITY
+            },
+            positions: margins,
+        })
+    }
+
+    /// Computes margin for the positions in a clearinghouse state, marked at
+    /// `positionValue / |szi|`.
+    pub fn account(&self, state: &UserStateResponse) -> Result<AccountMargin> {
+        let (account_value, positions) = state_positions(state)?;
+        self.compute(account_value, &positions)
+    }
+}
+
+/// Cross account value and open positions of a clearinghouse state, marked at
+/// `positionValue / |szi|`.
+pub(crate) fn state_positions(state: &UserStateResponse) -> Result<(f64, Vec<PositionInput>)> {
+    let parse = |value: &str| value.parse::<f64>().map_err(|_| Error::FloatStringParse);
+    let mut positions = Vec::with_capacity(state.asset_positions.len());
+    for asset_position in &state.asset_positions {
+        let position = &asset_position.position;
+        let szi = parse(&position.szi)?;
+        if szi == 0.0 {
+            continue;
+        }
+        positions.push(PositionInput {
+            coin: position.coin.clone(),
+            szi,
+            mark_px: parse(&position.position_value)? / szi.abs(),
+            isolated_margin: (position.leverage.type_string == "isolated")
+                .then(|| parse(&position.margin_used))
+                .transpose()?,
+        });
+    }
+    Ok((parse(&state.cross_margin_summary.account_value)?, positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calculator() -> MarginCalculator {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[
+                {"name":"BTC","szDecimals":5,"maxLeverage":40,"marginTableId":56},
+                {"name":"ETH","szDecimals":4,"maxLeverage":25,"marginTableId":25}
+            ],"marginTables":[[56,{"description":"tiered 40x","marginTiers":[
+                {"lowerBound":"0.0","maxLeverage":40},
+                {"lowerBound":"150000000.0","maxLeverage":20}
+            ]}]]}"#,
+        )
+        .unwrap();
+        MarginCalculator::from_meta(&meta)
+    }
+
+    #[test]
+    fn test_tiered_maintenance_margin() {
+        let calculator = calculator();
+        let btc = calculator.table("BTC").unwrap();
+        assert!((btc.maintenance_margin(100_000.0) - 1_250.0).abs() < 1e-9);
+        // Continuous at the tier boundary, then 2.5% on the excess
+        let at_bound = btc.maintenance_margin(150_000_000.0);
+        assert!((at_bound - 1_875_000.0).abs() < 1e-6);
+        let above = btc.maintenance_margin(160_000_000.0);
+        assert!((above - (1_875_000.0 + 250_000.0)).abs() < 1e-6);
+        assert_eq!(calculator.table("ETH").unwrap().max_leverage(1e9), 25);
+    }
+
+    #[test]
+    fn test_liquidation_prices() {
+        let calculator = calculator();
+        let account = calculator
+            .compute(
+                1_000.0,
+                &[
+                    PositionInput {
+                        coin: "ETH".to_string(),
+                        szi: 5.0,
+                        mark_px: 2_000.0,
+                        isolated_margin: None,
+                    },
+                    PositionInput {
+                        coin: "BTC".to_string(),
+                        szi: -0.1,
+                        mark_px: 100_000.0,
+                        isolated_margin: Some(500.0),
+                    },
+                ],
+            )
+            .unwrap();
+        assert!((account.maintenance_margin - 200.0).abs() < 1e-9);
+        assert!((account.margin_ratio - 0.2).abs() < 1e-12);
+
+        // Long: 2000 - 800 / 5 / (1 - 0.02)
+        let eth = &account.positions[0];
+        assert!((eth.liquidation_px.unwrap() - (2_000.0 - 160.0 / 0.98)).abs() < 1e-9);
+        // At the liquidation price equity equals maintenance margin
+        let liq = eth.liquidation_px.unwrap();
+        let equity = 1_000.0 + 5.0 * (liq - 2_000.0);
+        assert!((equity - 5.0 * liq * 0.02).abs() < 1e-6);
+
+        // Short isolated: 100000 + (500 - 125) / 0.1 / 1.0125
+        let btc = &account.positions[1];
+        assert!((btc.liquidation_px.unwrap() - (100_000.0 + 3_750.0 / 1.0125)).abs() < 1e-6);
+    }
+}
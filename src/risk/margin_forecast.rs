@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "exchange")]
+use crate::ClientOrderRequest;
+use crate::{prelude::*, Error, OpenOrdersResponse, UserStateResponse};
+
+/// A resting or about to be sent limit order, as input to `MarginForecaster::forecast`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PendingOrder {
+    pub coin: String,
+    pub is_buy: bool,
+    pub sz: f64,
+    pub px: f64,
+}
+
+impl PendingOrder {
+    /// `None` if the price or size does not parse.
+    pub fn from_open_order(order: &OpenOrdersResponse) -> Option<PendingOrder> {
+        Some(PendingOrder {
+            coin: order.coin.clone(),
+            is_buy: order.side.is_buy(),
+            sz: order.sz.parse().ok()?,
+            px: order.limit_px.parse().ok()?,
+        })
+    }
+
+    /// Reduce-only orders never add margin and are skipped.
+    #[cfg(feature = "exchange")]
+    pub fn from_client_order(order: &ClientOrderRequest) -> Option<PendingOrder> {
+        (!order.reduce_only).then(|| PendingOrder {
+            coin: order.asset.clone(),
+            is_buy: order.is_buy,
+            sz: order.sz,
+            px: order.limit_px,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ForecastPosition {
+    szi: f64,
+    mark_px: f64,
+    leverage: u32,
+    is_isolated: bool,
+}
+
+/// Margin of one coin now and if its orders fill.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoinMarginForecast {
+    pub coin: String,
+    pub szi: f64,
+    /// Position if every buy order fills
+    pub long_szi: f64,
+    /// Position if every sell order fills
+    pub short_szi: f64,
+    pub leverage: u32,
+    /// Cross margin the position uses now
+    pub margin: f64,
+    /// Cross margin if the side needing the most fills
+    pub worst_margin: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarginForecast {
+    /// Cross account value
+    pub account_value: f64,
+    /// Cross margin positions use now
+    pub margin: f64,
+    /// Cross margin if the orders of every coin fill on their costliest side
+    pub worst_margin: f64,
+    /// Account value left over in that case, negative when the orders over-commit margin
+    pub free_margin: f64,
+    pub coins: Vec<CoinMarginForecast>,
+}
+
+impl MarginForecast {
+    pub fn fits(&self) -> bool {
+        self.free_margin >= 0.0
+    }
+
+    pub fn coin(&self, coin: &str) -> Option<&CoinMarginForecast> {
+        self.coins.iter().find(|forecast| forecast.coin == coin)
+    }
+}
+
+/// Estimates the initial margin an account would use if its resting orders, and orders about
+/// to be sent, were filled, so a strategy quoting many coins at once does not commit more
+/// margin than the account holds.
+///
+/// Each side of a coin is assumed to fill entirely on its own: buys first close a short and
+/// then open a long at their limit prices, and the coin needs the margin of whichever side
+/// costs more, as the exchange does for open orders. Positions are valued at the mark price
+/// and margined at their leverage, coins without a position at the default leverage. Growing
+/// an isolated position draws on cross margin, the isolated margin already posted does not.
+#[derive(Clone, Debug)]
+pub struct MarginForecaster {
+    account_value: f64,
+    positions: HashMap<String, ForecastPosition>,
+    leverage: HashMap<String, u32>,
+    default_leverage: u32,
+}
+
+impl MarginForecaster {
+    /// Forecasts against `account_value` of cross equity, with new positions at 1x.
+    pub fn new(account_value: f64) -> MarginForecaster {
+        MarginForecaster {
+            account_value,
+            positions: HashMap::new(),
+            leverage: HashMap::new(),
+            default_leverage: 1,
+        }
+    }
+
+    /// Starts from the cross account value and the positions in a clearinghouse state, marked
+    /// at `positionValue / |szi|`.
+    pub fn from_user_state(state: &UserStateResponse) -> Result<MarginForecaster> {
+        let parse = |value: &str| value.parse::<f64>().map_err(|_| Error::FloatStringParse);
+        let mut forecaster =
+            MarginForecaster::new(parse(&state.cross_margin_summary.account_value)?);
+        for asset_position in &state.asset_positions {
+            let position = &asset_position.position;
+            let szi = parse(&position.szi)?;
+            if szi == 0.0 {
+                continue;
+            }
+            forecaster.positions.insert(
+                position.coin.clone(),
+                ForecastPosition {
+                    szi,
+                    mark_px: parse(&position.position_value)? / szi.abs(),
+                    leverage: position.leverage.value,
+                    is_isolated: position.leverage.type_string == "isolated",
+                },
+            );
+        }
+        Ok(forecaster)
+    }
+
+    /// Adds or replaces a cross position.
+    pub fn with_position(mut self, coin: &str, szi: f64, mark_px: f64, leverage: u32) -> Self {
+        self.positions.insert(
+            coin.to_string(),
+            ForecastPosition {
+                szi,
+                mark_px,
+                leverage,
+                is_isolated: false,
+            },
+        );
+        self
+    }
+
+    /// Leverage of `coin`, overriding that of its position.
+    pub fn with_leverage(mut self, coin: &str, leverage: u32) -> Self {
+        self.leverage.insert(coin.to_string(), leverage);
+        self
+    }
+
+    /// Leverage of coins without a position or an explicit leverage.
+    pub fn with_default_leverage(mut self, leverage: u32) -> Self {
+        self.default_leverage = leverage;
+        self
+    }
+
+    pub fn forecast(&self, orders: &[PendingOrder]) -> MarginForecast {
+        // Buy and sell size and notional of each coin
+        let mut sides: BTreeMap<&str, [(f64, f64); 2]> = BTreeMap::new();
+        for coin in self.positions.keys() {
+            sides.entry(coin).or_default();
+        }
+        for order in orders {
+            let side = &mut sides.entry(&order.coin).or_default()[usize::from(!order.is_buy)];
+            side.0 += order.sz;
+            side.1 += order.sz * order.px;
+        }
+
+        let mut coins = Vec::with_capacity(sides.len());
+        for (coin, [buys, sells]) in sides {
+            let position = self.positions.get(coin);
+            let (szi, mark_px) = position.map_or((0.0, 0.0), |p| (p.szi, p.mark_px));
+            let leverage = self
+                .leverage
+                .get(coin)
+                .copied()
+                .or(position.map(|p| p.leverage))
+                .unwrap_or(self.default_leverage)
+                .max(1) as f64;
+            let notional = szi.abs() * mark_px;
+            let long = extreme_notional(szi, mark_px, buys);
+            let short = extreme_notional(-szi, mark_px, sells);
+            // Isolated positions hold their own margin, only growth draws on cross
+            let posted = if position.is_some_and(|p| p.is_isolated) {
+                notional
+            } else {
+                0.0
+            };
+            coins.push(CoinMarginForecast {
+                coin: coin.to_string(),
+                szi,
+                long_szi: szi + buys.0,
+                short_szi: szi - sells.0,
+                leverage: leverage as u32,
+                margin: (notional - posted) / leverage,
+                worst_margin: (long.max(short) - posted).max(0.0) / leverage,
+            });
+        }
+
+        let margin = coins.iter().map(|coin| coin.margin).sum();
+        let worst_margin = coins.iter().map(|coin| coin.worst_margin).sum();
+        MarginForecast {
+            account_value: self.account_value,
+            margin,
+            worst_margin,
+            free_margin: self.account_value - worst_margin,
+            coins,
+        }
+    }
+}
+
+/// Notional of a position of `szi` after orders of `(size, notional)` in the direction of a
+/// positive `szi` all fill, with the position at `mark_px` and new size at the orders' prices.
+fn extreme_notional(szi: f64, mark_px: f64, (sz, ntl): (f64, f64)) -> f64 {
+    let after = szi + sz;
+    if after <= 0.0 {
+        return after.abs() * mark_px;
+    }
+    let opened = after - szi.max(0.0);
+    let avg_px = if sz > 0.0 { ntl / sz } else { mark_px };
+    szi.max(0.0) * mark_px + opened * avg_px
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(coin: &str, is_buy: bool, sz: f64, px: f64) -> PendingOrder {
+        PendingOrder {
+            coin: coin.to_string(),
+            is_buy,
+            sz,
+            px,
+        }
+    }
+
+    #[test]
+    fn test_forecasts_worst_side() {
+        let forecaster = MarginForecaster::new(1_000.0)
+            .with_position("ETH", -1.0, 2_000.0, 10)
+            .with_default_leverage(5);
+        let orders = [
+            // Closes the short and opens 2 long at 1990
+            order("ETH", true, 3.0, 1_990.0),
+            // Grows the short to 1.5
+            order("ETH", false, 0.5, 2_010.0),
+            order("SOL", true, 10.0, 100.0),
+            order("SOL", false, 5.0, 110.0),
+        ];
+        let forecast = forecaster.forecast(&orders);
+        let eth = forecast.coin("ETH").unwrap();
+        assert!((eth.margin - 200.0).abs() < 1e-9);
+        assert_eq!((eth.long_szi, eth.short_szi), (2.0, -1.5));
+        // Long side: 2 * 1990 / 10, short side: 1.5 * 2000 / 10
+        assert!((eth.worst_margin - 398.0).abs() < 1e-9);
+        let sol = forecast.coin("SOL").unwrap();
+        assert_eq!(sol.leverage, 5);
+        assert!((sol.worst_margin - 200.0).abs() < 1e-9);
+        assert!((forecast.free_margin - 402.0).abs() < 1e-9);
+        assert!(forecast.fits());
+
+        let more = [orders.as_slice(), &[order("BTC", true, 0.1, 50_000.0)]].concat();
+        assert!(!forecaster.forecast(&more).fits());
+    }
+}
@@ -0,0 +1,41 @@
+mod activity;
+mod book_guard;
+mod degradation;
+#[cfg(feature = "exchange")]
+mod engine;
+mod equity;
+mod fleet;
+#[cfg(feature = "exchange")]
+mod isolated_margin;
+#[cfg(feature = "exchange")]
+mod liquidation;
+mod margin;
+mod margin_forecast;
+mod node_check;
+#[cfg(feature = "exchange")]
+mod order_guard;
+mod status;
+mod var;
+
+pub use activity::{ActivityAnomaly, ActivityMonitor, AnomalyKind};
+pub use book_guard::{BookGuard, BookViolation};
+pub use degradation::{
+    Capability, CapabilityHealth, DegradationMode, DegradationSupervisor, DegradationTransition,
+};
+#[cfg(feature = "exchange")]
+pub use engine::{RiskEngine, RiskLimits, RiskViolation};
+pub use equity::{DrawdownBreach, EquitySnapshot, EquityTracker};
+pub use fleet::{AccountSummary, FleetMonitor, FleetTotals};
+#[cfg(feature = "exchange")]
+pub use isolated_margin::{IsolatedMarginConfig, IsolatedMarginKeeper, MarginAlert, TopUp};
+#[cfg(feature = "exchange")]
+pub use liquidation::{LiquidationAlert, LiquidationThreshold, LiquidationWatchdog};
+pub use margin::{
+    AccountMargin, MarginCalculator, MarginTable, MarginTier, PositionInput, PositionMargin,
+};
+pub use margin_forecast::{CoinMarginForecast, MarginForecast, MarginForecaster, PendingOrder};
+pub use node_check::{NodeConsistencyChecker, NodeConsistencyReport, NodeDivergence};
+#[cfg(feature = "exchange")]
+pub use order_guard::{OrderGuard, PriceBandPolicy};
+pub use status::{ExchangeMonitor, ExchangeStatus};
+pub use var::{PortfolioRisk, StressResult, StressScenario, ValueAtRisk};
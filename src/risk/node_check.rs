@@ -0,0 +1,393 @@
+use std::{fmt, future::Future, pin::pin, time::Duration};
+
+use alloy::primitives::Address;
+use tracing::warn;
+
+use crate::{prelude::*, rt, BaseUrl, InfoClient, L2SnapshotResponse, UserStateResponse};
+
+/// A way a local node's data differs from the reference API.
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum NodeDivergence {
+    #[error("{coin} local book is {lag:?} behind the reference, limit {max_lag:?}")]
+    BookLag {
+        coin: String,
+        lag: Duration,
+        max_lag: Duration,
+    },
+    #[error("{coin} local mid {local} is {bps:.1} bps from reference mid {reference}")]
+    Mid {
+        coin: String,
+        local: f64,
+        reference: f64,
+        bps: f64,
+    },
+    #[error("{coin} book is empty on the {side} node")]
+    EmptyBook { coin: String, side: &'static str },
+    #[error("{user} {coin} position is {local} locally and {reference} on the reference")]
+    Position {
+        user: Address,
+        coin: String,
+        local: f64,
+        reference: f64,
+    },
+    #[error("{user} account value is {local} locally and {reference} on the reference")]
+    AccountValue {
+        user: Address,
+        local: f64,
+        reference: f64,
+    },
+}
+
+/// Divergences found by one `NodeConsistencyChecker::check`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NodeConsistencyReport {
+    pub divergences: Vec<NodeDivergence>,
+}
+
+impl NodeConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+type ReportCallback = Box<dyn FnMut(&NodeConsistencyReport) + Send>;
+
+/// Compares a local node, as reached with `BaseUrl::Localhost`, against the public API, so a
+/// node that stopped syncing or follows a fork is caught before trading on its data.
+///
+/// Each check fetches `l2Book` of the watched coins and `clearinghouseState` of the watched
+/// users from both. Books fail when the local one lags the reference by more than the lag
+/// limit or their mids are further apart than the mid limit; accounts fail when a position
+/// size differs or account values are further apart than the value limit, as a fraction of
+/// the reference. Both requests are made at nearly the same time, so the limits only need to
+/// absorb the movement between them.
+pub struct NodeConsistencyChecker {
+    local: InfoClient,
+    reference: InfoClient,
+    coins: Vec<String>,
+    users: Vec<Address>,
+    max_lag: Duration,
+    max_mid_bps: f64,
+    max_value_divergence: f64,
+    consistent: bool,
+    on_divergence: Option<ReportCallback>,
+}
+
+impl fmt::Debug for NodeConsistencyChecker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeConsistencyChecker")
+            .field("coins", &self.coins)
+            .field("users", &self.users)
+            .field("max_lag", &self.max_lag)
+            .field("max_mid_bps", &self.max_mid_bps)
+            .field("max_value_divergence", &self.max_value_divergence)
+            .field("consistent", &self.consistent)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NodeConsistencyChecker {
+    pub fn new(local: InfoClient, reference: InfoClient) -> NodeConsistencyChecker {
+        NodeConsistencyChecker {
+            local,
+            reference,
+            coins: Vec::new(),
+            users: Vec::new(),
+            max_lag: Duration::from_secs(2),
+            max_mid_bps: 10.0,
+            max_value_divergence: 0.005,
+            consistent: true,
+            on_divergence: None,
+        }
+    }
+
+    /// Compares a node at `BaseUrl::Localhost` against mainnet.
+    pub async fn localhost() -> Result<NodeConsistencyChecker> {
+        let local = InfoClient::new(None, Some(BaseUrl::Localhost)).await?;
+        let reference = InfoClient::new(None, Some(BaseUrl::Mainnet)).await?;
+        Ok(NodeConsistencyChecker::new(local, reference))
+    }
+
+    pub fn with_coin(mut self, coin: impl Into<String>) -> Self {
+        self.coins.push(coin.into());
+        self
+    }
+
+    pub fn with_user(mut self, user: Address) -> Self {
+        self.users.push(user);
+        self
+    }
+
+    /// How far the local book's time may be behind the reference's, 2 seconds by default.
+    pub fn with_max_lag(mut self, max_lag: Duration) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// How far apart the mids may be, 10 bps by default.
+    pub fn with_max_mid_bps(mut self, bps: f64) -> Self {
+        self.max_mid_bps = bps;
+        self
+    }
+
+    /// How far apart account values may be as a fraction of the reference, 0.5% by default.
+    pub fn with_max_value_divergence(mut self, fraction: f64) -> Self {
+        self.max_value_divergence = fraction;
+        self
+    }
+
+    /// Called with every report that found divergences.
+    pub fn with_on_divergence(
+        mut self,
+        on_divergence: impl FnMut(&NodeConsistencyReport) + Send + 'static,
+    ) -> Self {
+        self.on_divergence = Some(Box::new(on_divergence));
+        self
+    }
+
+    /// Whether the last check found the node consistent, true before the first.
+    pub fn is_consistent(&self) -> bool {
+        self.consistent
+    }
+
+    /// Fetches and compares the watched books and accounts from both APIs.
+    pub async fn check(&mut self) -> Result<NodeConsistencyReport> {
+        let mut report = NodeConsistencyReport::default();
+        for coin in &self.coins {
+            let (local, reference) = tokio::try_join!(
+                self.local.l2_snapshot(coin.clone()),
+                self.reference.l2_snapshot(coin.clone())
+            )?;
+            report
+                .divergences
+                .extend(self.compare_books(&local, &reference));
+        }
+        for &user in &self.users {
+            let (local, reference) =
+                tokio::try_join!(self.local.user_state(user), self.reference.user_state(user))?;
+            report
+                .divergences
+                .extend(self.compare_accounts(user, &local, &reference));
+        }
+        self.consistent = report.is_consistent();
+        if !self.consistent {
+            for divergence in &report.divergences {
+                warn!("Local node diverges from the reference: {divergence}");
+            }
+            if let Some(on_divergence) = &mut self.on_divergence {
+                on_divergence(&report);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks every `interval` until `shutdown` resolves. A failed request counts as
+    /// inconsistent.
+    pub async fn run(&mut self, interval: Duration, shutdown: impl Future<Output = ()>) {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            if let Err(err) = self.check().await {
+                warn!("Could not compare the local node: {err}");
+                self.consistent = false;
+            }
+            tokio::select! {
+                _ = &mut shutdown => return,
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+
+    fn compare_books(
+        &self,
+        local: &L2SnapshotResponse,
+        reference: &L2SnapshotResponse,
+    ) -> Vec<NodeDivergence> {
+        let mut divergences = Vec::new();
+        let coin = &reference.coin;
+        let lag = reference
+            .time
+            .as_millis()
+            .saturating_sub(local.time.as_millis());
+        if lag > self.max_lag.as_millis() as u64 {
+            divergences.push(NodeDivergence::BookLag {
+                coin: coin.clone(),
+                lag: Duration::from_millis(lag),
+                max_lag: self.max_lag,
+            });
+        }
+        match (snapshot_mid(local), snapshot_mid(reference)) {
+            (Some(local), Some(reference)) => {
+                let bps = (local - reference).abs() / reference * 1e4;
+                if bps > self.max_mid_bps {
+                    divergences.push(NodeDivergence::Mid {
+                        coin: coin.clone(),
+                        local,
+                        reference,
+                        bps,
+                    });
+                }
+            }
+            (None, Some(_)) => divergences.push(NodeDivergence::EmptyBook {
+                coin: coin.clone(),
+                side: "local",
+            }),
+            (_, None) => divergences.push(NodeDivergence::EmptyBook {
+                coin: coin.clone(),
+                side: "reference",
+            }),
+        }
+        divergences
+    }
+
+    fn compare_accounts(
+        &self,
+        user: Address,
+        local: &UserStateResponse,
+        reference: &UserStateResponse,
+    ) -> Vec<NodeDivergence> {
+        let mut divergences = Vec::new();
+        let local_positions = positions(local);
+        let reference_positions = positions(reference);
+        let mut coins: Vec<&str> = local_positions
+            .iter()
+            .chain(&reference_positions)
+            .map(|(coin, _)| *coin)
+            .collect();
+        coins.sort_unstable();
+        coins.dedup();
+        let size = |positions: &[(&str, f64)], coin: &str| {
+            positions
+                .iter()
+                .find(|(c, _)| *c == coin)
+                .map_or(0.0, |(_, szi)| *szi)
+        };
+        for coin in coins {
+            let (local, reference) = (
+                size(&local_positions, coin),
+                size(&reference_positions, coin),
+            );
+            if (local - reference).abs() > EPSILON {
+                divergences.push(NodeDivergence::Position {
+                    user,
+                    coin: coin.to_string(),
+                    local,
+                    reference,
+                });
+            }
+        }
+        let local = parse(&local.margin_summary.account_value);
+        let reference = parse(&reference.margin_summary.account_value);
+        if (local - reference).abs() > self.max_value_divergence * reference.abs().max(1.0) {
+            divergences.push(NodeDivergence::AccountValue {
+                user,
+                local,
+                reference,
+            });
+        }
+        divergences
+    }
+}
+
+const EPSILON: f64 = 1e-9;
+
+fn parse(value: &str) -> f64 {
+    value.parse().unwrap_or_default()
+}
+
+fn snapshot_mid(snapshot: &L2SnapshotResponse) -> Option<f64> {
+    let best = |side: usize| Some(parse(&snapshot.levels.get(side)?.first()?.px));
+    Some((best(0)? + best(1)?) / 2.0)
+}
+
+fn positions(state: &UserStateResponse) -> Vec<(&str, f64)> {
+    state
+        .asset_positions
+        .iter()
+        .map(|p| (p.position.coin.as_str(), parse(&p.position.szi)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(time: u64, bid: &str, ask: &str) -> L2SnapshotResponse {
+        serde_json::from_value(serde_json::json!({
+            "coin": "BTC",
+            "time": time,
+            "levels": [[{"n": 1, "px": bid, "sz": "1"}], [{"n": 1, "px": ask, "sz": "1"}]],
+        }))
+        .unwrap()
+    }
+
+    fn account(value: &str, positions: &[(&str, &str)]) -> UserStateResponse {
+        let summary = serde_json::json!({
+            "accountValue": value,
+            "totalMarginUsed": "0",
+            "totalNtlPos": "0",
+            "totalRawUsd": value,
+        });
+        let positions: Vec<_> = positions
+            .iter()
+            .map(|(coin, szi)| {
+                serde_json::json!({"type": "oneWay", "position": {
+                    "coin": coin, "entryPx": null, "liquidationPx": null,
+                    "leverage": {"type": "cross", "value": 10},
+                    "marginUsed": "0", "positionValue": "0", "returnOnEquity": "0",
+                    "szi": szi, "unrealizedPnl": "0", "maxLeverage": 50,
+                    "cumFunding": {"allTime": "0", "sinceChange": "0", "sinceOpen": "0"},
+                }})
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "assetPositions": positions,
+            "crossMarginSummary": summary,
+            "marginSummary": summary,
+            "withdrawable": value,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reports_stale_and_diverging_node() {
+        let local = InfoClient::new(None, Some(BaseUrl::Localhost))
+            .await
+            .unwrap();
+        let reference = InfoClient::new(None, Some(BaseUrl::Mainnet)).await.unwrap();
+        let checker = NodeConsistencyChecker::new(local, reference);
+
+        let reference_book = book(10_000, "100", "100.1");
+        assert!(checker
+            .compare_books(&book(9_000, "100", "100.1"), &reference_book)
+            .is_empty());
+        // Five seconds behind and a stale price
+        let divergences = checker.compare_books(&book(5_000, "99", "99.1"), &reference_book);
+        assert!(matches!(
+            divergences[..],
+            [NodeDivergence::BookLag { lag, .. }, NodeDivergence::Mid { .. }]
+                if lag == Duration::from_secs(5)
+        ));
+
+        let user = Address::repeat_byte(1);
+        let reference_account = account("1000", &[("BTC", "0.5")]);
+        assert!(checker
+            .compare_accounts(
+                user,
+                &account("1001", &[("BTC", "0.5")]),
+                &reference_account
+            )
+            .is_empty());
+        let divergences =
+            checker.compare_accounts(user, &account("900", &[("ETH", "-1")]), &reference_account);
+        assert_eq!(divergences.len(), 3);
+        assert_eq!(
+            divergences[0],
+            NodeDivergence::Position {
+                user,
+                coin: "BTC".to_string(),
+                local: 0.0,
+                reference: 0.5,
+            }
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use tracing::warn;
+
+use crate::{
+    price_tick_size, round_to_tick, ClientOrder, ClientOrderRequest, Message, RiskViolation,
+    RoundingMode,
+};
+
+/// What `OrderGuard` does with an order priced outside the band.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PriceBandPolicy {
+    #[default]
+    Reject,
+    /// Moves the price to the edge of the band. Spot orders, whose tick size is not known,
+    /// are rejected instead.
+    Clamp,
+}
+
+/// Last line of defence against fat fingers and strategy bugs, checking every order an
+/// `ExchangeClient` sends, whichever strategy or helper built it.
+///
+/// Limit prices further than `max_deviation_bps` from the coin's mid are rejected with
+/// `Error::RiskCheck`, or clamped to the band by policy, and orders larger than the coin's
+/// maximum size are rejected. Trigger orders are exempt from the band, since stops
+/// legitimately sit far from the market, and reduce-only orders from the size caps.
+///
+/// Mids come from `allMids` messages passed to `handle_message` or from `set_mid`, with a
+/// mark price where preferred. Orders on coins without a mid are only size checked unless
+/// `with_require_mid` is set. Clones share mids.
+#[derive(Clone, Debug)]
+pub struct OrderGuard {
+    max_deviation_bps: f64,
+    policy: PriceBandPolicy,
+    max_size: HashMap<String, f64>,
+    require_mid: bool,
+    mids: Arc<RwLock<HashMap<String, f64>>>,
+}
+
+impl OrderGuard {
+    pub fn new(max_deviation_bps: f64) -> OrderGuard {
+        OrderGuard {
+            max_deviation_bps,
+            policy: PriceBandPolicy::default(),
+            max_size: HashMap::new(),
+            require_mid: false,
+            mids: Arc::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: PriceBandPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Largest size of a single order in `coin`.
+    pub fn with_max_size(mut self, coin: &str, sz: f64) -> Self {
+        self.max_size.insert(coin.to_string(), sz);
+        self
+    }
+
+    /// Rejects orders on coins whose mid is not known yet.
+    pub fn with_require_mid(mut self, require_mid: bool) -> Self {
+        self.require_mid = require_mid;
+        self
+    }
+
+    pub fn set_mid(&self, coin: &str, mid: f64) {
+        self.mids
+            .write()
+            .expect("order guard lock poisoned")
+            .insert(coin.to_string(), mid);
+    }
+
+    pub fn mid(&self, coin: &str) -> Option<f64> {
+        self.mids
+            .read()
+            .expect("order guard lock poisoned")
+            .get(coin)
+            .copied()
+    }
+
+    pub fn handle_message(&self, message: &Message) {
+        if let Message::AllMids(all_mids) = message {
+            let mut mids = self.mids.write().expect("order guard lock poisoned");
+            for (coin, mid) in &all_mids.data.mids {
+                if let Ok(mid) = mid.parse() {
+                    mids.insert(coin.clone(), mid);
+                }
+            }
+        }
+    }
+
+    /// Checks `order`, clamping its price in place under `PriceBandPolicy::Clamp`. Perp
+    /// orders pass their size decimals so clamped prices land on a valid tick.
+    pub fn check(
+        &self,
+        order: &mut ClientOrderRequest,
+        sz_decimals: Option<u32>,
+    ) -> Result<(), RiskViolation> {
+        if let Some(&limit) = self.max_size.get(&order.asset) {
+            if !order.reduce_only && order.sz > limit {
+                return Err(RiskViolation::MaxOrderSize {
+                    coin: order.asset.clone(),
+                    limit,
+                    sz: order.sz,
+                });
+            }
+        }
+        if matches!(order.order_type, ClientOrder::Trigger(_)) {
+            return Ok(());
+        }
+        let Some(mid) = self.mid(&order.asset).filter(|mid| *mid > 0.0) else {
+            if self.require_mid {
+                return Err(RiskViolation::MissingMid(order.asset.clone()));
+            }
+            return Ok(());
+        };
+        let bps = (order.limit_px - mid).abs() / mid * 10_000.0;
+        if bps <= self.max_deviation_bps {
+            return Ok(());
+        }
+        let violation = RiskViolation::PriceCollar {
+            coin: order.asset.clone(),
+            px: order.limit_px,
+            mid,
+            bps,
+        };
+        let (PriceBandPolicy::Clamp, Some(sz_decimals)) = (self.policy, sz_decimals) else {
+            return Err(violation);
+        };
+        // Round towards the mid so the clamped price stays inside the band
+        let above = order.limit_px > mid;
+        let edge = mid * (1.0 + self.max_deviation_bps / 10_000.0 * if above { 1.0 } else { -1.0 });
+        let tick = price_tick_size(edge, sz_decimals, false);
+        let mode = if above {
+            RoundingMode::Down
+        } else {
+            RoundingMode::Up
+        };
+        let clamped = round_to_tick(edge, tick, mode);
+        warn!("{violation}, clamped to {clamped}");
+        order.limit_px = clamped;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientLimit, ClientTrigger, Tif, TriggerCondition};
+
+    fn order(limit_px: f64, sz: f64) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        }
+    }
+
+    #[test]
+    fn test_price_band_and_size_caps() {
+        let guard = OrderGuard::new(500.0).with_max_size("ETH", 10.0);
+        assert!(guard.check(&mut order(3000.0, 1.0), Some(4)).is_ok());
+        guard.set_mid("ETH", 2000.0);
+        assert!(guard.check(&mut order(2090.0, 1.0), Some(4)).is_ok());
+        assert!(matches!(
+            guard.check(&mut order(2200.0, 1.0), Some(4)),
+            Err(RiskViolation::PriceCollar { .. })
+        ));
+        assert!(matches!(
+            guard.check(&mut order(2000.0, 100.0), Some(4)),
+            Err(RiskViolation::MaxOrderSize { .. })
+        ));
+        let mut reducing = ClientOrderRequest {
+            reduce_only: true,
+            ..order(2000.0, 100.0)
+        };
+        assert!(guard.check(&mut reducing, Some(4)).is_ok());
+        let mut stop = ClientOrderRequest {
+            order_type: ClientOrder::Trigger(ClientTrigger {
+                is_market: true,
+                trigger_px: 1500.0,
+                tpsl: TriggerCondition::StopLoss,
+            }),
+            ..order(1400.0, 1.0)
+        };
+        assert!(guard.check(&mut stop, Some(4)).is_ok());
+
+        let guard = guard.with_policy(PriceBandPolicy::Clamp);
+        let mut far = order(2200.0, 1.0);
+        guard.check(&mut far, Some(4)).unwrap();
+        assert_eq!(far.limit_px, 2100.0);
+        let mut low = order(1500.0, 1.0);
+        guard.check(&mut low, Some(4)).unwrap();
+        assert_eq!(low.limit_px, 1900.0);
+        assert!(guard.check(&mut order(2200.0, 1.0), None).is_err());
+    }
+}
@@ -0,0 +1,311 @@
+use std::{collections::VecDeque, fmt, future::Future, pin::pin, time::Duration};
+
+use tracing::{info, warn};
+
+use crate::{rt, rt::Instant, InfoClient, Message};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ExchangeStatus {
+    Operational,
+    /// Requests are failing or erroring more than usual, quotes are at risk of going stale
+    Degraded,
+    /// Heartbeats have stopped answering or blocks have stopped being produced, as during
+    /// maintenance
+    Down,
+}
+
+type StatusCallback = Box<dyn FnMut(ExchangeStatus, ExchangeStatus) + Send>;
+
+/// Detects exchange downtime and degradation from failed heartbeats, spikes in the request
+/// error rate and stalled block production, calling the status callback with the previous and
+/// new status on every transition, so strategies can pull their quotes during maintenance.
+///
+/// Heartbeats come from `check`, or `run` in the background, and from websocket messages
+/// passed to `on_message`. Request outcomes are fed through `record_request` and block heights,
+/// from the explorer or `EvmClient::l1_block_number`, through `record_block`. Block stalls are
+/// only detected once a first height has been recorded.
+pub struct ExchangeMonitor {
+    down_after: u32,
+    error_window: Duration,
+    error_rate: f64,
+    min_samples: usize,
+    stall_timeout: Duration,
+    consecutive_failures: u32,
+    requests: VecDeque<(Instant, bool)>,
+    last_block: Option<(u64, Instant)>,
+    status: ExchangeStatus,
+    on_change: Option<StatusCallback>,
+}
+
+impl fmt::Debug for ExchangeMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExchangeMonitor")
+            .field("down_after", &self.down_after)
+            .field("error_window", &self.error_window)
+            .field("error_rate", &self.error_rate)
+            .field("stall_timeout", &self.stall_timeout)
+            .field("consecutive_failures", &self.consecutive_failures)
+            .field("last_block", &self.last_block)
+            .field("status", &self.status)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for ExchangeMonitor {
+    fn default() -> Self {
+        ExchangeMonitor::new()
+    }
+}
+
+impl ExchangeMonitor {
+    pub fn new() -> ExchangeMonitor {
+        ExchangeMonitor {
+            down_after: 3,
+            error_window: Duration::from_secs(60),
+            error_rate: 0.5,
+            min_samples: 10,
+            stall_timeout: Duration::from_secs(30),
+            consecutive_failures: 0,
+            requests: VecDeque::new(),
+            last_block: None,
+            status: ExchangeStatus::Operational,
+            on_change: None,
+        }
+    }
+
+    /// Called with the previous and new status on every transition.
+    pub fn with_on_change(
+        mut self,
+        on_change: impl FnMut(ExchangeStatus, ExchangeStatus) + Send + 'static,
+    ) -> Self {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
+    /// Consecutive failed heartbeats after which the exchange is considered down, 3 by default.
+    /// Fewer failures mark it degraded.
+    pub fn with_down_after(mut self, failures: u32) -> Self {
+        self.down_after = failures.max(1);
+        self
+    }
+
+    /// Marks the exchange degraded when more than `error_rate` of the requests within `window`
+    /// failed, once at least `min_samples` were made. 50% of at least 10 requests over a minute
+    /// by default.
+    pub fn with_error_spike(
+        mut self,
+        window: Duration,
+        error_rate: f64,
+        min_samples: usize,
+    ) -> Self {
+        self.error_window = window;
+        self.error_rate = error_rate;
+        self.min_samples = min_samples.max(1);
+        self
+    }
+
+    /// How long the block height may stay unchanged before the exchange is considered down,
+    /// 30 seconds by default.
+    pub fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    pub fn status(&self) -> ExchangeStatus {
+        self.status
+    }
+
+    pub fn is_operational(&self) -> bool {
+        self.status == ExchangeStatus::Operational
+    }
+
+    /// Sends a lightweight `allMids` request as a heartbeat, returning the updated status.
+    pub async fn check(&mut self, info: &InfoClient) -> ExchangeStatus {
+        let ok = match info.all_mids().await {
+            Ok(_) => true,
+            Err(err) => {
+                warn!("Exchange heartbeat failed: {err}");
+                false
+            }
+        };
+        self.record_heartbeat(ok)
+    }
+
+    /// Checks the exchange every `interval` until `shutdown` resolves.
+    pub async fn run(
+        &mut self,
+        info: &InfoClient,
+        interval: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            self.check(info).await;
+            tokio::select! {
+                _ = &mut shutdown => return,
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+
+    pub fn record_heartbeat(&mut self, ok: bool) -> ExchangeStatus {
+        self.record_heartbeat_at(ok, Instant::now())
+    }
+
+    /// Records the outcome of a request made to the exchange.
+    pub fn record_request(&mut self, ok: bool) -> ExchangeStatus {
+        self.record_request_at(ok, Instant::now())
+    }
+
+    /// Records the latest block height, the exchange is considered down while it stays the
+    /// same for longer than the stall timeout.
+    pub fn record_block(&mut self, height: u64) -> ExchangeStatus {
+        self.record_block_at(height, Instant::now())
+    }
+
+    /// Counts websocket messages as heartbeats and `NoData`, sent when the connection drops,
+    /// as a failed one.
+    pub fn on_message(&mut self, message: &Message) -> ExchangeStatus {
+        self.record_heartbeat(!matches!(message, Message::NoData))
+    }
+
+    /// Re-evaluates time based conditions such as block stalls, returning the updated status.
+    pub fn tick(&mut self) -> ExchangeStatus {
+        self.update(Instant::now())
+    }
+
+    fn record_heartbeat_at(&mut self, ok: bool, now: Instant) -> ExchangeStatus {
+        self.consecutive_failures = if ok {
+            0
+        } else {
+            self.consecutive_failures.saturating_add(1)
+        };
+        self.update(now)
+    }
+
+    fn record_request_at(&mut self, ok: bool, now: Instant) -> ExchangeStatus {
+        self.requests.push_back((now, ok));
+        self.update(now)
+    }
+
+    fn record_block_at(&mut self, height: u64, now: Instant) -> ExchangeStatus {
+        match self.last_block {
+            Some((last, _)) if last >= height => {}
+            _ => self.last_block = Some((height, now)),
+        }
+        self.update(now)
+    }
+
+    fn update(&mut self, now: Instant) -> ExchangeStatus {
+        while self
+            .requests
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > self.error_window)
+        {
+            self.requests.pop_front();
+        }
+        let stalled = self
+            .last_block
+            .is_some_and(|(_, at)| now.saturating_duration_since(at) > self.stall_timeout);
+        let failed = self.requests.iter().filter(|(_, ok)| !ok).count();
+        let error_spike = self.requests.len() >= self.min_samples
+            && failed as f64 > self.error_rate * self.requests.len() as f64;
+
+        let status = if stalled || self.consecutive_failures >= self.down_after {
+            ExchangeStatus::Down
+        } else if error_spike || self.consecutive_failures > 0 {
+            ExchangeStatus::Degraded
+        } else {
+            ExchangeStatus::Operational
+        };
+        if status != self.status {
+            let previous = std::mem::replace(&mut self.status, status);
+            info!(
+                "Exchange status changed from {previous:?} to {status:?}, failed heartbeats: {}, \
+                 failed requests: {failed}/{}, block stalled: {stalled}",
+                self.consecutive_failures,
+                self.requests.len()
+            );
+            if let Some(on_change) = &mut self.on_change {
+                on_change(previous, status);
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_status_transitions() {
+        let transitions = Arc::new(Mutex::new(Vec::new()));
+        let recorded = transitions.clone();
+        let mut monitor = ExchangeMonitor::new()
+            .with_down_after(2)
+            .with_error_spike(Duration::from_secs(10), 0.5, 4)
+            .with_stall_timeout(Duration::from_secs(5))
+            .with_on_change(move |from, to| recorded.lock().unwrap().push((from, to)));
+        let start = Instant::now();
+
+        // Failed heartbeats degrade, then take the exchange down until one succeeds
+        assert_eq!(
+            monitor.record_heartbeat_at(false, start),
+            ExchangeStatus::Degraded
+        );
+        assert_eq!(
+            monitor.record_heartbeat_at(false, start),
+            ExchangeStatus::Down
+        );
+        assert_eq!(
+            monitor.record_heartbeat_at(true, start),
+            ExchangeStatus::Operational
+        );
+
+        // An error spike needs enough samples and ages out of the window
+        for ok in [false, false, true] {
+            assert_eq!(
+                monitor.record_request_at(ok, start),
+                ExchangeStatus::Operational
+            );
+        }
+        assert_eq!(
+            monitor.record_request_at(false, start),
+            ExchangeStatus::Degraded
+        );
+        assert_eq!(
+            monitor.update(start + Duration::from_secs(11)),
+            ExchangeStatus::Operational
+        );
+
+        // Block production stalling takes the exchange down until the height moves again
+        let later = start + Duration::from_secs(20);
+        monitor.record_block_at(100, later);
+        monitor.record_block_at(100, later + Duration::from_secs(3));
+        assert_eq!(
+            monitor.update(later + Duration::from_secs(6)),
+            ExchangeStatus::Down
+        );
+        assert_eq!(
+            monitor.record_block_at(101, later + Duration::from_secs(7)),
+            ExchangeStatus::Operational
+        );
+
+        use ExchangeStatus::*;
+        assert_eq!(
+            *transitions.lock().unwrap(),
+            vec![
+                (Operational, Degraded),
+                (Degraded, Down),
+                (Down, Operational),
+                (Operational, Degraded),
+                (Degraded, Operational),
+                (Operational, Down),
+                (Down, Operational),
+            ]
+        );
+    }
+}
@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::Address;
+use futures_util::future::try_join_all;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    candle_interval_ms, prelude::*, risk::margin::state_positions, AccountMargin, Error,
+    InfoClient, MarginCalculator, PositionInput, Timestamp,
+};
+
+/// Price shocks applied by `PortfolioRisk::stress`, as fractions of the mark price.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StressScenario {
+    pub name: String,
+    #[serde(default)]
+    pub shocks: HashMap<String, f64>,
+    /// Shock of every coin not in `shocks`
+    #[serde(default)]
+    pub default_shock: f64,
+}
+
+impl StressScenario {
+    pub fn new(name: impl Into<String>) -> StressScenario {
+        StressScenario {
+            name: name.into(),
+            ..StressScenario::default()
+        }
+    }
+
+    pub fn with_shock(mut self, coin: impl Into<String>, shock: f64) -> Self {
+        self.shocks.insert(coin.into(), shock);
+        self
+    }
+
+    /// Shocks each of `coins` by the same fraction, such as -0.2 across majors.
+    pub fn with_shocks<S: Into<String>>(
+        mut self,
+        coins: impl IntoIterator<Item = S>,
+        shock: f64,
+    ) -> Self {
+        for coin in coins {
+            self.shocks.insert(coin.into(), shock);
+        }
+        self
+    }
+
+    pub fn with_default_shock(mut self, shock: f64) -> Self {
+        self.default_shock = shock;
+        self
+    }
+
+    pub fn shock(&self, coin: &str) -> f64 {
+        self.shocks.get(coin).copied().unwrap_or(self.default_shock)
+    }
+}
+
+/// Margin of the positions before and after a `StressScenario`.
+#[derive(Clone, Debug)]
+pub struct StressResult {
+    pub scenario: String,
+    /// Change in value of all positions
+    pub pnl: f64,
+    pub before: AccountMargin,
+    /// Margin at the shocked prices
+    pub after: AccountMargin,
+    /// Coins whose positions would be liquidated at the shocked prices: every cross position
+    /// once the cross account falls to its maintenance margin, and isolated positions whose
+    /// margin does
+    pub liquidations: Vec<String>,
+}
+
+/// One-period value at risk of the positions, as a loss in USD.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueAtRisk {
+    pub confidence: f64,
+    /// Periods of returns the estimate is based on
+    pub periods: usize,
+    /// Mean and standard deviation of the PnL per period
+    pub mean: f64,
+    pub std_dev: f64,
+    /// Loss not exceeded with `confidence`, taking the PnL as normally distributed
+    pub parametric: f64,
+    /// Loss not exceeded in a `confidence` share of past periods
+    pub historical: f64,
+}
+
+/// Value at risk and stress tests of an account's positions, with margin computed by a
+/// `MarginCalculator`.
+///
+/// Value at risk revalues the current positions with past returns of their coins, which
+/// `historical_returns` fetches from candles, so it holds the positions fixed over the
+/// period and ignores funding.
+#[derive(Clone, Debug)]
+pub struct PortfolioRisk {
+    calculator: MarginCalculator,
+    account_value: f64,
+    positions: Vec<PositionInput>,
+}
+
+impl PortfolioRisk {
+    /// `account_value` is the cross account value, excluding isolated margin.
+    pub fn new(
+        calculator: MarginCalculator,
+        account_value: f64,
+        positions: Vec<PositionInput>,
+    ) -> PortfolioRisk {
+        PortfolioRisk {
+            calculator,
+            account_value,
+            positions,
+        }
+    }
+
+    /// Fetches meta and the positions of `user`.
+    pub async fn fetch(info: &InfoClient, user: Address) -> Result<PortfolioRisk> {
+        let (meta, state) = tokio::try_join!(info.meta(), info.user_state(user))?;
+        let (account_value, positions) = state_positions(&state)?;
+        Ok(PortfolioRisk::new(
+            MarginCalculator::from_meta(&meta),
+            account_value,
+            positions,
+        ))
+    }
+
+    pub fn positions(&self) -> &[PositionInput] {
+        &self.positions
+    }
+
+    pub fn stress(&self, scenario: &StressScenario) -> Result<StressResult> {
+        let before = self
+            .calculator
+            .compute(self.account_value, &self.positions)?;
+        let mut account_value = self.account_value;
+        let mut pnl = 0.0;
+        let shocked: Vec<PositionInput> = self
+            .positions
+            .iter()
+            .map(|position| {
+                let mark_px = (position.mark_px * (1.0 + scenario.shock(&position.coin))).max(0.0);
+                let position_pnl = position.szi * (mark_px - position.mark_px);
+                pnl += position_pnl;
+                if position.isolated_margin.is_none() {
+                    account_value += position_pnl;
+                }
+                PositionInput {
+                    mark_px,
+                    isolated_margin: position.isolated_margin.map(|margin| margin + position_pnl),
+                    ..position.clone()
+                }
+            })
+            .collect();
+        let after = self.calculator.compute(account_value, &shocked)?;
+        let cross_liquidated = after.margin_ratio >= 1.0;
+        let liquidations = after
+            .positions
+            .iter()
+            .zip(&shocked)
+            .filter(|(margin, position)| match position.isolated_margin {
+                Some(isolated_margin) => isolated_margin <= margin.maintenance_margin,
+                None => cross_liquidated,
+            })
+            .map(|(margin, _)| margin.coin.clone())
+            .collect();
+        Ok(StressResult {
+            scenario: scenario.name.clone(),
+            pnl,
+            before,
+            after,
+            liquidations,
+        })
+    }
+
+    /// Value at risk at `confidence`, such as 0.99, from aligned per-period returns of each
+    /// coin, the latest last, as from `historical_returns`. `None` without at least two
+    /// periods of returns for every position.
+    pub fn value_at_risk(
+        &self,
+        returns: &HashMap<String, Vec<f64>>,
+        confidence: f64,
+    ) -> Option<ValueAtRisk> {
+        let periods = self
+            .positions
+            .iter()
+            .map(|position| returns.get(&position.coin).map(Vec::len))
+            .min()
+            .unwrap_or(Some(0))?;
+        if periods < 2 {
+            return None;
+        }
+        let mut pnls = vec![0.0; periods];
+        for position in &self.positions {
+            let coin_returns = &returns[&position.coin];
+            let recent = &coin_returns[coin_returns.len() - periods..];
+            for (pnl, r) in pnls.iter_mut().zip(recent) {
+                *pnl += position.szi * position.mark_px * r;
+            }
+        }
+        let mean = pnls.iter().sum::<f64>() / periods as f64;
+        let variance =
+            pnls.iter().map(|pnl| (pnl - mean).powi(2)).sum::<f64>() / (periods - 1) as f64;
+        let std_dev = variance.sqrt();
+        pnls.sort_by(f64::total_cmp);
+        let index = (((1.0 - confidence) * periods as f64).floor() as usize).min(periods - 1);
+        Some(ValueAtRisk {
+            confidence,
+            periods,
+            mean,
+            std_dev,
+            parametric: inverse_normal_cdf(confidence) * std_dev - mean,
+            historical: -pnls[index],
+        })
+    }
+
+    /// Returns of each position's coin over the last `periods` closed candles of `interval`,
+    /// aligned on the candles every coin has.
+    pub async fn historical_returns(
+        &self,
+        info: &InfoClient,
+        interval: &str,
+        periods: usize,
+    ) -> Result<HashMap<String, Vec<f64>>> {
+        let interval_ms = candle_interval_ms(interval)
+            .ok_or_else(|| Error::InvalidConfig(format!("Unsupported interval {interval}")))?;
+        let now = Timestamp::now();
+        let start = now
+            .as_millis()
+            .saturating_sub(interval_ms * (periods as u64 + 2));
+        let candles = try_join_all(self.positions.iter().map(|position| {
+            info.candles_snapshot(
+                position.coin.clone(),
+                interval.to_string(),
+                start.into(),
+                now,
+            )
+        }))
+        .await?;
+        let closes: Vec<BTreeMap<u64, f64>> = candles
+            .iter()
+            .map(|candles| {
+                candles
+                    .iter()
+                    .filter(|candle| candle.time_close < now)
+                    .filter_map(|candle| {
+                        Some((candle.time_open.as_millis(), candle.close.parse().ok()?))
+                    })
+                    .collect()
+            })
+            .collect();
+        let times: Vec<u64> = closes
+            .first()
+            .map(|first| {
+                first
+                    .keys()
+                    .filter(|time| closes.iter().all(|closes| closes.contains_key(time)))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let times = &times[times.len().saturating_sub(periods + 1)..];
+        Ok(self
+            .positions
+            .iter()
+            .zip(&closes)
+            .map(|(position, closes)| {
+                let returns = times
+                    .windows(2)
+                    .map(|pair| closes[&pair[1]] / closes[&pair[0]] - 1.0)
+                    .collect();
+                (position.coin.clone(), returns)
+            })
+            .collect())
+    }
+}
+
+/// Quantile of the standard normal distribution, by Acklam's rational approximation.
+fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    let p = p.clamp(1e-12, 1.0 - 1e-12);
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < 0.02425 {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - 0.02425 {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Meta;
+
+    fn risk() -> PortfolioRisk {
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[
+                {"name":"BTC","szDecimals":5,"maxLeverage":40},
+                {"name":"ETH","szDecimals":4,"maxLeverage":25}
+            ],"marginTables":[]}"#,
+        )
+        .unwrap();
+        let position =
+            |coin: &str, szi: f64, mark_px: f64, isolated_margin: Option<f64>| PositionInput {
+                coin: coin.to_string(),
+                szi,
+                mark_px,
+                isolated_margin,
+            };
+        PortfolioRisk::new(
+            MarginCalculator::from_meta(&meta),
+            1_000.0,
+            vec![
+                position("ETH", 5.0, 2_000.0, None),
+                position("BTC", -0.1, 100_000.0, Some(500.0)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_stress_projects_margin_and_liquidations() {
+        let risk = risk();
+        let mild = risk
+            .stress(&StressScenario::new("ETH -5%").with_shock("ETH", -0.05))
+            .unwrap();
+        assert!((mild.pnl + 500.0).abs() < 1e-9);
+        assert!((mild.after.account_value - 500.0).abs() < 1e-9);
+        // 2% maintenance on 9500 of notional
+        assert!((mild.after.maintenance_margin - 190.0).abs() < 1e-9);
+        assert!(mild.liquidations.is_empty());
+
+        let majors = StressScenario::new("majors +20%").with_shocks(["BTC", "ETH"], 0.2);
+        let result = risk.stress(&majors).unwrap();
+        // The short loses 2000 against 500 of isolated margin, the long gains
+        assert_eq!(result.liquidations, ["BTC"]);
+        let crash = risk
+            .stress(&StressScenario::new("crash").with_default_shock(-0.1))
+            .unwrap();
+        assert_eq!(crash.liquidations, ["ETH"]);
+        assert!(crash.after.margin_ratio >= 1.0);
+    }
+
+    #[test]
+    fn test_value_at_risk() {
+        let risk = risk();
+        let returns = HashMap::from([
+            ("ETH".to_string(), vec![0.5, 0.01, -0.01, 0.03, -0.01]),
+            ("BTC".to_string(), vec![0.0, 0.01, 0.0, 0.01]),
+        ]);
+        // ETH is 10000 long, BTC 10000 short, over the last four periods
+        let var = risk.value_at_risk(&returns, 0.75).unwrap();
+        assert_eq!(var.periods, 4);
+        assert!((var.historical - 200.0).abs() < 1e-9);
+        assert!(var.mean.abs() < 1e-9);
+        assert!((inverse_normal_cdf(0.975) - 1.959_964).abs() < 1e-5);
+        assert!((var.parametric - 0.674_49 * var.std_dev).abs() < 1e-2);
+        assert!(risk.value_at_risk(&HashMap::new(), 0.99).is_none());
+    }
+}
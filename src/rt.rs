@@ -0,0 +1,178 @@
+//! Timing and task primitives, backed by tokio or another [`Runtime`] on native targets and
+//! by the browser event loop on `wasm32`.
+
+use std::{future::Future, time::Duration};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{pin::Pin, sync::OnceLock};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{prelude::*, Error};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::Instant;
+
+/// A background task or timer handed to a [`Runtime`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type TaskFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+/// The executor running the SDK's background tasks, such as websocket readers, keep-warm
+/// loops and reference feeds, and its timers. Defaults to [`TokioRuntime`].
+///
+/// HTTP and websocket I/O always goes through tokio's reactor. The smol and async-std
+/// runtimes provide one with `async-compat`, so await client futures inside [`compat`] there.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait Runtime: Send + Sync + 'static {
+    fn spawn(&self, fut: TaskFuture);
+
+    fn sleep(&self, duration: Duration) -> TaskFuture;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+static RUNTIME: OnceLock<Box<dyn Runtime>> = OnceLock::new();
+
+/// Installs the runtime used for the rest of the process. Call it before creating clients,
+/// tasks spawned earlier stay on tokio. Fails if a runtime was already installed.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set_runtime(runtime: impl Runtime) -> Result<()> {
+    RUNTIME
+        .set(Box::new(runtime))
+        .map_err(|_| Error::RuntimeAlreadySet)
+}
+
+/// Spawns onto and sleeps with the ambient tokio runtime.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioRuntime;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Runtime for TokioRuntime {
+    fn spawn(&self, fut: TaskFuture) {
+        tokio::spawn(fut);
+    }
+
+    fn sleep(&self, duration: Duration) -> TaskFuture {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Runs tasks on smol's global executor, inside [`compat`] for their I/O.
+#[cfg(all(feature = "smol", not(target_arch = "wasm32")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmolRuntime;
+
+#[cfg(all(feature = "smol", not(target_arch = "wasm32")))]
+impl Runtime for SmolRuntime {
+    fn spawn(&self, fut: TaskFuture) {
+        smol::spawn(compat(fut)).detach();
+    }
+
+    fn sleep(&self, duration: Duration) -> TaskFuture {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
+
+/// Runs tasks on async-std's executor, inside [`compat`] for their I/O.
+#[cfg(all(feature = "async-std", not(target_arch = "wasm32")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(all(feature = "async-std", not(target_arch = "wasm32")))]
+impl Runtime for AsyncStdRuntime {
+    fn spawn(&self, fut: TaskFuture) {
+        async_std::task::spawn(compat(fut));
+    }
+
+    fn sleep(&self, duration: Duration) -> TaskFuture {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// Provides a tokio context, started on a background thread, for the HTTP and websocket I/O
+/// of `fut` when it is run by another executor.
+#[cfg(all(
+    any(feature = "smol", feature = "async-std"),
+    not(target_arch = "wasm32")
+))]
+pub fn compat<F: Future>(fut: F) -> impl Future<Output = F::Output> {
+    async_compat::Compat::new(fut)
+}
+
+/// Sleeps on the installed [`Clock`](crate::Clock), the runtime's timers unless one is.
+pub(crate) async fn sleep(duration: Duration) {
+    match crate::clock::installed() {
+        Some(clock) => clock.sleep(duration).await,
+        None => runtime_sleep(duration).await,
+    }
+}
+
+pub(crate) async fn runtime_sleep(duration: Duration) {
+    #[cfg(not(target_arch = "wasm32"))]
+    match RUNTIME.get() {
+        Some(runtime) => runtime.sleep(duration).await,
+        None => tokio::time::sleep(duration).await,
+    }
+    #[cfg(target_arch = "wasm32")]
+    gloo_timers::future::sleep(duration).await;
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg_attr(not(feature = "ws"), allow(dead_code))]
+pub(crate) fn spawn<F: Future<Output = ()> + Send + 'static>(fut: F) {
+    match RUNTIME.get() {
+        Some(runtime) => runtime.spawn(Box::pin(fut)),
+        None => {
+            tokio::spawn(fut);
+        }
+    }
+}
+
+/// Browser futures wrap JS handles and are not `Send`, they run on the page's event loop.
+#[cfg(target_arch = "wasm32")]
+#[cfg_attr(not(feature = "ws"), allow(dead_code))]
+pub(crate) fn spawn<F: Future<Output = ()> + 'static>(fut: F) {
+    wasm_bindgen_futures::spawn_local(fut);
+}
+
+/// `Send` on native targets. Browser futures are not `Send`, so on `wasm32` every type
+/// implements it.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSend for T {}
+
+#[cfg(all(test, feature = "smol"))]
+mod tests {
+    use super::*;
+    use crate::req::HttpClient;
+
+    #[test]
+    fn test_smol_runtime() {
+        smol::block_on(compat(async {
+            let (sender, receiver) = tokio::sync::oneshot::channel();
+            SmolRuntime.spawn(Box::pin(async move {
+                SmolRuntime.sleep(Duration::from_millis(10)).await;
+                sender.send(()).unwrap();
+            }));
+            receiver.await.unwrap();
+
+            // Requests keep running on tokio's reactor
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let mut http_client = HttpClient::new(
+                reqwest::Client::new(),
+                format!("http://{}", listener.local_addr().unwrap()),
+            );
+            http_client.timeout = Some(Duration::from_millis(50));
+            let res = http_client.post("/info", "{}".to_string()).await;
+            assert!(matches!(res, Err(Error::Timeout(_))));
+        }));
+    }
+}
@@ -0,0 +1,417 @@
+use std::{fmt, time::Duration};
+
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use tracing::{info, warn};
+
+use crate::{
+    prelude::*, rt::Instant, truncate_float, ClientCancelRequest, ClientLimit, ClientOrder,
+    ClientOrderRequest, Error, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus,
+    MarketOrderParams, Tif,
+};
+
+/// A step of a `Scenario`, run in the order given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScenarioStep {
+    /// Approves a fresh agent wallet, which signs the orders of later steps
+    ApproveAgent,
+    /// Requests faucet funds if configured and waits until the account is funded
+    Fund,
+    /// Rests a post-only bid away from the mid and cancels it
+    Quote,
+    /// Buys at market and sells the filled size back
+    Fill,
+    /// Withdraws USDC through the bridge
+    Withdraw,
+}
+
+impl ScenarioStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScenarioStep::ApproveAgent => "approve agent",
+            ScenarioStep::Fund => "fund",
+            ScenarioStep::Quote => "quote",
+            ScenarioStep::Fill => "fill",
+            ScenarioStep::Withdraw => "withdraw",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepResult {
+    Passed(String),
+    Failed(String),
+    /// Not run because an earlier step failed
+    Skipped,
+}
+
+#[derive(Clone, Debug)]
+pub struct StepOutcome {
+    pub step: ScenarioStep,
+    pub result: StepResult,
+    pub elapsed: Duration,
+}
+
+/// What `Scenario::run` did, one outcome per step.
+#[derive(Clone, Debug)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub steps: Vec<StepOutcome>,
+}
+
+impl ScenarioReport {
+    pub fn passed(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|outcome| matches!(outcome.result, StepResult::Passed(_)))
+    }
+
+    /// The step that stopped the scenario, with its error.
+    pub fn failure(&self) -> Option<(ScenarioStep, &str)> {
+        self.steps.iter().find_map(|outcome| match &outcome.result {
+            StepResult::Failed(err) => Some((outcome.step, err.as_str())),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verdict = if self.passed() { "passed" } else { "failed" };
+        write!(f, "{}: {verdict}", self.name)?;
+        for outcome in &self.steps {
+            let (status, detail) = match &outcome.result {
+                StepResult::Passed(detail) => ("ok", detail.as_str()),
+                StepResult::Failed(err) => ("FAILED", err.as_str()),
+                StepResult::Skipped => ("skipped", ""),
+            };
+            write!(
+                f,
+                "\n  {} {status} ({}ms) {detail}",
+                outcome.step.as_str(),
+                outcome.elapsed.as_millis()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A scripted end-to-end flow against testnet or a `MockServer`, for smoke-testing a
+/// deployment: keys, network access, signing and the account's permissions.
+///
+/// The default script approves an agent, waits for funds, quotes, round-trips `sz` of `coin`
+/// at market and withdraws. Steps run until one fails; the rest are skipped. The fill step
+/// leaves no position as long as both legs fill completely.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    name: String,
+    coin: String,
+    sz: f64,
+    steps: Vec<ScenarioStep>,
+    quote_offset: f64,
+    slippage: Option<f64>,
+    faucet_url: Option<String>,
+    min_account_value: f64,
+    funding_timeout: Duration,
+    withdraw_amount: f64,
+    withdraw_destination: Option<Address>,
+}
+
+impl Scenario {
+    pub fn new(name: &str, coin: &str, sz: f64) -> Scenario {
+        Scenario {
+            name: name.to_string(),
+            coin: coin.to_string(),
+            sz,
+            steps: vec![
+                ScenarioStep::ApproveAgent,
+                ScenarioStep::Fund,
+                ScenarioStep::Quote,
+                ScenarioStep::Fill,
+                ScenarioStep::Withdraw,
+            ],
+            quote_offset: 0.05,
+            slippage: None,
+            faucet_url: None,
+            min_account_value: 10.0,
+            funding_timeout: Duration::from_secs(60),
+            withdraw_amount: 2.0,
+            withdraw_destination: None,
+        }
+    }
+
+    pub fn with_steps(mut self, steps: Vec<ScenarioStep>) -> Self {
+        self.steps = steps;
+        self
+    }
+
+    /// Fraction below the mid the quote step bids at, 5% by default.
+    pub fn with_quote_offset(mut self, quote_offset: f64) -> Self {
+        self.quote_offset = quote_offset;
+        self
+    }
+
+    /// Slippage of the fill step, the client's default otherwise.
+    pub fn with_slippage(mut self, slippage: f64) -> Self {
+        self.slippage = Some(slippage);
+        self
+    }
+
+    /// Faucet service asked for funds by the fund step, see `ExchangeClient::request_testnet_funds`.
+    pub fn with_faucet_url(mut self, faucet_url: &str) -> Self {
+        self.faucet_url = Some(faucet_url.to_string());
+        self
+    }
+
+    /// Account value in USDC the fund step waits for, at most `timeout`.
+    pub fn with_funding(mut self, min_account_value: f64, timeout: Duration) -> Self {
+        self.min_account_value = min_account_value;
+        self.funding_timeout = timeout;
+        self
+    }
+
+    /// USDC the withdraw step withdraws, 2 by default to cover the bridge fee, to
+    /// `destination` or the account itself.
+    pub fn with_withdraw(mut self, amount: f64, destination: Option<Address>) -> Self {
+        self.withdraw_amount = amount;
+        self.withdraw_destination = destination;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn steps(&self) -> &[ScenarioStep] {
+        &self.steps
+    }
+
+    /// Runs the steps with `exchange`, recording each outcome. Fails only with
+    /// `Error::ChainNotAllowed` for a mainnet client, as scenarios trade and withdraw.
+    pub async fn run(&self, exchange: &ExchangeClient) -> Result<ScenarioReport> {
+        if exchange.http_client.is_mainnet() {
+            return Err(Error::ChainNotAllowed);
+        }
+        let mut agent = None;
+        let mut steps = Vec::with_capacity(self.steps.len());
+        let mut failed = false;
+        for &step in &self.steps {
+            if failed {
+                steps.push(StepOutcome {
+                    step,
+                    result: StepResult::Skipped,
+                    elapsed: Duration::ZERO,
+                });
+                continue;
+            }
+            let started = Instant::now();
+            let result = match self.run_step(exchange, step, &mut agent).await {
+                Ok(detail) => {
+                    info!(scenario = %self.name, step = step.as_str(), "{detail}");
+                    StepResult::Passed(detail)
+                }
+                Err(err) => {
+                    warn!(scenario = %self.name, step = step.as_str(), "{err}");
+                    failed = true;
+                    StepResult::Failed(err.to_string())
+                }
+            };
+            steps.push(StepOutcome {
+                step,
+                result,
+                elapsed: started.elapsed(),
+            });
+        }
+        Ok(ScenarioReport {
+            name: self.name.clone(),
+            steps,
+        })
+    }
+
+    async fn run_step(
+        &self,
+        exchange: &ExchangeClient,
+        step: ScenarioStep,
+        agent: &mut Option<PrivateKeySigner>,
+    ) -> Result<String> {
+        match step {
+            ScenarioStep::ApproveAgent => {
+                let (key, status) = exchange.approve_agent(None).await?;
+                first_status(status, "approve agent")?;
+                let signer = PrivateKeySigner::from_bytes(&key)
+                    .map_err(|e| Error::PrivateKeyParse(e.to_string()))?;
+                let detail = format!("approved agent {}", signer.address());
+                *agent = Some(signer);
+                Ok(detail)
+            }
+            ScenarioStep::Fund => {
+                if let Some(faucet_url) = &self.faucet_url {
+                    exchange.request_testnet_funds(faucet_url).await?;
+                }
+                let account_value = exchange
+                    .wait_for_testnet_funds(self.min_account_value, self.funding_timeout)
+                    .await?;
+                Ok(format!("account worth {account_value} USDC"))
+            }
+            ScenarioStep::Quote => {
+                let (px, sz_decimals) = exchange
+                    .calculate_slippage_price(&self.coin, false, self.quote_offset, None)
+                    .await?;
+                let order = ClientOrderRequest {
+                    asset: self.coin.clone(),
+                    is_buy: true,
+                    reduce_only: false,
+                    limit_px: px,
+                    sz: truncate_float(self.sz, sz_decimals, false),
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Alo }),
+                };
+                let status = exchange.order(order, agent.as_ref()).await?;
+                let ExchangeDataStatus::Resting(resting) = first_status(status, "quote")? else {
+                    return Err(Error::GenericRequest(format!(
+                        "{} quote at {px} did not rest",
+                        self.coin
+                    )));
+                };
+                let cancel = ClientCancelRequest {
+                    asset: self.coin.clone(),
+                    oid: resting.oid,
+                };
+                let status = exchange.cancel(cancel, agent.as_ref()).await?;
+                first_status(status, "cancel quote")?;
+                Ok(format!("rested and cancelled {} bid at {px}", self.coin))
+            }
+            ScenarioStep::Fill => {
+                let (sz, buy_px) = self.market(exchange, true, self.sz, agent).await?;
+                let (_, sell_px) = self.market(exchange, false, sz, agent).await?;
+                Ok(format!(
+                    "bought {sz} {} at {buy_px}, sold at {sell_px}",
+                    self.coin
+                ))
+            }
+            ScenarioStep::Withdraw => {
+                let destination = self
+                    .withdraw_destination
+                    .or(exchange.vault_address)
+                    .unwrap_or(exchange.wallet.address());
+                let status = exchange
+                    .withdraw_from_bridge(
+                        &self.withdraw_amount.to_string(),
+                        &destination.to_string(),
+                        None,
+                    )
+                    .await?;
+                first_status(status, "withdraw")?;
+                Ok(format!(
+                    "withdrew {} USDC to {destination}",
+                    self.withdraw_amount
+                ))
+            }
+        }
+    }
+
+    /// A market order of `sz`, returning the filled size and average price.
+    async fn market(
+        &self,
+        exchange: &ExchangeClient,
+        is_buy: bool,
+        sz: f64,
+        agent: &Option<PrivateKeySigner>,
+    ) -> Result<(f64, f64)> {
+        let status = exchange
+            .market_open(MarketOrderParams {
+                asset: &self.coin,
+                is_buy,
+                sz,
+                px: None,
+                slippage: self.slippage,
+                cloid: None,
+                wallet: agent.as_ref(),
+            })
+            .await?;
+        let side = if is_buy { "buy" } else { "sell" };
+        let ExchangeDataStatus::Filled(filled) = first_status(status, side)? else {
+            return Err(Error::GenericRequest(format!(
+                "{} market {side} did not fill",
+                self.coin
+            )));
+        };
+        let parse = |value: &str| value.parse::<f64>().map_err(|_| Error::FloatStringParse);
+        Ok((parse(&filled.total_sz)?, parse(&filled.avg_px)?))
+    }
+}
+
+/// The status of a single action, failing on a rejected request or order.
+fn first_status(status: ExchangeResponseStatus, what: &str) -> Result<ExchangeDataStatus> {
+    let status = match status {
+        ExchangeResponseStatus::Ok(response) => response
+            .data
+            .and_then(|data| data.statuses.into_iter().next())
+            .unwrap_or(ExchangeDataStatus::Success),
+        ExchangeResponseStatus::Err(err) => {
+            return Err(Error::GenericRequest(format!("Could not {what}: {err}")))
+        }
+    };
+    match status {
+        ExchangeDataStatus::Error(err) => {
+            Err(Error::GenericRequest(format!("Could not {what}: {err}")))
+        }
+        status => Ok(status),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::{MockConfig, MockEndpoint, MockFill, MockServer};
+
+    #[tokio::test]
+    async fn test_scenario_round_trip_against_mock() {
+        let wallet = PrivateKeySigner::random();
+        let server = MockServer::start(MockConfig {
+            user: wallet.address(),
+            ..MockConfig::default()
+        })
+        .await
+        .unwrap();
+        server.set_book("ETH", &[(1990.0, 10.0)], &[(2010.0, 10.0)]);
+        let exchange = ExchangeClient::new(None, wallet, Some(server.base_url()), None, None)
+            .await
+            .unwrap();
+
+        let report = Scenario::new("smoke", "ETH", 0.1)
+            .run(&exchange)
+            .await
+            .unwrap();
+        assert!(report.passed(), "{report}");
+        assert_eq!(report.steps.len(), 5);
+        let types: Vec<_> = server
+            .requests(MockEndpoint::Exchange)
+            .iter()
+            .map(|body| body["action"]["type"].clone())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "approveAgent",
+                "order",
+                "cancel",
+                "order",
+                "order",
+                "withdraw3"
+            ]
+        );
+        assert!(server.positions().iter().all(|p| p.szi.abs() < 1e-9));
+
+        // A rejected fill stops the scenario before withdrawing
+        server.set_fill(MockFill::Reject("Insufficient margin".to_string()));
+        let report = Scenario::new("rejected", "ETH", 0.1)
+            .with_steps(vec![ScenarioStep::Fill, ScenarioStep::Withdraw])
+            .run(&exchange)
+            .await
+            .unwrap();
+        let (step, err) = report.failure().unwrap();
+        assert_eq!(step, ScenarioStep::Fill);
+        assert!(err.contains("Insufficient margin"));
+        assert_eq!(report.steps[1].result, StepResult::Skipped);
+    }
+}
@@ -0,0 +1,410 @@
+#[cfg(feature = "exchange")]
+use std::sync::Arc;
+use std::{
+    fmt,
+    future::Future,
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+};
+
+#[cfg(feature = "exchange")]
+use alloy::signers::local::PrivateKeySigner;
+#[cfg(feature = "aws-secrets")]
+use chrono::Utc;
+#[cfg(any(feature = "vault", feature = "aws-secrets"))]
+use reqwest::Client;
+#[cfg(any(feature = "vault", feature = "aws-secrets"))]
+use serde_json::Value;
+#[cfg(feature = "aws-secrets")]
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "aws-secrets")]
+use crate::aws::{amz_date, authorization, AwsCredentials};
+#[cfg(any(feature = "vault", feature = "aws-secrets"))]
+use crate::req::{parse_response, reqwest_error};
+#[cfg(feature = "exchange")]
+use crate::SignerId;
+use crate::{prelude::*, Error};
+
+/// A secret being fetched by a [`SecretsProvider`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type SecretFuture<'a> = Pin<Box<dyn Future<Output = Result<String>> + Send + 'a>>;
+#[cfg(target_arch = "wasm32")]
+pub type SecretFuture<'a> = Pin<Box<dyn Future<Output = Result<String>> + 'a>>;
+
+/// Where private keys and other secrets are fetched from when a client is built, so they are
+/// never written in config files. See `ExchangeClientBuilder::wallet_secret` and
+/// `HyperliquidClient::builder_with_secret`.
+pub trait SecretsProvider: fmt::Debug + Send + Sync {
+    /// The secret stored under `name`, with surrounding whitespace removed.
+    fn secret<'a>(&'a self, name: &'a str) -> SecretFuture<'a>;
+}
+
+/// The private key stored under `name` in `provider`, as hex.
+#[cfg(feature = "exchange")]
+pub async fn load_wallet(provider: &dyn SecretsProvider, name: &str) -> Result<PrivateKeySigner> {
+    provider
+        .secret(name)
+        .await?
+        .parse()
+        .map_err(|e| Error::PrivateKeyParse(format!("{e}")))
+}
+
+/// Secrets in environment variables named after the secret, with an optional prefix.
+#[derive(Clone, Debug, Default)]
+pub struct EnvSecrets {
+    prefix: String,
+}
+
+impl EnvSecrets {
+    pub fn new() -> EnvSecrets {
+        EnvSecrets::default()
+    }
+
+    /// Reads `<prefix><name>` instead of `<name>`.
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+}
+
+impl SecretsProvider for EnvSecrets {
+    fn secret<'a>(&'a self, name: &'a str) -> SecretFuture<'a> {
+        let var = format!("{}{name}", self.prefix);
+        let secret = std::env::var(&var)
+            .map(|value| value.trim().to_string())
+            .map_err(|_| Error::Secret(format!("environment variable {var} not set")));
+        Box::pin(async move { secret })
+    }
+}
+
+/// Secrets in files named after the secret within a directory, such as the `/run/secrets`
+/// of Docker and Kubernetes.
+#[derive(Clone, Debug)]
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    pub fn new(dir: impl AsRef<Path>) -> FileSecrets {
+        FileSecrets {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl SecretsProvider for FileSecrets {
+    fn secret<'a>(&'a self, name: &'a str) -> SecretFuture<'a> {
+        Box::pin(async move {
+            // Names may contain subdirectories but must stay within the directory
+            if !Path::new(name)
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(Error::Secret(format!("invalid secret name {name}")));
+            }
+            let path = self.dir.join(name);
+            std::fs::read_to_string(&path)
+                .map(|secret| secret.trim().to_string())
+                .map_err(|e| Error::Secret(format!("{}: {e}", path.display())))
+        })
+    }
+}
+
+/// Secrets in a HashiCorp Vault KV version 2 engine. A secret's name is its path within the
+/// engine, and its value one field of the stored data, `private_key` by default.
+#[cfg(feature = "vault")]
+#[derive(Clone)]
+pub struct VaultSecrets {
+    client: Client,
+    addr: String,
+    token: String,
+    namespace: Option<String>,
+    mount: String,
+    field: String,
+}
+
+#[cfg(feature = "vault")]
+impl fmt::Debug for VaultSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultSecrets")
+            .field("addr", &self.addr)
+            .field("namespace", &self.namespace)
+            .field("mount", &self.mount)
+            .field("field", &self.field)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "vault")]
+impl VaultSecrets {
+    /// Reads from the Vault server at `addr`, such as `https://vault.internal:8200`, with
+    /// `token`, from the engine mounted at `secret`.
+    pub fn new(addr: &str, token: &str) -> VaultSecrets {
+        VaultSecrets {
+            client: Client::new(),
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            namespace: None,
+            mount: "secret".to_string(),
+            field: "private_key".to_string(),
+        }
+    }
+
+    /// Reads `VAULT_ADDR`, `VAULT_TOKEN` and `VAULT_NAMESPACE`, like the Vault CLI.
+    pub fn from_env() -> Option<VaultSecrets> {
+        let mut vault = VaultSecrets::new(
+            &std::env::var("VAULT_ADDR").ok()?,
+            &std::env::var("VAULT_TOKEN").ok()?,
+        );
+        vault.namespace = std::env::var("VAULT_NAMESPACE").ok();
+        Some(vault)
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Enterprise namespace the engine is in.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Path the KV engine is mounted at, `secret` by default.
+    pub fn with_mount(mut self, mount: &str) -> Self {
+        self.mount = mount.trim_matches('/').to_string();
+        self
+    }
+
+    /// Field of the stored data holding the secret, `private_key` by default.
+    pub fn with_field(mut self, field: &str) -> Self {
+        self.field = field.to_string();
+        self
+    }
+
+    async fn read(&self, name: &str) -> Result<String> {
+        let url = format!(
+            "{}/v1/{}/data/{}",
+            self.addr,
+            self.mount,
+            name.trim_matches('/')
+        );
+        let mut request = self.client.get(url).header("X-Vault-Token", &self.token);
+        if let Some(namespace) = &self.namespace {
+            request = request.header("X-Vault-Namespace", namespace);
+        }
+        let response = request.send().await.map_err(|e| reqwest_error(&e))?;
+        let body: Value = serde_json::from_str(&parse_response(response).await?)
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        body["data"]["data"][&self.field]
+            .as_str()
+            .map(|secret| secret.trim().to_string())
+            .ok_or_else(|| Error::Secret(format!("{name} has no field {}", self.field)))
+    }
+}
+
+#[cfg(feature = "vault")]
+impl SecretsProvider for VaultSecrets {
+    fn secret<'a>(&'a self, name: &'a str) -> SecretFuture<'a> {
+        Box::pin(self.read(name))
+    }
+}
+
+/// Secrets in AWS Secrets Manager, named by secret name or ARN. A secret stored as a JSON
+/// object is read from one of its keys with `with_json_key`.
+#[cfg(feature = "aws-secrets")]
+#[derive(Clone, Debug)]
+pub struct AwsSecretsManager {
+    client: Client,
+    credentials: Option<AwsCredentials>,
+    region: String,
+    endpoint: Option<String>,
+    json_key: Option<String>,
+}
+
+#[cfg(feature = "aws-secrets")]
+impl Default for AwsSecretsManager {
+    fn default() -> Self {
+        AwsSecretsManager::new()
+    }
+}
+
+#[cfg(feature = "aws-secrets")]
+impl AwsSecretsManager {
+    /// Reads credentials and `AWS_REGION` from the environment, `us-east-1` without it.
+    pub fn new() -> AwsSecretsManager {
+        AwsSecretsManager {
+            client: Client::new(),
+            credentials: AwsCredentials::from_env(),
+            region: std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            endpoint: None,
+            json_key: None,
+        }
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_credentials(mut self, credentials: AwsCredentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn with_region(mut self, region: &str) -> Self {
+        self.region = region.to_string();
+        self
+    }
+
+    /// Endpoint replacing the region's, such as a VPC endpoint or a local emulator.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Reads the secret from `key` of the JSON object stored in the secret string.
+    pub fn with_json_key(mut self, key: &str) -> Self {
+        self.json_key = Some(key.to_string());
+        self
+    }
+
+    async fn read(&self, name: &str) -> Result<String> {
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or_else(|| Error::Secret("no AWS credentials".to_string()))?;
+        let (url, host) = match &self.endpoint {
+            Some(endpoint) => {
+                let host = endpoint.split("://").last().unwrap_or(endpoint).to_string();
+                (format!("{endpoint}/"), host)
+            }
+            None => {
+                let host = format!("secretsmanager.{}.amazonaws.com", self.region);
+                (format!("https://{host}/"), host)
+            }
+        };
+        let body = serde_json::json!({ "SecretId": name }).to_string();
+        let time = Utc::now();
+        let mut headers = vec![
+            ("host", host),
+            (
+                "x-amz-content-sha256",
+                alloy::hex::encode(Sha256::digest(body.as_bytes())),
+            ),
+            ("x-amz-date", amz_date(time)),
+            ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = authorization(
+            credentials,
+            &self.region,
+            "secretsmanager",
+            "POST",
+            "/",
+            &headers,
+            time,
+        );
+        let mut request = self
+            .client
+            .post(url)
+            .header("content-type", "application/x-amz-json-1.1")
+            .header("authorization", authorization)
+            .body(body);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(|e| reqwest_error(&e))?;
+        let response: Value = serde_json::from_str(&parse_response(response).await?)
+            .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let secret = response["SecretString"]
+            .as_str()
+            .ok_or_else(|| Error::Secret(format!("{name} has no secret string")))?;
+        let secret = match &self.json_key {
+            Some(key) => {
+                let object: Value =
+                    serde_json::from_str(secret).map_err(|e| Error::JsonParse(e.to_string()))?;
+                object[key]
+                    .as_str()
+                    .ok_or_else(|| Error::Secret(format!("{name} has no key {key}")))?
+                    .to_string()
+            }
+            None => secret.to_string(),
+        };
+        Ok(secret.trim().to_string())
+    }
+}
+
+#[cfg(feature = "aws-secrets")]
+impl SecretsProvider for AwsSecretsManager {
+    fn secret<'a>(&'a self, name: &'a str) -> SecretFuture<'a> {
+        Box::pin(self.read(name))
+    }
+}
+
+/// A builder's signer, given directly or fetched from a `SecretsProvider` on `build`.
+#[cfg(feature = "exchange")]
+#[derive(Clone)]
+pub(crate) enum WalletSource {
+    Key(PrivateKeySigner),
+    Secret(Arc<dyn SecretsProvider>, String),
+}
+
+#[cfg(feature = "exchange")]
+impl WalletSource {
+    pub(crate) async fn load(self) -> Result<PrivateKeySigner> {
+        match self {
+            WalletSource::Key(wallet) => Ok(wallet),
+            WalletSource::Secret(provider, name) => load_wallet(provider.as_ref(), &name).await,
+        }
+    }
+}
+
+#[cfg(feature = "exchange")]
+impl fmt::Debug for WalletSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletSource::Key(wallet) => fmt::Debug::fmt(&SignerId::of(wallet), f),
+            WalletSource::Secret(provider, name) => f
+                .debug_struct("Secret")
+                .field("provider", provider)
+                .field("name", name)
+                .finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e";
+
+    #[tokio::test]
+    async fn test_env_and_file_secrets() {
+        std::env::set_var("HL_SECRETS_TEST_MAIN", format!(" {KEY}\n"));
+        let env = EnvSecrets::new().with_prefix("HL_SECRETS_TEST_");
+        assert_eq!(env.secret("MAIN").await.unwrap(), KEY);
+        assert!(matches!(env.secret("OTHER").await, Err(Error::Secret(_))));
+
+        let dir = std::env::temp_dir().join(format!("secrets_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("hl")).unwrap();
+        std::fs::write(dir.join("hl").join("main"), format!("{KEY}\n")).unwrap();
+        let files = FileSecrets::new(&dir);
+        assert_eq!(files.secret("hl/main").await.unwrap(), KEY);
+        assert!(files.secret("../main").await.is_err());
+
+        #[cfg(feature = "exchange")]
+        {
+            let expected: PrivateKeySigner = KEY.parse().unwrap();
+            let wallet = WalletSource::Secret(Arc::new(files), "hl/main".to_string());
+            assert!(format!("{wallet:?}").contains("hl/main"));
+            assert_eq!(wallet.load().await.unwrap().address(), expected.address());
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
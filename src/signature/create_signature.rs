@@ -3,8 +3,11 @@ use alloy::{
     signers::{local::PrivateKeySigner, Signature, SignerSync},
 };
 
+use tracing::instrument;
+
 use crate::{eip712::Eip712, prelude::*, signature::agent::l1, Error};
 
+#[instrument(skip(wallet), fields(signer = %wallet.address()))]
 pub(crate) fn sign_l1_action(
     wallet: &PrivateKeySigner,
     connection_id: B256,
@@ -18,6 +21,7 @@ pub(crate) fn sign_l1_action(
     sign_typed_data(&payload, wallet)
 }
 
+#[instrument(level = "debug", skip_all, fields(signer = %wallet.address()))]
 pub(crate) fn sign_typed_data<T: Eip712>(
     payload: &T,
     wallet: &PrivateKeySigner,
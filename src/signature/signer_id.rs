@@ -0,0 +1,41 @@
+use std::fmt;
+
+use alloy::{
+    hex,
+    primitives::{keccak256, Address},
+    signers::local::PrivateKeySigner,
+};
+
+/// Identifies a signer in logs by its address and a short fingerprint of its public key, never
+/// the key itself. Clients and builders holding a wallet print this in their `Debug` output.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignerId {
+    pub address: Address,
+    pub fingerprint: [u8; 4],
+}
+
+impl SignerId {
+    pub fn of(wallet: &PrivateKeySigner) -> SignerId {
+        // The address is the end of the same hash, so this reveals nothing more about the key
+        let hash = keccak256(wallet.public_key());
+        SignerId {
+            address: wallet.address(),
+            fingerprint: [hash[0], hash[1], hash[2], hash[3]],
+        }
+    }
+}
+
+impl fmt::Debug for SignerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignerId")
+            .field("address", &self.address)
+            .field("fingerprint", &hex::encode(self.fingerprint))
+            .finish()
+    }
+}
+
+impl fmt::Display for SignerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.address, hex::encode(self.fingerprint))
+    }
+}
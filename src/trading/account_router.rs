@@ -0,0 +1,414 @@
+use std::collections::HashMap;
+
+use tracing::warn;
+
+use crate::{
+    lot_size, prelude::*, round_to_tick, ClientOrderRequest, Exchange, ExchangeResponseStatus,
+    ExecutionReport, InfoClient, Message, RoundingMode, EPSILON,
+};
+
+/// One account `AccountRouter` sends child orders through, with its own limits.
+///
+/// Positions and free margin come from `AccountRouter::refresh`, the setters, and fills passed
+/// to `AccountRouter::handle_message`. Without free margin set, margin does not limit the
+/// account.
+#[derive(Debug)]
+pub struct RoutedAccount<E> {
+    name: String,
+    exchange: E,
+    weight: f64,
+    max_position: HashMap<String, f64>,
+    max_leverage: f64,
+    positions: HashMap<String, f64>,
+    free_margin: Option<f64>,
+}
+
+impl<E: Exchange> RoutedAccount<E> {
+    pub fn new(name: impl Into<String>, exchange: E) -> RoutedAccount<E> {
+        RoutedAccount {
+            name: name.into(),
+            exchange,
+            weight: 1.0,
+            max_position: HashMap::new(),
+            max_leverage: 1.0,
+            positions: HashMap::new(),
+            free_margin: None,
+        }
+    }
+
+    /// Share of each parent order relative to the other accounts, 1 by default.
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight.max(0.0);
+        self
+    }
+
+    /// Largest absolute position the account may hold in `coin`.
+    pub fn with_max_position(mut self, coin: impl Into<String>, sz: f64) -> Self {
+        self.max_position.insert(coin.into(), sz);
+        self
+    }
+
+    /// Notional a child order may reach per unit of free margin, 1 by default.
+    pub fn with_max_leverage(mut self, leverage: f64) -> Self {
+        self.max_leverage = leverage;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn exchange(&self) -> &E {
+        &self.exchange
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    pub fn position(&self, coin: &str) -> f64 {
+        self.positions.get(coin).copied().unwrap_or_default()
+    }
+
+    pub fn set_position(&mut self, coin: impl Into<String>, szi: f64) {
+        self.positions.insert(coin.into(), szi);
+    }
+
+    pub fn free_margin(&self) -> Option<f64> {
+        self.free_margin
+    }
+
+    pub fn set_free_margin(&mut self, usd: f64) {
+        self.free_margin = Some(usd);
+    }
+
+    /// Largest size of `order` the account has room for, within its position limit and the
+    /// notional its free margin allows. Reduce-only orders are only limited by the position
+    /// they reduce.
+    pub fn capacity(&self, order: &ClientOrderRequest) -> f64 {
+        let position = self.position(&order.asset);
+        let direction = if order.is_buy { 1.0 } else { -1.0 };
+        if order.reduce_only {
+            return (-direction * position).max(0.0);
+        }
+        let by_position = self
+            .max_position
+            .get(&order.asset)
+            .map_or(f64::INFINITY, |limit| limit - direction * position);
+        let by_margin = self.free_margin.map_or(f64::INFINITY, |margin| {
+            margin * self.max_leverage / order.limit_px
+        });
+        by_position.min(by_margin).max(0.0)
+    }
+}
+
+/// Size of a parent order given to one account.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChildAllocation {
+    pub account: String,
+    pub sz: f64,
+}
+
+/// Split of a parent order across accounts, from `AccountRouter::plan`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutePlan {
+    pub children: Vec<ChildAllocation>,
+    /// Size no account had room for
+    pub unallocated: f64,
+}
+
+/// A child order sent by `AccountRouter::route`.
+#[derive(Debug)]
+pub struct RoutedChild {
+    pub account: String,
+    pub order: ClientOrderRequest,
+    pub result: Result<ExchangeResponseStatus>,
+    /// Fills of this child only
+    pub report: ExecutionReport,
+}
+
+/// A parent order routed across accounts, with one execution report over all its fills.
+#[derive(Debug)]
+pub struct RoutedOrder {
+    pub children: Vec<RoutedChild>,
+    /// Size no account had room for, which was not sent
+    pub unallocated: f64,
+    pub report: ExecutionReport,
+}
+
+impl RoutedOrder {
+    /// Adds fills of the child orders, from the `userFills` streams of every account.
+    pub fn handle_message(&mut self, message: &Message) {
+        self.report.handle_message(message);
+        for child in &mut self.children {
+            child.report.handle_message(message);
+        }
+    }
+
+    /// Children the exchange or a risk check rejected outright.
+    pub fn failed(&self) -> impl Iterator<Item = &RoutedChild> {
+        self.children.iter().filter(|child| child.result.is_err())
+    }
+}
+
+/// Splits parent orders across several accounts or sub-accounts, such as segregated mandates
+/// of one fund, and aggregates the child fills into one `ExecutionReport`.
+///
+/// Each parent order is shared out by account weight, capped by each account's `capacity`;
+/// what an account has no room for goes to the others, and what none has room for is left
+/// unallocated. Children are rounded down to whole lots. Wrap an account's exchange in a
+/// `RiskEngine` to apply its full pre-trade checks as well.
+#[derive(Debug)]
+pub struct AccountRouter<E> {
+    accounts: Vec<RoutedAccount<E>>,
+}
+
+impl<E: Exchange> AccountRouter<E> {
+    pub fn new(accounts: Vec<RoutedAccount<E>>) -> AccountRouter<E> {
+        AccountRouter { accounts }
+    }
+
+    pub fn accounts(&self) -> &[RoutedAccount<E>] {
+        &self.accounts
+    }
+
+    pub fn account_mut(&mut self, name: &str) -> Option<&mut RoutedAccount<E>> {
+        self.accounts
+            .iter_mut()
+            .find(|account| account.name == name)
+    }
+
+    /// Replaces each account's positions and free margin with the exchange's.
+    pub async fn refresh(&mut self, info: &InfoClient) -> Result<()> {
+        for account in &mut self.accounts {
+            let state = info.user_state(account.exchange.address()).await?;
+            account.positions = state
+                .asset_positions
+                .iter()
+                .filter_map(|p| Some((p.position.coin.clone(), p.position.szi.parse().ok()?)))
+                .collect();
+            account.free_margin = state.withdrawable.parse().ok();
+        }
+        Ok(())
+    }
+
+    /// Updates account positions with their fills. Snapshots are skipped, as they repeat
+    /// fills already counted by `refresh`.
+    pub fn handle_message(&mut self, message: &Message) {
+        let Message::UserFills(fills) = message else {
+            return;
+        };
+        if fills.data.is_snapshot.unwrap_or(false) {
+            return;
+        }
+        let Some(account) = self
+            .accounts
+            .iter_mut()
+            .find(|account| account.exchange.address() == fills.data.user)
+        else {
+            return;
+        };
+        for fill in &fills.data.fills {
+            let Ok(sz) = fill.sz.parse::<f64>() else {
+                continue;
+            };
+            *account.positions.entry(fill.coin.clone()).or_default() += fill.side.sign() * sz;
+        }
+    }
+
+    /// Splits `order` across the accounts without sending anything.
+    pub fn plan(&self, order: &ClientOrderRequest, sz_decimals: u32) -> RoutePlan {
+        let lot = lot_size(sz_decimals);
+        let capacities: Vec<f64> = self
+            .accounts
+            .iter()
+            .map(|account| round_to_tick(account.capacity(order), lot, RoundingMode::Down))
+            .collect();
+        let mut sizes = vec![0.0; self.accounts.len()];
+        let mut remaining = order.sz;
+        // Each round fills the accounts with room pro rata; an account that runs out of room
+        // drops out and its share goes round again
+        while remaining > EPSILON {
+            let open: Vec<usize> = (0..self.accounts.len())
+                .filter(|&i| self.accounts[i].weight > 0.0 && capacities[i] - sizes[i] > EPSILON)
+                .collect();
+            let total_weight: f64 = open.iter().map(|&i| self.accounts[i].weight).sum();
+            if total_weight <= 0.0 {
+                break;
+            }
+            let mut given = 0.0;
+            for &i in &open {
+                let share = remaining * self.accounts[i].weight / total_weight;
+                let sz = share.min(capacities[i] - sizes[i]);
+                sizes[i] += sz;
+                given += sz;
+            }
+            remaining -= given;
+            if given <= EPSILON {
+                break;
+            }
+        }
+
+        let mut sizes: Vec<f64> = sizes
+            .into_iter()
+            .map(|sz| round_to_tick(sz + EPSILON, lot, RoundingMode::Down))
+            .collect();
+        // Lots lost to rounding go to the heaviest accounts with room
+        let mut order_by_weight: Vec<usize> = (0..sizes.len()).collect();
+        order_by_weight
+            .sort_by(|&a, &b| self.accounts[b].weight.total_cmp(&self.accounts[a].weight));
+        let mut leftover = round_to_tick(
+            order.sz - sizes.iter().sum::<f64>() + EPSILON,
+            lot,
+            RoundingMode::Down,
+        );
+        for i in order_by_weight {
+            if leftover < lot - EPSILON {
+                break;
+            }
+            let room = round_to_tick(capacities[i] - sizes[i] + EPSILON, lot, RoundingMode::Down);
+            let extra = leftover.min(room);
+            sizes[i] += extra;
+            leftover -= extra;
+        }
+
+        let allocated: f64 = sizes.iter().sum();
+        RoutePlan {
+            children: self
+                .accounts
+                .iter()
+                .zip(sizes)
+                .filter(|(_, sz)| *sz > EPSILON)
+                .map(|(account, sz)| ChildAllocation {
+                    account: account.name.clone(),
+                    sz,
+                })
+                .collect(),
+            unallocated: (order.sz - allocated).max(0.0),
+        }
+    }
+
+    /// Sends `order` as one child per account in the plan, each with its own cloid tracked by
+    /// the returned report, whose tag is `tag`. A child that fails does not stop the others.
+    pub async fn route(
+        &self,
+        order: ClientOrderRequest,
+        sz_decimals: u32,
+        tag: impl Into<String>,
+    ) -> RoutedOrder {
+        let plan = self.plan(&order, sz_decimals);
+        let mut report = ExecutionReport::new(tag);
+        let mut children = Vec::with_capacity(plan.children.len());
+        for allocation in plan.children {
+            let Some(account) = self
+                .accounts
+                .iter()
+                .find(|account| account.name == allocation.account)
+            else {
+                continue;
+            };
+            let cloid = report.new_cloid();
+            let mut child_report = ExecutionReport::new(account.name.clone());
+            child_report.track_cloid(cloid);
+            let child = ClientOrderRequest {
+                sz: allocation.sz,
+                cloid: Some(cloid),
+                ..order.clone()
+            };
+            let result = account.exchange.order(child.clone()).await;
+            if let Err(err) = &result {
+                warn!(account = account.name, "Child order failed: {err}");
+            }
+            children.push(RoutedChild {
+                account: allocation.account,
+                order: child,
+                result,
+                report: child_report,
+            });
+        }
+        RoutedOrder {
+            children,
+            unallocated: plan.unallocated,
+            report,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy::primitives::Address;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{ClientLimit, ClientOrder, PaperConfig, PaperExchange, Tif};
+
+    fn paper(address: Address) -> PaperExchange {
+        PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            address,
+            ..PaperConfig::default()
+        })
+    }
+
+    fn buy(sz: f64) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 2000.0,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_splits_by_weight_within_limits_and_aggregates_fills() {
+        let (sender, mut receiver) = unbounded_channel();
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut large = RoutedAccount::new("large", paper(a).with_sender(sender.clone()))
+            .with_weight(3.0)
+            .with_max_position("ETH", 5.0);
+        large.set_position("ETH", 1.0);
+        let mut small = RoutedAccount::new("small", paper(b).with_sender(sender)).with_weight(1.0);
+        // 2000 of margin at 2x buys 2 ETH at 2000
+        small.set_free_margin(2000.0);
+        let mut router = AccountRouter::new(vec![large, small.with_max_leverage(2.0)]);
+
+        let plan = router.plan(&buy(4.0), 2);
+        let sizes: Vec<f64> = plan.children.iter().map(|child| child.sz).collect();
+        assert_eq!(sizes, [3.0, 1.0]);
+        // The large account has room for 4 more, the small one for 2
+        let plan = router.plan(&buy(8.0), 2);
+        let sizes: Vec<f64> = plan.children.iter().map(|child| child.sz).collect();
+        assert_eq!(sizes, [4.0, 2.0]);
+        assert!((plan.unallocated - 2.0).abs() < 1e-9);
+
+        let book = r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2000","sz":"10","n":1}]]}}"#;
+        for account in router.accounts() {
+            account
+                .exchange()
+                .handle_message(&serde_json::from_str(book).unwrap());
+        }
+        // Paper exchanges each number trades from 1, where the exchange's trade ids are unique
+        router.accounts()[1]
+            .exchange()
+            .order(buy(0.1))
+            .await
+            .unwrap();
+        while receiver.try_recv().is_ok() {}
+        let mut routed = router.route(buy(2.0), 2, "rebalance").await;
+        assert!(routed.failed().next().is_none());
+        while let Ok(message) = receiver.try_recv() {
+            router.handle_message(&message);
+            routed.handle_message(&message);
+        }
+        assert!((routed.report.bought() - 2.0).abs() < 1e-9);
+        assert_eq!(routed.report.avg_px(), Some(2000.0));
+        assert!((routed.children[0].report.bought() - 1.5).abs() < 1e-9);
+        assert!((router.accounts()[0].position("ETH") - 2.5).abs() < 1e-9);
+        assert!((router.accounts()[1].position("ETH") - 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::HashMap, future::Future, pin::pin, time::Duration};
+
+use alloy::primitives::Address;
+use tracing::{info, warn};
+
+use crate::{prelude::*, rt, ExchangeClient, ExchangeResponseStatus, InfoClient, SubAccount};
+
+/// A sub-account's perp balance as the allocator sees it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubAccountBalance {
+    pub sub_account: Address,
+    pub account_value: f64,
+    /// Part of the account value not backing positions, the most that can be moved out
+    pub withdrawable: f64,
+}
+
+impl SubAccountBalance {
+    pub fn from_sub_account(sub_account: &SubAccount) -> SubAccountBalance {
+        let state = &sub_account.clearinghouse_state;
+        SubAccountBalance {
+            sub_account: sub_account.sub_account_user,
+            account_value: state
+                .margin_summary
+                .account_value
+                .parse()
+                .unwrap_or_default(),
+            withdrawable: state.withdrawable.parse().unwrap_or_default(),
+        }
+    }
+}
+
+/// One `subAccountTransfer` of an `AllocationPlan`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AllocationTransfer {
+    pub sub_account: Address,
+    /// Into the sub-account when true, back to the master otherwise
+    pub is_deposit: bool,
+    /// In USDC
+    pub usd: f64,
+}
+
+impl AllocationTransfer {
+    /// The amount in micro USDC as the action takes it, rounded down.
+    pub fn micro_usd(&self) -> u64 {
+        (self.usd * 1e6).floor() as u64
+    }
+}
+
+/// Transfers taking sub-accounts to their targets, withdrawals first so they fund the
+/// deposits.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AllocationPlan {
+    pub transfers: Vec<AllocationTransfer>,
+    /// Targeted sub-accounts the master does not have
+    pub missing: Vec<Address>,
+    /// USDC still missing from sub-accounts once the master's funds ran out
+    pub shortfall: f64,
+}
+
+impl AllocationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.transfers.is_empty()
+    }
+}
+
+/// Keeps target USDC balances on sub-accounts of a master account, such as one per strategy,
+/// by moving the difference with `subAccountTransfer`.
+///
+/// Balances are account values. Sub-accounts above their target give back at most their
+/// withdrawable balance; those below are topped up, largest gap first, from the master's
+/// withdrawable balance above its reserve plus what was withdrawn. Differences under the
+/// minimum transfer are left alone.
+#[derive(Clone, Debug)]
+pub struct SubAccountAllocator {
+    master: Address,
+    targets: HashMap<Address, f64>,
+    min_transfer: f64,
+    master_reserve: f64,
+}
+
+impl SubAccountAllocator {
+    /// `master` is the account owning the sub-accounts, which must sign the transfers.
+    pub fn new(master: Address) -> SubAccountAllocator {
+        SubAccountAllocator {
+            master,
+            targets: HashMap::new(),
+            min_transfer: 1.0,
+            master_reserve: 0.0,
+        }
+    }
+
+    /// Keeps `usd` on `sub_account`.
+    pub fn with_target(mut self, sub_account: Address, usd: f64) -> Self {
+        self.targets.insert(sub_account, usd.max(0.0));
+        self
+    }
+
+    /// Smallest transfer made, in USDC, 1 by default.
+    pub fn with_min_transfer(mut self, usd: f64) -> Self {
+        self.min_transfer = usd;
+        self
+    }
+
+    /// USDC always left withdrawable on the master account.
+    pub fn with_master_reserve(mut self, usd: f64) -> Self {
+        self.master_reserve = usd;
+        self
+    }
+
+    pub fn master(&self) -> Address {
+        self.master
+    }
+
+    pub fn targets(&self) -> &HashMap<Address, f64> {
+        &self.targets
+    }
+
+    /// Plans from the master's withdrawable balance and the balances of its sub-accounts.
+    pub fn plan(&self, master_withdrawable: f64, balances: &[SubAccountBalance]) -> AllocationPlan {
+        let mut plan = AllocationPlan::default();
+        let mut withdrawals = Vec::new();
+        let mut deposits = Vec::new();
+        for (&sub_account, &target) in &self.targets {
+            let Some(balance) = balances.iter().find(|b| b.sub_account == sub_account) else {
+                plan.missing.push(sub_account);
+                continue;
+            };
+            let gap = target - balance.account_value;
+            if gap >= self.min_transfer {
+                deposits.push((sub_account, gap));
+            } else if -gap >= self.min_transfer {
+                let usd = (-gap).min(balance.withdrawable);
+                if usd >= self.min_transfer {
+                    withdrawals.push(AllocationTransfer {
+                        sub_account,
+                        is_deposit: false,
+                        usd,
+                    });
+                }
+            }
+        }
+        plan.missing.sort();
+        withdrawals.sort_by(|a, b| b.usd.total_cmp(&a.usd));
+        deposits.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut available = (master_withdrawable - self.master_reserve).max(0.0)
+            + withdrawals.iter().map(|t| t.usd).sum::<f64>();
+        plan.transfers = withdrawals;
+        for (sub_account, gap) in deposits {
+            let usd = gap.min(available);
+            if usd < self.min_transfer {
+                plan.shortfall += gap;
+                continue;
+            }
+            available -= usd;
+            plan.shortfall += gap - usd;
+            plan.transfers.push(AllocationTransfer {
+                sub_account,
+                is_deposit: true,
+                usd,
+            });
+        }
+        plan
+    }
+
+    /// Fetches the master's and sub-accounts' balances and plans from them.
+    pub async fn fetch_plan(&self, info: &InfoClient) -> Result<AllocationPlan> {
+        let master = info.user_state(self.master).await?;
+        let sub_accounts = info.sub_accounts(self.master).await?;
+        let balances: Vec<SubAccountBalance> = sub_accounts
+            .iter()
+            .map(SubAccountBalance::from_sub_account)
+            .collect();
+        Ok(self.plan(master.withdrawable.parse().unwrap_or_default(), &balances))
+    }
+
+    /// Sends the transfers of `plan` in order, stopping at the first one that fails. The
+    /// exchange client must sign for the master account.
+    pub async fn execute(
+        &self,
+        exchange: &ExchangeClient,
+        plan: &AllocationPlan,
+    ) -> Result<Vec<ExchangeResponseStatus>> {
+        let mut statuses = Vec::with_capacity(plan.transfers.len());
+        for transfer in &plan.transfers {
+            info!(
+                sub_account = %transfer.sub_account,
+                is_deposit = transfer.is_deposit,
+                usd = transfer.usd,
+                "Transferring to reach sub-account target"
+            );
+            let status = exchange
+                .sub_account_transfer(
+                    transfer.sub_account,
+                    transfer.is_deposit,
+                    transfer.micro_usd(),
+                    None,
+                )
+                .await?;
+            let failed = matches!(status, ExchangeResponseStatus::Err(_));
+            statuses.push(status);
+            if failed {
+                break;
+            }
+        }
+        Ok(statuses)
+    }
+
+    /// Plans and executes once, returning the plan.
+    pub async fn rebalance(
+        &self,
+        info: &InfoClient,
+        exchange: &ExchangeClient,
+    ) -> Result<AllocationPlan> {
+        let plan = self.fetch_plan(info).await?;
+        for sub_account in &plan.missing {
+            warn!(%sub_account, "Allocation target is not a sub-account of the master");
+        }
+        if let Some(ExchangeResponseStatus::Err(err)) = self.execute(exchange, &plan).await?.pop() {
+            warn!("Sub-account transfer rejected: {err}");
+        }
+        Ok(plan)
+    }
+
+    /// Rebalances every `interval` until `shutdown` resolves, logging failures.
+    pub async fn run(
+        &self,
+        info: &InfoClient,
+        exchange: &ExchangeClient,
+        interval: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            if let Err(err) = self.rebalance(info, exchange).await {
+                warn!(master = %self.master, "Could not rebalance sub-accounts: {err}");
+            }
+            tokio::select! {
+                _ = &mut shutdown => return,
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balance(byte: u8, account_value: f64, withdrawable: f64) -> SubAccountBalance {
+        SubAccountBalance {
+            sub_account: Address::repeat_byte(byte),
+            account_value,
+            withdrawable,
+        }
+    }
+
+    #[test]
+    fn test_plan_withdraws_before_deposits() {
+        let allocator = SubAccountAllocator::new(Address::ZERO)
+            .with_target(Address::repeat_byte(1), 1000.0)
+            .with_target(Address::repeat_byte(2), 500.0)
+            .with_target(Address::repeat_byte(3), 2000.0)
+            .with_target(Address::repeat_byte(4), 100.0)
+            .with_target(Address::repeat_byte(5), 50.0)
+            .with_min_transfer(5.0)
+            .with_master_reserve(100.0);
+        let balances = [
+            // 500 over target but only 300 withdrawable
+            balance(1, 1500.0, 300.0),
+            balance(2, 200.0, 200.0),
+            balance(3, 1000.0, 1000.0),
+            // Within the minimum transfer
+            balance(4, 97.0, 97.0),
+        ];
+        let plan = allocator.plan(250.0, &balances);
+        assert_eq!(plan.missing, vec![Address::repeat_byte(5)]);
+        let transfers: Vec<(u8, bool, f64)> = plan
+            .transfers
+            .iter()
+            .map(|t| (t.sub_account.0[0], t.is_deposit, t.usd))
+            .collect();
+        // 150 spare on the master plus 300 withdrawn, to the largest gap first
+        assert_eq!(transfers, vec![(1, false, 300.0), (3, true, 450.0)]);
+        assert_eq!(plan.shortfall, 550.0 + 300.0);
+        assert_eq!(plan.transfers[1].micro_usd(), 450_000_000);
+    }
+}
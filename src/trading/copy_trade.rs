@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Exchange, InfoClient, Message, OrderManager, PositionDrift,
+    PositionTracker, RoundingMode, Strategy, Subscription, Tif, TradeInfo, EPSILON,
+};
+
+fn default_slippage_bps() -> f64 {
+    50.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CopyTradeConfig {
+    /// Follower size held per unit of leader size
+    pub scale: f64,
+    /// Size decimals of each coin to copy; the leader's trades in other coins are ignored
+    pub sz_decimals: HashMap<String, u32>,
+    /// Largest follower position by coin, in coin size either side of flat
+    #[serde(default)]
+    pub max_position: HashMap<String, f64>,
+    /// Largest follower position notional in any coin, in USDC
+    #[serde(default)]
+    pub max_notional: Option<f64>,
+    /// How far past the leader's fill price copying `Ioc` orders may fill
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: f64,
+}
+
+/// A copying order and how much of its fill the follower's tracker has not seen yet.
+#[derive(Clone, Debug)]
+struct CopyOrder {
+    cloid: Uuid,
+    oid: Option<u64>,
+    coin: String,
+    is_buy: bool,
+    filled_sz: f64,
+    streamed_sz: f64,
+    done: bool,
+}
+
+impl CopyOrder {
+    fn unconfirmed(&self) -> f64 {
+        let sz = (self.filled_sz - self.streamed_sz).max(0.0);
+        if self.is_buy {
+            sz
+        } else {
+            -sz
+        }
+    }
+}
+
+/// Mirrors a leader account's positions on a follower account, scaled by `scale` and capped
+/// per coin, trading each time the leader's fills move its position.
+///
+/// Copies are `Ioc` orders priced `slippage_bps` through the leader's fill price, so the
+/// follower never pays much more than the leader did and a gap left by a price that ran away
+/// is retried on the leader's next fill. Size bringing the follower back towards flat is sent
+/// reduce-only.
+///
+/// Pass every message of `subscriptions` to `on_message`; fills are told apart by the user of
+/// their `userFills` message. Seed both positions with `reconcile` at startup.
+#[derive(Debug)]
+pub struct CopyTrader {
+    config: CopyTradeConfig,
+    leader: PositionTracker,
+    follower: PositionTracker,
+    manager: OrderManager,
+    leader_address: Address,
+    follower_address: Address,
+    copies: Vec<CopyOrder>,
+}
+
+impl CopyTrader {
+    pub fn new(leader: Address, follower: Address, config: CopyTradeConfig) -> CopyTrader {
+        CopyTrader {
+            config,
+            leader: PositionTracker::new(leader),
+            follower: PositionTracker::new(follower),
+            manager: OrderManager::new(follower),
+            leader_address: leader,
+            follower_address: follower,
+            copies: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &CopyTradeConfig {
+        &self.config
+    }
+
+    pub fn leader(&self) -> &PositionTracker {
+        &self.leader
+    }
+
+    pub fn follower(&self) -> &PositionTracker {
+        &self.follower
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        vec![
+            Subscription::UserFills {
+                user: self.leader_address,
+            },
+            Subscription::UserFills {
+                user: self.follower_address,
+            },
+            Subscription::OrderUpdates {
+                user: self.follower_address,
+            },
+        ]
+    }
+
+    /// Seeds or corrects both accounts' positions from their clearinghouse states.
+    pub async fn reconcile(&mut self, info: &InfoClient) -> Result<Vec<PositionDrift>> {
+        let mut drifts = self.leader.reconcile(info).await?;
+        drifts.extend(self.follower.reconcile(info).await?);
+        self.copies.retain(|copy| !copy.done);
+        Ok(drifts)
+    }
+
+    /// Follower position `coin` should be at, the scaled leader position within the caps at
+    /// price `px`.
+    pub fn target(&self, coin: &str, px: f64) -> f64 {
+        let leader = self
+            .leader
+            .position(coin)
+            .map(|position| position.szi)
+            .unwrap_or_default();
+        let mut cap = self
+            .config
+            .max_position
+            .get(coin)
+            .copied()
+            .unwrap_or(f64::INFINITY);
+        if let Some(max_notional) = self.config.max_notional.filter(|_| px > 0.0) {
+            cap = cap.min(max_notional / px);
+        }
+        (leader * self.config.scale).clamp(-cap, cap)
+    }
+
+    /// Follower position in `coin`, including copies filled but not yet streamed.
+    pub fn follower_szi(&self, coin: &str) -> f64 {
+        let tracked = self
+            .follower
+            .position(coin)
+            .map(|position| position.szi)
+            .unwrap_or_default();
+        let unconfirmed: f64 = self
+            .copies
+            .iter()
+            .filter(|copy| copy.coin == coin)
+            .map(CopyOrder::unconfirmed)
+            .sum();
+        tracked + unconfirmed
+    }
+
+    /// Orders moving the follower's `coin` position to its target at `px`, empty when it is
+    /// within a lot of it or the coin is not copied.
+    pub fn plan(&self, coin: &str, px: f64) -> Vec<ClientOrderRequest> {
+        let Some(&sz_decimals) = self.config.sz_decimals.get(coin) else {
+            return Vec::new();
+        };
+        if px <= 0.0 {
+            return Vec::new();
+        }
+        let current = self.follower_szi(coin);
+        let trade = self.target(coin, px) - current;
+        let is_buy = trade > 0.0;
+        let reducing = if current * trade < 0.0 {
+            trade.abs().min(current.abs())
+        } else {
+            0.0
+        };
+
+        let lot = lot_size(sz_decimals);
+        let bps = if is_buy { 1.0 } else { -1.0 } * self.config.slippage_bps;
+        let limit_px = apply_bps(px, bps);
+        let tick = price_tick_size(limit_px, sz_decimals, false);
+        let mode = if is_buy {
+            RoundingMode::Down
+        } else {
+            RoundingMode::Up
+        };
+        let limit_px = round_to_tick(limit_px, tick, mode);
+        [(reducing, true), (trade.abs() - reducing, false)]
+            .into_iter()
+            .filter_map(|(sz, reduce_only)| {
+                let sz = round_to_tick(sz, lot, RoundingMode::Down);
+                (sz >= lot - EPSILON).then(|| ClientOrderRequest {
+                    asset: coin.to_string(),
+                    is_buy,
+                    reduce_only,
+                    limit_px,
+                    sz,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+                })
+            })
+            .collect()
+    }
+
+    /// Sends the orders from `plan`, unless a copy of `coin` is still in flight.
+    pub async fn copy<E: Exchange>(&mut self, exchange: &E, coin: &str, px: f64) -> Result<()> {
+        self.sync_copies();
+        if self
+            .copies
+            .iter()
+            .any(|copy| copy.coin == coin && !copy.done)
+        {
+            return Ok(());
+        }
+        let orders = self.plan(coin, px);
+        if orders.is_empty() {
+            return Ok(());
+        }
+        info!(
+            coin,
+            target = self.target(coin, px),
+            follower = self.follower_szi(coin),
+            "Copying leader position"
+        );
+        let sides: Vec<bool> = orders.iter().map(|order| order.is_buy).collect();
+        let statuses = self.manager.place(exchange, orders).await?;
+        self.copies.extend(
+            statuses
+                .iter()
+                .zip(sides)
+                .map(|(status, is_buy)| CopyOrder {
+                    cloid: status.request,
+                    oid: None,
+                    coin: coin.to_string(),
+                    is_buy,
+                    filled_sz: 0.0,
+                    streamed_sz: 0.0,
+                    done: false,
+                }),
+        );
+        self.sync_copies();
+        Ok(())
+    }
+
+    /// Copies order state out of the manager, dropping copies that are done and fully
+    /// streamed.
+    fn sync_copies(&mut self) {
+        for copy in self.copies.iter_mut() {
+            if let Some(order) = self.manager.order(copy.cloid) {
+                copy.oid = order.oid;
+                copy.filled_sz = order.filled_sz;
+                copy.done = order.state.is_done();
+            }
+        }
+        self.manager.remove_done();
+        self.copies
+            .retain(|copy| !copy.done || copy.unconfirmed().abs() > EPSILON);
+    }
+
+    /// Counts copy fills the follower's tracker is about to see for the first time.
+    fn apply_streamed_fill(&mut self, fill: &TradeInfo) {
+        if self.follower.has_fill(fill.tid) {
+            return;
+        }
+        if let Some(copy) = self
+            .copies
+            .iter_mut()
+            .find(|copy| copy.oid == Some(fill.oid))
+        {
+            copy.streamed_sz += fill.sz.parse::<f64>().unwrap_or_default();
+        }
+    }
+}
+
+impl Strategy for CopyTrader {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        match message {
+            Message::UserFills(fills) if fills.data.user == self.leader_address => {
+                // The snapshot predates the trader, whose positions come from `reconcile`
+                if fills.data.is_snapshot.unwrap_or(false) {
+                    return Ok(());
+                }
+                self.leader.handle_message(message);
+                let mut last_px: Vec<(String, f64)> = Vec::new();
+                for fill in &fills.data.fills {
+                    let Ok(px) = fill.px.parse::<f64>() else {
+                        continue;
+                    };
+                    match last_px.iter_mut().find(|(coin, _)| *coin == fill.coin) {
+                        Some(last) => last.1 = px,
+                        None => last_px.push((fill.coin.clone(), px)),
+                    }
+                }
+                for (coin, px) in last_px {
+                    self.copy(exchange, &coin, px).await?;
+                }
+            }
+            Message::UserFills(fills) if fills.data.user == self.follower_address => {
+                if !fills.data.is_snapshot.unwrap_or(false) {
+                    fills
+                        .data
+                        .fills
+                        .iter()
+                        .for_each(|fill| self.apply_streamed_fill(fill));
+                }
+                self.follower.handle_message(message);
+                self.manager.handle_message(message);
+                self.sync_copies();
+            }
+            Message::OrderUpdates(_) => {
+                self.manager.handle_message(message);
+                self.sync_copies();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    fn leader_fill(user: Address, tid: u64, side: &str, sz: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"userFills","data":{{"user":"{user}","fills":[{{"coin":"ETH","px":"2000","sz":"{sz}","side":"{side}","time":1,"startPosition":"0","dir":"Open Long","closedPnl":"0","hash":"0x0","oid":1,"crossed":true,"fee":"0","feeToken":"USDC","tid":{tid}}}]}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mirrors_leader_within_caps() {
+        let follower = Address::repeat_byte(2);
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            address: follower,
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&book);
+
+        let config: CopyTradeConfig = serde_json::from_str(
+            r#"{"scale":0.5,"sz_decimals":{"ETH":2},"max_position":{"ETH":1.5}}"#,
+        )
+        .unwrap();
+        let leader = Address::repeat_byte(1);
+        let mut trader = CopyTrader::new(leader, follower, config);
+
+        trader
+            .on_message(&leader_fill(leader, 1, "B", "2"), &exchange)
+            .await
+            .unwrap();
+        assert!((exchange.position("ETH").unwrap().szi - 1.0).abs() < EPSILON);
+        assert!((trader.follower_szi("ETH") - 1.0).abs() < EPSILON);
+
+        // Streamed fills replace the unconfirmed fill rather than adding to it
+        while let Ok(message) = receiver.try_recv() {
+            trader.on_message(&message, &exchange).await.unwrap();
+        }
+        assert!((trader.follower_szi("ETH") - 1.0).abs() < EPSILON);
+
+        // The leader doubles up but the follower stops at its cap
+        trader
+            .on_message(&leader_fill(leader, 2, "B", "2"), &exchange)
+            .await
+            .unwrap();
+        assert!((exchange.position("ETH").unwrap().szi - 1.5).abs() < EPSILON);
+
+        // Selling back below the cap is reduce-only
+        let Message::UserFills(fills) = leader_fill(leader, 3, "A", "2.4") else {
+            unreachable!()
+        };
+        trader.leader.apply_fill(&fills.data.fills[0]);
+        let orders = trader.plan("ETH", 2000.0);
+        assert_eq!(orders.len(), 1);
+        assert!(!orders[0].is_buy && orders[0].reduce_only);
+        assert!((orders[0].sz - 0.7).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Exchange, InfoClient, Message, OrderManager, PositionDrift,
+    PositionTracker, RoundingMode, Strategy, Subscription, Tif, TradeInfo, UserData, EPSILON,
+};
+
+fn default_slippage_bps() -> f64 {
+    50.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeltaHedgeConfig {
+    /// Perp the hedge position is held in
+    pub hedge_coin: String,
+    pub sz_decimals: u32,
+    /// Hedge-coin size carried by one unit of each coin's position, such as a beta. Positions
+    /// in coins missing here are ignored; the hedge coin itself always counts one for one.
+    #[serde(default)]
+    pub hedge_ratios: HashMap<String, f64>,
+    /// Net delta, in hedge-coin size, left unhedged either side of flat
+    pub tolerance: f64,
+    /// How far through the mid hedging `Ioc` orders are priced
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: f64,
+}
+
+/// A hedge order and how much of its fill the tracker has not seen yet.
+#[derive(Clone, Debug)]
+struct HedgeOrder {
+    cloid: Uuid,
+    oid: Option<u64>,
+    is_buy: bool,
+    filled_sz: f64,
+    /// Size of the order's fills streamed to the tracker
+    streamed_sz: f64,
+    done: bool,
+}
+
+impl HedgeOrder {
+    /// Signed size filled but not yet streamed.
+    fn unconfirmed(&self) -> f64 {
+        let sz = (self.filled_sz - self.streamed_sz).max(0.0);
+        if self.is_buy {
+            sz
+        } else {
+            -sz
+        }
+    }
+}
+
+/// Keeps the net delta of an account, plus any external positions, within a tolerance band by
+/// trading a perp hedge with `Ioc` orders. Size that closes the existing hedge position is sent
+/// reduce-only and only the remainder opens, so a hedge never flips by accident when the
+/// position moved underneath it.
+///
+/// Positions come from a `PositionTracker` fed by `on_message`; seed it with `reconcile`.
+/// Hedge fills count towards delta as soon as the order response reports them, until the
+/// streamed fills reach the tracker.
+#[derive(Debug)]
+pub struct DeltaHedger {
+    config: DeltaHedgeConfig,
+    tracker: PositionTracker,
+    manager: OrderManager,
+    external: BTreeMap<String, f64>,
+    mid: Option<f64>,
+    hedges: Vec<HedgeOrder>,
+}
+
+impl DeltaHedger {
+    /// `user` is the account holding the positions and the hedge.
+    pub fn new(user: Address, config: DeltaHedgeConfig) -> DeltaHedger {
+        DeltaHedger {
+            config,
+            tracker: PositionTracker::new(user),
+            manager: OrderManager::new(user),
+            external: BTreeMap::new(),
+            mid: None,
+            hedges: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &DeltaHedgeConfig {
+        &self.config
+    }
+
+    pub fn tracker(&self) -> &PositionTracker {
+        &self.tracker
+    }
+
+    /// Sets the signed size of a position held outside the account, such as on another venue,
+    /// counted with the hedge ratio of `coin`. Zero removes it.
+    pub fn set_external_position(&mut self, coin: &str, szi: f64) {
+        if szi == 0.0 {
+            self.external.remove(coin);
+        } else {
+            self.external.insert(coin.to_string(), szi);
+        }
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = self.tracker.subscriptions();
+        subscriptions.push(Subscription::OrderUpdates {
+            user: self.tracker.user(),
+        });
+        subscriptions
+    }
+
+    /// Seeds or corrects tracked positions from the clearinghouse state, which already
+    /// includes every finished hedge.
+    pub async fn reconcile(&mut self, info: &InfoClient) -> Result<Vec<PositionDrift>> {
+        let drifts = self.tracker.reconcile(info).await?;
+        self.hedges.retain(|hedge| !hedge.done);
+        Ok(drifts)
+    }
+
+    /// Signed size of the hedge position, including fills not yet streamed.
+    pub fn hedge_szi(&self) -> f64 {
+        let tracked = self
+            .tracker
+            .position(&self.config.hedge_coin)
+            .map(|position| position.szi)
+            .unwrap_or_default();
+        tracked + self.unconfirmed()
+    }
+
+    /// Net delta in hedge-coin size across tracked, external and unconfirmed hedge positions.
+    pub fn net_delta(&self) -> f64 {
+        let tracked = self
+            .tracker
+            .positions()
+            .map(|position| (position.coin.as_str(), position.szi));
+        let external = self
+            .external
+            .iter()
+            .map(|(coin, szi)| (coin.as_str(), *szi));
+        tracked
+            .chain(external)
+            .map(|(coin, szi)| szi * self.hedge_ratio(coin))
+            .sum::<f64>()
+            + self.unconfirmed()
+    }
+
+    /// Orders bringing the net delta back to flat at `mid`, empty while it is within the
+    /// tolerance band.
+    pub fn plan(&self, mid: f64) -> Vec<ClientOrderRequest> {
+        let config = &self.config;
+        let delta = self.net_delta();
+        if delta.abs() <= config.tolerance || mid <= 0.0 {
+            return Vec::new();
+        }
+        let trade = -delta;
+        let is_buy = trade > 0.0;
+        let hedge_szi = self.hedge_szi();
+        let reducing = if hedge_szi * trade < 0.0 {
+            trade.abs().min(hedge_szi.abs())
+        } else {
+            0.0
+        };
+
+        let lot = lot_size(config.sz_decimals);
+        let bps = if is_buy { 1.0 } else { -1.0 } * config.slippage_bps;
+        let px = apply_bps(mid, bps);
+        let tick = price_tick_size(px, config.sz_decimals, false);
+        let mode = if is_buy {
+            RoundingMode::Up
+        } else {
+            RoundingMode::Down
+        };
+        let limit_px = round_to_tick(px, tick, mode);
+        [(reducing, true), (trade.abs() - reducing, false)]
+            .into_iter()
+            .filter_map(|(sz, reduce_only)| {
+                let sz = round_to_tick(sz, lot, RoundingMode::Down);
+                (sz >= lot - EPSILON).then(|| ClientOrderRequest {
+                    asset: config.hedge_coin.clone(),
+                    is_buy,
+                    reduce_only,
+                    limit_px,
+                    sz,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+                })
+            })
+            .collect()
+    }
+
+    /// Sends the orders from `plan` at the latest hedge-coin mid, unless a hedge is still in
+    /// flight or no mid has been seen.
+    pub async fn rehedge<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        self.sync_hedges();
+        if self.hedges.iter().any(|hedge| !hedge.done) {
+            return Ok(());
+        }
+        let Some(mid) = self.mid else {
+            return Ok(());
+        };
+        let orders = self.plan(mid);
+        if orders.is_empty() {
+            return Ok(());
+        }
+        info!(
+            "Hedging net delta {} with {} {}",
+            self.net_delta(),
+            if orders[0].is_buy { "buy" } else { "sell" },
+            self.config.hedge_coin
+        );
+        let sides: Vec<bool> = orders.iter().map(|order| order.is_buy).collect();
+        let statuses = self.manager.place(exchange, orders).await?;
+        self.hedges.extend(
+            statuses
+                .iter()
+                .zip(sides)
+                .map(|(status, is_buy)| HedgeOrder {
+                    cloid: status.request,
+                    oid: None,
+                    is_buy,
+                    filled_sz: 0.0,
+                    streamed_sz: 0.0,
+                    done: false,
+                }),
+        );
+        self.sync_hedges();
+        Ok(())
+    }
+
+    fn hedge_ratio(&self, coin: &str) -> f64 {
+        if coin == self.config.hedge_coin {
+            return 1.0;
+        }
+        self.config
+            .hedge_ratios
+            .get(coin)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn unconfirmed(&self) -> f64 {
+        self.hedges.iter().map(HedgeOrder::unconfirmed).sum()
+    }
+
+    /// Copies hedge order state out of the manager, dropping hedges that are done and fully
+    /// streamed.
+    fn sync_hedges(&mut self) {
+        for hedge in self.hedges.iter_mut() {
+            if let Some(order) = self.manager.order(hedge.cloid) {
+                hedge.oid = order.oid;
+                hedge.filled_sz = order.filled_sz;
+                hedge.done = order.state.is_done();
+            }
+        }
+        self.manager.remove_done();
+        self.hedges
+            .retain(|hedge| !hedge.done || hedge.unconfirmed().abs() > EPSILON);
+    }
+
+    /// Counts hedge fills the tracker is about to see for the first time.
+    fn apply_streamed_fill(&mut self, fill: &TradeInfo) {
+        if fill.coin != self.config.hedge_coin || self.tracker.has_fill(fill.tid) {
+            return;
+        }
+        let Some(hedge) = self
+            .hedges
+            .iter_mut()
+            .find(|hedge| hedge.oid == Some(fill.oid))
+        else {
+            return;
+        };
+        hedge.streamed_sz += fill.sz.parse::<f64>().unwrap_or_default();
+    }
+}
+
+impl Strategy for DeltaHedger {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        match message {
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                fills
+                    .data
+                    .fills
+                    .iter()
+                    .for_each(|fill| self.apply_streamed_fill(fill));
+            }
+            Message::User(user) => {
+                if let UserData::Fills(fills) = &user.data {
+                    fills.iter().for_each(|fill| self.apply_streamed_fill(fill));
+                }
+            }
+            Message::AllMids(all_mids) => {
+                if let Some(mid) = all_mids
+                    .data
+                    .mids
+                    .get(&self.config.hedge_coin)
+                    .and_then(|mid| mid.parse().ok())
+                {
+                    self.mid = Some(mid);
+                }
+            }
+            _ => {}
+        }
+        self.tracker.handle_message(message);
+        self.manager.handle_message(message);
+        self.rehedge(exchange).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    fn mid(px: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"allMids","data":{{"mids":{{"ETH":"{px}"}}}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hedges_external_delta_within_band() {
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&book);
+
+        let config: DeltaHedgeConfig = serde_json::from_str(
+            r#"{"hedge_coin":"ETH","sz_decimals":2,"hedge_ratios":{"UETH":1},"tolerance":0.1}"#,
+        )
+        .unwrap();
+        let mut hedger = DeltaHedger::new(Address::ZERO, config);
+        hedger.set_external_position("UETH", 1.5);
+        hedger.set_external_position("BTC", 3.0);
+        hedger.on_message(&mid("2000"), &exchange).await.unwrap();
+        assert!((exchange.position("ETH").unwrap().szi + 1.5).abs() < EPSILON);
+        assert!(hedger.net_delta().abs() < EPSILON);
+
+        // Streamed fills replace the unconfirmed fill rather than adding to it
+        while let Ok(message) = receiver.try_recv() {
+            hedger.on_message(&message, &exchange).await.unwrap();
+        }
+        assert!((hedger.hedge_szi() + 1.5).abs() < EPSILON);
+        assert!(hedger.net_delta().abs() < EPSILON);
+
+        hedger.set_external_position("UETH", 1.45);
+        assert!(hedger.plan(2000.0).is_empty());
+
+        hedger.set_external_position("UETH", -0.5);
+        let orders = hedger.plan(2000.0);
+        assert_eq!(orders.len(), 2);
+        assert!(orders.iter().all(|order| order.is_buy));
+        assert!(orders[0].reduce_only);
+        assert_eq!(orders[0].sz, 1.5);
+        assert!(!orders[1].reduce_only);
+        assert_eq!(orders[1].sz, 0.5);
+
+        hedger.on_message(&mid("2000"), &exchange).await.unwrap();
+        assert!((exchange.position("ETH").unwrap().szi - 0.5).abs() < EPSILON);
+        assert!(hedger.net_delta().abs() < EPSILON);
+    }
+}
@@ -0,0 +1,386 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    apply_bps, helpers::float_to_string_for_hashing, lot_size, prelude::*, price_tick_size,
+    round_to_tick, ClientLimit, ClientOrder, ClientOrderRequest, Error, Exchange, ExchangeClient,
+    ExchangeDataStatus, ExchangeResponseStatus, InfoClient, RoundingMode, SpotMeta, Tif, EPSILON,
+};
+
+fn default_target() -> String {
+    "USDC".to_string()
+}
+
+fn default_min_notional() -> f64 {
+    10.0
+}
+
+fn default_slippage_bps() -> f64 {
+    100.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DustSweepConfig {
+    /// Token dust is converted into, traded against on each `TOKEN/target` pair
+    #[serde(default = "default_target")]
+    pub target: String,
+    /// Balances worth less than this, in the target token, are dust
+    #[serde(default = "default_min_notional")]
+    pub threshold: f64,
+    /// Smallest order notional the exchange accepts, 10 USDC
+    #[serde(default = "default_min_notional")]
+    pub min_order_notional: f64,
+    /// How far through the mid the `Ioc` orders are priced
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: f64,
+    /// Tokens never swept
+    #[serde(default)]
+    pub exclude: HashSet<String>,
+}
+
+/// How one dust balance is converted into the target token.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DustConversion {
+    /// Sell the balance
+    Sell { sz: f64 },
+    /// Buy `buy_sz` to lift the balance over the order minimum, then sell `sell_sz`, the
+    /// whole balance rounded down to the lot size
+    TopUpAndSell { buy_sz: f64, sell_sz: f64 },
+    /// No `TOKEN/target` pair or mid, or less than one lot; can only be transferred
+    Unsellable,
+}
+
+/// A spot balance worth less than the dust threshold.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DustBalance {
+    pub token: String,
+    /// Token as named in `spotSend`, `NAME:tokenId`
+    pub token_wire: String,
+    /// Pair traded against the target, e.g. `PURR/USDC` or `@107`
+    pub coin: Option<String>,
+    pub sz_decimals: u32,
+    pub wei_decimals: u32,
+    /// Balance not held by open orders
+    pub balance: f64,
+    /// Mid of the pair, zero without one
+    pub px: f64,
+    pub conversion: DustConversion,
+}
+
+impl DustBalance {
+    /// Value in the target token at the mid.
+    pub fn value(&self) -> f64 {
+        self.balance * self.px
+    }
+}
+
+/// Spot balances below the dust threshold, with the orders converting each into the target
+/// token or the amounts to transfer them away. `Display` prints it as a dry run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DustSweep {
+    pub target: String,
+    pub slippage_bps: f64,
+    pub balances: Vec<DustBalance>,
+}
+
+impl DustSweep {
+    /// Finds dust among `balances`, available amounts by token name, valued with `mids` by pair
+    /// name. Balances too small for an order are topped up to the order minimum first, and
+    /// tokens without a pair to the target are marked unsellable.
+    pub fn compute(
+        config: &DustSweepConfig,
+        spot_meta: &SpotMeta,
+        balances: &HashMap<String, f64>,
+        mids: &HashMap<String, f64>,
+    ) -> Result<DustSweep> {
+        let target_index = spot_meta
+            .tokens
+            .iter()
+            .find(|token| token.name == config.target)
+            .map(|token| token.index)
+            .ok_or_else(|| Error::InvalidConfig(format!("Unknown token {}", config.target)))?;
+
+        let mut dust = Vec::new();
+        for token in &spot_meta.tokens {
+            let balance = balances.get(&token.name).copied().unwrap_or_default();
+            if token.index == target_index
+                || balance <= EPSILON
+                || config.exclude.contains(&token.name)
+            {
+                continue;
+            }
+            let pair = spot_meta
+                .universe
+                .iter()
+                .find(|pair| pair.tokens == [token.index, target_index]);
+            let px = pair
+                .and_then(|pair| mids.get(&pair.name))
+                .copied()
+                .unwrap_or_default();
+            // Tokens that cannot be valued are dust only if there is no pair at all
+            if (px > 0.0 && balance * px >= config.threshold) || (pair.is_some() && px <= 0.0) {
+                continue;
+            }
+            let sz_decimals = token.sz_decimals as u32;
+            let lot = lot_size(sz_decimals);
+            let conversion = if pair.is_none() || balance < lot - EPSILON {
+                DustConversion::Unsellable
+            } else if balance * px >= config.min_order_notional {
+                DustConversion::Sell {
+                    sz: round_to_tick(balance, lot, RoundingMode::Down),
+                }
+            } else {
+                // Both orders have to clear the minimum, so buy the whole minimum
+                let buy_sz = round_to_tick(
+                    config.min_order_notional * (1.0 + config.slippage_bps / 10_000.0) / px,
+                    lot,
+                    RoundingMode::Up,
+                );
+                DustConversion::TopUpAndSell {
+                    buy_sz,
+                    sell_sz: round_to_tick(balance + buy_sz, lot, RoundingMode::Down),
+                }
+            };
+            dust.push(DustBalance {
+                token: token.name.clone(),
+                token_wire: format!("{}:{}", token.name, token.token_id),
+                coin: pair.map(|pair| pair.name.clone()),
+                sz_decimals,
+                wei_decimals: token.wei_decimals as u32,
+                balance,
+                px,
+                conversion,
+            });
+        }
+        Ok(DustSweep {
+            target: config.target.clone(),
+            slippage_bps: config.slippage_bps,
+            balances: dust,
+        })
+    }
+
+    /// Fetches the balances of `user` and the spot mids, and finds dust with them.
+    pub async fn fetch(
+        config: &DustSweepConfig,
+        info: &InfoClient,
+        user: Address,
+    ) -> Result<DustSweep> {
+        let spot_meta = info.spot_meta().await?;
+        let balances = info
+            .user_token_balances(user)
+            .await?
+            .balances
+            .into_iter()
+            .filter_map(|balance| {
+                let total: f64 = balance.total.parse().ok()?;
+                let hold: f64 = balance.hold.parse().unwrap_or_default();
+                Some((balance.coin, (total - hold).max(0.0)))
+            })
+            .collect();
+        let mids = info
+            .all_mids()
+            .await?
+            .into_iter()
+            .filter_map(|(coin, mid)| Some((coin, mid.parse().ok()?)))
+            .collect();
+        DustSweep::compute(config, &spot_meta, &balances, &mids)
+    }
+
+    /// Sells every sellable dust balance into the target token, topping up first where
+    /// needed, returning the tokens swept. A token whose orders fail is logged and skipped.
+    pub async fn convert<E: Exchange>(&self, exchange: &E) -> Result<Vec<String>> {
+        let mut swept = Vec::new();
+        for dust in &self.balances {
+            let Some(coin) = &dust.coin else {
+                continue;
+            };
+            let sell_sz = match dust.conversion {
+                DustConversion::Sell { sz } => sz,
+                DustConversion::TopUpAndSell { buy_sz, .. } => {
+                    let Some(bought) = self.send(exchange, dust, coin, true, buy_sz).await? else {
+                        continue;
+                    };
+                    let lot = lot_size(dust.sz_decimals);
+                    round_to_tick(dust.balance + bought, lot, RoundingMode::Down)
+                }
+                DustConversion::Unsellable => continue,
+            };
+            if self
+                .send(exchange, dust, coin, false, sell_sz)
+                .await?
+                .is_some()
+            {
+                info!("Converted {} {} into {}", sell_sz, dust.token, self.target);
+                swept.push(dust.token.clone());
+            }
+        }
+        Ok(swept)
+    }
+
+    /// Sends every dust balance, sellable or not, to `destination` with `spotSend`, returning
+    /// the tokens sent. A token whose transfer fails is logged and skipped.
+    pub async fn transfer(
+        &self,
+        exchange: &ExchangeClient,
+        destination: Address,
+    ) -> Result<Vec<String>> {
+        let destination = destination.to_string();
+        let mut sent = Vec::new();
+        for dust in &self.balances {
+            let wei = 10f64.powi(-(dust.wei_decimals.min(8) as i32));
+            let amount = round_to_tick(dust.balance, wei, RoundingMode::Down);
+            if amount <= 0.0 {
+                continue;
+            }
+            let amount = float_to_string_for_hashing(amount);
+            match exchange
+                .spot_transfer(&amount, &destination, &dust.token_wire, None)
+                .await?
+            {
+                ExchangeResponseStatus::Ok(_) => {
+                    info!("Sent {amount} {} to {destination}", dust.token);
+                    sent.push(dust.token.clone());
+                }
+                ExchangeResponseStatus::Err(err) => {
+                    warn!("Could not send {} dust: {err}", dust.token)
+                }
+            }
+        }
+        Ok(sent)
+    }
+
+    /// Sends one `Ioc` order, returning the size filled or `None` if it failed.
+    async fn send<E: Exchange>(
+        &self,
+        exchange: &E,
+        dust: &DustBalance,
+        coin: &str,
+        is_buy: bool,
+        sz: f64,
+    ) -> Result<Option<f64>> {
+        let bps = if is_buy { 1.0 } else { -1.0 } * self.slippage_bps;
+        let px = apply_bps(dust.px, bps);
+        let tick = price_tick_size(px, dust.sz_decimals, true);
+        let mode = if is_buy {
+            RoundingMode::Up
+        } else {
+            RoundingMode::Down
+        };
+        let order = ClientOrderRequest {
+            asset: coin.to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px: round_to_tick(px, tick, mode),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        };
+        let status = match exchange.order(order).await? {
+            ExchangeResponseStatus::Ok(response) => response
+                .data
+                .and_then(|data| data.statuses.into_iter().next()),
+            ExchangeResponseStatus::Err(err) => {
+                warn!("Could not trade {} dust: {err}", dust.token);
+                return Ok(None);
+            }
+        };
+        match status {
+            Some(ExchangeDataStatus::Filled(filled)) => Ok(filled.total_sz.parse().ok()),
+            other => {
+                warn!("{} dust order not filled: {other:?}", dust.token);
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DustSweep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total: f64 = self.balances.iter().map(DustBalance::value).sum();
+        writeln!(
+            f,
+            "{} dust balances worth {total:.2} {}",
+            self.balances.len(),
+            self.target
+        )?;
+        for dust in &self.balances {
+            let conversion = match dust.conversion {
+                DustConversion::Sell { sz } => format!("sell {sz}"),
+                DustConversion::TopUpAndSell { buy_sz, sell_sz } => {
+                    format!("buy {buy_sz} then sell {sell_sz}")
+                }
+                DustConversion::Unsellable => "transfer only".to_string(),
+            };
+            writeln!(
+                f,
+                "  {:<10} {:>16} worth {:>10.4} {}: {conversion}",
+                dust.token,
+                dust.balance,
+                dust.value(),
+                self.target
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_dust_and_tops_up_small_balances() {
+        let spot_meta: SpotMeta = serde_json::from_str(
+            r#"{"universe":[
+                {"tokens":[1,0],"name":"PURR/USDC","index":0,"isCanonical":true},
+                {"tokens":[2,0],"name":"@1","index":1,"isCanonical":false}
+            ],"tokens":[
+                {"name":"USDC","szDecimals":8,"weiDecimals":8,"index":0,"tokenId":"0x6d1e7cde53ba9467b783cb7c530ce054","isCanonical":true},
+                {"name":"PURR","szDecimals":0,"weiDecimals":5,"index":1,"tokenId":"0xc1fb593aeffbeb02f85e0308e9956a90","isCanonical":true},
+                {"name":"HFUN","szDecimals":2,"weiDecimals":8,"index":2,"tokenId":"0xbaf265ef389da684513d98d68edf4eae","isCanonical":false},
+                {"name":"ORPHAN","szDecimals":2,"weiDecimals":8,"index":3,"tokenId":"0x00000000000000000000000000000003","isCanonical":false}
+            ]}"#,
+        )
+        .unwrap();
+        let balances = HashMap::from([
+            ("USDC".to_string(), 3.0),
+            ("PURR".to_string(), 40.0),
+            ("HFUN".to_string(), 0.5),
+            ("ORPHAN".to_string(), 7.0),
+        ]);
+        let mids = HashMap::from([("PURR/USDC".to_string(), 0.2), ("@1".to_string(), 10.0)]);
+        let config: DustSweepConfig = serde_json::from_str(r#"{"threshold":20}"#).unwrap();
+        let sweep = DustSweep::compute(&config, &spot_meta, &balances, &mids).unwrap();
+
+        let tokens: Vec<&str> = sweep.balances.iter().map(|d| d.token.as_str()).collect();
+        assert_eq!(tokens, ["PURR", "HFUN", "ORPHAN"]);
+        // 8 USDC of PURR needs a top-up of 10.1 USDC at 1% slippage, rounded up to whole PURR
+        assert_eq!(
+            sweep.balances[0].conversion,
+            DustConversion::TopUpAndSell {
+                buy_sz: 51.0,
+                sell_sz: 91.0
+            }
+        );
+        assert_eq!(
+            sweep.balances[1].conversion,
+            DustConversion::TopUpAndSell {
+                buy_sz: 1.01,
+                sell_sz: 1.51
+            }
+        );
+        assert_eq!(sweep.balances[2].conversion, DustConversion::Unsellable);
+        assert_eq!(
+            sweep.balances[2].token_wire,
+            "ORPHAN:0x00000000000000000000000000000003"
+        );
+        assert!(sweep.to_string().contains("transfer only"));
+    }
+}
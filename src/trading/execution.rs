@@ -0,0 +1,422 @@
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Error, Exchange, Message, OrderManager, RoundingMode, Strategy,
+    Subscription, Tif, EPSILON,
+};
+
+/// How the parent size is spread over the slices of the execution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionSchedule {
+    /// Equal size in each of `slices` slices
+    Fixed { slices: usize },
+    /// One slice per weight, sized in proportion to it, e.g. from a historical volume profile
+    VolumeWeighted { weights: Vec<f64> },
+}
+
+impl ExecutionSchedule {
+    fn slices(&self) -> usize {
+        match self {
+            ExecutionSchedule::Fixed { slices } => *slices,
+            ExecutionSchedule::VolumeWeighted { weights } => weights.len(),
+        }
+    }
+
+    /// Fraction of the parent size due by the end of slice `slice`.
+    fn due(&self, slice: usize) -> f64 {
+        match self {
+            ExecutionSchedule::Fixed { slices } => (slice + 1) as f64 / *slices as f64,
+            ExecutionSchedule::VolumeWeighted { weights } => {
+                let total: f64 = weights.iter().sum();
+                weights.iter().take(slice + 1).sum::<f64>() / total
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildOrderStyle {
+    /// `Ioc` orders priced `slippage_bps` through the touch
+    Ioc { slippage_bps: f64 },
+    /// `Alo` orders resting at the touch, canceled at the end of their slice
+    Passive,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecutionConfig {
+    pub coin: String,
+    pub sz_decimals: u32,
+    pub is_buy: bool,
+    /// Parent order size
+    pub sz: f64,
+    pub duration_ms: u64,
+    pub schedule: ExecutionSchedule,
+    pub style: ChildOrderStyle,
+    /// Worst price any child order is placed at
+    #[serde(default)]
+    pub limit_px: Option<f64>,
+    /// Cap on the filled size as a fraction of the market volume traded since the start
+    #[serde(default)]
+    pub max_participation: Option<f64>,
+    #[serde(default)]
+    pub reduce_only: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionProgress {
+    pub filled_sz: f64,
+    /// Size-weighted average fill price, zero until the first fill
+    pub avg_px: f64,
+    /// Mid when the execution started
+    pub arrival_px: Option<f64>,
+    /// Volume traded in the coin since the start, including our own fills
+    pub market_volume: f64,
+    pub child_orders: usize,
+    pub done: bool,
+}
+
+impl ExecutionProgress {
+    /// Average fill price relative to the arrival price, positive when worse.
+    pub fn slippage_bps(&self, is_buy: bool) -> Option<f64> {
+        let arrival_px = self.arrival_px?;
+        if self.filled_sz < EPSILON {
+            return None;
+        }
+        let diff = (self.avg_px - arrival_px) / arrival_px * 10_000.0;
+        Some(if is_buy { diff } else { -diff })
+    }
+}
+
+/// Client-side TWAP/VWAP: works a parent order over `duration_ms` in child orders placed
+/// through an `OrderManager`.
+///
+/// Time is taken from the `l2Book` and `trades` messages passed to `on_message`, so a
+/// replayed stream runs the same schedule; `step` can be called from a timer when the stream
+/// is quiet. The size due by each slice that has not filled yet is placed at the start of the
+/// slice, capped by `max_participation`. Size left at the end of the duration is not
+/// executed.
+#[derive(Debug)]
+pub struct ExecutionAlgo {
+    config: ExecutionConfig,
+    manager: OrderManager,
+    start: Option<u64>,
+    now: u64,
+    slice: Option<usize>,
+    bid: Option<f64>,
+    ask: Option<f64>,
+    /// Fills of child orders no longer tracked by the manager
+    done_sz: f64,
+    done_notional: f64,
+    progress: ExecutionProgress,
+}
+
+impl ExecutionAlgo {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address, config: ExecutionConfig) -> Result<ExecutionAlgo> {
+        let valid_weights = match &config.schedule {
+            ExecutionSchedule::Fixed { .. } => true,
+            ExecutionSchedule::VolumeWeighted { weights } => {
+                weights.iter().all(|w| *w >= 0.0) && weights.iter().sum::<f64>() > 0.0
+            }
+        };
+        if config.sz <= 0.0 || config.schedule.slices() == 0 || !valid_weights {
+            return Err(Error::InvalidConfig(
+                "Execution needs a positive size and at least one weighted slice".to_string(),
+            ));
+        }
+        Ok(ExecutionAlgo {
+            config,
+            manager: OrderManager::new(user),
+            start: None,
+            now: 0,
+            slice: None,
+            bid: None,
+            ask: None,
+            done_sz: 0.0,
+            done_notional: 0.0,
+            progress: ExecutionProgress::default(),
+        })
+    }
+
+    pub fn config(&self) -> &ExecutionConfig {
+        &self.config
+    }
+
+    pub fn progress(&self) -> &ExecutionProgress {
+        &self.progress
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.progress.done
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = self.manager.subscriptions();
+        subscriptions.push(Subscription::L2Book {
+            coin: self.config.coin.clone(),
+        });
+        subscriptions.push(Subscription::Trades {
+            coin: self.config.coin.clone(),
+        });
+        subscriptions
+    }
+
+    /// Advances the schedule to `now`, in ms. Does nothing until a book has been seen.
+    pub async fn step<E: Exchange>(&mut self, exchange: &E, now: u64) -> Result<()> {
+        self.now = self.now.max(now);
+        self.update_fills();
+        if self.progress.done {
+            return Ok(());
+        }
+        let (Some(bid), Some(ask)) = (self.bid, self.ask) else {
+            return Ok(());
+        };
+        let start = *self.start.get_or_insert_with(|| {
+            self.progress.arrival_px = Some((bid + ask) / 2.0);
+            info!(
+                "Starting execution of {} {} at {}",
+                self.config.sz,
+                self.config.coin,
+                (bid + ask) / 2.0
+            );
+            now
+        });
+
+        let elapsed = self.now.saturating_sub(start);
+        let lot = lot_size(self.config.sz_decimals);
+        if elapsed >= self.config.duration_ms || self.config.sz - self.progress.filled_sz < lot {
+            self.cancel(exchange).await?;
+            self.progress.done = true;
+            info!(
+                "Execution of {} done, filled {} at {}",
+                self.config.coin, self.progress.filled_sz, self.progress.avg_px
+            );
+            return Ok(());
+        }
+
+        let slices = self.config.schedule.slices();
+        let slice_ms = (self.config.duration_ms / slices as u64).max(1);
+        let slice = ((elapsed / slice_ms) as usize).min(slices - 1);
+        if self.slice == Some(slice) && !self.manager.open_orders().is_empty() {
+            return Ok(());
+        }
+        if self.slice != Some(slice) {
+            self.slice = Some(slice);
+            self.cancel(exchange).await?;
+            self.update_fills();
+        }
+
+        let mut due = self.config.sz * self.config.schedule.due(slice);
+        if let Some(participation) = self.config.max_participation {
+            due = due.min(self.progress.market_volume * participation);
+        }
+        let pending: f64 = self
+            .manager
+            .open_orders()
+            .iter()
+            .map(|o| o.remaining_sz())
+            .sum();
+        let sz = round_to_tick(
+            due - self.progress.filled_sz - pending,
+            lot,
+            RoundingMode::Down,
+        );
+        if sz < lot - EPSILON {
+            return Ok(());
+        }
+        self.place(exchange, bid, ask, sz).await
+    }
+
+    /// Cancels open child orders, leaving the execution to be continued by `step`.
+    pub async fn cancel<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let cloids: Vec<Uuid> = self.manager.open_orders().iter().map(|o| o.cloid).collect();
+        if cloids.is_empty() {
+            return Ok(());
+        }
+        for status in self.manager.cancel(exchange, cloids).await? {
+            if let Err(err) = status.status {
+                warn!("Could not cancel child order {}: {err}", status.request);
+            }
+        }
+        Ok(())
+    }
+
+    async fn place<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        bid: f64,
+        ask: f64,
+        sz: f64,
+    ) -> Result<()> {
+        let is_buy = self.config.is_buy;
+        let (px, tif) = match self.config.style {
+            ChildOrderStyle::Ioc { slippage_bps } => {
+                let touch = if is_buy { ask } else { bid };
+                let bps = if is_buy { slippage_bps } else { -slippage_bps };
+                (apply_bps(touch, bps), Tif::Ioc)
+            }
+            ChildOrderStyle::Passive => (if is_buy { bid } else { ask }, Tif::Alo),
+        };
+        let px = match self.config.limit_px {
+            Some(limit_px) if is_buy => px.min(limit_px),
+            Some(limit_px) => px.max(limit_px),
+            None => px,
+        };
+        let tick = price_tick_size(px, self.config.sz_decimals, false);
+        let mode = if is_buy {
+            RoundingMode::Down
+        } else {
+            RoundingMode::Up
+        };
+        let order = ClientOrderRequest {
+            asset: self.config.coin.clone(),
+            is_buy,
+            reduce_only: self.config.reduce_only,
+            limit_px: round_to_tick(px, tick, mode),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif }),
+        };
+        self.progress.child_orders += 1;
+        for status in self.manager.place(exchange, vec![order]).await? {
+            if let Err(err) = status.status {
+                warn!("Child order rejected: {err}");
+            }
+        }
+        self.update_fills();
+        Ok(())
+    }
+
+    fn update_fills(&mut self) {
+        for order in self.manager.remove_done() {
+            self.done_sz += order.filled_sz;
+            self.done_notional += order.filled_sz * order.avg_fill_px;
+        }
+        let (mut sz, mut notional) = (self.done_sz, self.done_notional);
+        for order in self.manager.open_orders() {
+            sz += order.filled_sz;
+            notional += order.filled_sz * order.avg_fill_px;
+        }
+        self.progress.filled_sz = sz;
+        self.progress.avg_px = if sz > 0.0 { notional / sz } else { 0.0 };
+    }
+}
+
+impl Strategy for ExecutionAlgo {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        let coin = &self.config.coin;
+        let time = match message {
+            Message::L2Book(book) if &book.data.coin == coin => {
+                let best = |side: usize| -> Option<f64> {
+                    book.data.levels.get(side)?.first()?.px.parse().ok()
+                };
+                self.bid = best(0).or(self.bid);
+                self.ask = best(1).or(self.ask);
+                Some(book.data.time.as_millis())
+            }
+            Message::Trades(trades) => {
+                let mut time = None;
+                for trade in trades.data.iter().filter(|t| &t.coin == coin) {
+                    if self.start.is_some_and(|start| trade.time >= start) {
+                        self.progress.market_volume += trade.sz.parse::<f64>().unwrap_or(0.0);
+                    }
+                    time = Some(trade.time.as_millis());
+                }
+                time
+            }
+            _ => None,
+        };
+        self.manager.handle_message(message);
+        self.step(exchange, time.unwrap_or(self.now)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    fn book(time: u64) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"l2Book","data":{{"coin":"ETH","time":{time},"levels":[[{{"px":"1999","sz":"10","n":1}}],[{{"px":"2001","sz":"10","n":1}}]]}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_twap_slices_and_slippage() {
+        let config: ExecutionConfig = serde_json::from_str(
+            r#"{"coin":"ETH","sz_decimals":2,"is_buy":true,"sz":1,"duration_ms":4000,
+                "schedule":{"fixed":{"slices":4}},"style":{"ioc":{"slippage_bps":10}}}"#,
+        )
+        .unwrap();
+        let mut algo = ExecutionAlgo::new(Address::ZERO, config).unwrap();
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+
+        for (time, filled) in [(0, 0.25), (500, 0.25), (1000, 0.5), (2000, 0.75)] {
+            let book = book(time);
+            exchange.handle_message(&book);
+            algo.on_message(&book, &exchange).await.unwrap();
+            while let Ok(event) = receiver.try_recv() {
+                algo.on_message(&event, &exchange).await.unwrap();
+            }
+            assert!(
+                (algo.progress().filled_sz - filled).abs() < EPSILON,
+                "{time}"
+            );
+        }
+        assert_eq!(algo.progress().child_orders, 3);
+        assert!(!algo.is_done());
+
+        algo.step(&exchange, 4000).await.unwrap();
+        let progress = algo.progress();
+        assert!(progress.done);
+        assert_eq!(progress.arrival_px, Some(2000.0));
+        assert!((progress.avg_px - 2001.0).abs() < EPSILON);
+        assert!((progress.slippage_bps(true).unwrap() - 5.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_participation_cap() {
+        let config: ExecutionConfig = serde_json::from_str(
+            r#"{"coin":"ETH","sz_decimals":2,"is_buy":false,"sz":1,"duration_ms":1000,
+                "schedule":{"volume_weighted":{"weights":[1]}},"style":"passive","max_participation":0.1}"#,
+        )
+        .unwrap();
+        let mut algo = ExecutionAlgo::new(Address::ZERO, config).unwrap();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        let book = book(0);
+        exchange.handle_message(&book);
+        algo.on_message(&book, &exchange).await.unwrap();
+        assert!(exchange.open_orders().is_empty());
+
+        let trades: Message = serde_json::from_str(
+            r#"{"channel":"trades","data":[{"coin":"ETH","side":"B","px":"2000","sz":"3","time":10,"hash":"0x0","tid":1,"users":["0x0","0x0"]}]}"#,
+        )
+        .unwrap();
+        algo.on_message(&trades, &exchange).await.unwrap();
+        let open = exchange.open_orders();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].sz, "0.3");
+        assert_eq!(open[0].limit_px, "2001");
+    }
+}
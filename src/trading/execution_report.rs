@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use uuid::Uuid;
+
+use crate::{Message, Timestamp, TradeInfo, UserData, EPSILON};
+
+const USDC: &str = "USDC";
+
+/// Fills of one trading decision, whatever orders it took, with the average price paid once
+/// fees are included, so execution quality is measured per decision rather than per order.
+///
+/// Fills belong to the decision when their cloid starts with its prefix, or their cloid or
+/// order id was tracked. Cloids from `new_cloid` carry the prefix, so fills are still
+/// attributed after a restart. Fills are deduplicated by trade id.
+///
+/// Fees charged in a token other than USDC, as on spot buys, are valued at the fill price.
+/// Builder fees are part of the fee and reported separately as well.
+#[derive(Clone, Debug)]
+pub struct ExecutionReport {
+    tag: String,
+    cloid_prefix: Option<String>,
+    cloids: HashSet<String>,
+    oids: HashSet<u64>,
+    seen_fills: HashSet<u64>,
+    fills: usize,
+    bought: f64,
+    sold: f64,
+    buy_notional: f64,
+    sell_notional: f64,
+    fees: f64,
+    builder_fees: f64,
+    first_fill: Option<Timestamp>,
+    last_fill: Option<Timestamp>,
+}
+
+impl ExecutionReport {
+    /// `tag` names the decision, such as a signal id.
+    pub fn new(tag: impl Into<String>) -> ExecutionReport {
+        ExecutionReport {
+            tag: tag.into(),
+            cloid_prefix: None,
+            cloids: HashSet::new(),
+            oids: HashSet::new(),
+            seen_fills: HashSet::new(),
+            fills: 0,
+            bought: 0.0,
+            sold: 0.0,
+            buy_notional: 0.0,
+            sell_notional: 0.0,
+            fees: 0.0,
+            builder_fees: 0.0,
+            first_fill: None,
+            last_fill: None,
+        }
+    }
+
+    /// Counts fills whose cloid starts with the hex digits `prefix`, with or without `0x`.
+    pub fn with_cloid_prefix(mut self, prefix: &str) -> Self {
+        let prefix = normalize_cloid(prefix);
+        self.cloid_prefix = Some(prefix.chars().take(32).collect());
+        self
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// A random cloid for an order of this decision, starting with the cloid prefix if it is
+    /// valid hex, and tracked either way.
+    pub fn new_cloid(&mut self) -> Uuid {
+        let random = Uuid::new_v4().simple().to_string();
+        let cloid = self
+            .cloid_prefix
+            .as_ref()
+            .and_then(|prefix| {
+                Uuid::try_parse(&format!("{prefix}{}", &random[prefix.len()..])).ok()
+            })
+            .unwrap_or_else(|| Uuid::try_parse(&random).expect("simple uuid parses"));
+        self.track_cloid(cloid);
+        cloid
+    }
+
+    pub fn track_cloid(&mut self, cloid: Uuid) {
+        self.cloids.insert(cloid.simple().to_string());
+    }
+
+    /// Counts fills of `oid`, for orders placed without a cloid.
+    pub fn track_oid(&mut self, oid: u64) {
+        self.oids.insert(oid);
+    }
+
+    /// Whether `fill` belongs to this decision.
+    pub fn matches(&self, fill: &TradeInfo) -> bool {
+        if self.oids.contains(&fill.oid) {
+            return true;
+        }
+        let Some(cloid) = fill.cloid.as_deref().map(normalize_cloid) else {
+            return false;
+        };
+        self.cloids.contains(&cloid)
+            || self
+                .cloid_prefix
+                .as_ref()
+                .is_some_and(|prefix| cloid.starts_with(prefix.as_str()))
+    }
+
+    /// Adds `fill` if it belongs to this decision and was not added before, returning whether
+    /// it was.
+    pub fn add_fill(&mut self, fill: &TradeInfo) -> bool {
+        if !self.matches(fill) || self.seen_fills.contains(&fill.tid) {
+            return false;
+        }
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            return false;
+        };
+        self.seen_fills.insert(fill.tid);
+        let in_usdc = |fee: f64| {
+            if fill.fee_token == USDC {
+                fee
+            } else {
+                fee * px
+            }
+        };
+        self.fills += 1;
+        if fill.side.is_buy() {
+            self.bought += sz;
+            self.buy_notional += px * sz;
+        } else {
+            self.sold += sz;
+            self.sell_notional += px * sz;
+        }
+        self.fees += in_usdc(fill.fee.parse().unwrap_or_default());
+        self.builder_fees += in_usdc(
+            fill.builder_fee
+                .as_deref()
+                .and_then(|fee| fee.parse().ok())
+                .unwrap_or_default(),
+        );
+        self.first_fill = Some(
+            self.first_fill
+                .map_or(fill.time, |time| time.min(fill.time)),
+        );
+        self.last_fill = Some(self.last_fill.map_or(fill.time, |time| time.max(fill.time)));
+        true
+    }
+
+    /// Adds the fills of this decision in `userFills` and `userEvents` messages, snapshots
+    /// included, so fills from before a restart count.
+    pub fn handle_message(&mut self, message: &Message) {
+        let fills = match message {
+            Message::UserFills(fills) => &fills.data.fills,
+            Message::User(user) => match &user.data {
+                UserData::Fills(fills) => fills,
+                _ => return,
+            },
+            _ => return,
+        };
+        for fill in fills {
+            self.add_fill(fill);
+        }
+    }
+
+    pub fn fills(&self) -> usize {
+        self.fills
+    }
+
+    pub fn bought(&self) -> f64 {
+        self.bought
+    }
+
+    pub fn sold(&self) -> f64 {
+        self.sold
+    }
+
+    /// Size bought minus sold.
+    pub fn net_sz(&self) -> f64 {
+        self.bought - self.sold
+    }
+
+    /// Fees in USDC, builder fees included.
+    pub fn fees(&self) -> f64 {
+        self.fees
+    }
+
+    /// Part of `fees` paid to builders.
+    pub fn builder_fees(&self) -> f64 {
+        self.builder_fees
+    }
+
+    /// Size-weighted average price of every fill, before fees.
+    pub fn avg_px(&self) -> Option<f64> {
+        let sz = self.bought + self.sold;
+        (sz > EPSILON).then(|| (self.buy_notional + self.sell_notional) / sz)
+    }
+
+    /// Price paid per unit of the net size once fees are included: above the fill prices
+    /// when buying, below them when selling. None while the decision is flat.
+    pub fn net_avg_px(&self) -> Option<f64> {
+        let net_sz = self.net_sz();
+        let cost = self.buy_notional - self.sell_notional + self.fees;
+        (net_sz.abs() > EPSILON).then(|| cost / net_sz)
+    }
+
+    /// Net average price relative to `arrival_px`, the price when the decision was made,
+    /// positive when worse.
+    pub fn slippage_bps(&self, arrival_px: f64) -> Option<f64> {
+        let net_avg_px = self.net_avg_px()?;
+        let diff = (net_avg_px - arrival_px) / arrival_px * 10_000.0;
+        Some(if self.net_sz() > 0.0 { diff } else { -diff })
+    }
+
+    /// Time of the first and last fill.
+    pub fn fill_times(&self) -> Option<(Timestamp, Timestamp)> {
+        self.first_fill.zip(self.last_fill)
+    }
+}
+
+fn normalize_cloid(cloid: &str) -> String {
+    cloid.trim_start_matches("0x").to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FillDirection, Side};
+
+    fn fill(tid: u64, cloid: Option<&str>, side: Side, px: &str, sz: &str) -> TradeInfo {
+        TradeInfo {
+            coin: "ETH".to_string(),
+            side,
+            px: px.to_string(),
+            sz: sz.to_string(),
+            time: tid.into(),
+            hash: "0x0".to_string(),
+            start_position: "0".to_string(),
+            dir: FillDirection::OpenLong,
+            closed_pnl: "0".to_string(),
+            oid: tid,
+            cloid: cloid.map(str::to_string),
+            crossed: true,
+            fee: "1".to_string(),
+            fee_token: USDC.to_string(),
+            tid,
+            twap_id: None,
+            builder_fee: Some("0.25".to_string()),
+            liquidation: None,
+        }
+    }
+
+    #[test]
+    fn test_net_average_price_per_decision() {
+        let mut report = ExecutionReport::new("signal-1").with_cloid_prefix("0xABCD");
+        let cloid = report.new_cloid();
+        assert!(cloid.simple().to_string().starts_with("abcd"));
+        report.track_oid(9);
+
+        let ours = "0xabcd0000000000000000000000000001";
+        assert!(report.add_fill(&fill(1, Some(ours), Side::Buy, "100", "1")));
+        assert!(report.add_fill(&fill(2, Some(ours), Side::Buy, "102", "1")));
+        // Duplicate, another decision's order and an order tracked by id
+        assert!(!report.add_fill(&fill(2, Some(ours), Side::Buy, "102", "1")));
+        assert!(!report.add_fill(&fill(
+            3,
+            Some("0x12340000000000000000000000000001"),
+            Side::Buy,
+            "90",
+            "1"
+        )));
+        assert!(report.add_fill(&fill(9, None, Side::Buy, "104", "2")));
+
+        assert_eq!(report.fills(), 3);
+        assert_eq!(report.avg_px(), Some(102.5));
+        assert_eq!(report.fees(), 3.0);
+        assert_eq!(report.builder_fees(), 0.75);
+        // 410 paid plus 3 in fees for 4
+        assert_eq!(report.net_avg_px(), Some(103.25));
+        assert!((report.slippage_bps(100.0).unwrap() - 325.0).abs() < 1e-9);
+        assert_eq!(report.fill_times(), Some((1.into(), 9.into())));
+    }
+}
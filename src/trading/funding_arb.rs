@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use alloy::primitives::Address;
+use tracing::info;
+
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Error, Exchange, ExchangeResponseStatus, InfoClient, PredictedFunding,
+    RoundingMode, Tif, UserStateResponse, UserTokenBalanceResponse, VenueFundings, EPSILON,
+};
+
+const HOURS_PER_YEAR: f64 = 24.0 * 365.0;
+pub(super) const HL_VENUE: &str = "HlPerp";
+
+/// Predicted funding on one venue, normalized to an hourly rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VenueFunding {
+    pub venue: String,
+    pub hourly_rate: f64,
+    pub next_funding_time: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CarryOptions {
+    /// Spot token held against each perp, e.g. `ETH` to `UETH`
+    pub spot_tokens: HashMap<String, String>,
+    /// Annual cost of borrowing each perp's spot token, paid when the perp leg is long
+    pub borrow_apr: HashMap<String, f64>,
+}
+
+/// Carry of a delta-neutral perp/spot pair on one coin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FundingCarry {
+    pub coin: String,
+    /// Predicted Hyperliquid funding per hour, positive when longs pay
+    pub hourly_rate: f64,
+    pub next_funding_time: u64,
+    pub funding_apr: f64,
+    /// Perp side that collects funding
+    pub perp_is_long: bool,
+    /// Cost of the spot leg: the borrow APR when the perp is long against short spot
+    pub spot_leg_apr: f64,
+    /// Funding collected less the spot leg cost, annualized
+    pub net_apr: f64,
+    pub perp_szi: f64,
+    /// Balance of the spot token configured for the coin
+    pub spot_balance: Option<f64>,
+    /// Funding the current perp position receives per hour at the mid, negative when paying
+    pub position_carry_per_hour: f64,
+    pub other_venues: Vec<VenueFunding>,
+}
+
+pub(super) fn venue_funding(venue: &str, funding: &PredictedFunding) -> Option<VenueFunding> {
+    let rate: f64 = funding.funding_rate.parse().ok()?;
+    let hours = funding.funding_interval_hours.unwrap_or(match venue {
+        HL_VENUE => 1,
+        _ => 8,
+    });
+    Some(VenueFunding {
+        venue: venue.to_string(),
+        hourly_rate: rate / hours.max(1) as f64,
+        next_funding_time: funding.next_funding_time.as_millis(),
+    })
+}
+
+/// Joins predicted fundings with the account's perp positions and spot balances into the
+/// carry of each coin, highest net APR first.
+pub fn funding_carry(
+    predicted: &[(String, VenueFundings)],
+    state: &UserStateResponse,
+    balances: &UserTokenBalanceResponse,
+    mids: &HashMap<String, String>,
+    options: &CarryOptions,
+) -> Vec<FundingCarry> {
+    let positions: HashMap<&str, f64> = state
+        .asset_positions
+        .iter()
+        .map(|p| {
+            let szi = p.position.szi.parse().unwrap_or_default();
+            (p.position.coin.as_str(), szi)
+        })
+        .collect();
+    let spot: HashMap<&str, f64> = balances
+        .balances
+        .iter()
+        .map(|b| (b.coin.as_str(), b.total.parse().unwrap_or_default()))
+        .collect();
+
+    let mut carries: Vec<FundingCarry> = predicted
+        .iter()
+        .filter_map(|(coin, venues)| {
+            let mut venues: Vec<VenueFunding> = venues
+                .iter()
+                .filter_map(|(venue, funding)| venue_funding(venue, funding.as_ref()?))
+                .collect();
+            let hl = venues.iter().position(|v| v.venue == HL_VENUE)?;
+            let hl = venues.remove(hl);
+
+            let perp_is_long = hl.hourly_rate < 0.0;
+            let funding_apr = hl.hourly_rate * HOURS_PER_YEAR;
+            let spot_leg_apr = if perp_is_long {
+                options.borrow_apr.get(coin).copied().unwrap_or_default()
+            } else {
+                0.0
+            };
+            let perp_szi = positions.get(coin.as_str()).copied().unwrap_or_default();
+            let mid: f64 = mids
+                .get(coin)
+                .and_then(|mid| mid.parse().ok())
+                .unwrap_or_default();
+            Some(FundingCarry {
+                coin: coin.clone(),
+                hourly_rate: hl.hourly_rate,
+                next_funding_time: hl.next_funding_time,
+                funding_apr,
+                perp_is_long,
+                spot_leg_apr,
+                net_apr: funding_apr.abs() - spot_leg_apr,
+                perp_szi,
+                spot_balance: options
+                    .spot_tokens
+                    .get(coin)
+                    .map(|token| spot.get(token.as_str()).copied().unwrap_or_default()),
+                position_carry_per_hour: -perp_szi * mid * hl.hourly_rate,
+                other_venues: venues,
+            })
+        })
+        .collect();
+    carries.sort_by(|a, b| b.net_apr.total_cmp(&a.net_apr));
+    carries
+}
+
+#[derive(Clone, Debug)]
+pub struct DeltaNeutralConfig {
+    pub perp_coin: String,
+    pub perp_sz_decimals: u32,
+    /// Spot pair the spot leg trades, e.g. `UETH/USDC` or `@151`
+    pub spot_pair: String,
+    /// Base token of `spot_pair`, as named in spot balances
+    pub spot_token: String,
+    pub spot_sz_decimals: u32,
+    /// Spot size to hold against a perp short of the same size
+    pub target_sz: f64,
+    /// Difference from the target either leg may drift by before it is traded
+    pub tolerance: f64,
+    /// How far through the mid rebalancing `Ioc` orders are priced
+    pub slippage_bps: f64,
+}
+
+/// Keeps a long spot, short perp pair at `target_sz`, trading each leg back with `Ioc`
+/// orders when it drifts by more than `tolerance`. Setting the target to zero unwinds it.
+#[derive(Debug)]
+pub struct DeltaNeutralExecutor {
+    user: Address,
+    config: DeltaNeutralConfig,
+}
+
+impl DeltaNeutralExecutor {
+    /// `user` is the account holding both legs.
+    pub fn new(user: Address, config: DeltaNeutralConfig) -> DeltaNeutralExecutor {
+        DeltaNeutralExecutor { user, config }
+    }
+
+    pub fn config(&self) -> &DeltaNeutralConfig {
+        &self.config
+    }
+
+    pub fn set_target_sz(&mut self, target_sz: f64) {
+        self.config.target_sz = target_sz;
+    }
+
+    /// Orders bringing both legs back to the target from the given sizes and mids.
+    pub fn plan(
+        &self,
+        perp_szi: f64,
+        spot_balance: f64,
+        perp_mid: f64,
+        spot_mid: f64,
+    ) -> Vec<ClientOrderRequest> {
+        let config = &self.config;
+        let legs = [
+            (
+                &config.spot_pair,
+                config.spot_sz_decimals,
+                true,
+                config.target_sz - spot_balance,
+                spot_mid,
+            ),
+            (
+                &config.perp_coin,
+                config.perp_sz_decimals,
+                false,
+                -config.target_sz - perp_szi,
+                perp_mid,
+            ),
+        ];
+        legs.into_iter()
+            .filter_map(|(asset, sz_decimals, is_spot, diff, mid)| {
+                let lot = lot_size(sz_decimals);
+                let sz = round_to_tick(diff.abs(), lot, RoundingMode::Down);
+                if diff.abs() <= config.tolerance || sz < lot - EPSILON || mid <= 0.0 {
+                    return None;
+                }
+                let is_buy = diff > 0.0;
+                let bps = if is_buy { 1.0 } else { -1.0 } * config.slippage_bps;
+                let px = apply_bps(mid, bps);
+                let tick = price_tick_size(px, sz_decimals, is_spot);
+                Some(ClientOrderRequest {
+                    asset: asset.clone(),
+                    is_buy,
+                    reduce_only: false,
+                    limit_px: round_to_tick(px, tick, RoundingMode::Nearest),
+                    sz,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+                })
+            })
+            .collect()
+    }
+
+    /// Fetches both legs and the mids, then sends the orders from `plan`, returning them.
+    pub async fn rebalance<E: Exchange>(
+        &self,
+        exchange: &E,
+        info: &InfoClient,
+    ) -> Result<Vec<ClientOrderRequest>> {
+        let config = &self.config;
+        let state = info.user_state(self.user).await?;
+        let balances = info.user_token_balances(self.user).await?;
+        let mids = info.all_mids().await?;
+        let perp_szi = state
+            .asset_positions
+            .iter()
+            .find(|p| p.position.coin == config.perp_coin)
+            .and_then(|p| p.position.szi.parse().ok())
+            .unwrap_or_default();
+        let spot_balance = balances
+            .balances
+            .iter()
+            .find(|b| b.coin == config.spot_token)
+            .and_then(|b| b.total.parse().ok())
+            .unwrap_or_default();
+        let mid = |coin: &str| -> Result<f64> {
+            mids.get(coin)
+                .and_then(|mid| mid.parse().ok())
+                .ok_or_else(|| Error::GenericRequest(format!("No mid for {coin}")))
+        };
+        let orders = self.plan(
+            perp_szi,
+            spot_balance,
+            mid(&config.perp_coin)?,
+            mid(&config.spot_pair)?,
+        );
+        if orders.is_empty() {
+            return Ok(orders);
+        }
+        info!(
+            "Rebalancing {} pair from perp {perp_szi} and spot {spot_balance} to {}",
+            config.perp_coin, config.target_sz
+        );
+        if let ExchangeResponseStatus::Err(err) = exchange.bulk_order(orders.clone()).await? {
+            return Err(Error::GenericRequest(err.to_string()));
+        }
+        Ok(orders)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_funding_carry() {
+        let predicted: Vec<(String, VenueFundings)> =
+            serde_json::from_str(
+                r#"[
+                ["ETH",[["BinPerp",{"fundingRate":"0.0008","nextFundingTime":1,"fundingIntervalHours":8}],["HlPerp",{"fundingRate":"0.00002","nextFundingTime":2,"fundingIntervalHours":1}]]],
+                ["BTC",[["HlPerp",{"fundingRate":"-0.00001","nextFundingTime":2}],["BybitPerp",null]]]
+            ]"#,
+            )
+            .unwrap();
+        let state: UserStateResponse = serde_json::from_str(
+            r#"{"assetPositions":[{"type":"oneWay","position":{"coin":"ETH","entryPx":"2000","leverage":{"type":"cross","value":5},"liquidationPx":null,"marginUsed":"400","positionValue":"2000","returnOnEquity":"0","szi":"-1","unrealizedPnl":"0","maxLeverage":25,"cumFunding":{"allTime":"0","sinceOpen":"0","sinceChange":"0"}}}],
+            "crossMarginSummary":{"accountValue":"1000","totalMarginUsed":"400","totalNtlPos":"2000","totalRawUsd":"3000"},
+            "marginSummary":{"accountValue":"1000","totalMarginUsed":"400","totalNtlPos":"2000","totalRawUsd":"3000"},
+            "withdrawable":"600"}"#,
+        )
+        .unwrap();
+        let balances: UserTokenBalanceResponse = serde_json::from_str(
+            r#"{"balances":[{"coin":"UETH","hold":"0","total":"1","entryNtl":"2000"}]}"#,
+        )
+        .unwrap();
+        let mids = HashMap::from([("ETH".to_string(), "2000".to_string())]);
+        let options = CarryOptions {
+            spot_tokens: HashMap::from([("ETH".to_string(), "UETH".to_string())]),
+            borrow_apr: HashMap::from([("BTC".to_string(), 0.05)]),
+        };
+
+        let carries = funding_carry(&predicted, &state, &balances, &mids, &options);
+        assert_eq!(carries.len(), 2);
+        let eth = &carries[0];
+        assert_eq!(eth.coin, "ETH");
+        assert!(!eth.perp_is_long);
+        assert!((eth.funding_apr - 0.1752).abs() < 1e-9);
+        assert_eq!(eth.spot_balance, Some(1.0));
+        assert!((eth.position_carry_per_hour - 0.04).abs() < 1e-9);
+        assert_eq!(eth.other_venues[0].hourly_rate, 0.0001);
+
+        let btc = &carries[1];
+        assert!(btc.perp_is_long);
+        assert!((btc.net_apr - (0.0876 - 0.05)).abs() < 1e-9);
+        assert_eq!(btc.spot_balance, None);
+    }
+
+    #[test]
+    fn test_delta_neutral_plan() {
+        let executor = DeltaNeutralExecutor::new(
+            Address::ZERO,
+            DeltaNeutralConfig {
+                perp_coin: "ETH".to_string(),
+                perp_sz_decimals: 4,
+                spot_pair: "UETH/USDC".to_string(),
+                spot_token: "UETH".to_string(),
+                spot_sz_decimals: 4,
+                target_sz: 1.0,
+                tolerance: 0.01,
+                slippage_bps: 10.0,
+            },
+        );
+        let orders = executor.plan(-0.5, 0.995, 2000.0, 2001.0);
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].asset, "ETH");
+        assert!(!orders[0].is_buy);
+        assert_eq!(orders[0].sz, 0.5);
+        assert_eq!(orders[0].limit_px, 1998.0);
+
+        let orders = executor.plan(-1.0, 0.5, 2000.0, 2001.0);
+        assert_eq!(orders[0].asset, "UETH/USDC");
+        assert!(orders[0].is_buy);
+        assert_eq!(orders[0].sz, 0.5);
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::HashMap, fmt, future::Future, pin::pin, time::Duration};
+
+use alloy::primitives::Address;
+use tracing::{error, info, warn};
+
+use super::funding_arb::{venue_funding, HL_VENUE};
+use crate::{
+    apply_bps, helpers::now_timestamp_ms, prelude::*, price_tick_size, round_to_tick, rt,
+    ClientLimit, ClientOrder, ClientOrderRequest, Error, Exchange, ExchangeResponseStatus,
+    InfoClient, Meta, RoundingMode, Tif, UserStateResponse, VenueFundings, EPSILON,
+};
+
+const HOUR_MS: u64 = 60 * 60 * 1000;
+
+/// Upcoming Hyperliquid funding of one open perp position at the predicted rate.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FundingForecast {
+    pub coin: String,
+    pub szi: f64,
+    /// Mid the payment is valued at, or the position's value per unit without one
+    pub mark_px: f64,
+    /// Predicted funding per hour, positive when longs pay
+    pub hourly_rate: f64,
+    pub next_funding_time: u64,
+    /// Funding the position receives at the next payment, negative when paying
+    pub payment: f64,
+}
+
+impl FundingForecast {
+    /// Hourly payments from the next funding time up to and including `until`, in ms, as
+    /// `(time, payment)` pairs. Later payments assume the rate and position stay the same.
+    pub fn payments_until(&self, until: u64) -> Vec<(u64, f64)> {
+        (0..)
+            .map(|hour| self.next_funding_time + hour * HOUR_MS)
+            .take_while(|time| *time <= until)
+            .map(|time| (time, self.payment))
+            .collect()
+    }
+}
+
+/// Forecasts the next funding payment of every open perp position from predicted fundings,
+/// costliest first.
+pub fn forecast_funding(
+    predicted: &[(String, VenueFundings)],
+    state: &UserStateResponse,
+    mids: &HashMap<String, String>,
+) -> Vec<FundingForecast> {
+    let rates: HashMap<&str, (f64, u64)> = predicted
+        .iter()
+        .filter_map(|(coin, venues)| {
+            let hl = venues
+                .iter()
+                .filter(|(venue, _)| venue == HL_VENUE)
+                .find_map(|(venue, funding)| venue_funding(venue, funding.as_ref()?))?;
+            Some((coin.as_str(), (hl.hourly_rate, hl.next_funding_time)))
+        })
+        .collect();
+
+    let mut forecasts: Vec<FundingForecast> = state
+        .asset_positions
+        .iter()
+        .filter_map(|asset_position| {
+            let position = &asset_position.position;
+            let szi: f64 = position.szi.parse().ok()?;
+            if szi.abs() < EPSILON {
+                return None;
+            }
+            let &(hourly_rate, next_funding_time) = rates.get(position.coin.as_str())?;
+            let mark_px = mids
+                .get(&position.coin)
+                .and_then(|mid| mid.parse().ok())
+                .or_else(|| {
+                    let value: f64 = position.position_value.parse().ok()?;
+                    Some(value / szi.abs())
+                })?;
+            Some(FundingForecast {
+                coin: position.coin.clone(),
+                szi,
+                mark_px,
+                hourly_rate,
+                next_funding_time,
+                payment: -szi * mark_px * hourly_rate,
+            })
+        })
+        .collect();
+    forecasts.sort_by(|a, b| a.payment.total_cmp(&b.payment));
+    forecasts
+}
+
+/// What `FundingGuard` does with a position about to pay funding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FundingAction {
+    /// Close the position with a reduce-only order
+    Flatten,
+    /// Close the position and open the same size on the other side, which collects instead
+    Flip,
+}
+
+#[derive(Clone, Debug)]
+pub struct FundingGuardConfig {
+    /// How long before a funding time positions paying it are acted on
+    pub lead_time: Duration,
+    /// Smallest payment, in USDC, worth acting on
+    pub min_cost: f64,
+    pub action: FundingAction,
+    /// How far through the mid the `Ioc` orders are priced
+    pub slippage_bps: f64,
+}
+
+type PolicyCallback = Box<dyn FnMut(&FundingForecast) -> Option<FundingAction> + Send>;
+
+/// Flattens or flips positions shortly before they pay funding.
+///
+/// Each `check` forecasts the next payment of every position and acts on those due within the
+/// lead time that would pay at least `min_cost`, once per position and funding time. A policy
+/// set with `with_policy` replaces that rule. Putting positions back after the payment is left
+/// to the caller.
+pub struct FundingGuard {
+    user: Address,
+    config: FundingGuardConfig,
+    sz_decimals: HashMap<String, u32>,
+    /// Funding time each coin was last acted on
+    acted: HashMap<String, u64>,
+    policy: Option<PolicyCallback>,
+}
+
+impl fmt::Debug for FundingGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FundingGuard")
+            .field("user", &self.user)
+            .field("config", &self.config)
+            .field("acted", &self.acted)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FundingGuard {
+    pub fn new(user: Address, config: FundingGuardConfig) -> FundingGuard {
+        FundingGuard {
+            user,
+            config,
+            sz_decimals: HashMap::new(),
+            acted: HashMap::new(),
+            policy: None,
+        }
+    }
+
+    /// Decides the action for each forecast due within the lead time, `None` to leave it.
+    pub fn with_policy(
+        mut self,
+        policy: impl FnMut(&FundingForecast) -> Option<FundingAction> + Send + 'static,
+    ) -> Self {
+        self.policy = Some(Box::new(policy));
+        self
+    }
+
+    /// Size decimals for the orders, fetched by `check` if not set.
+    pub fn with_meta(mut self, meta: &Meta) -> Self {
+        self.set_meta(meta);
+        self
+    }
+
+    /// Fetches predicted fundings, positions and mids and acts on positions due, returning
+    /// their forecasts.
+    pub async fn check<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        info: &InfoClient,
+    ) -> Result<Vec<FundingForecast>> {
+        if self.sz_decimals.is_empty() {
+            let meta = info.meta().await?;
+            self.set_meta(&meta);
+        }
+        let predicted = info.predicted_fundings().await?;
+        let state = info.user_state(self.user).await?;
+        let mids = info.all_mids().await?;
+        let forecasts = forecast_funding(&predicted, &state, &mids);
+        self.apply(exchange, &forecasts, now_timestamp_ms()).await
+    }
+
+    /// Acts on the forecasts due at `now`, in ms, returning them.
+    pub async fn apply<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        forecasts: &[FundingForecast],
+        now: u64,
+    ) -> Result<Vec<FundingForecast>> {
+        let lead_ms = self.config.lead_time.as_millis() as u64;
+        let mut acted = Vec::new();
+        for forecast in forecasts {
+            let due = forecast.next_funding_time > now
+                && forecast.next_funding_time - now <= lead_ms
+                && self.acted.get(&forecast.coin) != Some(&forecast.next_funding_time);
+            if !due {
+                continue;
+            }
+            let action = match &mut self.policy {
+                Some(policy) => policy(forecast),
+                None => (forecast.payment <= -self.config.min_cost).then_some(self.config.action),
+            };
+            let Some(action) = action else {
+                continue;
+            };
+            self.acted
+                .insert(forecast.coin.clone(), forecast.next_funding_time);
+            let orders = self.orders(forecast, action);
+            if orders.is_empty() {
+                continue;
+            }
+            info!(
+                "{action:?} {} {} ahead of funding payment {:.2}",
+                forecast.coin, forecast.szi, forecast.payment
+            );
+            if let ExchangeResponseStatus::Err(err) = exchange.bulk_order(orders).await? {
+                return Err(Error::GenericRequest(err.to_string()));
+            }
+            acted.push(forecast.clone());
+        }
+        Ok(acted)
+    }
+
+    /// Calls `check` every `interval` until `shutdown` completes. Errors are logged and
+    /// checking continues.
+    pub async fn run<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        info: &InfoClient,
+        interval: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            if let Err(err) = self.check(exchange, info).await {
+                error!("Error checking funding: {err}");
+            }
+            tokio::select! {
+                _ = &mut shutdown => return Ok(()),
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+
+    fn set_meta(&mut self, meta: &Meta) {
+        self.sz_decimals = meta
+            .universe
+            .iter()
+            .map(|asset| (asset.name.clone(), asset.sz_decimals))
+            .collect();
+    }
+
+    /// A reduce-only close, followed by an opening order of the same size when flipping.
+    fn orders(&self, forecast: &FundingForecast, action: FundingAction) -> Vec<ClientOrderRequest> {
+        let Some(&sz_decimals) = self.sz_decimals.get(&forecast.coin) else {
+            warn!("No size decimals for {}, not acting", forecast.coin);
+            return Vec::new();
+        };
+        let is_buy = forecast.szi < 0.0;
+        let bps = if is_buy { 1.0 } else { -1.0 } * self.config.slippage_bps;
+        let px = apply_bps(forecast.mark_px, bps);
+        let tick = price_tick_size(px, sz_decimals, false);
+        let order = |reduce_only| ClientOrderRequest {
+            asset: forecast.coin.clone(),
+            is_buy,
+            reduce_only,
+            limit_px: round_to_tick(px, tick, RoundingMode::Nearest),
+            sz: forecast.szi.abs(),
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        };
+        match action {
+            FundingAction::Flatten => vec![order(true)],
+            FundingAction::Flip => vec![order(true), order(false)],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    #[tokio::test]
+    async fn test_forecast_and_flip_before_funding() {
+        let predicted: Vec<(String, VenueFundings)> = serde_json::from_str(
+            r#"[
+                ["ETH",[["BinPerp",{"fundingRate":"0.0008","nextFundingTime":1,"fundingIntervalHours":8}],["HlPerp",{"fundingRate":"0.0001","nextFundingTime":3600000,"fundingIntervalHours":1}]]],
+                ["BTC",[["HlPerp",{"fundingRate":"-0.0001","nextFundingTime":3600000}]]]
+            ]"#,
+        )
+        .unwrap();
+        let state: UserStateResponse = serde_json::from_str(
+            r#"{"assetPositions":[
+                {"type":"oneWay","position":{"coin":"ETH","entryPx":"2000","leverage":{"type":"cross","value":5},"liquidationPx":null,"marginUsed":"400","positionValue":"2000","returnOnEquity":"0","szi":"1","unrealizedPnl":"0","maxLeverage":25,"cumFunding":{"allTime":"0","sinceOpen":"0","sinceChange":"0"}}},
+                {"type":"oneWay","position":{"coin":"BTC","entryPx":"50000","leverage":{"type":"cross","value":5},"liquidationPx":null,"marginUsed":"100","positionValue":"500","returnOnEquity":"0","szi":"0.01","unrealizedPnl":"0","maxLeverage":25,"cumFunding":{"allTime":"0","sinceOpen":"0","sinceChange":"0"}}}
+            ],
+            "crossMarginSummary":{"accountValue":"1000","totalMarginUsed":"500","totalNtlPos":"2500","totalRawUsd":"3000"},
+            "marginSummary":{"accountValue":"1000","totalMarginUsed":"500","totalNtlPos":"2500","totalRawUsd":"3000"},
+            "withdrawable":"500"}"#,
+        )
+        .unwrap();
+        let mids = HashMap::from([("ETH".to_string(), "2000".to_string())]);
+
+        let forecasts = forecast_funding(&predicted, &state, &mids);
+        assert_eq!(forecasts.len(), 2);
+        assert_eq!(forecasts[0].coin, "ETH");
+        assert!((forecasts[0].payment + 0.2).abs() < EPSILON);
+        // BTC has no mid and is valued at its position value
+        assert!((forecasts[1].payment - 0.05).abs() < EPSILON);
+        assert_eq!(forecasts[0].payments_until(3 * HOUR_MS).len(), 3);
+
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        exchange.handle_message(
+            &serde_json::from_str(
+                r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+            )
+            .unwrap(),
+        );
+        exchange
+            .order(ClientOrderRequest {
+                asset: "ETH".to_string(),
+                is_buy: true,
+                reduce_only: false,
+                limit_px: 2001.0,
+                sz: 1.0,
+                cloid: None,
+                order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+            })
+            .await
+            .unwrap();
+
+        let meta: Meta = serde_json::from_str(
+            r#"{"universe":[{"name":"ETH","szDecimals":2,"maxLeverage":25},{"name":"BTC","szDecimals":5,"maxLeverage":40}]}"#,
+        )
+        .unwrap();
+        let mut guard = FundingGuard::new(
+            Address::ZERO,
+            FundingGuardConfig {
+                lead_time: Duration::from_secs(60),
+                min_cost: 0.1,
+                action: FundingAction::Flip,
+                slippage_bps: 50.0,
+            },
+        )
+        .with_meta(&meta);
+
+        let early = guard.apply(&exchange, &forecasts, 0).await.unwrap();
+        assert!(early.is_empty());
+        let acted = guard
+            .apply(&exchange, &forecasts, HOUR_MS - 30_000)
+            .await
+            .unwrap();
+        assert_eq!(acted.len(), 1);
+        assert_eq!(acted[0].coin, "ETH");
+        assert!((exchange.position("ETH").unwrap().szi + 1.0).abs() < EPSILON);
+
+        let again = guard
+            .apply(&exchange, &forecasts, HOUR_MS - 10_000)
+            .await
+            .unwrap();
+        assert!(again.is_empty());
+    }
+}
@@ -0,0 +1,465 @@
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::persist::{load_json, save_json};
+use crate::{
+    lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Error, Exchange, InfoClient, Message, OrderManager, OrderState,
+    RoundingMode, Strategy, Subscription, Tif, EPSILON,
+};
+
+/// What a grid does when the price leaves its range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridRebalance {
+    /// Keep the grid in place until the price comes back
+    #[default]
+    Hold,
+    /// Cancel and rebuild the grid, keeping its width, centred on the price
+    Recenter,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridConfig {
+    pub coin: String,
+    pub sz_decimals: u32,
+    pub lower_px: f64,
+    pub upper_px: f64,
+    /// Number of price levels, including both ends of the range
+    pub levels: usize,
+    pub size_per_level: f64,
+    /// Spaces levels by a constant ratio instead of a constant difference
+    #[serde(default)]
+    pub geometric: bool,
+    #[serde(default)]
+    pub rebalance: GridRebalance,
+}
+
+impl GridConfig {
+    fn validate(&self) -> Result<()> {
+        if !(self.lower_px > 0.0 && self.upper_px > self.lower_px) {
+            return Err(Error::InvalidConfig(format!(
+                "Grid range {}..{} is empty",
+                self.lower_px, self.upper_px
+            )));
+        }
+        if self.levels < 2 || self.size_per_level <= 0.0 {
+            return Err(Error::InvalidConfig(
+                "Grid needs at least 2 levels and a positive size".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn prices(&self) -> Vec<f64> {
+        let steps = (self.levels - 1) as f64;
+        (0..self.levels)
+            .map(|i| {
+                let px = if self.geometric {
+                    self.lower_px * (self.upper_px / self.lower_px).powf(i as f64 / steps)
+                } else {
+                    self.lower_px + (self.upper_px - self.lower_px) * i as f64 / steps
+                };
+                let tick = price_tick_size(px, self.sz_decimals, false);
+                round_to_tick(px, tick, RoundingMode::Nearest)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridLevel {
+    pub px: f64,
+    /// Side of the order at this level, `None` if it has none
+    pub is_buy: Option<bool>,
+    pub cloid: Option<Uuid>,
+}
+
+/// Everything needed to resume a grid, saved after every change when a state file is set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridState {
+    /// Range as currently placed, moved by `GridRebalance::Recenter`
+    pub config: GridConfig,
+    /// Lowest price first, empty until the grid is placed
+    pub levels: Vec<GridLevel>,
+    /// Net size bought by the grid
+    pub position: f64,
+    pub buys_filled: u64,
+    pub sells_filled: u64,
+}
+
+/// Grid strategy on one coin built on `OrderManager`.
+///
+/// Buys rest at every level below the price and sells at every level above it, the level
+/// nearest the price left empty. When a buy fills a sell is placed one level up, and when a
+/// sell fills a buy is placed one level down. Levels whose orders are canceled or rejected by
+/// the exchange are left empty.
+///
+/// With `with_state_file`, the state is written before orders are sent, so after a restart
+/// `load` and `resume` pick up the orders from the previous run instead of placing new ones.
+#[derive(Debug)]
+pub struct GridTrader {
+    state: GridState,
+    manager: OrderManager,
+    state_file: Option<PathBuf>,
+}
+
+impl GridTrader {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address, config: GridConfig) -> Result<GridTrader> {
+        config.validate()?;
+        Ok(GridTrader {
+            state: GridState {
+                config,
+                levels: Vec::new(),
+                position: 0.0,
+                buys_filled: 0,
+                sells_filled: 0,
+            },
+            manager: OrderManager::new(user),
+            state_file: None,
+        })
+    }
+
+    /// Loads a grid saved by a previous run, saving to the same file. Call `resume` before
+    /// passing it messages.
+    pub fn load(user: Address, path: impl AsRef<Path>) -> Result<GridTrader> {
+        let path = path.as_ref();
+        let state: GridState = load_json(path)?;
+        state.config.validate()?;
+        Ok(GridTrader {
+            state,
+            manager: OrderManager::new(user),
+            state_file: Some(path.to_path_buf()),
+        })
+    }
+
+    pub fn with_state_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn state(&self) -> &GridState {
+        &self.state
+    }
+
+    pub fn order_manager(&self) -> &OrderManager {
+        &self.manager
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = self.manager.subscriptions();
+        subscriptions.push(Subscription::AllMids);
+        subscriptions
+    }
+
+    /// Resolves the orders of a loaded grid against the exchange, applying fills missed while
+    /// stopped and re-placing only orders that never reached the book.
+    pub async fn resume<E: Exchange>(&mut self, exchange: &E, info: &InfoClient) -> Result<()> {
+        let requests: Vec<ClientOrderRequest> = self
+            .state
+            .levels
+            .iter()
+            .filter_map(|level| Some(self.request(level.px, level.is_buy?, level.cloid?)))
+            .collect();
+        for request in &requests {
+            self.manager.adopt(request)?;
+        }
+        self.manager.reconcile(info).await?;
+
+        let mut missing = Vec::new();
+        for (index, level) in self.state.levels.iter_mut().enumerate() {
+            let Some(cloid) = level.cloid else {
+                continue;
+            };
+            let state = self.manager.order(cloid).map(|order| &order.state);
+            if let Some(OrderState::Canceled | OrderState::Rejected(_)) = state {
+                level.cloid = None;
+                missing.push(index);
+            }
+        }
+        self.manager.remove_done();
+        info!("Resuming grid, re-placing {} orders", missing.len());
+        self.place_levels(exchange, missing).await?;
+        self.sync(exchange).await
+    }
+
+    /// Cancels every grid order and clears the levels, so the next price places a new grid.
+    pub async fn stop<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let cloids: Vec<Uuid> = self.live_cloids();
+        if !cloids.is_empty() {
+            for status in self.manager.cancel(exchange, cloids).await? {
+                if let Err(err) = status.status {
+                    warn!("Could not cancel grid order {}: {err}", status.request);
+                }
+            }
+        }
+        self.state.levels.clear();
+        self.save()
+    }
+
+    fn live_cloids(&self) -> Vec<Uuid> {
+        self.state
+            .levels
+            .iter()
+            .filter_map(|level| level.cloid)
+            .filter(|cloid| {
+                self.manager
+                    .order(*cloid)
+                    .is_some_and(|order| !order.state.is_done())
+            })
+            .collect()
+    }
+
+    async fn start<E: Exchange>(&mut self, exchange: &E, mid: f64) -> Result<()> {
+        let prices = self.state.config.prices();
+        let nearest = prices
+            .iter()
+            .enumerate()
+            .min_by(|a, b| (a.1 - mid).abs().total_cmp(&(b.1 - mid).abs()))
+            .map(|(i, _)| i)
+            .unwrap_or_default();
+        self.state.levels = prices
+            .into_iter()
+            .enumerate()
+            .map(|(i, px)| GridLevel {
+                px,
+                is_buy: (i != nearest).then_some(i < nearest),
+                cloid: None,
+            })
+            .collect();
+        info!(
+            "Placing grid on {} from {} to {} around {mid}",
+            self.state.config.coin, self.state.config.lower_px, self.state.config.upper_px
+        );
+        let indices = (0..self.state.levels.len())
+            .filter(|&i| i != nearest)
+            .collect();
+        self.place_levels(exchange, indices).await
+    }
+
+    async fn recenter<E: Exchange>(&mut self, exchange: &E, mid: f64) -> Result<()> {
+        self.stop(exchange).await?;
+        let config = &mut self.state.config;
+        if config.geometric {
+            let ratio = mid / (config.lower_px * config.upper_px).sqrt();
+            config.lower_px *= ratio;
+            config.upper_px *= ratio;
+        } else {
+            let shift = mid - (config.lower_px + config.upper_px) / 2.0;
+            config.lower_px += shift;
+            config.upper_px += shift;
+        }
+        self.start(exchange, mid).await
+    }
+
+    /// Places orders at `indices`, whose side must be set, saving their cloids first.
+    async fn place_levels<E: Exchange>(&mut self, exchange: &E, indices: Vec<usize>) -> Result<()> {
+        let mut requests = Vec::with_capacity(indices.len());
+        for index in indices {
+            let level = &mut self.state.levels[index];
+            let Some(is_buy) = level.is_buy else {
+                continue;
+            };
+            let cloid = Uuid::new_v4();
+            level.cloid = Some(cloid);
+            let px = level.px;
+            requests.push(self.request(px, is_buy, cloid));
+        }
+        if requests.is_empty() {
+            return Ok(());
+        }
+        self.save()?;
+        self.manager.place(exchange, requests).await?;
+        Ok(())
+    }
+
+    /// Moves filled orders to the neighbouring level and clears levels whose orders ended
+    /// otherwise.
+    async fn sync<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let mut to_place = Vec::new();
+        let mut changed = false;
+        for index in 0..self.state.levels.len() {
+            let level = &self.state.levels[index];
+            let (Some(cloid), Some(is_buy)) = (level.cloid, level.is_buy) else {
+                continue;
+            };
+            let Some(order) = self.manager.order(cloid) else {
+                continue;
+            };
+            match &order.state {
+                OrderState::Filled => {
+                    let sz = order.filled_sz;
+                    if is_buy {
+                        self.state.position += sz;
+                        self.state.buys_filled += 1;
+                    } else {
+                        self.state.position -= sz;
+                        self.state.sells_filled += 1;
+                    }
+                    let next = if is_buy {
+                        Some(index + 1)
+                    } else {
+                        index.checked_sub(1)
+                    };
+                    if let Some(next) = next.filter(|&next| next < self.state.levels.len()) {
+                        let next_level = &mut self.state.levels[next];
+                        if next_level.cloid.is_none() {
+                            next_level.is_buy = Some(!is_buy);
+                            to_place.push(next);
+                        }
+                    }
+                }
+                OrderState::Canceled => warn!("Grid order at {} was canceled", level.px),
+                OrderState::Rejected(reason) => {
+                    warn!("Grid order at {} was rejected: {reason}", level.px)
+                }
+                _ => continue,
+            }
+            let level = &mut self.state.levels[index];
+            level.cloid = None;
+            level.is_buy = None;
+            changed = true;
+        }
+        self.manager.remove_done();
+        if !to_place.is_empty() {
+            self.place_levels(exchange, to_place).await
+        } else if changed {
+            self.save()
+        } else {
+            Ok(())
+        }
+    }
+
+    fn request(&self, px: f64, is_buy: bool, cloid: Uuid) -> ClientOrderRequest {
+        let config = &self.state.config;
+        let lot = lot_size(config.sz_decimals);
+        ClientOrderRequest {
+            asset: config.coin.clone(),
+            is_buy,
+            reduce_only: false,
+            limit_px: px,
+            sz: round_to_tick(config.size_per_level, lot, RoundingMode::Down),
+            cloid: Some(cloid),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        match &self.state_file {
+            Some(path) => save_json(path, &self.state),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Strategy for GridTrader {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        let coin = &self.state.config.coin;
+        let mid = match message {
+            Message::AllMids(all_mids) => all_mids
+                .data
+                .mids
+                .get(coin)
+                .and_then(|mid| mid.parse::<f64>().ok()),
+            Message::L2Book(book) if &book.data.coin == coin => {
+                let best = |side: usize| -> Option<f64> {
+                    book.data.levels.get(side)?.first()?.px.parse().ok()
+                };
+                best(0).zip(best(1)).map(|(bid, ask)| (bid + ask) / 2.0)
+            }
+            _ => None,
+        };
+        self.manager.handle_message(message);
+
+        if let Some(mid) = mid {
+            let config = &self.state.config;
+            let out_of_range = mid < config.lower_px - EPSILON || mid > config.upper_px + EPSILON;
+            if self.state.levels.is_empty() {
+                return self.start(exchange, mid).await;
+            }
+            if out_of_range && config.rebalance == GridRebalance::Recenter {
+                return self.recenter(exchange, mid).await;
+            }
+        }
+        self.sync(exchange).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange, Side};
+
+    fn message(json: &str) -> Message {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_grid_flips_fills_and_saves_state() {
+        let config: GridConfig = serde_json::from_str(
+            r#"{"coin":"ETH","sz_decimals":3,"lower_px":1900,"upper_px":2100,"levels":5,"size_per_level":0.5}"#,
+        )
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("grid_{}.json", std::process::id()));
+        let mut grid = GridTrader::new(Address::ZERO, config)
+            .unwrap()
+            .with_state_file(&path);
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+
+        let book = message(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+        );
+        exchange.handle_message(&book);
+        grid.on_message(&book, &exchange).await.unwrap();
+        let mut prices: Vec<String> = exchange
+            .open_orders()
+            .into_iter()
+            .map(|o| o.limit_px)
+            .collect();
+        prices.sort();
+        assert_eq!(prices, vec!["1900", "1950", "2050", "2100"]);
+
+        // The buy at 1950 fills and is replaced by a sell one level up
+        exchange.handle_message(&message(
+            r#"{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"1950","sz":"1","time":2,"hash":"0x0","tid":1,"users":["0x0","0x0"]}]}"#,
+        ));
+        while let Ok(event) = receiver.try_recv() {
+            grid.on_message(&event, &exchange).await.unwrap();
+        }
+        let sells: Vec<String> = exchange
+            .open_orders()
+            .into_iter()
+            .filter(|o| o.side == Side::Sell)
+            .map(|o| o.limit_px)
+            .collect();
+        assert!(sells.contains(&"2000".to_string()));
+        assert_eq!(grid.state().position, 0.5);
+        assert_eq!(grid.state().buys_filled, 1);
+
+        let loaded = GridTrader::load(Address::ZERO, &path).unwrap();
+        let live = |state: &GridState| {
+            state
+                .levels
+                .iter()
+                .map(|level| (level.is_buy, level.cloid))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(live(loaded.state()), live(grid.state()));
+        std::fs::remove_file(path).unwrap();
+    }
+}
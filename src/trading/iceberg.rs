@@ -0,0 +1,282 @@
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    lot_size, prelude::*, round_to_tick, ClientLimit, ClientOrder, ClientOrderRequest, Error,
+    Exchange, ManagedOrder, Message, OrderManager, OrderState, RoundingMode, Strategy,
+    Subscription, Tif, EPSILON,
+};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IcebergConfig {
+    pub coin: String,
+    pub sz_decimals: u32,
+    pub is_buy: bool,
+    /// Total size to execute
+    pub sz: f64,
+    pub limit_px: f64,
+    /// Size shown at the book at a time
+    pub clip_sz: f64,
+    /// Clips are drawn uniformly within this fraction of `clip_sz`, e.g. 0.2 for ±20%
+    #[serde(default)]
+    pub clip_variance: f64,
+    /// Places clips as `Alo` so they never take liquidity
+    #[serde(default)]
+    pub post_only: bool,
+    #[serde(default)]
+    pub reduce_only: bool,
+}
+
+/// Works a large limit order by showing one clip at a time, posting the next clip from the
+/// `orderUpdates` and `userFills` stream once the current one has filled.
+///
+/// A clip canceled or rejected by the exchange stops the order rather than being re-posted.
+#[derive(Debug)]
+pub struct IcebergOrder {
+    config: IcebergConfig,
+    manager: OrderManager,
+    rng: u64,
+    clip: Option<Uuid>,
+    filled_sz: f64,
+    notional: f64,
+    clips: usize,
+    done: bool,
+}
+
+impl IcebergOrder {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address, config: IcebergConfig) -> Result<IcebergOrder> {
+        if config.sz <= 0.0 || config.clip_sz <= 0.0 || !(0.0..1.0).contains(&config.clip_variance)
+        {
+            return Err(Error::InvalidConfig(
+                "Iceberg needs positive sizes and a clip variance below 1".to_string(),
+            ));
+        }
+        Ok(IcebergOrder {
+            config,
+            manager: OrderManager::new(user),
+            rng: Uuid::new_v4().as_u64_pair().0,
+            clip: None,
+            filled_sz: 0.0,
+            notional: 0.0,
+            clips: 0,
+            done: false,
+        })
+    }
+
+    /// Seeds the clip size randomization, making clip sizes reproducible.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = seed;
+        self
+    }
+
+    pub fn config(&self) -> &IcebergConfig {
+        &self.config
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        self.manager.subscriptions()
+    }
+
+    pub fn filled_sz(&self) -> f64 {
+        self.filled_sz + self.clip().map_or(0.0, |clip| clip.filled_sz)
+    }
+
+    /// Size-weighted average fill price, zero until the first fill.
+    pub fn avg_px(&self) -> f64 {
+        let clip = self.clip();
+        let sz = self.filled_sz();
+        let notional = self.notional + clip.map_or(0.0, |clip| clip.filled_sz * clip.avg_fill_px);
+        if sz > 0.0 {
+            notional / sz
+        } else {
+            0.0
+        }
+    }
+
+    pub fn remaining_sz(&self) -> f64 {
+        (self.config.sz - self.filled_sz()).max(0.0)
+    }
+
+    /// Number of clips posted so far.
+    pub fn clips(&self) -> usize {
+        self.clips
+    }
+
+    /// The clip currently at the book.
+    pub fn clip(&self) -> Option<&ManagedOrder> {
+        self.clip.and_then(|cloid| self.manager.order(cloid))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Posts the next clip if none is working. Called by `on_message`, so it is only needed
+    /// to show the first clip before any message arrives.
+    pub async fn post<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        if let Some(clip) = self.clip() {
+            let (state, sz, px) = (clip.state.clone(), clip.filled_sz, clip.avg_fill_px);
+            match state {
+                OrderState::Filled => {}
+                OrderState::Canceled => {
+                    warn!(
+                        "Iceberg clip on {} was canceled, stopping",
+                        self.config.coin
+                    );
+                    self.done = true;
+                }
+                OrderState::Rejected(reason) => {
+                    warn!(
+                        "Iceberg clip on {} was rejected: {reason}",
+                        self.config.coin
+                    );
+                    self.done = true;
+                }
+                _ => return Ok(()),
+            }
+            self.filled_sz += sz;
+            self.notional += sz * px;
+            self.clip = None;
+            self.manager.remove_done();
+        }
+
+        let lot = lot_size(self.config.sz_decimals);
+        let remaining_sz = self.remaining_sz();
+        if self.done || remaining_sz < lot - EPSILON {
+            if !self.done {
+                info!(
+                    "Iceberg on {} done, filled {} at {}",
+                    self.config.coin,
+                    self.filled_sz(),
+                    self.avg_px()
+                );
+            }
+            self.done = true;
+            return Ok(());
+        }
+
+        let variance = self.config.clip_variance * (2.0 * self.next_unit() - 1.0);
+        let clip_sz = round_to_tick(
+            self.config.clip_sz * (1.0 + variance),
+            lot,
+            RoundingMode::Down,
+        )
+        .max(lot);
+        let sz = round_to_tick(clip_sz.min(remaining_sz), lot, RoundingMode::Down);
+        let tif = if self.config.post_only {
+            Tif::Alo
+        } else {
+            Tif::Gtc
+        };
+        let order = ClientOrderRequest {
+            asset: self.config.coin.clone(),
+            is_buy: self.config.is_buy,
+            reduce_only: self.config.reduce_only,
+            limit_px: self.config.limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif }),
+        };
+        self.clips += 1;
+        let statuses = self.manager.place(exchange, vec![order]).await?;
+        self.clip = statuses.first().map(|status| status.request);
+        Ok(())
+    }
+
+    /// Cancels the working clip and stops posting new ones.
+    pub async fn cancel<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        self.done = true;
+        let Some(clip) = self.clip().filter(|clip| !clip.state.is_done()) else {
+            return Ok(());
+        };
+        let cloid = clip.cloid;
+        for status in self.manager.cancel(exchange, vec![cloid]).await? {
+            if let Err(err) = status.status {
+                warn!("Could not cancel iceberg clip {cloid}: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Uniform draw in `[0, 1)` from a splitmix64 sequence.
+    fn next_unit(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+impl Strategy for IcebergOrder {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        self.manager.handle_message(message);
+        if self.done {
+            return Ok(());
+        }
+        self.post(exchange).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    #[tokio::test]
+    async fn test_iceberg_reposts_clips_until_filled() {
+        let config: IcebergConfig = serde_json::from_str(
+            r#"{"coin":"ETH","sz_decimals":2,"is_buy":true,"sz":1,"limit_px":2000,"clip_sz":0.3,"clip_variance":0.2}"#,
+        )
+        .unwrap();
+        let mut iceberg = IcebergOrder::new(Address::ZERO, config)
+            .unwrap()
+            .with_seed(7);
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&book);
+        iceberg.post(&exchange).await.unwrap();
+
+        let trade: Message = serde_json::from_str(
+            r#"{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"2000","sz":"10","time":2,"hash":"0x0","tid":1,"users":["0x0","0x0"]}]}"#,
+        )
+        .unwrap();
+        for _ in 0..10 {
+            let open = exchange.open_orders();
+            assert_eq!(open.len(), 1);
+            let sz: f64 = open[0].sz.parse().unwrap();
+            assert!(sz <= 0.36 + EPSILON);
+            assert!(sz >= 0.24 - EPSILON || (iceberg.remaining_sz() - sz).abs() < EPSILON);
+
+            exchange.handle_message(&trade);
+            while let Ok(event) = receiver.try_recv() {
+                iceberg.on_message(&event, &exchange).await.unwrap();
+            }
+            if iceberg.is_done() {
+                break;
+            }
+        }
+        assert!(iceberg.is_done());
+        assert!((iceberg.filled_sz() - 1.0).abs() < EPSILON);
+        assert!(iceberg.clips() >= 3);
+        assert_eq!(iceberg.avg_px(), 2000.0);
+        assert!(exchange.open_orders().is_empty());
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{lot_size, round_to_tick, RoundingMode};
+
+/// How much `QuoteJitter` varies quotes. Everything defaults to no variation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JitterConfig {
+    /// Largest fraction a displayed size is reduced by, below 1
+    #[serde(default)]
+    pub size_variance: f64,
+    /// Largest number of ticks a quote is moved away from its target price
+    #[serde(default)]
+    pub price_ticks: u32,
+    /// Largest fraction a refresh interval is shortened or lengthened by, below 1
+    #[serde(default)]
+    pub timing_variance: f64,
+}
+
+/// Randomizes displayed sizes, quote prices and refresh timing so quotes are harder to
+/// recognise and anticipate.
+///
+/// Sizes are only reduced and prices only moved away from the other side, so a jittered quote
+/// never exceeds its size limit or tightens the spread, and both stay on the lot and tick
+/// grids. Seed it with `with_seed` for reproducible draws.
+#[derive(Clone, Debug)]
+pub struct QuoteJitter {
+    config: JitterConfig,
+    rng: u64,
+}
+
+impl QuoteJitter {
+    pub fn new(config: JitterConfig) -> QuoteJitter {
+        QuoteJitter {
+            config,
+            rng: RandomState::new().build_hasher().finish(),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = seed;
+        self
+    }
+
+    pub fn config(&self) -> &JitterConfig {
+        &self.config
+    }
+
+    /// `sz` reduced by up to `size_variance`, rounded down to `sz_decimals` but never below
+    /// one lot.
+    pub fn size(&mut self, sz: f64, sz_decimals: u32) -> f64 {
+        let variance = self.config.size_variance.clamp(0.0, 1.0);
+        if variance == 0.0 {
+            return sz;
+        }
+        let lot = lot_size(sz_decimals);
+        let jittered = sz * (1.0 - variance * self.next_unit());
+        round_to_tick(jittered, lot, RoundingMode::Down).max(lot.min(sz))
+    }
+
+    /// `px` moved away from the spread by up to `price_ticks` whole ticks: down for a bid,
+    /// up for an ask. A bid stays at least one tick.
+    pub fn price(&mut self, px: f64, tick: f64, is_buy: bool) -> f64 {
+        if self.config.price_ticks == 0 {
+            return px;
+        }
+        let ticks = (self.next_unit() * (self.config.price_ticks + 1) as f64).floor();
+        let offset = ticks * tick;
+        let jittered = if is_buy {
+            (px - offset).max(tick)
+        } else {
+            px + offset
+        };
+        round_to_tick(jittered, tick, RoundingMode::Nearest)
+    }
+
+    /// `interval` shortened or lengthened by up to `timing_variance`.
+    pub fn delay(&mut self, interval: Duration) -> Duration {
+        let variance = self.config.timing_variance.clamp(0.0, 1.0);
+        if variance == 0.0 {
+            return interval;
+        }
+        interval.mul_f64(1.0 + variance * (2.0 * self.next_unit() - 1.0))
+    }
+
+    /// Uniform draw in `[0, 1)` from a splitmix64 sequence.
+    fn next_unit(&mut self) -> f64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_is_bounded_and_reproducible() {
+        let config = JitterConfig {
+            size_variance: 0.3,
+            price_ticks: 2,
+            timing_variance: 0.5,
+        };
+        let mut jitter = QuoteJitter::new(config).with_seed(42);
+        let mut sizes = Vec::new();
+        for _ in 0..200 {
+            let sz = jitter.size(1.0, 2);
+            assert!((0.7 - 1e-9..=1.0).contains(&sz));
+            assert!((sz * 100.0 - (sz * 100.0).round()).abs() < 1e-9);
+            sizes.push(sz);
+
+            let bid = jitter.price(100.0, 0.5, true);
+            let ask = jitter.price(101.0, 0.5, false);
+            assert!((99.0..=100.0).contains(&bid) && (bid * 2.0).fract() == 0.0);
+            assert!((101.0..=102.0).contains(&ask) && (ask * 2.0).fract() == 0.0);
+
+            let delay = jitter.delay(Duration::from_secs(10));
+            assert!((Duration::from_secs(5)..=Duration::from_secs(15)).contains(&delay));
+        }
+        assert!(sizes.iter().any(|&sz| sz < 0.9));
+
+        let mut replay = QuoteJitter::new(config).with_seed(42);
+        assert_eq!(replay.size(1.0, 2), sizes[0]);
+        // Without variance values pass through
+        let mut none = QuoteJitter::new(JitterConfig::default());
+        assert_eq!(none.size(1.234, 2), 1.234);
+        assert_eq!(none.price(100.0, 0.5, true), 100.0);
+    }
+}
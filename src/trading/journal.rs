@@ -0,0 +1,597 @@
+use std::{path::Path, sync::Mutex};
+
+use alloy::primitives::{Address, B256};
+
+use rusqlite::{params, Connection, ToSql};
+use serde::Serialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    helpers::now_timestamp_ms, prelude::*, ClientOrder, ClientOrderRequest, Error,
+    ExchangeResponseStatus, ManagedOrder, OrderEvent, OrderState, OrderTag, TradeInfo,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS events (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    time INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    cloid TEXT,
+    oid INTEGER,
+    detail TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS orders (
+    cloid TEXT PRIMARY KEY,
+    oid INTEGER,
+    coin TEXT NOT NULL,
+    is_buy INTEGER NOT NULL,
+    limit_px REAL NOT NULL,
+    sz REAL NOT NULL,
+    filled_sz REAL NOT NULL,
+    avg_fill_px REAL NOT NULL,
+    state TEXT NOT NULL,
+    reason TEXT,
+    done INTEGER NOT NULL,
+    updated INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS fills (
+    tid INTEGER PRIMARY KEY,
+    cloid TEXT NOT NULL,
+    oid INTEGER NOT NULL,
+    time INTEGER NOT NULL,
+    coin TEXT NOT NULL,
+    side TEXT NOT NULL,
+    px TEXT NOT NULL,
+    sz TEXT NOT NULL,
+    fee TEXT NOT NULL
+);
+";
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Journal(err.to_string())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalKind {
+    /// An order submitted to the exchange
+    Order,
+    /// A cancel submitted to the exchange
+    Cancel,
+    Ack,
+    Fill,
+    PartiallyFilled,
+    /// The order left the book, filled or canceled
+    Done,
+    Rejected,
+    /// An action sent by an `ExchangeClient`, with the hash it signed
+    Action,
+}
+
+impl JournalKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            JournalKind::Order => "order",
+            JournalKind::Cancel => "cancel",
+            JournalKind::Ack => "ack",
+            JournalKind::Fill => "fill",
+            JournalKind::PartiallyFilled => "partially_filled",
+            JournalKind::Done => "done",
+            JournalKind::Rejected => "rejected",
+            JournalKind::Action => "action",
+        }
+    }
+
+    fn parse(kind: &str) -> Option<JournalKind> {
+        [
+            JournalKind::Order,
+            JournalKind::Cancel,
+            JournalKind::Ack,
+            JournalKind::Fill,
+            JournalKind::PartiallyFilled,
+            JournalKind::Done,
+            JournalKind::Rejected,
+            JournalKind::Action,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.as_str() == kind)
+    }
+}
+
+/// One journaled action or lifecycle change, in the order it happened.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub seq: i64,
+    /// Local time of recording in milliseconds
+    pub time: u64,
+    pub kind: JournalKind,
+    pub cloid: Option<Uuid>,
+    pub oid: Option<u64>,
+    /// JSON with the order's parameters, the fill, or the terminal state
+    pub detail: String,
+}
+
+/// Not yet terminal order read back from the journal.
+#[derive(Debug)]
+pub(crate) struct JournaledOrder {
+    pub(crate) cloid: Uuid,
+    pub(crate) oid: Option<u64>,
+    pub(crate) coin: String,
+    pub(crate) is_buy: bool,
+    pub(crate) limit_px: f64,
+    pub(crate) sz: f64,
+    pub(crate) filled_sz: f64,
+    pub(crate) avg_fill_px: f64,
+    pub(crate) state: OrderState,
+    /// Trade ids of the fills already applied
+    pub(crate) tids: Vec<u64>,
+}
+
+/// Local SQLite journal of every order and cancel an `OrderManager` submits and every ack,
+/// fill and terminal state it sees.
+///
+/// Attach it with `OrderManager::with_journal`. After a crash, `OrderManager::restore` brings
+/// back the orders that were still open, which `reconcile` then checks against the exchange.
+/// `entries` reads the log back for audits. Attached to an `ExchangeClient` with
+/// `ExchangeClient::with_journal`, it also logs every action sent with the hash it signed.
+#[derive(Debug)]
+pub struct Journal {
+    conn: Mutex<Connection>,
+}
+
+impl Journal {
+    /// Opens or creates the journal at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Journal> {
+        Journal::with_connection(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> Result<Journal> {
+        Journal::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Journal> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Journal {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().expect("journal lock poisoned")
+    }
+
+    /// Entries recorded at or after `since`, in milliseconds.
+    pub fn entries(&self, since: u64) -> Result<Vec<JournalEntry>> {
+        self.query_entries("WHERE time >= ?1", since as i64)
+    }
+
+    /// Entries of one order, from submission on.
+    pub fn entries_for(&self, cloid: Uuid) -> Result<Vec<JournalEntry>> {
+        self.query_entries("WHERE cloid = ?1", cloid.to_string())
+    }
+
+    /// Entries of every order whose cloid carries `tag`.
+    pub fn entries_for_tag(&self, tag: OrderTag) -> Result<Vec<JournalEntry>> {
+        self.query_entries(
+            "WHERE cloid LIKE ?1",
+            format!("{}%", tag.hyphenated_prefix()),
+        )
+    }
+
+    /// Actions logged by an `ExchangeClient` with `hash`, the connection id of L1 actions or the
+    /// EIP-712 signing hash of user-signed ones.
+    pub fn entries_for_action(&self, hash: B256) -> Result<Vec<JournalEntry>> {
+        self.query_entries(
+            "WHERE kind = 'action' AND json_extract(detail, '$.hash') = ?1",
+            hash.to_string(),
+        )
+    }
+
+    fn query_entries(&self, filter: &str, value: impl ToSql) -> Result<Vec<JournalEntry>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(&format!(
+            "SELECT seq, time, kind, cloid, oid, detail FROM events {filter} ORDER BY seq"
+        ))?;
+        let rows = statement.query_map([value], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+                row.get::<_, String>(5)?,
+            ))
+        })?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (seq, time, kind, cloid, oid, detail) = row?;
+            entries.push(JournalEntry {
+                seq,
+                time: time as u64,
+                kind: JournalKind::parse(&kind)
+                    .ok_or_else(|| Error::Journal(format!("unknown entry kind {kind}")))?,
+                cloid: cloid.and_then(|cloid| Uuid::try_parse(&cloid).ok()),
+                oid: oid.map(|oid| oid as u64),
+                detail,
+            });
+        }
+        Ok(entries)
+    }
+
+    pub(crate) fn open_orders(&self) -> Result<Vec<JournaledOrder>> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT cloid, oid, coin, is_buy, limit_px, sz, filled_sz, avg_fill_px, state
+             FROM orders WHERE done = 0",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, bool>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, f64>(5)?,
+                row.get::<_, f64>(6)?,
+                row.get::<_, f64>(7)?,
+                row.get::<_, String>(8)?,
+            ))
+        })?;
+        let mut tids = conn.prepare("SELECT tid FROM fills WHERE cloid = ?1")?;
+        let mut orders = Vec::new();
+        for row in rows {
+            let (cloid, oid, coin, is_buy, limit_px, sz, filled_sz, avg_fill_px, state) = row?;
+            let tids = tids
+                .query_map([&cloid], |row| row.get::<_, i64>(0))?
+                .map(|tid| tid.map(|tid| tid as u64))
+                .collect::<rusqlite::Result<Vec<u64>>>()?;
+            orders.push(JournaledOrder {
+                cloid: Uuid::try_parse(&cloid).map_err(|e| Error::Journal(e.to_string()))?,
+                oid: oid.map(|oid| oid as u64),
+                coin,
+                is_buy,
+                limit_px,
+                sz,
+                filled_sz,
+                avg_fill_px,
+                state: match state.as_str() {
+                    "resting" => OrderState::Resting,
+                    "partially_filled" => OrderState::PartiallyFilled,
+                    _ => OrderState::Pending,
+                },
+                tids,
+            });
+        }
+        Ok(orders)
+    }
+
+    pub(crate) fn record_order(
+        &self,
+        order: &ClientOrderRequest,
+        managed: &ManagedOrder,
+    ) -> Result<()> {
+        let order_type = match &order.order_type {
+            ClientOrder::Limit(limit) => json!({ "limit": { "tif": limit.tif } }),
+            ClientOrder::Trigger(trigger) => json!({
+                "trigger": {
+                    "isMarket": trigger.is_market,
+                    "triggerPx": trigger.trigger_px,
+                    "tpsl": trigger.tpsl,
+                }
+            }),
+        };
+        let detail = json!({
+            "coin": order.asset,
+            "isBuy": order.is_buy,
+            "limitPx": order.limit_px,
+            "sz": order.sz,
+            "reduceOnly": order.reduce_only,
+            "orderType": order_type,
+        });
+        self.record(JournalKind::Order, managed, detail)
+    }
+
+    pub(crate) fn record_cancel(&self, managed: &ManagedOrder) -> Result<()> {
+        self.record(JournalKind::Cancel, managed, json!({}))
+    }
+
+    pub(crate) fn record_event(&self, event: &OrderEvent, managed: &ManagedOrder) -> Result<()> {
+        let (kind, detail) = match event {
+            OrderEvent::Acked { .. } => (JournalKind::Ack, json!({})),
+            OrderEvent::PartiallyFilled {
+                px,
+                sz,
+                remaining_sz,
+                ..
+            } => (
+                JournalKind::PartiallyFilled,
+                json!({ "px": px, "sz": sz, "remainingSz": remaining_sz }),
+            ),
+            OrderEvent::Done { state, .. } => (
+                JournalKind::Done,
+                json!({ "state": state_str(state), "filledSz": managed.filled_sz }),
+            ),
+            OrderEvent::Rejected { reason, .. } => {
+                (JournalKind::Rejected, json!({ "reason": reason }))
+            }
+        };
+        self.record(kind, managed, detail)
+    }
+
+    pub(crate) fn record_fill(&self, fill: &TradeInfo, managed: &ManagedOrder) -> Result<()> {
+        let conn = self.conn();
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO fills (tid, cloid, oid, time, coin, side, px, sz, fee)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                fill.tid as i64,
+                managed.cloid.to_string(),
+                fill.oid as i64,
+                fill.time.as_millis() as i64,
+                fill.coin,
+                fill.side.as_str(),
+                fill.px,
+                fill.sz,
+                fill.fee,
+            ],
+        )?;
+        drop(conn);
+        if inserted == 0 {
+            return Ok(());
+        }
+        let detail = json!({
+            "tid": fill.tid,
+            "time": fill.time,
+            "px": fill.px,
+            "sz": fill.sz,
+            "fee": fill.fee,
+            "feeToken": fill.fee_token,
+        });
+        self.record(JournalKind::Fill, managed, detail)
+    }
+
+    pub(crate) fn record_action(
+        &self,
+        action_type: &str,
+        nonce: u64,
+        hash: B256,
+        signer: Address,
+        vault_address: Option<Address>,
+        result: &Result<ExchangeResponseStatus>,
+    ) -> Result<()> {
+        let status = match result {
+            Ok(ExchangeResponseStatus::Ok(_)) => json!("ok"),
+            Ok(ExchangeResponseStatus::Err(err)) => json!({ "rejected": err.to_string() }),
+            Err(err) => json!({ "error": err.to_string() }),
+        };
+        let detail = json!({
+            "type": action_type,
+            "nonce": nonce,
+            "hash": hash.to_string(),
+            "signer": signer,
+            "vaultAddress": vault_address,
+            "status": status,
+        });
+        self.conn().execute(
+            "INSERT INTO events (time, kind, detail) VALUES (?1, ?2, ?3)",
+            params![
+                now_timestamp_ms() as i64,
+                JournalKind::Action.as_str(),
+                detail.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Appends an entry and writes the order's current state, in one transaction.
+    fn record(
+        &self,
+        kind: JournalKind,
+        managed: &ManagedOrder,
+        detail: serde_json::Value,
+    ) -> Result<()> {
+        let now = now_timestamp_ms() as i64;
+        let cloid = managed.cloid.to_string();
+        let oid = managed.oid.map(|oid| oid as i64);
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO events (time, kind, cloid, oid, detail) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![now, kind.as_str(), cloid, oid, detail.to_string()],
+        )?;
+        let reason = match &managed.state {
+            OrderState::Rejected(reason) => Some(reason.as_str()),
+            _ => None,
+        };
+        tx.execute(
+            "INSERT INTO orders
+                (cloid, oid, coin, is_buy, limit_px, sz, filled_sz, avg_fill_px, state, reason,
+                 done, updated)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+             ON CONFLICT(cloid) DO UPDATE SET
+                oid = excluded.oid, filled_sz = excluded.filled_sz,
+                avg_fill_px = excluded.avg_fill_px, state = excluded.state,
+                reason = excluded.reason, done = excluded.done, updated = excluded.updated",
+            params![
+                cloid,
+                oid,
+                managed.coin,
+                managed.is_buy,
+                managed.limit_px,
+                managed.sz,
+                managed.filled_sz,
+                managed.avg_fill_px,
+                state_str(&managed.state),
+                reason,
+                managed.state.is_done(),
+                now,
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+fn state_str(state: &OrderState) -> &'static str {
+    match state {
+        OrderState::Pending => "pending",
+        OrderState::Resting => "resting",
+        OrderState::PartiallyFilled => "partially_filled",
+        OrderState::Filled => "filled",
+        OrderState::Canceled => "canceled",
+        OrderState::Rejected(_) => "rejected",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use alloy::signers::local::PrivateKeySigner;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{
+        BaseUrl, ClientLimit, ExchangeClient, LatencyHook, Message, OrderManager, PaperConfig,
+        PaperExchange, SpotMeta, Tif,
+    };
+
+    #[tokio::test]
+    async fn test_journal_records_and_restores() {
+        let path = std::env::temp_dir().join(format!("journal_{}.sqlite", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        exchange.handle_message(
+            &serde_json::from_str::<Message>(
+                r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+            )
+            .unwrap(),
+        );
+
+        let cloid = {
+            let journal = std::sync::Arc::new(Journal::open(&path).unwrap());
+            let mut manager = OrderManager::new(Address::ZERO).with_journal(journal.clone());
+            let statuses = manager
+                .place(
+                    &exchange,
+                    vec![ClientOrderRequest {
+                        asset: "ETH".to_string(),
+                        is_buy: true,
+                        reduce_only: false,
+                        limit_px: 1999.0,
+                        sz: 2.0,
+                        cloid: Some(OrderTag(3).new_cloid()),
+                        order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+                    }],
+                )
+                .await
+                .unwrap();
+            exchange.handle_message(
+                &serde_json::from_str::<Message>(
+                    r#"{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"1999","sz":"0.5","time":2,"hash":"0x0","tid":1,"users":["0x0","0x0"]}]}"#,
+                )
+                .unwrap(),
+            );
+            while let Ok(message) = receiver.try_recv() {
+                manager.handle_message(&message);
+                // Replayed messages are not journaled twice
+                manager.handle_message(&message);
+            }
+            statuses[0].request
+        };
+
+        // As if the process restarted
+        let journal = Journal::open(&path).unwrap();
+        let kinds: Vec<JournalKind> = journal
+            .entries_for(cloid)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                JournalKind::Order,
+                JournalKind::Ack,
+                JournalKind::Fill,
+                JournalKind::PartiallyFilled
+            ]
+        );
+        assert_eq!(journal.entries_for_tag(OrderTag(3)).unwrap().len(), 4);
+        assert!(journal.entries_for_tag(OrderTag(4)).unwrap().is_empty());
+        let mut manager = OrderManager::new(Address::ZERO);
+        assert_eq!(manager.restore(&journal).unwrap(), 1);
+        let order = manager.order(cloid).unwrap();
+        assert_eq!(order.state, OrderState::PartiallyFilled);
+        assert!((order.filled_sz - 0.5).abs() < crate::EPSILON);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_logs_actions_with_hash() {
+        let journal = Arc::new(Journal::in_memory().unwrap());
+        let hashes = Arc::new(Mutex::new(Vec::new()));
+        let hook = {
+            let hashes = hashes.clone();
+            LatencyHook::new(move |latency| hashes.lock().unwrap().push(latency.hash))
+        };
+        let wallet: PrivateKeySigner =
+            "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .unwrap();
+        // Dry run, so nothing is sent to the unreachable API
+        let exchange = ExchangeClient::builder()
+            .base_url(BaseUrl::custom("http://127.0.0.1:9"))
+            .meta(
+                serde_json::from_str(
+                    r#"{"universe":[{"name":"ETH","szDecimals":4,"maxLeverage":50}]}"#,
+                )
+                .unwrap(),
+            )
+            .spot_meta(SpotMeta {
+                universe: vec![],
+                tokens: vec![],
+            })
+            .wallet(wallet.clone())
+            .dry_run(true)
+            .latency_hook(hook)
+            .journal(journal.clone())
+            .build()
+            .await
+            .unwrap();
+        exchange
+            .order(
+                ClientOrderRequest {
+                    asset: "ETH".to_string(),
+                    is_buy: true,
+                    reduce_only: false,
+                    limit_px: 2000.0,
+                    sz: 0.1,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let hash = hashes.lock().unwrap()[0];
+        let entries = journal.entries_for_action(hash).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, JournalKind::Action);
+        let detail: serde_json::Value = serde_json::from_str(&entries[0].detail).unwrap();
+        assert_eq!(detail["type"], "order");
+        assert_eq!(detail["status"], "ok");
+        assert_eq!(detail["signer"], json!(wallet.address()));
+        assert!(journal.entries_for_action(B256::ZERO).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(feature = "exchange")]
+mod account_router;
+#[cfg(feature = "exchange")]
+mod allocator;
+#[cfg(feature = "exchange")]
+mod copy_trade;
+#[cfg(feature = "exchange")]
+mod delta_hedge;
+#[cfg(feature = "exchange")]
+mod dust;
+#[cfg(feature = "exchange")]
+mod execution;
+#[cfg(feature = "exchange")]
+mod execution_report;
+#[cfg(feature = "exchange")]
+mod funding_arb;
+#[cfg(feature = "exchange")]
+mod funding_guard;
+#[cfg(feature = "exchange")]
+mod grid;
+#[cfg(feature = "exchange")]
+mod iceberg;
+mod jitter;
+#[cfg(feature = "journal")]
+mod journal;
+#[cfg(feature = "exchange")]
+mod multi_market_maker;
+#[cfg(feature = "exchange")]
+mod oco;
+#[cfg(feature = "exchange")]
+mod order_manager;
+#[cfg(feature = "exchange")]
+pub(crate) mod persist;
+mod position_tracker;
+#[cfg(feature = "exchange")]
+mod quote_sync;
+#[cfg(feature = "exchange")]
+mod quoting;
+#[cfg(feature = "exchange")]
+mod rebalance;
+#[cfg(feature = "exchange")]
+mod reconcile;
+#[cfg(feature = "exchange")]
+mod recurring;
+#[cfg(feature = "exchange")]
+mod runtime;
+#[cfg(feature = "exchange")]
+mod self_trade;
+#[cfg(feature = "exchange")]
+mod shadow;
+#[cfg(feature = "exchange")]
+mod strategy;
+#[cfg(feature = "exchange")]
+mod trailing_stop;
+#[cfg(feature = "exchange")]
+mod two_leg;
+#[cfg(feature = "exchange")]
+mod vault;
+
+#[cfg(feature = "exchange")]
+pub use account_router::{
+    AccountRouter, ChildAllocation, RoutePlan, RoutedAccount, RoutedChild, RoutedOrder,
+};
+#[cfg(feature = "exchange")]
+pub use allocator::{AllocationPlan, AllocationTransfer, SubAccountAllocator, SubAccountBalance};
+#[cfg(feature = "exchange")]
+pub use copy_trade::{CopyTradeConfig, CopyTrader};
+#[cfg(feature = "exchange")]
+pub use delta_hedge::{DeltaHedgeConfig, DeltaHedger};
+#[cfg(feature = "exchange")]
+pub use dust::{DustBalance, DustConversion, DustSweep, DustSweepConfig};
+#[cfg(feature = "exchange")]
+pub use execution::{
+    ChildOrderStyle, ExecutionAlgo, ExecutionConfig, ExecutionProgress, ExecutionSchedule,
+};
+#[cfg(feature = "exchange")]
+pub use execution_report::ExecutionReport;
+#[cfg(feature = "exchange")]
+pub use funding_arb::{
+    funding_carry, CarryOptions, DeltaNeutralConfig, DeltaNeutralExecutor, FundingCarry,
+    VenueFunding,
+};
+#[cfg(feature = "exchange")]
+pub use funding_guard::{
+    forecast_funding, FundingAction, FundingForecast, FundingGuard, FundingGuardConfig,
+};
+#[cfg(feature = "exchange")]
+pub use grid::{GridConfig, GridLevel, GridRebalance, GridState, GridTrader};
+#[cfg(feature = "exchange")]
+pub use iceberg::{IcebergConfig, IcebergOrder};
+pub use jitter::{JitterConfig, QuoteJitter};
+#[cfg(feature = "journal")]
+pub use journal::{Journal, JournalEntry, JournalKind};
+#[cfg(feature = "exchange")]
+pub use multi_market_maker::{CoinQuoteConfig, MultiMarketMaker, MultiMarketMakerConfig, Quote};
+#[cfg(feature = "exchange")]
+pub use oco::{OcoLeg, OcoManager, OcoPair, OcoState};
+#[cfg(feature = "exchange")]
+pub use order_manager::{
+    ManagedOrder, OrderEvent, OrderManager, OrderState, QueuePosition, SubmitOnce, SubmitOutcome,
+};
+pub use position_tracker::{Position, PositionDrift, PositionSnapshot, PositionTracker};
+#[cfg(feature = "exchange")]
+pub use quote_sync::{
+    QuoteLevel, QuotePlan, QuoteSync, QuoteSyncStatuses, QuoteTarget, RestingQuote,
+};
+#[cfg(feature = "exchange")]
+pub use quoting::{FairValue, LinearSkew, MidFairValue, QuoteSkew, Skew};
+#[cfg(feature = "exchange")]
+pub use rebalance::{RebalanceConfig, RebalanceExecution, RebalancePlan, RebalanceTrade};
+#[cfg(feature = "exchange")]
+pub use reconcile::{reconcile, Discrepancy, ReconcileOptions, ReconcileReport};
+#[cfg(feature = "exchange")]
+pub use recurring::{
+    CatchUp, RecurringAction, RecurringJob, RecurringJobState, RecurringSchedule,
+    RecurringScheduler,
+};
+#[cfg(feature = "exchange")]
+pub use runtime::StrategyRuntime;
+#[cfg(feature = "exchange")]
+pub use self_trade::{OwnRestingOrder, SelfTradeBook, SelfTradePolicy};
+#[cfg(feature = "exchange")]
+pub use shadow::{ExecutionStats, ShadowComparator, ShadowReport};
+#[cfg(feature = "exchange")]
+pub use strategy::{EventStrategy, Strategy, StrategyContext};
+#[cfg(feature = "exchange")]
+pub use trailing_stop::{
+    TrailDistance, TrailPriceSource, TrailingStop, TrailingStopConfig, TrailingStopState,
+};
+#[cfg(feature = "exchange")]
+pub use two_leg::{LegFill, ResidualPolicy, TradeLeg, TwoLegConfig, TwoLegExecutor, TwoLegOutcome};
+#[cfg(feature = "exchange")]
+pub use vault::{FollowerEquity, VaultOperator, VaultState};
@@ -0,0 +1,615 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    future::Future,
+    pin::pin,
+};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tracing::{error, info, warn};
+
+use crate::{
+    apply_bps, exchange::pair_statuses, lot_size, prelude::*, price_tick_size, round_to_tick,
+    ClientCancelRequest, ClientLimit, ClientOrder, ClientOrderRequest, Exchange,
+    ExchangeDataStatus, FairValue, InfoClient, JitterConfig, LinearSkew, Message, MidFairValue,
+    QuoteJitter, QuoteSkew, RoundingMode, Skew, Strategy, Subscription, Tif, TradeInfo, UserData,
+    EPSILON,
+};
+
+/// Quoting parameters of one coin.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoinQuoteConfig {
+    pub coin: String,
+    pub sz_decimals: u32,
+    /// Distance of each quote from the fair price
+    pub half_spread_bps: f64,
+    /// Size quoted on each side, reduced near `max_position`
+    pub order_size: f64,
+    /// Absolute position beyond which the side adding to it stops quoting
+    pub max_position: f64,
+    /// Ticks the target price must move from a resting quote before it is replaced
+    #[serde(default = "default_refresh_ticks")]
+    pub refresh_ticks: u32,
+    /// Shift of both quotes against the position when it is `max_position` away from
+    /// `target_position`, so that inventory is worked off
+    #[serde(default)]
+    pub skew_bps: f64,
+    /// Position the default `LinearSkew` steers towards
+    #[serde(default)]
+    pub target_position: f64,
+}
+
+fn default_refresh_ticks() -> u32 {
+    2
+}
+
+fn default_post_only() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiMarketMakerConfig {
+    pub coins: Vec<CoinQuoteConfig>,
+    /// Places quotes as `Alo` so they never take liquidity
+    #[serde(default = "default_post_only")]
+    pub post_only: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quote {
+    pub oid: u64,
+    pub px: f64,
+    pub sz: f64,
+}
+
+#[derive(Debug)]
+struct CoinState {
+    config: CoinQuoteConfig,
+    position: f64,
+    mid: Option<f64>,
+    bid: Option<Quote>,
+    ask: Option<Quote>,
+}
+
+impl CoinState {
+    fn quote_mut(&mut self, is_buy: bool) -> &mut Option<Quote> {
+        if is_buy {
+            &mut self.bid
+        } else {
+            &mut self.ask
+        }
+    }
+
+    /// Target price and size of the bid and the ask, `None` for a side not to quote.
+    fn targets(&self, fair: f64, skew: Skew) -> [Option<(f64, f64)>; 2] {
+        let config = &self.config;
+        let fair = apply_bps(fair, skew.price_bps);
+        let tick = price_tick_size(fair, config.sz_decimals, false);
+        let bid_px = round_to_tick(
+            apply_bps(fair, -config.half_spread_bps),
+            tick,
+            RoundingMode::Down,
+        );
+        let mut ask_px = round_to_tick(
+            apply_bps(fair, config.half_spread_bps),
+            tick,
+            RoundingMode::Up,
+        );
+        if ask_px <= bid_px {
+            ask_px = bid_px + tick;
+        }
+
+        let lot = lot_size(config.sz_decimals);
+        let size = |room: f64, factor: f64| {
+            let sz = round_to_tick(
+                room.min(config.order_size * factor.max(0.0)).max(0.0),
+                lot,
+                RoundingMode::Down,
+            );
+            (sz > EPSILON).then_some(sz)
+        };
+        [
+            size(config.max_position - self.position, skew.bid_size_factor).map(|sz| (bid_px, sz)),
+            size(config.max_position + self.position, skew.ask_size_factor).map(|sz| (ask_px, sz)),
+        ]
+    }
+
+    /// Whether `quote` is off `target` by more than the refresh threshold, widened by what
+    /// `jitter` may have moved it.
+    fn needs_refresh(
+        &self,
+        quote: Option<Quote>,
+        target: Option<(f64, f64)>,
+        jitter: JitterConfig,
+    ) -> bool {
+        match (quote, target) {
+            (None, None) => false,
+            (Some(quote), Some((px, sz))) => {
+                let tick = price_tick_size(px, self.config.sz_decimals, false);
+                let ticks_away = (quote.px - px).abs() / tick;
+                let refresh_ticks = self.config.refresh_ticks.max(1) + jitter.price_ticks;
+                ticks_away + 1e-6 >= refresh_ticks as f64
+                    || quote.sz > sz + EPSILON
+                    || quote.sz < sz * (1.0 - jitter.size_variance) - EPSILON
+            }
+            _ => true,
+        }
+    }
+}
+
+/// Quotes a two-sided market on several coins around their mids, skewing prices against
+/// inventory and replacing quotes only once the target moves by `refresh_ticks`.
+///
+/// The price quotes are centred on and the inventory skew are pluggable with
+/// `with_fair_value` and `with_skew`, defaulting to `MidFairValue` and `LinearSkew`.
+///
+/// It is a `Strategy`, so it runs live with `run`, on a `PaperExchange` or in a `Backtester`.
+/// Mids come from `allMids` or `l2Book` messages and positions from `userFills`; seed
+/// existing positions with `sync_positions`. All quotes across coins are refreshed with one
+/// bulk cancel and one bulk order per message. With `with_jitter`, new quotes have their
+/// sizes and prices randomized.
+pub struct MultiMarketMaker {
+    user: Address,
+    post_only: bool,
+    coins: BTreeMap<String, CoinState>,
+    seen_fills: HashSet<u64>,
+    fair_value: Box<dyn FairValue + Send>,
+    skew: Box<dyn QuoteSkew + Send>,
+    jitter: Option<QuoteJitter>,
+}
+
+impl std::fmt::Debug for MultiMarketMaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiMarketMaker")
+            .field("user", &self.user)
+            .field("post_only", &self.post_only)
+            .field("coins", &self.coins)
+            .field("jitter", &self.jitter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MultiMarketMaker {
+    pub fn new(user: Address, config: MultiMarketMakerConfig) -> MultiMarketMaker {
+        let coins = config
+            .coins
+            .into_iter()
+            .map(|config| {
+                (
+                    config.coin.clone(),
+                    CoinState {
+                        config,
+                        position: 0.0,
+                        mid: None,
+                        bid: None,
+                        ask: None,
+                    },
+                )
+            })
+            .collect();
+        MultiMarketMaker {
+            user,
+            post_only: config.post_only,
+            coins,
+            seen_fills: HashSet::new(),
+            fair_value: Box::new(MidFairValue),
+            skew: Box::new(LinearSkew),
+            jitter: None,
+        }
+    }
+
+    pub fn with_fair_value(mut self, fair_value: impl FairValue + Send + 'static) -> Self {
+        self.fair_value = Box::new(fair_value);
+        self
+    }
+
+    pub fn with_skew(mut self, skew: impl QuoteSkew + Send + 'static) -> Self {
+        self.skew = Box::new(skew);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: QuoteJitter) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        vec![
+            Subscription::AllMids,
+            Subscription::UserFills { user: self.user },
+            Subscription::OrderUpdates { user: self.user },
+        ]
+    }
+
+    pub fn position(&self, coin: &str) -> Option<f64> {
+        self.coins.get(coin).map(|state| state.position)
+    }
+
+    pub fn set_position(&mut self, coin: &str, szi: f64) {
+        if let Some(state) = self.coins.get_mut(coin) {
+            state.position = szi;
+        }
+    }
+
+    /// Resting bid and ask of `coin`.
+    pub fn quotes(&self, coin: &str) -> Option<(Option<Quote>, Option<Quote>)> {
+        self.coins.get(coin).map(|state| (state.bid, state.ask))
+    }
+
+    /// Sets positions of the quoted coins from the clearinghouse state.
+    pub async fn sync_positions(&mut self, info: &InfoClient) -> Result<()> {
+        let state = info.user_state(self.user).await?;
+        for state in self.coins.values_mut() {
+            state.position = 0.0;
+        }
+        for asset_position in state.asset_positions {
+            let position = asset_position.position;
+            self.set_position(&position.coin, position.szi.parse().unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    /// Quotes on messages from `receiver` until `shutdown` completes or the channel closes,
+    /// then cancels all quotes. Errors placing or cancelling are logged and quoting continues.
+    pub async fn run<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        mut receiver: UnboundedReceiver<Message>,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    if let Err(err) = self.on_message(&message, exchange).await {
+                        error!("Error quoting: {err}");
+                    }
+                }
+            }
+        }
+        self.cancel_all(exchange).await
+    }
+
+    /// Cancels every resting quote.
+    pub async fn cancel_all<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let cancels: Vec<ClientCancelRequest> = self
+            .coins
+            .iter()
+            .flat_map(|(coin, state)| {
+                [state.bid, state.ask]
+                    .into_iter()
+                    .flatten()
+                    .map(|quote| ClientCancelRequest {
+                        asset: coin.clone(),
+                        oid: quote.oid,
+                    })
+            })
+            .collect();
+        if cancels.is_empty() {
+            return Ok(());
+        }
+        let response = exchange.bulk_cancel(cancels.clone()).await?;
+        for status in pair_statuses(cancels, response) {
+            if let Err(err) = status.status {
+                warn!("Could not cancel quote {}: {err}", status.request.oid);
+            }
+        }
+        for state in self.coins.values_mut() {
+            state.bid = None;
+            state.ask = None;
+        }
+        info!("Cancelled all quotes");
+        Ok(())
+    }
+
+    fn apply_fill(&mut self, fill: &TradeInfo) -> Option<String> {
+        if !self.seen_fills.insert(fill.tid) {
+            return None;
+        }
+        let state = self.coins.get_mut(&fill.coin)?;
+        let sz: f64 = fill.sz.parse().ok()?;
+        let is_buy = fill.side.is_buy();
+        state.position += if is_buy { sz } else { -sz };
+        let quote = state.quote_mut(is_buy);
+        if let Some(resting) = quote.as_mut().filter(|q| q.oid == fill.oid) {
+            resting.sz -= sz;
+            if resting.sz < EPSILON {
+                *quote = None;
+            }
+        }
+        info!(
+            "Fill: {} {sz} {} at {}, position {}",
+            if is_buy { "bought" } else { "sold" },
+            fill.coin,
+            fill.px,
+            state.position
+        );
+        Some(fill.coin.clone())
+    }
+
+    /// Cancels and replaces the quotes of `coins` that are off target.
+    async fn refresh<E: Exchange>(&mut self, coins: Vec<String>, exchange: &E) -> Result<()> {
+        let mut cancels = Vec::new();
+        let mut orders = Vec::new();
+        let jitter = self
+            .jitter
+            .as_ref()
+            .map(|jitter| *jitter.config())
+            .unwrap_or_default();
+        for coin in coins {
+            let Some(state) = self.coins.get(&coin) else {
+                continue;
+            };
+            let Some(mid) = state.mid else {
+                continue;
+            };
+            let targets = match self.fair_value.fair_value(&coin, mid) {
+                Some(fair) => state.targets(fair, self.skew.skew(&state.config, state.position)),
+                None => [None, None],
+            };
+            for (is_buy, quote, target) in [
+                (true, state.bid, targets[0]),
+                (false, state.ask, targets[1]),
+            ] {
+                if !state.needs_refresh(quote, target, jitter) {
+                    continue;
+                }
+                match quote {
+                    Some(quote) => cancels.push((
+                        ClientCancelRequest {
+                            asset: coin.clone(),
+                            oid: quote.oid,
+                        },
+                        is_buy,
+                        target,
+                    )),
+                    None => {
+                        if let Some((px, sz)) = target {
+                            orders.push(self.order(&coin, is_buy, px, sz));
+                        }
+                    }
+                }
+            }
+        }
+
+        if !cancels.is_empty() {
+            let requests: Vec<ClientCancelRequest> = cancels
+                .iter()
+                .map(|(cancel, _, _)| cancel.clone())
+                .collect();
+            let response = exchange.bulk_cancel(requests.clone()).await?;
+            for (status, (cancel, is_buy, target)) in
+                pair_statuses(requests, response).into_iter().zip(cancels)
+            {
+                let Some(state) = self.coins.get_mut(&cancel.asset) else {
+                    continue;
+                };
+                *state.quote_mut(is_buy) = None;
+                match (status.status, target) {
+                    (Ok(_), Some((px, sz))) => {
+                        orders.push(self.order(&cancel.asset, is_buy, px, sz))
+                    }
+                    (Ok(_), None) => {}
+                    // Most likely filled, requote once the fill arrives
+                    (Err(err), _) => warn!("Could not cancel quote {}: {err}", cancel.oid),
+                }
+            }
+        }
+
+        if orders.is_empty() {
+            return Ok(());
+        }
+        if let Some(jitter) = &mut self.jitter {
+            for order in &mut orders {
+                let Some(state) = self.coins.get(&order.asset) else {
+                    continue;
+                };
+                let sz_decimals = state.config.sz_decimals;
+                let tick = price_tick_size(order.limit_px, sz_decimals, false);
+                order.limit_px = jitter.price(order.limit_px, tick, order.is_buy);
+                order.sz = jitter.size(order.sz, sz_decimals);
+            }
+        }
+        let response = exchange.bulk_order(orders.clone()).await?;
+        for status in pair_statuses(orders, response) {
+            let order = status.request;
+            match status.status {
+                Ok(ExchangeDataStatus::Resting(resting)) => {
+                    if let Some(state) = self.coins.get_mut(&order.asset) {
+                        *state.quote_mut(order.is_buy) = Some(Quote {
+                            oid: resting.oid,
+                            px: order.limit_px,
+                            sz: order.sz,
+                        });
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => warn!(
+                    "Could not quote {} {} at {}: {err}",
+                    order.asset,
+                    if order.is_buy { "bid" } else { "ask" },
+                    order.limit_px
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    fn order(&self, coin: &str, is_buy: bool, limit_px: f64, sz: f64) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: coin.to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: if self.post_only { Tif::Alo } else { Tif::Gtc },
+            }),
+        }
+    }
+}
+
+impl Strategy for MultiMarketMaker {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        let mut changed = Vec::new();
+        match message {
+            Message::AllMids(all_mids) => {
+                for (coin, state) in self.coins.iter_mut() {
+                    if let Some(mid) = all_mids.data.mids.get(coin).and_then(|m| m.parse().ok()) {
+                        state.mid = Some(mid);
+                        changed.push(coin.clone());
+                    }
+                }
+            }
+            Message::L2Book(book) => {
+                let best = |side: usize| -> Option<f64> {
+                    book.data.levels.get(side)?.first()?.px.parse().ok()
+                };
+                if let (Some(state), Some(bid), Some(ask)) =
+                    (self.coins.get_mut(&book.data.coin), best(0), best(1))
+                {
+                    state.mid = Some((bid + ask) / 2.0);
+                    changed.push(book.data.coin.clone());
+                }
+            }
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                for fill in &fills.data.fills {
+                    changed.extend(self.apply_fill(fill));
+                }
+            }
+            Message::User(user) => {
+                if let UserData::Fills(fills) = &user.data {
+                    for fill in fills {
+                        changed.extend(self.apply_fill(fill));
+                    }
+                }
+            }
+            Message::OrderUpdates(updates) => {
+                for update in updates.data.iter().filter(|u| u.status != "open") {
+                    let Some(state) = self.coins.get_mut(&update.order.coin) else {
+                        continue;
+                    };
+                    for quote in [&mut state.bid, &mut state.ask] {
+                        if quote.is_some_and(|q| q.oid == update.order.oid) {
+                            *quote = None;
+                            changed.push(update.order.coin.clone());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        changed.sort();
+        changed.dedup();
+        self.refresh(changed, exchange).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    fn book(coin: &str, bid: f64, ask: f64) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"l2Book","data":{{"coin":"{coin}","time":1,"levels":[
+                [{{"px":"{bid}","sz":"100","n":1}}],[{{"px":"{ask}","sz":"100","n":1}}]
+            ]}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_quotes_skew_and_cancel_all() {
+        let config: MultiMarketMakerConfig = serde_json::from_str(
+            r#"{"coins":[
+                {"coin":"ETH","sz_decimals":3,"half_spread_bps":10,"order_size":1,"max_position":2,"skew_bps":20},
+                {"coin":"BTC","sz_decimals":4,"half_spread_bps":5,"order_size":0.1,"max_position":0.1}
+            ]}"#,
+        )
+        .unwrap();
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let mut maker = MultiMarketMaker::new(Address::ZERO, config);
+
+        for message in [
+            book("ETH", 1999.0, 2001.0),
+            book("BTC", 99_990.0, 100_010.0),
+        ] {
+            exchange.handle_message(&message);
+            maker.on_message(&message, &exchange).await.unwrap();
+        }
+        assert_eq!(exchange.open_orders().len(), 4);
+        let (bid, ask) = maker.quotes("ETH").unwrap();
+        assert_eq!((bid.unwrap().px, ask.unwrap().px), (1998.0, 2002.0));
+
+        // A trade through the bid fills it, the maker skews both quotes down
+        let trades: Message = serde_json::from_str(
+            r#"{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"1997","sz":"5","time":2,"hash":"0x0","tid":1,"users":["0x0","0x0"]}]}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&trades);
+        while let Ok(event) = receiver.try_recv() {
+            maker.on_message(&event, &exchange).await.unwrap();
+        }
+        assert_eq!(maker.position("ETH"), Some(1.0));
+        let (bid, ask) = maker.quotes("ETH").unwrap();
+        assert_eq!(bid.unwrap().px, 1996.0);
+        assert_eq!(ask.unwrap().px, 2000.0);
+
+        maker.cancel_all(&exchange).await.unwrap();
+        assert!(exchange.open_orders().is_empty());
+        assert_eq!(maker.quotes("BTC"), Some((None, None)));
+    }
+
+    /// Only widens the ask size, leaving prices alone.
+    struct AskHeavy;
+
+    impl QuoteSkew for AskHeavy {
+        fn skew(&mut self, _config: &CoinQuoteConfig, _position: f64) -> Skew {
+            Skew {
+                ask_size_factor: 2.0,
+                ..Skew::default()
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_fair_value_and_skew() {
+        let config: MultiMarketMakerConfig = serde_json::from_str(
+            r#"{"coins":[{"coin":"ETH","sz_decimals":3,"half_spread_bps":10,"order_size":1,"max_position":5}],"post_only":false}"#,
+        )
+        .unwrap();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        // External reference above the mid; no quotes for other coins
+        let mut maker = MultiMarketMaker::new(Address::ZERO, config)
+            .with_fair_value(|coin: &str, mid: f64| (coin == "ETH").then_some(mid + 1.0))
+            .with_skew(AskHeavy);
+
+        let message = book("ETH", 1999.0, 2001.0);
+        exchange.handle_message(&message);
+        maker.on_message(&message, &exchange).await.unwrap();
+        let (bid, ask) = maker.quotes("ETH").unwrap();
+        let (bid, ask) = (bid.unwrap(), ask.unwrap());
+        assert_eq!((bid.px, bid.sz), (1998.9, 1.0));
+        assert_eq!((ask.px, ask.sz), (2003.1, 2.0));
+    }
+}
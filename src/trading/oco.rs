@@ -0,0 +1,380 @@
+use std::collections::BTreeMap;
+
+use alloy::primitives::Address;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    prelude::*, ClientOrderRequest, Error, Exchange, InfoClient, Message, OrderManager, OrderState,
+    Strategy, Subscription, EPSILON,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OcoLeg {
+    TakeProfit,
+    StopLoss,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OcoState {
+    Working,
+    /// One leg filled and the other was canceled
+    Filled(OcoLeg),
+    /// Both legs filled before the sibling could be canceled
+    BothFilled,
+    /// A leg was canceled or rejected outside the pair, and the sibling canceled with it
+    Broken(String),
+    /// Both legs were canceled through `OcoManager::cancel`
+    Canceled,
+}
+
+#[derive(Clone, Debug)]
+pub struct OcoPair {
+    pub id: Uuid,
+    pub state: OcoState,
+    /// Cloids of the legs, changed when a leg is re-placed at a smaller size
+    pub take_profit: Uuid,
+    pub stop_loss: Uuid,
+    take_profit_request: ClientOrderRequest,
+    stop_loss_request: ClientOrderRequest,
+}
+
+impl OcoPair {
+    fn cloid(&self, leg: OcoLeg) -> Uuid {
+        match leg {
+            OcoLeg::TakeProfit => self.take_profit,
+            OcoLeg::StopLoss => self.stop_loss,
+        }
+    }
+
+    fn request(&self, leg: OcoLeg) -> &ClientOrderRequest {
+        match leg {
+            OcoLeg::TakeProfit => &self.take_profit_request,
+            OcoLeg::StopLoss => &self.stop_loss_request,
+        }
+    }
+
+    fn request_mut(&mut self, leg: OcoLeg) -> (&mut Uuid, &mut ClientOrderRequest) {
+        match leg {
+            OcoLeg::TakeProfit => (&mut self.take_profit, &mut self.take_profit_request),
+            OcoLeg::StopLoss => (&mut self.stop_loss, &mut self.stop_loss_request),
+        }
+    }
+}
+
+/// Client-side one-cancels-other pairs on top of `OrderManager`.
+///
+/// When one leg fills the sibling is canceled. When one leg partially fills, a sibling that
+/// is not reduce-only is re-placed at the filled leg's remaining size, so the pair never
+/// executes more than one leg's size; reduce-only siblings are left as they are. Fills that
+/// race the cancel are reported as `OcoState::BothFilled`. After a reconnect, `reconcile`
+/// catches up on fills the stream missed before siblings are canceled.
+#[derive(Debug)]
+pub struct OcoManager {
+    manager: OrderManager,
+    pairs: BTreeMap<Uuid, OcoPair>,
+}
+
+impl OcoManager {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address) -> OcoManager {
+        OcoManager {
+            manager: OrderManager::new(user),
+            pairs: BTreeMap::new(),
+        }
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        self.manager.subscriptions()
+    }
+
+    pub fn pair(&self, id: Uuid) -> Option<&OcoPair> {
+        self.pairs.get(&id)
+    }
+
+    /// Pairs whose legs are still working.
+    pub fn working(&self) -> Vec<&OcoPair> {
+        self.pairs
+            .values()
+            .filter(|pair| pair.state == OcoState::Working)
+            .collect()
+    }
+
+    pub fn order_manager(&self) -> &OrderManager {
+        &self.manager
+    }
+
+    /// Places both legs in one request and returns the pair id. If either leg is rejected
+    /// the other is canceled and the pair is `Broken`.
+    pub async fn place<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        mut take_profit: ClientOrderRequest,
+        mut stop_loss: ClientOrderRequest,
+    ) -> Result<Uuid> {
+        let tp_cloid = *take_profit.cloid.get_or_insert_with(Uuid::new_v4);
+        let sl_cloid = *stop_loss.cloid.get_or_insert_with(Uuid::new_v4);
+        let id = Uuid::new_v4();
+        self.pairs.insert(
+            id,
+            OcoPair {
+                id,
+                state: OcoState::Working,
+                take_profit: tp_cloid,
+                stop_loss: sl_cloid,
+                take_profit_request: take_profit.clone(),
+                stop_loss_request: stop_loss.clone(),
+            },
+        );
+        if let Err(err) = self
+            .manager
+            .place(exchange, vec![take_profit, stop_loss])
+            .await
+        {
+            self.pairs.remove(&id);
+            return Err(err);
+        }
+        self.sync(exchange).await?;
+        Ok(id)
+    }
+
+    /// Cancels both legs of a working pair.
+    pub async fn cancel<E: Exchange>(&mut self, exchange: &E, id: Uuid) -> Result<()> {
+        let pair = self
+            .pairs
+            .get(&id)
+            .filter(|pair| pair.state == OcoState::Working)
+            .ok_or_else(|| Error::OrderNotFound(id.to_string()))?;
+        let legs = [pair.take_profit, pair.stop_loss];
+        self.cancel_open(exchange, &legs).await?;
+        if let Some(pair) = self.pairs.get_mut(&id) {
+            pair.state = OcoState::Canceled;
+        }
+        self.sync(exchange).await
+    }
+
+    /// Resolves the legs against the exchange, e.g. after a reconnect, then applies the result.
+    pub async fn reconcile<E: Exchange>(&mut self, exchange: &E, info: &InfoClient) -> Result<()> {
+        self.manager.reconcile(info).await?;
+        self.sync(exchange).await
+    }
+
+    /// Removes pairs that are no longer working and have no open leg, returning them.
+    pub fn remove_done(&mut self) -> Vec<OcoPair> {
+        let done: Vec<Uuid> = self
+            .pairs
+            .values()
+            .filter(|pair| {
+                pair.state != OcoState::Working
+                    && [pair.take_profit, pair.stop_loss].iter().all(|cloid| {
+                        self.manager
+                            .order(*cloid)
+                            .is_none_or(|order| order.state.is_done())
+                    })
+            })
+            .map(|pair| pair.id)
+            .collect();
+        self.manager.remove_done();
+        done.iter().filter_map(|id| self.pairs.remove(id)).collect()
+    }
+
+    async fn cancel_open<E: Exchange>(&mut self, exchange: &E, cloids: &[Uuid]) -> Result<()> {
+        let open: Vec<Uuid> = cloids
+            .iter()
+            .copied()
+            .filter(|cloid| {
+                self.manager
+                    .order(*cloid)
+                    .is_some_and(|order| !order.state.is_done())
+            })
+            .collect();
+        if open.is_empty() {
+            return Ok(());
+        }
+        for status in self.manager.cancel(exchange, open).await? {
+            // Usually a fill racing the cancel, which the stream or `reconcile` reports
+            if let Err(err) = status.status {
+                warn!("Could not cancel OCO leg {}: {err}", status.request);
+            }
+        }
+        Ok(())
+    }
+
+    async fn sync<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let ids: Vec<Uuid> = self.working().iter().map(|pair| pair.id).collect();
+        for id in ids {
+            self.sync_pair(exchange, id).await?;
+        }
+        Ok(())
+    }
+
+    async fn sync_pair<E: Exchange>(&mut self, exchange: &E, id: Uuid) -> Result<()> {
+        let pair = &self.pairs[&id];
+        let order = |leg| self.manager.order(pair.cloid(leg));
+        let (Some(tp), Some(sl)) = (order(OcoLeg::TakeProfit), order(OcoLeg::StopLoss)) else {
+            return Ok(());
+        };
+
+        let state = match (&tp.state, &sl.state) {
+            (OrderState::Filled, OrderState::Filled) => {
+                warn!("Both legs of OCO pair {id} filled");
+                OcoState::BothFilled
+            }
+            (OrderState::Filled, _) => OcoState::Filled(OcoLeg::TakeProfit),
+            (_, OrderState::Filled) => OcoState::Filled(OcoLeg::StopLoss),
+            (OrderState::Rejected(reason), _) | (_, OrderState::Rejected(reason)) => {
+                OcoState::Broken(reason.clone())
+            }
+            (OrderState::Canceled, _) | (_, OrderState::Canceled) => {
+                OcoState::Broken("Leg canceled".to_string())
+            }
+            _ => {
+                // A partial fill on one leg shrinks the sibling to the leg's remaining size
+                let resize = [(OcoLeg::StopLoss, tp, sl), (OcoLeg::TakeProfit, sl, tp)]
+                    .into_iter()
+                    .find(|(_, filled, sibling)| {
+                        filled.filled_sz > EPSILON
+                            && sibling.filled_sz < EPSILON
+                            && sibling.remaining_sz() > filled.remaining_sz() + EPSILON
+                    })
+                    .map(|(leg, filled, _)| (leg, filled.remaining_sz()));
+                if let Some((leg, sz)) = resize {
+                    self.resize(exchange, id, leg, sz).await?;
+                }
+                return Ok(());
+            }
+        };
+
+        if let OcoState::Filled(leg) = &state {
+            info!("OCO pair {id} filled on {leg:?}, canceling sibling");
+        }
+        let legs = [pair.take_profit, pair.stop_loss];
+        self.cancel_open(exchange, &legs).await?;
+        // A fill reported by the cancel response resolves the race before the state is set
+        if let Some(pair) = self.pairs.get_mut(&id) {
+            pair.state = state;
+        }
+        let both_filled = legs.iter().all(|cloid| {
+            self.manager
+                .order(*cloid)
+                .is_some_and(|o| o.state == OrderState::Filled)
+        });
+        if both_filled {
+            if let Some(pair) = self.pairs.get_mut(&id) {
+                pair.state = OcoState::BothFilled;
+            }
+        }
+        Ok(())
+    }
+
+    async fn resize<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        id: Uuid,
+        leg: OcoLeg,
+        sz: f64,
+    ) -> Result<()> {
+        let pair = &self.pairs[&id];
+        if pair.request(leg).reduce_only {
+            return Ok(());
+        }
+        let old = pair.cloid(leg);
+        let statuses = self.manager.cancel(exchange, vec![old]).await?;
+        if statuses.iter().any(|status| status.status.is_err()) {
+            // The sibling may have filled meanwhile; the stream will tell
+            return Ok(());
+        }
+
+        let Some(pair) = self.pairs.get_mut(&id) else {
+            return Ok(());
+        };
+        let (cloid, request) = pair.request_mut(leg);
+        let new = Uuid::new_v4();
+        *cloid = new;
+        request.cloid = Some(new);
+        request.sz = sz;
+        let request = request.clone();
+        info!("Re-placing {leg:?} leg of OCO pair {id} at {sz}");
+        self.manager.place(exchange, vec![request]).await?;
+        Ok(())
+    }
+}
+
+impl Strategy for OcoManager {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        self.manager.handle_message(message);
+        self.sync(exchange).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{ClientLimit, ClientOrder, PaperConfig, PaperExchange, Tif};
+
+    fn limit(is_buy: bool, px: f64) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px: px,
+            sz: 1.0,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        }
+    }
+
+    fn trade(px: &str, sz: &str, tid: u64) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"trades","data":[{{"coin":"ETH","side":"B","px":"{px}","sz":"{sz}","time":2,"hash":"0x0","tid":{tid},"users":["0x0","0x0"]}}]}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oco_resizes_on_partial_fill_and_cancels_sibling() {
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        exchange.handle_message(
+            &serde_json::from_str(
+                r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+            )
+            .unwrap(),
+        );
+        let mut oco = OcoManager::new(Address::ZERO);
+        let id = oco
+            .place(&exchange, limit(false, 2100.0), limit(true, 1900.0))
+            .await
+            .unwrap();
+        assert_eq!(exchange.open_orders().len(), 2);
+
+        exchange.handle_message(&trade("2100", "0.4", 1));
+        while let Ok(event) = receiver.try_recv() {
+            oco.on_message(&event, &exchange).await.unwrap();
+        }
+        let open = exchange.open_orders();
+        let buy = open.iter().find(|o| o.side.is_buy()).unwrap();
+        assert_eq!(buy.sz, "0.6");
+        assert_eq!(oco.pair(id).unwrap().state, OcoState::Working);
+
+        exchange.handle_message(&trade("2100", "1", 2));
+        while let Ok(event) = receiver.try_recv() {
+            oco.on_message(&event, &exchange).await.unwrap();
+        }
+        assert_eq!(
+            oco.pair(id).unwrap().state,
+            OcoState::Filled(OcoLeg::TakeProfit)
+        );
+        assert!(exchange.open_orders().is_empty());
+        assert_eq!(oco.remove_done().len(), 1);
+    }
+}
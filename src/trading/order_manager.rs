@@ -0,0 +1,1318 @@
+#[cfg(feature = "journal")]
+use std::sync::Arc;
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{instrument, warn};
+use uuid::Uuid;
+
+#[cfg(feature = "journal")]
+use crate::Journal;
+use crate::{
+    exchange::pair_statuses, prelude::*, rt, BulkRequestStatus, ClientCancelRequestCloid,
+    ClientOrderRequest, Error, Exchange, ExchangeDataStatus, ExchangeError, InfoClient, L2BookData,
+    Message, OrderStatusResponse, OrderTag, OrderUpdate, SelfTradeBook, SelfTradePolicy, Side,
+    Subscription, Trade, TradeInfo, EPSILON,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OrderState {
+    /// Submitted but not yet acknowledged by the exchange
+    Pending,
+    Resting,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected(String),
+}
+
+impl OrderState {
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self,
+            OrderState::Filled | OrderState::Canceled | OrderState::Rejected(_)
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ManagedOrder {
+    pub cloid: Uuid,
+    pub oid: Option<u64>,
+    pub coin: String,
+    pub is_buy: bool,
+    pub limit_px: f64,
+    pub sz: f64,
+    pub filled_sz: f64,
+    /// Size-weighted average fill price, zero until the first fill
+    pub avg_fill_px: f64,
+    pub state: OrderState,
+    seen_fills: HashSet<u64>,
+    /// Fill totals were taken from the order response and are replaced by streamed fills
+    rest_fill: bool,
+    queue: Option<QueuePosition>,
+}
+
+impl ManagedOrder {
+    pub fn remaining_sz(&self) -> f64 {
+        (self.sz - self.filled_sz).max(0.0)
+    }
+
+    /// Tag of the order's cloid, see `OrderTag`.
+    pub fn tag(&self) -> Option<OrderTag> {
+        OrderTag::of(&self.cloid)
+    }
+
+    pub(crate) fn has_fill(&self, tid: u64) -> bool {
+        self.seen_fills.contains(&tid)
+    }
+
+    fn add_fill(&mut self, px: f64, sz: f64) {
+        let total = self.filled_sz + sz;
+        if total > 0.0 {
+            self.avg_fill_px = (self.avg_fill_px * self.filled_sz + px * sz) / total;
+        }
+        self.filled_sz = total;
+    }
+
+    pub(crate) fn is_resting(&self) -> bool {
+        self.oid.is_some()
+            && matches!(
+                self.state,
+                OrderState::Resting | OrderState::PartiallyFilled
+            )
+    }
+}
+
+/// Estimated place of a resting order in the queue at its price level.
+///
+/// The estimate starts from the first book after the order is acknowledged, assuming it joined
+/// at the back of the level. Trades at the order's price take from the size ahead of it, and a
+/// level shrinking below that size means orders ahead were canceled. Cancels behind the order
+/// cannot be told apart from those ahead, so the estimate only ever moves forward as far as the
+/// book allows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QueuePosition {
+    /// Size resting ahead of the order at its price
+    pub ahead_sz: f64,
+    /// Total size at the order's price in the last book, the order's own included
+    pub level_sz: f64,
+}
+
+impl QueuePosition {
+    /// Share of the level ahead of the order, from zero at the front to one at the back.
+    pub fn fraction_ahead(&self) -> f64 {
+        if self.level_sz > 0.0 {
+            (self.ahead_sz / self.level_sz).min(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum OrderEvent {
+    Acked {
+        cloid: Uuid,
+        oid: u64,
+    },
+    PartiallyFilled {
+        cloid: Uuid,
+        oid: u64,
+        px: f64,
+        sz: f64,
+        remaining_sz: f64,
+    },
+    /// The order left the book, with `state` either `Filled` or `Canceled`
+    Done {
+        cloid: Uuid,
+        oid: Option<u64>,
+        state: OrderState,
+    },
+    Rejected {
+        cloid: Uuid,
+        reason: String,
+    },
+}
+
+/// How `OrderManager::place_once` retries a submission whose outcome is unknown.
+#[derive(Clone, Debug)]
+pub struct SubmitOnce {
+    /// Submissions made before giving up, including the first
+    pub max_attempts: u32,
+    /// Wait after an ambiguous failure before checking the order status, so a request still
+    /// in flight has landed
+    pub verify_delay: Duration,
+}
+
+impl Default for SubmitOnce {
+    fn default() -> Self {
+        SubmitOnce {
+            max_attempts: 3,
+            verify_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SubmitOutcome {
+    Submitted(BulkRequestStatus<Uuid>),
+    /// An earlier submission of the cloid reached the exchange; `state` is the tracked order's
+    /// after applying its status
+    AlreadyPlaced {
+        cloid: Uuid,
+        state: OrderState,
+    },
+}
+
+/// Submits orders and tracks their lifecycle.
+///
+/// State is driven by the responses to `place` and `cancel`, by `orderUpdates` and `userFills`
+/// messages passed to `handle_message`, and by `reconcile`, which catches up on anything the
+/// stream missed. Only orders placed through the manager are tracked; they are keyed by cloid,
+/// and one is generated for orders without it.
+#[derive(Debug)]
+pub struct OrderManager {
+    user: Address,
+    orders: HashMap<Uuid, ManagedOrder>,
+    oid_to_cloid: HashMap<u64, Uuid>,
+    events: Option<UnboundedSender<OrderEvent>>,
+    self_trade: Option<(SelfTradeBook, SelfTradePolicy)>,
+    #[cfg(feature = "journal")]
+    journal: Option<Arc<Journal>>,
+}
+
+impl OrderManager {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address) -> OrderManager {
+        OrderManager {
+            user,
+            orders: HashMap::new(),
+            oid_to_cloid: HashMap::new(),
+            events: None,
+            self_trade: None,
+            #[cfg(feature = "journal")]
+            journal: None,
+        }
+    }
+
+    pub fn with_events(mut self, sender: UnboundedSender<OrderEvent>) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    /// Publishes resting orders to `book` and checks orders placed against every order in it,
+    /// applying `policy` to those that would cross one. Share `book` between the managers of
+    /// sub-accounts and agents to prevent self-trades between them.
+    pub fn with_self_trade_prevention(
+        mut self,
+        book: SelfTradeBook,
+        policy: SelfTradePolicy,
+    ) -> Self {
+        for order in self.orders.values() {
+            book.update(self.user, order);
+        }
+        self.self_trade = Some((book, policy));
+        self
+    }
+
+    /// Records every order and cancel submitted, and every ack, fill and terminal state, to
+    /// `journal`. Failed writes are logged and do not stop trading.
+    #[cfg(feature = "journal")]
+    pub fn with_journal(mut self, journal: Arc<Journal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Tracks the orders `journal` last saw open, with the fills already applied to them, and
+    /// returns how many. Follow with `reconcile` to catch up on what happened since.
+    #[cfg(feature = "journal")]
+    pub fn restore(&mut self, journal: &Journal) -> Result<usize> {
+        let orders = journal.open_orders()?;
+        let restored = orders.len();
+        for order in orders {
+            if let Some(oid) = order.oid {
+                self.oid_to_cloid.insert(oid, order.cloid);
+            }
+            self.orders.insert(
+                order.cloid,
+                ManagedOrder {
+                    cloid: order.cloid,
+                    oid: order.oid,
+                    coin: order.coin,
+                    is_buy: order.is_buy,
+                    limit_px: order.limit_px,
+                    sz: order.sz,
+                    filled_sz: order.filled_sz,
+                    avg_fill_px: order.avg_fill_px,
+                    state: order.state,
+                    seen_fills: order.tids.into_iter().collect(),
+                    rest_fill: false,
+                    queue: None,
+                },
+            );
+            self.publish(order.cloid);
+        }
+        Ok(restored)
+    }
+
+    pub(crate) fn user(&self) -> Address {
+        self.user
+    }
+
+    /// Subscriptions whose messages should be passed to `handle_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        vec![
+            Subscription::OrderUpdates { user: self.user },
+            Subscription::UserFills { user: self.user },
+        ]
+    }
+
+    /// Orders that have not reached a terminal state.
+    pub fn open_orders(&self) -> Vec<&ManagedOrder> {
+        self.orders
+            .values()
+            .filter(|o| !o.state.is_done())
+            .collect()
+    }
+
+    /// Open orders whose cloid carries `tag`, for one strategy among several sharing the
+    /// manager.
+    pub fn open_orders_tagged(&self, tag: OrderTag) -> Vec<&ManagedOrder> {
+        self.orders
+            .values()
+            .filter(|o| !o.state.is_done() && o.tag() == Some(tag))
+            .collect()
+    }
+
+    pub fn order(&self, cloid: Uuid) -> Option<&ManagedOrder> {
+        self.orders.get(&cloid)
+    }
+
+    pub fn order_by_oid(&self, oid: u64) -> Option<&ManagedOrder> {
+        self.oid_to_cloid
+            .get(&oid)
+            .and_then(|cloid| self.orders.get(cloid))
+    }
+
+    /// Stops tracking orders in a terminal state, returning them.
+    pub fn remove_done(&mut self) -> Vec<ManagedOrder> {
+        let done: Vec<Uuid> = self
+            .orders
+            .values()
+            .filter(|o| o.state.is_done())
+            .map(|o| o.cloid)
+            .collect();
+        let mut removed = Vec::with_capacity(done.len());
+        for cloid in done {
+            if let Some(order) = self.orders.remove(&cloid) {
+                if let Some(oid) = order.oid {
+                    self.oid_to_cloid.remove(&oid);
+                }
+                removed.push(order);
+            }
+        }
+        removed
+    }
+
+    /// Places `orders`, returning each order's cloid with its status. If the request itself
+    /// fails the orders stay `Pending` until `reconcile` or the stream resolves them.
+    ///
+    /// With self-trade prevention, orders rejected by the policy are not sent and get an error
+    /// status, and the crossing resting orders it cancels are canceled first. Resting orders of
+    /// other managers sharing the book can only block an order, whatever the policy.
+    #[instrument(skip_all, fields(user = %self.user, orders = orders.len()))]
+    pub async fn place<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        mut orders: Vec<ClientOrderRequest>,
+    ) -> Result<Vec<BulkRequestStatus<Uuid>>> {
+        let mut cloids = Vec::with_capacity(orders.len());
+        for order in orders.iter_mut() {
+            let cloid = *order.cloid.get_or_insert_with(Uuid::new_v4);
+            cloids.push(cloid);
+            self.track(order, cloid);
+            #[cfg(feature = "journal")]
+            self.journal(cloid, |journal, managed| {
+                journal.record_order(order, managed)
+            });
+        }
+
+        let blocked = self.prevent_self_trades(exchange, &mut orders).await?;
+        let mut sent_cloids = Vec::with_capacity(orders.len());
+        let mut sent = Vec::with_capacity(orders.len());
+        for ((order, &cloid), reason) in orders.into_iter().zip(&cloids).zip(&blocked) {
+            match reason {
+                Some(reason) => self.reject(cloid, reason.clone()),
+                None => {
+                    sent_cloids.push(cloid);
+                    sent.push(order);
+                }
+            }
+        }
+        if sent.is_empty() {
+            return Ok(blocked_statuses(cloids, blocked, Vec::new()));
+        }
+
+        let response = match exchange.bulk_order(sent).await {
+            Ok(response) => response,
+            // Rejected before anything was sent
+            Err(Error::AssetNotFound) => {
+                for cloid in &cloids {
+                    self.orders.remove(cloid);
+                }
+                return Err(Error::AssetNotFound);
+            }
+            Err(err) => return Err(err),
+        };
+        let statuses = pair_statuses(sent_cloids, response);
+        for status in &statuses {
+            self.apply_order_status(status);
+        }
+        Ok(blocked_statuses(cloids, blocked, statuses))
+    }
+
+    /// Applies the self-trade policy to `orders` before they are sent, cancelling the crossing
+    /// resting orders it calls for. Returns why each order that must not be sent was blocked.
+    async fn prevent_self_trades<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        orders: &mut [ClientOrderRequest],
+    ) -> Result<Vec<Option<String>>> {
+        let mut blocked = vec![None; orders.len()];
+        let Some((book, policy)) = self.self_trade.clone() else {
+            return Ok(blocked);
+        };
+        let mut cancels = Vec::new();
+        for (order, blocked) in orders.iter_mut().zip(blocked.iter_mut()) {
+            let crossing = book.crossing(order);
+            if crossing.is_empty() {
+                continue;
+            }
+            // Only orders tracked here can be canceled through `exchange`
+            let foreign = crossing.iter().find(|resting| {
+                resting.user != self.user || !self.orders.contains_key(&resting.cloid)
+            });
+            let resting_sz: f64 = crossing.iter().map(|resting| resting.remaining_sz).sum();
+            let reason = match (policy, foreign) {
+                (SelfTradePolicy::CancelIncoming, _) | (_, Some(_)) => {
+                    let resting = foreign.unwrap_or(&crossing[0]);
+                    Some(format!(
+                        "{} order at {} would trade against resting order {} of {}",
+                        order.asset, order.limit_px, resting.cloid, resting.user
+                    ))
+                }
+                (SelfTradePolicy::Decrement, None) if order.sz <= resting_sz + EPSILON => {
+                    Some(format!(
+                        "{} order of {} is decremented away by {resting_sz} resting",
+                        order.asset, order.sz
+                    ))
+                }
+                (SelfTradePolicy::Decrement, None) => {
+                    order.sz -= resting_sz;
+                    None
+                }
+                (SelfTradePolicy::CancelResting, None) => None,
+            };
+            match reason {
+                Some(reason) => {
+                    warn!(coin = order.asset, %reason, "Blocked self-trade");
+                    *blocked = Some(reason);
+                }
+                None => cancels.extend(crossing.iter().map(|resting| resting.cloid)),
+            }
+        }
+        if !cancels.is_empty() {
+            cancels.sort();
+            cancels.dedup();
+            // Orders that fail to cancel have usually filled, so cannot be traded against
+            self.cancel(exchange, cancels).await?;
+        }
+        Ok(blocked)
+    }
+
+    /// Places `order` at most once, keyed by its cloid. When a submission times out or fails
+    /// with a server error, the order status is checked before retrying, and a later call for a
+    /// cloid already tracked, e.g. restored from the journal by a new process, checks it before
+    /// submitting at all.
+    ///
+    /// The order must have a cloid. Fails with the last error once `config.max_attempts`
+    /// submissions have failed without the order reaching the exchange.
+    #[instrument(skip_all, fields(user = %self.user, cloid = ?order.cloid))]
+    pub async fn place_once<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        info: &InfoClient,
+        order: ClientOrderRequest,
+        config: &SubmitOnce,
+    ) -> Result<SubmitOutcome> {
+        let cloid = order.cloid.ok_or(Error::NoCloid)?;
+        match self.orders.get(&cloid) {
+            Some(managed) if managed.state.is_done() => {
+                return Ok(SubmitOutcome::AlreadyPlaced {
+                    cloid,
+                    state: managed.state.clone(),
+                });
+            }
+            Some(_) => {
+                if let Some(outcome) = self.verify(info, cloid).await? {
+                    return Ok(outcome);
+                }
+            }
+            None => {
+                self.track(&order, cloid);
+                #[cfg(feature = "journal")]
+                self.journal(cloid, |journal, managed| {
+                    journal.record_order(&order, managed)
+                });
+            }
+        }
+
+        let mut attempt = 1;
+        loop {
+            let err = match exchange.bulk_order(vec![order.clone()]).await {
+                Ok(response) => {
+                    let status = pair_statuses(vec![cloid], response)
+                        .pop()
+                        .ok_or_else(|| Error::GenericParse("empty order response".to_string()))?;
+                    self.apply_order_status(&status);
+                    return Ok(SubmitOutcome::Submitted(status));
+                }
+                Err(Error::AssetNotFound) => {
+                    self.orders.remove(&cloid);
+                    return Err(Error::AssetNotFound);
+                }
+                Err(err @ (Error::Timeout(_) | Error::ServerRequest { .. })) => err,
+                Err(err) => return Err(err),
+            };
+            rt::sleep(config.verify_delay).await;
+            if let Some(outcome) = self.verify(info, cloid).await? {
+                return Ok(outcome);
+            }
+            if attempt >= config.max_attempts {
+                return Err(err);
+            }
+            warn!(%cloid, attempt, %err, "Order not found after failed submission, retrying");
+            attempt += 1;
+        }
+    }
+
+    /// Cancels tracked orders by cloid. Orders are marked `Canceled` once the exchange confirms;
+    /// a failed cancel usually means the order already filled, which the stream reports.
+    #[instrument(skip_all, fields(user = %self.user, cloids = ?cloids))]
+    pub async fn cancel<E: Exchange>(
+        &mut self,
+        exchange: &E,
+        cloids: Vec<Uuid>,
+    ) -> Result<Vec<BulkRequestStatus<Uuid>>> {
+        let mut cancels = Vec::with_capacity(cloids.len());
+        for cloid in &cloids {
+            let order = self
+                .orders
+                .get(cloid)
+                .ok_or_else(|| Error::OrderNotFound(cloid.to_string()))?;
+            cancels.push(ClientCancelRequestCloid {
+                asset: order.coin.clone(),
+                cloid: *cloid,
+            });
+            #[cfg(feature = "journal")]
+            self.journal(*cloid, Journal::record_cancel);
+        }
+
+        let response = exchange.bulk_cancel_by_cloid(cancels).await?;
+        let statuses = pair_statuses(cloids, response);
+        for status in &statuses {
+            if status.is_ok() {
+                self.finish(status.request, OrderState::Canceled);
+            }
+        }
+        Ok(statuses)
+    }
+
+    /// Tracks an order submitted earlier, e.g. by a previous run, as `Pending` until
+    /// `reconcile` or the stream resolves it. The order must have a cloid.
+    pub fn adopt(&mut self, order: &ClientOrderRequest) -> Result<Uuid> {
+        let cloid = order.cloid.ok_or(Error::NoCloid)?;
+        if !self.orders.contains_key(&cloid) {
+            self.track(order, cloid);
+        }
+        Ok(cloid)
+    }
+
+    /// Queue position of a resting order, once a book for its coin has been seen since it was
+    /// acknowledged.
+    pub fn queue_position(&self, cloid: Uuid) -> Option<QueuePosition> {
+        self.orders
+            .get(&cloid)
+            .filter(|o| o.is_resting())
+            .and_then(|o| o.queue)
+    }
+
+    /// Applies `orderUpdates` and `userFills` messages, and `l2Book` and `trades` messages for
+    /// queue positions; others are ignored.
+    pub fn handle_message(&mut self, message: &Message) {
+        match message {
+            Message::L2Book(book) => self.apply_book(&book.data),
+            Message::Trades(trades) => {
+                for trade in &trades.data {
+                    self.apply_trade(trade);
+                }
+            }
+            Message::OrderUpdates(updates) => {
+                for update in &updates.data {
+                    self.apply_update(update);
+                }
+            }
+            Message::UserFills(fills) => {
+                for fill in &fills.data.fills {
+                    self.apply_fill(fill);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Brings open orders in line with the exchange: orders no longer resting are resolved
+    /// from their order status, or from historical orders if their oid is unknown.
+    pub async fn reconcile(&mut self, info: &InfoClient) -> Result<()> {
+        let resting = info.open_orders(self.user).await?;
+        let mut resting_cloids = HashSet::new();
+        for open in &resting {
+            let Some(cloid) = self.lookup(open.cloid.as_deref(), open.oid) else {
+                continue;
+            };
+            resting_cloids.insert(cloid);
+            self.acknowledge(cloid, open.oid);
+        }
+
+        let missing: Vec<(Uuid, Option<u64>)> = self
+            .open_orders()
+            .into_iter()
+            .filter(|o| !resting_cloids.contains(&o.cloid))
+            .map(|o| (o.cloid, o.oid))
+            .collect();
+        let mut historical = None;
+        for (cloid, oid) in missing {
+            let status = match oid {
+                Some(oid) => info
+                    .query_order_by_oid(self.user, oid)
+                    .await?
+                    .order
+                    .map(|o| o.status),
+                None => {
+                    if historical.is_none() {
+                        historical = Some(info.historical_orders(self.user).await?);
+                    }
+                    historical.iter().flatten().find_map(|o| {
+                        let found = o.order.cloid.as_deref().and_then(parse_cloid);
+                        if found == Some(cloid) {
+                            self.oid_to_cloid.insert(o.order.oid, cloid);
+                            Some(o.status.clone())
+                        } else {
+                            None
+                        }
+                    })
+                }
+            };
+            match status {
+                Some(status) => self.apply_status(cloid, &status),
+                None => self.reject(cloid, "Order not found on exchange".to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up `cloid` on the exchange, returning `AlreadyPlaced` if it is known there.
+    async fn verify(&mut self, info: &InfoClient, cloid: Uuid) -> Result<Option<SubmitOutcome>> {
+        let response = info.query_order_by_cloid(self.user, cloid).await?;
+        Ok(self.apply_verified(cloid, response))
+    }
+
+    fn apply_verified(
+        &mut self,
+        cloid: Uuid,
+        response: OrderStatusResponse,
+    ) -> Option<SubmitOutcome> {
+        let found = response.order?;
+        self.acknowledge(cloid, found.order.oid);
+        self.apply_status(cloid, &found.status);
+        let state = self.orders.get(&cloid)?.state.clone();
+        Some(SubmitOutcome::AlreadyPlaced { cloid, state })
+    }
+
+    fn track(&mut self, order: &ClientOrderRequest, cloid: Uuid) {
+        self.orders.insert(
+            cloid,
+            ManagedOrder {
+                cloid,
+                oid: None,
+                coin: order.asset.clone(),
+                is_buy: order.is_buy,
+                limit_px: order.limit_px,
+                sz: order.sz,
+                filled_sz: 0.0,
+                avg_fill_px: 0.0,
+                state: OrderState::Pending,
+                seen_fills: HashSet::new(),
+                rest_fill: false,
+                queue: None,
+            },
+        );
+    }
+
+    #[cfg(feature = "journal")]
+    fn journal(&self, cloid: Uuid, record: impl FnOnce(&Journal, &ManagedOrder) -> Result<()>) {
+        let (Some(journal), Some(order)) = (&self.journal, self.orders.get(&cloid)) else {
+            return;
+        };
+        if let Err(err) = record(journal, order) {
+            warn!(%cloid, %err, "Could not write to journal");
+        }
+    }
+
+    fn lookup(&self, cloid: Option<&str>, oid: u64) -> Option<Uuid> {
+        cloid
+            .and_then(parse_cloid)
+            .filter(|cloid| self.orders.contains_key(cloid))
+            .or_else(|| self.oid_to_cloid.get(&oid).copied())
+    }
+
+    /// Publishes the order's current state to the self-trade book.
+    fn publish(&self, cloid: Uuid) {
+        if let (Some((book, _)), Some(order)) = (&self.self_trade, self.orders.get(&cloid)) {
+            book.update(self.user, order);
+        }
+    }
+
+    fn emit(&self, event: OrderEvent) {
+        let cloid = match &event {
+            OrderEvent::Acked { cloid, .. }
+            | OrderEvent::PartiallyFilled { cloid, .. }
+            | OrderEvent::Done { cloid, .. }
+            | OrderEvent::Rejected { cloid, .. } => *cloid,
+        };
+        self.publish(cloid);
+        #[cfg(feature = "journal")]
+        self.journal(cloid, |journal, managed| {
+            journal.record_event(&event, managed)
+        });
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    fn apply_order_status(&mut self, status: &BulkRequestStatus<Uuid>) {
+        let cloid = status.request;
+        match &status.status {
+            Ok(ExchangeDataStatus::Resting(resting)) => self.acknowledge(cloid, resting.oid),
+            Ok(ExchangeDataStatus::Filled(filled)) => {
+                self.acknowledge(cloid, filled.oid);
+                if let Some(order) = self.orders.get_mut(&cloid) {
+                    if order.seen_fills.is_empty() {
+                        order.filled_sz = filled.total_sz.parse().unwrap_or(order.sz);
+                        order.avg_fill_px = filled.avg_px.parse().unwrap_or_default();
+                        order.rest_fill = true;
+                    }
+                }
+                self.finish(cloid, OrderState::Filled);
+            }
+            Ok(_) => {}
+            Err(err) => self.reject(cloid, err.to_string()),
+        }
+    }
+
+    fn apply_update(&mut self, update: &OrderUpdate) {
+        let Some(cloid) = self.lookup(update.order.cloid.as_deref(), update.order.oid) else {
+            return;
+        };
+        match update.status.as_str() {
+            "open" => self.acknowledge(cloid, update.order.oid),
+            status => {
+                self.oid_to_cloid.insert(update.order.oid, cloid);
+                if let Some(order) = self.orders.get_mut(&cloid) {
+                    order.oid = Some(update.order.oid);
+                }
+                self.apply_status(cloid, status);
+            }
+        }
+    }
+
+    /// Applies a terminal order status string such as `filled` or `marginCanceled`.
+    fn apply_status(&mut self, cloid: Uuid, status: &str) {
+        match status {
+            "open" | "triggered" => {}
+            "filled" => self.finish(cloid, OrderState::Filled),
+            "rejected" => self.reject(cloid, status.to_string()),
+            status
+                if status == "canceled"
+                    || status == "scheduledCancel"
+                    || status.ends_with("Canceled") =>
+            {
+                self.finish(cloid, OrderState::Canceled)
+            }
+            status => warn!(%cloid, status, "Unknown order status"),
+        }
+    }
+
+    pub(crate) fn apply_fill(&mut self, fill: &TradeInfo) {
+        let Some(cloid) = self.lookup(fill.cloid.as_deref(), fill.oid) else {
+            return;
+        };
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            warn!(%cloid, tid = fill.tid, "Could not parse fill");
+            return;
+        };
+        let Some(order) = self.orders.get_mut(&cloid) else {
+            return;
+        };
+        if !order.seen_fills.insert(fill.tid) {
+            return;
+        }
+        if order.rest_fill {
+            order.rest_fill = false;
+            order.filled_sz = 0.0;
+            order.avg_fill_px = 0.0;
+        }
+        order.oid = Some(fill.oid);
+        order.add_fill(px, sz);
+        self.oid_to_cloid.insert(fill.oid, cloid);
+        #[cfg(feature = "journal")]
+        self.journal(cloid, |journal, managed| journal.record_fill(fill, managed));
+
+        let order = &self.orders[&cloid];
+        if order.state.is_done() {
+            return;
+        }
+        if order.remaining_sz() < EPSILON {
+            self.finish(cloid, OrderState::Filled);
+        } else {
+            let remaining_sz = order.remaining_sz();
+            if let Some(order) = self.orders.get_mut(&cloid) {
+                order.state = OrderState::PartiallyFilled;
+            }
+            self.emit(OrderEvent::PartiallyFilled {
+                cloid,
+                oid: fill.oid,
+                px,
+                sz,
+                remaining_sz,
+            });
+        }
+    }
+
+    fn apply_book(&mut self, book: &L2BookData) {
+        let levels: Vec<Vec<(f64, f64)>> = book
+            .levels
+            .iter()
+            .map(|side| {
+                side.iter()
+                    .filter_map(|level| Some((level.px.parse().ok()?, level.sz.parse().ok()?)))
+                    .collect()
+            })
+            .collect();
+        for order in self.orders.values_mut() {
+            if order.coin != book.coin || !order.is_resting() {
+                continue;
+            }
+            let Some(side) = levels.get(if order.is_buy { 0 } else { 1 }) else {
+                continue;
+            };
+            let level_sz = match side
+                .iter()
+                .find(|(px, _)| (px - order.limit_px).abs() < EPSILON)
+            {
+                Some(&(_, sz)) => sz,
+                // Levels past the last one shown are unknown, those within it are empty
+                None if side.last().is_some_and(|&(last, _)| {
+                    if order.is_buy {
+                        order.limit_px > last
+                    } else {
+                        order.limit_px < last
+                    }
+                }) =>
+                {
+                    0.0
+                }
+                None => continue,
+            };
+            let behind_own = (level_sz - order.remaining_sz()).max(0.0);
+            let ahead_sz = match order.queue {
+                Some(queue) => queue.ahead_sz.min(behind_own),
+                None => behind_own,
+            };
+            order.queue = Some(QueuePosition { ahead_sz, level_sz });
+        }
+    }
+
+    fn apply_trade(&mut self, trade: &Trade) {
+        let (Ok(px), Ok(sz)) = (trade.px.parse::<f64>(), trade.sz.parse::<f64>()) else {
+            return;
+        };
+        for order in self.orders.values_mut() {
+            // Takers selling trade against resting bids and takers buying against asks
+            if order.coin != trade.coin
+                || !order.is_resting()
+                || order.is_buy != (trade.side == Side::Sell)
+            {
+                continue;
+            }
+            let Some(queue) = order.queue.as_mut() else {
+                continue;
+            };
+            let through = if order.is_buy {
+                px < order.limit_px - EPSILON
+            } else {
+                px > order.limit_px + EPSILON
+            };
+            if through {
+                queue.ahead_sz = 0.0;
+            } else if (px - order.limit_px).abs() < EPSILON {
+                queue.ahead_sz = (queue.ahead_sz - sz).max(0.0);
+            }
+        }
+    }
+
+    fn acknowledge(&mut self, cloid: Uuid, oid: u64) {
+        let Some(order) = self.orders.get_mut(&cloid) else {
+            return;
+        };
+        order.oid = Some(oid);
+        self.oid_to_cloid.insert(oid, cloid);
+        if order.state == OrderState::Pending {
+            order.state = OrderState::Resting;
+            self.emit(OrderEvent::Acked { cloid, oid });
+        }
+    }
+
+    fn finish(&mut self, cloid: Uuid, state: OrderState) {
+        let Some(order) = self.orders.get_mut(&cloid) else {
+            return;
+        };
+        if order.state.is_done() {
+            return;
+        }
+        order.state = state.clone();
+        let oid = order.oid;
+        self.emit(OrderEvent::Done { cloid, oid, state });
+    }
+
+    fn reject(&mut self, cloid: Uuid, reason: String) {
+        let Some(order) = self.orders.get_mut(&cloid) else {
+            return;
+        };
+        if order.state.is_done() {
+            return;
+        }
+        order.state = OrderState::Rejected(reason.clone());
+        self.emit(OrderEvent::Rejected { cloid, reason });
+    }
+}
+
+/// Statuses of all `cloids` in order, from the reasons orders were blocked and the statuses of
+/// those sent.
+fn blocked_statuses(
+    cloids: Vec<Uuid>,
+    blocked: Vec<Option<String>>,
+    sent: Vec<BulkRequestStatus<Uuid>>,
+) -> Vec<BulkRequestStatus<Uuid>> {
+    let mut sent = sent.into_iter();
+    cloids
+        .into_iter()
+        .zip(blocked)
+        .filter_map(|(cloid, reason)| match reason {
+            Some(reason) => Some(BulkRequestStatus {
+                request: cloid,
+                status: Err(ExchangeError::Other(reason)),
+            }),
+            None => sent.next(),
+        })
+        .collect()
+}
+
+/// Parses the `0x`-prefixed hex form cloids take on the wire.
+pub(crate) fn parse_cloid(cloid: &str) -> Option<Uuid> {
+    Uuid::try_parse(cloid.trim_start_matches("0x")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+    use crate::{
+        ClientLimit, ClientOrder, ExchangeResponseStatus, InfoRequest, PaperConfig, PaperExchange,
+        Tif,
+    };
+
+    fn manager_with_order() -> (
+        OrderManager,
+        Uuid,
+        tokio::sync::mpsc::UnboundedReceiver<OrderEvent>,
+    ) {
+        let (sender, receiver) = unbounded_channel();
+        let mut manager = OrderManager::new(Address::ZERO).with_events(sender);
+        let cloid = Uuid::from_u128(0x1e60610f0b3d420597c88c1fed2ad5ee);
+        let order = ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 2000.0,
+            sz: 2.0,
+            cloid: Some(cloid),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+        manager.track(&order, cloid);
+        (manager, cloid, receiver)
+    }
+
+    fn fills_message(fills: &[(u64, &str, &str)]) -> Message {
+        let fills: Vec<String> = fills
+            .iter()
+            .map(|(tid, px, sz)| {
+                format!(
+                    r#"{{"coin":"ETH","side":"B","px":"{px}","sz":"{sz}","time":1,"hash":"0x0",
+                    "startPosition":"0","dir":"Open Long","closedPnl":"0","oid":42,"cloid":null,
+                    "crossed":false,"fee":"0","feeToken":"USDC","tid":{tid}}}"#
+                )
+            })
+            .collect();
+        serde_json::from_str(&format!(
+            r#"{{"channel":"userFills","data":{{"user":"0x0000000000000000000000000000000000000000","fills":[{}]}}}}"#,
+            fills.join(",")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lifecycle_from_response_and_stream() {
+        let (mut manager, cloid, mut events) = manager_with_order();
+        let response: ExchangeResponseStatus = serde_json::from_str(
+            r#"{"status":"ok","response":{"type":"order","data":{"statuses":[{"resting":{"oid":42}}]}}}"#,
+        )
+        .unwrap();
+        for status in pair_statuses(vec![cloid], response) {
+            manager.apply_order_status(&status);
+        }
+        assert!(matches!(
+            events.try_recv(),
+            Ok(OrderEvent::Acked { oid: 42, .. })
+        ));
+
+        // Fills are matched by oid and deduplicated by trade id
+        manager.handle_message(&fills_message(&[(1, "2000", "0.5"), (1, "2000", "0.5")]));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(OrderEvent::PartiallyFilled { remaining_sz, .. }) if (remaining_sz - 1.5).abs() < EPSILON
+        ));
+        assert!(events.try_recv().is_err());
+
+        manager.handle_message(&fills_message(&[(2, "1990", "1.5")]));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(OrderEvent::Done {
+                state: OrderState::Filled,
+                ..
+            })
+        ));
+        let order = manager.order(cloid).unwrap();
+        assert!((order.avg_fill_px - 1992.5).abs() < EPSILON);
+        assert!(manager.open_orders().is_empty());
+        assert_eq!(manager.remove_done().len(), 1);
+        assert!(manager.order_by_oid(42).is_none());
+    }
+
+    #[test]
+    fn test_order_updates_and_rejections() {
+        let (mut manager, cloid, mut events) = manager_with_order();
+        let update: Message = serde_json::from_str(
+            r#"{"channel":"orderUpdates","data":[{"order":{"coin":"ETH","side":"B","limitPx":"2000","sz":"2","oid":7,"timestamp":1,"origSz":"2","cloid":"0x1e60610f0b3d420597c88c1fed2ad5ee"},"status":"marginCanceled","statusTimestamp":2}]}"#,
+        )
+        .unwrap();
+        manager.handle_message(&update);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(OrderEvent::Done {
+                oid: Some(7),
+                state: OrderState::Canceled,
+                ..
+            })
+        ));
+        assert_eq!(manager.order_by_oid(7).unwrap().cloid, cloid);
+
+        let (mut manager, cloid, mut events) = manager_with_order();
+        let response = ExchangeResponseStatus::Err("Insufficient margin to place order.".into());
+        for status in pair_statuses(vec![cloid], response) {
+            manager.apply_order_status(&status);
+        }
+        assert!(matches!(events.try_recv(), Ok(OrderEvent::Rejected { .. })));
+        assert!(matches!(
+            manager.order(cloid).unwrap().state,
+            OrderState::Rejected(_)
+        ));
+    }
+
+    #[test]
+    fn test_verify_submission_by_cloid() {
+        let request = InfoRequest::OrderStatusByCloid {
+            user: Address::ZERO,
+            cloid: crate::helpers::uuid_to_hex_string(Uuid::from_u128(1)),
+        };
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            serde_json::json!({
+                "type": "orderStatus",
+                "user": Address::ZERO,
+                "oid": "0x00000000000000000000000000000001"
+            })
+        );
+
+        let (mut manager, cloid, mut events) = manager_with_order();
+        let unknown: OrderStatusResponse =
+            serde_json::from_str(r#"{"status":"unknownOid"}"#).unwrap();
+        assert!(manager.apply_verified(cloid, unknown).is_none());
+        assert_eq!(manager.order(cloid).unwrap().state, OrderState::Pending);
+
+        let found: OrderStatusResponse = serde_json::from_str(
+            r#"{"status":"order","order":{"order":{"coin":"ETH","side":"B","limitPx":"2000","sz":"2","oid":9,"timestamp":1,"triggerCondition":"N/A","isTrigger":false,"triggerPx":"0.0","isPositionTpsl":false,"reduceOnly":false,"orderType":"Limit","origSz":"2","tif":"Gtc","cloid":"0x1e60610f0b3d420597c88c1fed2ad5ee"},"status":"open","statusTimestamp":2}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            manager.apply_verified(cloid, found),
+            Some(SubmitOutcome::AlreadyPlaced {
+                state: OrderState::Resting,
+                ..
+            })
+        ));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(OrderEvent::Acked { oid: 9, .. })
+        ));
+        assert_eq!(manager.order_by_oid(9).unwrap().cloid, cloid);
+    }
+
+    /// Order entry whose submissions all time out, as when the response is lost.
+    #[derive(Default)]
+    struct TimingOut {
+        attempts: std::sync::atomic::AtomicUsize,
+    }
+
+    impl Exchange for TimingOut {
+        fn address(&self) -> Address {
+            Address::ZERO
+        }
+
+        async fn bulk_order(
+            &self,
+            _orders: Vec<ClientOrderRequest>,
+        ) -> Result<ExchangeResponseStatus> {
+            self.attempts
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Err(Error::Timeout("operation timed out".to_string()))
+        }
+
+        async fn bulk_cancel(
+            &self,
+            _cancels: Vec<crate::ClientCancelRequest>,
+        ) -> Result<ExchangeResponseStatus> {
+            unreachable!()
+        }
+
+        async fn bulk_cancel_by_cloid(
+            &self,
+            _cancels: Vec<ClientCancelRequestCloid>,
+        ) -> Result<ExchangeResponseStatus> {
+            unreachable!()
+        }
+    }
+
+    /// Info API answering every request with `body`.
+    async fn info_answering(body: &'static str) -> InfoClient {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        InfoClient::new(None, Some(crate::BaseUrl::custom(base_url)))
+            .await
+            .unwrap()
+    }
+
+    fn order_with_cloid() -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 2000.0,
+            sz: 2.0,
+            cloid: Some(Uuid::from_u128(0x1e60610f0b3d420597c88c1fed2ad5ee)),
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_place_once_finds_timed_out_order() {
+        let info = info_answering(
+            r#"{"status":"order","order":{"order":{"coin":"ETH","side":"B","limitPx":"2000","sz":"2","oid":9,"timestamp":1,"triggerCondition":"N/A","isTrigger":false,"triggerPx":"0.0","isPositionTpsl":false,"reduceOnly":false,"orderType":"Limit","origSz":"2","tif":"Gtc","cloid":"0x1e60610f0b3d420597c88c1fed2ad5ee"},"status":"open","statusTimestamp":2}}"#,
+        )
+        .await;
+        let exchange = TimingOut::default();
+        let config = SubmitOnce {
+            max_attempts: 3,
+            verify_delay: Duration::ZERO,
+        };
+        let mut manager = OrderManager::new(Address::ZERO);
+        let outcome = manager
+            .place_once(&exchange, &info, order_with_cloid(), &config)
+            .await
+            .unwrap();
+        assert!(matches!(
+            outcome,
+            SubmitOutcome::AlreadyPlaced {
+                state: OrderState::Resting,
+                ..
+            }
+        ));
+        // The order landed, so it is not submitted again
+        assert_eq!(
+            exchange.attempts.load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+        assert_eq!(manager.order_by_oid(9).unwrap().state, OrderState::Resting);
+    }
+
+    #[tokio::test]
+    async fn test_place_once_retries_missing_order() {
+        let info = info_answering(r#"{"status":"unknownOid"}"#).await;
+        let exchange = TimingOut::default();
+        let config = SubmitOnce {
+            max_attempts: 2,
+            verify_delay: Duration::ZERO,
+        };
+        let mut manager = OrderManager::new(Address::ZERO);
+        let res = manager
+            .place_once(&exchange, &info, order_with_cloid(), &config)
+            .await;
+        assert!(matches!(res, Err(Error::Timeout(_))));
+        assert_eq!(
+            exchange.attempts.load(std::sync::atomic::Ordering::Relaxed),
+            2
+        );
+        let cloid = order_with_cloid().cloid.unwrap();
+        assert_eq!(manager.order(cloid).unwrap().state, OrderState::Pending);
+    }
+
+    #[test]
+    fn test_queue_position_from_book_and_trades() {
+        let (mut manager, cloid, _events) = manager_with_order();
+        manager.acknowledge(cloid, 42);
+        assert!(manager.queue_position(cloid).is_none());
+
+        let book = |bid_sz: &str| -> Message {
+            serde_json::from_str(&format!(
+                r#"{{"channel":"l2Book","data":{{"coin":"ETH","time":1,"levels":[[{{"px":"2001","sz":"1","n":1}},{{"px":"2000","sz":"{bid_sz}","n":3}},{{"px":"1999","sz":"4","n":2}}],[{{"px":"2002","sz":"1","n":1}}]]}}}}"#
+            ))
+            .unwrap()
+        };
+        let trades = |side: &str, px: &str, sz: &str| -> Message {
+            serde_json::from_str(&format!(
+                r#"{{"channel":"trades","data":[{{"coin":"ETH","side":"{side}","px":"{px}","sz":"{sz}","time":1,"hash":"0x0","tid":1,"users":["0x0","0x0"]}}]}}"#
+            ))
+            .unwrap()
+        };
+
+        // Our 2 joined behind 5 already resting at 2000
+        manager.handle_message(&book("7"));
+        let queue = manager.queue_position(cloid).unwrap();
+        assert!((queue.ahead_sz - 5.0).abs() < EPSILON);
+        assert!((queue.fraction_ahead() - 5.0 / 7.0).abs() < EPSILON);
+
+        // Sells at our price take from the front, buys and other prices do not
+        manager.handle_message(&trades("A", "2000", "1.5"));
+        manager.handle_message(&trades("B", "2000", "1"));
+        manager.handle_message(&trades("A", "2001", "1"));
+        let queue = manager.queue_position(cloid).unwrap();
+        assert!((queue.ahead_sz - 3.5).abs() < EPSILON);
+
+        // The level shrinking to 4 leaves at most 2 ahead, and growing again adds nothing ahead
+        manager.handle_message(&book("4"));
+        manager.handle_message(&book("9"));
+        let queue = manager.queue_position(cloid).unwrap();
+        assert!((queue.ahead_sz - 2.0).abs() < EPSILON);
+        assert!((queue.level_sz - 9.0).abs() < EPSILON);
+
+        // Trading through the price puts the order at the front
+        manager.handle_message(&trades("A", "1999", "10"));
+        assert_eq!(manager.queue_position(cloid).unwrap().ahead_sz, 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_self_trade_prevention() {
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        exchange.handle_message(
+            &serde_json::from_str::<Message>(
+                r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+            )
+            .unwrap(),
+        );
+        let order = |is_buy: bool, sz: f64| ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px: 2000.0,
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+        };
+        let book = SelfTradeBook::new();
+        let mut manager = OrderManager::new(Address::ZERO)
+            .with_self_trade_prevention(book.clone(), SelfTradePolicy::Decrement);
+        let mut other = OrderManager::new(Address::repeat_byte(1))
+            .with_self_trade_prevention(book.clone(), SelfTradePolicy::CancelResting);
+
+        manager
+            .place(&exchange, vec![order(false, 1.0)])
+            .await
+            .unwrap();
+        assert_eq!(book.len(), 1);
+
+        // Another account's resting order can only block
+        let statuses = other
+            .place(&exchange, vec![order(true, 1.0)])
+            .await
+            .unwrap();
+        assert!(matches!(statuses[0].status, Err(ExchangeError::Other(_))));
+        assert!(matches!(
+            other.order(statuses[0].request).unwrap().state,
+            OrderState::Rejected(_)
+        ));
+
+        // A smaller order is dropped, a larger one cancels the resting order and is reduced
+        let statuses = manager
+            .place(&exchange, vec![order(true, 0.5), order(true, 3.0)])
+            .await
+            .unwrap();
+        assert!(statuses[0].status.is_err());
+        assert!(statuses[1].status.is_ok());
+        let open = exchange.open_orders();
+        assert_eq!(open.len(), 1);
+        assert!(open[0].side == Side::Buy && open[0].sz == "2");
+        assert_eq!(book.crossing(&order(false, 1.0)).len(), 1);
+    }
+}
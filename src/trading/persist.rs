@@ -0,0 +1,19 @@
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{prelude::*, Error};
+
+pub(crate) fn load_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let data = std::fs::read_to_string(path).map_err(|e| Error::Io(e.to_string()))?;
+    serde_json::from_str(&data).map_err(|e| Error::JsonParse(e.to_string()))
+}
+
+/// Writes to a temporary file renamed over `path`, so a crash never leaves a partial file.
+pub(crate) fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let data = serde_json::to_string_pretty(value).map_err(|e| Error::JsonParse(e.to_string()))?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, data)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| Error::Io(e.to_string()))
+}
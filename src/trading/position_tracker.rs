@@ -0,0 +1,323 @@
+use std::collections::{BTreeMap, HashSet};
+
+use alloy::primitives::Address;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    prelude::*, InfoClient, Message, Subscription, TradeInfo, UserData, UserFunding,
+    UserStateResponse, EPSILON,
+};
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Position {
+    pub coin: String,
+    /// Signed size, positive when long
+    pub szi: f64,
+    pub entry_px: f64,
+    /// PnL of closed size, before fees and funding
+    pub realized_pnl: f64,
+    pub fees: f64,
+    /// Funding received, negative when paid
+    pub funding: f64,
+    /// Latest mid, `None` until one is seen
+    pub mark_px: Option<f64>,
+}
+
+impl Position {
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.mark_px
+            .map(|mark_px| self.szi * (mark_px - self.entry_px))
+            .unwrap_or_default()
+    }
+
+    /// Realized and unrealized PnL net of fees and funding.
+    pub fn total_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl() + self.funding - self.fees
+    }
+
+    pub(crate) fn apply_fill(&mut self, is_buy: bool, px: f64, sz: f64) {
+        let signed = if is_buy { sz } else { -sz };
+        let same_side = self.szi * signed >= 0.0;
+        if same_side {
+            let new_szi = self.szi + signed;
+            self.entry_px = (self.entry_px * self.szi.abs() + px * sz) / new_szi.abs();
+            self.szi = new_szi;
+            return;
+        }
+
+        let closed = sz.min(self.szi.abs());
+        self.realized_pnl += closed * (px - self.entry_px) * self.szi.signum();
+        self.szi += signed;
+        if self.szi.abs() < EPSILON {
+            self.szi = 0.0;
+            self.entry_px = 0.0;
+        } else if self.szi * signed > 0.0 {
+            // Flipped through zero, the remainder opens at the fill price
+            self.entry_px = px;
+        }
+    }
+}
+
+/// Difference between the tracked and the exchange-reported size of a position, found and
+/// corrected by `PositionTracker::reconcile`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionDrift {
+    pub coin: String,
+    pub local_szi: f64,
+    pub exchange_szi: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PositionSnapshot {
+    pub user: Address,
+    pub positions: Vec<Position>,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub fees: f64,
+    pub funding: f64,
+}
+
+/// Maintains per-asset positions and PnL from streamed fills, funding payments and mids.
+///
+/// Pass `userFills`, `userFundings`, `userEvents` and `allMids` messages to `handle_message`
+/// and call `reconcile` periodically to correct sizes and entry prices against the
+/// clearinghouse state. Snapshot messages are skipped since they predate the tracker; seed it
+/// with `reconcile` instead.
+#[derive(Debug)]
+pub struct PositionTracker {
+    user: Address,
+    positions: BTreeMap<String, Position>,
+    seen_fills: HashSet<u64>,
+}
+
+impl PositionTracker {
+    pub fn new(user: Address) -> PositionTracker {
+        PositionTracker {
+            user,
+            positions: BTreeMap::new(),
+            seen_fills: HashSet::new(),
+        }
+    }
+
+    /// Subscriptions whose messages should be passed to `handle_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        vec![
+            Subscription::UserFills { user: self.user },
+            Subscription::UserFundings { user: self.user },
+            Subscription::AllMids,
+        ]
+    }
+
+    pub fn position(&self, coin: &str) -> Option<&Position> {
+        self.positions.get(coin)
+    }
+
+    /// Tracked positions by coin, including closed ones that carry realized PnL.
+    pub fn positions(&self) -> impl Iterator<Item = &Position> {
+        self.positions.values()
+    }
+
+    pub fn set_mark_px(&mut self, coin: &str, mark_px: f64) {
+        if let Some(position) = self.positions.get_mut(coin) {
+            position.mark_px = Some(mark_px);
+        }
+    }
+
+    pub fn handle_message(&mut self, message: &Message) {
+        match message {
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                fills
+                    .data
+                    .fills
+                    .iter()
+                    .for_each(|fill| self.apply_fill(fill));
+            }
+            Message::UserFundings(fundings) if !fundings.data.is_snapshot.unwrap_or(false) => {
+                fundings
+                    .data
+                    .fundings
+                    .iter()
+                    .for_each(|funding| self.apply_funding(funding));
+            }
+            Message::User(user) => match &user.data {
+                UserData::Fills(fills) => fills.iter().for_each(|fill| self.apply_fill(fill)),
+                UserData::Funding(funding) => self.apply_funding(funding),
+                _ => {}
+            },
+            Message::AllMids(all_mids) => {
+                for (coin, position) in self.positions.iter_mut() {
+                    if let Some(mid) = all_mids.data.mids.get(coin) {
+                        position.mark_px = mid.parse().ok().or(position.mark_px);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn apply_fill(&mut self, fill: &TradeInfo) {
+        if !self.seen_fills.insert(fill.tid) {
+            return;
+        }
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            warn!("Could not parse fill {}", fill.tid);
+            return;
+        };
+        let position = self.entry(&fill.coin);
+        position.apply_fill(fill.side.is_buy(), px, sz);
+        position.fees += fill.fee.parse::<f64>().unwrap_or_default();
+    }
+
+    pub fn apply_funding(&mut self, funding: &UserFunding) {
+        match funding.usdc.parse::<f64>() {
+            Ok(usdc) => self.entry(&funding.coin).funding += usdc,
+            Err(_) => warn!("Could not parse funding for {}", funding.coin),
+        }
+    }
+
+    /// Replaces perp sizes and entry prices with the exchange's, keeping accumulated PnL, and
+    /// returns the positions that had drifted. Spot balances are left untouched.
+    pub async fn reconcile(&mut self, info: &InfoClient) -> Result<Vec<PositionDrift>> {
+        let state = info.user_state(self.user).await?;
+        Ok(self.apply_state(&state))
+    }
+
+    #[cfg(feature = "exchange")]
+    pub(crate) fn user(&self) -> Address {
+        self.user
+    }
+
+    #[cfg(feature = "exchange")]
+    pub(crate) fn has_fill(&self, tid: u64) -> bool {
+        self.seen_fills.contains(&tid)
+    }
+
+    /// Tracked perp positions whose size differs from `state`.
+    pub(crate) fn drifts(&self, state: &UserStateResponse) -> Vec<PositionDrift> {
+        let exchange_positions = exchange_positions(state);
+        // Positions seen for the first time are being seeded rather than drifting
+        self.positions
+            .iter()
+            .filter(|(coin, _)| !is_spot(coin))
+            .filter_map(|(coin, position)| {
+                let (szi, _) = exchange_positions.get(coin).copied().unwrap_or_default();
+                ((position.szi - szi).abs() > EPSILON).then(|| PositionDrift {
+                    coin: coin.clone(),
+                    local_szi: position.szi,
+                    exchange_szi: szi,
+                })
+            })
+            .collect()
+    }
+
+    pub(crate) fn apply_state(&mut self, state: &UserStateResponse) -> Vec<PositionDrift> {
+        let drifts = self.drifts(state);
+        for drift in &drifts {
+            warn!(
+                "Position drift on {}: tracked {}, exchange {}",
+                drift.coin, drift.local_szi, drift.exchange_szi
+            );
+        }
+        let exchange_positions = exchange_positions(state);
+        for coin in exchange_positions.keys() {
+            self.entry(coin);
+        }
+        for (coin, position) in self.positions.iter_mut() {
+            if is_spot(coin) {
+                continue;
+            }
+            let (szi, entry_px) = exchange_positions.get(coin).copied().unwrap_or_default();
+            position.szi = szi;
+            position.entry_px = entry_px;
+        }
+        drifts
+    }
+
+    pub fn snapshot(&self) -> PositionSnapshot {
+        let positions: Vec<Position> = self.positions.values().cloned().collect();
+        PositionSnapshot {
+            user: self.user,
+            realized_pnl: positions.iter().map(|p| p.realized_pnl).sum(),
+            unrealized_pnl: positions.iter().map(|p| p.unrealized_pnl()).sum(),
+            fees: positions.iter().map(|p| p.fees).sum(),
+            funding: positions.iter().map(|p| p.funding).sum(),
+            positions,
+        }
+    }
+
+    fn entry(&mut self, coin: &str) -> &mut Position {
+        self.positions
+            .entry(coin.to_string())
+            .or_insert_with(|| Position {
+                coin: coin.to_string(),
+                ..Position::default()
+            })
+    }
+}
+
+/// Perp sizes and entry prices by coin.
+fn exchange_positions(state: &UserStateResponse) -> BTreeMap<String, (f64, f64)> {
+    state
+        .asset_positions
+        .iter()
+        .map(|asset_position| {
+            let position = &asset_position.position;
+            let szi = position.szi.parse::<f64>().unwrap_or_default();
+            let entry_px = position
+                .entry_px
+                .as_deref()
+                .and_then(|px| px.parse::<f64>().ok())
+                .unwrap_or_default();
+            (position.coin.clone(), (szi, entry_px))
+        })
+        .collect()
+}
+
+/// Spot pairs are named `BASE/QUOTE` or `@index`.
+fn is_spot(coin: &str) -> bool {
+    coin.contains('/') || coin.starts_with('@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fills_funding_and_marks() {
+        let mut tracker = PositionTracker::new(Address::ZERO);
+        let message: Message = serde_json::from_str(
+            r#"{"channel":"user","data":{"fills":[
+                {"coin":"ETH","side":"B","px":"2000","sz":"1","time":1,"hash":"0x0","startPosition":"0","dir":"Open Long","closedPnl":"0","oid":1,"cloid":null,"crossed":true,"fee":"1","feeToken":"USDC","tid":1},
+                {"coin":"ETH","side":"B","px":"2100","sz":"1","time":2,"hash":"0x0","startPosition":"1","dir":"Open Long","closedPnl":"0","oid":2,"cloid":null,"crossed":true,"fee":"1","feeToken":"USDC","tid":2},
+                {"coin":"ETH","side":"A","px":"2200","sz":"3","time":3,"hash":"0x0","startPosition":"2","dir":"Long > Short","closedPnl":"300","oid":3,"cloid":null,"crossed":true,"fee":"2","feeToken":"USDC","tid":3},
+                {"coin":"ETH","side":"A","px":"2200","sz":"3","time":3,"hash":"0x0","startPosition":"2","dir":"Long > Short","closedPnl":"300","oid":3,"cloid":null,"crossed":true,"fee":"2","feeToken":"USDC","tid":3}
+            ]}}"#,
+        )
+        .unwrap();
+        tracker.handle_message(&message);
+        let funding: Message = serde_json::from_str(
+            r#"{"channel":"user","data":{"funding":{"time":4,"coin":"ETH","usdc":"-0.5","szi":"-1","fundingRate":"0.0001"}}}"#,
+        )
+        .unwrap();
+        tracker.handle_message(&funding);
+        let mids: Message =
+            serde_json::from_str(r#"{"channel":"allMids","data":{"mids":{"ETH":"2150"}}}"#)
+                .unwrap();
+        tracker.handle_message(&mids);
+
+        let eth = tracker.position("ETH").unwrap();
+        assert!((eth.szi + 1.0).abs() < EPSILON);
+        assert!((eth.entry_px - 2200.0).abs() < EPSILON);
+        assert!((eth.realized_pnl - 300.0).abs() < EPSILON);
+        assert!((eth.unrealized_pnl() - 50.0).abs() < EPSILON);
+        assert!((eth.total_pnl() - (300.0 + 50.0 - 0.5 - 4.0)).abs() < EPSILON);
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(snapshot.positions.len(), 1);
+        assert!((snapshot.fees - 4.0).abs() < EPSILON);
+    }
+}
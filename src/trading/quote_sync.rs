@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    prelude::*, BulkRequestStatus, ClientCancelRequest, ClientLimit, ClientModifyRequest,
+    ClientOrder, ClientOrderRequest, ExchangeClient, OpenOrdersResponse, Tif,
+};
+
+/// Price and size of one quote level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QuoteLevel {
+    pub px: f64,
+    pub sz: f64,
+}
+
+/// Quotes wanted on one coin, best level first on each side.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuoteTarget {
+    pub bids: Vec<QuoteLevel>,
+    pub asks: Vec<QuoteLevel>,
+}
+
+/// An order already resting on the book.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RestingQuote {
+    pub coin: String,
+    pub oid: u64,
+    pub is_buy: bool,
+    pub px: f64,
+    pub sz: f64,
+}
+
+impl RestingQuote {
+    /// `None` if the price or size does not parse.
+    pub fn from_open_order(order: &OpenOrdersResponse) -> Option<RestingQuote> {
+        Some(RestingQuote {
+            coin: order.coin.clone(),
+            oid: order.oid,
+            is_buy: order.side.is_buy(),
+            px: order.limit_px.parse().ok()?,
+            sz: order.sz.parse().ok()?,
+        })
+    }
+}
+
+/// Cancels, modifies and new orders taking the book from its resting orders to the wanted
+/// quotes, from `QuoteSync::plan`.
+#[derive(Clone, Debug, Default)]
+pub struct QuotePlan {
+    pub cancels: Vec<ClientCancelRequest>,
+    pub modifies: Vec<ClientModifyRequest>,
+    pub places: Vec<ClientOrderRequest>,
+    /// Resting orders already matching a wanted level, left untouched
+    pub kept: Vec<u64>,
+}
+
+impl QuotePlan {
+    pub fn is_empty(&self) -> bool {
+        self.cancels.is_empty() && self.modifies.is_empty() && self.places.is_empty()
+    }
+
+    /// Exchange calls needed to carry out the plan, one per kind of change.
+    pub fn calls(&self) -> usize {
+        [
+            !self.cancels.is_empty(),
+            !self.modifies.is_empty(),
+            !self.places.is_empty(),
+        ]
+        .into_iter()
+        .filter(|&call| call)
+        .count()
+    }
+
+    /// Sends the cancels, then the modifies, then the new orders, each kind as a single batch.
+    /// Cancels go first so they free margin for what follows.
+    pub async fn execute(self, exchange: &ExchangeClient) -> Result<QuoteSyncStatuses> {
+        let mut statuses = QuoteSyncStatuses::default();
+        if !self.cancels.is_empty() {
+            statuses.cancels = exchange
+                .bulk_cancel_with_statuses(self.cancels, None)
+                .await?;
+        }
+        if !self.modifies.is_empty() {
+            statuses.modifies = exchange
+                .bulk_modify_with_statuses(self.modifies, None)
+                .await?;
+        }
+        if !self.places.is_empty() {
+            statuses.places = exchange.bulk_order_with_statuses(self.places, None).await?;
+        }
+        Ok(statuses)
+    }
+}
+
+/// Per-request outcome of `QuotePlan::execute`.
+#[derive(Debug, Default)]
+pub struct QuoteSyncStatuses {
+    pub cancels: Vec<BulkRequestStatus<ClientCancelRequest>>,
+    pub modifies: Vec<BulkRequestStatus<ClientModifyRequest>>,
+    pub places: Vec<BulkRequestStatus<ClientOrderRequest>>,
+}
+
+/// Works out the fewest changes turning the resting orders into a wanted set of quotes.
+///
+/// On each side of each coin, resting orders within the price and size tolerances of a wanted
+/// level are kept, so they hold their queue position. The remaining orders are modified onto
+/// the remaining levels, best first, and whatever is left over is cancelled or placed.
+/// Coins without a target are left alone; an empty target cancels the coin's orders.
+#[derive(Clone, Debug)]
+pub struct QuoteSync {
+    px_tolerance_bps: f64,
+    sz_tolerance: f64,
+    tif: Tif,
+    modify: bool,
+}
+
+impl Default for QuoteSync {
+    fn default() -> Self {
+        QuoteSync::new()
+    }
+}
+
+impl QuoteSync {
+    /// Keeps orders only at the exact wanted price and size, placing new ones `Alo`.
+    pub fn new() -> QuoteSync {
+        QuoteSync {
+            px_tolerance_bps: 0.0,
+            sz_tolerance: 0.0,
+            tif: Tif::Alo,
+            modify: true,
+        }
+    }
+
+    /// Keeps a resting order within `bps` of a wanted price rather than moving it.
+    pub fn with_px_tolerance_bps(mut self, bps: f64) -> Self {
+        self.px_tolerance_bps = bps;
+        self
+    }
+
+    /// Keeps a resting order whose size is within this fraction of the wanted size.
+    pub fn with_sz_tolerance(mut self, fraction: f64) -> Self {
+        self.sz_tolerance = fraction;
+        self
+    }
+
+    /// Time in force of placed and modified orders.
+    pub fn with_tif(mut self, tif: Tif) -> Self {
+        self.tif = tif;
+        self
+    }
+
+    /// Cancels and replaces orders instead of modifying them, for venues or accounts where
+    /// modifies are rejected or rate limited.
+    pub fn with_modify(mut self, modify: bool) -> Self {
+        self.modify = modify;
+        self
+    }
+
+    pub fn plan(
+        &self,
+        targets: &BTreeMap<String, QuoteTarget>,
+        resting: &[RestingQuote],
+    ) -> QuotePlan {
+        let mut plan = QuotePlan::default();
+        for (coin, target) in targets {
+            for (is_buy, levels) in [(true, &target.bids), (false, &target.asks)] {
+                let mut orders: Vec<&RestingQuote> = resting
+                    .iter()
+                    .filter(|order| order.coin == *coin && order.is_buy == is_buy)
+                    .collect();
+                // Best price first, as the levels are
+                orders.sort_by(|a, b| {
+                    let order = a.px.total_cmp(&b.px);
+                    if is_buy {
+                        order.reverse()
+                    } else {
+                        order
+                    }
+                });
+                self.plan_side(&mut plan, coin, is_buy, levels, orders);
+            }
+        }
+        plan
+    }
+
+    fn plan_side(
+        &self,
+        plan: &mut QuotePlan,
+        coin: &str,
+        is_buy: bool,
+        levels: &[QuoteLevel],
+        mut orders: Vec<&RestingQuote>,
+    ) {
+        let mut unmatched = Vec::new();
+        for level in levels {
+            match orders.iter().position(|order| self.matches(order, level)) {
+                Some(index) => plan.kept.push(orders.remove(index).oid),
+                None => unmatched.push(level),
+            }
+        }
+        let mut orders = orders.into_iter();
+        for level in unmatched {
+            let order = ClientOrderRequest {
+                asset: coin.to_string(),
+                is_buy,
+                reduce_only: false,
+                limit_px: level.px,
+                sz: level.sz,
+                cloid: None,
+                order_type: ClientOrder::Limit(ClientLimit { tif: self.tif }),
+            };
+            match orders.next() {
+                Some(resting) if self.modify => plan.modifies.push(ClientModifyRequest {
+                    oid: resting.oid,
+                    order,
+                }),
+                Some(resting) => {
+                    plan.cancels.push(ClientCancelRequest {
+                        asset: coin.to_string(),
+                        oid: resting.oid,
+                    });
+                    plan.places.push(order);
+                }
+                None => plan.places.push(order),
+            }
+        }
+        plan.cancels
+            .extend(orders.map(|resting| ClientCancelRequest {
+                asset: coin.to_string(),
+                oid: resting.oid,
+            }));
+    }
+
+    fn matches(&self, order: &RestingQuote, level: &QuoteLevel) -> bool {
+        let px_tolerance = level.px * self.px_tolerance_bps / 10_000.0;
+        let sz_tolerance = level.sz * self.sz_tolerance;
+        (order.px - level.px).abs() <= px_tolerance + f64::EPSILON * level.px
+            && (order.sz - level.sz).abs() <= sz_tolerance + f64::EPSILON * level.sz
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resting(oid: u64, is_buy: bool, px: f64, sz: f64) -> RestingQuote {
+        RestingQuote {
+            coin: "ETH".to_string(),
+            oid,
+            is_buy,
+            px,
+            sz,
+        }
+    }
+
+    #[test]
+    fn test_plans_minimal_changes() {
+        let level = |px, sz| QuoteLevel { px, sz };
+        let targets = BTreeMap::from([(
+            "ETH".to_string(),
+            QuoteTarget {
+                bids: vec![level(1999.0, 1.0), level(1998.0, 2.0)],
+                asks: vec![level(2001.0, 1.0)],
+            },
+        )]);
+        let resting = [
+            // Within tolerance of the best bid
+            resting(1, true, 1999.1, 1.0),
+            // Moved onto the second bid
+            resting(2, true, 1990.0, 2.0),
+            // Both asks are off, the better one is moved and the other cancelled
+            resting(3, false, 2005.0, 1.0),
+            resting(4, false, 2003.0, 1.0),
+            // Not synced
+            RestingQuote {
+                coin: "BTC".to_string(),
+                ..resting(5, true, 50000.0, 0.1)
+            },
+        ];
+
+        let sync = QuoteSync::new().with_px_tolerance_bps(1.0);
+        let plan = sync.plan(&targets, &resting);
+        assert_eq!(plan.kept, vec![1]);
+        let modified: Vec<_> = plan
+            .modifies
+            .iter()
+            .map(|modify| (modify.oid, modify.order.limit_px))
+            .collect();
+        assert_eq!(modified, vec![(2, 1998.0), (4, 2001.0)]);
+        assert_eq!(plan.cancels.len(), 1);
+        assert_eq!(plan.cancels[0].oid, 3);
+        assert!(plan.places.is_empty());
+        assert_eq!(plan.calls(), 2);
+
+        let plan = sync.with_modify(false).plan(&targets, &resting);
+        assert_eq!(plan.cancels.len(), 3);
+        assert_eq!(plan.places.len(), 2);
+        assert!(plan.modifies.is_empty());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::CoinQuoteConfig;
+
+/// Price a `MultiMarketMaker` centres its quotes on, before skew.
+pub trait FairValue {
+    /// `None` pulls the coin's quotes, e.g. while an external reference is stale.
+    fn fair_value(&mut self, coin: &str, mid: f64) -> Option<f64>;
+}
+
+/// Quotes around the exchange mid.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MidFairValue;
+
+impl FairValue for MidFairValue {
+    fn fair_value(&mut self, _coin: &str, mid: f64) -> Option<f64> {
+        Some(mid)
+    }
+}
+
+impl<F: FnMut(&str, f64) -> Option<f64>> FairValue for F {
+    fn fair_value(&mut self, coin: &str, mid: f64) -> Option<f64> {
+        self(coin, mid)
+    }
+}
+
+/// Adjustment of a coin's quotes for its inventory.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Skew {
+    /// Shift of both quotes from the fair value, negative to lower them
+    pub price_bps: f64,
+    /// Multipliers of `order_size` on each side, still capped by `max_position`
+    pub bid_size_factor: f64,
+    pub ask_size_factor: f64,
+}
+
+impl Default for Skew {
+    fn default() -> Self {
+        Skew {
+            price_bps: 0.0,
+            bid_size_factor: 1.0,
+            ask_size_factor: 1.0,
+        }
+    }
+}
+
+/// Skews quotes of a `MultiMarketMaker` based on the current position.
+pub trait QuoteSkew {
+    fn skew(&mut self, config: &CoinQuoteConfig, position: f64) -> Skew;
+}
+
+/// Shifts both quotes against the distance from `target_position`, linearly up to
+/// `skew_bps` when it reaches `max_position`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LinearSkew;
+
+impl QuoteSkew for LinearSkew {
+    fn skew(&mut self, config: &CoinQuoteConfig, position: f64) -> Skew {
+        let inventory = if config.max_position > 0.0 {
+            ((position - config.target_position) / config.max_position).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        Skew {
+            price_bps: -config.skew_bps * inventory,
+            ..Skew::default()
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    lot_size, prelude::*, round_to_tick, ChildOrderStyle, Error, Exchange, ExecutionAlgo,
+    ExecutionConfig, ExecutionSchedule, InfoClient, Message, RoundingMode, SpotMeta, Strategy,
+    Subscription, EPSILON,
+};
+
+fn default_quote() -> String {
+    "USDC".to_string()
+}
+
+fn default_min_notional() -> f64 {
+    10.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RebalanceConfig {
+    /// Target share of the portfolio by token name, normalized to sum to one. The quote token
+    /// may be included; tokens held but not listed are left alone and not counted.
+    pub targets: HashMap<String, f64>,
+    /// Token every pair is traded against
+    #[serde(default = "default_quote")]
+    pub quote: String,
+    /// Smallest trade sent, in quote; the exchange rejects orders under 10 USDC
+    #[serde(default = "default_min_notional")]
+    pub min_notional: f64,
+    /// Tokens within this share of the portfolio of their target are not traded
+    #[serde(default)]
+    pub tolerance: f64,
+}
+
+/// One spot order of a `RebalancePlan`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RebalanceTrade {
+    pub token: String,
+    /// Pair the order is placed on, e.g. `PURR/USDC` or `@107`
+    pub coin: String,
+    pub sz_decimals: u32,
+    pub is_buy: bool,
+    pub sz: f64,
+    /// Mid the trade was sized at
+    pub px: f64,
+    pub weight: f64,
+    pub target_weight: f64,
+}
+
+impl RebalanceTrade {
+    pub fn notional(&self) -> f64 {
+        self.sz * self.px
+    }
+}
+
+/// The spot orders taking holdings to their target weights, one per token at most, with sells
+/// first so their proceeds fund the buys. `Display` prints it as a dry run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RebalancePlan {
+    pub quote: String,
+    /// Value of the targeted tokens and the quote, in quote
+    pub total_value: f64,
+    pub trades: Vec<RebalanceTrade>,
+}
+
+impl RebalancePlan {
+    /// Plans from `balances` and `mids` by token and pair name. Sizes are rounded down to the
+    /// token's lot size, sells are capped at the balance, and trades under the minimum notional
+    /// are dropped.
+    pub fn compute(
+        config: &RebalanceConfig,
+        spot_meta: &SpotMeta,
+        balances: &HashMap<String, f64>,
+        mids: &HashMap<String, f64>,
+    ) -> Result<RebalancePlan> {
+        let weight_sum: f64 = config.targets.values().sum();
+        if weight_sum <= 0.0 || config.targets.values().any(|w| *w < 0.0) {
+            return Err(Error::InvalidConfig(
+                "Rebalance targets need non-negative weights with a positive sum".to_string(),
+            ));
+        }
+        let quote_index = spot_meta
+            .tokens
+            .iter()
+            .find(|token| token.name == config.quote)
+            .map(|token| token.index)
+            .ok_or_else(|| Error::InvalidConfig(format!("Unknown token {}", config.quote)))?;
+
+        let mut holdings = Vec::new();
+        for token in config
+            .targets
+            .keys()
+            .filter(|token| **token != config.quote)
+        {
+            let info = spot_meta
+                .tokens
+                .iter()
+                .find(|info| &info.name == token)
+                .ok_or_else(|| Error::InvalidConfig(format!("Unknown token {token}")))?;
+            let pair = spot_meta
+                .universe
+                .iter()
+                .find(|pair| pair.tokens == [info.index, quote_index])
+                .ok_or_else(|| Error::InvalidConfig(format!("No {token}/{} pair", config.quote)))?;
+            let px = *mids
+                .get(&pair.name)
+                .ok_or_else(|| Error::InvalidConfig(format!("No mid for {}", pair.name)))?;
+            let balance = balances.get(token).copied().unwrap_or_default();
+            holdings.push((token, &pair.name, info.sz_decimals as u32, px, balance));
+        }
+        let quote_balance = balances.get(&config.quote).copied().unwrap_or_default();
+        let total_value = quote_balance
+            + holdings
+                .iter()
+                .map(|(_, _, _, px, balance)| px * balance)
+                .sum::<f64>();
+
+        let mut trades = Vec::new();
+        if total_value > 0.0 {
+            for (token, coin, sz_decimals, px, balance) in holdings {
+                let weight = px * balance / total_value;
+                let target_weight = config.targets[token] / weight_sum;
+                if (weight - target_weight).abs() <= config.tolerance {
+                    continue;
+                }
+                let is_buy = target_weight > weight;
+                let lot = lot_size(sz_decimals);
+                let mut sz = (target_weight - weight).abs() * total_value / px;
+                if !is_buy {
+                    sz = sz.min(balance);
+                }
+                let sz = round_to_tick(sz, lot, RoundingMode::Down);
+                if sz < lot - EPSILON || sz * px < config.min_notional {
+                    continue;
+                }
+                trades.push(RebalanceTrade {
+                    token: token.clone(),
+                    coin: coin.clone(),
+                    sz_decimals,
+                    is_buy,
+                    sz,
+                    px,
+                    weight,
+                    target_weight,
+                });
+            }
+        }
+        trades.sort_by(|a, b| {
+            a.is_buy
+                .cmp(&b.is_buy)
+                .then(b.notional().total_cmp(&a.notional()))
+        });
+        Ok(RebalancePlan {
+            quote: config.quote.clone(),
+            total_value,
+            trades,
+        })
+    }
+
+    /// Fetches the balances of `user` and the spot mids, and plans with them.
+    pub async fn fetch(
+        config: &RebalanceConfig,
+        info: &InfoClient,
+        user: Address,
+    ) -> Result<RebalancePlan> {
+        let spot_meta = info.spot_meta().await?;
+        let balances = info
+            .user_token_balances(user)
+            .await?
+            .balances
+            .into_iter()
+            .filter_map(|balance| Some((balance.coin, balance.total.parse().ok()?)))
+            .collect();
+        let mids = info
+            .all_mids()
+            .await?
+            .into_iter()
+            .filter_map(|(coin, mid)| Some((coin, mid.parse().ok()?)))
+            .collect();
+        RebalancePlan::compute(config, &spot_meta, &balances, &mids)
+    }
+
+    /// Execution of each trade over `duration_ms` in `style` child orders, in the plan's order.
+    pub fn execution_configs(
+        &self,
+        duration_ms: u64,
+        schedule: ExecutionSchedule,
+        style: ChildOrderStyle,
+    ) -> Vec<ExecutionConfig> {
+        self.trades
+            .iter()
+            .map(|trade| ExecutionConfig {
+                coin: trade.coin.clone(),
+                sz_decimals: trade.sz_decimals,
+                is_buy: trade.is_buy,
+                sz: trade.sz,
+                duration_ms,
+                schedule: schedule.clone(),
+                style,
+                limit_px: None,
+                max_participation: None,
+                reduce_only: false,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for RebalancePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Portfolio value {:.2} {}", self.total_value, self.quote)?;
+        if self.trades.is_empty() {
+            return writeln!(f, "Nothing to rebalance");
+        }
+        for trade in &self.trades {
+            writeln!(
+                f,
+                "{} {} {} on {} for ~{:.2} {} ({:.1}% -> {:.1}%)",
+                if trade.is_buy { "BUY" } else { "SELL" },
+                trade.sz,
+                trade.token,
+                trade.coin,
+                trade.notional(),
+                self.quote,
+                trade.weight * 100.0,
+                trade.target_weight * 100.0,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Carries out a `RebalancePlan` with one `ExecutionAlgo` per trade. Buys start once every
+/// sell is done, so they are funded by the sells' proceeds.
+#[derive(Debug)]
+pub struct RebalanceExecution {
+    sells: Vec<ExecutionAlgo>,
+    buys: Vec<ExecutionAlgo>,
+}
+
+impl RebalanceExecution {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address, configs: Vec<ExecutionConfig>) -> Result<RebalanceExecution> {
+        let (mut sells, mut buys) = (Vec::new(), Vec::new());
+        for config in configs {
+            if config.is_buy {
+                buys.push(ExecutionAlgo::new(user, config)?);
+            } else {
+                sells.push(ExecutionAlgo::new(user, config)?);
+            }
+        }
+        Ok(RebalanceExecution { sells, buys })
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut seen = HashSet::new();
+        self.algos()
+            .flat_map(ExecutionAlgo::subscriptions)
+            .filter(|subscription| {
+                seen.insert(serde_json::to_string(subscription).unwrap_or_default())
+            })
+            .collect()
+    }
+
+    pub fn algos(&self) -> impl Iterator<Item = &ExecutionAlgo> {
+        self.sells.iter().chain(&self.buys)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.algos().all(ExecutionAlgo::is_done)
+    }
+
+    fn selling(&self) -> bool {
+        !self.sells.iter().all(ExecutionAlgo::is_done)
+    }
+}
+
+impl Strategy for RebalanceExecution {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        for algo in &mut self.sells {
+            algo.on_message(message, exchange).await?;
+        }
+        if !self.selling() {
+            for algo in &mut self.buys {
+                algo.on_message(message, exchange).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_respects_lots_and_min_notional() {
+        let spot_meta: SpotMeta = serde_json::from_str(
+            r#"{"universe":[
+                {"tokens":[1,0],"name":"PURR/USDC","index":0,"isCanonical":true},
+                {"tokens":[2,0],"name":"@1","index":1,"isCanonical":false},
+                {"tokens":[3,0],"name":"@2","index":2,"isCanonical":false}],
+            "tokens":[
+                {"name":"USDC","szDecimals":8,"weiDecimals":8,"index":0,"tokenId":"0x6d1e7cde53ba9467b783cb7c530ce054","isCanonical":true},
+                {"name":"PURR","szDecimals":0,"weiDecimals":5,"index":1,"tokenId":"0xc1fb593aeffbeb02f85e0308e9956a90","isCanonical":true},
+                {"name":"HYPE","szDecimals":2,"weiDecimals":8,"index":2,"tokenId":"0x0d01dc56dcaaca66ad901c959b4011ec","isCanonical":false},
+                {"name":"FOO","szDecimals":2,"weiDecimals":8,"index":3,"tokenId":"0x0d01dc56dcaaca66ad901c959b4011ed","isCanonical":false}]}"#,
+        )
+        .unwrap();
+        let config: RebalanceConfig =
+            serde_json::from_str(r#"{"targets":{"PURR":1,"HYPE":2,"FOO":0.02,"USDC":0.98}}"#)
+                .unwrap();
+        let balances = HashMap::from([
+            ("USDC".to_string(), 100.0),
+            ("PURR".to_string(), 1000.0),
+            ("HYPE".to_string(), 0.0),
+            ("FOO".to_string(), 0.0),
+        ]);
+        let mids = HashMap::from([
+            ("PURR/USDC".to_string(), 0.2),
+            ("@1".to_string(), 30.0),
+            ("@2".to_string(), 1.0),
+        ]);
+
+        let plan = RebalancePlan::compute(&config, &spot_meta, &balances, &mids).unwrap();
+        assert!((plan.total_value - 300.0).abs() < EPSILON);
+        // FOO's 1.5 USDC is under the minimum notional
+        let trades: Vec<_> = plan
+            .trades
+            .iter()
+            .map(|t| (t.coin.as_str(), t.is_buy, t.sz))
+            .collect();
+        assert_eq!(trades, [("PURR/USDC", false, 625.0), ("@1", true, 5.0)]);
+        assert!(plan.to_string().contains("SELL 625 PURR on PURR/USDC"));
+
+        let configs = plan.execution_configs(
+            60_000,
+            ExecutionSchedule::Fixed { slices: 4 },
+            ChildOrderStyle::Passive,
+        );
+        let execution = RebalanceExecution::new(Address::ZERO, configs).unwrap();
+        assert!(execution.selling());
+        // Account subscriptions are shared by both executions
+        assert_eq!(execution.subscriptions().len(), 6);
+    }
+}
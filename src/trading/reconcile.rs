@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::order_manager::parse_cloid;
+use crate::{
+    prelude::*, Error, InfoClient, OrderManager, PositionDrift, PositionTracker, TradeInfo, EPSILON,
+};
+
+/// A difference between local state and the exchange, found by `reconcile`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Discrepancy {
+    /// Resting on the exchange but not tracked by the `OrderManager`
+    #[serde(rename_all = "camelCase")]
+    UntrackedOrder {
+        coin: String,
+        oid: u64,
+        cloid: Option<String>,
+        is_buy: bool,
+        limit_px: f64,
+        sz: f64,
+    },
+    /// Open in the `OrderManager` but no longer resting on the exchange
+    #[serde(rename_all = "camelCase")]
+    MissingOrder {
+        cloid: Uuid,
+        oid: Option<u64>,
+        coin: String,
+    },
+    /// Resting on both with a different remaining size
+    #[serde(rename_all = "camelCase")]
+    OrderSize {
+        cloid: Uuid,
+        oid: u64,
+        local_sz: f64,
+        exchange_sz: f64,
+    },
+    /// A recent fill not applied to the `PositionTracker`, or to the tracked order it belongs to
+    #[serde(rename_all = "camelCase")]
+    MissedFill {
+        coin: String,
+        tid: u64,
+        oid: u64,
+        /// The tracked order, if the fill belongs to one
+        cloid: Option<Uuid>,
+        is_buy: bool,
+        px: f64,
+        sz: f64,
+        time: u64,
+    },
+    /// Position size differs from the clearinghouse state
+    Position(PositionDrift),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ReconcileOptions {
+    /// Apply corrections instead of only reporting
+    pub correct: bool,
+    /// Fills before this time in ms are not compared. Set it to when tracking started, since
+    /// earlier fills are already part of positions seeded from the clearinghouse state.
+    pub fills_since: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReconcileReport {
+    pub discrepancies: Vec<Discrepancy>,
+    /// Whether local state was corrected after comparing
+    pub corrected: bool,
+}
+
+impl ReconcileReport {
+    pub fn is_clean(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+}
+
+/// Compares `orders` and `positions` with the exchange's open orders, clearinghouse state and
+/// recent fills, and reports what differs. Both must track the same account.
+///
+/// With `ReconcileOptions::correct`, missed fills are applied to both, orders no longer
+/// resting are resolved as in `OrderManager::reconcile`, and positions are reset to the
+/// exchange's as in `PositionTracker::reconcile`. Untracked orders are only reported, since
+/// the manager cannot know what they were placed for.
+pub async fn reconcile(
+    info: &InfoClient,
+    orders: &mut OrderManager,
+    positions: &mut PositionTracker,
+    options: &ReconcileOptions,
+) -> Result<ReconcileReport> {
+    let user = orders.user();
+    if positions.user() != user {
+        return Err(Error::InvalidConfig(
+            "OrderManager and PositionTracker track different accounts".to_string(),
+        ));
+    }
+    let snapshot = info.account_snapshot(user).await?;
+    let (resting, state) = (snapshot.open_orders, snapshot.state);
+    let mut fills: Vec<TradeInfo> = snapshot
+        .fills
+        .into_iter()
+        .filter(|fill| fill.time >= options.fills_since)
+        .map(TradeInfo::from)
+        .collect();
+    fills.sort_by_key(|fill| (fill.time, fill.tid));
+
+    let mut discrepancies = Vec::new();
+    let mut matched = HashSet::new();
+    for order in orders.open_orders() {
+        let open = resting.iter().find(|open| {
+            open.cloid.as_deref().and_then(parse_cloid) == Some(order.cloid)
+                || order.oid == Some(open.oid)
+        });
+        let Some(open) = open else {
+            discrepancies.push(Discrepancy::MissingOrder {
+                cloid: order.cloid,
+                oid: order.oid,
+                coin: order.coin.clone(),
+            });
+            continue;
+        };
+        matched.insert(open.oid);
+        let exchange_sz = open.sz.parse::<f64>().unwrap_or_default();
+        if (order.remaining_sz() - exchange_sz).abs() > EPSILON {
+            discrepancies.push(Discrepancy::OrderSize {
+                cloid: order.cloid,
+                oid: open.oid,
+                local_sz: order.remaining_sz(),
+                exchange_sz,
+            });
+        }
+    }
+    for open in resting.iter().filter(|open| !matched.contains(&open.oid)) {
+        discrepancies.push(Discrepancy::UntrackedOrder {
+            coin: open.coin.clone(),
+            oid: open.oid,
+            cloid: open.cloid.clone(),
+            is_buy: open.side.is_buy(),
+            limit_px: open.limit_px.parse().unwrap_or_default(),
+            sz: open.sz.parse().unwrap_or_default(),
+        });
+    }
+
+    for fill in &fills {
+        let order = orders.order_by_oid(fill.oid);
+        let order_missed = order.is_some_and(|order| !order.has_fill(fill.tid));
+        if order_missed || !positions.has_fill(fill.tid) {
+            discrepancies.push(Discrepancy::MissedFill {
+                coin: fill.coin.clone(),
+                tid: fill.tid,
+                oid: fill.oid,
+                cloid: order.map(|order| order.cloid),
+                is_buy: fill.side.is_buy(),
+                px: fill.px.parse().unwrap_or_default(),
+                sz: fill.sz.parse().unwrap_or_default(),
+                time: fill.time.as_millis(),
+            });
+        }
+    }
+    discrepancies.extend(
+        positions
+            .drifts(&state)
+            .into_iter()
+            .map(Discrepancy::Position),
+    );
+
+    if options.correct {
+        for fill in &fills {
+            orders.apply_fill(fill);
+            positions.apply_fill(fill);
+        }
+        orders.reconcile(info).await?;
+        positions.apply_state(&state);
+    }
+    Ok(ReconcileReport {
+        discrepancies,
+        corrected: options.correct,
+    })
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use alloy::signers::local::PrivateKeySigner;
+
+    use super::*;
+    use crate::{
+        ClientLimit, ClientOrder, ClientOrderRequest, ExchangeClient, MockConfig, MockServer, Tif,
+    };
+
+    fn order(is_buy: bool, limit_px: f64, tif: Tif) -> ClientOrderRequest {
+        ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy,
+            reduce_only: false,
+            limit_px,
+            sz: 1.0,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif }),
+        }
+    }
+
+    fn kinds(report: &ReconcileReport) -> Vec<&'static str> {
+        let mut kinds: Vec<_> = report
+            .discrepancies
+            .iter()
+            .map(|discrepancy| match discrepancy {
+                Discrepancy::UntrackedOrder { .. } => "untracked",
+                Discrepancy::MissingOrder { .. } => "missing",
+                Discrepancy::OrderSize { .. } => "size",
+                Discrepancy::MissedFill { .. } => "fill",
+                Discrepancy::Position(_) => "position",
+            })
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_reports_and_corrects() {
+        let wallet: PrivateKeySigner =
+            "e908f86dbb4d55ac876378565aafeabc187f6690f046459397b17d9b9a19688e"
+                .parse()
+                .unwrap();
+        let user = wallet.address();
+        let server = MockServer::start(MockConfig {
+            user,
+            ..MockConfig::default()
+        })
+        .await
+        .unwrap();
+        server.set_book("ETH", &[(1999.0, 5.0)], &[(2001.0, 5.0)]);
+        let exchange = ExchangeClient::new(None, wallet, Some(server.base_url()), None, None)
+            .await
+            .unwrap();
+        let info = exchange.info_client();
+        let mut orders = OrderManager::new(user);
+        let mut positions = PositionTracker::new(user);
+
+        orders
+            .place(&exchange, vec![order(true, 1990.0, Tif::Gtc)])
+            .await
+            .unwrap();
+        orders
+            .place(&exchange, vec![order(true, 2002.0, Tif::Ioc)])
+            .await
+            .unwrap();
+        exchange
+            .order(order(false, 2010.0, Tif::Gtc), None)
+            .await
+            .unwrap();
+        positions.reconcile(&info).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let since = crate::helpers::now_timestamp_ms();
+        // The stream is not consumed, so neither component sees this fill
+        server.trade("ETH", false, 1990.0, 0.4);
+
+        let options = ReconcileOptions {
+            correct: false,
+            fills_since: since,
+        };
+        let report = reconcile(&info, &mut orders, &mut positions, &options)
+            .await
+            .unwrap();
+        assert_eq!(kinds(&report), ["fill", "position", "size", "untracked"]);
+        assert!(matches!(
+            &report.discrepancies[..],
+            [Discrepancy::OrderSize { local_sz, exchange_sz, .. }, ..]
+                if (local_sz - 1.0).abs() < EPSILON && (exchange_sz - 0.6).abs() < EPSILON
+        ));
+
+        let options = ReconcileOptions {
+            correct: true,
+            ..options
+        };
+        assert!(
+            reconcile(&info, &mut orders, &mut positions, &options)
+                .await
+                .unwrap()
+                .corrected
+        );
+        assert!((positions.position("ETH").unwrap().szi - 1.4).abs() < EPSILON);
+        let report = reconcile(&info, &mut orders, &mut positions, &options)
+            .await
+            .unwrap();
+        assert_eq!(kinds(&report), ["untracked"]);
+    }
+}
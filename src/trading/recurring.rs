@@ -0,0 +1,487 @@
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::pin,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use chrono::{DateTime, Datelike, Days, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::persist::{load_json, save_json};
+use crate::{
+    helpers::{float_to_string_for_hashing, now_timestamp_ms},
+    lot_size,
+    prelude::*,
+    round_to_tick, rt, Error, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus,
+    MarketOrderParams, RoundingMode,
+};
+
+/// When a recurring job runs. Times are UTC.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecurringSchedule {
+    /// Every `interval_secs`, counted from `anchor_ms`
+    Every {
+        interval_secs: u64,
+        #[serde(default)]
+        anchor_ms: u64,
+    },
+    Daily {
+        hour: u32,
+        minute: u32,
+    },
+    /// `weekday` counts from 0 for Monday
+    Weekly {
+        weekday: u32,
+        hour: u32,
+        minute: u32,
+    },
+}
+
+impl RecurringSchedule {
+    /// First run strictly after `time`, in ms.
+    pub fn next_after(&self, time: u64) -> Result<u64> {
+        let invalid = || Error::InvalidConfig(format!("Invalid schedule {self:?}"));
+        match *self {
+            RecurringSchedule::Every {
+                interval_secs,
+                anchor_ms,
+            } => {
+                let interval = interval_secs.checked_mul(1000).filter(|i| *i > 0);
+                let interval = interval.ok_or_else(invalid)?;
+                if time < anchor_ms {
+                    return Ok(anchor_ms);
+                }
+                Ok(anchor_ms + ((time - anchor_ms) / interval + 1) * interval)
+            }
+            RecurringSchedule::Daily { hour, minute } => {
+                let at = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)?;
+                next_at(time, at, 1, |_| 0)
+            }
+            RecurringSchedule::Weekly {
+                weekday,
+                hour,
+                minute,
+            } => {
+                let at = NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(invalid)?;
+                if weekday > 6 {
+                    return Err(invalid());
+                }
+                next_at(time, at, 7, |date| {
+                    (weekday + 7 - date.weekday().num_days_from_monday()) % 7
+                })
+            }
+        }
+    }
+}
+
+/// First `at` after `time`, `offset` days after the day of `time`, else `period` days later.
+fn next_at(
+    time: u64,
+    at: NaiveTime,
+    period: u64,
+    offset: impl Fn(&DateTime<Utc>) -> u32,
+) -> Result<u64> {
+    let now = DateTime::<Utc>::from_timestamp_millis(time as i64)
+        .ok_or_else(|| Error::InvalidConfig(format!("Invalid time {time}")))?;
+    let date = now.date_naive() + Days::new(offset(&now) as u64);
+    let mut next = date.and_time(at).and_utc();
+    if next <= now {
+        next = next + Days::new(period);
+    }
+    Ok(next.timestamp_millis() as u64)
+}
+
+/// What a recurring job does on each run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecurringAction {
+    /// `usdSend` of USDC from the perp account
+    UsdSend { destination: Address, amount: f64 },
+    /// `spotSend` of `token`, named `NAME:tokenId`
+    SpotSend {
+        destination: Address,
+        token: String,
+        amount: f64,
+    },
+    /// USDC between the spot and perp accounts
+    ClassTransfer { usdc: f64, to_perp: bool },
+    /// Market buy of `notional` USDC worth of a perp at the mid
+    Buy {
+        coin: String,
+        notional: f64,
+        /// Fraction the order may fill above the mid, 5% by default
+        #[serde(default)]
+        slippage: Option<f64>,
+    },
+}
+
+/// What to do with runs whose time passed more than the grace period ago, such as while the
+/// process was down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Run once for any number of missed runs
+    #[default]
+    Once,
+    /// Run every missed run
+    All,
+    /// Drop missed runs
+    Skip,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecurringJob {
+    pub name: String,
+    pub schedule: RecurringSchedule,
+    pub action: RecurringAction,
+    #[serde(default)]
+    pub catch_up: CatchUp,
+}
+
+/// A job and its progress, saved whenever it changes when a state file is set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecurringJobState {
+    pub job: RecurringJob,
+    /// Next scheduled run, in ms
+    pub next_run: u64,
+    pub last_run: Option<u64>,
+    pub runs: u64,
+    pub last_error: Option<String>,
+}
+
+/// Most missed runs made up for by `CatchUp::All`.
+const MAX_CATCH_UP: usize = 1000;
+
+/// Runs recurring transfers and purchases on their schedules, for treasury automation and
+/// dollar-cost averaging.
+///
+/// With a state file, progress survives restarts and runs missed while the process was down
+/// are made up according to each job's `CatchUp`. Runs are saved as done before they are sent,
+/// so a crash in between skips a run rather than repeating a transfer.
+#[derive(Debug)]
+pub struct RecurringScheduler {
+    jobs: Vec<RecurringJobState>,
+    grace: Duration,
+    state_file: Option<PathBuf>,
+}
+
+impl RecurringScheduler {
+    /// Schedules `jobs` from now.
+    pub fn new(jobs: Vec<RecurringJob>) -> Result<RecurringScheduler> {
+        RecurringScheduler::new_at(jobs, now_timestamp_ms())
+    }
+
+    /// Loads jobs and progress saved by a previous run, saving to the same file.
+    pub fn load(path: impl AsRef<Path>) -> Result<RecurringScheduler> {
+        let path = path.as_ref();
+        Ok(RecurringScheduler {
+            jobs: load_json(path)?,
+            grace: Duration::from_secs(60),
+            state_file: Some(path.to_path_buf()),
+        })
+    }
+
+    pub fn with_state_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// How late a run may start before it counts as missed, a minute by default.
+    pub fn with_grace(mut self, grace: Duration) -> Self {
+        self.grace = grace;
+        self
+    }
+
+    pub fn jobs(&self) -> &[RecurringJobState] {
+        &self.jobs
+    }
+
+    /// Earliest next run of any job.
+    pub fn next_run(&self) -> Option<u64> {
+        self.jobs.iter().map(|job| job.next_run).min()
+    }
+
+    /// Sends every run that is due, returning how many were sent. Failed runs are recorded in
+    /// their job's `last_error` and not retried.
+    pub async fn run_due(&mut self, exchange: &ExchangeClient) -> Result<usize> {
+        let due = self.take_due(now_timestamp_ms())?;
+        for &(index, scheduled) in &due {
+            let job = &self.jobs[index].job;
+            info!("Running {} scheduled at {scheduled}", job.name);
+            let result = execute(exchange, &job.action).await;
+            let state = &mut self.jobs[index];
+            state.last_error = result.err().map(|err| {
+                warn!("Recurring job {} failed: {err}", state.job.name);
+                err.to_string()
+            });
+            self.save()?;
+        }
+        Ok(due.len())
+    }
+
+    /// Calls `run_due` whenever a run is due until `shutdown` completes, checking at least
+    /// every `poll`. Errors are logged and scheduling continues.
+    pub async fn run(
+        &mut self,
+        exchange: &ExchangeClient,
+        poll: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            if let Err(err) = self.run_due(exchange).await {
+                warn!("Error running recurring jobs: {err}");
+            }
+            let wait = self
+                .next_run()
+                .map(|next| Duration::from_millis(next.saturating_sub(now_timestamp_ms())))
+                .unwrap_or(poll)
+                .min(poll);
+            tokio::select! {
+                _ = &mut shutdown => return Ok(()),
+                _ = rt::sleep(wait) => {}
+            }
+        }
+    }
+
+    fn new_at(jobs: Vec<RecurringJob>, now: u64) -> Result<RecurringScheduler> {
+        let jobs = jobs
+            .into_iter()
+            .map(|job| {
+                Ok(RecurringJobState {
+                    next_run: job.schedule.next_after(now)?,
+                    job,
+                    last_run: None,
+                    runs: 0,
+                    last_error: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(RecurringScheduler {
+            jobs,
+            grace: Duration::from_secs(60),
+            state_file: None,
+        })
+    }
+
+    /// Runs due at `now` as job index and scheduled time, marking them done and moving each
+    /// job to its next run after `now`.
+    fn take_due(&mut self, now: u64) -> Result<Vec<(usize, u64)>> {
+        let grace = self.grace.as_millis() as u64;
+        let mut due = Vec::new();
+        for (index, state) in self.jobs.iter_mut().enumerate() {
+            if state.next_run > now {
+                continue;
+            }
+            let mut scheduled = vec![state.next_run];
+            while scheduled.len() < MAX_CATCH_UP {
+                let next = state
+                    .job
+                    .schedule
+                    .next_after(scheduled[scheduled.len() - 1])?;
+                if next > now {
+                    break;
+                }
+                scheduled.push(next);
+            }
+            let on_time = |time: &u64| now - time <= grace;
+            let runs: Vec<u64> = match state.job.catch_up {
+                CatchUp::All => scheduled,
+                CatchUp::Once => scheduled.last().copied().into_iter().collect(),
+                CatchUp::Skip => scheduled
+                    .into_iter()
+                    .rev()
+                    .take(1)
+                    .filter(on_time)
+                    .collect(),
+            };
+            state.next_run = state.job.schedule.next_after(now)?;
+            if let Some(last) = runs.last() {
+                state.last_run = Some(*last);
+                state.runs += runs.len() as u64;
+            }
+            due.extend(runs.into_iter().map(|time| (index, time)));
+        }
+        if !due.is_empty() || self.jobs.iter().any(|job| job.next_run <= now) {
+            self.save()?;
+        }
+        Ok(due)
+    }
+
+    fn save(&self) -> Result<()> {
+        match &self.state_file {
+            Some(path) => save_json(path, &self.jobs),
+            None => Ok(()),
+        }
+    }
+}
+
+async fn execute(exchange: &ExchangeClient, action: &RecurringAction) -> Result<()> {
+    let response = match action {
+        RecurringAction::UsdSend {
+            destination,
+            amount,
+        } => {
+            exchange
+                .usdc_transfer(
+                    &float_to_string_for_hashing(*amount),
+                    &destination.to_string(),
+                    None,
+                )
+                .await?
+        }
+        RecurringAction::SpotSend {
+            destination,
+            token,
+            amount,
+        } => {
+            exchange
+                .spot_transfer(
+                    &float_to_string_for_hashing(*amount),
+                    &destination.to_string(),
+                    token,
+                    None,
+                )
+                .await?
+        }
+        RecurringAction::ClassTransfer { usdc, to_perp } => {
+            exchange.class_transfer(*usdc, *to_perp, None).await?
+        }
+        RecurringAction::Buy {
+            coin,
+            notional,
+            slippage,
+        } => {
+            let sz_decimals = exchange
+                .meta
+                .universe
+                .iter()
+                .find(|asset| &asset.name == coin)
+                .map(|asset| asset.sz_decimals)
+                .ok_or(Error::AssetNotFound)?;
+            let mid: f64 = exchange
+                .info_client()
+                .all_mids()
+                .await?
+                .get(coin)
+                .and_then(|mid| mid.parse().ok())
+                .ok_or_else(|| Error::GenericRequest(format!("No mid for {coin}")))?;
+            let lot = lot_size(sz_decimals);
+            let sz = round_to_tick(notional / mid, lot, RoundingMode::Down);
+            if sz < lot {
+                return Err(Error::InvalidOrder(format!(
+                    "{notional} USDC buys less than one lot of {coin}"
+                )));
+            }
+            exchange
+                .market_open(MarketOrderParams {
+                    asset: coin,
+                    is_buy: true,
+                    sz,
+                    px: Some(mid),
+                    slippage: *slippage,
+                    cloid: None,
+                    wallet: None,
+                })
+                .await?
+        }
+    };
+    match response {
+        ExchangeResponseStatus::Ok(response) => {
+            let statuses = response.data.map(|data| data.statuses).unwrap_or_default();
+            match statuses.into_iter().next() {
+                Some(ExchangeDataStatus::Error(err)) => Err(Error::GenericRequest(err.to_string())),
+                _ => Ok(()),
+            }
+        }
+        ExchangeResponseStatus::Err(err) => Err(Error::GenericRequest(err.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * 60 * 1000;
+
+    fn job(catch_up: CatchUp) -> RecurringJob {
+        RecurringJob {
+            name: format!("{catch_up:?}"),
+            schedule: RecurringSchedule::Every {
+                interval_secs: 3600,
+                anchor_ms: 0,
+            },
+            action: RecurringAction::ClassTransfer {
+                usdc: 10.0,
+                to_perp: true,
+            },
+            catch_up,
+        }
+    }
+
+    #[test]
+    fn test_schedules() {
+        // Thursday 1 January 1970, 00:00
+        let daily = RecurringSchedule::Daily {
+            hour: 9,
+            minute: 30,
+        };
+        assert_eq!(daily.next_after(0).unwrap(), 9 * HOUR + 30 * 60_000);
+        assert_eq!(
+            daily.next_after(9 * HOUR + 30 * 60_000).unwrap(),
+            33 * HOUR + 30 * 60_000
+        );
+        let monday = RecurringSchedule::Weekly {
+            weekday: 0,
+            hour: 0,
+            minute: 0,
+        };
+        assert_eq!(monday.next_after(0).unwrap(), 4 * 24 * HOUR);
+        let thursday = RecurringSchedule::Weekly {
+            weekday: 3,
+            hour: 0,
+            minute: 0,
+        };
+        assert_eq!(thursday.next_after(0).unwrap(), 7 * 24 * HOUR);
+        assert!(RecurringSchedule::Daily {
+            hour: 24,
+            minute: 0
+        }
+        .next_after(0)
+        .is_err());
+    }
+
+    #[test]
+    fn test_catch_up_and_persistence() {
+        let path = std::env::temp_dir().join(format!("recurring_{}.json", std::process::id()));
+        let mut scheduler = RecurringScheduler::new_at(
+            vec![job(CatchUp::Once), job(CatchUp::All), job(CatchUp::Skip)],
+            0,
+        )
+        .unwrap()
+        .with_state_file(&path);
+        assert_eq!(scheduler.next_run(), Some(HOUR));
+        assert!(scheduler.take_due(HOUR - 1).unwrap().is_empty());
+
+        // Down for three runs, back 30 minutes after the last
+        let due = scheduler.take_due(3 * HOUR + HOUR / 2).unwrap();
+        assert_eq!(
+            due,
+            vec![(0, 3 * HOUR), (1, HOUR), (1, 2 * HOUR), (1, 3 * HOUR)]
+        );
+        assert!(scheduler.jobs().iter().all(|job| job.next_run == 4 * HOUR));
+
+        // On time runs go ahead whatever the policy
+        let due = scheduler.take_due(4 * HOUR + 1000).unwrap();
+        assert_eq!(due, vec![(0, 4 * HOUR), (1, 4 * HOUR), (2, 4 * HOUR)]);
+
+        let loaded = RecurringScheduler::load(&path).unwrap();
+        assert_eq!(loaded.jobs(), scheduler.jobs());
+        assert_eq!(loaded.jobs()[1].runs, 4);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,303 @@
+#[cfg(feature = "ws")]
+use std::{future::Future, pin::pin};
+use std::{sync::Arc, time::Duration};
+
+use alloy::primitives::Address;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+#[cfg(feature = "ws")]
+use tracing::error;
+
+use crate::{
+    prelude::*, Clock, EventStrategy, Exchange, Message, OrderEvent, OrderManager, Strategy,
+    StrategyContext, Subscription,
+};
+#[cfg(feature = "ws")]
+use crate::{InfoClient, RiskEngine};
+
+/// Runs an `EventStrategy`: feeds messages to an `OrderManager`, calls the strategy's hooks
+/// for books, trades, fills, order lifecycle events and timers, and with `run` owns the
+/// subscriptions and the event loop.
+///
+/// The runtime is itself a `Strategy`, so the same event strategy can be run against a
+/// `PaperExchange` or in a `Backtester`. Timers only fire in `run`; elsewhere `on_timer` is
+/// called by the driver. They run on the process clock unless given one with `with_clock`,
+/// such as a `SimClock` a test moves forward.
+#[derive(Debug)]
+pub struct StrategyRuntime<S> {
+    strategy: S,
+    orders: OrderManager,
+    events: UnboundedReceiver<OrderEvent>,
+    timer: Option<Duration>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S: EventStrategy> StrategyRuntime<S> {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address, strategy: S) -> StrategyRuntime<S> {
+        let (sender, events) = unbounded_channel();
+        StrategyRuntime {
+            strategy,
+            orders: OrderManager::new(user).with_events(sender),
+            events,
+            timer: None,
+            clock: crate::clock(),
+        }
+    }
+
+    /// Calls `EventStrategy::on_timer` every `interval` while running.
+    pub fn with_timer(mut self, interval: Duration) -> Self {
+        self.timer = Some(interval);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
+    pub fn strategy_mut(&mut self) -> &mut S {
+        &mut self.strategy
+    }
+
+    pub fn orders(&self) -> &OrderManager {
+        &self.orders
+    }
+
+    /// The strategy's subscriptions followed by the `OrderManager`'s.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = self.strategy.subscriptions();
+        subscriptions.extend(self.orders.subscriptions());
+        subscriptions
+    }
+
+    pub async fn on_timer<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let mut ctx = StrategyContext::new(exchange, &mut self.orders);
+        self.strategy.on_timer(&mut ctx).await?;
+        self.dispatch_events(exchange).await
+    }
+
+    /// Cancels every open order placed through the runtime.
+    pub async fn cancel_all<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        let cloids: Vec<_> = self
+            .orders
+            .open_orders()
+            .iter()
+            .map(|order| order.cloid)
+            .collect();
+        if !cloids.is_empty() {
+            self.orders.cancel(exchange, cloids).await?;
+        }
+        Ok(())
+    }
+
+    /// Subscribes, reconciles `risk` with the account, then runs the strategy until
+    /// `shutdown` completes or the subscriptions close, and cancels its open orders. Orders
+    /// go through the `RiskEngine`'s checks, so its price collar needs `allMids` among the
+    /// strategy's subscriptions. Errors from hooks are logged and the loop continues.
+    ///
+    /// To stop with a `CancellationToken`, pass `token.cancelled()` as `shutdown`. Use a
+    /// different token from the clients' own, which would also abort the closing cancels.
+    #[cfg(feature = "ws")]
+    pub async fn run(
+        &mut self,
+        info: &InfoClient,
+        risk: &RiskEngine,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<()> {
+        let (sender, mut receiver) = unbounded_channel();
+        for subscription in self.subscriptions() {
+            info.subscribe(subscription, sender.clone()).await?;
+        }
+        drop(sender);
+        risk.reconcile(info).await?;
+
+        let mut shutdown = pin!(shutdown);
+        let next_at =
+            |clock: &dyn Clock, interval: Duration| clock.now_ms() + interval.as_millis() as u64;
+        let mut next_timer = self.timer.map(|interval| next_at(&*self.clock, interval));
+        loop {
+            let clock = self.clock.clone();
+            let timer = async move {
+                match next_timer {
+                    Some(at) => {
+                        let remaining = at.saturating_sub(clock.now_ms());
+                        clock.sleep(Duration::from_millis(remaining)).await
+                    }
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                _ = &mut shutdown => break,
+                message = receiver.recv() => {
+                    let Some(message) = message else {
+                        break;
+                    };
+                    risk.handle_message(&message);
+                    if let Err(err) = self.on_message(&message, risk).await {
+                        error!(%err, "Strategy failed handling message");
+                    }
+                }
+                _ = timer => {
+                    next_timer = self.timer.map(|interval| next_at(&*self.clock, interval));
+                    if let Err(err) = self.on_timer(risk).await {
+                        error!(%err, "Strategy failed handling timer");
+                    }
+                }
+            }
+        }
+        self.cancel_all(risk).await
+    }
+
+    /// Passes order lifecycle events to the strategy, including those caused by orders it
+    /// places while handling them.
+    async fn dispatch_events<E: Exchange>(&mut self, exchange: &E) -> Result<()> {
+        while let Ok(event) = self.events.try_recv() {
+            let mut ctx = StrategyContext::new(exchange, &mut self.orders);
+            self.strategy.on_order_update(&event, &mut ctx).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: EventStrategy> Strategy for StrategyRuntime<S> {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        self.orders.handle_message(message);
+        let mut ctx = StrategyContext::new(exchange, &mut self.orders);
+        match message {
+            Message::L2Book(book) => self.strategy.on_book(&book.data, &mut ctx).await?,
+            Message::Trades(trades) => {
+                for trade in &trades.data {
+                    self.strategy.on_trade(trade, &mut ctx).await?;
+                }
+            }
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                for fill in &fills.data.fills {
+                    self.strategy.on_fill(fill, &mut ctx).await?;
+                }
+            }
+            _ => {}
+        }
+        self.dispatch_events(exchange).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ClientLimit, ClientOrder, ClientOrderRequest, L2BookData, OrderState, PaperConfig,
+        PaperExchange, Tif, TradeInfo,
+    };
+
+    /// Joins the bid once, then records what comes back.
+    #[derive(Default)]
+    struct JoinBid {
+        placed: bool,
+        fills: usize,
+        events: Vec<OrderEvent>,
+        timers: usize,
+    }
+
+    impl EventStrategy for JoinBid {
+        fn subscriptions(&self) -> Vec<Subscription> {
+            vec![Subscription::L2Book {
+                coin: "ETH".to_string(),
+            }]
+        }
+
+        async fn on_book<E: Exchange>(
+            &mut self,
+            book: &L2BookData,
+            ctx: &mut StrategyContext<'_, E>,
+        ) -> Result<()> {
+            if self.placed {
+                return Ok(());
+            }
+            self.placed = true;
+            let bid = book.levels[0][0].px.parse().unwrap();
+            ctx.place(vec![ClientOrderRequest {
+                asset: "ETH".to_string(),
+                is_buy: true,
+                reduce_only: false,
+                limit_px: bid,
+                sz: 1.0,
+                cloid: None,
+                order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+            }])
+            .await?;
+            Ok(())
+        }
+
+        async fn on_fill<E: Exchange>(
+            &mut self,
+            _fill: &TradeInfo,
+            _ctx: &mut StrategyContext<'_, E>,
+        ) -> Result<()> {
+            self.fills += 1;
+            Ok(())
+        }
+
+        async fn on_order_update<E: Exchange>(
+            &mut self,
+            event: &OrderEvent,
+            _ctx: &mut StrategyContext<'_, E>,
+        ) -> Result<()> {
+            self.events.push(event.clone());
+            Ok(())
+        }
+
+        async fn on_timer<E: Exchange>(&mut self, ctx: &mut StrategyContext<'_, E>) -> Result<()> {
+            self.timers += 1;
+            assert_eq!(ctx.orders().open_orders().len(), 0);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runtime_dispatches_hooks() {
+        let (sender, mut receiver) = unbounded_channel();
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        })
+        .with_sender(sender);
+        let mut runtime = StrategyRuntime::new(Address::ZERO, JoinBid::default());
+        assert_eq!(runtime.subscriptions().len(), 3);
+
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&book);
+        runtime.on_message(&book, &exchange).await.unwrap();
+        assert!(matches!(
+            runtime.strategy().events.as_slice(),
+            [OrderEvent::Acked { .. }]
+        ));
+
+        let trades: Message = serde_json::from_str(
+            r#"{"channel":"trades","data":[{"coin":"ETH","side":"A","px":"1999","sz":"1","time":2,"hash":"0x0","tid":1,"users":["0x0","0x0"]}]}"#,
+        )
+        .unwrap();
+        exchange.handle_message(&trades);
+        while let Ok(message) = receiver.try_recv() {
+            runtime.on_message(&message, &exchange).await.unwrap();
+        }
+        let strategy = runtime.strategy();
+        assert_eq!(strategy.fills, 1);
+        assert!(matches!(
+            strategy.events.last(),
+            Some(OrderEvent::Done {
+                state: OrderState::Filled,
+                ..
+            })
+        ));
+
+        runtime.on_timer(&exchange).await.unwrap();
+        assert_eq!(runtime.strategy().timers, 1);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use alloy::primitives::Address;
+use uuid::Uuid;
+
+use crate::{ClientOrderRequest, ManagedOrder, EPSILON};
+
+/// What `OrderManager::place` does with an order that would trade against a resting order of
+/// an account sharing the same `SelfTradeBook`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelfTradePolicy {
+    /// Cancel the crossing resting orders, then send the order
+    CancelResting,
+    /// Reject the order without sending it
+    CancelIncoming,
+    /// Cancel the crossing resting orders and send the order reduced by their remaining size.
+    /// An order no larger than them is rejected instead and they are left resting, as resting
+    /// orders cannot be reduced through `Exchange`.
+    Decrement,
+}
+
+/// A resting order published to a `SelfTradeBook`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OwnRestingOrder {
+    pub user: Address,
+    pub cloid: Uuid,
+    pub coin: String,
+    pub is_buy: bool,
+    pub limit_px: f64,
+    pub remaining_sz: f64,
+}
+
+/// Resting orders of every `OrderManager` sharing the book, for preventing self-trades across
+/// sub-accounts and agents. Clones share the same orders.
+#[derive(Clone, Debug, Default)]
+pub struct SelfTradeBook {
+    orders: Arc<Mutex<HashMap<Uuid, OwnRestingOrder>>>,
+}
+
+impl SelfTradeBook {
+    pub fn new() -> SelfTradeBook {
+        SelfTradeBook::default()
+    }
+
+    /// Resting orders on the other side of `order`'s coin at or through its limit price.
+    pub fn crossing(&self, order: &ClientOrderRequest) -> Vec<OwnRestingOrder> {
+        self.lock()
+            .values()
+            .filter(|resting| {
+                resting.coin == order.asset
+                    && resting.is_buy != order.is_buy
+                    && if order.is_buy {
+                        order.limit_px >= resting.limit_px - EPSILON
+                    } else {
+                        order.limit_px <= resting.limit_px + EPSILON
+                    }
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Publishes `order` while it rests and withdraws it otherwise.
+    pub(crate) fn update(&self, user: Address, order: &ManagedOrder) {
+        let mut orders = self.lock();
+        if order.is_resting() {
+            orders.insert(
+                order.cloid,
+                OwnRestingOrder {
+                    user,
+                    cloid: order.cloid,
+                    coin: order.coin.clone(),
+                    is_buy: order.is_buy,
+                    limit_px: order.limit_px,
+                    remaining_sz: order.remaining_sz(),
+                },
+            );
+        } else {
+            orders.remove(&order.cloid);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Uuid, OwnRestingOrder>> {
+        self.orders.lock().expect("self-trade book lock poisoned")
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+use crate::{
+    prelude::*, Exchange, Message, PaperConfig, PaperExchange, Strategy, TradeInfo, UserData,
+    EPSILON,
+};
+
+/// Fills of one side of a shadow run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExecutionStats {
+    pub fills: usize,
+    /// Total size filled
+    pub volume: f64,
+    pub notional: f64,
+    pub fees: f64,
+    pub closed_pnl: f64,
+    /// Signed size bought minus sold by coin
+    pub positions: HashMap<String, f64>,
+}
+
+impl ExecutionStats {
+    /// Realized PnL net of fees.
+    pub fn net_pnl(&self) -> f64 {
+        self.closed_pnl - self.fees
+    }
+
+    fn record(&mut self, fill: &TradeInfo) {
+        let (Ok(px), Ok(sz)) = (fill.px.parse::<f64>(), fill.sz.parse::<f64>()) else {
+            return;
+        };
+        self.fills += 1;
+        self.volume += sz;
+        self.notional += px * sz;
+        self.fees += fill.fee.parse::<f64>().unwrap_or_default();
+        self.closed_pnl += fill.closed_pnl.parse::<f64>().unwrap_or_default();
+        let signed = if fill.side.is_buy() { sz } else { -sz };
+        *self.positions.entry(fill.coin.clone()).or_default() += signed;
+    }
+
+    fn record_message(&mut self, message: &Message) {
+        match message {
+            Message::UserFills(fills) if !fills.data.is_snapshot.unwrap_or(false) => {
+                fills.data.fills.iter().for_each(|fill| self.record(fill));
+            }
+            Message::User(user) => {
+                if let UserData::Fills(fills) = &user.data {
+                    fills.iter().for_each(|fill| self.record(fill));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Live and simulated fills side by side, with how far the simulation strayed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ShadowReport {
+    pub live: ExecutionStats,
+    pub paper: ExecutionStats,
+}
+
+impl ShadowReport {
+    /// Paper fills per live fill, 1 when the simulation fills as often as the exchange.
+    pub fn fill_ratio(&self) -> Option<f64> {
+        (self.live.fills > 0).then(|| self.paper.fills as f64 / self.live.fills as f64)
+    }
+
+    /// Live minus paper filled notional.
+    pub fn notional_divergence(&self) -> f64 {
+        self.live.notional - self.paper.notional
+    }
+
+    /// Live minus paper realized PnL net of fees.
+    pub fn pnl_divergence(&self) -> f64 {
+        self.live.net_pnl() - self.paper.net_pnl()
+    }
+
+    /// Live minus paper position, for coins where they differ.
+    pub fn position_divergence(&self) -> HashMap<String, f64> {
+        let mut divergence = self.live.positions.clone();
+        for (coin, szi) in &self.paper.positions {
+            *divergence.entry(coin.clone()).or_default() -= szi;
+        }
+        divergence.retain(|_, szi| szi.abs() > EPSILON);
+        divergence
+    }
+}
+
+/// Runs two instances of the same strategy side by side, one trading on the exchange and a
+/// shadow trading on a `PaperExchange` fed the same market data, and compares their fills and
+/// PnL to measure how faithful the simulation is before scaling size.
+///
+/// Pass every message of the live subscriptions to `on_message`: market data reaches both
+/// instances and the paper exchange, while account messages such as `userFills` and
+/// `orderUpdates` only reach the live instance. The shadow gets the paper exchange's own
+/// fills and order updates instead. Both instances should be built with the same parameters.
+#[derive(Debug)]
+pub struct ShadowComparator<S> {
+    live: S,
+    shadow: S,
+    paper: PaperExchange,
+    paper_events: UnboundedReceiver<Message>,
+    report: ShadowReport,
+}
+
+impl<S: Strategy> ShadowComparator<S> {
+    /// `paper` should use the account's fee rates and a latency close to the live round trip.
+    pub fn new(live: S, shadow: S, paper: PaperConfig) -> ShadowComparator<S> {
+        let (sender, paper_events) = unbounded_channel();
+        ShadowComparator {
+            live,
+            shadow,
+            paper: PaperExchange::new(paper).with_sender(sender),
+            paper_events,
+            report: ShadowReport::default(),
+        }
+    }
+
+    pub fn live(&self) -> &S {
+        &self.live
+    }
+
+    pub fn shadow(&self) -> &S {
+        &self.shadow
+    }
+
+    pub fn paper(&self) -> &PaperExchange {
+        &self.paper
+    }
+
+    pub fn report(&self) -> &ShadowReport {
+        &self.report
+    }
+
+    /// Feeds a live message to the instances it concerns, live orders going to `exchange`.
+    /// The shadow still sees market data when the live instance fails on it.
+    pub async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        if is_account_message(message) {
+            self.report.live.record_message(message);
+            return self.live.on_message(message, exchange).await;
+        }
+        self.paper.handle_message(message);
+        let live = self.live.on_message(message, exchange).await;
+        self.shadow.on_message(message, &self.paper).await?;
+        self.dispatch_paper_events().await?;
+        live
+    }
+
+    /// Passes the paper exchange's fills and order updates to the shadow, including those
+    /// caused by orders it places while handling them.
+    async fn dispatch_paper_events(&mut self) -> Result<()> {
+        while let Ok(event) = self.paper_events.try_recv() {
+            self.report.paper.record_message(&event);
+            self.shadow.on_message(&event, &self.paper).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Messages about the account rather than the market, only meaningful to the live instance.
+fn is_account_message(message: &Message) -> bool {
+    matches!(
+        message,
+        Message::User(_)
+            | Message::UserFills(_)
+            | Message::OrderUpdates(_)
+            | Message::UserFundings(_)
+            | Message::UserNonFundingLedgerUpdates(_)
+            | Message::Notification(_)
+            | Message::WebData2(_)
+            | Message::ActiveAssetData(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{ClientLimit, ClientOrder, ClientOrderRequest, Tif};
+
+    /// Joins the bid of the first book it sees.
+    #[derive(Default)]
+    struct JoinBid {
+        placed: bool,
+    }
+
+    impl Strategy for JoinBid {
+        async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+            let Message::L2Book(book) = message else {
+                return Ok(());
+            };
+            if std::mem::replace(&mut self.placed, true) {
+                return Ok(());
+            }
+            exchange
+                .order(ClientOrderRequest {
+                    asset: "ETH".to_string(),
+                    is_buy: true,
+                    reduce_only: false,
+                    limit_px: book.data.levels[0][0].px.parse().unwrap(),
+                    sz: 1.0,
+                    cloid: None,
+                    order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Gtc }),
+                })
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compares_live_and_paper_fills() {
+        let config = PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        };
+        // A second paper exchange stands in for the live one, filling only half the order
+        let (sender, mut live_events) = unbounded_channel();
+        let live = PaperExchange::new(config.clone()).with_sender(sender);
+        let mut comparator = ShadowComparator::new(JoinBid::default(), JoinBid::default(), config);
+
+        let book: Message = serde_json::from_str(
+            r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"1999","sz":"10","n":1}],[{"px":"2001","sz":"10","n":1}]]}}"#,
+        )
+        .unwrap();
+        live.handle_message(&book);
+        comparator.on_message(&book, &live).await.unwrap();
+        assert_eq!(comparator.paper().open_orders().len(), 1);
+
+        let trades = |sz: &str| -> Message {
+            serde_json::from_str(&format!(
+                r#"{{"channel":"trades","data":[{{"coin":"ETH","side":"A","px":"1999","sz":"{sz}","time":2,"hash":"0x0","tid":1,"users":["0x0","0x0"]}}]}}"#
+            ))
+            .unwrap()
+        };
+        live.handle_message(&trades("0.5"));
+        comparator.on_message(&trades("1"), &live).await.unwrap();
+        while let Ok(message) = live_events.try_recv() {
+            comparator.on_message(&message, &live).await.unwrap();
+        }
+
+        let report = comparator.report();
+        assert_eq!((report.live.fills, report.paper.fills), (1, 1));
+        assert!((report.notional_divergence() + 999.5).abs() < 1e-6);
+        assert!((report.position_divergence()["ETH"] + 0.5).abs() < 1e-9);
+        assert!(report.pnl_divergence() > 0.0);
+    }
+}
@@ -0,0 +1,111 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::{
+    prelude::*, BulkRequestStatus, ClientOrderRequest, Exchange, L2BookData, Message, OrderEvent,
+    OrderManager, Subscription, Trade, TradeInfo,
+};
+
+/// Decision logic driven by websocket messages and placing orders through `Exchange`.
+///
+/// The same implementation runs live, fed from `InfoClient::subscribe` with an
+/// `ExchangeClient`, against a `PaperExchange`, or in a `Backtester` over historical data.
+/// Fills and order updates for the strategy's own orders arrive as `userFills` and
+/// `orderUpdates` messages.
+pub trait Strategy {
+    fn on_message<E: Exchange>(
+        &mut self,
+        message: &Message,
+        exchange: &E,
+    ) -> impl Future<Output = Result<()>>;
+}
+
+/// Decision logic split into hooks for the events a strategy usually reacts to, run by a
+/// `StrategyRuntime` that owns the subscriptions, the `OrderManager` and the event loop.
+///
+/// Hooks default to doing nothing. Orders placed through the context are tracked by the
+/// runtime's `OrderManager`, and their lifecycle comes back through `on_order_update`.
+pub trait EventStrategy {
+    /// Market data the strategy needs. Order updates and fills of the account are subscribed
+    /// by the runtime.
+    fn subscriptions(&self) -> Vec<Subscription>;
+
+    fn on_book<E: Exchange>(
+        &mut self,
+        _book: &L2BookData,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+
+    /// Called once per trade of a `trades` message.
+    fn on_trade<E: Exchange>(
+        &mut self,
+        _trade: &Trade,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+
+    /// Called once per new fill of the account, after the `OrderManager` has applied it.
+    /// Snapshot fills sent on subscribing are not passed on.
+    fn on_fill<E: Exchange>(
+        &mut self,
+        _fill: &TradeInfo,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+
+    /// Called for every lifecycle change of an order placed through the context.
+    fn on_order_update<E: Exchange>(
+        &mut self,
+        _event: &OrderEvent,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+
+    /// Called every `StrategyRuntime::with_timer` interval.
+    fn on_timer<E: Exchange>(
+        &mut self,
+        _ctx: &mut StrategyContext<'_, E>,
+    ) -> impl Future<Output = Result<()>> {
+        async { Ok(()) }
+    }
+}
+
+/// Order entry handed to `EventStrategy` hooks.
+#[derive(Debug)]
+pub struct StrategyContext<'a, E> {
+    exchange: &'a E,
+    orders: &'a mut OrderManager,
+}
+
+impl<'a, E: Exchange> StrategyContext<'a, E> {
+    pub(crate) fn new(exchange: &'a E, orders: &'a mut OrderManager) -> Self {
+        StrategyContext { exchange, orders }
+    }
+
+    pub fn exchange(&self) -> &E {
+        self.exchange
+    }
+
+    pub fn orders(&self) -> &OrderManager {
+        self.orders
+    }
+
+    /// Places `orders` through the `OrderManager`, see `OrderManager::place`.
+    pub async fn place(
+        &mut self,
+        orders: Vec<ClientOrderRequest>,
+    ) -> Result<Vec<BulkRequestStatus<Uuid>>> {
+        self.orders.place(self.exchange, orders).await
+    }
+
+    /// Cancels tracked orders by cloid, see `OrderManager::cancel`.
+    pub async fn cancel(&mut self, cloids: Vec<Uuid>) -> Result<Vec<BulkRequestStatus<Uuid>>> {
+        self.orders.cancel(self.exchange, cloids).await
+    }
+}
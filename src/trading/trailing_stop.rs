@@ -0,0 +1,357 @@
+use std::path::{Path, PathBuf};
+
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::persist::{load_json, save_json};
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, AssetCtx, ClientLimit,
+    ClientOrder, ClientOrderRequest, Error, Exchange, Message, OrderManager, OrderState,
+    RoundingMode, Strategy, Subscription, Tif, EPSILON,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailDistance {
+    Absolute(f64),
+    Bps(f64),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailPriceSource {
+    /// Mid from `allMids`
+    #[default]
+    Mid,
+    /// Mark price from `activeAssetCtx`
+    Mark,
+}
+
+fn default_slippage_bps() -> f64 {
+    50.0
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrailingStopConfig {
+    pub coin: String,
+    pub sz_decimals: u32,
+    /// Side of the close order, `false` to protect a long
+    pub is_buy: bool,
+    pub sz: f64,
+    pub distance: TrailDistance,
+    #[serde(default)]
+    pub price_source: TrailPriceSource,
+    /// How far through the trigger price the reduce-only `Ioc` close order is priced
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: f64,
+}
+
+/// Everything needed to resume a trailing stop, saved whenever it changes when a state file
+/// is set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrailingStopState {
+    pub config: TrailingStopConfig,
+    /// Best price seen: the high for a long, the low for a short
+    pub extreme_px: Option<f64>,
+    pub triggered: bool,
+    /// Size closed since the trigger
+    pub filled_sz: f64,
+    pub done: bool,
+}
+
+/// Trails a stop behind the best price seen and closes with reduce-only `Ioc` orders once the
+/// price comes back by the configured distance, retrying on each price update until `sz` is
+/// closed or the position is flat.
+#[derive(Debug)]
+pub struct TrailingStop {
+    state: TrailingStopState,
+    manager: OrderManager,
+    state_file: Option<PathBuf>,
+    close: Option<Uuid>,
+}
+
+impl TrailingStop {
+    /// `user` is the account orders are placed for, the vault if trading for one.
+    pub fn new(user: Address, config: TrailingStopConfig) -> Result<TrailingStop> {
+        validate(&config)?;
+        Ok(TrailingStop {
+            state: TrailingStopState {
+                config,
+                extreme_px: None,
+                triggered: false,
+                filled_sz: 0.0,
+                done: false,
+            },
+            manager: OrderManager::new(user),
+            state_file: None,
+            close: None,
+        })
+    }
+
+    /// Loads a stop saved by a previous run, keeping its high-water mark and saving to the
+    /// same file.
+    pub fn load(user: Address, path: impl AsRef<Path>) -> Result<TrailingStop> {
+        let path = path.as_ref();
+        let state: TrailingStopState = load_json(path)?;
+        validate(&state.config)?;
+        Ok(TrailingStop {
+            state,
+            manager: OrderManager::new(user),
+            state_file: Some(path.to_path_buf()),
+            close: None,
+        })
+    }
+
+    pub fn with_state_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn state(&self) -> &TrailingStopState {
+        &self.state
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state.done
+    }
+
+    /// Price at which the stop triggers, `None` until a price has been seen.
+    pub fn stop_px(&self) -> Option<f64> {
+        let extreme_px = self.state.extreme_px?;
+        // A long is protected by a sell, which triggers below the high
+        let sign = if self.state.config.is_buy { 1.0 } else { -1.0 };
+        Some(match self.state.config.distance {
+            TrailDistance::Absolute(distance) => extreme_px + sign * distance,
+            TrailDistance::Bps(bps) => apply_bps(extreme_px, sign * bps),
+        })
+    }
+
+    /// Subscriptions whose messages should be passed to `on_message`.
+    pub fn subscriptions(&self) -> Vec<Subscription> {
+        let mut subscriptions = self.manager.subscriptions();
+        subscriptions.push(match self.state.config.price_source {
+            TrailPriceSource::Mid => Subscription::AllMids,
+            TrailPriceSource::Mark => Subscription::ActiveAssetCtx {
+                coin: self.state.config.coin.clone(),
+            },
+        });
+        subscriptions
+    }
+
+    /// Applies a new price, moving the stop or closing if it is crossed.
+    pub async fn on_price<E: Exchange>(&mut self, exchange: &E, px: f64) -> Result<()> {
+        self.update_close();
+        if self.state.done {
+            return Ok(());
+        }
+        if !self.state.triggered {
+            let is_buy = self.state.config.is_buy;
+            let better = self.state.extreme_px.is_none_or(|extreme_px| {
+                if is_buy {
+                    px < extreme_px
+                } else {
+                    px > extreme_px
+                }
+            });
+            if better {
+                self.state.extreme_px = Some(px);
+                return self.save();
+            }
+            let Some(stop_px) = self.stop_px() else {
+                return Ok(());
+            };
+            if (is_buy && px < stop_px) || (!is_buy && px > stop_px) {
+                return Ok(());
+            }
+            info!(
+                "Trailing stop on {} triggered at {px}, best was {:?}",
+                self.state.config.coin, self.state.extreme_px
+            );
+            self.state.triggered = true;
+            self.save()?;
+        }
+        if self.close.is_none() {
+            self.place_close(exchange, px).await?;
+        }
+        Ok(())
+    }
+
+    async fn place_close<E: Exchange>(&mut self, exchange: &E, px: f64) -> Result<()> {
+        let config = &self.state.config;
+        let lot = lot_size(config.sz_decimals);
+        let sz = round_to_tick(config.sz - self.state.filled_sz, lot, RoundingMode::Down);
+        if sz < lot - EPSILON {
+            self.state.done = true;
+            return self.save();
+        }
+        let bps = if config.is_buy {
+            config.slippage_bps
+        } else {
+            -config.slippage_bps
+        };
+        let limit_px = apply_bps(px, bps);
+        let tick = price_tick_size(limit_px, config.sz_decimals, false);
+        let mode = if config.is_buy {
+            RoundingMode::Up
+        } else {
+            RoundingMode::Down
+        };
+        let order = ClientOrderRequest {
+            asset: config.coin.clone(),
+            is_buy: config.is_buy,
+            reduce_only: true,
+            limit_px: round_to_tick(limit_px, tick, mode),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        };
+        let statuses = self.manager.place(exchange, vec![order]).await?;
+        self.close = statuses.first().map(|status| status.request);
+        self.update_close();
+        Ok(())
+    }
+
+    /// Accounts for a finished close order, leaving the next price update to retry the rest.
+    fn update_close(&mut self) {
+        let Some(order) = self.close.and_then(|cloid| self.manager.order(cloid)) else {
+            return;
+        };
+        match &order.state {
+            OrderState::Filled | OrderState::Canceled => {}
+            OrderState::Rejected(reason) => {
+                // Usually the position is already flat
+                warn!("Trailing stop close order rejected: {reason}");
+                self.state.done = true;
+            }
+            _ => return,
+        }
+        self.state.filled_sz += order.filled_sz;
+        let lot = lot_size(self.state.config.sz_decimals);
+        if self.state.config.sz - self.state.filled_sz < lot - EPSILON {
+            self.state.done = true;
+        }
+        self.close = None;
+        self.manager.remove_done();
+        if let Err(err) = self.save() {
+            warn!("Could not save trailing stop state: {err}");
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        match &self.state_file {
+            Some(path) => save_json(path, &self.state),
+            None => Ok(()),
+        }
+    }
+}
+
+fn validate(config: &TrailingStopConfig) -> Result<()> {
+    let distance = match config.distance {
+        TrailDistance::Absolute(distance) | TrailDistance::Bps(distance) => distance,
+    };
+    if config.sz <= 0.0 || distance <= 0.0 {
+        return Err(Error::InvalidConfig(
+            "Trailing stop needs a positive size and distance".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl Strategy for TrailingStop {
+    async fn on_message<E: Exchange>(&mut self, message: &Message, exchange: &E) -> Result<()> {
+        self.manager.handle_message(message);
+        let coin = &self.state.config.coin;
+        let px = match (self.state.config.price_source, message) {
+            (TrailPriceSource::Mid, Message::AllMids(all_mids)) => {
+                all_mids.data.mids.get(coin).and_then(|px| px.parse().ok())
+            }
+            (TrailPriceSource::Mark, Message::ActiveAssetCtx(ctx)) if &ctx.data.coin == coin => {
+                let shared = match &ctx.data.ctx {
+                    AssetCtx::Perps(ctx) => &ctx.shared,
+                    AssetCtx::Spot(ctx) => &ctx.shared,
+                };
+                shared.mark_px.parse().ok()
+            }
+            _ => None,
+        };
+        match px {
+            Some(px) => self.on_price(exchange, px).await,
+            None => {
+                self.update_close();
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{PaperConfig, PaperExchange};
+
+    fn mid(px: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"allMids","data":{{"mids":{{"ETH":"{px}"}}}}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn book(bid: &str, ask: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"channel":"l2Book","data":{{"coin":"ETH","time":1,"levels":[[{{"px":"{bid}","sz":"10","n":1}}],[{{"px":"{ask}","sz":"10","n":1}}]]}}}}"#
+        ))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_trailing_stop_follows_high_and_closes() {
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        exchange.handle_message(&book("1999", "2001"));
+        let buy = ClientOrderRequest {
+            asset: "ETH".to_string(),
+            is_buy: true,
+            reduce_only: false,
+            limit_px: 2001.0,
+            sz: 1.0,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        };
+        exchange.order(buy).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("trailing_stop_{}.json", std::process::id()));
+        let config: TrailingStopConfig = serde_json::from_str(
+            r#"{"coin":"ETH","sz_decimals":2,"is_buy":false,"sz":1,"distance":{"bps":100}}"#,
+        )
+        .unwrap();
+        let mut stop = TrailingStop::new(Address::ZERO, config)
+            .unwrap()
+            .with_state_file(&path);
+        for px in ["2000", "2100", "2080"] {
+            stop.on_message(&mid(px), &exchange).await.unwrap();
+        }
+        assert_eq!(stop.state().extreme_px, Some(2100.0));
+        assert_eq!(stop.stop_px(), Some(2079.0));
+        assert!(!stop.state().triggered);
+
+        let loaded = TrailingStop::load(Address::ZERO, &path).unwrap();
+        assert_eq!(loaded.state().extreme_px, Some(2100.0));
+
+        exchange.handle_message(&book("2069", "2071"));
+        stop.on_message(&mid("2070"), &exchange).await.unwrap();
+        assert!(stop.state().triggered);
+        assert!(stop.is_done());
+        assert!((stop.state().filled_sz - 1.0).abs() < EPSILON);
+        assert!(exchange
+            .position("ETH")
+            .is_none_or(|p| p.szi.abs() < EPSILON));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,382 @@
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    apply_bps, lot_size, prelude::*, price_tick_size, round_to_tick, ClientLimit, ClientOrder,
+    ClientOrderRequest, Error, Exchange, ExchangeDataStatus, ExchangeError, ExchangeResponseStatus,
+    RoundingMode, Tif, EPSILON,
+};
+
+/// One side of a two-legged trade.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TradeLeg {
+    /// Perp coin or spot pair, e.g. `ETH` or `UETH/USDC`
+    pub asset: String,
+    pub is_buy: bool,
+    pub sz: f64,
+    /// Worst price the leg is first sent at
+    pub limit_px: f64,
+    pub sz_decimals: u32,
+    pub is_spot: bool,
+}
+
+impl TradeLeg {
+    fn lot(&self) -> f64 {
+        lot_size(self.sz_decimals)
+    }
+
+    fn order(&self, is_buy: bool, sz: f64, px: f64) -> Option<ClientOrderRequest> {
+        let sz = round_to_tick(sz, self.lot(), RoundingMode::Down);
+        if sz < self.lot() - EPSILON || px <= 0.0 {
+            return None;
+        }
+        let tick = price_tick_size(px, self.sz_decimals, self.is_spot);
+        Some(ClientOrderRequest {
+            asset: self.asset.clone(),
+            is_buy,
+            reduce_only: false,
+            limit_px: round_to_tick(px, tick, RoundingMode::Nearest),
+            sz,
+            cloid: None,
+            order_type: ClientOrder::Limit(ClientLimit { tif: Tif::Ioc }),
+        })
+    }
+}
+
+/// `bps` further through the book than `px` for an order on side `is_buy`.
+fn through(is_buy: bool, px: f64, bps: f64) -> f64 {
+    apply_bps(px, if is_buy { bps } else { -bps })
+}
+
+/// What `TwoLegExecutor` does with a residual the retries did not close.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResidualPolicy {
+    /// Leaves the legs unbalanced for the caller to handle
+    Leave,
+    /// Completes the lagging leg `slippage_bps` through its limit price
+    HedgeAtMarket { slippage_bps: f64 },
+    /// Trades the leading leg back to the lagging one, `slippage_bps` through its average fill
+    Unwind { slippage_bps: f64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TwoLegConfig {
+    pub first: TradeLeg,
+    pub second: TradeLeg,
+    /// Times the lagging leg's shortfall is sent again before the residual policy applies
+    pub retries: u32,
+    /// How much further through its limit price each retry goes than the previous one
+    pub retry_slippage_bps: f64,
+    pub residual: ResidualPolicy,
+}
+
+/// What one leg traded.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegFill {
+    /// Size traded in the leg's direction
+    pub filled_sz: f64,
+    /// Size traded back by an unwind
+    pub unwound_sz: f64,
+    /// Notional of `filled_sz`
+    pub notional: f64,
+    pub orders: u32,
+}
+
+impl LegFill {
+    /// Size the leg holds after unwinding.
+    pub fn net_sz(&self) -> f64 {
+        self.filled_sz - self.unwound_sz
+    }
+
+    pub fn avg_px(&self) -> Option<f64> {
+        (self.filled_sz > EPSILON).then(|| self.notional / self.filled_sz)
+    }
+}
+
+/// Result of `TwoLegExecutor::execute`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TwoLegOutcome {
+    pub first: LegFill,
+    pub second: LegFill,
+    /// Share of its size the first leg holds less that of the second, 0 when hedged
+    pub imbalance: f64,
+    /// Whether the legs match to within a lot
+    pub balanced: bool,
+}
+
+/// Trades two legs meant to hedge each other, such as a spot buy against a perp short, and
+/// closes the gap left when one fills less than the other.
+///
+/// Both legs go out as `Ioc` orders in one action, which the exchange processes in order but
+/// not atomically. Legs are balanced by the share of their size held, so they may differ in
+/// size. The lagging leg's shortfall is retried, each time further through its limit price,
+/// and whatever is left after the retries is handled by the residual policy.
+#[derive(Clone, Debug)]
+pub struct TwoLegExecutor {
+    config: TwoLegConfig,
+}
+
+impl TwoLegExecutor {
+    pub fn new(config: TwoLegConfig) -> TwoLegExecutor {
+        TwoLegExecutor { config }
+    }
+
+    pub fn config(&self) -> &TwoLegConfig {
+        &self.config
+    }
+
+    pub async fn execute<E: Exchange>(&self, exchange: &E) -> Result<TwoLegOutcome> {
+        let config = &self.config;
+        let legs = [&config.first, &config.second];
+        let mut fills = [LegFill::default(), LegFill::default()];
+
+        let orders: Vec<(usize, ClientOrderRequest)> = legs
+            .iter()
+            .enumerate()
+            .filter_map(|(index, leg)| Some((index, leg.order(leg.is_buy, leg.sz, leg.limit_px)?)))
+            .collect();
+        let (indices, orders): (Vec<usize>, Vec<ClientOrderRequest>) = orders.into_iter().unzip();
+        for (index, fill) in indices.into_iter().zip(send(exchange, orders).await?) {
+            record(&mut fills[index], fill, false);
+        }
+
+        for attempt in 1..=config.retries {
+            let Some((lagging, shortfall, _)) = self.residual(&fills) else {
+                break;
+            };
+            let leg = legs[lagging];
+            let bps = config.retry_slippage_bps * attempt as f64;
+            info!(
+                asset = leg.asset,
+                shortfall, attempt, "Retrying lagging leg"
+            );
+            let px = through(leg.is_buy, leg.limit_px, bps);
+            trade(
+                exchange,
+                &mut fills[lagging],
+                leg,
+                leg.is_buy,
+                shortfall,
+                px,
+            )
+            .await?;
+        }
+
+        if let Some((lagging, shortfall, excess)) = self.residual(&fills) {
+            match config.residual {
+                ResidualPolicy::Leave => {}
+                ResidualPolicy::HedgeAtMarket { slippage_bps } => {
+                    let leg = legs[lagging];
+                    warn!(asset = leg.asset, shortfall, "Hedging residual at market");
+                    let px = through(leg.is_buy, leg.limit_px, slippage_bps);
+                    trade(
+                        exchange,
+                        &mut fills[lagging],
+                        leg,
+                        leg.is_buy,
+                        shortfall,
+                        px,
+                    )
+                    .await?;
+                }
+                ResidualPolicy::Unwind { slippage_bps } => {
+                    let leading = 1 - lagging;
+                    let leg = legs[leading];
+                    let entry_px = fills[leading].avg_px().unwrap_or(leg.limit_px);
+                    let px = through(!leg.is_buy, entry_px, slippage_bps);
+                    warn!(asset = leg.asset, excess, "Unwinding leading leg");
+                    trade(exchange, &mut fills[leading], leg, !leg.is_buy, excess, px).await?;
+                }
+            }
+        }
+
+        let balanced = self.residual(&fills).is_none();
+        let [first, second] = fills;
+        Ok(TwoLegOutcome {
+            imbalance: first.net_sz() / config.first.sz - second.net_sz() / config.second.sz,
+            first,
+            second,
+            balanced,
+        })
+    }
+
+    /// The lagging leg, its shortfall and the leading leg's excess, unless both are under a
+    /// lot.
+    fn residual(&self, fills: &[LegFill; 2]) -> Option<(usize, f64, f64)> {
+        let legs = [&self.config.first, &self.config.second];
+        let ratios = [0, 1].map(|index| fills[index].net_sz() / legs[index].sz);
+        let (leading, lagging) = if ratios[0] >= ratios[1] {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let gap = ratios[leading] - ratios[lagging];
+        let shortfall = gap * legs[lagging].sz;
+        let excess = gap * legs[leading].sz;
+        (shortfall >= legs[lagging].lot() - EPSILON || excess >= legs[leading].lot() - EPSILON)
+            .then_some((lagging, shortfall, excess))
+    }
+}
+
+/// Sends one `Ioc` order on `leg`, recording what it filled.
+async fn trade<E: Exchange>(
+    exchange: &E,
+    fill: &mut LegFill,
+    leg: &TradeLeg,
+    is_buy: bool,
+    sz: f64,
+    px: f64,
+) -> Result<()> {
+    let Some(order) = leg.order(is_buy, sz, px) else {
+        return Ok(());
+    };
+    if let Some(filled) = send(exchange, vec![order]).await?.pop() {
+        record(fill, filled, is_buy != leg.is_buy);
+    }
+    Ok(())
+}
+
+/// Sends `orders`, returning the size and average price each filled.
+async fn send<E: Exchange>(
+    exchange: &E,
+    orders: Vec<ClientOrderRequest>,
+) -> Result<Vec<Option<(f64, f64)>>> {
+    if orders.is_empty() {
+        return Ok(Vec::new());
+    }
+    let count = orders.len();
+    let statuses = match exchange.bulk_order(orders).await? {
+        ExchangeResponseStatus::Ok(response) => {
+            response.data.map(|data| data.statuses).unwrap_or_default()
+        }
+        ExchangeResponseStatus::Err(err) => return Err(Error::GenericRequest(err.to_string())),
+    };
+    let mut fills: Vec<Option<(f64, f64)>> = statuses
+        .into_iter()
+        .map(|status| match status {
+            ExchangeDataStatus::Filled(filled) => {
+                Some((filled.total_sz.parse().ok()?, filled.avg_px.parse().ok()?))
+            }
+            ExchangeDataStatus::Error(ExchangeError::IocCancelled(_)) => None,
+            other => {
+                warn!("Leg order not filled: {other:?}");
+                None
+            }
+        })
+        .collect();
+    fills.resize(count, None);
+    Ok(fills)
+}
+
+fn record(fill: &mut LegFill, filled: Option<(f64, f64)>, unwind: bool) {
+    fill.orders += 1;
+    let Some((sz, px)) = filled else {
+        return;
+    };
+    if unwind {
+        fill.unwound_sz += sz;
+    } else {
+        fill.filled_sz += sz;
+        fill.notional += sz * px;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{Message, PaperConfig, PaperExchange};
+
+    fn book(coin: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) -> Message {
+        let levels = |levels: &[(f64, f64)]| {
+            levels
+                .iter()
+                .map(|(px, sz)| serde_json::json!({"px": px.to_string(), "sz": sz.to_string(), "n": 1}))
+                .collect::<Vec<_>>()
+        };
+        serde_json::from_value(serde_json::json!({
+            "channel": "l2Book",
+            "data": {"coin": coin, "time": 1, "levels": [levels(bids), levels(asks)]},
+        }))
+        .unwrap()
+    }
+
+    fn paper() -> PaperExchange {
+        let exchange = PaperExchange::new(PaperConfig {
+            latency: Duration::ZERO,
+            ..PaperConfig::default()
+        });
+        exchange.handle_message(&book("ETH", &[(1999.0, 10.0)], &[(2001.0, 10.0)]));
+        // Only a quarter of the short leg is within its limit price
+        exchange.handle_message(&book(
+            "SOL",
+            &[(100.0, 0.5), (94.0, 10.0)],
+            &[(101.0, 10.0)],
+        ));
+        exchange
+    }
+
+    fn config(residual: ResidualPolicy) -> TwoLegConfig {
+        TwoLegConfig {
+            first: TradeLeg {
+                asset: "ETH".to_string(),
+                is_buy: true,
+                sz: 1.0,
+                limit_px: 2010.0,
+                sz_decimals: 2,
+                is_spot: false,
+            },
+            second: TradeLeg {
+                asset: "SOL".to_string(),
+                is_buy: false,
+                sz: 2.0,
+                limit_px: 99.0,
+                sz_decimals: 1,
+                is_spot: false,
+            },
+            retries: 1,
+            retry_slippage_bps: 50.0,
+            residual,
+        }
+    }
+
+    fn szi(exchange: &PaperExchange, coin: &str) -> f64 {
+        exchange.position(coin).map_or(0.0, |p| p.szi)
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_applies_residual_policy() {
+        let exchange = paper();
+        let executor = TwoLegExecutor::new(config(ResidualPolicy::Leave));
+        let outcome = executor.execute(&exchange).await.unwrap();
+        // The retry does not reach the next level
+        assert_eq!(outcome.second.orders, 2);
+        assert!((outcome.imbalance - 0.75).abs() < 1e-9);
+        assert!(!outcome.balanced);
+        assert_eq!(outcome.first.avg_px(), Some(2001.0));
+
+        let exchange = paper();
+        let executor = TwoLegExecutor::new(config(ResidualPolicy::Unwind {
+            slippage_bps: 100.0,
+        }));
+        let outcome = executor.execute(&exchange).await.unwrap();
+        assert!(outcome.balanced);
+        assert!((outcome.first.unwound_sz - 0.75).abs() < 1e-9);
+        assert!((szi(&exchange, "ETH") - 0.25).abs() < 1e-9);
+        assert!((szi(&exchange, "SOL") + 0.5).abs() < 1e-9);
+
+        let exchange = paper();
+        let executor = TwoLegExecutor::new(config(ResidualPolicy::HedgeAtMarket {
+            slippage_bps: 700.0,
+        }));
+        let outcome = executor.execute(&exchange).await.unwrap();
+        assert!(outcome.balanced);
+        assert!((szi(&exchange, "ETH") - 1.0).abs() < 1e-9);
+        assert!((szi(&exchange, "SOL") + 2.0).abs() < 1e-9);
+        assert!((outcome.second.avg_px().unwrap() - (50.0 + 141.0) / 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,309 @@
+use std::{fmt, future::Future, pin::pin, time::Duration};
+
+use alloy::primitives::Address;
+use tracing::{info, warn};
+
+use crate::{
+    prelude::*, rt, ExchangeClient, ExchangeResponseStatus, InfoClient, Timestamp,
+    VaultDetailsResponse, EPSILON,
+};
+
+/// Smallest deposit the exchange accepts into a vault, in USDC
+const MIN_VAULT_DEPOSIT: f64 = 5.0;
+
+/// One follower's stake, parsed from `vaultDetails`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FollowerEquity {
+    pub user: Address,
+    pub equity: f64,
+    pub pnl: f64,
+    pub all_time_pnl: f64,
+    /// Share of the vault's equity
+    pub fraction: f64,
+    /// Until when, in ms, the stake cannot be withdrawn
+    pub lockup_until: Option<Timestamp>,
+}
+
+/// A vault's equity split between its leader and followers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VaultState {
+    pub vault: Address,
+    pub leader: Address,
+    pub equity: f64,
+    pub leader_equity: f64,
+    pub leader_fraction: f64,
+    /// Every depositor other than the leader, largest stake first
+    pub followers: Vec<FollowerEquity>,
+    pub max_distributable: f64,
+    pub max_withdrawable: f64,
+    pub is_closed: bool,
+}
+
+impl VaultState {
+    /// Vault equity is the sum of the depositors' stakes, of which the leader holds
+    /// `leader_fraction`.
+    pub fn from_details(details: &VaultDetailsResponse) -> VaultState {
+        let parse = |value: &str| value.parse::<f64>().unwrap_or_default();
+        let stakes: Vec<_> = details
+            .followers
+            .iter()
+            .filter(|follower| follower.user != details.leader)
+            .map(|follower| {
+                (
+                    follower,
+                    parse(&follower.vault_equity),
+                    parse(&follower.pnl),
+                    parse(&follower.all_time_pnl),
+                )
+            })
+            .collect();
+        let follower_equity: f64 = stakes.iter().map(|(_, equity, ..)| equity).sum();
+        let equity = if details.leader_fraction < 1.0 {
+            follower_equity / (1.0 - details.leader_fraction)
+        } else {
+            follower_equity
+        };
+        let mut followers: Vec<_> = stakes
+            .into_iter()
+            .map(|(follower, stake, pnl, all_time_pnl)| FollowerEquity {
+                user: follower.user,
+                equity: stake,
+                pnl,
+                all_time_pnl,
+                fraction: if equity > EPSILON {
+                    stake / equity
+                } else {
+                    0.0
+                },
+                lockup_until: follower.lockup_until,
+            })
+            .collect();
+        followers.sort_by(|a, b| b.equity.total_cmp(&a.equity));
+        VaultState {
+            vault: details.vault_address,
+            leader: details.leader,
+            equity,
+            leader_equity: equity - follower_equity,
+            leader_fraction: details.leader_fraction,
+            followers,
+            max_distributable: details.max_distributable,
+            max_withdrawable: details.max_withdrawable,
+            is_closed: details.is_closed,
+        }
+    }
+
+    pub fn follower(&self, user: Address) -> Option<&FollowerEquity> {
+        self.followers.iter().find(|follower| follower.user == user)
+    }
+}
+
+type DistributableCallback = Box<dyn FnMut(&VaultState) + Send>;
+
+/// Runs the routine side of leading a vault: keeps the leader's stake above the minimum the
+/// exchange requires, by depositing with `vaultTransfer` when followers' deposits or profits
+/// dilute it, and checks on a schedule whether enough profit has built up to distribute.
+///
+/// Every check reads the leader and all followers from a single `vaultDetails` query.
+pub struct VaultOperator {
+    vault: Address,
+    min_leader_fraction: f64,
+    stake_buffer: f64,
+    max_top_up: f64,
+    distribution_threshold: f64,
+    on_distributable: Option<DistributableCallback>,
+}
+
+impl fmt::Debug for VaultOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultOperator")
+            .field("vault", &self.vault)
+            .field("min_leader_fraction", &self.min_leader_fraction)
+            .field("stake_buffer", &self.stake_buffer)
+            .field("max_top_up", &self.max_top_up)
+            .field("distribution_threshold", &self.distribution_threshold)
+            .finish_non_exhaustive()
+    }
+}
+
+impl VaultOperator {
+    /// Keeps the leader at 5% of the vault, the exchange's minimum, topping up to 5.5% with at
+    /// most 10000 USDC at a time.
+    pub fn new(vault: Address) -> VaultOperator {
+        VaultOperator {
+            vault,
+            min_leader_fraction: 0.05,
+            stake_buffer: 0.005,
+            max_top_up: 10_000.0,
+            distribution_threshold: 0.0,
+            on_distributable: None,
+        }
+    }
+
+    pub fn with_min_leader_fraction(mut self, fraction: f64) -> Self {
+        self.min_leader_fraction = fraction;
+        self
+    }
+
+    /// Fraction above the minimum a top-up restores, so small deposits do not trigger another.
+    pub fn with_stake_buffer(mut self, buffer: f64) -> Self {
+        self.stake_buffer = buffer;
+        self
+    }
+
+    /// Largest single deposit made to restore the leader's stake, in USDC.
+    pub fn with_max_top_up(mut self, usdc: f64) -> Self {
+        self.max_top_up = usdc;
+        self
+    }
+
+    /// Smallest distributable amount, in USDC, reported to the `on_distributable` callback.
+    pub fn with_distribution_threshold(mut self, usdc: f64) -> Self {
+        self.distribution_threshold = usdc;
+        self
+    }
+
+    pub fn with_on_distributable(mut self, f: impl FnMut(&VaultState) + Send + 'static) -> Self {
+        self.on_distributable = Some(Box::new(f));
+        self
+    }
+
+    pub fn vault(&self) -> Address {
+        self.vault
+    }
+
+    pub async fn state(&self, info: &InfoClient) -> Result<VaultState> {
+        let details = info.vault_details(self.vault, None).await?;
+        Ok(VaultState::from_details(&details))
+    }
+
+    /// USDC the leader must deposit to hold `min_leader_fraction` plus the buffer, `None` when
+    /// the stake is already above the minimum. Capped at `max_top_up` and raised to the
+    /// smallest deposit the exchange accepts.
+    pub fn leader_top_up(&self, state: &VaultState) -> Option<f64> {
+        if state.is_closed || state.leader_fraction >= self.min_leader_fraction {
+            return None;
+        }
+        // Depositing d gives (leader + d) / (equity + d) = target
+        let target = (self.min_leader_fraction + self.stake_buffer).min(1.0 - EPSILON);
+        let needed = (target * state.equity - state.leader_equity) / (1.0 - target);
+        Some(needed.min(self.max_top_up).max(MIN_VAULT_DEPOSIT))
+    }
+
+    /// Deposits from the leader's account when its stake has fallen below the minimum. The
+    /// exchange client must sign for the leader.
+    pub async fn ensure_leader_stake(
+        &self,
+        info: &InfoClient,
+        exchange: &ExchangeClient,
+    ) -> Result<Option<ExchangeResponseStatus>> {
+        let state = self.state(info).await?;
+        let Some(usdc) = self.leader_top_up(&state) else {
+            return Ok(None);
+        };
+        info!(
+            vault = %self.vault,
+            leader_fraction = state.leader_fraction,
+            usdc,
+            "Restoring vault leader stake"
+        );
+        let usd = (usdc * 1e6).ceil() as u64;
+        exchange
+            .vault_transfer(true, usd, Some(self.vault), None)
+            .await
+            .map(Some)
+    }
+
+    /// Fetches the vault and passes it to the `on_distributable` callback when at least the
+    /// threshold can be distributed, returning the distributable amount if so.
+    pub async fn check_distribution(&mut self, info: &InfoClient) -> Result<Option<f64>> {
+        let state = self.state(info).await?;
+        Ok(self.distributable(&state))
+    }
+
+    fn distributable(&mut self, state: &VaultState) -> Option<f64> {
+        if state.max_distributable <= EPSILON
+            || state.max_distributable < self.distribution_threshold
+        {
+            return None;
+        }
+        if let Some(callback) = &mut self.on_distributable {
+            callback(state);
+        }
+        Some(state.max_distributable)
+    }
+
+    /// Checks the leader's stake and distributable profit every `interval` until `shutdown`
+    /// resolves, logging failures.
+    pub async fn run(
+        &mut self,
+        info: &InfoClient,
+        exchange: &ExchangeClient,
+        interval: Duration,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let mut shutdown = pin!(shutdown);
+        loop {
+            if let Err(err) = self.ensure_leader_stake(info, exchange).await {
+                warn!(vault = %self.vault, "Could not restore vault leader stake: {err}");
+            }
+            if let Err(err) = self.check_distribution(info).await {
+                warn!(vault = %self.vault, "Could not check vault distribution: {err}");
+            }
+            tokio::select! {
+                _ = &mut shutdown => return,
+                _ = rt::sleep(interval) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_vault_equity_and_leader_top_up() {
+        let (vault, leader) = (Address::repeat_byte(9), Address::repeat_byte(1));
+        let follower = |user: Address, equity: &str| {
+            format!(
+                r#"{{"user":"{user}","vaultEquity":"{equity}","pnl":"1","allTimePnl":"2","daysFollowing":3,"vaultEntryTime":0,"lockupUntil":null}}"#
+            )
+        };
+        let details: VaultDetailsResponse = serde_json::from_str(&format!(
+            r#"{{"name":"v","vaultAddress":"{vault}","leader":"{leader}","leaderFraction":0.04,"leaderCommission":0.1,"followers":[{},{},{}],"followerState":null,"maxDistributable":250.0,"maxWithdrawable":9000.0,"isClosed":false}}"#,
+            follower(leader, "400"),
+            follower(Address::repeat_byte(2), "3600"),
+            follower(Address::repeat_byte(3), "6000"),
+        ))
+        .unwrap();
+        let state = VaultState::from_details(&details);
+        assert!((state.equity - 10_000.0).abs() < 1e-6);
+        assert!((state.leader_equity - 400.0).abs() < 1e-6);
+        assert_eq!(state.followers[0].user, Address::repeat_byte(3));
+        assert!((state.follower(Address::repeat_byte(2)).unwrap().fraction - 0.36).abs() < 1e-9);
+
+        // (400 + d) / (10000 + d) = 0.055
+        let operator = VaultOperator::new(vault);
+        let top_up = operator.leader_top_up(&state).unwrap();
+        assert!((top_up - 150.0 / 0.945).abs() < 1e-6);
+        let operator = operator.with_max_top_up(100.0);
+        assert_eq!(operator.leader_top_up(&state), Some(100.0));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut operator = operator
+            .with_distribution_threshold(100.0)
+            .with_on_distributable(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        assert_eq!(operator.distributable(&state), Some(250.0));
+        operator = operator.with_distribution_threshold(500.0);
+        assert_eq!(operator.distributable(&state), None);
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    fmt,
+    ops::{Add, Sub},
+    str::FromStr,
+    time::Duration,
+};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Side of an order, fill or trade: `B` for buys and `A` for sells on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    #[serde(rename = "B")]
+    Buy,
+    #[serde(rename = "A")]
+    Sell,
+}
+
+impl Side {
+    pub fn from_is_buy(is_buy: bool) -> Side {
+        if is_buy {
+            Side::Buy
+        } else {
+            Side::Sell
+        }
+    }
+
+    pub fn is_buy(self) -> bool {
+        self == Side::Buy
+    }
+
+    pub fn opposite(self) -> Side {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+
+    /// 1 for buys and -1 for sells, the sign of the position change.
+    pub fn sign(self) -> f64 {
+        match self {
+            Side::Buy => 1.0,
+            Side::Sell => -1.0,
+        }
+    }
+
+    /// The wire value.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Side::Buy => "B",
+            Side::Sell => "A",
+        }
+    }
+}
+
+/// Time in force of a limit order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tif {
+    /// Good til canceled
+    Gtc,
+    /// Immediate or cancel, the unfilled rest is canceled
+    Ioc,
+    /// Add liquidity only, rejected if it would cross
+    Alo,
+}
+
+impl Tif {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tif::Gtc => "Gtc",
+            Tif::Ioc => "Ioc",
+            Tif::Alo => "Alo",
+        }
+    }
+}
+
+/// Whether a trigger order takes profit or stops a loss, `tp` or `sl` on the wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TriggerCondition {
+    #[serde(rename = "tp")]
+    TakeProfit,
+    #[serde(rename = "sl")]
+    StopLoss,
+}
+
+impl TriggerCondition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TriggerCondition::TakeProfit => "tp",
+            TriggerCondition::StopLoss => "sl",
+        }
+    }
+}
+
+/// What a fill did to the position, the `dir` of fills. Values the SDK does not know, such as
+/// spot dust conversions or settlement, are kept as sent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum FillDirection {
+    OpenLong,
+    OpenShort,
+    CloseLong,
+    CloseShort,
+    /// Flipped from long to short, `Long > Short` on the wire
+    LongToShort,
+    /// Flipped from short to long, `Short > Long` on the wire
+    ShortToLong,
+    /// Spot buy
+    Buy,
+    /// Spot sell
+    Sell,
+    Other(String),
+}
+
+impl FillDirection {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FillDirection::OpenLong => "Open Long",
+            FillDirection::OpenShort => "Open Short",
+            FillDirection::CloseLong => "Close Long",
+            FillDirection::CloseShort => "Close Short",
+            FillDirection::LongToShort => "Long > Short",
+            FillDirection::ShortToLong => "Short > Long",
+            FillDirection::Buy => "Buy",
+            FillDirection::Sell => "Sell",
+            FillDirection::Other(dir) => dir,
+        }
+    }
+
+    /// Whether the fill closed some or all of a position, including flips.
+    pub fn closes_position(&self) -> bool {
+        matches!(
+            self,
+            FillDirection::CloseLong
+                | FillDirection::CloseShort
+                | FillDirection::LongToShort
+                | FillDirection::ShortToLong
+        )
+    }
+}
+
+impl From<String> for FillDirection {
+    fn from(dir: String) -> Self {
+        match dir.as_str() {
+            "Open Long" => FillDirection::OpenLong,
+            "Open Short" => FillDirection::OpenShort,
+            "Close Long" => FillDirection::CloseLong,
+            "Close Short" => FillDirection::CloseShort,
+            "Long > Short" => FillDirection::LongToShort,
+            "Short > Long" => FillDirection::ShortToLong,
+            "Buy" => FillDirection::Buy,
+            "Sell" => FillDirection::Sell,
+            _ => FillDirection::Other(dir),
+        }
+    }
+}
+
+impl From<FillDirection> for String {
+    fn from(dir: FillDirection) -> Self {
+        match dir {
+            FillDirection::Other(dir) => dir,
+            dir => dir.as_str().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FillDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a liquidated position was closed: against the book, or taken over by the liquidator
+/// vault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LiquidationMethod {
+    Market,
+    Backstop,
+    #[serde(other)]
+    Unknown,
+}
+
+/// A point in time, sent as Unix milliseconds on the wire.
+///
+/// Converts from and to `u64` milliseconds and `DateTime<Utc>`, and compares with raw
+/// milliseconds, so times from different endpoints cannot be mixed up with seconds or
+/// durations.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    pub const fn from_millis(millis: u64) -> Timestamp {
+        Timestamp(millis)
+    }
+
+    pub fn now() -> Timestamp {
+        Timestamp(crate::helpers::now_timestamp_ms())
+    }
+
+    pub const fn as_millis(self) -> u64 {
+        self.0
+    }
+
+    /// Saturates at the latest time `DateTime` can hold.
+    pub fn to_datetime(self) -> DateTime<Utc> {
+        i64::try_from(self.0)
+            .ok()
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// Time elapsed since `earlier`, zero if it is later.
+    pub fn duration_since(self, earlier: Timestamp) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0))
+    }
+
+    /// Start of the interval of `interval` containing this time, counting from the epoch.
+    pub fn floor(self, interval: Duration) -> Timestamp {
+        let interval_ms = (interval.as_millis() as u64).max(1);
+        Timestamp(self.0 - self.0 % interval_ms)
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(millis: u64) -> Self {
+        Timestamp(millis)
+    }
+}
+
+impl From<Timestamp> for u64 {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+/// Times before the epoch become the epoch.
+impl From<DateTime<Utc>> for Timestamp {
+    fn from(datetime: DateTime<Utc>) -> Self {
+        Timestamp(datetime.timestamp_millis().max(0) as u64)
+    }
+}
+
+impl From<Timestamp> for DateTime<Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.to_datetime()
+    }
+}
+
+impl PartialEq<u64> for Timestamp {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd<u64> for Timestamp {
+    fn partial_cmp(&self, other: &u64) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(other)
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, duration: Duration) -> Timestamp {
+        Timestamp(self.0.saturating_add(duration.as_millis() as u64))
+    }
+}
+
+impl Sub<Duration> for Timestamp {
+    type Output = Timestamp;
+
+    fn sub(self, duration: Duration) -> Timestamp {
+        Timestamp(self.0.saturating_sub(duration.as_millis() as u64))
+    }
+}
+
+/// RFC 3339 in UTC with milliseconds, such as `2024-01-01T13:00:00.250Z`.
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            &self
+                .to_datetime()
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+        )
+    }
+}
+
+/// Application-level id carried in the cloid of an order, so fills and order updates can be
+/// attributed to the strategy that placed them when several share one account.
+///
+/// Tagged cloids keep the tag in their first four bytes, a marker in the next two and the
+/// UUID version 8, with the rest random. Random version 4 cloids never read as tagged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OrderTag(pub u32);
+
+impl OrderTag {
+    const MARKER: &'static str = "7467";
+
+    /// Tag of a cloid in its `0x`-prefixed wire form, None for untagged cloids.
+    pub fn from_cloid(cloid: &str) -> Option<OrderTag> {
+        let hex = cloid.trim_start_matches("0x").replace('-', "");
+        if hex.len() != 32
+            || !hex.is_ascii()
+            || !hex[8..12].eq_ignore_ascii_case(Self::MARKER)
+            || &hex[12..13] != "8"
+        {
+            return None;
+        }
+        u32::from_str_radix(&hex[..8], 16).ok().map(OrderTag)
+    }
+
+    /// A new random cloid carrying this tag.
+    #[cfg(feature = "exchange")]
+    pub fn new_cloid(self) -> uuid::Uuid {
+        let mut bytes = uuid::Uuid::new_v4().into_bytes();
+        bytes[..4].copy_from_slice(&self.0.to_be_bytes());
+        bytes[4..6].copy_from_slice(&[0x74, 0x67]);
+        // Version 8; the variant bits are already set by v4
+        bytes[6] = (bytes[6] & 0x0f) | 0x80;
+        uuid::Uuid::from_bytes(bytes)
+    }
+
+    #[cfg(feature = "exchange")]
+    pub fn of(cloid: &uuid::Uuid) -> Option<OrderTag> {
+        OrderTag::from_cloid(&cloid.simple().to_string())
+    }
+
+    /// Start shared by the hyphenated form of every cloid with this tag.
+    #[cfg(feature = "journal")]
+    pub(crate) fn hyphenated_prefix(self) -> String {
+        format!("{:08x}-{}-8", self.0, Self::MARKER)
+    }
+}
+
+impl fmt::Display for OrderTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+macro_rules! wire_str {
+    ($($ty:ident { $($value:literal => $variant:ident),* }),*) => {
+        $(
+            impl fmt::Display for $ty {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(self.as_str())
+                }
+            }
+
+            /// Parses the wire value, ignoring case.
+            impl FromStr for $ty {
+                type Err = Error;
+
+                fn from_str(s: &str) -> Result<Self, Error> {
+                    $(
+                        if s.eq_ignore_ascii_case($value) {
+                            return Ok($ty::$variant);
+                        }
+                    )*
+                    Err(Error::GenericParse(format!(
+                        concat!("Invalid ", stringify!($ty), " {:?}"),
+                        s
+                    )))
+                }
+            }
+        )*
+    };
+}
+
+wire_str! {
+    Side { "B" => Buy, "A" => Sell },
+    Tif { "Gtc" => Gtc, "Ioc" => Ioc, "Alo" => Alo },
+    TriggerCondition { "tp" => TakeProfit, "sl" => StopLoss }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_values() {
+        assert_eq!(serde_json::to_string(&Side::Sell).unwrap(), r#""A""#);
+        assert_eq!(serde_json::from_str::<Tif>(r#""Alo""#).unwrap(), Tif::Alo);
+        assert_eq!(
+            serde_json::to_string(&TriggerCondition::StopLoss).unwrap(),
+            r#""sl""#
+        );
+        assert!(serde_json::from_str::<Side>(r#""S""#).is_err());
+        assert_eq!("ioc".parse::<Tif>().unwrap(), Tif::Ioc);
+        assert_eq!(Side::Buy.to_string(), "B");
+
+        let dirs: Vec<FillDirection> =
+            serde_json::from_str(r#"["Long > Short","Close Short","Spot Dust Conversion"]"#)
+                .unwrap();
+        assert_eq!(dirs[0], FillDirection::LongToShort);
+        assert!(dirs[1].closes_position());
+        assert_eq!(
+            serde_json::to_string(&dirs[2]).unwrap(),
+            r#""Spot Dust Conversion""#
+        );
+        assert_eq!(
+            serde_json::from_str::<LiquidationMethod>(r#""backstop""#).unwrap(),
+            LiquidationMethod::Backstop
+        );
+    }
+
+    #[test]
+    fn test_order_tag_cloid() {
+        assert_eq!(
+            OrderTag::from_cloid("0x0000002a74678123a456789abcdef012"),
+            Some(OrderTag(42))
+        );
+        // A random cloid with the marker bytes but version 4
+        assert_eq!(
+            OrderTag::from_cloid("0x0000002a74674123a456789abcdef012"),
+            None
+        );
+        assert_eq!(OrderTag::from_cloid("0x1234"), None);
+        #[cfg(feature = "exchange")]
+        {
+            let cloid = OrderTag(7).new_cloid();
+            assert_eq!(OrderTag::of(&cloid), Some(OrderTag(7)));
+            assert_eq!(
+                OrderTag::from_cloid(&format!("0x{}", cloid.simple())),
+                Some(OrderTag(7))
+            );
+            assert_eq!(OrderTag::of(&uuid::Uuid::new_v4()), None);
+        }
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let time: Timestamp = serde_json::from_str("1704114000250").unwrap();
+        assert_eq!(time, 1_704_114_000_250);
+        assert_eq!(serde_json::to_string(&time).unwrap(), "1704114000250");
+        assert_eq!(time.to_string(), "2024-01-01T13:00:00.250Z");
+        assert_eq!(Timestamp::from(time.to_datetime()), time);
+        assert_eq!(
+            time.floor(Duration::from_secs(3600)),
+            Timestamp::from_millis(1_704_114_000_000)
+        );
+        let later = time + Duration::from_secs(1);
+        assert_eq!(later.duration_since(time), Duration::from_secs(1));
+        assert_eq!(time.duration_since(later), Duration::ZERO);
+        assert!(later > time.as_millis());
+    }
+}
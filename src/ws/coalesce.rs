@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use crate::{rt, rt::Instant, Message};
+
+/// Holds back `l2Book` updates for a window, delivering only the latest book of each coin,
+/// so bursts of updates cost one message per coin instead of one per update.
+#[derive(Debug)]
+pub(crate) struct BookCoalescer {
+    window: Option<Duration>,
+    /// Latest held back book by subscription identifier, in order of first arrival
+    pending: Vec<(String, Message)>,
+    flush_at: Option<Instant>,
+}
+
+impl BookCoalescer {
+    /// Coalesces over `window`, or passes every update through without one.
+    pub(crate) fn new(window: Option<Duration>) -> BookCoalescer {
+        BookCoalescer {
+            window: window.filter(|window| !window.is_zero()),
+            pending: Vec::new(),
+            flush_at: None,
+        }
+    }
+
+    /// Holds back `message` if it is a book update, returning it otherwise.
+    pub(crate) fn push(
+        &mut self,
+        identifier: String,
+        message: Message,
+    ) -> Option<(String, Message)> {
+        let (Some(window), Message::L2Book(_)) = (self.window, &message) else {
+            return Some((identifier, message));
+        };
+        self.push_at(identifier, message, window, Instant::now());
+        None
+    }
+
+    /// Resolves once held back books are due. Never resolves while none are held back.
+    pub(crate) async fn due(&self) {
+        match self.flush_at {
+            Some(flush_at) => rt::sleep(flush_at.saturating_duration_since(Instant::now())).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Held back books, latest per coin.
+    pub(crate) fn take(&mut self) -> Vec<(String, Message)> {
+        self.flush_at = None;
+        std::mem::take(&mut self.pending)
+    }
+
+    fn push_at(&mut self, identifier: String, message: Message, window: Duration, now: Instant) {
+        match self.pending.iter_mut().find(|(id, _)| *id == identifier) {
+            Some((_, latest)) => *latest = message,
+            None => self.pending.push((identifier, message)),
+        }
+        self.flush_at.get_or_insert(now + window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(coin: &str, time: u64) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "channel": "l2Book",
+            "data": {"coin": coin, "time": time, "levels": [[], []]},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_keeps_latest_book_per_coin() {
+        let mut coalescer = BookCoalescer::new(Some(Duration::from_millis(10)));
+        assert!(matches!(
+            coalescer.push("pong".to_string(), Message::Pong),
+            Some((_, Message::Pong))
+        ));
+        for (coin, time) in [("ETH", 1), ("BTC", 2), ("ETH", 3)] {
+            assert!(coalescer.push(coin.to_string(), book(coin, time)).is_none());
+        }
+        assert!(coalescer.flush_at.is_some());
+        let books: Vec<(String, u64)> = coalescer
+            .take()
+            .into_iter()
+            .map(|(id, message)| match message {
+                Message::L2Book(book) => (id, book.data.time.as_millis()),
+                other => panic!("unexpected {other:?}"),
+            })
+            .collect();
+        assert_eq!(books, vec![("ETH".to_string(), 3), ("BTC".to_string(), 2)]);
+        assert!(coalescer.flush_at.is_none());
+
+        let mut passthrough = BookCoalescer::new(None);
+        assert!(passthrough
+            .push("ETH".to_string(), book("ETH", 1))
+            .is_some());
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{Message, UserData};
+
+/// Events remembered per stream, enough to cover the overlap after a reconnect.
+const SEEN_CAPACITY: usize = 10_000;
+
+/// Identity of a user event, the same however often it is delivered.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum EventKey {
+    Fill { tid: u64, oid: u64 },
+    OrderUpdate { oid: u64, status: String, time: u64 },
+    Funding { time: u64, coin: String },
+    Ledger { time: u64, hash: String },
+    Liquidation { lid: u64 },
+    NonUserCancel { oid: u64 },
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    keys: HashSet<EventKey>,
+    order: VecDeque<EventKey>,
+}
+
+impl Seen {
+    /// Remembers `key`, returning whether it is new.
+    fn insert(&mut self, key: EventKey) -> bool {
+        if !self.keys.insert(key.clone()) {
+            return false;
+        }
+        self.order.push_back(key);
+        if self.order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Drops user events already delivered on a stream, such as fills and order updates sent
+/// again after a reconnect, so consumers never count them twice.
+///
+/// Snapshots are passed through whole, consumers tell them apart by `is_snapshot`, but their
+/// events are remembered so later updates repeating them are dropped.
+#[derive(Debug, Default)]
+pub(crate) struct UserEventDedup {
+    streams: HashMap<String, Seen>,
+}
+
+impl UserEventDedup {
+    /// `message` without the events already delivered on the stream `identifier`, `None` when
+    /// nothing new is left. Messages other than user events are returned unchanged.
+    pub(crate) fn filter(&mut self, identifier: &str, mut message: Message) -> Option<Message> {
+        let seen = self.streams.entry(identifier.to_string()).or_default();
+        let remaining = match &mut message {
+            Message::UserFills(fills) => {
+                let snapshot = fills.data.is_snapshot.unwrap_or(false);
+                retain_new(&mut fills.data.fills, seen, snapshot, |fill| {
+                    EventKey::Fill {
+                        tid: fill.tid,
+                        oid: fill.oid,
+                    }
+                })
+            }
+            Message::OrderUpdates(updates) => {
+                retain_new(&mut updates.data, seen, false, |update| {
+                    EventKey::OrderUpdate {
+                        oid: update.order.oid,
+                        status: update.status.clone(),
+                        time: update.status_timestamp.as_millis(),
+                    }
+                })
+            }
+            Message::UserFundings(fundings) => {
+                let snapshot = fundings.data.is_snapshot.unwrap_or(false);
+                retain_new(&mut fundings.data.fundings, seen, snapshot, |funding| {
+                    EventKey::Funding {
+                        time: funding.time.as_millis(),
+                        coin: funding.coin.clone(),
+                    }
+                })
+            }
+            Message::UserNonFundingLedgerUpdates(updates) => {
+                let snapshot = updates.data.is_snapshot.unwrap_or(false);
+                retain_new(
+                    &mut updates.data.non_funding_ledger_updates,
+                    seen,
+                    snapshot,
+                    |update| EventKey::Ledger {
+                        time: update.time.as_millis(),
+                        hash: update.hash.clone(),
+                    },
+                )
+            }
+            Message::User(user) => match &mut user.data {
+                UserData::Fills(fills) => retain_new(fills, seen, false, |fill| EventKey::Fill {
+                    tid: fill.tid,
+                    oid: fill.oid,
+                }),
+                UserData::Funding(funding) => usize::from(seen.insert(EventKey::Funding {
+                    time: funding.time.as_millis(),
+                    coin: funding.coin.clone(),
+                })),
+                UserData::Liquidation(liquidation) => {
+                    usize::from(seen.insert(EventKey::Liquidation {
+                        lid: liquidation.lid,
+                    }))
+                }
+                UserData::NonUserCancel(cancels) => retain_new(cancels, seen, false, |cancel| {
+                    EventKey::NonUserCancel { oid: cancel.oid }
+                }),
+            },
+            _ => return Some(message),
+        };
+        (remaining > 0).then_some(message)
+    }
+}
+
+/// Keeps the events of `events` not seen before, or all of them for a snapshot, returning how
+/// many are left.
+fn retain_new<T>(
+    events: &mut Vec<T>,
+    seen: &mut Seen,
+    snapshot: bool,
+    key: impl Fn(&T) -> EventKey,
+) -> usize {
+    if snapshot {
+        for event in events.iter() {
+            seen.insert(key(event));
+        }
+        // An empty snapshot still tells consumers the subscription is live
+        return events.len().max(1);
+    }
+    events.retain(|event| seen.insert(key(event)));
+    events.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fills(tids: &[u64], is_snapshot: bool) -> Message {
+        let fills: Vec<serde_json::Value> = tids
+            .iter()
+            .map(|tid| {
+                serde_json::json!({
+                    "coin": "ETH", "side": "B", "px": "2000", "sz": "1", "time": 1,
+                    "hash": "0x0", "startPosition": "0", "dir": "Open Long",
+                    "closedPnl": "0", "oid": 7, "cloid": null, "crossed": true, "fee": "0",
+                    "feeToken": "USDC", "tid": tid,
+                })
+            })
+            .collect();
+        serde_json::from_value(serde_json::json!({
+            "channel": "userFills",
+            "data": {
+                "isSnapshot": is_snapshot,
+                "user": "0x0000000000000000000000000000000000000001",
+                "fills": fills,
+            },
+        }))
+        .unwrap()
+    }
+
+    fn tids(message: Option<Message>) -> Vec<u64> {
+        match message {
+            Some(Message::UserFills(fills)) => fills.data.fills.iter().map(|f| f.tid).collect(),
+            None => Vec::new(),
+            other => panic!("unexpected {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_drops_redelivered_fills() {
+        let mut dedup = UserEventDedup::default();
+        assert_eq!(
+            tids(dedup.filter("fills", fills(&[1, 2], false))),
+            vec![1, 2]
+        );
+        // The snapshot after a reconnect is passed through, updates repeating it are dropped
+        assert_eq!(
+            tids(dedup.filter("fills", fills(&[1, 2, 3], true))),
+            vec![1, 2, 3]
+        );
+        assert_eq!(tids(dedup.filter("fills", fills(&[3, 4], false))), vec![4]);
+        assert!(dedup.filter("fills", fills(&[4], false)).is_none());
+        // Streams are independent
+        assert_eq!(tids(dedup.filter("other", fills(&[4], false))), vec![4]);
+        assert!(matches!(
+            dedup.filter("fills", Message::Pong),
+            Some(Message::Pong)
+        ));
+    }
+}
@@ -1,78 +1,233 @@
-use serde::Deserialize;
+use alloy::primitives::Address;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{prelude::*, ws::sub_structs::*, Error};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum Subscription {
+    AllMids,
+    Notification { user: Address },
+    WebData2 { user: Address },
+    Candle { coin: String, interval: String },
+    L2Book { coin: String },
+    Trades { coin: String },
+    OrderUpdates { user: Address },
+    UserEvents { user: Address },
+    UserFills { user: Address },
+    UserFundings { user: Address },
+    UserNonFundingLedgerUpdates { user: Address },
+    ActiveAssetCtx { coin: String },
+    ActiveAssetData { user: Address, coin: String },
+    Bbo { coin: String },
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "channel")]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum Message {
+    NoData,
+    HyperliquidError(String),
+    AllMids(AllMids),
+    Trades(Trades),
+    L2Book(L2Book),
+    User(User),
+    UserFills(UserFills),
+    Candle(Candle),
+    SubscriptionResponse,
+    OrderUpdates(OrderUpdates),
+    UserFundings(UserFundings),
+    UserNonFundingLedgerUpdates(UserNonFundingLedgerUpdates),
+    Notification(Notification),
+    WebData2(WebData2),
+    ActiveAssetCtx(ActiveAssetCtx),
+    ActiveAssetData(ActiveAssetData),
+    ActiveSpotAssetCtx(ActiveSpotAssetCtx),
+    Bbo(Bbo),
+    /// Response to a request sent on the websocket, delivered to the request rather than to
+    /// subscribers
+    Post(Post),
+    Pong,
+    /// A channel this version does not know, as subscribed with
+    /// `InfoClient::subscribe_custom`
+    #[serde(untagged)]
+    Custom(CustomMessage),
+}
+
+/// Subscription to a channel this version does not know, with the payload the exchange expects
+/// and the `channel` its messages arrive on.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CustomSubscription {
+    pub channel: String,
+    pub subscription: serde_json::Value,
+}
+
+impl CustomSubscription {
+    pub fn new(channel: impl Into<String>, subscription: serde_json::Value) -> CustomSubscription {
+        CustomSubscription {
+            channel: channel.into(),
+            subscription,
+        }
+    }
+}
+
+/// Message of a channel this version does not know, with its data left as JSON.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CustomMessage {
+    pub channel: String,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
 
-use crate::ws::sub_structs::*;
+impl CustomMessage {
+    /// Deserializes the data into a type of the caller's.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T> {
+        T::deserialize(&self.data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Post {
+    pub data: PostData,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct PostData {
+    pub id: u64,
+    pub response: PostResponse,
+}
 
-#[derive(Deserialize, Clone, Debug)]
+/// Body of a websocket post response. `Action` carries the same JSON as a REST `/exchange`
+/// response and `Info` the same as a REST `/info` response.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum PostResponse {
+    Info(serde_json::Value),
+    Action(serde_json::Value),
+    Error(String),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Trades {
     pub data: Vec<Trade>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct L2Book {
     pub data: L2BookData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct AllMids {
     pub data: AllMidsData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct User {
     pub data: UserData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct UserFills {
     pub data: UserFillsData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Candle {
     pub data: CandleData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct OrderUpdates {
     pub data: Vec<OrderUpdate>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct UserFundings {
     pub data: UserFundingsData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct UserNonFundingLedgerUpdates {
     pub data: UserNonFundingLedgerUpdatesData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Notification {
     pub data: NotificationData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct WebData2 {
     pub data: WebData2Data,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ActiveAssetCtx {
     pub data: ActiveAssetCtxData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ActiveSpotAssetCtx {
     pub data: ActiveSpotAssetCtxData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct ActiveAssetData {
     pub data: ActiveAssetDataData,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct Bbo {
     pub data: BboData,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_round_trip_and_tolerate_additions() {
+        let text = r#"{"channel":"l2Book","data":{"coin":"ETH","time":1,"levels":[[{"px":"2000","sz":"1","n":2}],[]],"newField":true}}"#;
+        let message: Message = serde_json::from_str(text).unwrap();
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["channel"], "l2Book");
+        assert_eq!(json["data"]["levels"][0][0]["px"], "2000");
+        assert!(matches!(
+            serde_json::from_value::<Message>(json).unwrap(),
+            Message::L2Book(book) if book.data.coin == "ETH"
+        ));
+
+        let message: Message =
+            serde_json::from_str(r#"{"channel":"somethingNew","data":[]}"#).unwrap();
+        let Message::Custom(custom) = message else {
+            panic!("expected a custom message");
+        };
+        assert_eq!(custom.channel, "somethingNew");
+        assert_eq!(custom.decode::<Vec<u64>>().unwrap(), Vec::<u64>::new());
+        assert_eq!(
+            serde_json::to_value(Message::Custom(custom)).unwrap(),
+            serde_json::json!({"channel": "somethingNew", "data": []})
+        );
+        let update: LedgerUpdateData = serde_json::from_str(
+            r#"{"time":1,"hash":"0x1","delta":{"type":"somethingNew","usdc":"1"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(update.delta, LedgerUpdate::Unknown));
+
+        let text = r#"{"channel":"userFills","data":{"user":"0x0000000000000000000000000000000000000001","fills":[{"coin":"ETH","px":"2000","sz":"1","side":"A","time":1,"startPosition":"1","dir":"Close Long","closedPnl":"-50","hash":"0x1","oid":7,"crossed":true,"fee":"0.5","feeToken":"USDC","builderFee":"0.1","tid":9,"liquidation":{"liquidatedUser":"0x0000000000000000000000000000000000000002","markPx":"1999","method":"market"}}]}}"#;
+        let Message::UserFills(fills) = serde_json::from_str(text).unwrap() else {
+            panic!("expected fills");
+        };
+        let fill = &fills.data.fills[0];
+        assert_eq!(fill.dir, crate::FillDirection::CloseLong);
+        assert_eq!(fill.builder_fee.as_deref(), Some("0.1"));
+        assert!(fill.is_liquidation());
+        assert_eq!(
+            fill.liquidation.as_ref().unwrap().method,
+            crate::LiquidationMethod::Market
+        );
+    }
+}
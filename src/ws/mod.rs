@@ -1,7 +1,18 @@
+#[cfg(feature = "ws")]
+mod coalesce;
+#[cfg(feature = "ws")]
+mod dedup;
 mod message_types;
+#[cfg(feature = "ws")]
+mod shedding;
 mod sub_structs;
+#[cfg(feature = "ws")]
+pub(crate) mod transport;
+#[cfg(feature = "ws")]
 mod ws_manager;
 pub use message_types::*;
+#[cfg(feature = "ws")]
+pub use shedding::{BandwidthBudget, ShedStats, SheddingReport, SubscriptionPriority};
 pub use sub_structs::*;
-pub(crate) use ws_manager::WsManager;
-pub use ws_manager::{Message, Subscription};
+#[cfg(feature = "ws")]
+pub(crate) use ws_manager::{WsManager, WsOptions};
@@ -0,0 +1,379 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{rt, rt::Instant, Message};
+
+/// How readily a coin's market data is shed when the connection is over its
+/// `BandwidthBudget`, `Low` first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionPriority {
+    /// Shed as soon as the connection is over budget
+    Low,
+    /// Shed once the connection is over twice its budget
+    #[default]
+    Normal,
+    /// Never shed
+    High,
+}
+
+/// Market data of one coin shed by a `BandwidthBudget`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShedStats {
+    /// Book, BBO, candle and asset context updates replaced by a later one before delivery
+    pub coalesced: u64,
+    /// Trade messages not delivered
+    pub dropped: u64,
+}
+
+/// What a `BandwidthBudget` has shed so far, from `BandwidthBudget::report`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SheddingReport {
+    /// Highest priority being shed at the last message, `None` within budget
+    pub shedding: Option<SubscriptionPriority>,
+    /// Traffic over the last second as a multiple of the budget
+    pub load: f64,
+    pub by_coin: BTreeMap<String, ShedStats>,
+}
+
+/// Limits on the websocket traffic a connection passes on at full rate, with priorities by
+/// coin deciding what is shed first beyond them.
+///
+/// Past the budget, market data of `Low` priority coins is shed, and past twice the budget
+/// that of `Normal` coins too, while `High` coins and account channels such as fills and
+/// order updates are always delivered. Shedding keeps only the latest book, BBO, candle and
+/// asset context of each channel, delivered once per coalescing window (one second unless
+/// set), and drops trades outright. Clones share the `report` of what was shed.
+#[derive(Clone, Debug)]
+pub struct BandwidthBudget {
+    max_bytes_per_sec: Option<u64>,
+    max_messages_per_sec: Option<u64>,
+    coalesce_window: Duration,
+    default_priority: SubscriptionPriority,
+    priorities: HashMap<String, SubscriptionPriority>,
+    report: Arc<Mutex<SheddingReport>>,
+}
+
+impl Default for BandwidthBudget {
+    fn default() -> Self {
+        BandwidthBudget::new()
+    }
+}
+
+impl BandwidthBudget {
+    /// A budget without limits, shedding nothing until one is set.
+    pub fn new() -> BandwidthBudget {
+        BandwidthBudget {
+            max_bytes_per_sec: None,
+            max_messages_per_sec: None,
+            coalesce_window: Duration::from_secs(1),
+            default_priority: SubscriptionPriority::Normal,
+            priorities: HashMap::new(),
+            report: Arc::default(),
+        }
+    }
+
+    /// Bytes of messages received per second, for limited bandwidth.
+    pub fn with_max_bytes_per_sec(mut self, bytes: u64) -> Self {
+        self.max_bytes_per_sec = Some(bytes.max(1));
+        self
+    }
+
+    /// Messages received per second, for limited CPU to parse and handle them.
+    pub fn with_max_messages_per_sec(mut self, messages: u64) -> Self {
+        self.max_messages_per_sec = Some(messages.max(1));
+        self
+    }
+
+    pub fn with_coalesce_window(mut self, window: Duration) -> Self {
+        self.coalesce_window = window;
+        self
+    }
+
+    /// Priority of coins without one of their own, `Normal` unless set.
+    pub fn with_default_priority(mut self, priority: SubscriptionPriority) -> Self {
+        self.default_priority = priority;
+        self
+    }
+
+    pub fn with_priority(mut self, coin: &str, priority: SubscriptionPriority) -> Self {
+        self.priorities.insert(coin.to_string(), priority);
+        self
+    }
+
+    pub fn priority(&self, coin: &str) -> SubscriptionPriority {
+        self.priorities
+            .get(coin)
+            .copied()
+            .unwrap_or(self.default_priority)
+    }
+
+    pub fn report(&self) -> SheddingReport {
+        self.report.lock().expect("shedding lock poisoned").clone()
+    }
+
+    /// Traffic as a multiple of the budget, the largest over the limits set.
+    fn load(&self, bytes: u64, messages: u64, secs: f64) -> f64 {
+        let ratio = |count: u64, limit: Option<u64>| {
+            limit.map_or(0.0, |limit| count as f64 / secs / limit as f64)
+        };
+        f64::max(
+            ratio(bytes, self.max_bytes_per_sec),
+            ratio(messages, self.max_messages_per_sec),
+        )
+    }
+}
+
+/// Applies a `BandwidthBudget` to the messages of a connection, holding back the updates it
+/// coalesces until they are due.
+#[derive(Debug)]
+pub(crate) struct Shedder {
+    budget: Option<BandwidthBudget>,
+    window_start: Instant,
+    bytes: u64,
+    messages: u64,
+    /// Load over the last full window
+    last_load: f64,
+    /// Latest held back update by subscription identifier, in order of first arrival
+    pending: Vec<(String, Message)>,
+    flush_at: Option<Instant>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<crate::Metrics>>,
+}
+
+impl Shedder {
+    const WINDOW: Duration = Duration::from_secs(1);
+
+    /// Sheds by `budget`, or passes every message through without one.
+    pub(crate) fn new(budget: Option<BandwidthBudget>) -> Shedder {
+        Shedder {
+            budget,
+            window_start: Instant::now(),
+            bytes: 0,
+            messages: 0,
+            last_load: 0.0,
+            pending: Vec::new(),
+            flush_at: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn with_metrics(mut self, metrics: Option<Arc<crate::Metrics>>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Counts a received message of `bytes` against the budget.
+    pub(crate) fn record(&mut self, bytes: usize) {
+        self.record_at(bytes, Instant::now());
+    }
+
+    /// Holds back or drops `message` if its coin is being shed, returning it otherwise.
+    pub(crate) fn push(
+        &mut self,
+        identifier: String,
+        message: Message,
+    ) -> Option<(String, Message)> {
+        self.push_at(identifier, message, Instant::now())
+    }
+
+    /// Resolves once held back updates are due. Never resolves while none are held back.
+    pub(crate) async fn due(&self) {
+        match self.flush_at {
+            Some(flush_at) => rt::sleep(flush_at.saturating_duration_since(Instant::now())).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Held back updates, latest per channel.
+    pub(crate) fn take(&mut self) -> Vec<(String, Message)> {
+        self.flush_at = None;
+        std::mem::take(&mut self.pending)
+    }
+
+    fn record_at(&mut self, bytes: usize, now: Instant) {
+        let Some(budget) = &self.budget else {
+            return;
+        };
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= Self::WINDOW {
+            self.last_load = budget.load(self.bytes, self.messages, elapsed.as_secs_f64());
+            self.window_start = now;
+            self.bytes = 0;
+            self.messages = 0;
+        }
+        self.bytes += bytes as u64;
+        self.messages += 1;
+    }
+
+    /// Load over the last full window, or the current one if it already exceeds that.
+    fn load(&self, budget: &BandwidthBudget) -> f64 {
+        let current = budget.load(self.bytes, self.messages, Self::WINDOW.as_secs_f64());
+        f64::max(self.last_load, current)
+    }
+
+    fn push_at(
+        &mut self,
+        identifier: String,
+        message: Message,
+        now: Instant,
+    ) -> Option<(String, Message)> {
+        let Some(budget) = &self.budget else {
+            return Some((identifier, message));
+        };
+        let Some((coin, coalesce)) = shed_kind(&message) else {
+            return Some((identifier, message));
+        };
+        let load = self.load(budget);
+        let shedding = if load > 2.0 {
+            Some(SubscriptionPriority::Normal)
+        } else if load > 1.0 {
+            Some(SubscriptionPriority::Low)
+        } else {
+            None
+        };
+        let shed = shedding.is_some_and(|level| budget.priority(coin) <= level);
+        let held = self.pending.iter().position(|(id, _)| *id == identifier);
+
+        let mut report = budget.report.lock().expect("shedding lock poisoned");
+        report.shedding = shedding;
+        report.load = load;
+        if !shed && held.is_none() {
+            return Some((identifier, message));
+        }
+        let coin = coin.to_string();
+        let stats = report.by_coin.entry(coin.clone()).or_default();
+        let action = match (shed, held) {
+            // A held back update is stale once a later one is delivered
+            (false, Some(index)) => {
+                self.pending.remove(index);
+                stats.coalesced += 1;
+                drop(report);
+                self.count(&coin, "coalesced");
+                return Some((identifier, message));
+            }
+            (_, Some(index)) => {
+                self.pending[index].1 = message;
+                stats.coalesced += 1;
+                "coalesced"
+            }
+            (_, None) if coalesce => {
+                self.pending.push((identifier, message));
+                self.flush_at.get_or_insert(now + budget.coalesce_window);
+                return None;
+            }
+            (_, None) => {
+                stats.dropped += 1;
+                "dropped"
+            }
+        };
+        drop(report);
+        self.count(&coin, action);
+        None
+    }
+
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn count(&self, coin: &str, action: &str) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.inc_ws_shed(coin, action);
+        }
+    }
+}
+
+/// Coin of a sheddable market data message, and whether it is coalesced rather than dropped.
+fn shed_kind(message: &Message) -> Option<(&str, bool)> {
+    match message {
+        Message::L2Book(book) => Some((&book.data.coin, true)),
+        Message::Bbo(bbo) => Some((&bbo.data.coin, true)),
+        Message::Candle(candle) => Some((&candle.data.coin, true)),
+        Message::ActiveAssetCtx(ctx) => Some((&ctx.data.coin, true)),
+        Message::ActiveSpotAssetCtx(ctx) => Some((&ctx.data.coin, true)),
+        Message::Trades(trades) => trades
+            .data
+            .first()
+            .map(|trade| (trade.coin.as_str(), false)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(channel: &str, coin: &str) -> Message {
+        let data = match channel {
+            "l2Book" => serde_json::json!({"coin": coin, "time": 1, "levels": [[], []]}),
+            _ => serde_json::json!([{
+                "coin": coin, "side": "B", "px": "1", "sz": "1", "time": 1, "hash": "0x",
+                "tid": 1, "users": ["0x1", "0x2"]
+            }]),
+        };
+        serde_json::from_value(serde_json::json!({"channel": channel, "data": data})).unwrap()
+    }
+
+    #[test]
+    fn test_sheds_low_priority_coins_first() {
+        let budget = BandwidthBudget::new()
+            .with_max_messages_per_sec(10)
+            .with_default_priority(SubscriptionPriority::Low)
+            .with_priority("BTC", SubscriptionPriority::Normal)
+            .with_priority("ETH", SubscriptionPriority::High);
+        let mut shedder = Shedder::new(Some(budget.clone()));
+        let start = Instant::now();
+        let mut push = |channel: &str, coin: &str| {
+            shedder.record_at(100, start);
+            shedder
+                .push_at(format!("{channel}:{coin}"), message(channel, coin), start)
+                .is_some()
+        };
+
+        // Within budget everything is delivered
+        for _ in 0..10 {
+            assert!(push("l2Book", "DOGE"));
+        }
+        // Over budget, low priority books are held and trades dropped
+        assert!(!push("l2Book", "DOGE"));
+        assert!(!push("l2Book", "DOGE"));
+        assert!(!push("trades", "DOGE"));
+        assert!(push("l2Book", "BTC"));
+        assert_eq!(budget.report().shedding, Some(SubscriptionPriority::Low));
+        for _ in 0..8 {
+            push("trades", "ETH");
+        }
+        // Past twice the budget, normal priority coins are shed too but high ones never
+        assert!(!push("l2Book", "BTC"));
+        assert!(push("l2Book", "ETH"));
+
+        let report = budget.report();
+        assert_eq!(report.shedding, Some(SubscriptionPriority::Normal));
+        assert_eq!(
+            report.by_coin["DOGE"],
+            ShedStats {
+                coalesced: 1,
+                dropped: 1
+            }
+        );
+        assert!(!report.by_coin.contains_key("ETH"));
+        let held: Vec<String> = shedder.take().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(held, ["l2Book:DOGE", "l2Book:BTC"]);
+
+        // Once traffic calms down, the next window is back within budget
+        let later = start + Duration::from_secs(4);
+        shedder.record_at(100, start + Duration::from_secs(2));
+        shedder.record_at(100, later);
+        let delivered =
+            shedder.push_at("l2Book:DOGE".to_string(), message("l2Book", "DOGE"), later);
+        assert!(delivered.is_some());
+        assert_eq!(budget.report().shedding, None);
+    }
+}
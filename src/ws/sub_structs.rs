@@ -3,50 +3,58 @@ use std::collections::HashMap;
 use alloy::primitives::Address;
 use serde::{Deserialize, Serialize};
 
-use crate::Leverage;
+use crate::{
+    CandlesSnapshotResponse, FillDirection, FillLiquidation, Leverage, OrderTag, Side, Timestamp,
+    UserFillsResponse,
+};
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct Trade {
     pub coin: String,
-    pub side: String,
+    pub side: Side,
     pub px: String,
     pub sz: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub hash: String,
     pub tid: u64,
     pub users: (String, String),
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct BookLevel {
     pub px: String,
     pub sz: String,
     pub n: u64,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct L2BookData {
     pub coin: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub levels: Vec<Vec<BookLevel>>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct AllMidsData {
     pub mids: HashMap<String, String>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct TradeInfo {
     pub coin: String,
-    pub side: String,
+    pub side: Side,
     pub px: String,
     pub sz: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub hash: String,
     pub start_position: String,
-    pub dir: String,
+    pub dir: FillDirection,
     pub closed_pnl: String,
     pub oid: u64,
     pub cloid: Option<String>,
@@ -54,18 +62,60 @@ pub struct TradeInfo {
     pub fee: String,
     pub fee_token: String,
     pub tid: u64,
+    pub twap_id: Option<u64>,
+    /// Part of `fee` paid to the builder, when the order had one
+    pub builder_fee: Option<String>,
+    pub liquidation: Option<FillLiquidation>,
+}
+
+impl TradeInfo {
+    pub fn is_liquidation(&self) -> bool {
+        self.liquidation.is_some()
+    }
+
+    /// Tag of the order's cloid, see `OrderTag`.
+    pub fn tag(&self) -> Option<OrderTag> {
+        self.cloid.as_deref().and_then(OrderTag::from_cloid)
+    }
+}
+
+impl From<UserFillsResponse> for TradeInfo {
+    fn from(fill: UserFillsResponse) -> Self {
+        TradeInfo {
+            coin: fill.coin,
+            side: fill.side,
+            px: fill.px,
+            sz: fill.sz,
+            time: fill.time,
+            hash: fill.hash,
+            start_position: fill.start_position,
+            dir: fill.dir,
+            closed_pnl: fill.closed_pnl,
+            oid: fill.oid,
+            cloid: fill.cloid,
+            crossed: fill.crossed,
+            fee: fill.fee,
+            fee_token: fill.fee_token,
+            tid: fill.tid,
+            twap_id: fill.twap_id,
+            builder_fee: fill.builder_fee,
+            liquidation: fill.liquidation,
+        }
+    }
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserFillsData {
     pub is_snapshot: Option<bool>,
     pub user: Address,
     pub fills: Vec<TradeInfo>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub enum UserData {
     Fills(Vec<TradeInfo>),
     Funding(UserFunding),
@@ -73,7 +123,8 @@ pub enum UserData {
     NonUserCancel(Vec<NonUserCancel>),
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct Liquidation {
     pub lid: u64,
     pub liquidator: String,
@@ -82,16 +133,18 @@ pub struct Liquidation {
     pub liquidated_account_value: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct NonUserCancel {
     pub coin: String,
     pub oid: u64,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct CandleData {
     #[serde(rename = "T")]
-    pub time_close: u64,
+    pub time_close: Timestamp,
     #[serde(rename = "c")]
     pub close: String,
     #[serde(rename = "h")]
@@ -107,68 +160,99 @@ pub struct CandleData {
     #[serde(rename = "s")]
     pub coin: String,
     #[serde(rename = "t")]
-    pub time_open: u64,
+    pub time_open: Timestamp,
     #[serde(rename = "v")]
     pub volume: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+impl From<CandlesSnapshotResponse> for CandleData {
+    fn from(candle: CandlesSnapshotResponse) -> Self {
+        CandleData {
+            time_close: candle.time_close,
+            close: candle.close,
+            high: candle.high,
+            interval: candle.candle_interval,
+            low: candle.low,
+            num_trades: candle.num_trades,
+            open: candle.open,
+            coin: candle.coin,
+            time_open: candle.time_open,
+            volume: candle.vlm,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct OrderUpdate {
     pub order: BasicOrder,
     pub status: String,
-    pub status_timestamp: u64,
+    pub status_timestamp: Timestamp,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct BasicOrder {
     pub coin: String,
-    pub side: String,
+    pub side: Side,
     pub limit_px: String,
     pub sz: String,
     pub oid: u64,
-    pub timestamp: u64,
+    pub timestamp: Timestamp,
     pub orig_sz: String,
     pub cloid: Option<String>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+impl BasicOrder {
+    /// Tag of the order's cloid, see `OrderTag`.
+    pub fn tag(&self) -> Option<OrderTag> {
+        self.cloid.as_deref().and_then(OrderTag::from_cloid)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserFundingsData {
     pub is_snapshot: Option<bool>,
     pub user: Address,
     pub fundings: Vec<UserFunding>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserFunding {
-    pub time: u64,
+    pub time: Timestamp,
     pub coin: String,
     pub usdc: String,
     pub szi: String,
     pub funding_rate: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct UserNonFundingLedgerUpdatesData {
     pub is_snapshot: Option<bool>,
     pub user: Address,
     pub non_funding_ledger_updates: Vec<LedgerUpdateData>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct LedgerUpdateData {
-    pub time: u64,
+    pub time: Timestamp,
     pub hash: String,
     pub delta: LedgerUpdate,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type")]
+#[non_exhaustive]
 pub enum LedgerUpdate {
     Deposit(Deposit),
     Withdraw(Withdraw),
@@ -183,21 +267,27 @@ pub enum LedgerUpdate {
     AccountClassTransfer(AccountClassTransfer),
     SpotTransfer(SpotTransfer),
     SpotGenesis(SpotGenesis),
+    /// An update type this version does not know
+    #[serde(other)]
+    Unknown,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct Deposit {
     pub usdc: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct Withdraw {
     pub usdc: String,
     pub nonce: u64,
     pub fee: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct InternalTransfer {
     pub usdc: String,
     pub user: Address,
@@ -205,35 +295,40 @@ pub struct InternalTransfer {
     pub fee: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct SubAccountTransfer {
     pub usdc: String,
     pub user: Address,
     pub destination: Address,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct LedgerLiquidation {
     pub account_value: u64,
     pub leverage_type: String,
     pub liquidated_positions: Vec<LiquidatedPosition>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct LiquidatedPosition {
     pub coin: String,
     pub szi: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct VaultDelta {
     pub vault: Address,
     pub usdc: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct VaultWithdraw {
     pub vault: Address,
     pub user: Address,
@@ -244,21 +339,24 @@ pub struct VaultWithdraw {
     pub net_withdrawn_usd: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct VaultLeaderCommission {
     pub user: Address,
     pub usdc: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct AccountClassTransfer {
     pub usdc: String,
     pub to_perp: bool,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct SpotTransfer {
     pub token: String,
     pub amount: String,
@@ -268,25 +366,29 @@ pub struct SpotTransfer {
     pub fee: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct SpotGenesis {
     pub token: String,
     pub amount: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[non_exhaustive]
 pub struct NotificationData {
     pub notification: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct WebData2Data {
     pub user: Address,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ActiveAssetCtxData {
     pub coin: String,
     pub ctx: AssetCtx,
@@ -302,6 +404,7 @@ pub enum AssetCtx {
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct SharedAssetCtx {
     pub day_ntl_vlm: String,
     pub prev_day_px: String,
@@ -311,6 +414,7 @@ pub struct SharedAssetCtx {
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct PerpsAssetCtx {
     #[serde(flatten)]
     pub shared: SharedAssetCtx,
@@ -319,8 +423,9 @@ pub struct PerpsAssetCtx {
     pub oracle_px: String,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ActiveSpotAssetCtxData {
     pub coin: String,
     pub ctx: SpotAssetCtx,
@@ -328,6 +433,7 @@ pub struct ActiveSpotAssetCtxData {
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct SpotAssetCtx {
     #[serde(flatten)]
     pub shared: SharedAssetCtx,
@@ -336,6 +442,7 @@ pub struct SpotAssetCtx {
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct ActiveAssetDataData {
     pub user: Address,
     pub coin: String,
@@ -344,10 +451,11 @@ pub struct ActiveAssetDataData {
     pub available_to_trade: Vec<String>,
 }
 
-#[derive(Deserialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+#[non_exhaustive]
 pub struct BboData {
     pub coin: String,
-    pub time: u64,
+    pub time: Timestamp,
     pub bbo: Vec<Option<BookLevel>>,
 }
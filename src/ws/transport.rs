@@ -0,0 +1,81 @@
+//! Websocket transport: tokio-tungstenite on native targets, the browser WebSocket API on
+//! `wasm32`.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod native {
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::{
+        client_async_tls, connect_async,
+        tungstenite::{self, protocol},
+        MaybeTlsStream, WebSocketStream,
+    };
+
+    use crate::{prelude::*, req::ProxyConfig, Error};
+
+    pub(crate) type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+    pub(crate) type WsMessage = protocol::Message;
+    pub(crate) type WsError = tungstenite::Error;
+
+    pub(crate) fn text_message(payload: String) -> WsMessage {
+        protocol::Message::Text(payload)
+    }
+
+    pub(crate) fn message_text(message: WsMessage) -> std::result::Result<String, String> {
+        message.into_text().map_err(|e| e.to_string())
+    }
+
+    pub(crate) async fn connect(url: &str, proxy: Option<&ProxyConfig>) -> Result<WsStream> {
+        let Some(proxy) = proxy else {
+            return Ok(connect_async(url)
+                .await
+                .map_err(|e| Error::Websocket(e.to_string()))?
+                .0);
+        };
+        let parsed = reqwest::Url::parse(url).map_err(|e| Error::Websocket(e.to_string()))?;
+        let host = parsed
+            .host_str()
+            .ok_or_else(|| Error::Websocket(format!("No host in websocket url {url}")))?;
+        let port = parsed.port_or_known_default().unwrap_or(443);
+        let stream = proxy.connect(host, port).await?;
+        Ok(client_async_tls(url, stream)
+            .await
+            .map_err(|e| Error::Websocket(e.to_string()))?
+            .0)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use gloo_net::websocket::{futures::WebSocket, Message, WebSocketError};
+
+    use crate::{prelude::*, req::ProxyConfig, Error};
+
+    pub(crate) type WsStream = WebSocket;
+    pub(crate) type WsMessage = Message;
+    pub(crate) type WsError = WebSocketError;
+
+    pub(crate) fn text_message(payload: String) -> WsMessage {
+        Message::Text(payload)
+    }
+
+    pub(crate) fn message_text(message: WsMessage) -> std::result::Result<String, String> {
+        match message {
+            Message::Text(text) => Ok(text),
+            Message::Bytes(bytes) => String::from_utf8(bytes).map_err(|e| e.to_string()),
+        }
+    }
+
+    pub(crate) async fn connect(url: &str, proxy: Option<&ProxyConfig>) -> Result<WsStream> {
+        if proxy.is_some() {
+            return Err(Error::Websocket(
+                "proxies are controlled by the browser on wasm32".to_string(),
+            ));
+        }
+        WebSocket::open(url).map_err(|e| Error::Websocket(e.to_string()))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use native::*;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use wasm::*;
@@ -1,104 +1,119 @@
 use std::{
     borrow::BorrowMut,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ops::DerefMut,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    sync::Arc,
     time::Duration,
 };
 
-use alloy::primitives::Address;
 use futures_util::{stream::SplitSink, SinkExt, StreamExt};
-use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
-use tokio::{
-    net::TcpStream,
-    spawn,
-    sync::{mpsc::UnboundedSender, Mutex},
-    time,
-};
-use tokio_tungstenite::{
-    connect_async,
-    tungstenite::{self, protocol},
-    MaybeTlsStream, WebSocketStream,
-};
+use tokio::sync::{mpsc::UnboundedSender, oneshot, Mutex};
+use tracing::{error, info, info_span, instrument, warn, Instrument};
 
 use crate::{
     prelude::*,
-    ws::message_types::{
-        ActiveAssetData, ActiveSpotAssetCtx, AllMids, Bbo, Candle, L2Book, OrderUpdates, Trades,
-        User,
-    },
-    ActiveAssetCtx, Error, Notification, UserFills, UserFundings, UserNonFundingLedgerUpdates,
-    WebData2,
+    req::{CancellationToken, EndpointPool, FailureKind, ProxyConfig, Recorder, SchemaCheck},
+    rt::{self, spawn, Instant},
+    ws::coalesce::BookCoalescer,
+    ws::dedup::UserEventDedup,
+    ws::shedding::Shedder,
+    ws::transport::{connect, message_text, text_message, WsError, WsMessage, WsStream},
+    Error, Message, PostResponse, Subscription,
 };
 
+/// Parses a websocket frame. With the `simd-json` feature frames are parsed in place by
+/// simd-json, reusing per-thread buffers across messages.
+fn parse_message(data: String) -> Result<Message> {
+    #[cfg(all(feature = "simd-json", not(target_arch = "wasm32")))]
+    {
+        thread_local! {
+            static BUFFERS: std::cell::RefCell<simd_json::Buffers> = Default::default();
+        }
+        let mut bytes = data.into_bytes();
+        BUFFERS
+            .with_borrow_mut(|buffers| {
+                simd_json::serde::from_slice_with_buffers(&mut bytes, buffers)
+            })
+            .map_err(|e| Error::JsonParse(e.to_string()))
+    }
+    #[cfg(not(all(feature = "simd-json", not(target_arch = "wasm32"))))]
+    {
+        serde_json::from_str(&data).map_err(|e| Error::JsonParse(e.to_string()))
+    }
+}
+
+/// Parses a websocket frame, checking it for schema drift.
+fn parse_checked(data: &str, check: &SchemaCheck) -> Result<Message> {
+    let received: serde_json::Value =
+        serde_json::from_str(data).map_err(|e| Error::JsonParse(e.to_string()))?;
+    let message = Message::deserialize(&received).map_err(|e| Error::JsonParse(e.to_string()))?;
+    let channel = received["channel"].as_str().unwrap_or_default();
+    check.check(&format!("ws:{channel}"), &received, &message)?;
+    Ok(message)
+}
+
 #[derive(Debug)]
 struct SubscriptionData {
     sending_channel: UnboundedSender<Message>,
+    /// Receives the first message of the subscription instead of `sending_channel`
+    snapshot: Option<oneshot::Sender<Message>>,
     subscription_id: u32,
     id: String,
 }
-#[derive(Debug)]
+
+/// Connection settings of a `WsManager`, taken from the `InfoClient` opening it.
+#[derive(Clone, Default)]
+pub(crate) struct WsOptions {
+    pub(crate) reconnect: bool,
+    pub(crate) proxy: Option<ProxyConfig>,
+    pub(crate) recorder: Option<Recorder>,
+    pub(crate) schema_check: Option<SchemaCheck>,
+    pub(crate) endpoints: Option<Arc<EndpointPool>>,
+    /// Window over which `l2Book` updates are coalesced into the latest book per coin
+    pub(crate) book_coalescing: Option<Duration>,
+    pub(crate) bandwidth_budget: Option<crate::BandwidthBudget>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: Option<Arc<crate::Metrics>>,
+}
+
+/// Requests sent with `WsManager::post` awaiting their response, by id.
+type PendingPosts = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<PostResponse>>>>;
+
 pub(crate) struct WsManager {
-    stop_flag: Arc<AtomicBool>,
-    writer: Arc<Mutex<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>>>,
+    /// Stops the reader and ping tasks, cancelled when the manager is dropped
+    cancel: CancellationToken,
+    writer: Arc<Mutex<SplitSink<WsStream, WsMessage>>>,
     subscriptions: Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
     subscription_id: u32,
+    /// Key in `subscriptions` of each subscription id
     subscription_identifiers: HashMap<u32, String>,
+    #[cfg(feature = "exchange")]
+    pending_posts: PendingPosts,
+    #[cfg(feature = "exchange")]
+    post_id: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(tag = "type")]
-#[serde(rename_all = "camelCase")]
-pub enum Subscription {
-    AllMids,
-    Notification { user: Address },
-    WebData2 { user: Address },
-    Candle { coin: String, interval: String },
-    L2Book { coin: String },
-    Trades { coin: String },
-    OrderUpdates { user: Address },
-    UserEvents { user: Address },
-    UserFills { user: Address },
-    UserFundings { user: Address },
-    UserNonFundingLedgerUpdates { user: Address },
-    ActiveAssetCtx { coin: String },
-    ActiveAssetData { user: Address, coin: String },
-    Bbo { coin: String },
+#[derive(Serialize)]
+pub(crate) struct SubscriptionSendData<'a> {
+    method: &'static str,
+    subscription: &'a serde_json::Value,
 }
 
-#[derive(Deserialize, Clone, Debug)]
-#[serde(tag = "channel")]
-#[serde(rename_all = "camelCase")]
-pub enum Message {
-    NoData,
-    HyperliquidError(String),
-    AllMids(AllMids),
-    Trades(Trades),
-    L2Book(L2Book),
-    User(User),
-    UserFills(UserFills),
-    Candle(Candle),
-    SubscriptionResponse,
-    OrderUpdates(OrderUpdates),
-    UserFundings(UserFundings),
-    UserNonFundingLedgerUpdates(UserNonFundingLedgerUpdates),
-    Notification(Notification),
-    WebData2(WebData2),
-    ActiveAssetCtx(ActiveAssetCtx),
-    ActiveAssetData(ActiveAssetData),
-    ActiveSpotAssetCtx(ActiveSpotAssetCtx),
-    Bbo(Bbo),
-    Pong,
+#[cfg(feature = "exchange")]
+#[derive(Serialize)]
+struct PostSendData<'a> {
+    method: &'static str,
+    id: u64,
+    request: PostRequest<'a>,
 }
 
+#[cfg(feature = "exchange")]
 #[derive(Serialize)]
-pub(crate) struct SubscriptionSendData<'a> {
-    method: &'static str,
-    subscription: &'a serde_json::Value,
+struct PostRequest<'a> {
+    #[serde(rename = "type")]
+    request_type: &'static str,
+    payload: &'a serde_json::Value,
 }
 
 #[derive(Serialize)]
@@ -106,124 +121,278 @@ pub(crate) struct Ping {
     method: &'static str,
 }
 
+// The browser websocket handle is not `Debug`, so the writer is left out
+impl std::fmt::Debug for WsManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsManager")
+            .field("cancel", &self.cancel)
+            .field("subscriptions", &self.subscriptions)
+            .field("subscription_id", &self.subscription_id)
+            .field("subscription_identifiers", &self.subscription_identifiers)
+            .finish_non_exhaustive()
+    }
+}
+
 impl WsManager {
     const SEND_PING_INTERVAL: u64 = 50;
-
-    pub(crate) async fn new(url: String, reconnect: bool) -> Result<WsManager> {
-        let stop_flag = Arc::new(AtomicBool::new(false));
-
-        let (writer, mut reader) = Self::connect(&url).await?.split();
+    const FAILBACK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+    // Browser websocket handles are single threaded, the Arc is only shared between tasks
+    #[cfg_attr(target_arch = "wasm32", allow(clippy::arc_with_non_send_sync))]
+    pub(crate) async fn new(
+        url: String,
+        cancel: CancellationToken,
+        options: WsOptions,
+    ) -> Result<WsManager> {
+        let WsOptions {
+            reconnect,
+            proxy,
+            recorder,
+            schema_check,
+            endpoints,
+            book_coalescing,
+            bandwidth_budget,
+            #[cfg(feature = "metrics")]
+            metrics,
+        } = options;
+        let (ws, mut url) = tokio::select! {
+            _ = cancel.cancelled() => return Err(Error::Cancelled),
+            ws = Self::connect_any(&url, endpoints.as_deref(), proxy.as_ref()) => ws?,
+        };
+        let (writer, mut reader) = ws.split();
+        info!(url, "Websocket connected");
+        let url_label = url.clone();
         let writer = Arc::new(Mutex::new(writer));
 
         let subscriptions_map: HashMap<String, Vec<SubscriptionData>> = HashMap::new();
         let subscriptions = Arc::new(Mutex::new(subscriptions_map));
         let subscriptions_copy = Arc::clone(&subscriptions);
+        let pending_posts = PendingPosts::default();
 
         {
             let writer = writer.clone();
-            let stop_flag = Arc::clone(&stop_flag);
+            let cancel = cancel.clone();
+            let pending_posts = Arc::clone(&pending_posts);
             let reader_fut = async move {
-                while !stop_flag.load(Ordering::Relaxed) {
-                    if let Some(data) = reader.next().await {
-                        if let Err(err) =
-                            WsManager::parse_and_send_data(data, &subscriptions_copy).await
+                let mut dedup = UserEventDedup::default();
+                let mut books = BookCoalescer::new(book_coalescing);
+                let shedder = Shedder::new(bandwidth_budget);
+                #[cfg(feature = "metrics")]
+                let shedder = shedder.with_metrics(metrics.clone());
+                let mut shedder = shedder;
+                let mut next_failback_check = Instant::now() + Self::FAILBACK_CHECK_INTERVAL;
+                loop {
+                    let data = tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        data = reader.next() => data,
+                        _ = books.due() => {
+                            Self::send_all(&subscriptions_copy, books.take()).await;
+                            continue;
+                        }
+                        _ = shedder.due() => {
+                            Self::send_all(&subscriptions_copy, shedder.take()).await;
+                            continue;
+                        }
+                        // Moves back to the preferred endpoint once it is in rotation again
+                        preferred = Self::failback_target(endpoints.as_deref(), &url, next_failback_check) => {
+                            next_failback_check = Instant::now() + Self::FAILBACK_CHECK_INTERVAL;
+                            let Some(preferred) = preferred else {
+                                continue;
+                            };
+                            let Ok(ws) = connect(&preferred, proxy.as_ref()).await else {
+                                continue;
+                            };
+                            let (new_writer, new_reader) = ws.split();
+                            reader = new_reader;
+                            let mut writer_guard = writer.lock().await;
+                            *writer_guard = new_writer;
+                            Self::resubscribe(writer_guard.deref_mut(), &subscriptions_copy).await;
+                            drop(writer_guard);
+                            info!(from = url, to = preferred, "Websocket failed back");
+                            url = preferred;
+                            if let Err(err) = WsManager::send_to_all_subscriptions(
+                                &subscriptions_copy,
+                                Message::NoData,
+                            )
+                            .await
+                            {
+                                warn!(error = %err, "Could not notify subscribers of failback");
+                            }
+                            continue;
+                        }
+                    };
+                    if let Some(data) = data {
+                        if let Err(err) = WsManager::parse_and_send_data(
+                            data,
+                            &subscriptions_copy,
+                            &pending_posts,
+                            &mut dedup,
+                            &mut books,
+                            &mut shedder,
+                            recorder.as_ref(),
+                            schema_check.as_ref(),
+                            #[cfg(feature = "metrics")]
+                            metrics.as_deref(),
+                        )
+                        .await
                         {
-                            error!("Error processing data received by WsManager reader: {err}");
+                            error!(error = %err, "Could not process websocket message");
                         }
                     } else {
-                        warn!("WsManager disconnected");
+                        warn!("Websocket disconnected");
+                        Self::send_all(&subscriptions_copy, shedder.take()).await;
+                        Self::send_all(&subscriptions_copy, books.take()).await;
+                        // Dropping the senders fails requests whose response will not arrive
+                        pending_posts
+                            .lock()
+                            .expect("pending posts lock poisoned")
+                            .clear();
                         if let Err(err) = WsManager::send_to_all_subscriptions(
                             &subscriptions_copy,
                             Message::NoData,
                         )
                         .await
                         {
-                            warn!("Error sending disconnection notification err={err}");
+                            warn!(error = %err, "Could not notify subscribers of disconnection");
                         }
                         if reconnect {
                             // Always sleep for 1 second before attempting to reconnect so it does not spin during reconnecting. This could be enhanced with exponential backoff.
-                            tokio::time::sleep(Duration::from_secs(1)).await;
-                            info!("WsManager attempting to reconnect");
-                            match Self::connect(&url).await {
-                                Ok(ws) => {
+                            tokio::select! {
+                                _ = cancel.cancelled() => break,
+                                _ = rt::sleep(Duration::from_secs(1)) => {}
+                            }
+                            info!("Websocket reconnecting");
+                            match Self::connect_any(&url, endpoints.as_deref(), proxy.as_ref())
+                                .await
+                            {
+                                Ok((ws, new_url)) => {
                                     let (new_writer, new_reader) = ws.split();
                                     reader = new_reader;
                                     let mut writer_guard = writer.lock().await;
                                     *writer_guard = new_writer;
-                                    for (identifier, v) in subscriptions_copy.lock().await.iter() {
-                                        // TODO should these special keys be removed and instead use the simpler direct identifier mapping?
-                                        if identifier.eq("userEvents")
-                                            || identifier.eq("orderUpdates")
-                                        {
-                                            for subscription_data in v {
-                                                if let Err(err) = Self::subscribe(
-                                                    writer_guard.deref_mut(),
-                                                    &subscription_data.id,
-                                                )
-                                                .await
-                                                {
-                                                    error!(
-                                                        "Could not resubscribe {identifier}: {err}"
-                                                    );
-                                                }
-                                            }
-                                        } else if let Err(err) =
-                                            Self::subscribe(writer_guard.deref_mut(), identifier)
-                                                .await
-                                        {
-                                            error!("Could not resubscribe correctly {identifier}: {err}");
-                                        }
+                                    Self::resubscribe(
+                                        writer_guard.deref_mut(),
+                                        &subscriptions_copy,
+                                    )
+                                    .await;
+                                    info!(url = new_url, "Websocket reconnected");
+                                    url = new_url;
+                                    #[cfg(feature = "metrics")]
+                                    if let Some(metrics) = &metrics {
+                                        metrics.inc_ws_reconnect();
                                     }
-                                    info!("WsManager reconnect finished");
                                 }
-                                Err(err) => error!("Could not connect to websocket {err}"),
+                                Err(err) => error!(error = %err, "Could not reconnect websocket"),
                             }
                         } else {
-                            error!("WsManager reconnection disabled. Will not reconnect and exiting reader task.");
+                            error!("Websocket reconnection disabled, stopping reader");
                             break;
                         }
                     }
                 }
-                warn!("ws message reader task stopped");
+                warn!("Websocket reader stopped");
             };
-            spawn(reader_fut);
+            spawn(reader_fut.instrument(info_span!("ws_reader", url = %url_label)));
         }
 
         {
-            let stop_flag = Arc::clone(&stop_flag);
+            let cancel = cancel.clone();
             let writer = Arc::clone(&writer);
             let ping_fut = async move {
-                while !stop_flag.load(Ordering::Relaxed) {
+                while !cancel.is_cancelled() {
                     match serde_json::to_string(&Ping { method: "ping" }) {
                         Ok(payload) => {
                             let mut writer = writer.lock().await;
-                            if let Err(err) = writer.send(protocol::Message::Text(payload)).await {
-                                error!("Error pinging server: {err}")
+                            if let Err(err) = writer.send(text_message(payload)).await {
+                                error!(error = %err, "Could not ping websocket")
                             }
                         }
-                        Err(err) => error!("Error serializing ping message: {err}"),
+                        Err(err) => error!(error = %err, "Could not serialize ping"),
+                    }
+                    tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        _ = rt::sleep(Duration::from_secs(Self::SEND_PING_INTERVAL)) => {}
                     }
-                    time::sleep(Duration::from_secs(Self::SEND_PING_INTERVAL)).await;
                 }
-                warn!("ws ping task stopped");
+                warn!("Websocket ping task stopped");
             };
-            spawn(ping_fut);
+            spawn(ping_fut.instrument(info_span!("ws_ping", url = %url_label)));
         }
 
         Ok(WsManager {
-            stop_flag,
+            cancel,
             writer,
             subscriptions,
             subscription_id: 0,
             subscription_identifiers: HashMap::new(),
+            #[cfg(feature = "exchange")]
+            pending_posts,
+            #[cfg(feature = "exchange")]
+            post_id: 0,
         })
     }
 
-    async fn connect(url: &str) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
-        Ok(connect_async(url)
-            .await
-            .map_err(|e| Error::Websocket(e.to_string()))?
-            .0)
+    /// Connects to `url`, or with an endpoint pool to the first endpoint in rotation that
+    /// accepts the connection, returning the URL connected to.
+    async fn connect_any(
+        url: &str,
+        endpoints: Option<&EndpointPool>,
+        proxy: Option<&ProxyConfig>,
+    ) -> Result<(WsStream, String)> {
+        let Some(endpoints) = endpoints else {
+            return Ok((connect(url, proxy).await?, url.to_string()));
+        };
+        let mut tried = Vec::new();
+        loop {
+            let endpoint = endpoints.active();
+            let url = endpoint.get_ws_url();
+            match connect(&url, proxy).await {
+                Ok(ws) => {
+                    endpoints.record_success(&endpoint);
+                    return Ok((ws, url));
+                }
+                Err(err) => {
+                    endpoints.record_failure(&endpoint, FailureKind::Connect, &err);
+                    tried.push(endpoint);
+                    if tried.contains(&endpoints.active()) {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Waits until `at`, then returns the websocket URL of the active endpoint if it is not
+    /// `url`. Never resolves without an endpoint pool.
+    async fn failback_target(
+        endpoints: Option<&EndpointPool>,
+        url: &str,
+        at: Instant,
+    ) -> Option<String> {
+        let Some(endpoints) = endpoints else {
+            return std::future::pending().await;
+        };
+        rt::sleep(at.saturating_duration_since(Instant::now())).await;
+        Some(endpoints.active().get_ws_url()).filter(|active| active != url)
+    }
+
+    async fn resubscribe(
+        writer: &mut SplitSink<WsStream, WsMessage>,
+        subscriptions: &Mutex<HashMap<String, Vec<SubscriptionData>>>,
+    ) {
+        for (identifier, v) in subscriptions.lock().await.iter() {
+            // Entries such as userEvents and custom channels hold subscriptions sent with
+            // different identifiers, each sent once
+            let mut sent = HashSet::new();
+            for subscription_data in v {
+                if !sent.insert(subscription_data.id.as_str()) {
+                    continue;
+                }
+                if let Err(err) = Self::subscribe(writer, &subscription_data.id).await {
+                    error!(identifier, error = %err, "Could not resubscribe");
+                }
+            }
+        }
     }
 
     fn get_identifier(message: &Message) -> Result<String> {
@@ -293,43 +462,72 @@ impl WsManager {
                 coin: bbo.data.coin.clone(),
             })
             .map_err(|e| Error::JsonParse(e.to_string())),
-            Message::SubscriptionResponse | Message::Pong => Ok(String::default()),
+            Message::Custom(custom) => Ok(custom_entry(&custom.channel)),
+            Message::SubscriptionResponse | Message::Post(_) | Message::Pong => {
+                Ok(String::default())
+            }
             Message::NoData => Ok("".to_string()),
             Message::HyperliquidError(err) => Ok(format!("hyperliquid error: {err:?}")),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn parse_and_send_data(
-        data: std::result::Result<protocol::Message, tungstenite::Error>,
+        data: std::result::Result<WsMessage, WsError>,
         subscriptions: &Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
+        pending_posts: &PendingPosts,
+        dedup: &mut UserEventDedup,
+        books: &mut BookCoalescer,
+        shedder: &mut Shedder,
+        recorder: Option<&Recorder>,
+        schema_check: Option<&SchemaCheck>,
+        #[cfg(feature = "metrics")] metrics: Option<&crate::Metrics>,
     ) -> Result<()> {
         match data {
-            Ok(data) => match data.into_text() {
+            Ok(data) => match message_text(data) {
                 Ok(data) => {
                     if !data.starts_with('{') {
                         return Ok(());
                     }
-                    let message = serde_json::from_str::<Message>(&data)
-                        .map_err(|e| Error::JsonParse(e.to_string()))?;
+                    if let Some(recorder) = recorder {
+                        recorder.record_ws(&data);
+                    }
+                    shedder.record(data.len());
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = metrics {
+                        metrics.inc_ws_message(crate::metrics::ws_channel(&data));
+                    }
+                    let message = match schema_check {
+                        Some(check) => parse_checked(&data, check)?,
+                        None => parse_message(data)?,
+                    };
+                    if let Message::Post(post) = message {
+                        let sender = pending_posts
+                            .lock()
+                            .expect("pending posts lock poisoned")
+                            .remove(&post.data.id);
+                        // The request may have timed out and stopped waiting
+                        if let Some(sender) = sender {
+                            let _ = sender.send(post.data.response);
+                        }
+                        return Ok(());
+                    }
                     let identifier = WsManager::get_identifier(&message)?;
                     if identifier.is_empty() {
                         return Ok(());
                     }
+                    let Some(message) = dedup.filter(&identifier, message) else {
+                        return Ok(());
+                    };
+                    let Some((identifier, message)) = shedder.push(identifier, message) else {
+                        return Ok(());
+                    };
+                    let Some((identifier, message)) = books.push(identifier, message) else {
+                        return Ok(());
+                    };
 
                     let mut subscriptions = subscriptions.lock().await;
-                    let mut res = Ok(());
-                    if let Some(subscription_datas) = subscriptions.get_mut(&identifier) {
-                        for subscription_data in subscription_datas {
-                            if let Err(e) = subscription_data
-                                .sending_channel
-                                .send(message.clone())
-                                .map_err(|e| Error::WsSend(e.to_string()))
-                            {
-                                res = Err(e);
-                            }
-                        }
-                    }
-                    res
+                    Self::send_to_subscription(&mut subscriptions, &identifier, message)
                 }
                 Err(err) => {
                     let error = Error::ReaderTextConversion(err.to_string());
@@ -351,6 +549,50 @@ impl WsManager {
         }
     }
 
+    fn send_to_subscription(
+        subscriptions: &mut HashMap<String, Vec<SubscriptionData>>,
+        identifier: &str,
+        message: Message,
+    ) -> Result<()> {
+        let mut res = Ok(());
+        if let Some(subscription_datas) = subscriptions.get_mut(identifier) {
+            for subscription_data in subscription_datas {
+                // Goes to the channel instead if the caller stopped waiting for the snapshot
+                let message = match subscription_data.snapshot.take() {
+                    Some(snapshot) => match snapshot.send(message.clone()) {
+                        Ok(()) => continue,
+                        Err(message) => message,
+                    },
+                    None => message.clone(),
+                };
+                if let Err(e) = subscription_data
+                    .sending_channel
+                    .send(message)
+                    .map_err(|e| Error::WsSend(e.to_string()))
+                {
+                    res = Err(e);
+                }
+            }
+        }
+        res
+    }
+
+    /// Delivers messages held back by the reader, such as coalesced books.
+    async fn send_all(
+        subscriptions: &Mutex<HashMap<String, Vec<SubscriptionData>>>,
+        messages: Vec<(String, Message)>,
+    ) {
+        if messages.is_empty() {
+            return;
+        }
+        let mut subscriptions = subscriptions.lock().await;
+        for (identifier, message) in messages {
+            if let Err(err) = Self::send_to_subscription(&mut subscriptions, &identifier, message) {
+                error!(error = %err, "Could not deliver websocket message");
+            }
+        }
+    }
+
     async fn send_to_all_subscriptions(
         subscriptions: &Arc<Mutex<HashMap<String, Vec<SubscriptionData>>>>,
         message: Message,
@@ -373,7 +615,7 @@ impl WsManager {
 
     async fn send_subscription_data(
         method: &'static str,
-        writer: &mut SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>,
+        writer: &mut SplitSink<WsStream, WsMessage>,
         identifier: &str,
     ) -> Result<()> {
         let payload = serde_json::to_string(&SubscriptionSendData {
@@ -384,63 +626,118 @@ impl WsManager {
         .map_err(|e| Error::JsonParse(e.to_string()))?;
 
         writer
-            .send(protocol::Message::Text(payload))
+            .send(text_message(payload))
             .await
             .map_err(|e| Error::Websocket(e.to_string()))?;
         Ok(())
     }
 
     async fn subscribe(
-        writer: &mut SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>,
+        writer: &mut SplitSink<WsStream, WsMessage>,
         identifier: &str,
     ) -> Result<()> {
         Self::send_subscription_data("subscribe", writer, identifier).await
     }
 
     async fn unsubscribe(
-        writer: &mut SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, protocol::Message>,
+        writer: &mut SplitSink<WsStream, WsMessage>,
         identifier: &str,
     ) -> Result<()> {
         Self::send_subscription_data("unsubscribe", writer, identifier).await
     }
 
+    /// Sends `payload` as an `info` or `action` post request, returning a receiver for the
+    /// response. The receiver fails if the connection drops before the response arrives.
+    #[cfg(feature = "exchange")]
+    pub(crate) async fn post(
+        &mut self,
+        request_type: &'static str,
+        payload: &serde_json::Value,
+    ) -> Result<oneshot::Receiver<PostResponse>> {
+        self.post_id += 1;
+        let id = self.post_id;
+        let text = serde_json::to_string(&PostSendData {
+            method: "post",
+            id,
+            request: PostRequest {
+                request_type,
+                payload,
+            },
+        })
+        .map_err(|e| Error::JsonParse(e.to_string()))?;
+        let (sender, receiver) = oneshot::channel();
+        self.pending_posts
+            .lock()
+            .expect("pending posts lock poisoned")
+            .insert(id, sender);
+        let sent = self.writer.lock().await.send(text_message(text)).await;
+        if let Err(err) = sent {
+            self.pending_posts
+                .lock()
+                .expect("pending posts lock poisoned")
+                .remove(&id);
+            return Err(Error::Websocket(err.to_string()));
+        }
+        Ok(receiver)
+    }
+
+    /// Adds a subscriber to `identifier`, subscribing on the connection if it is the first.
+    /// With `snapshot` set, the first message of the channel goes there rather than to
+    /// `sending_channel`.
+    #[instrument(level = "debug", skip(self, sending_channel, snapshot))]
     pub(crate) async fn add_subscription(
         &mut self,
         identifier: String,
         sending_channel: UnboundedSender<Message>,
+        snapshot: Option<oneshot::Sender<Message>>,
     ) -> Result<u32> {
-        let mut subscriptions = self.subscriptions.lock().await;
-
-        let identifier_entry = if let Subscription::UserEvents { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "userEvents".to_string()
-        } else if let Subscription::OrderUpdates { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
+        let entry = match serde_json::from_str::<Subscription>(&identifier)
+            .map_err(|e| Error::JsonParse(e.to_string()))?
         {
-            "orderUpdates".to_string()
-        } else {
-            identifier.clone()
+            Subscription::UserEvents { user: _ } => "userEvents".to_string(),
+            Subscription::OrderUpdates { user: _ } => "orderUpdates".to_string(),
+            _ => identifier.clone(),
         };
-        let subscriptions = subscriptions
-            .entry(identifier_entry.clone())
-            .or_insert(Vec::new());
+        self.add_subscription_entry(entry, identifier, sending_channel, snapshot)
+            .await
+    }
 
-        if !subscriptions.is_empty() && identifier_entry.eq("userEvents") {
+    /// Adds a subscriber to a channel this version does not know, whose messages are routed
+    /// by their channel alone.
+    #[instrument(level = "debug", skip(self, sending_channel))]
+    pub(crate) async fn add_custom_subscription(
+        &mut self,
+        channel: &str,
+        identifier: String,
+        sending_channel: UnboundedSender<Message>,
+    ) -> Result<u32> {
+        self.add_subscription_entry(custom_entry(channel), identifier, sending_channel, None)
+            .await
+    }
+
+    async fn add_subscription_entry(
+        &mut self,
+        entry: String,
+        identifier: String,
+        sending_channel: UnboundedSender<Message>,
+        snapshot: Option<oneshot::Sender<Message>>,
+    ) -> Result<u32> {
+        let mut subscriptions = self.subscriptions.lock().await;
+        let subscriptions = subscriptions.entry(entry.clone()).or_insert(Vec::new());
+
+        if !subscriptions.is_empty() && entry.eq("userEvents") {
             return Err(Error::UserEvents);
         }
 
-        if subscriptions.is_empty() {
+        if !subscriptions.iter().any(|data| data.id == identifier) {
             Self::subscribe(self.writer.lock().await.borrow_mut(), identifier.as_str()).await?;
         }
 
         let subscription_id = self.subscription_id;
-        self.subscription_identifiers
-            .insert(subscription_id, identifier.clone());
+        self.subscription_identifiers.insert(subscription_id, entry);
         subscriptions.push(SubscriptionData {
             sending_channel,
+            snapshot,
             subscription_id,
             id: identifier,
         });
@@ -449,49 +746,114 @@ impl WsManager {
         Ok(subscription_id)
     }
 
+    #[instrument(level = "debug", skip(self))]
     pub(crate) async fn remove_subscription(&mut self, subscription_id: u32) -> Result<()> {
-        let identifier = self
+        let entry = self
             .subscription_identifiers
-            .get(&subscription_id)
-            .ok_or(Error::SubscriptionNotFound)?
-            .clone();
-
-        let identifier_entry = if let Subscription::UserEvents { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "userEvents".to_string()
-        } else if let Subscription::OrderUpdates { user: _ } =
-            serde_json::from_str::<Subscription>(&identifier)
-                .map_err(|e| Error::JsonParse(e.to_string()))?
-        {
-            "orderUpdates".to_string()
-        } else {
-            identifier.clone()
-        };
-
-        self.subscription_identifiers.remove(&subscription_id);
+            .remove(&subscription_id)
+            .ok_or(Error::SubscriptionNotFound)?;
 
         let mut subscriptions = self.subscriptions.lock().await;
 
         let subscriptions = subscriptions
-            .get_mut(&identifier_entry)
+            .get_mut(&entry)
             .ok_or(Error::SubscriptionNotFound)?;
         let index = subscriptions
             .iter()
             .position(|subscription_data| subscription_data.subscription_id == subscription_id)
             .ok_or(Error::SubscriptionNotFound)?;
-        subscriptions.remove(index);
+        let removed = subscriptions.remove(index);
 
-        if subscriptions.is_empty() {
-            Self::unsubscribe(self.writer.lock().await.borrow_mut(), identifier.as_str()).await?;
+        if !subscriptions.iter().any(|data| data.id == removed.id) {
+            Self::unsubscribe(self.writer.lock().await.borrow_mut(), removed.id.as_str()).await?;
         }
         Ok(())
     }
 }
 
+/// Key of the subscriptions to a channel this version does not know.
+fn custom_entry(channel: &str) -> String {
+    format!("custom:{channel}")
+}
+
 impl Drop for WsManager {
     fn drop(&mut self) {
-        self.stop_flag.store(true, Ordering::Relaxed);
+        self.cancel.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message() {
+        let text = r#"{"channel":"trades","data":[{"coin":"ETH","side":"B","px":"2000.5","sz":"0.1","time":1,"hash":"0x0","tid":7,"users":["0x1","0x2"]}]}"#;
+        let Message::Trades(trades) = parse_message(text.to_string()).unwrap() else {
+            panic!("expected trades");
+        };
+        assert_eq!(
+            (trades.data[0].px.as_str(), trades.data[0].tid),
+            ("2000.5", 7)
+        );
+        assert!(matches!(
+            parse_message(
+                r#"{"channel":"bbo","data":{"coin":"ETH","time":1,"bbo":[null,null]}}"#.to_string()
+            ),
+            Ok(Message::Bbo(_))
+        ));
+        assert!(parse_message("{".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_goes_to_its_own_receiver() {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (snapshot, mut snapshot_receiver) = oneshot::channel();
+        let mut subscriptions = HashMap::from([(
+            "id".to_string(),
+            vec![SubscriptionData {
+                sending_channel: sender,
+                snapshot: Some(snapshot),
+                subscription_id: 0,
+                id: "id".to_string(),
+            }],
+        )]);
+        let message = |text: &str| Message::HyperliquidError(text.to_string());
+        WsManager::send_to_subscription(&mut subscriptions, "id", message("first")).unwrap();
+        WsManager::send_to_subscription(&mut subscriptions, "id", message("second")).unwrap();
+        assert!(matches!(
+            snapshot_receiver.try_recv(),
+            Ok(Message::HyperliquidError(text)) if text == "first"
+        ));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(Message::HyperliquidError(text)) if text == "second"
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_custom_channel_is_routed_by_name() {
+        let message =
+            parse_message(r#"{"channel":"newFeed","data":{"coin":"ETH","value":"1.5"}}"#.into())
+                .unwrap();
+        let identifier = WsManager::get_identifier(&message).unwrap();
+        assert_eq!(identifier, custom_entry("newFeed"));
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut subscriptions = HashMap::from([(
+            identifier.clone(),
+            vec![SubscriptionData {
+                sending_channel: sender,
+                snapshot: None,
+                subscription_id: 0,
+                id: r#"{"type":"newFeed","coin":"ETH"}"#.to_string(),
+            }],
+        )]);
+        WsManager::send_to_subscription(&mut subscriptions, &identifier, message).unwrap();
+        let Ok(Message::Custom(custom)) = receiver.try_recv() else {
+            panic!("expected a custom message");
+        };
+        assert_eq!(custom.data["value"], "1.5");
     }
 }